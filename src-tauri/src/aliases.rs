@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// Aliases every source has without pinning them, worked out from the index
+// each time: `latest` is its highest semver version, `stable` its highest
+// release. They move as soon as a newer version is indexed.
+pub const AUTOMATIC_ALIASES: &[&str] = &["latest", "stable"];
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SetVersionAliasParams {
+    pub source: String,
+    /// The name to resolve, e.g. `nightly`
+    pub alias: String,
+    /// The indexed version it stands for, e.g. `master` or `1.38.0`
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RemoveVersionAliasParams {
+    pub source: String,
+    pub alias: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListVersionAliasesParams {
+    /// Only this source's aliases (default every source's)
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct VersionAlias {
+    pub source: String,
+    pub alias: String,
+    pub version: String,
+    /// Set by hand rather than worked out from the index
+    pub pinned: bool,
+}
+
+// Aliases set by hand, by source, kept in a JSON file beside the index.
+// A pinned alias wins over an automatic one of the same name, e.g. to hold
+// `stable` at an LTS release.
+pub struct VersionAliases {
+    path: PathBuf,
+    pinned: RwLock<HashMap<String, BTreeMap<String, String>>>,
+}
+
+impl VersionAliases {
+    // A missing or unreadable file starts with no pinned aliases
+    pub fn load(path: PathBuf) -> Self {
+        let pinned = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid version aliases {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            path,
+            pinned: RwLock::new(pinned),
+        }
+    }
+
+    pub fn get(&self, source: &str, alias: &str) -> Option<String> {
+        self.pinned.read().unwrap().get(source)?.get(alias).cloned()
+    }
+
+    // What `alias` is pinned to, by source
+    pub fn pinned_to(&self, alias: &str) -> HashMap<String, String> {
+        self.pinned
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(source, aliases)| Some((source.clone(), aliases.get(alias)?.clone())))
+            .collect()
+    }
+
+    pub fn all(&self) -> HashMap<String, BTreeMap<String, String>> {
+        self.pinned.read().unwrap().clone()
+    }
+
+    // Whether `name` may be an alias of the source, pinned or automatic
+    pub fn is_alias(&self, source: &str, name: &str) -> bool {
+        AUTOMATIC_ALIASES.contains(&name) || self.get(source, name).is_some()
+    }
+
+    pub fn set(&self, source: &str, alias: &str, version: &str) -> Result<()> {
+        let mut pinned = self.pinned.write().unwrap();
+        pinned
+            .entry(source.to_string())
+            .or_default()
+            .insert(alias.to_string(), version.to_string());
+        self.save(&pinned)
+    }
+
+    // The version the alias was pinned to, None if it wasn't
+    pub fn remove(&self, source: &str, alias: &str) -> Result<Option<String>> {
+        let mut pinned = self.pinned.write().unwrap();
+        let Some(aliases) = pinned.get_mut(source) else {
+            return Ok(None);
+        };
+        let removed = aliases.remove(alias);
+        if aliases.is_empty() {
+            pinned.remove(source);
+        }
+        if removed.is_some() {
+            self.save(&pinned)?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self, pinned: &HashMap<String, BTreeMap<String, String>>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(pinned)?)?;
+        Ok(())
+    }
+}
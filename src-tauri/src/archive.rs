@@ -0,0 +1,156 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::search::SearchService;
+use crate::versions::version_key;
+use crate::McpError;
+
+// Versions left in the main index when the caller doesn't say
+const DEFAULT_KEEP_VERSIONS: usize = 1;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ArchiveVersionsParams {
+    /// The source whose old versions to archive, e.g. `tokio`
+    pub source: String,
+    /// Versions to archive, e.g. `["1.36.0", "1.37.0"]`; aliases work too.
+    /// Default every semver version but the newest `keep`.
+    #[serde(default)]
+    pub versions: Vec<String>,
+    /// How many of the newest versions stay when `versions` is empty
+    /// (default 1)
+    pub keep: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RestoreVersionsParams {
+    pub source: String,
+    /// Archived versions to move back, e.g. `["1.36.0"]`
+    pub versions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListArchivedParams {
+    /// Only this source's versions (default every source's)
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ArchivedVersion {
+    pub source: String,
+    pub version: String,
+    /// Pages and sections
+    pub documents: usize,
+}
+
+// Moves superseded versions of a source out of the main index into the
+// archive, which searches leave out unless they ask for it. Versions whose
+// names aren't semver are only moved when named.
+pub fn archive_versions(
+    search_service: &SearchService,
+    params: &ArchiveVersionsParams,
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    let archive = search_service
+        .archive()
+        .ok_or_else(|| McpError::unsupported("This index has no archive"))?;
+    let versions = if params.versions.is_empty() {
+        superseded_versions(
+            search_service,
+            &params.source,
+            params.keep.unwrap_or(DEFAULT_KEEP_VERSIONS),
+        )?
+    } else {
+        params
+            .versions
+            .iter()
+            .map(|version| {
+                search_service
+                    .resolve_version(&params.source, version)
+                    .map_err(|e| McpError::index("Failed to resolve the version alias", e))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    move_versions(search_service, archive, &params.source, &versions)
+}
+
+pub fn restore_versions(
+    search_service: &SearchService,
+    params: &RestoreVersionsParams,
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    let archive = search_service
+        .archive()
+        .ok_or_else(|| McpError::unsupported("This index has no archive"))?;
+    move_versions(archive, search_service, &params.source, &params.versions)
+}
+
+pub fn list_archived(
+    search_service: &SearchService,
+    params: &ListArchivedParams,
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    let Some(archive) = search_service.archive() else {
+        return Ok(Vec::new());
+    };
+    let counts = archive
+        .version_counts(params.source.as_deref())
+        .map_err(|e| McpError::index("Failed to list archived versions", e))?;
+    Ok(counts
+        .into_iter()
+        .map(|((source, version), documents)| ArchivedVersion {
+            source,
+            version,
+            documents,
+        })
+        .collect())
+}
+
+// Every semver version of the source but the newest `keep`, oldest first
+fn superseded_versions(search_service: &SearchService, source: &str, keep: usize) -> Result<Vec<String>, McpError> {
+    let counts = search_service
+        .version_counts(Some(source))
+        .map_err(|e| McpError::index("Failed to list the source's versions", e))?;
+    let mut versions: Vec<(u64, String)> = counts
+        .into_keys()
+        .filter_map(|(_, version)| Some((version_key(&version)?, version)))
+        .collect();
+    versions.sort();
+    versions.truncate(versions.len().saturating_sub(keep));
+    Ok(versions.into_iter().map(|(_, version)| version).collect())
+}
+
+// Each version is copied before it's removed, so a failure part way leaves
+// it in both indexes rather than in neither
+fn move_versions(
+    from: &SearchService,
+    to: &SearchService,
+    source: &str,
+    versions: &[String],
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    let counts = from
+        .version_counts(Some(source))
+        .map_err(|e| McpError::index("Failed to list the source's versions", e))?;
+    if let Some(missing) = versions
+        .iter()
+        .find(|version| !counts.contains_key(&(source.to_string(), version.to_string())))
+    {
+        return Err(McpError::not_found(format!("Version {} of source {}", missing, source)));
+    }
+
+    let mut moved = Vec::with_capacity(versions.len());
+    for version in versions {
+        let documents = from
+            .version_documents(source, version)
+            .map_err(|e| McpError::index("Failed to read the version's documents", e))?;
+        to.import_documents(&documents)
+            .map_err(|e| McpError::index("Failed to copy the version's documents", e))?;
+        let ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+        from.delete_documents(&ids)
+            .map_err(|e| McpError::index("Failed to remove the version's documents", e))?;
+        info!("Moved {} documents of {} {}", documents.len(), source, version);
+        moved.push(ArchivedVersion {
+            source: source.to_string(),
+            version: version.clone(),
+            documents: documents.len(),
+        });
+    }
+    Ok(moved)
+}
@@ -0,0 +1,170 @@
+use std::sync::{Arc, RwLock};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::context::{self, Citation, GetContextParams};
+use crate::embeddings::Embeddings;
+use crate::ingest::Fetcher;
+use crate::rerank::Reranker;
+use crate::search::{SearchOptions, SearchService};
+use crate::settings::AssistantSettings;
+use crate::validation;
+use crate::McpError;
+
+// A chat completion, however long the answer, fits in this
+const MAX_ANSWER_BYTES: usize = 1024 * 1024;
+
+const SYSTEM_PROMPT: &str = "You are a documentation assistant. Answer the question using only the numbered \
+documentation excerpts you are given, and cite the excerpts you rely on as [n]. If the excerpts do not answer \
+the question, say so.";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct AskDocsParams {
+    /// The question in plain words, e.g. "How do I shut down a tokio
+    /// runtime without waiting for blocking tasks?"
+    pub question: String,
+    /// Only use this source's documents, e.g. `tokio`
+    pub source: Option<String>,
+    /// Only use documents of this version, or of the version an alias like
+    /// `stable` stands for
+    pub version: Option<String>,
+    /// Use the versions this workspace pins
+    pub workspace: Option<String>,
+    /// Find the excerpts by meaning, as `semantic` in searchDocuments
+    #[serde(default)]
+    pub semantic: bool,
+    /// Rerank the excerpts with a cross-encoder first, as `rerank` in
+    /// searchDocuments
+    #[serde(default)]
+    pub rerank: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct AskDocsResponse {
+    /// The model's answer, citing excerpts as `[n]`
+    pub answer: String,
+    /// The excerpts the model was given, by the numbers it cites them by
+    pub citations: Vec<Citation>,
+    /// The model that answered; None when no excerpt matched and no model
+    /// was asked
+    pub model: Option<String>,
+}
+
+// Answers questions about the indexed docs with an LLM behind an
+// OpenAI-compatible chat API, local or hosted. The question's best hits go
+// into the prompt as numbered excerpts, as getContext assembles them, and
+// the model is told to answer from those alone.
+pub struct Assistant {
+    search_service: Arc<SearchService>,
+    embeddings: Arc<Embeddings>,
+    reranker: Arc<Reranker>,
+    fetcher: Arc<Fetcher>,
+    settings: RwLock<AssistantSettings>,
+}
+
+impl Assistant {
+    pub fn new(
+        search_service: Arc<SearchService>,
+        embeddings: Arc<Embeddings>,
+        reranker: Arc<Reranker>,
+        fetcher: Arc<Fetcher>,
+        settings: AssistantSettings,
+    ) -> Self {
+        Self {
+            search_service,
+            embeddings,
+            reranker,
+            fetcher,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn replace(&self, settings: AssistantSettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    // Whether a model is set up to be asked; None when it isn't, or the
+    // settings to ask it with
+    pub fn settings(&self) -> Option<AssistantSettings> {
+        Some(self.settings.read().unwrap().clone()).filter(|settings| settings.enabled)
+    }
+
+    // `options` carries the caller's preferences, as in getContext
+    pub async fn ask(&self, options: SearchOptions, params: &AskDocsParams) -> Result<AskDocsResponse, McpError> {
+        validation::validate_ask_docs(params)?;
+        let settings = self.settings().ok_or_else(|| {
+            McpError::unsupported(
+                "Documentation Q&A is off; turn on assistant.enabled and set an LLM endpoint in the settings",
+            )
+        })?;
+
+        let context_params = GetContextParams {
+            query: params.question.clone(),
+            max_tokens: Some(settings.context_tokens),
+            source: params.source.clone(),
+            version: params.version.clone(),
+            workspace: None,
+            semantic: params.semantic,
+            rerank: params.rerank,
+        };
+        let context = context::get_context(
+            &self.search_service,
+            &self.embeddings,
+            &self.reranker,
+            options,
+            &context_params,
+        )
+        .await?;
+        if context.citations.is_empty() {
+            return Ok(AskDocsResponse {
+                answer: format!("No indexed documents match \"{}\".", params.question),
+                citations: Vec::new(),
+                model: None,
+            });
+        }
+
+        let prompt = format!(
+            "Documentation excerpts:\n\n{}\nQuestion: {}",
+            context.context, params.question
+        );
+        let answer = self
+            .complete(&settings, SYSTEM_PROMPT, &prompt, settings.max_tokens)
+            .await?;
+        Ok(AskDocsResponse {
+            answer,
+            citations: context.citations,
+            model: Some(settings.model),
+        })
+    }
+
+    // One chat completion: the model's reply to `prompt`, trimmed
+    pub async fn complete(
+        &self,
+        settings: &AssistantSettings,
+        system: &str,
+        prompt: &str,
+        max_tokens: usize,
+    ) -> Result<String, McpError> {
+        let url = Fetcher::parse_url(&format!("{}/chat/completions", settings.url.trim_end_matches('/')))?;
+        let body = json!({
+            "model": settings.model,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+            "max_tokens": max_tokens,
+            "temperature": 0.2,
+        });
+        let reply = self
+            .fetcher
+            .post_json(&url, settings.api_key.as_deref(), &body, MAX_ANSWER_BYTES)
+            .await?;
+        reply
+            .pointer("/choices/0/message/content")
+            .and_then(Value::as_str)
+            .map(|answer| answer.trim().to_string())
+            .ok_or_else(|| McpError::fetch(url.as_str(), "the answer has no message content"))
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::util::{self, now_millis};
+use crate::McpError;
+
+// The log is rotated to `audit.log.1` once it grows past this
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+// One invocation: who called what, with which params (only a digest is
+// kept, so document bodies and queries don't end up in the log), how long
+// it took and whether it failed.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    // "rpc", "rest" or "app" (Tauri commands from the UI)
+    pub transport: String,
+    // The API key or token name, "app" for the UI
+    pub caller: String,
+    pub method: String,
+    // First 8 bytes of the SHA-256 of the JSON params, in hex
+    pub params_digest: String,
+    pub duration_ms: u64,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i64>,
+    // The id the RPC server gave the call, as in its log lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    /// Maximum number of entries to return, newest first (default 100, at most 1000)
+    pub limit: Option<usize>,
+    /// Only entries from this caller
+    pub caller: Option<String>,
+    /// Only entries for this method
+    pub method: Option<String>,
+    /// Only entries at or after this time, in milliseconds since the Unix epoch
+    pub since: Option<u64>,
+    /// Only the JSON-RPC call with this request id
+    pub request_id: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditEntry>,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+// Append-only JSON lines in the config directory. Writing is best effort: a
+// failed write is reported on stderr but never fails the call being logged.
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<LogFile>,
+}
+
+impl AuditLog {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file: Mutex::new(LogFile { file, size }),
+        })
+    }
+
+    // Starts timing a call; `finish` on the result writes the entry
+    pub fn begin(
+        self: &Arc<Self>,
+        transport: &str,
+        caller: &str,
+        method: &str,
+        params: &impl Serialize,
+    ) -> AuditCall {
+        AuditCall {
+            log: Arc::clone(self),
+            started: Instant::now(),
+            entry: AuditEntry {
+                timestamp: now_millis(),
+                transport: transport.to_string(),
+                caller: caller.to_string(),
+                method: method.to_string(),
+                params_digest: params_digest(params),
+                duration_ms: 0,
+                ok: true,
+                error_code: None,
+                request_id: None,
+            },
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut log = self.file.lock().unwrap();
+        if log.size + line.len() as u64 > MAX_LOG_BYTES {
+            std::fs::rename(&self.path, self.rotated_path())?;
+            log.file = open_append(&self.path)?;
+            log.size = 0;
+        }
+        log.file.write_all(&line)?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Matching entries, newest first
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT);
+        let mut entries = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for line in BufReader::new(file).lines() {
+                // A line cut short by a crash is skipped rather than failing the query
+                let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else {
+                    continue;
+                };
+                if query.caller.as_ref().map_or(true, |caller| *caller == entry.caller)
+                    && query.method.as_ref().map_or(true, |method| *method == entry.method)
+                    && query.since.map_or(true, |since| entry.timestamp >= since)
+                    && query.request_id.as_ref().map_or(true, |id| entry.request_id.as_ref() == Some(id))
+                {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.reverse();
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".1");
+        path.into()
+    }
+}
+
+pub struct AuditCall {
+    log: Arc<AuditLog>,
+    started: Instant,
+    entry: AuditEntry,
+}
+
+impl AuditCall {
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.entry.request_id = Some(request_id.to_string());
+        self
+    }
+
+    // `error_code` is the failure's JSON-RPC code, None on success
+    pub fn finish(mut self, error_code: Option<i64>) {
+        self.entry.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.entry.ok = error_code.is_none();
+        self.entry.error_code = error_code;
+        if let Err(e) = self.log.append(&self.entry) {
+            error!("Failed to write audit log: {:?}", e);
+        }
+    }
+
+    // Finishes with the outcome of `result` and passes it through
+    pub fn record<T>(self, result: Result<T, McpError>) -> Result<T, McpError> {
+        self.finish(result.as_ref().err().map(McpError::code));
+        result
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    Ok(util::open_private(path, OpenOptions::new().create(true).append(true))?)
+}
+
+fn params_digest(params: &impl Serialize) -> String {
+    let json = serde_json::to_vec(params).unwrap_or_default();
+    util::sha256_hex(&json)[..16].to_string()
+}
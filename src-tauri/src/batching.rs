@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::error;
+
+use crate::search::{SearchService, SearchableDocument};
+use crate::settings::BatchSettings;
+use crate::McpError;
+
+// flush takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct FlushParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct FlushResponse {
+    /// Queued documents this flush committed
+    pub committed: usize,
+}
+
+#[derive(Default)]
+struct Pending {
+    documents: Vec<SearchableDocument>,
+    // When the oldest of them was queued
+    since: Option<Instant>,
+    // Why a batch committed in the background failed, kept for the next
+    // flush to report
+    failure: Option<String>,
+}
+
+// Queues documents added one at a time and commits them together, so an
+// agent streaming hundreds of addDocument calls costs a handful of commits
+// rather than one each. A batch is committed once it holds
+// `max_documents`, or `max_delay_ms` after its first document came in,
+// whichever is first. Queued documents aren't searchable until then;
+// `flush` commits them at once for callers that need to read their writes.
+pub struct AddQueue {
+    search_service: Arc<SearchService>,
+    settings: RwLock<BatchSettings>,
+    pending: Mutex<Pending>,
+    // Held while a batch is committed, so a flush waits for one under way
+    committing: tokio::sync::Mutex<()>,
+    wake: Notify,
+}
+
+impl AddQueue {
+    pub fn new(search_service: Arc<SearchService>, settings: BatchSettings) -> Self {
+        Self {
+            search_service,
+            settings: RwLock::new(settings),
+            pending: Mutex::new(Pending::default()),
+            committing: tokio::sync::Mutex::new(()),
+            wake: Notify::new(),
+        }
+    }
+
+    pub fn replace(&self, settings: BatchSettings) {
+        *self.settings.write().unwrap() = settings;
+        self.wake.notify_one();
+    }
+
+    // Queues a document that's been validated; how many are then waiting
+    pub fn add(&self, document: SearchableDocument) -> usize {
+        let max_documents = self.settings.read().unwrap().max_documents;
+        let queued = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.since.get_or_insert_with(Instant::now);
+            pending.documents.push(document);
+            pending.documents.len()
+        };
+        // The first document starts the clock, and a full batch goes now
+        if queued == 1 || queued >= max_documents {
+            self.wake.notify_one();
+        }
+        queued
+    }
+
+    // Documents waiting for their batch to be committed
+    pub fn queued(&self) -> usize {
+        self.pending.lock().unwrap().documents.len()
+    }
+
+    // Commits whatever is queued, after any batch already being committed.
+    // Fails if that, or a batch before it, failed to commit.
+    pub async fn flush(&self) -> Result<FlushResponse, McpError> {
+        let _committing = self.committing.lock().await;
+        let (documents, failure) = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.since = None;
+            (std::mem::take(&mut pending.documents), pending.failure.take())
+        };
+        let committed = documents.len();
+        self.commit(documents)
+            .await
+            .map_err(|e| McpError::index("Failed to commit queued documents", e))?;
+        if let Some(failure) = failure {
+            return Err(McpError::index(
+                "An earlier batch of queued documents failed to commit",
+                failure,
+            ));
+        }
+        Ok(FlushResponse { committed })
+    }
+
+    // Commits batches as they fill up or come due, for as long as the app
+    // runs
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let since = self.pending.lock().unwrap().since;
+            let Some(since) = since else {
+                self.wake.notified().await;
+                continue;
+            };
+            let (max_documents, delay) = {
+                let settings = self.settings.read().unwrap();
+                (settings.max_documents, Duration::from_millis(settings.max_delay_ms))
+            };
+            let full = self.pending.lock().unwrap().documents.len() >= max_documents;
+            if !full {
+                // Woken early when the batch fills up or the settings change
+                tokio::select! {
+                    _ = tokio::time::sleep_until(since + delay) => {}
+                    _ = self.wake.notified() => continue,
+                }
+            }
+
+            let _committing = self.committing.lock().await;
+            let documents = {
+                let mut pending = self.pending.lock().unwrap();
+                pending.since = None;
+                std::mem::take(&mut pending.documents)
+            };
+            let count = documents.len();
+            if let Err(e) = self.commit(documents).await {
+                error!("Failed to commit {} queued documents: {}", count, e);
+                self.pending.lock().unwrap().failure = Some(e.to_string());
+            }
+        }
+    }
+
+    async fn commit(&self, documents: Vec<SearchableDocument>) -> Result<()> {
+        if documents.is_empty() {
+            return Ok(());
+        }
+        self.search_service
+            .blocking(move |service| service.add_documents(documents, |_, _| {}))
+            .await
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::search::{SearchOptions, SearchService, SearchableDocument};
+
+pub const DEFAULT_BENCHMARK_DOCUMENTS: usize = 5000;
+pub const DEFAULT_BENCHMARK_QUERIES: usize = 500;
+
+// Documents written per commit, as a crawl would
+const BATCH_SIZE: usize = 500;
+
+// Distinct words in the synthetic corpus
+const VOCABULARY: usize = 5000;
+
+// Each run writes and searches the same corpus, so runs compare
+const SEED: u64 = 0x5eed;
+
+const SYLLABLES: &[&str] = &[
+    "ka", "lo", "mi", "ren", "to", "sa", "vu", "ex", "ne", "bor", "qui", "da", "pel", "shi", "gan", "tru", "ol", "fe",
+    "zan", "wi",
+];
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RunBenchmarkParams {
+    /// Synthetic documents to index (default 5000)
+    pub documents: Option<usize>,
+    /// Searches to time once they're indexed (default 500)
+    pub queries: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct BenchmarkReport {
+    pub documents: usize,
+    /// Bytes of title and body text indexed
+    pub text_bytes: u64,
+    pub index_ms: u64,
+    pub docs_per_sec: f64,
+    /// The benchmark index's size once written
+    pub index_bytes: u64,
+    pub segments: usize,
+    pub queries: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+// Indexes a synthetic corpus into a scratch index opened with the live
+// index's settings, then times a query workload against it. The live index
+// isn't touched, and the scratch one is deleted afterwards. Words follow a
+// rough Zipf distribution, as real text does, so some queries match most of
+// the corpus and others a handful of documents.
+pub fn run_benchmark(search_service: &SearchService, params: &RunBenchmarkParams) -> Result<BenchmarkReport> {
+    let documents = params.documents.unwrap_or(DEFAULT_BENCHMARK_DOCUMENTS);
+    let queries = params.queries.unwrap_or(DEFAULT_BENCHMARK_QUERIES);
+    let dir = tempfile::tempdir()?;
+    let scratch = search_service.open_like(dir.path())?;
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let vocabulary: Vec<String> = (0..VOCABULARY).map(|i| word(i, &mut rng)).collect();
+
+    let mut text_bytes = 0;
+    let started = Instant::now();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for i in 0..documents {
+        let document = document(i, &vocabulary, &mut rng);
+        text_bytes += (document.title.len() + document.body.len()) as u64;
+        batch.push(document);
+        if batch.len() == BATCH_SIZE || i + 1 == documents {
+            scratch.add_documents(std::mem::take(&mut batch), |_, _| {})?;
+        }
+    }
+    let index_secs = started.elapsed().as_secs_f64();
+
+    let options = SearchOptions::default();
+    let mut latencies = Vec::with_capacity(queries);
+    for _ in 0..queries {
+        let terms = rng.gen_range(1..=3);
+        let query: Vec<&str> = (0..terms).map(|_| pick(&vocabulary, &mut rng)).collect();
+        let started = Instant::now();
+        scratch.run_search(&query.join(" "), 10, &options)?;
+        latencies.push(started.elapsed().as_secs_f64() * 1000.0);
+    }
+    latencies.sort_by(f64::total_cmp);
+    let usage = scratch.usage()?;
+    // The index files go with the directory, so close them first
+    drop(scratch);
+    dir.close()?;
+
+    Ok(BenchmarkReport {
+        documents,
+        text_bytes,
+        index_ms: (index_secs * 1000.0) as u64,
+        docs_per_sec: documents as f64 / index_secs.max(f64::EPSILON),
+        index_bytes: usage.index_bytes,
+        segments: usage.segments,
+        queries,
+        p50_ms: percentile(&latencies, 0.50),
+        p95_ms: percentile(&latencies, 0.95),
+        max_ms: latencies.last().copied().unwrap_or(0.0),
+    })
+}
+
+// A made-up word of two to four syllables
+fn word(i: usize, rng: &mut StdRng) -> String {
+    let syllables = rng.gen_range(2..=4);
+    let mut word: String = (0..syllables)
+        .map(|_| SYLLABLES[rng.gen_range(0..SYLLABLES.len())])
+        .collect();
+    // Keeps words distinct however the syllables fall
+    word.push_str(&i.to_string());
+    word
+}
+
+// Low ranks come up far more often than high ones
+fn pick<'a>(vocabulary: &'a [String], rng: &mut StdRng) -> &'a str {
+    let rank = (rng.gen::<f64>().powi(3) * vocabulary.len() as f64) as usize;
+    &vocabulary[rank.min(vocabulary.len() - 1)]
+}
+
+// A page of a few paragraphs and now and then a code block, a few KB long,
+// spread over ten sources with a few versions each
+fn document(i: usize, vocabulary: &[String], rng: &mut StdRng) -> SearchableDocument {
+    let sentence = |rng: &mut StdRng, words: usize| -> String {
+        (0..words).map(|_| pick(vocabulary, rng)).collect::<Vec<_>>().join(" ")
+    };
+    let words = rng.gen_range(3..=8);
+    let title = sentence(rng, words);
+    let mut body = String::new();
+    for _ in 0..rng.gen_range(3..=10) {
+        for _ in 0..rng.gen_range(2..=6) {
+            let words = rng.gen_range(8..=24);
+            body.push_str(&sentence(rng, words));
+            body.push_str(". ");
+        }
+        body.push_str("\n\n");
+        if rng.gen_bool(0.2) {
+            body.push_str(&format!("```\n{}();\n```\n\n", sentence(rng, 3).replace(' ', "_")));
+        }
+    }
+    let source = format!("bench-{}", i % 10);
+    SearchableDocument {
+        id: format!("{}-{}", source, i),
+        title,
+        body,
+        version: Some(format!("1.{}.0", i % 4)),
+        url: Some(format!("https://bench.invalid/{}/{}", source, i)),
+        source,
+        tags: Vec::new(),
+        indexed_at: None,
+        published_at: None,
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    }
+}
+
+// Nearest rank, from latencies sorted low to high
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
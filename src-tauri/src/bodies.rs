@@ -0,0 +1,63 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::util::sha256_hex;
+
+// Holds document bodies too large to keep in Tantivy's doc store, one file
+// per document id, when `index.external_body_bytes` is set. The index still
+// gets the full text to search; it just stores a short excerpt. Files are
+// named by the SHA-256 of the id, so any id makes a valid name, and spread
+// over subdirectories by its first two hex digits.
+pub struct BodyStore {
+    dir: PathBuf,
+    min_bytes: usize,
+}
+
+impl BodyStore {
+    pub fn open(dir: impl AsRef<Path>, min_bytes: usize) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, min_bytes })
+    }
+
+    // Whether a body is large enough to be kept here
+    pub fn keeps(&self, body: &str) -> bool {
+        body.len() > self.min_bytes
+    }
+
+    // Written to a temporary file first, so a reader never sees half a body
+    pub fn put(&self, id: &str, contents: &[u8]) -> Result<()> {
+        let path = self.path(id);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, contents)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    // None if no body is kept for `id`
+    pub fn get(&self, id: &str) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(self.path(id)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read the body of {}", id)),
+        }
+    }
+
+    // Removing a body that isn't there is not an error
+    pub fn remove(&self, id: &str) -> Result<()> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        let name = sha256_hex(id.as_bytes());
+        self.dir.join(&name[..2]).join(name)
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::search::{SearchOptions, SearchableDocument};
+
+// Searches remembered at once; the least recently used goes first
+const MAX_ENTRIES: usize = 256;
+
+struct Entry {
+    results: Arc<Vec<SearchableDocument>>,
+    // When it was last hit, counted in lookups
+    used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    clock: u64,
+    // Bumped on every clear, so a search that read the index before a
+    // commit doesn't store its results after it
+    generation: u64,
+}
+
+// Results of recent searches by (query, options, limit), so an agent asking
+// the same thing again in a loop gets its answer without a search. Cleared
+// whenever the index commits or anything else that changes results does.
+#[derive(Default)]
+pub struct SearchCache {
+    entries: Mutex<Entries>,
+}
+
+impl SearchCache {
+    // Searches by vector aren't cached: their hits also depend on the
+    // embeddings, which change without a commit
+    pub fn key(query_str: &str, limit: usize, options: &SearchOptions) -> Option<String> {
+        if options.query_vector.is_some() {
+            return None;
+        }
+        Some(format!("{}\u{0}{}\u{0}{:?}", query_str, limit, options))
+    }
+
+    // The cached results, and the generation to store fresh ones under
+    pub fn get(&self, key: &str) -> (Option<Arc<Vec<SearchableDocument>>>, u64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let (clock, generation) = (entries.clock, entries.generation);
+        let results = entries.entries.get_mut(key).map(|entry| {
+            entry.used = clock;
+            entry.results.clone()
+        });
+        (results, generation)
+    }
+
+    // Dropped when the cache was cleared since `generation` was handed out
+    pub fn insert(&self, key: String, generation: u64, results: Arc<Vec<SearchableDocument>>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation {
+            return;
+        }
+        if entries.entries.len() >= MAX_ENTRIES && !entries.entries.contains_key(&key) {
+            let oldest = entries.entries.iter().min_by_key(|(_, entry)| entry.used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.entries.remove(&oldest);
+            }
+        }
+        let used = entries.clock;
+        entries.entries.insert(key, Entry { results, used });
+    }
+
+    // How many searches are cached
+    pub fn count(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.entries.clear();
+        entries.generation += 1;
+    }
+}
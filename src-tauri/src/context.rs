@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::embeddings::Embeddings;
+use crate::rerank::Reranker;
+use crate::search::{SearchOptions, SearchService, SearchableDocument};
+use crate::validation;
+use crate::McpError;
+
+pub const DEFAULT_CONTEXT_TOKENS: usize = 4000;
+
+// Hits read for a context; a budget rarely fits more than this many
+const CANDIDATES: usize = 40;
+
+// A rough count that holds for English prose and code alike
+const BYTES_PER_TOKEN: usize = 4;
+
+// Below this, the hit that doesn't fit is left out rather than cut short
+const MIN_PARTIAL_TOKENS: usize = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct GetContextParams {
+    /// What the context is for, e.g. "how do I shut down a tokio runtime"
+    pub query: String,
+    /// How long the context may be, in tokens counted as 4 bytes of text each
+    /// (default 4000)
+    pub max_tokens: Option<usize>,
+    /// Only use this source's documents, e.g. `tokio`
+    pub source: Option<String>,
+    /// Only use documents of this version, or of the version an alias like
+    /// `stable` stands for
+    pub version: Option<String>,
+    /// Use the versions this workspace pins
+    pub workspace: Option<String>,
+    /// Find the hits by meaning, as `semantic` in searchDocuments
+    #[serde(default)]
+    pub semantic: bool,
+    /// Rerank the hits with a cross-encoder first, as `rerank` in
+    /// searchDocuments
+    #[serde(default)]
+    pub rerank: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Citation {
+    /// The number the context cites it by, e.g. 1 for `[1]`
+    pub number: usize,
+    pub id: String,
+    pub title: String,
+    pub source: String,
+    pub version: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ContextResponse {
+    /// The hits, each under a `[n] title (source version) <url>` line
+    pub context: String,
+    pub citations: Vec<Citation>,
+    /// The context's length in estimated tokens
+    pub tokens: usize,
+    /// Whether hits were left out, or the last one cut short, to stay within
+    /// the budget
+    pub truncated: bool,
+}
+
+// Searches for the query and packs the best hits into one block of text to
+// hand to an LLM, within a token budget. `options` carries the caller's
+// preferences, e.g. a session's preferred versions or a workspace's pins.
+pub async fn get_context(
+    search_service: &Arc<SearchService>,
+    embeddings: &Arc<Embeddings>,
+    reranker: &Arc<Reranker>,
+    mut options: SearchOptions,
+    params: &GetContextParams,
+) -> Result<ContextResponse, McpError> {
+    validation::validate_get_context(params)?;
+    options.source = params.source.clone();
+    options.version = params.version.clone();
+    if params.semantic {
+        options.query_vector = Some(embeddings.query_vector(&params.query).await?);
+    }
+    let wanted = if params.rerank {
+        reranker.candidates(CANDIDATES)
+    } else {
+        CANDIDATES
+    };
+    let mut documents = search_service
+        .search(&params.query, wanted, &options)
+        .await
+        .map_err(|e| McpError::search(&params.query, e))?;
+    if params.rerank {
+        documents = reranker.rerank(&params.query, documents, CANDIDATES).await?;
+    }
+    Ok(assemble(documents, params.max_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS)))
+}
+
+// Hits with the same body as one before them are dropped. The rest are
+// taken best first while they fit, then set out with the hits from the same
+// page next to each other, in the order of the page's best hit.
+fn assemble(documents: Vec<SearchableDocument>, max_tokens: usize) -> ContextResponse {
+    let budget = max_tokens * BYTES_PER_TOKEN;
+    let mut bodies = HashSet::new();
+    let mut taken: Vec<(SearchableDocument, String)> = Vec::new();
+    let mut used = 0;
+    let mut truncated = false;
+    for document in documents {
+        let key = document.content_hash.clone().unwrap_or_else(|| document.body.clone());
+        if !bodies.insert(key) {
+            continue;
+        }
+        let header = header(taken.len() + 1, &document);
+        let needed = header.len() + document.body.len() + 2;
+        if used + needed <= budget {
+            used += needed;
+            let body = document.body.clone();
+            taken.push((document, body));
+            continue;
+        }
+        truncated = true;
+        let room = budget.saturating_sub(used + header.len() + 2);
+        if room >= MIN_PARTIAL_TOKENS * BYTES_PER_TOKEN {
+            let limit = (room - 3).min(document.body.len());
+            let cut = (0..=limit)
+                .rev()
+                .find(|&i| document.body.is_char_boundary(i))
+                .unwrap_or(0);
+            let body = format!("{}...", document.body[..cut].trim_end());
+            taken.push((document, body));
+        }
+        break;
+    }
+
+    // Hits of a page and its sections go together
+    let page_of = |document: &SearchableDocument| document.parent_id.clone().unwrap_or_else(|| document.id.clone());
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    for (document, _) in &taken {
+        let next = groups.len();
+        groups.entry(page_of(document)).or_insert(next);
+    }
+    taken.sort_by_key(|(document, _)| groups[&page_of(document)]);
+
+    let mut context = String::new();
+    let mut citations = Vec::with_capacity(taken.len());
+    for (i, (document, body)) in taken.into_iter().enumerate() {
+        if !context.is_empty() {
+            context.push('\n');
+        }
+        context.push_str(&header(i + 1, &document));
+        context.push_str(body.trim());
+        context.push('\n');
+        citations.push(Citation {
+            number: i + 1,
+            id: document.id,
+            title: document.title,
+            source: document.source,
+            version: document.version,
+            url: document.url,
+        });
+    }
+    ContextResponse {
+        tokens: context.len().div_ceil(BYTES_PER_TOKEN),
+        context,
+        citations,
+        truncated,
+    }
+}
+
+// e.g. "[2] Runtime » Shutdown (tokio 1.38.0) <https://docs.rs/...>"
+fn header(number: usize, document: &SearchableDocument) -> String {
+    let mut header = format!("[{}] {} ({}", number, document.title, document.source);
+    if let Some(version) = &document.version {
+        header.push(' ');
+        header.push_str(version);
+    }
+    header.push(')');
+    if let Some(url) = &document.url {
+        header.push_str(&format!(" <{}>", url));
+    }
+    header.push('\n');
+    header
+}
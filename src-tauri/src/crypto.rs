@@ -0,0 +1,168 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::info;
+
+use crate::settings::KeyStorage;
+use crate::util;
+
+// Where the key lives in the OS keychain
+const KEYCHAIN_SERVICE: &str = "com.mcp.dashboard";
+const KEYCHAIN_ACCOUNT: &str = "index-encryption-key";
+
+// Encrypts the stored copies of document fields when
+// `index.encrypt_at_rest` is on. Only what Tantivy stores is sealed: the
+// inverted index (terms and postings) is derived from the plaintext and
+// stays readable, as do ids, sources and versions, which lookups and
+// filters need.
+//
+// The key is 32 random bytes, hex-encoded, kept in the OS keychain. Where
+// there is no keychain (e.g. Linux without a Secret Service), setting
+// `index.key_storage` to "file" keeps it in its own file next to the
+// settings instead, readable only by the user, the same way as the API key.
+// Losing it makes the stored documents unreadable.
+#[derive(Clone)]
+pub struct DocumentCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl DocumentCipher {
+    // `path` is the key file: where the key is kept with `KeyStorage::File`,
+    // and where a key kept there before is moved from into the keychain
+    pub fn load_or_create(storage: KeyStorage, path: &Path) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let key_bytes = match storage {
+            KeyStorage::Keychain => keychain_key(path, &rng)?,
+            KeyStorage::File => file_key(path, &rng)?,
+        };
+
+        let key = UnboundKey::new(&AES_256_GCM, &key_bytes).map_err(|_| anyhow!("Invalid index encryption key"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng,
+        })
+    }
+
+    // A fresh random nonce, followed by the ciphertext and tag
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| anyhow!("Failed to generate a nonce"))?;
+
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow!("Failed to encrypt document"))?;
+        let mut output = nonce.to_vec();
+        output.append(&mut sealed);
+        Ok(output)
+    }
+
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + AES_256_GCM.tag_len() {
+            bail!("Encrypted document is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+
+        let mut buffer = ciphertext.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut buffer)
+            .map_err(|_| anyhow!("Failed to decrypt document; was the index encrypted with another key?"))?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+fn keychain_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(keychain_error)
+}
+
+fn keychain_error(e: keyring::Error) -> anyhow::Error {
+    anyhow!(
+        "Cannot keep the index encryption key in the OS keychain ({}); \
+         set index.key_storage to \"file\" to keep it in index.key instead",
+        e
+    )
+}
+
+fn keychain_key(path: &Path, rng: &SystemRandom) -> Result<Vec<u8>> {
+    let entry = keychain_entry()?;
+    match entry.get_password() {
+        Ok(hex) => return decode_hex(hex.trim()),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(keychain_error(e)),
+    }
+
+    let moved = match std::fs::read_to_string(path) {
+        Ok(contents) => Some(decode_hex(contents.trim())?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let bytes = match &moved {
+        Some(bytes) => bytes.clone(),
+        None => generate_key(rng)?,
+    };
+    entry.set_password(&util::hex(&bytes)).map_err(keychain_error)?;
+    // Read back before the file goes, so a keychain that quietly drops the
+    // key can't lose it
+    let saved = entry.get_password().map_err(keychain_error)?;
+    if decode_hex(saved.trim())? != bytes {
+        bail!("The OS keychain did not keep the index encryption key");
+    }
+    if moved.is_some() {
+        std::fs::remove_file(path)?;
+        info!("Moved the index encryption key from {:?} into the OS keychain", path);
+    } else {
+        info!("Generated a new index encryption key in the OS keychain");
+    }
+    Ok(bytes)
+}
+
+// A key already in the keychain is moved out into the file, so switching
+// to file storage doesn't lose it
+fn file_key(path: &Path, rng: &SystemRandom) -> Result<Vec<u8>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => return decode_hex(contents.trim()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let kept = keychain_entry().ok().and_then(|entry| Some((entry.get_password().ok()?, entry)));
+    let bytes = match &kept {
+        Some((hex, _)) => decode_hex(hex.trim())?,
+        None => generate_key(rng)?,
+    };
+    // Never over a key already there: documents encrypted with it would be lost
+    let mut file = util::open_private(path, OpenOptions::new().write(true).create_new(true))?;
+    file.write_all(util::hex(&bytes).as_bytes())?;
+    match kept {
+        Some((_, entry)) => {
+            let _ = entry.delete_credential();
+            info!("Moved the index encryption key from the OS keychain into {:?}", path);
+        }
+        None => info!("Generated a new index encryption key in {:?}", path),
+    }
+    Ok(bytes)
+}
+
+fn generate_key(rng: &SystemRandom) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; AES_256_GCM.key_len()];
+    rng.fill(&mut bytes).map_err(|_| anyhow!("Failed to generate an encryption key"))?;
+    Ok(bytes)
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.is_ascii() || text.len() % 2 != 0 {
+        bail!("Encryption key is not valid hex");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| anyhow!("Encryption key is not valid hex")))
+        .collect()
+}
+
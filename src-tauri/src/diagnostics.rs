@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::health;
+use crate::logging::Logging;
+use crate::resources;
+use crate::rpc::RpcState;
+use crate::settings::Settings;
+use crate::util::now_millis;
+use crate::McpError;
+
+// The most log a bundle carries, newest first; an older log that doesn't fit
+// keeps its end
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+const REDACTED: &str = "redacted";
+
+// Zip record signatures
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+// Zip 2.0, the first with deflate; the high byte of "made by" says Unix, so
+// the permissions in the external attributes are read
+const ZIP_VERSION: u16 = 20;
+const ZIP_MADE_BY: u16 = (3 << 8) | ZIP_VERSION;
+// Names are UTF-8
+const ZIP_UTF8_NAMES: u16 = 1 << 11;
+const ZIP_DEFLATED: u16 = 8;
+const FILE_MODE: u32 = 0o100644;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct DiagnosticsBundle {
+    /// The bundle, a .zip in the app's log directory
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+// A bundle to attach to a bug report: the app's recent logs, its settings
+// with secrets taken out, and what the index and server look like now.
+// Queries and documents stay out, and so do the audit and slow-query logs.
+// A broken index is when a bundle is wanted most, so what can't be measured
+// goes in as an error rather than failing the export.
+pub async fn export_diagnostics(
+    state: &RpcState,
+    settings: &Settings,
+    logging: &Logging,
+) -> Result<DiagnosticsBundle, McpError> {
+    let created = now_millis();
+    let index = state
+        .search_service
+        .blocking(|service| service.index_stats())
+        .await
+        .map(|(documents, segments)| json!({ "documents": documents, "segments": segments }))
+        .unwrap_or_else(|e| json!({ "error": e.to_string() }));
+    let usage = resources::resource_usage(state)
+        .await
+        .map(|usage| json!(usage))
+        .unwrap_or_else(|e| json!({ "error": e.to_string() }));
+    let report = json!({
+        "created": created,
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "log_level": logging.level().level,
+        "health": health::health(state),
+        "index": index,
+        "resource_usage": usage,
+    });
+    let mut settings = serde_json::to_value(settings)
+        .map_err(|e| McpError::internal(format!("Failed to serialize settings: {}", e)))?;
+    redact(&mut settings, false);
+
+    let dir = logging.path().parent().unwrap_or(Path::new(".")).to_path_buf();
+    let name = format!("diagnostics-{}.zip", Utc::now().format("%Y%m%d-%H%M%S"));
+    let path = dir.join(name);
+    let files = logging.files();
+    let written = path.clone();
+    tokio::task::spawn_blocking(move || write_bundle(&written, &report, &settings, &files))
+        .await
+        .map_err(|e| McpError::internal(format!("Failed to write diagnostics: {}", e)))?
+        .map_err(|e| McpError::internal(format!("Failed to write diagnostics: {}", e)))?;
+    let bytes = std::fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+    Ok(DiagnosticsBundle { path, bytes })
+}
+
+fn write_bundle(path: &Path, report: &Value, settings: &Value, logs: &[PathBuf]) -> Result<()> {
+    let mut archive = ZipWriter::new(File::create(path)?);
+    archive.append("diagnostics.json", &serde_json::to_vec_pretty(report)?)?;
+    archive.append("settings.json", &serde_json::to_vec_pretty(settings)?)?;
+    let mut budget = MAX_LOG_BYTES;
+    for log in logs {
+        if budget == 0 {
+            break;
+        }
+        let Some(name) = log.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // Read rather than added as a file: the live log grows while it's
+        // being copied
+        let contents = match read_tail(log, budget) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        budget -= contents.len() as u64;
+        archive.append(&format!("logs/{}", name), &contents)?;
+    }
+    archive.finish()
+}
+
+// Writes a zip of deflated files, enough for a bundle: no zip64, so it
+// stays under 4 GiB and 65535 files, which the log cap keeps it well within
+struct ZipWriter<W: Write> {
+    out: BufWriter<W>,
+    // Bytes written so far, where the next record starts
+    offset: u64,
+    // Central directory entries, written at the end
+    directory: Vec<u8>,
+    entries: u16,
+    // MS-DOS time and date every entry is stamped with
+    modified: (u16, u16),
+}
+
+impl<W: Write> ZipWriter<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out: BufWriter::new(out),
+            offset: 0,
+            directory: Vec::new(),
+            entries: 0,
+            modified: dos_time(Utc::now()),
+        }
+    }
+
+    fn append(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+        let too_big = || format!("{} doesn't fit in a zip without zip64", name);
+        let size = u32::try_from(contents.len()).with_context(too_big)?;
+        let compressed_size = u32::try_from(compressed.len()).with_context(too_big)?;
+        let offset = u32::try_from(self.offset).with_context(too_big)?;
+        let name_len = u16::try_from(name.len()).with_context(too_big)?;
+        let entries = self.entries.checked_add(1).with_context(too_big)?;
+        let (time, date) = self.modified;
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        put32(&mut local, LOCAL_HEADER);
+        for field in [ZIP_VERSION, ZIP_UTF8_NAMES, ZIP_DEFLATED, time, date] {
+            put16(&mut local, field);
+        }
+        for field in [crc.sum(), compressed_size, size] {
+            put32(&mut local, field);
+        }
+        put16(&mut local, name_len);
+        put16(&mut local, 0);
+        local.extend_from_slice(name.as_bytes());
+        self.out.write_all(&local)?;
+        self.out.write_all(&compressed)?;
+
+        let central = &mut self.directory;
+        put32(central, CENTRAL_HEADER);
+        for field in [ZIP_MADE_BY, ZIP_VERSION, ZIP_UTF8_NAMES, ZIP_DEFLATED, time, date] {
+            put16(central, field);
+        }
+        for field in [crc.sum(), compressed_size, size] {
+            put32(central, field);
+        }
+        // Name length; no extra field, comment, disk number or internal
+        // attributes
+        for field in [name_len, 0, 0, 0, 0] {
+            put16(central, field);
+        }
+        put32(central, FILE_MODE << 16);
+        put32(central, offset);
+        central.extend_from_slice(name.as_bytes());
+
+        self.offset += (local.len() + compressed.len()) as u64;
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let too_big = "The bundle doesn't fit in a zip without zip64";
+        let directory_size = u32::try_from(self.directory.len()).context(too_big)?;
+        let directory_offset = u32::try_from(self.offset).context(too_big)?;
+        self.out.write_all(&self.directory)?;
+        let mut end = Vec::with_capacity(22);
+        put32(&mut end, END_OF_CENTRAL_DIRECTORY);
+        // Everything is on disk 0
+        for field in [0, 0, self.entries, self.entries] {
+            put16(&mut end, field);
+        }
+        put32(&mut end, directory_size);
+        put32(&mut end, directory_offset);
+        // No comment
+        put16(&mut end, 0);
+        self.out.write_all(&end)?;
+        self.out.into_inner().map_err(|e| e.into_error())?.flush()?;
+        Ok(())
+    }
+}
+
+// Zip stamps files with an MS-DOS time, in two-second steps from 1980; the
+// bundle's is in UTC
+fn dos_time(now: DateTime<Utc>) -> (u16, u16) {
+    let year = now.year().clamp(1980, 2107) as u16 - 1980;
+    let time = (now.hour() as u16) << 11 | (now.minute() as u16) << 5 | (now.second() as u16 / 2);
+    let date = year << 9 | (now.month() as u16) << 5 | now.day() as u16;
+    (time, date)
+}
+
+fn put16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+// The last `limit` bytes of the file, where its newest lines are
+fn read_tail(path: &Path, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(limit)))?;
+    let mut contents = Vec::new();
+    file.take(limit).read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+// Takes out API keys, header values, which often carry tokens, and
+// passwords in URLs such as a proxy's. Unset keys stay null, so the bundle
+// still says whether one was set.
+fn redact(value: &mut Value, secret: bool) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                redact(value, secret || is_secret(key));
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact(item, secret);
+            }
+        }
+        Value::String(text) if secret => *text = REDACTED.to_string(),
+        Value::String(text) => {
+            if let Ok(mut url) = Url::parse(text) {
+                if url.password().is_some() && url.set_password(Some(REDACTED)).is_ok() {
+                    *text = url.to_string();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    key.ends_with("api_key") || key.ends_with("headers") || key.contains("password") || key.contains("secret")
+}
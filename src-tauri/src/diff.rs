@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::search::{SearchService, SearchableDocument};
+use crate::McpError;
+
+// Changed pages diffed when the caller doesn't say
+pub const DEFAULT_DIFF_LIMIT: usize = 50;
+
+// Lines of unchanged text kept around each change
+const CONTEXT_LINES: usize = 2;
+
+// Line pairs compared at most for one page, after its unchanged start and
+// end are set aside; past that the page is reported changed without a diff
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+// Stands in for the version inside an id, so the same page of two versions
+// gets the same key
+const VERSION_PLACEHOLDER: &str = "\u{0}";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct DiffVersionsParams {
+    /// The source both versions were indexed under, e.g. `tokio`
+    pub source: String,
+    /// The older version, e.g. `1.37.0`, or an alias of one
+    pub v1: String,
+    /// The newer version, e.g. `1.38.0` or `latest`
+    pub v2: String,
+    /// Maximum number of changed pages to diff (default 50)
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct PageRef {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ChangedPage {
+    pub v1_id: String,
+    pub v2_id: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Unified diff of the body, from v1 to v2; None when the page is too
+    /// long to compare line by line
+    pub diff: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct VersionDiff {
+    pub source: String,
+    pub v1: String,
+    pub v2: String,
+    /// Pages only in v2
+    pub added: Vec<PageRef>,
+    /// Pages only in v1
+    pub removed: Vec<PageRef>,
+    /// Pages in both whose bodies differ, up to the limit asked for
+    pub changed: Vec<ChangedPage>,
+    /// Whether more pages changed than are listed
+    pub truncated: bool,
+    pub unchanged: usize,
+}
+
+// Compares the indexed documents of two versions of a source, so an agent
+// can answer "what changed" from the docs themselves. Either version may be
+// an alias such as `stable`.
+pub fn diff_versions(search_service: &SearchService, params: &DiffVersionsParams) -> Result<VersionDiff, McpError> {
+    let resolve = |version: &str| {
+        search_service
+            .resolve_version(&params.source, version)
+            .map_err(|e| McpError::index("Failed to resolve the version alias", e))
+    };
+    let (v1, v2) = (resolve(&params.v1)?, resolve(&params.v2)?);
+    if v1 == v2 {
+        return Err(McpError::validation(Some("v2"), format!("v1 and v2 are both {}", v1)));
+    }
+    let documents = |version: &str| -> Result<Vec<SearchableDocument>, McpError> {
+        let documents = search_service
+            .version_documents(&params.source, version)
+            .map_err(|e| McpError::index("Failed to read the versions' documents", e))?;
+        if documents.is_empty() {
+            return Err(McpError::not_found(format!("Documents of {} {}", params.source, version)));
+        }
+        Ok(documents)
+    };
+    let (v1_documents, v2_documents) = (documents(&v1)?, documents(&v2)?);
+    let limit = params.limit.unwrap_or(DEFAULT_DIFF_LIMIT);
+    Ok(compare(&params.source, &v1, v1_documents, &v2, v2_documents, limit))
+}
+
+// Pages and sections are matched by id with the version taken out, e.g.
+// ".../tokio/1.37.0/tokio/sync/index.html" with ".../tokio/1.38.0/...",
+// then the ones left over by title, when that's unambiguous.
+fn compare(
+    source: &str,
+    v1: &str,
+    v1_documents: Vec<SearchableDocument>,
+    v2: &str,
+    v2_documents: Vec<SearchableDocument>,
+    limit: usize,
+) -> VersionDiff {
+    let match_key = |document: &SearchableDocument, version: &str| document.id.replace(version, VERSION_PLACEHOLDER);
+    let mut old: HashMap<String, SearchableDocument> =
+        v1_documents.into_iter().map(|document| (match_key(&document, v1), document)).collect();
+    let mut pairs = Vec::new();
+    let mut unmatched = Vec::new();
+    for document in v2_documents {
+        match old.remove(&match_key(&document, v2)) {
+            Some(before) => pairs.push((before, document)),
+            None => unmatched.push(document),
+        }
+    }
+
+    let mut old_by_title: HashMap<String, Vec<String>> = HashMap::new();
+    for (key, document) in &old {
+        old_by_title.entry(document.title.clone()).or_default().push(key.clone());
+    }
+    let mut added = Vec::new();
+    for document in unmatched {
+        let moved = match old_by_title.get(&document.title).map(Vec::as_slice) {
+            Some([key]) => old.remove(key),
+            _ => None,
+        };
+        match moved {
+            Some(before) => pairs.push((before, document)),
+            None => added.push(page_ref(&document)),
+        }
+    }
+    let mut removed: Vec<PageRef> = old.values().map(page_ref).collect();
+
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    pairs.sort_by(|(_, a), (_, b)| a.id.cmp(&b.id));
+    for (before, after) in pairs {
+        if before.body == after.body {
+            unchanged += 1;
+            continue;
+        }
+        changed.push(ChangedPage {
+            diff: unified_diff(&before.body, &after.body),
+            v1_id: before.id,
+            v2_id: after.id,
+            title: after.title,
+            url: after.url,
+        });
+    }
+    let truncated = changed.len() > limit;
+    changed.truncate(limit);
+    added.sort_by(|a, b| a.id.cmp(&b.id));
+    removed.sort_by(|a, b| a.id.cmp(&b.id));
+
+    VersionDiff {
+        source: source.to_string(),
+        v1: v1.to_string(),
+        v2: v2.to_string(),
+        added,
+        removed,
+        changed,
+        truncated,
+        unchanged,
+    }
+}
+
+fn page_ref(document: &SearchableDocument) -> PageRef {
+    PageRef {
+        id: document.id.clone(),
+        title: document.title.clone(),
+        url: document.url.clone(),
+    }
+}
+
+// A line diff in unified format, from the longest common subsequence of
+// the lines both texts don't simply share at their start and end
+fn unified_diff(before: &str, after: &str) -> Option<String> {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        return None;
+    }
+
+    // lcs[i * (m + 1) + j]: the common subsequence of a_mid[i..] and b_mid[j..]
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if a_mid[i] == b_mid[j] {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(char, &str)> = a[..prefix].iter().map(|line| (' ', *line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a_mid[i] == b_mid[j] {
+            ops.push((' ', a_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+            ops.push(('-', a_mid[i]));
+            i += 1;
+        } else {
+            ops.push(('+', b_mid[j]));
+            j += 1;
+        }
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|line| (' ', *line)));
+    Some(hunks(&ops))
+}
+
+// Groups changes less than two contexts apart into one hunk
+fn hunks(ops: &[(char, &str)]) -> String {
+    let changes: Vec<usize> = ops.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ').map(|(i, _)| i).collect();
+    let lines = |ops: &[(char, &str)], side: char| ops.iter().filter(|(tag, _)| *tag != side).count();
+    let mut diff = String::new();
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT_LINES);
+        while k + 1 < changes.len() && changes[k + 1] - changes[k] <= 2 * CONTEXT_LINES + 1 {
+            k += 1;
+        }
+        let end = (changes[k] + CONTEXT_LINES + 1).min(ops.len());
+        k += 1;
+
+        let hunk = &ops[start..end];
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            lines(&ops[..start], '+') + 1,
+            lines(hunk, '+'),
+            lines(&ops[..start], '-') + 1,
+            lines(hunk, '-'),
+        ));
+        for (tag, line) in hunk {
+            diff.push(*tag);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    diff
+}
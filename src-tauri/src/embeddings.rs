@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{anyhow, bail, Result};
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+use crate::ingest::Fetcher;
+use crate::search::{project_document, IndexEvent, SearchOptions, SearchService, SearchableDocument};
+use crate::settings::{EmbeddingSettings, RemoteEmbeddingSettings};
+use crate::validation;
+use crate::McpError;
+
+// BGE small: 384 dimensions and about 130 MB, downloaded on first use
+const MODEL: EmbeddingModel = EmbeddingModel::BGESmallENV15;
+
+// The model reads 512 tokens at most, which this comfortably covers
+const MAX_TEXT_CHARS: usize = 2000;
+
+// Documents embedded per call to the local model
+const BATCH_SIZE: usize = 32;
+
+// Documents read per page when catching up on the whole index
+const CATCH_UP_PAGE: usize = 500;
+
+// An embeddings API's answer to the largest batch allowed, with the widest
+// vectors around, fits in this
+const MAX_ANSWER_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SemanticSearchParams {
+    /// What to look for in plain words, e.g. "how do I cancel a future"
+    pub query: String,
+    /// Maximum number of results (default 10)
+    pub limit: Option<usize>,
+    /// Only documents of this source
+    pub source: Option<String>,
+    /// Only documents of this version
+    pub version: Option<String>,
+    /// Only return these fields of each hit, as in searchDocuments; there
+    /// are no snippets
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct SemanticHit {
+    /// Cosine similarity of the document to the query, up to 1
+    pub score: f32,
+    // The whole document, or only the fields asked for
+    #[schemars(with = "SearchableDocument")]
+    pub document: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct SemanticSearchResponse {
+    pub hits: Vec<SemanticHit>,
+}
+
+// What an OpenAI-compatible /embeddings endpoint answers
+#[derive(Deserialize)]
+struct EmbeddingsAnswer {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+// Embeds every page and section with a sentence-transformer, so searches
+// can match what a document means rather than the words it uses. The model
+// is a small one run locally, or one behind an OpenAI-compatible API.
+// Documents are embedded in the background as they're indexed, into the
+// search service's vector index.
+pub struct Embeddings {
+    search_service: Arc<SearchService>,
+    fetcher: Arc<Fetcher>,
+    settings: RwLock<EmbeddingSettings>,
+    model_dir: PathBuf,
+    // Loaded when first needed, since that may mean downloading it
+    model: Mutex<Option<TextEmbedding>>,
+    changed: Notify,
+}
+
+impl Embeddings {
+    pub fn new(
+        model_dir: PathBuf,
+        search_service: Arc<SearchService>,
+        fetcher: Arc<Fetcher>,
+        settings: EmbeddingSettings,
+    ) -> Self {
+        search_service.use_vector_model(&model_id(&settings));
+        Self {
+            search_service,
+            fetcher,
+            settings: RwLock::new(settings),
+            model_dir,
+            model: Mutex::new(None),
+            changed: Notify::new(),
+        }
+    }
+
+    // Turning embeddings on catches up on everything indexed meanwhile.
+    // Switching to another model drops every vector, and the index is
+    // embedded again with the new one.
+    pub fn replace(&self, settings: EmbeddingSettings) {
+        self.search_service.use_vector_model(&model_id(&settings));
+        *self.settings.write().unwrap() = settings;
+        self.changed.notify_one();
+    }
+
+    // The query's embedding, for a vector search through the search
+    // service's options
+    pub async fn query_vector(self: &Arc<Self>, query: &str) -> Result<Vec<f32>, McpError> {
+        if !self.settings.read().unwrap().enabled {
+            return Err(McpError::unsupported(
+                "Semantic search is off; turn on embeddings.enabled in the settings",
+            ));
+        }
+        let (_, mut vectors) = self
+            .embed(vec![query.to_string()])
+            .await
+            .map_err(|e| McpError::internal(format!("Failed to embed the query: {}", e)))?;
+        vectors
+            .pop()
+            .ok_or_else(|| McpError::internal("The model returned no embedding"))
+    }
+
+    // The best matches for the query by meaning, across the documents
+    // embedded so far
+    pub async fn search(self: Arc<Self>, params: SemanticSearchParams) -> Result<SemanticSearchResponse, McpError> {
+        validation::validate_semantic_search(&params)?;
+        let vector = self.query_vector(&params.query).await?;
+        let options = SearchOptions {
+            source: params.source.clone(),
+            version: params.version.clone(),
+            query_vector: Some(vector.clone()),
+            keep_near_duplicates: true,
+            ..Default::default()
+        };
+        let documents = self
+            .search_service
+            .search(&params.query, params.limit.unwrap_or(10), &options)
+            .await
+            .map_err(|e| McpError::search(&params.query, e))?;
+        Ok(SemanticSearchResponse {
+            hits: documents
+                .into_iter()
+                .map(|document| SemanticHit {
+                    score: self
+                        .search_service
+                        .similarity(&document.id, &vector)
+                        .unwrap_or_default(),
+                    document: project_document(document, None, &params.fields),
+                })
+                .collect(),
+        })
+    }
+
+    // Embeds documents as they're indexed until the app exits. Events
+    // arriving together are embedded in batches. The search service drops
+    // the vectors of documents that change or go by itself.
+    pub async fn run(self: Arc<Self>) {
+        let mut events = self.search_service.subscribe();
+        let mut catch_up = true;
+        loop {
+            if catch_up && self.settings.read().unwrap().enabled {
+                catch_up = false;
+                if let Err(e) = self.catch_up().await {
+                    error!("Failed to embed the index: {}", e);
+                }
+            }
+
+            let mut added = Vec::new();
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(IndexEvent::DocumentAdded { id, .. }) => added.push(id),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => catch_up = true,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = self.changed.notified() => catch_up = true,
+            }
+            loop {
+                match events.try_recv() {
+                    Ok(IndexEvent::DocumentAdded { id, .. }) => added.push(id),
+                    Ok(_) => {}
+                    Err(TryRecvError::Lagged(_)) => catch_up = true,
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                }
+            }
+
+            if !added.is_empty() && !catch_up && self.settings.read().unwrap().enabled {
+                if let Err(e) = self.embed_ids(&added).await {
+                    error!("Failed to embed documents: {}", e);
+                }
+            }
+            if let Err(e) = self.search_service.save_vectors() {
+                error!("Failed to save the vector index: {}", e);
+            }
+        }
+    }
+
+    // Embeds every indexed document without a vector, and drops the vectors
+    // of documents no longer indexed
+    async fn catch_up(self: &Arc<Self>) -> Result<()> {
+        let mut indexed = HashSet::new();
+        let mut offset = 0;
+        loop {
+            let page = self
+                .search_service
+                .blocking(move |service| service.list_documents(offset, CATCH_UP_PAGE))
+                .await?;
+            let count = page.len();
+            indexed.extend(page.iter().map(|document| document.id.clone()));
+            let missing: Vec<SearchableDocument> = page
+                .into_iter()
+                .filter(|document| !self.search_service.has_vector(&document.id))
+                .collect();
+            self.embed_documents(&missing).await?;
+            self.search_service.save_vectors()?;
+            if count < CATCH_UP_PAGE {
+                break;
+            }
+            offset += count;
+        }
+        let gone: Vec<String> = self
+            .search_service
+            .vector_ids()
+            .into_iter()
+            .filter(|id| !indexed.contains(id))
+            .collect();
+        self.search_service.remove_vectors(&gone);
+        self.search_service.save_vectors()
+    }
+
+    async fn embed_ids(self: &Arc<Self>, ids: &[String]) -> Result<()> {
+        let ids = ids.to_vec();
+        let documents = self
+            .search_service
+            .blocking(move |service| {
+                let mut documents = Vec::with_capacity(ids.len());
+                for id in &ids {
+                    documents.extend(service.get_document(id)?);
+                }
+                Ok::<_, anyhow::Error>(documents)
+            })
+            .await?;
+        self.embed_documents(&documents).await
+    }
+
+    // Vectors from a model switched away from meanwhile are thrown away
+    async fn embed_documents(self: &Arc<Self>, documents: &[SearchableDocument]) -> Result<()> {
+        let batch_size = match &self.settings.read().unwrap().remote {
+            Some(remote) => remote.batch_size.max(1),
+            None => BATCH_SIZE,
+        };
+        for batch in documents.chunks(batch_size) {
+            let texts = batch.iter().map(embedding_text).collect();
+            let (model_id, vectors) = self.embed(texts).await?;
+            let embeddings = batch.iter().map(|document| document.id.clone()).zip(vectors).collect();
+            if !self.search_service.set_vectors(&model_id, embeddings) {
+                return Ok(());
+            }
+        }
+        if !documents.is_empty() {
+            info!("Embedded {} documents", documents.len());
+        }
+        Ok(())
+    }
+
+    // Unit-length vectors, in the order of the texts, with the id of the
+    // model that made them
+    async fn embed(self: &Arc<Self>, texts: Vec<String>) -> Result<(String, Vec<Vec<f32>>)> {
+        let settings = self.settings.read().unwrap().clone();
+        let mut vectors = match &settings.remote {
+            Some(remote) => self.embed_remotely(remote, texts).await?,
+            None => {
+                let this = self.clone();
+                tokio::task::spawn_blocking(move || this.embed_locally(texts))
+                    .await
+                    .map_err(|e| anyhow!("Embedding task failed: {}", e))??
+            }
+        };
+        for vector in &mut vectors {
+            normalize(vector);
+        }
+        Ok((model_id(&settings), vectors))
+    }
+
+    fn embed_locally(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut model = self.model.lock().unwrap();
+        if model.is_none() {
+            info!("Loading embedding model {:?} into {:?}", MODEL, self.model_dir);
+            let options = InitOptions::new(MODEL)
+                .with_cache_dir(self.model_dir.clone())
+                .with_show_download_progress(false);
+            *model = Some(TextEmbedding::try_new(options)?);
+        }
+        let model = model.as_mut().ok_or_else(|| anyhow!("Embedding model not loaded"))?;
+        model.embed(texts, Some(BATCH_SIZE))
+    }
+
+    // One request per batch; the fetcher retries it while it fails
+    // transiently, e.g. on 429s from a rate-limited API
+    async fn embed_remotely(&self, remote: &RemoteEmbeddingSettings, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let url = Fetcher::parse_url(&format!("{}/embeddings", remote.url.trim_end_matches('/')))?;
+        let count = texts.len();
+        let body = json!({ "model": remote.model, "input": texts });
+        let answer = self
+            .fetcher
+            .post_json(&url, remote.api_key.as_deref(), &body, MAX_ANSWER_BYTES)
+            .await?;
+        let mut answer: EmbeddingsAnswer = serde_json::from_value(answer)?;
+        if answer.data.len() != count {
+            bail!(
+                "{} answered with {} embeddings for {} texts",
+                url,
+                answer.data.len(),
+                count
+            );
+        }
+        answer.data.sort_by_key(|data| data.index);
+        Ok(answer.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+// Vectors made by different models can't be compared, so the vector index
+// is tagged with the model that made them
+fn model_id(settings: &EmbeddingSettings) -> String {
+    match &settings.remote {
+        Some(remote) => format!("{} {}", remote.url.trim_end_matches('/'), remote.model),
+        None => format!("local {:?}", MODEL),
+    }
+}
+
+// A section's title already leads with its page's headings
+fn embedding_text(document: &SearchableDocument) -> String {
+    let text = format!("{}\n{}", document.title, document.body);
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let length = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if length > 0.0 {
+        for x in vector.iter_mut() {
+            *x /= length;
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use tantivy::query::QueryParserError;
+
+// Application errors shared by the Tauri commands, JSON-RPC and REST.
+// Every variant has a fixed numeric code (JSON-RPC server error range, plus
+// the standard codes where one fits) so clients can branch on it; the
+// message is for humans and may change. Serializes as
+// `{ "code": ..., "message": ..., "data": { "kind": ..., ... } }` everywhere.
+#[derive(Debug, Clone)]
+pub enum McpError {
+    // The index couldn't be read or written
+    Index { message: String },
+    // The search query isn't valid Tantivy query syntax
+    QueryParse { query: String, message: String },
+    // A document or resource doesn't exist
+    NotFound { resource: String },
+    // The request was well-formed JSON but its values are not acceptable
+    Validation { field: Option<String>, message: String },
+    // The client (or platform) lacks something the request needs
+    Unsupported { message: String },
+    // The RPC server couldn't be started or reconfigured
+    Server { message: String },
+    // The request didn't carry a valid API key
+    Unauthorized { message: String },
+    // The API key is valid but its scope doesn't cover the request
+    Forbidden { message: String },
+    // A page couldn't be downloaded
+    Fetch { url: String, message: String },
+    // The client is over its request rate
+    RateLimited { retry_after_ms: u64 },
+    Internal { message: String },
+}
+
+impl McpError {
+    pub const INDEX: i64 = -32001;
+    // Same code MCP uses for "resource not found"
+    pub const NOT_FOUND: i64 = -32002;
+    pub const QUERY_PARSE: i64 = -32003;
+    pub const UNSUPPORTED: i64 = -32004;
+    pub const SERVER: i64 = -32005;
+    pub const UNAUTHORIZED: i64 = -32006;
+    pub const RATE_LIMITED: i64 = -32007;
+    pub const FORBIDDEN: i64 = -32008;
+    pub const FETCH: i64 = -32009;
+    pub const VALIDATION: i64 = -32602;
+    pub const INTERNAL: i64 = -32603;
+
+    // `context` says what was being attempted, e.g. "Failed to add document"
+    pub fn index(context: &str, error: impl fmt::Display) -> Self {
+        McpError::Index {
+            message: format!("{}: {}", context, error),
+        }
+    }
+
+    // Tells query syntax errors apart from index failures
+    pub fn search(query: &str, error: anyhow::Error) -> Self {
+        match error.downcast_ref::<QueryParserError>() {
+            Some(parse_error) => McpError::QueryParse {
+                query: query.to_string(),
+                message: parse_error.to_string(),
+            },
+            None => McpError::index("Failed to search documents", error),
+        }
+    }
+
+    pub fn not_found(resource: impl Into<String>) -> Self {
+        McpError::NotFound {
+            resource: resource.into(),
+        }
+    }
+
+    pub fn validation(field: Option<&str>, message: impl Into<String>) -> Self {
+        McpError::Validation {
+            field: field.map(str::to_string),
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        McpError::Unsupported {
+            message: message.into(),
+        }
+    }
+
+    pub fn server(message: impl Into<String>) -> Self {
+        McpError::Server {
+            message: message.into(),
+        }
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        McpError::Unauthorized {
+            message: message.into(),
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        McpError::Forbidden {
+            message: message.into(),
+        }
+    }
+
+    pub fn fetch(url: &str, error: impl fmt::Display) -> Self {
+        McpError::Fetch {
+            url: url.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn rate_limited(retry_after: Duration) -> Self {
+        McpError::RateLimited {
+            retry_after_ms: retry_after.as_millis() as u64,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        McpError::Internal {
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            McpError::Index { .. } => Self::INDEX,
+            McpError::QueryParse { .. } => Self::QUERY_PARSE,
+            McpError::NotFound { .. } => Self::NOT_FOUND,
+            McpError::Validation { .. } => Self::VALIDATION,
+            McpError::Unsupported { .. } => Self::UNSUPPORTED,
+            McpError::Server { .. } => Self::SERVER,
+            McpError::Unauthorized { .. } => Self::UNAUTHORIZED,
+            McpError::Forbidden { .. } => Self::FORBIDDEN,
+            McpError::Fetch { .. } => Self::FETCH,
+            McpError::RateLimited { .. } => Self::RATE_LIMITED,
+            McpError::Internal { .. } => Self::INTERNAL,
+        }
+    }
+
+    // Machine-readable details; `kind` names the variant
+    pub fn data(&self) -> Value {
+        match self {
+            McpError::Index { .. } => json!({ "kind": "index" }),
+            McpError::QueryParse { query, .. } => json!({ "kind": "queryParse", "query": query }),
+            McpError::NotFound { resource } => json!({ "kind": "notFound", "resource": resource }),
+            McpError::Validation { field, .. } => json!({ "kind": "validation", "field": field }),
+            McpError::Unsupported { .. } => json!({ "kind": "unsupported" }),
+            McpError::Server { .. } => json!({ "kind": "server" }),
+            McpError::Unauthorized { .. } => json!({ "kind": "unauthorized" }),
+            McpError::Forbidden { .. } => json!({ "kind": "forbidden" }),
+            McpError::Fetch { url, .. } => json!({ "kind": "fetch", "url": url }),
+            McpError::RateLimited { retry_after_ms } => json!({ "kind": "rateLimited", "retryAfterMs": retry_after_ms }),
+            McpError::Internal { .. } => json!({ "kind": "internal" }),
+        }
+    }
+}
+
+impl fmt::Display for McpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McpError::QueryParse { query, message } => write!(f, "Invalid query \"{}\": {}", query, message),
+            McpError::NotFound { resource } => write!(f, "{} not found", resource),
+            McpError::Fetch { url, message } => write!(f, "Failed to fetch {}: {}", url, message),
+            McpError::RateLimited { retry_after_ms } => {
+                write!(f, "Too many requests; retry in {} ms", retry_after_ms)
+            }
+            McpError::Index { message }
+            | McpError::Validation { message, .. }
+            | McpError::Unsupported { message }
+            | McpError::Server { message }
+            | McpError::Unauthorized { message }
+            | McpError::Forbidden { message }
+            | McpError::Internal { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for McpError {}
+
+impl Serialize for McpError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("McpError", 3)?;
+        error.serialize_field("code", &self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("data", &self.data())?;
+        error.end()
+    }
+}
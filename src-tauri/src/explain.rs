@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tantivy::query::{BooleanQuery, Explanation, Occur, PhrasePrefixQuery, PhraseQuery, Query, TermQuery};
+use tantivy::schema::Field;
+use tantivy::{DocAddress, Searcher, TantivyError, Term};
+
+use crate::search::SearchService;
+use crate::validation;
+use crate::McpError;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ExplainQueryParams {
+    /// A query as searchDocuments takes it
+    pub query: String,
+    /// The document to score, as search hits give its id
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClauseOccur {
+    Must,
+    Should,
+    MustNot,
+}
+
+// One query in the parsed tree
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct QueryNode {
+    /// "boolean", "term", "phrase" or "phrase_prefix", or "other" for a
+    /// query that isn't broken down further, such as a boosted one
+    pub kind: String,
+    /// How the clause has to match, inside a boolean query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occur: Option<ClauseOccur>,
+    /// The fields it searches
+    pub fields: Vec<String>,
+    /// The term or phrase as the field's analyzer left it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// How tantivy prints a query of kind "other"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// What it scores the document on its own; absent when it doesn't match.
+    /// A must_not clause that matches is why the document doesn't.
+    pub score: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<QueryNode>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct QueryExplanation {
+    pub matched: bool,
+    /// The document's text score for the query, before reranking, freshness,
+    /// metric boosts and preferred versions
+    pub score: f32,
+    /// How much of the score each field's clauses make up. A clause that isn't
+    /// broken down and searches several fields counts under their names joined
+    /// with "+".
+    pub fields: BTreeMap<String, f32>,
+    pub tree: QueryNode,
+    /// tantivy's own breakdown of the score, down to each term's BM25 idf,
+    /// term frequency and field norm, when the document matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Value>,
+}
+
+// explainQuery: how the query parses, and how it scores the document, for
+// tuning relevance
+pub async fn explain_query(
+    search_service: &Arc<SearchService>,
+    params: &ExplainQueryParams,
+) -> Result<QueryExplanation, McpError> {
+    validation::validate_explain_query(params)?;
+    let query = params.query.clone();
+    let id = params.id.clone();
+    search_service
+        .blocking(move |service| service.explain(&query, &id))
+        .await
+        .map_err(|e| McpError::search(&params.query, e))?
+        .ok_or_else(|| McpError::not_found(format!("Document {}", params.id)))
+}
+
+pub fn explain(searcher: &Searcher, query: &dyn Query, doc: DocAddress) -> Result<QueryExplanation> {
+    let explanation = explained(searcher, query, doc)?;
+    let tree = node(searcher, query, None, doc)?;
+    let mut fields = BTreeMap::new();
+    add_contributions(&tree, &mut fields);
+    Ok(QueryExplanation {
+        matched: explanation.is_some(),
+        score: explanation.as_ref().map_or(0.0, |explanation| explanation.value()),
+        fields,
+        tree,
+        explanation: explanation
+            .map(|explanation| serde_json::to_value(&explanation))
+            .transpose()?,
+    })
+}
+
+// tantivy refuses to explain a document the query doesn't match
+fn explained(searcher: &Searcher, query: &dyn Query, doc: DocAddress) -> Result<Option<Explanation>> {
+    match query.explain(searcher, doc) {
+        Ok(explanation) => Ok(Some(explanation)),
+        Err(TantivyError::InvalidArgument(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Each clause is scored on its own, which comes to the same as its share of
+// the whole: boolean queries add up their clauses' scores
+fn node(searcher: &Searcher, query: &dyn Query, occur: Option<Occur>, doc: DocAddress) -> Result<QueryNode> {
+    let schema = searcher.schema();
+    let name = |field: Field| schema.get_field_name(field).to_string();
+    let mut node = QueryNode {
+        kind: "other".to_string(),
+        occur: occur.map(|occur| match occur {
+            Occur::Must => ClauseOccur::Must,
+            Occur::Should => ClauseOccur::Should,
+            Occur::MustNot => ClauseOccur::MustNot,
+        }),
+        fields: Vec::new(),
+        text: None,
+        query: None,
+        score: explained(searcher, query, doc)?.map(|explanation| explanation.value()),
+        children: Vec::new(),
+    };
+    if let Some(boolean) = query.downcast_ref::<BooleanQuery>() {
+        node.kind = "boolean".to_string();
+        for (occur, clause) in boolean.clauses() {
+            node.children
+                .push(self::node(searcher, clause.as_ref(), Some(*occur), doc)?);
+        }
+        let fields: BTreeSet<&String> = node.children.iter().flat_map(|child| &child.fields).collect();
+        node.fields = fields.into_iter().cloned().collect();
+    } else if let Some(term) = query.downcast_ref::<TermQuery>() {
+        node.kind = "term".to_string();
+        node.fields = vec![name(term.term().field())];
+        node.text = Some(text(term.term()));
+    } else if let Some(phrase) = query.downcast_ref::<PhraseQuery>() {
+        node.kind = "phrase".to_string();
+        node.fields = vec![name(phrase.field())];
+        node.text = Some(phrase.phrase_terms().iter().map(text).collect::<Vec<_>>().join(" "));
+    } else if let Some(phrase) = query.downcast_ref::<PhrasePrefixQuery>() {
+        node.kind = "phrase_prefix".to_string();
+        node.fields = vec![name(phrase.field())];
+        node.text = Some(phrase.phrase_terms().iter().map(text).collect::<Vec<_>>().join(" "));
+    } else {
+        let mut fields = BTreeSet::new();
+        query.query_terms(&mut |term, _| {
+            fields.insert(name(term.field()));
+        });
+        node.fields = fields.into_iter().collect();
+        node.query = Some(format!("{:?}", query));
+    }
+    Ok(node)
+}
+
+// Only what counts towards the score: matching clauses that aren't
+// must_not, down to the ones that aren't broken down further. Clauses that
+// search no field, such as "*", aren't counted.
+fn add_contributions(node: &QueryNode, fields: &mut BTreeMap<String, f32>) {
+    let Some(score) = node.score else {
+        return;
+    };
+    if node.occur == Some(ClauseOccur::MustNot) {
+        return;
+    }
+    if node.children.is_empty() {
+        if !node.fields.is_empty() {
+            *fields.entry(node.fields.join("+")).or_default() += score;
+        }
+    } else {
+        for child in &node.children {
+            add_contributions(child, fields);
+        }
+    }
+}
+
+fn text(term: &Term) -> String {
+    match term.value().as_str() {
+        Some(text) => text.to_string(),
+        None => format!("{:?}", term),
+    }
+}
@@ -0,0 +1,74 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::RpcState;
+
+// getHealth takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct GetHealthParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct HealthCheck {
+    pub ok: bool,
+    /// What was found, or why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Health {
+    /// Every check passed, so the server can take work
+    pub ready: bool,
+    /// The index opens for reading
+    pub index: HealthCheck,
+    /// The last write could open an index writer
+    pub writer: HealthCheck,
+    /// Warm-up has finished, or was skipped
+    pub warm: HealthCheck,
+    pub uptime_secs: u64,
+    pub version: String,
+}
+
+// Whether the server is actually usable, for /readyz and getHealth. Cheap
+// enough for a supervisor to poll: nothing is searched or written.
+pub fn health(state: &RpcState) -> Health {
+    let index = match state.search_service.index_stats() {
+        Ok((documents, segments)) => HealthCheck {
+            ok: true,
+            detail: Some(format!("{} documents in {} segments", documents, segments)),
+        },
+        Err(e) => HealthCheck {
+            ok: false,
+            detail: Some(format!("Failed to open the index: {}", e)),
+        },
+    };
+    let writer = match state.search_service.writer_error() {
+        None => HealthCheck { ok: true, detail: None },
+        Some(e) => HealthCheck {
+            ok: false,
+            detail: Some(format!("Failed to open an index writer: {}", e)),
+        },
+    };
+    let warm = match state.search_ready.get() {
+        Some(ready) if ready.warmed => HealthCheck {
+            ok: true,
+            detail: Some(format!("Warmed up in {} ms", ready.elapsed_ms)),
+        },
+        Some(_) => HealthCheck {
+            ok: true,
+            detail: Some("Not warmed up, so the first searches may be slow".to_string()),
+        },
+        None => HealthCheck {
+            ok: false,
+            detail: Some("Warming up".to_string()),
+        },
+    };
+    Health {
+        ready: index.ok && writer.ok && warm.ok,
+        index,
+        writer,
+        warm,
+        uptime_secs: state.started.elapsed().as_secs(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
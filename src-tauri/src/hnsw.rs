@@ -0,0 +1,609 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+use tracing::warn;
+
+// Links per node and layer; the bottom layer, which every node is in, gets
+// twice as many
+const M: usize = 16;
+
+// Candidates kept while linking a new node: more is slower and finds
+// better neighbours
+const EF_CONSTRUCTION: usize = 100;
+
+// No node goes higher, whatever the dice say
+const MAX_LEVEL: usize = 16;
+
+// Deleted nodes are only dropped from the graph, by rebuilding it, once
+// there are at least this many and more of them than live ones
+const MIN_REBUILD: usize = 1000;
+
+// Anything wider is no embedding, but a corrupt file
+const MAX_DIMENSIONS: usize = 65_536;
+
+const MAGIC: &[u8; 8] = b"LDMHNSW1";
+
+struct Node {
+    id: String,
+    // Unit length, so 1 minus the dot product is the cosine distance
+    vector: Vec<f32>,
+    // Neighbours on each layer the node is in, from the bottom up
+    links: Vec<Vec<u32>>,
+    // Deleted nodes still route searches until the next rebuild, but are
+    // never returned
+    deleted: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Approximate nearest neighbours over document embeddings: a hierarchical
+// navigable small world graph, kept in memory and saved to a file beside
+// the Tantivy index. Vectors are tagged with the model that made them,
+// since vectors from different models can't be compared.
+pub struct VectorIndex {
+    path: PathBuf,
+    model: Option<String>,
+    nodes: Vec<Node>,
+    // Live nodes by document id
+    ids: HashMap<String, u32>,
+    // Where searches start: a node on the top layer
+    entry: Option<u32>,
+    // Changed since it was last saved
+    dirty: bool,
+}
+
+impl VectorIndex {
+    // A missing or unreadable file starts empty
+    pub fn load(path: PathBuf) -> Self {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Self::empty(path),
+        };
+        Self::read(path.clone(), &mut BufReader::new(file)).unwrap_or_else(|e| {
+            warn!("Ignoring invalid vector index {:?}: {}", path, e);
+            Self::empty(path)
+        })
+    }
+
+    fn empty(path: PathBuf) -> Self {
+        Self {
+            path,
+            model: None,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            dirty: false,
+        }
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.ids.keys()
+    }
+
+    // Cosine similarity of the document's vector to the query's
+    pub fn similarity(&self, id: &str, query: &[f32]) -> Option<f32> {
+        let node = &self.nodes[*self.ids.get(id)? as usize];
+        Some(1.0 - distance(&node.vector, query))
+    }
+
+    // Switching to another model drops every vector
+    pub fn set_model(&mut self, model: &str) {
+        if self.model.as_deref() == Some(model) {
+            return;
+        }
+        *self = Self::empty(std::mem::take(&mut self.path));
+        self.model = Some(model.to_string());
+        self.dirty = true;
+    }
+
+    // Replaces the document's vector, if it had one
+    pub fn insert(&mut self, id: &str, vector: Vec<f32>) {
+        self.remove(id);
+        let node = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            id: id.to_string(),
+            vector,
+            links: vec![Vec::new(); random_level() + 1],
+            deleted: false,
+        });
+        self.ids.insert(id.to_string(), node);
+        self.dirty = true;
+        match self.entry {
+            Some(entry) => self.link(node, entry),
+            None => self.entry = Some(node),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(node) = self.ids.remove(id) else {
+            return false;
+        };
+        self.nodes[node as usize].deleted = true;
+        self.dirty = true;
+        let deleted = self.nodes.len() - self.ids.len();
+        if deleted >= MIN_REBUILD && deleted > self.ids.len() {
+            self.rebuild();
+        }
+        true
+    }
+
+    // The `k` live documents nearest the query, nearest first, with their
+    // cosine similarity to it. A larger `ef` searches more of the graph.
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(String, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        let mut nearest = entry;
+        for layer in (1..self.nodes[entry as usize].links.len()).rev() {
+            nearest = self.closest(query, nearest, layer);
+        }
+        self.search_layer(query, &[nearest], ef.max(k), 0)
+            .into_iter()
+            .filter(|candidate| !self.nodes[candidate.node as usize].deleted)
+            .take(k)
+            .map(|candidate| (self.nodes[candidate.node as usize].id.clone(), 1.0 - candidate.distance))
+            .collect()
+    }
+
+    // Written to a temporary file first, so a crash part way leaves the
+    // last copy whole
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temporary = self.path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&temporary)?);
+        out.write_all(MAGIC)?;
+        write_str(&mut out, self.model.as_deref().unwrap_or_default())?;
+        write_u32(&mut out, self.entry.unwrap_or(u32::MAX))?;
+        write_u32(&mut out, self.nodes.len() as u32)?;
+        for node in &self.nodes {
+            out.write_all(&[node.deleted as u8])?;
+            write_str(&mut out, &node.id)?;
+            write_u32(&mut out, node.vector.len() as u32)?;
+            for x in &node.vector {
+                out.write_all(&x.to_le_bytes())?;
+            }
+            write_u32(&mut out, node.links.len() as u32)?;
+            for links in &node.links {
+                write_u32(&mut out, links.len() as u32)?;
+                for link in links {
+                    write_u32(&mut out, *link)?;
+                }
+            }
+        }
+        out.into_inner()
+            .map_err(|e| anyhow!("Failed to write {:?}: {}", temporary, e))?;
+        std::fs::rename(&temporary, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // Finds the new node's neighbours from the top of the graph down, and
+    // links it to them both ways on each of its layers
+    fn link(&mut self, node: u32, entry: u32) {
+        let level = self.nodes[node as usize].links.len() - 1;
+        let top = self.nodes[entry as usize].links.len() - 1;
+        let vector = self.nodes[node as usize].vector.clone();
+        let mut nearest = entry;
+        for layer in (level + 1..=top).rev() {
+            nearest = self.closest(&vector, nearest, layer);
+        }
+        let mut entries = vec![nearest];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&vector, &entries, EF_CONSTRUCTION, layer);
+            let neighbours: Vec<u32> = found.iter().take(M).map(|candidate| candidate.node).collect();
+            for &neighbour in &neighbours {
+                let links = &mut self.nodes[neighbour as usize].links[layer];
+                links.push(node);
+                if links.len() > max_links(layer) {
+                    self.prune(neighbour, layer);
+                }
+            }
+            self.nodes[node as usize].links[layer] = neighbours;
+            entries = found.into_iter().map(|candidate| candidate.node).collect();
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    // Keeps the node's closest links on the layer
+    fn prune(&mut self, node: u32, layer: usize) {
+        let vector = &self.nodes[node as usize].vector;
+        let mut links: Vec<Candidate> = self.nodes[node as usize].links[layer]
+            .iter()
+            .map(|&link| Candidate {
+                distance: distance(vector, &self.nodes[link as usize].vector),
+                node: link,
+            })
+            .collect();
+        links.sort();
+        links.truncate(max_links(layer));
+        self.nodes[node as usize].links[layer] = links.into_iter().map(|candidate| candidate.node).collect();
+    }
+
+    // Walks the layer greedily towards the query
+    fn closest(&self, query: &[f32], start: u32, layer: usize) -> u32 {
+        let mut nearest = start;
+        let mut best = distance(query, &self.nodes[nearest as usize].vector);
+        loop {
+            let mut moved = false;
+            for &link in &self.nodes[nearest as usize].links[layer] {
+                let distance = distance(query, &self.nodes[link as usize].vector);
+                if distance < best {
+                    best = distance;
+                    nearest = link;
+                    moved = true;
+                }
+            }
+            if !moved {
+                return nearest;
+            }
+        }
+    }
+
+    // The `ef` nodes nearest the query that a best-first walk of the layer
+    // finds, deleted ones included, nearest first
+    fn search_layer(&self, query: &[f32], entries: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        // Nearest first
+        let mut candidates = BinaryHeap::new();
+        // Farthest first, so the worst is the one to drop
+        let mut found = BinaryHeap::new();
+        for &node in entries {
+            let candidate = Candidate {
+                distance: distance(query, &self.nodes[node as usize].vector),
+                node,
+            };
+            candidates.push(Reverse(candidate));
+            found.push(candidate);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+        while let Some(Reverse(current)) = candidates.pop() {
+            if found.len() >= ef
+                && found
+                    .peek()
+                    .is_some_and(|farthest| current.distance > farthest.distance)
+            {
+                break;
+            }
+            for &link in &self.nodes[current.node as usize].links[layer] {
+                if !visited.insert(link) {
+                    continue;
+                }
+                let distance = distance(query, &self.nodes[link as usize].vector);
+                if found.len() < ef || found.peek().is_some_and(|farthest| distance < farthest.distance) {
+                    let candidate = Candidate { distance, node: link };
+                    candidates.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    // Links the live nodes afresh, leaving the deleted ones out
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.ids.clear();
+        self.entry = None;
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(&node.id, node.vector);
+        }
+    }
+
+    fn read(path: PathBuf, input: &mut impl Read) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            bail!("not a vector index");
+        }
+        let model = read_str(input)?;
+        let entry = read_u32(input)?;
+        let count = read_u32(input)? as usize;
+        let mut nodes = Vec::new();
+        let mut ids = HashMap::new();
+        for node in 0..count {
+            let mut deleted = [0u8; 1];
+            input.read_exact(&mut deleted)?;
+            let id = read_str(input)?;
+            let dimensions = read_u32(input)? as usize;
+            if dimensions > MAX_DIMENSIONS {
+                bail!("vector of {} dimensions", dimensions);
+            }
+            let mut bytes = vec![0u8; dimensions * 4];
+            input.read_exact(&mut bytes)?;
+            let vector = bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            let layers = read_u32(input)? as usize;
+            if layers == 0 || layers > MAX_LEVEL + 1 {
+                bail!("node in {} layers", layers);
+            }
+            let mut links = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let length = read_u32(input)? as usize;
+                let mut layer = Vec::with_capacity(length.min(2 * M));
+                for _ in 0..length {
+                    layer.push(read_u32(input)?);
+                }
+                links.push(layer);
+            }
+            if deleted[0] == 0 {
+                ids.insert(id.clone(), node as u32);
+            }
+            nodes.push(Node {
+                id,
+                vector,
+                links,
+                deleted: deleted[0] != 0,
+            });
+        }
+
+        // A link to a node that isn't on its layer would panic a search
+        for node in &nodes {
+            for (layer, links) in node.links.iter().enumerate() {
+                if links
+                    .iter()
+                    .any(|&link| nodes.get(link as usize).map_or(true, |n| n.links.len() <= layer))
+                {
+                    bail!("broken link from {}", node.id);
+                }
+            }
+        }
+        let entry = (entry != u32::MAX).then_some(entry);
+        if entry.is_some_and(|entry| entry as usize >= nodes.len()) || (entry.is_none() && !nodes.is_empty()) {
+            bail!("no entry point");
+        }
+        Ok(Self {
+            path,
+            model: Some(model).filter(|model| !model.is_empty()),
+            nodes,
+            ids,
+            entry,
+            dirty: false,
+        })
+    }
+}
+
+fn max_links(layer: usize) -> usize {
+    if layer == 0 {
+        2 * M
+    } else {
+        M
+    }
+}
+
+// Each layer up has 1/M of the nodes of the one below
+fn random_level() -> usize {
+    let uniform: f64 = rand::random::<f64>().max(f64::MIN_POSITIVE);
+    let level = (-uniform.ln() / (M as f64).ln()).floor() as usize;
+    level.min(MAX_LEVEL)
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+fn write_u32(out: &mut impl Write, value: u32) -> Result<()> {
+    out.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_str(out: &mut impl Write, value: &str) -> Result<()> {
+    write_u32(out, value.len() as u32)?;
+    out.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_u32(input: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_str(input: &mut impl Read) -> Result<String> {
+    let length = read_u32(input)? as usize;
+    let mut bytes = Vec::new();
+    input.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        bail!("file ends early");
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn unit(vector: Vec<f32>) -> Vec<f32> {
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        vector.into_iter().map(|x| x / norm).collect()
+    }
+
+    fn random_vectors(count: usize, dimensions: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| unit((0..dimensions).map(|_| rng.gen_range(-1.0..1.0)).collect()))
+            .collect()
+    }
+
+    // Points on the unit circle, one per degree given
+    fn circle(degrees: &[u32]) -> VectorIndex {
+        let mut index = VectorIndex::empty(PathBuf::from("unused"));
+        for degree in degrees {
+            let angle = (*degree as f32).to_radians();
+            index.insert(&format!("d{}", degree), vec![angle.cos(), angle.sin()]);
+        }
+        index
+    }
+
+    fn ids(hits: &[(String, f32)]) -> Vec<&str> {
+        hits.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    #[test]
+    fn search_returns_the_nearest_in_order() {
+        let index = circle(&[0, 10, 45, 90, 180, 270]);
+        let query = [(12f32).to_radians().cos(), (12f32).to_radians().sin()];
+        let hits = index.search(&query, 3, 10);
+        assert_eq!(ids(&hits), ["d10", "d0", "d45"]);
+        assert!((hits[0].1 - (2f32).to_radians().cos()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn search_skips_removed_and_replaced_vectors() {
+        let mut index = circle(&[0, 10, 45, 90]);
+        assert!(index.remove("d10"));
+        assert!(!index.remove("d10"));
+        index.insert("d0", vec![-1.0, 0.0]);
+        let hits = index.search(&[1.0, 0.0], 4, 10);
+        assert_eq!(ids(&hits), ["d45", "d90", "d0"]);
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn search_recalls_most_true_neighbours() {
+        let vectors = random_vectors(1000, 16, 1);
+        let mut index = VectorIndex::empty(PathBuf::from("unused"));
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector.clone());
+        }
+
+        let mut found = 0;
+        let queries = random_vectors(20, 16, 2);
+        for query in &queries {
+            let mut exact: Vec<(usize, f32)> =
+                vectors.iter().enumerate().map(|(i, vector)| (i, distance(query, vector))).collect();
+            exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let exact: HashSet<String> = exact.iter().take(10).map(|(i, _)| i.to_string()).collect();
+            found += index.search(query, 10, 50).iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+        let recall = found as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.9, "recall {}", recall);
+    }
+
+    #[test]
+    fn save_and_load_keep_the_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.hnsw");
+        let vectors = random_vectors(200, 8, 3);
+        let mut index = VectorIndex::empty(path.clone());
+        index.set_model("test-model");
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector.clone());
+        }
+        index.remove("7");
+        index.save().unwrap();
+
+        let loaded = VectorIndex::load(path);
+        assert_eq!(loaded.model(), Some("test-model"));
+        assert_eq!(loaded.len(), 199);
+        assert!(!loaded.contains("7"));
+        for query in random_vectors(5, 8, 4) {
+            assert_eq!(loaded.search(&query, 5, 20), index.search(&query, 5, 20));
+        }
+    }
+
+    #[test]
+    fn rebuild_drops_deleted_nodes() {
+        let vectors = random_vectors(MIN_REBUILD * 2, 4, 5);
+        let mut index = VectorIndex::empty(PathBuf::from("unused"));
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector.clone());
+        }
+        for i in 0..=MIN_REBUILD {
+            index.remove(&i.to_string());
+        }
+        assert_eq!(index.nodes.len(), MIN_REBUILD - 1);
+        assert_eq!(index.len(), MIN_REBUILD - 1);
+        let (id, similarity) = &index.search(&vectors[MIN_REBUILD + 1], 1, 10)[0];
+        assert_eq!(id, &(MIN_REBUILD + 1).to_string());
+        assert!(*similarity > 0.999);
+    }
+
+    #[test]
+    fn load_ignores_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.hnsw");
+        assert!(VectorIndex::load(path.clone()).is_empty());
+
+        let mut index = circle(&[0, 90, 180]);
+        index.path = path.clone();
+        index.save().unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        assert_eq!(VectorIndex::load(path.clone()).len(), 3);
+
+        let mut broken = saved.clone();
+        broken[0] = b'X';
+        let truncated = saved[..saved.len() - 2].to_vec();
+        // Point the last link of the last node past the end of the graph
+        let mut dangling = saved.clone();
+        let end = dangling.len();
+        dangling[end - 4..].copy_from_slice(&99u32.to_le_bytes());
+        // An entry point that isn't a node
+        let mut no_entry = saved;
+        let entry = MAGIC.len() + 4;
+        no_entry[entry..entry + 4].copy_from_slice(&99u32.to_le_bytes());
+
+        for contents in [broken, truncated, dangling, no_entry] {
+            std::fs::write(&path, contents).unwrap();
+            let loaded = VectorIndex::load(path.clone());
+            assert!(loaded.is_empty());
+            assert_eq!(loaded.model(), None);
+        }
+    }
+}
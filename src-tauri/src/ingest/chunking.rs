@@ -0,0 +1,187 @@
+use std::sync::RwLock;
+
+use super::sections::{breadcrumb_title, heading, split_sections};
+use crate::search::SearchableDocument;
+use crate::settings::{ChunkStrategy, ChunkingSettings};
+use crate::validation::validate_document;
+
+// How pages are split into documents before they're indexed, by source,
+// from the settings. Replaced in place when the settings change, so running
+// crawls pick it up; pages already indexed keep their chunks until they're
+// indexed again.
+pub struct ChunkingRules {
+    settings: RwLock<ChunkingSettings>,
+}
+
+impl ChunkingRules {
+    pub fn new(settings: ChunkingSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn replace(&self, settings: ChunkingSettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    // The page, then the chunks split out of it with the page as their
+    // parent_id, the way the page's source is set to be split. Pages that
+    // are already a chunk of another come back as they are.
+    pub fn split(&self, page: SearchableDocument) -> Vec<SearchableDocument> {
+        let strategy = {
+            let settings = self.settings.read().unwrap();
+            settings.sources.get(&page.source).unwrap_or(&settings.strategy).clone()
+        };
+        if page.parent_id.is_some() {
+            return vec![page];
+        }
+        match strategy {
+            ChunkStrategy::Headings => split_sections(page),
+            ChunkStrategy::Tokens { tokens, overlap } => {
+                split_lines(page, |lines| token_windows(lines, tokens, overlap))
+            }
+            ChunkStrategy::CodeBlocks { max_chars } => split_lines(page, |lines| block_runs(lines, max_chars)),
+            ChunkStrategy::Whole => vec![page],
+        }
+    }
+}
+
+// Ranges of lines with about `tokens` words each, counting words as tokens,
+// each starting `overlap` words before the one before it ended. A line is
+// never cut, so code and tables stay readable.
+fn token_windows(lines: &[&str], tokens: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let words: Vec<usize> = lines.iter().map(|line| line.split_whitespace().count()).collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut count = 0;
+        while end < lines.len() && (end == start || count + words[end] <= tokens) {
+            count += words[end];
+            end += 1;
+        }
+        chunks.push((start, end));
+        if end == lines.len() {
+            break;
+        }
+        let mut next = end;
+        let mut back = 0;
+        while next > start + 1 && back + words[next - 1] <= overlap {
+            next -= 1;
+            back += words[next];
+        }
+        start = next;
+    }
+    chunks
+}
+
+// Ranges of lines holding whole paragraphs, up to `max_chars` each. A fenced
+// code block counts as one paragraph, blank lines and all, so it's never
+// split from itself; one longer than `max_chars` is a chunk of its own.
+fn block_runs(lines: &[&str], max_chars: usize) -> Vec<(usize, usize)> {
+    let mut blocks: Vec<(usize, usize, usize)> = Vec::new();
+    let mut in_fence = false;
+    let mut start = None;
+    let mut chars = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && line.trim().is_empty() {
+            if let Some(start) = start.take() {
+                blocks.push((start, i, chars));
+            }
+            chars = 0;
+            continue;
+        }
+        start.get_or_insert(i);
+        chars += line.len() + 1;
+    }
+    if let Some(start) = start {
+        blocks.push((start, lines.len(), chars));
+    }
+
+    let mut chunks: Vec<(usize, usize)> = Vec::new();
+    let mut size = 0;
+    for (start, end, chars) in blocks {
+        match chunks.last_mut() {
+            Some(chunk) if size + chars <= max_chars => {
+                chunk.1 = end;
+                size += chars;
+            }
+            _ => {
+                chunks.push((start, end));
+                size = chars;
+            }
+        }
+    }
+    chunks
+}
+
+// The page keeps the first chunk and the rest become documents of their
+// own, "<page id>#chunk-<n>", titled with the headings above where they
+// start. Chunks have no anchor of their own, so they link to the page.
+// Pages that fit in one chunk, or with a chunk that wouldn't pass
+// validation, come back as they are.
+fn split_lines(
+    page: SearchableDocument,
+    chunk: impl Fn(&[&str]) -> Vec<(usize, usize)>,
+) -> Vec<SearchableDocument> {
+    let body = page.body.clone();
+    let lines: Vec<&str> = body.lines().collect();
+    let chunks = chunk(&lines);
+    if chunks.len() < 2 {
+        return vec![page];
+    }
+    let (first, first_end) = chunks[0];
+
+    // The headings above each line, taken where a chunk starts
+    let mut starts = chunks.iter().map(|(start, _)| *start).peekable();
+    let mut paths = Vec::with_capacity(chunks.len());
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if let Some((level, text)) = (!in_fence).then(|| heading(line)).flatten() {
+            path.retain(|(above, _)| *above < level);
+            path.push((level, text));
+        }
+        while starts.next_if_eq(&i).is_some() {
+            paths.push(path.iter().map(|(_, text)| text.clone()).collect::<Vec<String>>());
+        }
+    }
+
+    let mut documents = Vec::with_capacity(chunks.len());
+    for (n, ((start, end), heading_path)) in chunks.into_iter().zip(paths).enumerate().skip(1) {
+        let body = lines[start..end].join("\n");
+        let body = body.trim();
+        if body.is_empty() {
+            continue;
+        }
+        documents.push(SearchableDocument {
+            id: format!("{}#chunk-{}", page.id, n + 1),
+            title: breadcrumb_title(&page.title, &heading_path),
+            body: body.to_string(),
+            indexed_at: None,
+            parent_id: Some(page.id.clone()),
+            heading_path,
+            anchor: None,
+            ..page.clone()
+        });
+    }
+    if documents.iter().any(|chunk| validate_document(chunk, "document").is_err()) {
+        return vec![page];
+    }
+    documents.insert(
+        0,
+        SearchableDocument {
+            body: lines[first..first_end].join("\n").trim().to_string(),
+            ..page
+        },
+    );
+    documents
+}
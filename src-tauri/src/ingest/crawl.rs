@@ -0,0 +1,890 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, error, info, warn};
+use url::Url;
+
+use super::failures::{FailureReport, PageFailure};
+use super::inventory::{inventory_pages, inventory_url};
+use super::robots::RobotsPolicy;
+use super::sitemap::sitemap_urls;
+use super::pages::{PageRecord, PageStore};
+use super::{fetch_page_document, ChunkingRules, ExtractionRules, Fetcher, PageDocument};
+use crate::search::{SearchService, SearchableDocument};
+use crate::util::now_millis;
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Crawled pages are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 25;
+
+// Finished crawls kept around for `listCrawls`; older ones are forgotten
+const MAX_FINISHED_CRAWLS: usize = 50;
+
+// Per-page errors kept in a crawl's status; later ones are only counted
+const MAX_REPORTED_ERRORS: usize = 20;
+
+// Links to these are never followed, they can't hold a readable page
+const SKIPPED_EXTENSIONS: &[&str] = &[
+    "7z", "avi", "css", "dmg", "exe", "gif", "gz", "ico", "jpeg", "jpg", "js", "mov", "mp3", "mp4", "pdf", "png",
+    "svg", "tar", "tgz", "wasm", "webm", "webp", "woff", "woff2", "xml", "zip",
+];
+
+// listCrawls takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListCrawlsParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlOptions {
+    /// Page the crawl starts from
+    pub seed_url: String,
+    /// Source every crawled page is indexed under
+    pub source: String,
+    /// Version recorded on every crawled page
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Only follow links to the seed's host (default true)
+    #[serde(default = "default_same_host")]
+    pub same_host: bool,
+    /// Only follow links whose path starts with this. Defaults to the seed's
+    /// directory, so crawling /docs/intro stays under /docs/.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// How many links away from the seed to go; 0 indexes only the seed (default 3)
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Stop after this many pages (default 200)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    /// Pages downloaded at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Pages downloaded from any one host at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub host_concurrency: usize,
+    /// Crawl pages robots.txt disallows and skip its Crawl-delay, e.g. for
+    /// a source you host yourself (default false)
+    #[serde(default)]
+    pub ignore_robots_txt: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SitemapCrawlOptions {
+    /// sitemap.xml or sitemap index to read page URLs from
+    pub url: String,
+    /// Source every listed page is indexed under
+    pub source: String,
+    /// Version recorded on every listed page
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Only index pages on the sitemap's host (default true)
+    #[serde(default = "default_same_host")]
+    pub same_host: bool,
+    /// Only index pages whose path starts with this (default: all of them)
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Stop after this many pages (default 1000)
+    #[serde(default = "default_max_sitemap_pages")]
+    pub max_pages: usize,
+    /// Pages downloaded at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Pages downloaded from any one host at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub host_concurrency: usize,
+    /// Crawl pages robots.txt disallows and skip its Crawl-delay, e.g. for
+    /// a source you host yourself (default false)
+    #[serde(default)]
+    pub ignore_robots_txt: bool,
+}
+
+impl SitemapCrawlOptions {
+    // A crawl over the listed pages only; links on them aren't followed
+    fn into_crawl(self) -> CrawlOptions {
+        CrawlOptions {
+            seed_url: self.url,
+            source: self.source,
+            version: self.version,
+            same_host: self.same_host,
+            path_prefix: Some(self.path_prefix.unwrap_or_else(|| "/".to_string())),
+            max_depth: 0,
+            max_pages: self.max_pages,
+            concurrency: self.concurrency,
+            host_concurrency: self.host_concurrency,
+            ignore_robots_txt: self.ignore_robots_txt,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryCrawlOptions {
+    /// A Sphinx objects.inv, or the root of the docs it sits in, e.g.
+    /// https://requests.readthedocs.io/en/latest/
+    pub url: String,
+    /// Source every listed page is indexed under
+    pub source: String,
+    /// Version recorded on every page; defaults to the inventory's version
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Only pages documenting objects of these Sphinx domains, e.g. ["py"]
+    /// (default: all of them)
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Only index pages on the inventory's host (default true)
+    #[serde(default = "default_same_host")]
+    pub same_host: bool,
+    /// Only index pages whose path starts with this. Defaults to the
+    /// inventory's directory, so other versions of the docs are left out.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Stop after this many pages (default 1000)
+    #[serde(default = "default_max_sitemap_pages")]
+    pub max_pages: usize,
+    /// Pages downloaded at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Pages downloaded from any one host at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub host_concurrency: usize,
+    /// Crawl pages robots.txt disallows and skip its Crawl-delay, e.g. for
+    /// a source you host yourself (default false)
+    #[serde(default)]
+    pub ignore_robots_txt: bool,
+}
+
+impl InventoryCrawlOptions {
+    // Like a sitemap crawl: the listed pages only
+    fn into_crawl(self, inventory: &Url) -> CrawlOptions {
+        CrawlOptions {
+            seed_url: inventory.to_string(),
+            source: self.source,
+            version: self.version,
+            same_host: self.same_host,
+            path_prefix: self.path_prefix,
+            max_depth: 0,
+            max_pages: self.max_pages,
+            concurrency: self.concurrency,
+            host_concurrency: self.host_concurrency,
+            ignore_robots_txt: self.ignore_robots_txt,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshOptions {
+    /// Source whose downloaded pages should be checked for changes
+    pub source: String,
+    /// Pages checked at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Pages checked on any one host at the same time (default 4)
+    #[serde(default = "default_concurrency")]
+    pub host_concurrency: usize,
+    /// Crawl pages robots.txt disallows and skip its Crawl-delay (default false)
+    #[serde(default)]
+    pub ignore_robots_txt: bool,
+}
+
+impl RefreshOptions {
+    // Every known page is a seed; links aren't followed
+    fn into_crawl(self, pages: usize) -> CrawlOptions {
+        CrawlOptions {
+            seed_url: String::new(),
+            source: self.source,
+            version: None,
+            same_host: false,
+            path_prefix: Some("/".to_string()),
+            max_depth: 0,
+            max_pages: pages,
+            concurrency: self.concurrency,
+            host_concurrency: self.host_concurrency,
+            ignore_robots_txt: self.ignore_robots_txt,
+        }
+    }
+}
+
+fn default_same_host() -> bool {
+    true
+}
+
+fn default_max_depth() -> usize {
+    3
+}
+
+fn default_max_pages() -> usize {
+    200
+}
+
+fn default_max_sitemap_pages() -> usize {
+    1000
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CrawlKind {
+    // Following links from a seed page
+    Links,
+    // The pages a sitemap lists
+    Sitemap,
+    // The pages a Sphinx inventory documents objects on
+    Inventory,
+    // Re-checking the pages already downloaded for a source
+    Refresh,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CrawlState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlStatus {
+    pub id: String,
+    pub kind: CrawlKind,
+    pub source: String,
+    // The seed page, sitemap or inventory; None for refreshes
+    pub seed_url: Option<String>,
+    pub state: CrawlState,
+    pub pages_indexed: usize,
+    // Refreshed pages the server reported unchanged, or whose text was the same
+    pub pages_unchanged: usize,
+    pub pages_failed: usize,
+    // Disallowed by robots.txt
+    pub pages_skipped: usize,
+    // Found and in scope, but not downloaded yet
+    pub pages_queued: usize,
+    // Milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    // The first few failures, as "url: reason"
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CrawlEvent {
+    Progress { crawl: CrawlStatus },
+    Finished { crawl: CrawlStatus },
+}
+
+struct CrawlJob {
+    status: Arc<Mutex<CrawlStatus>>,
+    cancel: watch::Sender<bool>,
+}
+
+// Where a crawl starts
+enum Start {
+    Seed(Url),
+    Sitemap(Url),
+    // An objects.inv and the Sphinx domains to take pages from
+    Inventory(Url, Vec<String>),
+    // Pages downloaded before, with what we know about them
+    Known(Vec<(Url, PageRecord)>),
+}
+
+// Runs crawls in the background and keeps their status for the UI and
+// for clients polling `getCrawlStatus`.
+pub struct CrawlManager {
+    fetcher: Arc<Fetcher>,
+    rules: Arc<ExtractionRules>,
+    chunking: Arc<ChunkingRules>,
+    search_service: Arc<SearchService>,
+    pages: Arc<PageStore>,
+    failures: Arc<FailureReport>,
+    jobs: Mutex<HashMap<String, CrawlJob>>,
+    events: broadcast::Sender<CrawlEvent>,
+}
+
+impl CrawlManager {
+    pub fn new(
+        fetcher: Arc<Fetcher>,
+        rules: Arc<ExtractionRules>,
+        chunking: Arc<ChunkingRules>,
+        search_service: Arc<SearchService>,
+        pages: Arc<PageStore>,
+    ) -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            fetcher,
+            rules,
+            chunking,
+            search_service,
+            pages,
+            failures: Arc::new(FailureReport::default()),
+            jobs: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CrawlEvent> {
+        self.events.subscribe()
+    }
+
+    // Checks the seed and starts crawling in the background. Options are
+    // expected to be validated already.
+    pub fn start(&self, options: CrawlOptions) -> Result<CrawlStatus, McpError> {
+        let seed = parse_seed(&options.seed_url, "seedUrl")?;
+        let scope = CrawlScope::new(&seed, &options);
+        self.launch(options, scope, Start::Seed(seed))
+    }
+
+    // Indexes the pages a sitemap (or sitemap index) lists, as a crawl
+    pub fn start_sitemap(&self, options: SitemapCrawlOptions) -> Result<CrawlStatus, McpError> {
+        let options = options.into_crawl();
+        let sitemap = parse_seed(&options.seed_url, "url")?;
+        let scope = CrawlScope::new(&sitemap, &options);
+        self.launch(options, scope, Start::Sitemap(sitemap))
+    }
+
+    // Indexes the pages of a Sphinx project's objects.inv, as a crawl
+    pub fn start_inventory(&self, options: InventoryCrawlOptions) -> Result<CrawlStatus, McpError> {
+        let inventory = inventory_url(&parse_seed(&options.url, "url")?);
+        let domains = options.domains.clone();
+        let options = options.into_crawl(&inventory);
+        let scope = CrawlScope::new(&inventory, &options);
+        self.launch(options, scope, Start::Inventory(inventory, domains))
+    }
+
+    // Downloads the source's pages again where they changed. Records of
+    // pages no longer in the index are dropped first.
+    pub async fn start_refresh(&self, options: RefreshOptions) -> Result<CrawlStatus, McpError> {
+        let records = self.pages.for_source(&options.source);
+        let ids: Vec<String> = records.iter().map(|(id, _)| id.clone()).collect();
+        let indexed = self
+            .search_service
+            .blocking(move |service| {
+                ids.iter()
+                    .map(|id| service.get_document(id).map(|document| document.is_some()))
+                    .collect::<anyhow::Result<Vec<bool>>>()
+            })
+            .await
+            .map_err(|e| McpError::index("Failed to read document", e))?;
+        let mut known = Vec::new();
+        let mut gone = Vec::new();
+        for ((id, record), indexed) in records.into_iter().zip(indexed) {
+            match Url::parse(&id) {
+                Ok(url) if indexed => known.push((url, record)),
+                _ => gone.push(id),
+            }
+        }
+        if let Err(e) = self.pages.forget(&gone) {
+            error!("Failed to save page records: {}", e);
+        }
+        if known.is_empty() {
+            return Err(McpError::not_found(format!("Downloaded pages of source {}", options.source)));
+        }
+
+        let options = options.into_crawl(known.len());
+        let scope = CrawlScope::everything();
+        self.launch(options, scope, Start::Known(known))
+    }
+
+    fn launch(&self, options: CrawlOptions, scope: CrawlScope, start: Start) -> Result<CrawlStatus, McpError> {
+        let (kind, seed_url, queued) = match &start {
+            Start::Seed(url) => (CrawlKind::Links, Some(url.to_string()), 1),
+            Start::Sitemap(url) => (CrawlKind::Sitemap, Some(url.to_string()), 0),
+            Start::Inventory(url, _) => (CrawlKind::Inventory, Some(url.to_string()), 0),
+            Start::Known(pages) => (CrawlKind::Refresh, None, pages.len()),
+        };
+        let status = CrawlStatus {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            kind,
+            source: options.source.clone(),
+            seed_url,
+            state: CrawlState::Running,
+            pages_indexed: 0,
+            pages_unchanged: 0,
+            pages_failed: 0,
+            pages_skipped: 0,
+            pages_queued: queued,
+            started_at: now_millis(),
+            finished_at: None,
+            errors: Vec::new(),
+        };
+        let shared = Arc::new(Mutex::new(status.clone()));
+        let (cancel, cancelled) = watch::channel(false);
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            prune_finished(&mut jobs);
+            jobs.insert(
+                status.id.clone(),
+                CrawlJob {
+                    status: shared.clone(),
+                    cancel,
+                },
+            );
+        }
+
+        info!("Crawl {} ({:?}) started for source {}", status.id, kind, options.source);
+        let robots = (!options.ignore_robots_txt).then(|| Arc::new(RobotsPolicy::new(self.fetcher.clone())));
+        let crawl = Crawl {
+            options,
+            scope,
+            robots,
+            fetcher: self.fetcher.clone(),
+            rules: self.rules.clone(),
+            chunking: self.chunking.clone(),
+            search_service: self.search_service.clone(),
+            pages: self.pages.clone(),
+            failures: self.failures.clone(),
+            status: shared,
+            events: self.events.clone(),
+        };
+        tokio::spawn(crawl.run(start, cancelled));
+        Ok(status)
+    }
+
+    // Pages already downloaded are still indexed; nothing new is fetched
+    pub fn cancel(&self, id: &str) -> Result<CrawlStatus, McpError> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(id).ok_or_else(|| McpError::not_found(format!("Crawl {}", id)))?;
+        let _ = job.cancel.send(true);
+        let status = job.status.lock().unwrap().clone();
+        Ok(status)
+    }
+
+    pub fn status(&self, id: &str) -> Result<CrawlStatus, McpError> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(id).ok_or_else(|| McpError::not_found(format!("Crawl {}", id)))?;
+        let status = job.status.lock().unwrap().clone();
+        Ok(status)
+    }
+
+    // Newest first
+    pub fn list(&self) -> Vec<CrawlStatus> {
+        let mut crawls: Vec<CrawlStatus> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|job| job.status.lock().unwrap().clone())
+            .collect();
+        crawls.sort_by_key(|crawl| std::cmp::Reverse(crawl.started_at));
+        crawls
+    }
+
+    // Pages of the source that crawls failed on and haven't got since
+    pub fn failures(&self, source: &str) -> Vec<PageFailure> {
+        self.failures.for_source(source)
+    }
+}
+
+fn parse_seed(url: &str, field: &str) -> Result<Url, McpError> {
+    Fetcher::parse_url(url).map_err(|_| McpError::validation(Some(field), format!("Invalid URL {}", url)))
+}
+
+fn prune_finished(jobs: &mut HashMap<String, CrawlJob>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .iter()
+        .filter_map(|(id, job)| job.status.lock().unwrap().finished_at.map(|at| (at, id.clone())))
+        .collect();
+    if finished.len() < MAX_FINISHED_CRAWLS {
+        return;
+    }
+    finished.sort();
+    let excess = finished.len() + 1 - MAX_FINISHED_CRAWLS;
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
+
+// Which links a crawl follows
+struct CrawlScope {
+    host: Option<String>,
+    path_prefix: String,
+}
+
+impl CrawlScope {
+    fn new(seed: &Url, options: &CrawlOptions) -> Self {
+        let path_prefix = match &options.path_prefix {
+            Some(prefix) => prefix.clone(),
+            None => {
+                let path = seed.path();
+                path[..path.rfind('/').map_or(0, |i| i + 1)].to_string()
+            }
+        };
+        Self {
+            host: options.same_host.then(|| seed.host_str().unwrap_or_default().to_string()),
+            path_prefix,
+        }
+    }
+
+    // For refreshes, whose pages were all in scope when first crawled
+    fn everything() -> Self {
+        Self {
+            host: None,
+            path_prefix: "/".to_string(),
+        }
+    }
+
+    // Resolves `href` against the page it was found on; None if it leaves the scope
+    fn follow(&self, page: &Url, href: &str) -> Option<Url> {
+        let mut url = page.join(href).ok()?;
+        url.set_fragment(None);
+        self.allows(&url).then_some(url)
+    }
+
+    fn allows(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        if let Some(host) = &self.host {
+            if url.host_str() != Some(host.as_str()) {
+                return false;
+            }
+        }
+        if !url.path().starts_with(&self.path_prefix) {
+            return false;
+        }
+        let extension = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_ascii_lowercase());
+        !extension.is_some_and(|extension| SKIPPED_EXTENSIONS.contains(&extension.as_str()))
+    }
+}
+
+// A page to download, and what we know about it from an earlier download
+struct Target {
+    url: Url,
+    depth: usize,
+    known: Option<PageRecord>,
+}
+
+enum Outcome {
+    Fetched(Box<PageDocument>),
+    Unchanged,
+    Disallowed,
+    Failed(McpError),
+}
+
+struct Crawl {
+    options: CrawlOptions,
+    scope: CrawlScope,
+    // None when the crawl ignores robots.txt
+    robots: Option<Arc<RobotsPolicy>>,
+    fetcher: Arc<Fetcher>,
+    rules: Arc<ExtractionRules>,
+    chunking: Arc<ChunkingRules>,
+    search_service: Arc<SearchService>,
+    pages: Arc<PageStore>,
+    failures: Arc<FailureReport>,
+    status: Arc<Mutex<CrawlStatus>>,
+    events: broadcast::Sender<CrawlEvent>,
+}
+
+impl Crawl {
+    // Breadth first from the seeds, at most `concurrency` downloads at a
+    // time and `host_concurrency` of them from the same host
+    async fn run(mut self, start: Start, mut cancelled: watch::Receiver<bool>) {
+        let targets: Vec<Target> = match start {
+            Start::Seed(url) => vec![Target { url, depth: 0, known: None }],
+            Start::Sitemap(url) => match self.sitemap_pages(&url, &mut cancelled).await {
+                Some(pages) => pages.into_iter().map(|url| Target { url, depth: 0, known: None }).collect(),
+                None => return,
+            },
+            Start::Inventory(url, domains) => match self.inventory_pages(&url, &domains, &mut cancelled).await {
+                Some(pages) => pages.into_iter().map(|url| Target { url, depth: 0, known: None }).collect(),
+                None => return,
+            },
+            Start::Known(pages) => pages
+                .into_iter()
+                .map(|(url, record)| Target {
+                    url,
+                    depth: 0,
+                    known: Some(record),
+                })
+                .collect(),
+        };
+        let mut seen: HashSet<String> = targets.iter().map(|target| target.url.to_string()).collect();
+        let mut frontier: VecDeque<Target> = targets.into();
+        let mut in_flight = FuturesUnordered::new();
+        let mut per_host: HashMap<String, usize> = HashMap::new();
+        let mut started = 0usize;
+        let mut batch = Vec::new();
+        let mut outcome = CrawlState::Completed;
+
+        loop {
+            while in_flight.len() < self.options.concurrency && started < self.options.max_pages {
+                // The first queued page whose host has a free slot
+                let host_free = |target: &Target| {
+                    per_host.get(host_of(&target.url)).map_or(true, |active| *active < self.options.host_concurrency)
+                };
+                let Some(target) = frontier.iter().position(host_free).and_then(|at| frontier.remove(at)) else {
+                    break;
+                };
+                *per_host.entry(host_of(&target.url).to_string()).or_default() += 1;
+                started += 1;
+                in_flight.push(self.visit(target));
+            }
+
+            let (target, result) = tokio::select! {
+                next = in_flight.next() => match next {
+                    Some(visited) => visited,
+                    None => break,
+                },
+                _ = cancelled.wait_for(|cancelled| *cancelled) => {
+                    outcome = CrawlState::Cancelled;
+                    break;
+                }
+            };
+
+            if let Some(active) = per_host.get_mut(host_of(&target.url)) {
+                *active -= 1;
+            }
+            match result {
+                Outcome::Fetched(page) => match self.accept(*page, target.depth, &mut seen, &mut frontier) {
+                    Ok(page) => batch.push(page),
+                    Err(e) => self.failed(&target.url, &e),
+                },
+                Outcome::Unchanged => {
+                    self.failures.clear(&self.options.source, [target.url.as_str()]);
+                    self.update(|status| status.pages_unchanged += 1);
+                }
+                Outcome::Disallowed => {
+                    debug!("Crawl skipped {}, disallowed by robots.txt", target.url);
+                    self.update(|status| status.pages_skipped += 1);
+                }
+                Outcome::Failed(e) => self.failed(&target.url, &e),
+            }
+            if batch.len() >= INDEX_BATCH_SIZE && !self.index(std::mem::take(&mut batch)).await {
+                outcome = CrawlState::Failed;
+                break;
+            }
+
+            let queued = in_flight.len() + frontier.len().min(self.options.max_pages - started);
+            self.update(|status| status.pages_queued = queued);
+        }
+
+        if outcome != CrawlState::Failed && !batch.is_empty() && !self.index(batch).await {
+            outcome = CrawlState::Failed;
+        }
+        let status = self.status.lock().unwrap().clone();
+        if outcome == CrawlState::Completed && status.pages_indexed == 0 && status.pages_unchanged == 0 {
+            outcome = CrawlState::Failed;
+        }
+        self.finish(outcome);
+    }
+
+    // Downloads one page unless robots.txt says no
+    fn visit(&self, target: Target) -> impl std::future::Future<Output = (Target, Outcome)> {
+        let fetcher = self.fetcher.clone();
+        let rules = self.rules.clone();
+        let robots = self.robots.clone();
+        let source = self.options.source.clone();
+        // Refreshed pages keep the version they were indexed with
+        let version = match &target.known {
+            Some(known) => known.version.clone(),
+            None => self.options.version.clone(),
+        };
+        async move {
+            if let Some(robots) = robots {
+                if !robots.admit(&target.url).await {
+                    return (target, Outcome::Disallowed);
+                }
+            }
+            let known = target.known.as_ref();
+            let fetched = fetch_page_document(&fetcher, &rules, &target.url, Some(source), version, known).await;
+            let outcome = match fetched {
+                Ok(Some(page)) => Outcome::Fetched(Box::new(page)),
+                Ok(None) => Outcome::Unchanged,
+                Err(e) => Outcome::Failed(e),
+            };
+            (target, outcome)
+        }
+    }
+
+    // The in-scope pages a sitemap lists. None if the crawl ended while
+    // reading it, because the sitemap was unusable or the crawl was cancelled.
+    async fn sitemap_pages(&self, sitemap_url: &Url, cancelled: &mut watch::Receiver<bool>) -> Option<Vec<Url>> {
+        let listed = tokio::select! {
+            listed = sitemap_urls(&self.fetcher, sitemap_url, self.options.max_pages) => listed,
+            _ = cancelled.wait_for(|cancelled| *cancelled) => {
+                self.finish(CrawlState::Cancelled);
+                return None;
+            }
+        };
+        match listed {
+            Ok(pages) => {
+                let pages: Vec<Url> = pages.into_iter().filter(|url| self.scope.allows(url)).collect();
+                let queued = pages.len();
+                self.update(|status| status.pages_queued = queued);
+                Some(pages)
+            }
+            Err(e) => {
+                self.failed(sitemap_url, &e);
+                self.finish(CrawlState::Failed);
+                None
+            }
+        }
+    }
+
+    // Like `sitemap_pages`, for an inventory. Pages get the inventory's
+    // version unless the crawl names one.
+    async fn inventory_pages(
+        &mut self,
+        inventory_url: &Url,
+        domains: &[String],
+        cancelled: &mut watch::Receiver<bool>,
+    ) -> Option<Vec<Url>> {
+        let listed = tokio::select! {
+            listed = inventory_pages(&self.fetcher, inventory_url, domains, self.options.max_pages) => listed,
+            _ = cancelled.wait_for(|cancelled| *cancelled) => {
+                self.finish(CrawlState::Cancelled);
+                return None;
+            }
+        };
+        match listed {
+            Ok(inventory) => {
+                if self.options.version.is_none() {
+                    self.options.version = inventory.version;
+                }
+                let pages: Vec<Url> = inventory.pages.into_iter().filter(|url| self.scope.allows(url)).collect();
+                let queued = pages.len();
+                self.update(|status| status.pages_queued = queued);
+                Some(pages)
+            }
+            Err(e) => {
+                self.failed(inventory_url, &e);
+                self.finish(CrawlState::Failed);
+                None
+            }
+        }
+    }
+
+    fn finish(&self, outcome: CrawlState) {
+        let crawl = {
+            let mut status = self.status.lock().unwrap();
+            status.state = outcome;
+            status.pages_queued = 0;
+            status.finished_at = Some(now_millis());
+            status.clone()
+        };
+        info!(
+            "Crawl {} finished ({:?}): {} pages indexed, {} unchanged, {} failed",
+            crawl.id, crawl.state, crawl.pages_indexed, crawl.pages_unchanged, crawl.pages_failed
+        );
+        let _ = self.events.send(CrawlEvent::Finished { crawl });
+    }
+
+    // Queues the page's in-scope links and hands the page back for indexing
+    fn accept(
+        &self,
+        page: PageDocument,
+        depth: usize,
+        seen: &mut HashSet<String>,
+        frontier: &mut VecDeque<Target>,
+    ) -> Result<PageDocument, McpError> {
+        // Redirects can land on a page that is also linked to directly
+        seen.insert(page.url.to_string());
+        if depth < self.options.max_depth {
+            for href in &page.links {
+                if let Some(url) = self.scope.follow(&page.url, href) {
+                    if seen.insert(url.to_string()) {
+                        frontier.push_back(Target {
+                            url,
+                            depth: depth + 1,
+                            known: None,
+                        });
+                    }
+                }
+            }
+        }
+        validate_document(&page.document, "document")?;
+        Ok(page)
+    }
+
+    // False if the batch couldn't be written, which ends the crawl
+    async fn index(&self, pages: Vec<PageDocument>) -> bool {
+        let count = pages.len();
+        let ids: Vec<String> = pages.iter().map(|page| page.document.id.clone()).collect();
+        let (documents, records): (Vec<SearchableDocument>, Vec<(String, PageRecord)>) = pages
+            .into_iter()
+            .map(|page| {
+                let id = page.document.id.clone();
+                (page.document, (id, page.record))
+            })
+            .unzip();
+        let documents: Vec<SearchableDocument> =
+            documents.into_iter().flat_map(|page| self.chunking.split(page)).collect();
+        let search_service = self.search_service.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            search_service.add_documents(documents, |_, _| {})
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => {
+                if let Err(e) = self.pages.remember(records) {
+                    error!("Failed to save page records: {}", e);
+                }
+                self.failures.clear(&self.options.source, ids.iter().map(String::as_str));
+                self.update(|status| status.pages_indexed += count);
+                true
+            }
+            Ok(Err(e)) => {
+                self.batch_failed(&ids, McpError::index("Failed to add crawled pages", e));
+                false
+            }
+            Err(e) => {
+                self.batch_failed(&ids, McpError::internal(format!("Indexing task failed: {}", e)));
+                false
+            }
+        }
+    }
+
+    fn batch_failed(&self, ids: &[String], error: McpError) {
+        warn!("Crawl could not index {} pages: {}", ids.len(), error);
+        let crawl_id = self.status.lock().unwrap().id.clone();
+        for id in ids {
+            self.failures.record(&self.options.source, id, &crawl_id, error.to_string());
+        }
+        self.update(|status| {
+            status.pages_failed += ids.len();
+            status.errors.push(error.to_string());
+        });
+    }
+
+    fn failed(&self, url: &Url, error: &McpError) {
+        warn!("Crawl failed on {}: {}", url, error);
+        let message = format!("{}: {}", url, error);
+        let crawl_id = self.status.lock().unwrap().id.clone();
+        self.failures.record(&self.options.source, url.as_str(), &crawl_id, error.to_string());
+        self.update(|status| {
+            status.pages_failed += 1;
+            if status.errors.len() < MAX_REPORTED_ERRORS {
+                status.errors.push(message);
+            }
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut CrawlStatus)) {
+        let crawl = {
+            let mut status = self.status.lock().unwrap();
+            change(&mut status);
+            status.clone()
+        };
+        let _ = self.events.send(CrawlEvent::Progress { crawl });
+    }
+}
+
+fn host_of(url: &Url) -> &str {
+    url.host_str().unwrap_or_default()
+}
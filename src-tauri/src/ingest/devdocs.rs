@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::info;
+use url::Url;
+
+use super::docset::{page_documents, Entry};
+use super::{html, Fetcher};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Entries are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 500;
+
+// The catalogue of documentation sets, and where their files are served
+const DEVDOCS_CATALOGUE: &str = "https://devdocs.io/docs.json";
+const DEVDOCS_DOCUMENTS: &str = "https://documents.devdocs.io";
+// Ids link to the entry on devdocs.io
+const DEVDOCS_APP: &str = "https://devdocs.io";
+
+// The largest packs (e.g. for Node.js or PHP) are a few tens of MB
+const MAX_PACK_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DevdocsOptions {
+    /// A documentation set by its DevDocs slug (e.g. "python~3.12", "react")
+    /// or name (e.g. "Python")
+    pub doc: String,
+    /// Picks the release of `doc` when it's given by name, e.g. "3.12";
+    /// the newest one otherwise. Also the version documents are filed under.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Source to file the entries under; defaults to the set's name
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DevdocsResponse {
+    pub source: String,
+    /// The pack that was downloaded, e.g. "python~3.12"
+    pub slug: String,
+    pub version: Option<String>,
+    pub indexed: usize,
+    /// Pages or entries that couldn't be indexed, as "where: reason"
+    pub errors: Vec<String>,
+}
+
+// Downloads a DevDocs pack and indexes it one document per entry of its
+// index, the way docsets are: an entry gets the part of its page from its
+// anchor to the next entry. Pages without entries get a document each.
+// `on_progress` gets (pages read, total).
+pub async fn index_devdocs(
+    fetcher: &Fetcher,
+    search_service: Arc<SearchService>,
+    options: DevdocsOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<DevdocsResponse, McpError> {
+    let catalogue_url = Url::parse(DEVDOCS_CATALOGUE).map_err(|e| McpError::internal(e.to_string()))?;
+    let catalogue: Value = serde_json::from_slice(&fetcher.fetch_bytes(&catalogue_url).await?)
+        .map_err(|e| McpError::fetch(DEVDOCS_CATALOGUE, format!("not the DevDocs catalogue: {}", e)))?;
+    let doc = find_doc(&catalogue, options.doc.trim(), options.version.as_deref())?;
+    let slug = doc["slug"].as_str().unwrap_or_default().to_string();
+    let name = doc["name"].as_str().unwrap_or(&slug).to_string();
+    let doc_version = ["version", "release"]
+        .iter()
+        .find_map(|key| doc[key].as_str().filter(|version| !version.is_empty()))
+        .map(str::to_string);
+    let version = options.version.clone().or_else(|| doc_version.clone());
+    let source = options.source.clone().unwrap_or(name);
+
+    // The modification time busts caches between releases of a pack
+    let mtime = doc["mtime"].as_u64().unwrap_or_default();
+    let mut files = Vec::new();
+    for file in ["index.json", "db.json"] {
+        let url = Url::parse(&format!("{}/{}/{}?{}", DEVDOCS_DOCUMENTS, slug, file, mtime))
+            .map_err(|e| McpError::internal(e.to_string()))?;
+        files.push(fetcher.fetch_large(&url, MAX_PACK_BYTES).await?);
+    }
+    let db = files.pop().unwrap_or_default();
+    let index = files.pop().unwrap_or_default();
+
+    let pack = slug.clone();
+    let (indexed, errors, source) = tokio::task::spawn_blocking(move || {
+        let index: Value = serde_json::from_slice(&index)
+            .map_err(|e| McpError::fetch(&pack, format!("pack index is corrupt: {}", e)))?;
+        let db: Map<String, Value> = serde_json::from_slice(&db)
+            .map_err(|e| McpError::fetch(&pack, format!("pack pages are corrupt: {}", e)))?;
+        let (indexed, errors) = index_pack(
+            &search_service,
+            &pack,
+            &index,
+            &db,
+            &source,
+            version.as_deref(),
+            on_progress,
+        )?;
+        Ok::<_, McpError>((indexed, errors, source))
+    })
+    .await
+    .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))??;
+    info!("Indexed {} entries of DevDocs pack {} as source {}", indexed, slug, source);
+    Ok(DevdocsResponse {
+        source,
+        slug,
+        version: options.version.or(doc_version),
+        indexed,
+        errors,
+    })
+}
+
+// The catalogue entry for `doc`: the one with that slug, else the newest
+// release of the set with that name (or slug before the `~`) matching
+// `version` if given. The catalogue lists releases newest first.
+fn find_doc<'a>(catalogue: &'a Value, doc: &str, version: Option<&str>) -> Result<&'a Value, McpError> {
+    let docs = catalogue.as_array().map(Vec::as_slice).unwrap_or_default();
+    let by_slug = docs.iter().find(|entry| entry["slug"].as_str().is_some_and(|slug| slug.eq_ignore_ascii_case(doc)));
+    if let Some(found) = by_slug {
+        return Ok(found);
+    }
+    let wanted = doc.to_lowercase();
+    docs.iter()
+        .filter(|entry| {
+            let slug = entry["slug"].as_str().unwrap_or_default();
+            let base = slug.split('~').next().unwrap_or_default();
+            entry["name"].as_str().is_some_and(|name| name.to_lowercase() == wanted) || base == wanted
+        })
+        .find(|entry| {
+            version.map_or(true, |version| {
+                ["version", "release"].iter().any(|key| entry[key].as_str() == Some(version))
+                    || entry["slug"].as_str().is_some_and(|slug| slug.ends_with(&format!("~{}", version)))
+            })
+        })
+        .ok_or_else(|| {
+            let what = match version {
+                Some(version) => format!("{} {}", doc, version),
+                None => doc.to_string(),
+            };
+            McpError::not_found(format!("DevDocs documentation {}", what))
+        })
+}
+
+fn index_pack(
+    search_service: &SearchService,
+    slug: &str,
+    index: &Value,
+    db: &Map<String, Value>,
+    source: &str,
+    version: Option<&str>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<(usize, Vec<String>), McpError> {
+    // Entries by page; `path` is the page's key in db.json plus an anchor
+    let mut pages: BTreeMap<&str, Vec<Entry>> = BTreeMap::new();
+    for entry in index["entries"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        let (Some(name), Some(path)) = (entry["name"].as_str(), entry["path"].as_str()) else {
+            continue;
+        };
+        let (page, anchor) = match path.split_once('#') {
+            Some((page, anchor)) => (page, Some(anchor.to_string())),
+            None => (path, None),
+        };
+        pages.entry(page).or_default().push(Entry {
+            name: name.to_string(),
+            kind: entry["type"].as_str().unwrap_or("Entry").to_string(),
+            anchor,
+        });
+    }
+
+    let mut errors = Vec::new();
+    let mut documents = Vec::new();
+    let mut indexed = 0;
+    for (done, (page, html)) in db.iter().enumerate() {
+        on_progress(done, db.len());
+        let Some(html) = html.as_str() else {
+            continue;
+        };
+        let entries = pages.remove(page.as_str()).unwrap_or_else(|| {
+            let title = html::extract(html, None).title;
+            vec![Entry {
+                name: if title.is_empty() { page.clone() } else { title },
+                kind: "Page".to_string(),
+                anchor: None,
+            }]
+        });
+        let page_url = format!("{}/{}/{}", DEVDOCS_APP, slug, page);
+        for document in page_documents(&page_url, html, entries, source, version) {
+            match validate_document(&document, "document") {
+                Ok(()) => documents.push(document),
+                Err(e) => errors.push(format!("{}: {}", document.id, e)),
+            }
+        }
+        if documents.len() >= INDEX_BATCH_SIZE {
+            indexed += add(search_service, std::mem::take(&mut documents))?;
+        }
+    }
+    indexed += add(search_service, documents)?;
+    on_progress(db.len(), db.len());
+    errors.extend(pages.keys().map(|page| format!("{}: page missing from the pack", page)));
+    Ok((indexed, errors))
+}
+
+fn add(search_service: &SearchService, documents: Vec<SearchableDocument>) -> Result<usize, McpError> {
+    let count = documents.len();
+    if count > 0 {
+        search_service
+            .add_documents(documents, |_, _| {})
+            .map_err(|e| McpError::index("Failed to add DevDocs entries", e))?;
+    }
+    Ok(count)
+}
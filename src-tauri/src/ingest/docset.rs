@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+use rusqlite::{Connection, OpenFlags};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use url::Url;
+
+use super::{html, markdown};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Entries are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 500;
+
+// An entry's section ends at the next entry's anchor or heading, and after
+// this many blocks at the latest
+const MAX_SECTION_BLOCKS: usize = 40;
+
+// Elements an entry's anchor can sit inside that belong to the entry's own
+// title, e.g. <h2><a name="..."></a>open</h2> or <dt><code id="...">
+const TITLE_ELEMENTS: &[&str] = &[
+    "a", "b", "code", "dt", "em", "h1", "h2", "h3", "h4", "h5", "h6", "span", "strong", "tt",
+];
+
+// Type abbreviations of docsets generated by Apple's docsetutil
+const APPLE_TYPES: &[(&str, &str)] = &[
+    ("cat", "Category"),
+    ("cl", "Class"),
+    ("clconst", "Constant"),
+    ("clm", "Method"),
+    ("data", "Variable"),
+    ("econst", "Constant"),
+    ("func", "Function"),
+    ("instm", "Method"),
+    ("instp", "Property"),
+    ("intf", "Protocol"),
+    ("intfcm", "Method"),
+    ("intfm", "Method"),
+    ("intfp", "Property"),
+    ("macro", "Macro"),
+    ("struct", "Struct"),
+    ("tag", "Type"),
+    ("tdef", "Type"),
+    ("union", "Union"),
+];
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocsetOptions {
+    /// A Dash or Zeal docset folder, e.g. ~/.local/share/Zeal/Zeal/docsets/Python_3.docset
+    pub path: String,
+    /// Source to file the entries under; defaults to the docset's name
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DocsetResponse {
+    pub source: String,
+    pub indexed: usize,
+    /// Pages or entries that couldn't be read or indexed, as "where: reason"
+    pub errors: Vec<String>,
+}
+
+pub(super) struct Entry {
+    pub(super) name: String,
+    // e.g. Class, Function, Guide
+    pub(super) kind: String,
+    // Anchor on the page, if the entry isn't the whole page
+    pub(super) anchor: Option<String>,
+}
+
+// Indexes a docset one document per entry of its search index. An entry
+// that points into a page gets the part of the page from its anchor up to
+// the next entry or heading; one for a whole page gets the page. Blocking;
+// run it off the async runtime. `on_progress` gets (pages read, total).
+pub fn index_docset(
+    search_service: &SearchService,
+    options: &DocsetOptions,
+    on_progress: impl Fn(usize, usize),
+) -> Result<DocsetResponse, McpError> {
+    let path = options.path.trim();
+    let docset = std::fs::canonicalize(path)
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read {}: {}", path, e)))?;
+    let contents = docset.join("Contents");
+    let resources = contents.join("Resources");
+    let documents_dir = resources.join("Documents");
+    if !resources.join("docSet.dsidx").is_file() {
+        return Err(McpError::validation(
+            Some("path"),
+            format!("{} is not a docset: Contents/Resources/docSet.dsidx is missing", docset.display()),
+        ));
+    }
+    if !documents_dir.is_dir() {
+        return Err(McpError::validation(
+            Some("path"),
+            "docsets with compressed documents (tarix.tgz) are not supported; \
+             open it in Dash or Zeal once to unpack it",
+        ));
+    }
+    let source = options
+        .source
+        .clone()
+        .or_else(|| bundle_name(&contents.join("Info.plist")))
+        .unwrap_or_else(|| file_stem(&docset));
+
+    let pages = read_entries(&resources.join("docSet.dsidx"))
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read the docset's search index: {}", e)))?;
+    let root = Url::from_directory_path(&documents_dir)
+        .map_err(|()| McpError::internal(format!("{} has no file URL", documents_dir.display())))?;
+
+    let mut errors = Vec::new();
+    let mut documents = Vec::new();
+    let mut indexed = 0;
+    let total = pages.len();
+    for (done, (page, entries)) in pages.into_iter().enumerate() {
+        on_progress(done, total);
+        let Some(path) = root.join(&page).ok().and_then(|url| url.to_file_path().ok()) else {
+            errors.push(format!("{}: not a path inside the docset", page));
+            continue;
+        };
+        if !path.starts_with(&documents_dir) {
+            errors.push(format!("{}: not a path inside the docset", page));
+            continue;
+        }
+        let html = match std::fs::read(&path) {
+            Ok(html) => String::from_utf8_lossy(&html).into_owned(),
+            Err(e) => {
+                errors.push(format!("{}: {}", page, e));
+                continue;
+            }
+        };
+        let page_url = Url::from_file_path(&path).map(String::from).unwrap_or_else(|()| path.display().to_string());
+        for document in page_documents(&page_url, &html, entries, &source, options.version.as_deref()) {
+            match validate_document(&document, "document") {
+                Ok(()) => documents.push(document),
+                Err(e) => errors.push(format!("{}: {}", document.id, e)),
+            }
+        }
+        if documents.len() >= INDEX_BATCH_SIZE {
+            indexed += documents.len();
+            search_service
+                .add_documents(std::mem::take(&mut documents), |_, _| {})
+                .map_err(|e| McpError::index("Failed to add docset entries", e))?;
+        }
+    }
+    if !documents.is_empty() {
+        indexed += documents.len();
+        search_service
+            .add_documents(documents, |_, _| {})
+            .map_err(|e| McpError::index("Failed to add docset entries", e))?;
+    }
+    on_progress(total, total);
+    info!("Indexed {} entries of docset {} as source {}", indexed, docset.display(), source);
+    Ok(DocsetResponse {
+        source,
+        indexed,
+        errors,
+    })
+}
+
+fn bundle_name(info_plist: &Path) -> Option<String> {
+    let info = plist::Value::from_file(info_plist).ok()?;
+    let info = info.as_dictionary()?;
+    ["CFBundleName", "CFBundleIdentifier"]
+        .iter()
+        .find_map(|key| info.get(key)?.as_string().map(str::trim).filter(|name| !name.is_empty()))
+        .map(str::to_string)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "docset".to_string())
+}
+
+// The entries of the search index by page, pages in path order. Dash's
+// own format is a single searchIndex table; docsets generated by Apple's
+// docsetutil keep a Core Data store instead.
+fn read_entries(index: &Path) -> rusqlite::Result<BTreeMap<String, Vec<Entry>>> {
+    let connection = Connection::open_with_flags(index, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let dash: bool = connection.query_row(
+        "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'searchIndex'",
+        [],
+        |row| row.get(0),
+    )?;
+    let query = if dash {
+        "SELECT name, type, path, NULL FROM searchIndex"
+    } else {
+        "SELECT ZTOKEN.ZTOKENNAME, ZTOKENTYPE.ZTYPENAME, ZFILEPATH.ZPATH, ZTOKENMETAINFORMATION.ZANCHOR
+         FROM ZTOKEN
+         JOIN ZTOKENMETAINFORMATION ON ZTOKEN.ZMETAINFORMATION = ZTOKENMETAINFORMATION.Z_PK
+         JOIN ZFILEPATH ON ZTOKENMETAINFORMATION.ZFILE = ZFILEPATH.Z_PK
+         JOIN ZTOKENTYPE ON ZTOKEN.ZTOKENTYPE = ZTOKENTYPE.Z_PK"
+    };
+
+    let mut statement = connection.prepare(query)?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+    let mut pages: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+    for row in rows {
+        let (name, kind, path, anchor) = row?;
+        // Dash paths can start with `<dash_entry_...>` tags for its own use
+        let path = match path.strip_prefix('<') {
+            Some(_) => path.rsplit_once('>').map_or(path.as_str(), |(_, path)| path),
+            None => path.as_str(),
+        };
+        // Entries that link to the web instead of the bundled documents
+        if path.starts_with("http://") || path.starts_with("https://") {
+            continue;
+        }
+        let (page, fragment) = match path.split_once('#') {
+            Some((page, fragment)) => (page, Some(fragment.to_string())),
+            None => (path, None),
+        };
+        let kind = APPLE_TYPES
+            .iter()
+            .find(|(short, _)| *short == kind)
+            .map_or(kind.clone(), |(_, long)| long.to_string());
+        pages.entry(page.to_string()).or_default().push(Entry {
+            name,
+            kind,
+            anchor: anchor.or(fragment).filter(|anchor| !anchor.is_empty()),
+        });
+    }
+    Ok(pages)
+}
+
+// The documents for one page's entries. Entries for the whole page share a
+// single document, titled with the first of them.
+pub(super) fn page_documents(
+    page_url: &str,
+    html: &str,
+    entries: Vec<Entry>,
+    source: &str,
+    version: Option<&str>,
+) -> Vec<SearchableDocument> {
+    let document = kuchikiki::parse_html().one(html);
+    if let Ok(scripts) = document.select("script, style, noscript, template") {
+        let scripts: Vec<NodeRef> = scripts.map(|element| element.as_node().clone()).collect();
+        for node in scripts {
+            node.detach();
+        }
+    }
+    let targets = anchor_targets(&document);
+    let entry_anchors: HashSet<&str> = entries.iter().filter_map(|entry| entry.anchor.as_deref()).collect();
+
+    let mut seen = HashSet::new();
+    let mut documents = Vec::new();
+    for entry in &entries {
+        let target = entry.anchor.as_deref().and_then(|anchor| Some((anchor, targets.get(anchor)?)));
+        let id = match target {
+            Some((anchor, _)) => format!("{}#{}", page_url, anchor),
+            None => page_url.to_string(),
+        };
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let body = match target {
+            Some((_, target)) => section(target, &entry_anchors),
+            None => html::extract(html, None).markdown,
+        };
+        documents.push(SearchableDocument {
+            url: Some(id.clone()),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: None,
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: target.map(|(anchor, _)| anchor.to_string()),
+            content_hash: None,
+            id,
+            title: entry.name.clone(),
+            body: format!("{}: {}\n\n{}", entry.kind, entry.name, body.trim()),
+            source: source.to_string(),
+            version: version.map(str::to_string),
+        });
+    }
+    documents
+}
+
+// Elements by their `id`, and `<a name>` anchors by name
+fn anchor_targets(document: &NodeRef) -> HashMap<String, NodeRef> {
+    let mut targets = HashMap::new();
+    for element in document.descendants().filter_map(|node| node.into_element_ref()) {
+        let attributes = element.attributes.borrow();
+        let name = if element.name.local.as_ref() == "a" { attributes.get("name") } else { None };
+        for anchor in attributes.get("id").into_iter().chain(name) {
+            targets.entry(anchor.to_string()).or_insert_with(|| element.as_node().clone());
+        }
+    }
+    targets
+}
+
+// The blocks from an entry's anchor up to the next entry's anchor or the
+// next heading. Dash anchors are usually empty <a> tags placed just before
+// the element they mark.
+fn section(target: &NodeRef, entry_anchors: &HashSet<&str>) -> String {
+    let mut start = target.clone();
+    while start.text_contents().trim().is_empty() {
+        let parent = start.parent().filter(|parent| TITLE_ELEMENTS.contains(&tag(parent)));
+        match parent.or_else(|| next_element(&start)) {
+            Some(next) => start = next,
+            None => return String::new(),
+        }
+    }
+    // Climb from inline elements (e.g. an anchor inside a <dt>'s <code>) to
+    // the block holding them
+    while let Some(parent) = start.parent().filter(|parent| TITLE_ELEMENTS.contains(&tag(parent))) {
+        start = parent;
+    }
+    let heading_level = heading_level_of(&start);
+
+    let mut blocks = vec![start.clone()];
+    let mut node = start;
+    while blocks.len() < MAX_SECTION_BLOCKS {
+        let Some(next) = node.next_sibling() else {
+            break;
+        };
+        node = next;
+        if holds_anchor(&node, entry_anchors) {
+            break;
+        }
+        if let Some(level) = heading_level_of(&node) {
+            if heading_level.map_or(true, |start| level <= start) {
+                break;
+            }
+        }
+        // A <dt> starts the next definition in a list of them
+        if tag(&node) == "dt" && tag(&blocks[0]) == "dt" {
+            break;
+        }
+        blocks.push(node.clone());
+    }
+    markdown::render_nodes(&blocks)
+}
+
+// The next element after `node` in document order, skipping its children
+fn next_element(node: &NodeRef) -> Option<NodeRef> {
+    let mut current = node.clone();
+    loop {
+        if let Some(sibling) = current.next_sibling() {
+            if sibling.as_element().is_some() {
+                return Some(sibling);
+            }
+            current = sibling;
+            continue;
+        }
+        current = current.parent()?;
+    }
+}
+
+fn holds_anchor(node: &NodeRef, entry_anchors: &HashSet<&str>) -> bool {
+    node.inclusive_descendants().filter_map(|node| node.into_element_ref()).any(|element| {
+        let attributes = element.attributes.borrow();
+        ["id", "name"].iter().any(|key| attributes.get(*key).is_some_and(|anchor| entry_anchors.contains(anchor)))
+    })
+}
+
+fn heading_level_of(node: &NodeRef) -> Option<u8> {
+    match tag(node).as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+fn tag(node: &NodeRef) -> &str {
+    node.as_element().map_or("", |element| element.name.local.as_ref())
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures_util::stream::{self, StreamExt};
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use super::llms::llms_site;
+use super::sections::section_anchor;
+use super::sitemap::sitemap_urls;
+use super::{directory_url, markdown, CrawlManager, CrawlOptions, CrawlStatus, Fetcher};
+use crate::search::{facet_path, SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Sections are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 200;
+
+// Joins the levels of a section's place on the site
+const BREADCRUMB_SEPARATOR: &str = " » ";
+
+// How deep the crawl of a site of any other kind goes
+const CRAWL_DEPTH: usize = 5;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocsSiteOptions {
+    /// Root of a documentation site, e.g. https://docs.pydantic.dev/latest/.
+    /// Sites with an llms.txt and sites built with Docusaurus or MkDocs are
+    /// read directly; others are crawled.
+    pub url: String,
+    /// Source to file the sections under; defaults to the site's name
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Overrides the version read from the site (mike's versions.json for
+    /// MkDocs, each page's docs version for Docusaurus)
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Pages read at most from the sitemap of a Docusaurus site without a
+    /// local search index, from the links in llms.txt, or by the crawl
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    /// Pages downloaded at the same time when reading page by page
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Read the site's llms-full.txt or llms.txt, when it has one, rather
+    /// than its pages (default true)
+    #[serde(default = "default_llms_txt")]
+    pub llms_txt: bool,
+}
+
+fn default_max_pages() -> usize {
+    1000
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_llms_txt() -> bool {
+    true
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DocsSiteResponse {
+    pub source: String,
+    /// What the site was read from: "llms-full.txt", "llms.txt",
+    /// "docusaurus", "mkdocs", or "crawl" for any other site
+    pub generator: String,
+    pub indexed: usize,
+    /// Pages or sections that couldn't be read or indexed, as "where: reason"
+    pub errors: Vec<String>,
+    /// The crawl of a site of any other kind, which runs in the background
+    pub crawl: Option<CrawlStatus>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Generator {
+    Docusaurus,
+    Mkdocs,
+}
+
+impl Generator {
+    fn name(self) -> &'static str {
+        match self {
+            Generator::Docusaurus => "docusaurus",
+            Generator::Mkdocs => "mkdocs",
+        }
+    }
+}
+
+pub(super) struct Section {
+    // The page's URL, with the heading's anchor below the page's top
+    pub(super) id: String,
+    // Sidebar categories, the page's title, then the heading
+    pub(super) breadcrumbs: Vec<String>,
+    pub(super) body: String,
+    pub(super) version: Option<String>,
+}
+
+// Indexes a documentation site one document per section, titled with the
+// section's breadcrumbs. The site's llms-full.txt or llms.txt comes first:
+// it's written for this. Docusaurus and MkDocs sites are read from the
+// search index the generator (or, for Docusaurus, the local search plugin)
+// writes; Docusaurus sites without one are read page by page from their
+// sitemap. Any other site is crawled in the background. `on_progress` gets
+// (sections written, total).
+pub async fn index_docs_site(
+    fetcher: &Fetcher,
+    crawls: &CrawlManager,
+    search_service: Arc<SearchService>,
+    options: DocsSiteOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<DocsSiteResponse, McpError> {
+    let root = directory_url(&Fetcher::parse_url(&options.url)?);
+    let mut errors = Vec::new();
+    let llms = match options.llms_txt {
+        true => llms_site(fetcher, &root, options.max_pages, options.concurrency, &mut errors).await?,
+        false => None,
+    };
+    let (generator, site_name, sections) = match llms {
+        Some(site) => (site.file.to_string(), site.name, site.sections),
+        None => {
+            let front = fetcher.fetch(&root).await?;
+            let (generator, site_name) = generator_of(&front.body);
+            let Some(generator) = generator else {
+                let source = options
+                    .source
+                    .or(site_name)
+                    .unwrap_or_else(|| root.host_str().unwrap_or("web").to_string());
+                let crawl = crawls.start(CrawlOptions {
+                    seed_url: root.to_string(),
+                    source: source.clone(),
+                    version: options.version,
+                    same_host: true,
+                    path_prefix: Some(root.path().to_string()),
+                    max_depth: CRAWL_DEPTH,
+                    max_pages: options.max_pages,
+                    concurrency: options.concurrency,
+                    host_concurrency: options.concurrency,
+                    ignore_robots_txt: false,
+                })?;
+                info!("Crawling {} as source {}: no llms.txt, Docusaurus or MkDocs", root, source);
+                return Ok(DocsSiteResponse {
+                    source,
+                    generator: "crawl".to_string(),
+                    indexed: 0,
+                    errors,
+                    crawl: Some(crawl),
+                });
+            };
+            let sections = match generator {
+                Generator::Mkdocs => mkdocs_sections(fetcher, &root).await?,
+                Generator::Docusaurus => match docusaurus_search_sections(fetcher, &root).await {
+                    Some(sections) => sections,
+                    None => docusaurus_page_sections(fetcher, &root, &options, &mut errors).await?,
+                },
+            };
+            (generator.name().to_string(), site_name, sections)
+        }
+    };
+    let source = options
+        .source
+        .or(site_name)
+        .unwrap_or_else(|| root.host_str().unwrap_or("web").to_string());
+
+    let mut documents = Vec::new();
+    for section in sections {
+        let version = options.version.clone().or(section.version);
+        // Under the source and version, then the section's parent pages
+        let parents = &section.breadcrumbs[..section.breadcrumbs.len().saturating_sub(1)];
+        let facet = facet_path(
+            source
+                .split([':', '/'])
+                .chain(version.as_deref())
+                .chain(parents.iter().map(String::as_str)),
+        );
+        let document = SearchableDocument {
+            url: Some(section.id.clone()),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: Some(facet),
+            parent_id: None,
+            heading_path: section.breadcrumbs.clone(),
+            anchor: section_anchor(&section.id),
+            content_hash: None,
+            id: section.id,
+            title: section.breadcrumbs.join(BREADCRUMB_SEPARATOR),
+            body: section.body,
+            source: source.clone(),
+            version,
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", document.id, e)),
+        }
+    }
+
+    let indexed = documents.len();
+    tokio::task::spawn_blocking(move || {
+        let mut written = 0;
+        for batch in documents.chunks(INDEX_BATCH_SIZE) {
+            search_service.add_documents(batch.to_vec(), |done, _| on_progress(written + done, indexed))?;
+            written += batch.len();
+        }
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+    .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+    .map_err(|e| McpError::index("Failed to add site sections", e))?;
+    info!("Indexed {} sections of {} site {} as source {}", indexed, generator, root, source);
+    Ok(DocsSiteResponse {
+        source,
+        generator,
+        indexed,
+        errors,
+        crawl: None,
+    })
+}
+
+// The generator named by the front page's <meta name="generator">, and the
+// site's name
+fn generator_of(html: &str) -> (Option<Generator>, Option<String>) {
+    let document = kuchikiki::parse_html().one(html);
+    let generator = meta_content(&document, "meta[name=generator]").and_then(|generator| {
+        let generator = generator.to_lowercase();
+        if generator.starts_with("docusaurus") {
+            Some(Generator::Docusaurus)
+        } else if generator.starts_with("mkdocs") {
+            Some(Generator::Mkdocs)
+        } else {
+            None
+        }
+    });
+    (generator, meta_content(&document, "meta[property='og:site_name']"))
+}
+
+fn meta_content(document: &NodeRef, selector: &str) -> Option<String> {
+    let meta = document.select_first(selector).ok()?;
+    let content = meta.attributes.borrow().get("content")?.trim().to_string();
+    (!content.is_empty()).then_some(content)
+}
+
+// MkDocs' search/search_index.json lists every page, then each of its
+// sections with the heading's anchor in `location`. Material for MkDocs
+// keeps only the text before the first heading in a page's entry; plain
+// MkDocs repeats the whole page there, which is cut where the first section
+// starts.
+async fn mkdocs_sections(fetcher: &Fetcher, root: &Url) -> Result<Vec<Section>, McpError> {
+    let index_url = root.join("search/search_index.json").map_err(|e| McpError::fetch(root.as_str(), e))?;
+    let index: Value = serde_json::from_str(&fetcher.fetch(&index_url).await?.body)
+        .map_err(|e| McpError::fetch(index_url.as_str(), format!("not an MkDocs search index: {}", e)))?;
+    let version = mike_version(fetcher, root).await;
+
+    let entries: Vec<(&str, &str, String)> = index["docs"]
+        .as_array()
+        .map(|docs| {
+            docs.iter()
+                .map(|doc| {
+                    let location = doc["location"].as_str().unwrap_or_default();
+                    let title = doc["title"].as_str().unwrap_or_default().trim();
+                    (location, title, html_text(doc["text"].as_str().unwrap_or_default()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let page_titles: HashMap<&str, &str> = entries
+        .iter()
+        .filter(|(location, _, _)| !location.contains('#'))
+        .map(|(location, title, _)| (*location, *title))
+        .collect();
+
+    let mut sections = Vec::new();
+    for (position, (location, title, text)) in entries.iter().enumerate() {
+        let Ok(url) = root.join(location) else {
+            continue;
+        };
+        let (page, anchor) = location.split_once('#').unwrap_or((location, ""));
+        let mut body = text.as_str();
+        let mut breadcrumbs = vec![page_titles.get(page).copied().unwrap_or(title).to_string()];
+        if anchor.is_empty() {
+            let section_prefix = format!("{}#", page);
+            let first_section = entries.get(position + 1).filter(|(next, _, _)| next.starts_with(&section_prefix));
+            if let Some((_, _, next_text)) = first_section {
+                let start: String = next_text.chars().take(80).collect();
+                if let Some(at) = body.find(&start).filter(|_| !start.is_empty()) {
+                    body = &body[..at];
+                }
+            }
+        } else if !title.is_empty() {
+            breadcrumbs.push(title.to_string());
+        }
+        let body = body.trim();
+        if body.is_empty() {
+            continue;
+        }
+        sections.push(Section {
+            id: url.to_string(),
+            breadcrumbs,
+            body: body.to_string(),
+            version: version.clone(),
+        });
+    }
+    Ok(sections)
+}
+
+// mike, which publishes versioned MkDocs sites, keeps a versions.json
+// beside the version folders; the root's last folder names the version or
+// one of its aliases (such as "latest")
+async fn mike_version(fetcher: &Fetcher, root: &Url) -> Option<String> {
+    let folder = root.path_segments()?.rev().find(|segment| !segment.is_empty())?.to_string();
+    let versions_url = root.join("../versions.json").ok()?;
+    let versions: Value = serde_json::from_str(&fetcher.fetch(&versions_url).await.ok()?.body).ok()?;
+    versions.as_array()?.iter().find_map(|entry| {
+        let version = entry["version"].as_str()?;
+        let aliases = entry["aliases"].as_array().cloned().unwrap_or_default();
+        (version == folder || aliases.iter().any(|alias| alias.as_str() == Some(folder.as_str())))
+            .then(|| version.to_string())
+    })
+}
+
+// Docusaurus sites have no search index of their own; the common local
+// search plugin (@easyops-cn/docusaurus-search-local) writes
+// search-index.json with page titles, headings and paragraphs as separate
+// documents. None when the site doesn't have it.
+async fn docusaurus_search_sections(fetcher: &Fetcher, root: &Url) -> Option<Vec<Section>> {
+    let index_url = root.join("search-index.json").ok()?;
+    let index: Value = serde_json::from_str(&fetcher.fetch(&index_url).await.ok()?.body).ok()?;
+    let documents: Vec<&Value> = index
+        .as_array()?
+        .iter()
+        .filter_map(|group| group["documents"].as_array())
+        .flatten()
+        .collect();
+
+    // Pages by id: their URL, title and sidebar breadcrumbs
+    let mut pages: HashMap<u64, (&str, &str, Vec<String>)> = HashMap::new();
+    for document in &documents {
+        if document.get("p").is_none() {
+            let id = document["i"].as_u64();
+            let (Some(id), Some(url), Some(title)) = (id, document["u"].as_str(), document["t"].as_str()) else {
+                continue;
+            };
+            let breadcrumbs = document["b"]
+                .as_array()
+                .map(|crumbs| crumbs.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default();
+            pages.insert(id, (url, title, breadcrumbs));
+        }
+    }
+
+    // Paragraphs (which carry their section's title in `s`) grouped by
+    // page and heading, in index order
+    let mut order = Vec::new();
+    let mut texts: HashMap<(u64, String), (String, Vec<&str>)> = HashMap::new();
+    for document in &documents {
+        let page = document["p"].as_u64();
+        let (Some(page), Some(text), Some(heading)) = (page, document["t"].as_str(), document["s"].as_str()) else {
+            continue;
+        };
+        let anchor = document["h"].as_str().unwrap_or_default().trim_start_matches('#').to_string();
+        let key = (page, anchor);
+        let entry = texts.entry(key.clone()).or_insert_with(|| {
+            order.push(key);
+            (heading.to_string(), Vec::new())
+        });
+        entry.1.push(text);
+    }
+
+    let sections = order
+        .into_iter()
+        .filter_map(|key| {
+            let (url, title, crumbs) = pages.get(&key.0)?;
+            let (heading, paragraphs) = texts.remove(&key)?;
+            let mut url = root.join(url).ok()?;
+            let mut breadcrumbs = crumbs.clone();
+            breadcrumbs.push(title.to_string());
+            if !key.1.is_empty() {
+                url.set_fragment(Some(&key.1));
+                if heading != *title {
+                    breadcrumbs.push(heading);
+                }
+            }
+            Some(Section {
+                id: url.to_string(),
+                breadcrumbs,
+                body: paragraphs.join("\n\n"),
+                version: None,
+            })
+        })
+        .collect();
+    Some(sections)
+}
+
+// A Docusaurus site read page by page from its sitemap. Only doc pages are
+// kept; each is split at its h2 and h3 headings, which Docusaurus gives ids.
+async fn docusaurus_page_sections(
+    fetcher: &Fetcher,
+    root: &Url,
+    options: &DocsSiteOptions,
+    errors: &mut Vec<String>,
+) -> Result<Vec<Section>, McpError> {
+    let sitemap = root.join("sitemap.xml").map_err(|e| McpError::fetch(root.as_str(), e))?;
+    let pages = sitemap_urls(fetcher, &sitemap, options.max_pages).await?;
+    let mut fetched = stream::iter(pages)
+        .map(|url| async move {
+            let result = fetcher.fetch(&url).await;
+            (url, result)
+        })
+        .buffered(options.concurrency.max(1));
+
+    let mut sections = Vec::new();
+    while let Some((url, result)) = fetched.next().await {
+        match result {
+            Ok(page) => sections.extend(docusaurus_page(&page.url, &page.body)),
+            Err(e) => errors.push(format!("{}: {}", url, e)),
+        }
+    }
+    Ok(sections)
+}
+
+fn docusaurus_page(url: &Url, html: &str) -> Vec<Section> {
+    let document = kuchikiki::parse_html().one(html);
+    let Ok(content) = document.select_first(".theme-doc-markdown") else {
+        return Vec::new();
+    };
+    let version = meta_content(&document, "meta[name=docusaurus_version]").filter(|version| version != "current");
+    let mut crumbs: Vec<String> = document
+        .select(".breadcrumbs__item")
+        .map(|items| {
+            items
+                .map(|item| collapse_whitespace(&item.text_contents()))
+                .filter(|text| !text.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let title = content
+        .as_node()
+        .select_first("h1")
+        .map(|h1| collapse_whitespace(&h1.text_contents()))
+        .unwrap_or_else(|()| crumbs.pop().unwrap_or_else(|| url.to_string()));
+    // The last breadcrumb is the page itself
+    if crumbs.last() == Some(&title) {
+        crumbs.pop();
+    }
+    let mut page_url = url.clone();
+    page_url.set_fragment(None);
+
+    // Content between headings is moved into a container per section. An
+    // h3's section keeps the h2 it's under as a breadcrumb.
+    let mut sections = Vec::new();
+    let mut current: Option<(Vec<String>, String)> = None;
+    let mut h2: Option<String> = None;
+    let mut container = NodeRef::new_document();
+    let mut push = |heading: Option<(Vec<String>, String)>, container: &NodeRef| {
+        let body = markdown::render(container);
+        let body = body.trim();
+        if body.is_empty() {
+            return;
+        }
+        let mut breadcrumbs = crumbs.clone();
+        breadcrumbs.push(title.clone());
+        let mut id = page_url.clone();
+        if let Some((headings, anchor)) = heading {
+            breadcrumbs.extend(headings);
+            id.set_fragment(Some(&anchor));
+        }
+        sections.push(Section {
+            id: id.to_string(),
+            breadcrumbs,
+            body: body.to_string(),
+            version: version.clone(),
+        });
+    };
+    let blocks: Vec<NodeRef> = content.as_node().children().collect();
+    for block in blocks {
+        let element = block.as_element().map(|element| element.name.local.to_string());
+        match element.as_deref() {
+            Some("h1") | Some("header") => continue,
+            Some(level @ ("h2" | "h3")) => {
+                push(current.take(), &container);
+                container = NodeRef::new_document();
+                let anchor = block
+                    .as_element()
+                    .and_then(|element| element.attributes.borrow().get("id").map(str::to_string));
+                // The permalink after the heading's text is a zero-width "#"
+                let heading = collapse_whitespace(&block.text_contents().replace('\u{200b}', ""));
+                let heading = heading.trim_end_matches('#').trim().to_string();
+                let headings = if level == "h2" {
+                    h2 = Some(heading.clone());
+                    vec![heading]
+                } else {
+                    h2.iter().cloned().chain([heading]).collect()
+                };
+                current = anchor.map(|anchor| (headings, anchor));
+            }
+            _ => container.append(block),
+        }
+    }
+    push(current, &container);
+    sections
+}
+
+// The readable text of an index entry, which may be HTML
+fn html_text(html: &str) -> String {
+    if !html.contains('<') {
+        return html.trim().to_string();
+    }
+    let document = kuchikiki::parse_html().one(format!("<body>{}</body>", html));
+    match document.select_first("body") {
+        Ok(body) => markdown::render(body.as_node()),
+        Err(()) => collapse_whitespace(&document.text_contents()),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,153 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use super::registry::{self, Ecosystem, LatestVersionParams};
+use super::{rustdoc, CrawlManager, CrawlOptions, CrawlStatus, Fetcher};
+use crate::search::SearchService;
+use crate::McpError;
+
+const DOCS_RS: &str = "https://docs.rs";
+
+// Rustdoc JSON of the biggest crates (e.g. windows) runs to hundreds of MB
+// once decompressed
+const MAX_JSON_BYTES: usize = 512 * 1024 * 1024;
+
+// How deep the HTML crawl goes from the crate's root page: enough for
+// items in nested modules
+const CRAWL_DEPTH: usize = 8;
+const CRAWL_CONCURRENCY: usize = 4;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateOptions {
+    /// The crate's name on crates.io, e.g. "tokio"
+    pub name: String,
+    /// Defaults to the newest stable release
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Source to file the items under; defaults to "crate:{name}"
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Pages the HTML crawl stops after, when docs.rs has no rustdoc JSON
+    /// for the release (default 1000)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+}
+
+pub(super) fn default_max_pages() -> usize {
+    1000
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateResponse {
+    pub source: String,
+    pub version: String,
+    /// Items indexed from the rustdoc JSON; 0 when the pages are crawled
+    pub indexed: usize,
+    /// Items that couldn't be indexed, and why the rustdoc JSON couldn't be
+    /// used when the pages are crawled instead
+    pub errors: Vec<String>,
+    /// The crawl of the crate's pages, when docs.rs has no usable rustdoc
+    /// JSON for the release (older builds)
+    pub crawl: Option<CrawlStatus>,
+}
+
+// Indexes a crate's documentation as built by docs.rs. The rustdoc JSON
+// docs.rs keeps for recent builds is indexed one document per item, the
+// same as a local one. For releases without it, the generated pages are
+// crawled in the background instead. `on_progress` gets (items written,
+// total).
+pub async fn index_crate(
+    fetcher: &Fetcher,
+    crawls: &CrawlManager,
+    search_service: Arc<SearchService>,
+    options: CrateOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<CrateResponse, McpError> {
+    let name = options.name.trim().to_string();
+    let version = match &options.version {
+        Some(version) => version.trim().to_string(),
+        None => {
+            // The newest stable release, or pre-release when there are only those
+            let params = LatestVersionParams {
+                ecosystem: Ecosystem::Crates,
+                package: name.clone(),
+            };
+            let releases = registry::latest_version(fetcher, &params).await?;
+            releases.latest.or(releases.prerelease).map(|release| release.version).unwrap_or_default()
+        }
+    };
+    let source = options.source.clone().unwrap_or_else(|| format!("crate:{}", name));
+
+    let json_url = Url::parse(&format!("{}/crate/{}/{}/json.gz", DOCS_RS, name, version))
+        .map_err(|e| McpError::validation(Some("name"), e.to_string()))?;
+    let json_error = match fetcher.fetch_large(&json_url, MAX_JSON_BYTES).await {
+        Ok(compressed) => {
+            let (item_source, item_version) = (source.clone(), version.clone());
+            let indexed = tokio::task::spawn_blocking(move || {
+                let krate = decompress(&compressed)?;
+                rustdoc::index_crate(&search_service, &krate, Some(item_source), Some(item_version), on_progress)
+            })
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?;
+            match indexed {
+                Ok(response) => {
+                    return Ok(CrateResponse {
+                        source: response.source,
+                        version,
+                        indexed: response.indexed,
+                        errors: response.errors,
+                        crawl: None,
+                    })
+                }
+                // Bad JSON (e.g. a format this version can't read) leaves
+                // the pages; index errors are real ones
+                Err(e @ McpError::Index { .. }) => return Err(e),
+                Err(e) => e,
+            }
+        }
+        Err(e) => e,
+    };
+
+    // Pages live under docs.rs/{crate}/{version}/{library}/, where the
+    // library's name is the crate's with dashes made underscores
+    let library = name.replace('-', "_");
+    let crawl = crawls.start(CrawlOptions {
+        seed_url: format!("{}/{}/{}/{}/", DOCS_RS, name, version, library),
+        source: source.clone(),
+        version: Some(version.clone()),
+        same_host: true,
+        path_prefix: Some(format!("/{}/{}/{}/", name, version, library)),
+        max_depth: CRAWL_DEPTH,
+        max_pages: options.max_pages,
+        concurrency: CRAWL_CONCURRENCY,
+        host_concurrency: CRAWL_CONCURRENCY,
+        ignore_robots_txt: false,
+    })?;
+    info!("No rustdoc JSON for {} {} ({}); crawling its pages instead", name, version, json_error);
+    Ok(CrateResponse {
+        source,
+        version,
+        indexed: 0,
+        errors: vec![format!("rustdoc JSON: {}", json_error)],
+        crawl: Some(crawl),
+    })
+}
+
+fn decompress(compressed: &[u8]) -> Result<Value, McpError> {
+    let mut json = Vec::new();
+    GzDecoder::new(compressed)
+        .take(MAX_JSON_BYTES as u64)
+        .read_to_end(&mut json)
+        .map_err(|e| McpError::validation(Some("name"), format!("rustdoc JSON is corrupt: {}", e)))?;
+    serde_json::from_slice(&json)
+        .map_err(|e| McpError::validation(Some("name"), format!("not rustdoc JSON: {}", e)))
+}
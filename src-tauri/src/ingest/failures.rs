@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::util::now_millis;
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PageFailure {
+    pub url: String,
+    pub error: String,
+    // The crawl that last failed on the page
+    pub crawl_id: String,
+    // Milliseconds since the Unix epoch
+    pub failed_at: u64,
+    // Crawls in a row that failed on the page
+    pub failures: u32,
+}
+
+// Pages crawls couldn't download or index, by source and URL, until a
+// later crawl gets them. Kept in memory only.
+#[derive(Default)]
+pub struct FailureReport {
+    sources: Mutex<HashMap<String, HashMap<String, PageFailure>>>,
+}
+
+impl FailureReport {
+    pub fn record(&self, source: &str, url: &str, crawl_id: &str, error: String) {
+        let mut sources = self.sources.lock().unwrap();
+        let pages = sources.entry(source.to_string()).or_default();
+        let failures = pages.get(url).map_or(0, |failure| failure.failures) + 1;
+        pages.insert(
+            url.to_string(),
+            PageFailure {
+                url: url.to_string(),
+                error,
+                crawl_id: crawl_id.to_string(),
+                failed_at: now_millis(),
+                failures,
+            },
+        );
+    }
+
+    pub fn clear<'a>(&self, source: &str, urls: impl IntoIterator<Item = &'a str>) {
+        let mut sources = self.sources.lock().unwrap();
+        if let Some(pages) = sources.get_mut(source) {
+            for url in urls {
+                pages.remove(url);
+            }
+            if pages.is_empty() {
+                sources.remove(source);
+            }
+        }
+    }
+
+    // By URL
+    pub fn for_source(&self, source: &str) -> Vec<PageFailure> {
+        let mut failures: Vec<PageFailure> = self
+            .sources
+            .lock()
+            .unwrap()
+            .get(source)
+            .map(|pages| pages.values().cloned().collect())
+            .unwrap_or_default();
+        failures.sort_by(|a, b| a.url.cmp(&b.url));
+        failures
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use kuchikiki::traits::TendrilSink;
+use roxmltree::Node;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Notify};
+use tracing::{error, info, warn};
+use url::Url;
+
+use super::{date_millis, fetch_document, markdown, ChunkingRules, ExtractionRules, Fetcher, PageRecord, PageStore};
+use crate::search::{SearchService, SearchableDocument};
+use crate::util::now_millis;
+use crate::validation::validate_document;
+use crate::McpError;
+
+const MINUTE_MILLIS: u64 = 60 * 1000;
+
+// As with schedules: how often the watcher looks at the clock when no
+// check is due sooner
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Entry ids remembered per feed; feeds list far fewer entries than this
+const MAX_SEEN_ENTRIES: usize = 1000;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFeedOptions {
+    /// The RSS or Atom feed, e.g. a project's blog or changelog feed
+    pub url: String,
+    /// Source to file the entries under; defaults to the feed's host name
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Minutes between checks (default 60)
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    /// Index the page each new entry links to instead of the entry's own
+    /// text, for feeds that only carry summaries (default false)
+    #[serde(default)]
+    pub fetch_pages: bool,
+}
+
+fn default_interval_minutes() -> u64 {
+    60
+}
+
+// listFeeds takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListFeedsParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedFeed {
+    pub url: String,
+    pub source: String,
+    pub version: Option<String>,
+    pub interval_minutes: u64,
+    pub fetch_pages: bool,
+    // The feed's own title, once it has been read
+    pub title: Option<String>,
+    // Milliseconds since the Unix epoch
+    pub last_check: Option<u64>,
+    pub next_check: u64,
+    // Entries indexed since the feed was added
+    pub indexed: usize,
+    // Why the last check failed, if it did
+    pub last_error: Option<String>,
+}
+
+// A watched feed with what the watcher remembers between checks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FeedRecord {
+    #[serde(flatten)]
+    feed: WatchedFeed,
+    // Ids of the entries already seen, oldest first
+    seen: Vec<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FeedEvent {
+    // Entries a check found and indexed
+    NewContent {
+        feed: String,
+        source: String,
+        entries: Vec<NewEntry>,
+    },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NewEntry {
+    pub id: String,
+    pub title: String,
+}
+
+struct Feed {
+    title: Option<String>,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    id: String,
+    title: Option<String>,
+    link: Option<Url>,
+    published: Option<String>,
+    // Markdown
+    content: String,
+    // The entry's categories, which become its tags
+    categories: Vec<String>,
+}
+
+// Checks RSS and Atom feeds on their intervals and indexes the entries it
+// hasn't seen before, so blogs and changelogs stay current without
+// recrawling whole sites. Feeds are kept in a JSON file next to the settings.
+pub struct FeedWatcher {
+    fetcher: Arc<Fetcher>,
+    rules: Arc<ExtractionRules>,
+    chunking: Arc<ChunkingRules>,
+    search_service: Arc<SearchService>,
+    pages: Arc<PageStore>,
+    path: PathBuf,
+    feeds: Mutex<HashMap<String, FeedRecord>>,
+    changed: Notify,
+    events: broadcast::Sender<FeedEvent>,
+}
+
+impl FeedWatcher {
+    // A missing or unreadable file starts with no feeds
+    pub fn load(
+        path: PathBuf,
+        fetcher: Arc<Fetcher>,
+        rules: Arc<ExtractionRules>,
+        chunking: Arc<ChunkingRules>,
+        search_service: Arc<SearchService>,
+        pages: Arc<PageStore>,
+    ) -> Self {
+        let feeds = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid watched feeds {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let (events, _) = broadcast::channel(64);
+        Self {
+            fetcher,
+            rules,
+            chunking,
+            search_service,
+            pages,
+            path,
+            feeds: Mutex::new(feeds),
+            changed: Notify::new(),
+            events,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<FeedEvent> {
+        self.events.subscribe()
+    }
+
+    // Adds or replaces a feed and checks it right away. A replaced feed
+    // keeps the entries it has seen. Options are expected to be validated
+    // already.
+    pub fn watch(&self, options: WatchFeedOptions) -> Result<WatchedFeed, McpError> {
+        let url = Fetcher::parse_url(&options.url)?;
+        let source = options
+            .source
+            .unwrap_or_else(|| url.host_str().unwrap_or("web").to_string());
+        let feed = {
+            let mut feeds = self.feeds.lock().unwrap();
+            let previous = feeds.remove(url.as_str());
+            let record = FeedRecord {
+                feed: WatchedFeed {
+                    url: url.to_string(),
+                    source,
+                    version: options.version,
+                    interval_minutes: options.interval_minutes,
+                    fetch_pages: options.fetch_pages,
+                    title: previous.as_ref().and_then(|previous| previous.feed.title.clone()),
+                    last_check: previous.as_ref().and_then(|previous| previous.feed.last_check),
+                    next_check: now_millis(),
+                    indexed: previous.as_ref().map_or(0, |previous| previous.feed.indexed),
+                    last_error: None,
+                },
+                seen: previous.map(|previous| previous.seen).unwrap_or_default(),
+                etag: None,
+                last_modified: None,
+            };
+            let feed = record.feed.clone();
+            feeds.insert(url.to_string(), record);
+            self.save(&feeds)
+                .map_err(|e| McpError::internal(format!("Failed to save watched feeds: {}", e)))?;
+            feed
+        };
+        self.changed.notify_one();
+        Ok(feed)
+    }
+
+    // Entries already indexed stay in the index
+    pub fn unwatch(&self, url: &str) -> Result<WatchedFeed, McpError> {
+        let key = Fetcher::parse_url(url).map_or_else(|_| url.to_string(), |url| url.to_string());
+        let mut feeds = self.feeds.lock().unwrap();
+        let record = feeds
+            .remove(&key)
+            .ok_or_else(|| McpError::not_found(format!("Watched feed {}", url)))?;
+        self.save(&feeds)
+            .map_err(|e| McpError::internal(format!("Failed to save watched feeds: {}", e)))?;
+        Ok(record.feed)
+    }
+
+    // Soonest next check first
+    pub fn list(&self) -> Vec<WatchedFeed> {
+        let mut feeds: Vec<WatchedFeed> =
+            self.feeds.lock().unwrap().values().map(|record| record.feed.clone()).collect();
+        feeds.sort_by(|a, b| a.next_check.cmp(&b.next_check).then_with(|| a.url.cmp(&b.url)));
+        feeds
+    }
+
+    // Checks due feeds, one at a time, until the app exits
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let now = now_millis();
+            let due: Vec<FeedRecord> = {
+                let mut feeds = self.feeds.lock().unwrap();
+                feeds
+                    .values_mut()
+                    .filter(|record| record.feed.next_check <= now)
+                    .map(|record| {
+                        // Counted from the start of the check, which may be slow
+                        record.feed.next_check = now + record.feed.interval_minutes * MINUTE_MILLIS;
+                        record.clone()
+                    })
+                    .collect()
+            };
+            for record in due {
+                self.check(record).await;
+            }
+            let wait = self
+                .feeds
+                .lock()
+                .unwrap()
+                .values()
+                .map(|record| record.feed.next_check)
+                .min()
+                .map_or(CHECK_INTERVAL, |next| {
+                    Duration::from_millis(next.saturating_sub(now_millis())).min(CHECK_INTERVAL)
+                });
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+
+    async fn check(&self, mut record: FeedRecord) {
+        let url = record.feed.url.clone();
+        let result = self.read_new_entries(&mut record).await;
+        let now = now_millis();
+        let new_entries = match result {
+            Ok(new_entries) => {
+                record.feed.last_error = None;
+                new_entries
+            }
+            Err(e) => {
+                warn!("Checking feed {} failed: {}", url, e);
+                record.feed.last_error = Some(e.to_string());
+                Vec::new()
+            }
+        };
+        record.feed.last_check = Some(now);
+        if record.seen.len() > MAX_SEEN_ENTRIES {
+            record.seen.drain(..record.seen.len() - MAX_SEEN_ENTRIES);
+        }
+
+        {
+            let mut feeds = self.feeds.lock().unwrap();
+            // Unwatched or replaced while it was being checked
+            let Some(current) = feeds.get_mut(&url) else {
+                return;
+            };
+            current.feed.title = record.feed.title.clone();
+            current.feed.last_check = record.feed.last_check;
+            current.feed.last_error = record.feed.last_error.clone();
+            current.feed.indexed += new_entries.len();
+            current.seen = record.seen;
+            current.etag = record.etag;
+            current.last_modified = record.last_modified;
+            if let Err(e) = self.save(&feeds) {
+                error!("Failed to save watched feeds: {}", e);
+            }
+        }
+        if !new_entries.is_empty() {
+            info!("Indexed {} new entries of feed {}", new_entries.len(), url);
+            let _ = self.events.send(FeedEvent::NewContent {
+                feed: url,
+                source: record.feed.source,
+                entries: new_entries,
+            });
+        }
+    }
+
+    // Downloads the feed and indexes the entries not seen before. The
+    // entries only count as seen once they are in the index.
+    async fn read_new_entries(&self, record: &mut FeedRecord) -> Result<Vec<NewEntry>, McpError> {
+        let url = Fetcher::parse_url(&record.feed.url)?;
+        let known = PageRecord {
+            etag: record.etag.clone(),
+            last_modified: record.last_modified.clone(),
+            ..Default::default()
+        };
+        let Some(page) = self.fetcher.fetch_if_changed(&url, Some(&known)).await? else {
+            return Ok(Vec::new());
+        };
+        let feed = parse_feed(&page.body, &page.url)
+            .ok_or_else(|| McpError::fetch(url.as_str(), "not an RSS or Atom feed"))?;
+        record.feed.title = feed.title.clone().or(record.feed.title.take());
+
+        let seen: HashSet<&str> = record.seen.iter().map(String::as_str).collect();
+        let entries: Vec<Entry> = feed.entries.into_iter().filter(|entry| !seen.contains(entry.id.as_str())).collect();
+        let mut documents = Vec::new();
+        let mut page_records = Vec::new();
+        let mut new_ids = Vec::new();
+        for entry in entries {
+            new_ids.push(entry.id.clone());
+            let page = match entry.link.as_ref().filter(|_| record.feed.fetch_pages) {
+                Some(link) => {
+                    let (source, version) = (Some(record.feed.source.clone()), record.feed.version.clone());
+                    match fetch_document(&self.fetcher, &self.rules, link.as_str(), source, version).await {
+                        Ok(page) => Some(page),
+                        Err(e) => {
+                            warn!("Indexing the entry's own text instead of {}: {}", link, e);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            let document = match page {
+                Some(page) => {
+                    page_records.push((page.document.id.clone(), page.record));
+                    SearchableDocument {
+                        tags: entry.categories.clone(),
+                        published_at: entry.published.as_deref().and_then(date_millis),
+                        ..page.document
+                    }
+                }
+                None => entry_document(&entry, &url, feed.title.as_deref(), &record.feed),
+            };
+            if validate_document(&document, "document").is_ok() {
+                documents.push(document);
+            }
+        }
+
+        let new_entries: Vec<NewEntry> = documents
+            .iter()
+            .map(|document| NewEntry {
+                id: document.id.clone(),
+                title: document.title.clone(),
+            })
+            .collect();
+        if !documents.is_empty() {
+            let search_service = self.search_service.clone();
+            let documents = documents.into_iter().flat_map(|page| self.chunking.split(page)).collect();
+            let add = move || search_service.add_documents(documents, |_, _| {});
+            tokio::task::spawn_blocking(add)
+                .await
+                .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+                .map_err(|e| McpError::index("Failed to add feed entries", e))?;
+        }
+        if !page_records.is_empty() {
+            if let Err(e) = self.pages.remember(page_records) {
+                error!("Failed to save page records: {}", e);
+            }
+        }
+        record.seen.extend(new_ids);
+        record.etag = page.etag;
+        record.last_modified = page.last_modified;
+        Ok(new_entries)
+    }
+
+    fn save(&self, feeds: &HashMap<String, FeedRecord>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(feeds)?)?;
+        Ok(())
+    }
+}
+
+// The entry's text as the feed carries it, under its link (or the feed's
+// URL and the entry's id, for entries without one)
+fn entry_document(entry: &Entry, feed_url: &Url, feed_title: Option<&str>, feed: &WatchedFeed) -> SearchableDocument {
+    let id = match &entry.link {
+        Some(link) => link.to_string(),
+        None => format!("{}#{}", feed_url, entry.id),
+    };
+    let title = entry
+        .title
+        .clone()
+        .or_else(|| feed_title.map(str::to_string))
+        .unwrap_or_else(|| id.clone());
+    let published = entry.published.as_ref().map(|date| format!("Published: {}\n\n", date)).unwrap_or_default();
+    SearchableDocument {
+        id,
+        title,
+        body: format!("{}{}", published, entry.content).trim().to_string(),
+        source: feed.source.clone(),
+        version: feed.version.clone(),
+        url: Some(entry.link.as_ref().unwrap_or(feed_url).to_string()),
+        tags: entry.categories.clone(),
+        indexed_at: None,
+        published_at: entry.published.as_deref().and_then(date_millis),
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    }
+}
+
+// RSS 2.0, RSS 1.0 (RDF) and Atom. Elements are matched by local name, so
+// namespaced extensions such as <content:encoded> and <dc:date> count.
+fn parse_feed(xml: &str, base: &Url) -> Option<Feed> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document = roxmltree::Document::parse_with_options(xml, options).ok()?;
+    let root = document.root_element();
+    let (channel, atom) = match root.tag_name().name() {
+        "rss" => (child(root, "channel")?, false),
+        "RDF" => (child(root, "channel").unwrap_or(root), false),
+        "feed" => (root, true),
+        _ => return None,
+    };
+    let entry_name = if atom { "entry" } else { "item" };
+    let entries = root
+        .descendants()
+        .filter(|node| node.tag_name().name() == entry_name)
+        .filter_map(|node| if atom { atom_entry(node, base) } else { rss_item(node, base) })
+        .collect();
+    Some(Feed {
+        title: child_text(channel, "title"),
+        entries,
+    })
+}
+
+fn rss_item(item: Node, base: &Url) -> Option<Entry> {
+    let link = child_text(item, "link").and_then(|link| resolve(base, &link));
+    let id = child_text(item, "guid")
+        .or_else(|| link.as_ref().map(Url::to_string))
+        .or_else(|| child_text(item, "title"))?;
+    let content = ["encoded", "description"]
+        .iter()
+        .find_map(|name| child_text(item, name))
+        .map(|html| html_text(&html))
+        .unwrap_or_default();
+    Some(Entry {
+        id,
+        title: child_text(item, "title").map(|title| html_text(&title)),
+        link,
+        published: child_text(item, "pubDate").or_else(|| child_text(item, "date")),
+        content,
+        categories: categories(item, |node| Some(text_of(node))),
+    })
+}
+
+fn atom_entry(entry: Node, base: &Url) -> Option<Entry> {
+    let link = entry
+        .children()
+        .filter(|node| node.tag_name().name() == "link")
+        .find(|node| node.attribute("rel").map_or(true, |rel| rel == "alternate"))
+        .and_then(|node| node.attribute("href"))
+        .and_then(|href| resolve(base, href));
+    let id = child_text(entry, "id")
+        .or_else(|| link.as_ref().map(Url::to_string))
+        .or_else(|| child_text(entry, "title"))?;
+    let content = ["content", "summary"]
+        .iter()
+        .find_map(|name| child(entry, name).filter(|node| !text_of(*node).trim().is_empty()))
+        .map(atom_text)
+        .unwrap_or_default();
+    Some(Entry {
+        id,
+        title: child(entry, "title").map(atom_text).filter(|title| !title.is_empty()),
+        link,
+        published: child_text(entry, "published").or_else(|| child_text(entry, "updated")),
+        content,
+        categories: categories(entry, |node| node.attribute("term").map(str::to_string)),
+    })
+}
+
+// RSS has the category as the element's text, with <dc:subject> as an
+// alternative; Atom has it in the `term` attribute
+fn categories(entry: Node, value: impl Fn(Node) -> Option<String>) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for node in entry.children().filter(|node| matches!(node.tag_name().name(), "category" | "subject")) {
+        let Some(category) = value(node).map(|category| category.trim().to_string()) else {
+            continue;
+        };
+        if !category.is_empty() && !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    categories
+}
+
+// Atom text constructs say whether they are text, escaped HTML or XHTML
+fn atom_text(node: Node) -> String {
+    match node.attribute("type") {
+        Some("html") | Some("text/html") => html_text(&text_of(node)),
+        Some("xhtml") => collapse_whitespace(&text_of(node)),
+        _ => text_of(node).trim().to_string(),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(text_of).map(|text| text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+// Text and CDATA of the element and everything inside it
+fn text_of(node: Node) -> String {
+    node.descendants().filter_map(|node| node.text()).collect()
+}
+
+// Fragments stay: changelog feeds link each entry to its own anchor
+fn resolve(base: &Url, link: &str) -> Option<Url> {
+    let url = base.join(link.trim()).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+// Entry content is usually escaped HTML
+fn html_text(html: &str) -> String {
+    if !html.contains('<') {
+        return html.trim().to_string();
+    }
+    let document = kuchikiki::parse_html().one(format!("<body>{}</body>", html));
+    match document.select_first("body") {
+        Ok(body) => markdown::render(body.as_node()),
+        Err(()) => collapse_whitespace(&document.text_contents()),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use tauri_plugin_http::reqwest;
+use tauri_plugin_http::reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::{error, warn};
+use url::Url;
+
+use super::pages::PageRecord;
+use crate::settings::FetchSettings;
+use crate::McpError;
+
+// Pages bigger than this are refused rather than read into memory
+const MAX_PAGE_BYTES: usize = 10 * 1024 * 1024;
+
+// How much of an error answer to an API call is kept for the message
+const MAX_ERROR_BYTES: usize = 4096;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 10;
+
+// Attempts at a request that keeps failing for reasons that may pass:
+// timeouts, dropped connections, 429 and most 5xx answers
+const MAX_ATTEMPTS: u32 = 3;
+// Wait before the first retry, doubled for each one after
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+// Longest wait between attempts, whatever Retry-After asks for
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(30);
+
+// Also the product token robots.txt groups are matched against
+pub(super) const USER_AGENT_TOKEN: &str = "LatestDocsMCP";
+const USER_AGENT: &str = concat!("LatestDocsMCP/", env!("CARGO_PKG_VERSION"));
+
+pub struct FetchedPage {
+    // Where the page ended up after redirects
+    pub url: Url,
+    pub content_type: String,
+    pub body: String,
+    // Validators for conditional requests when the page is fetched again
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+// Shared HTTP client for everything that downloads documentation
+pub struct Fetcher {
+    config: RwLock<FetchConfig>,
+}
+
+struct FetchConfig {
+    client: reqwest::Client,
+    // By lowercased host name
+    host_headers: HashMap<String, HeaderMap>,
+}
+
+impl FetchConfig {
+    fn build(settings: &FetchSettings) -> Result<Self, McpError> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+            .default_headers(header_map(&settings.headers, "headers")?)
+            .danger_accept_invalid_certs(settings.accept_invalid_certs);
+
+        if let Some(proxy) = &settings.proxy {
+            let no_proxy = settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| McpError::validation(Some("proxy"), format!("Invalid proxy URL: {}", e)))?
+                .no_proxy(no_proxy);
+            builder = builder.proxy(proxy);
+        }
+        for path in &settings.ca_certificates {
+            let invalid = |e: &dyn std::fmt::Display| {
+                McpError::validation(Some("ca_certificates"), format!("Could not read {:?}: {}", path, e))
+            };
+            let pem = std::fs::read(path).map_err(|e| invalid(&e))?;
+            for certificate in reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| invalid(&e))? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let mut host_headers = HashMap::new();
+        for (host, headers) in &settings.host_headers {
+            let field = format!("host_headers.{}", host);
+            host_headers.insert(host.to_ascii_lowercase(), header_map(headers, &field)?);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| McpError::internal(format!("Failed to configure the HTTP client: {}", e)))?;
+        Ok(Self { client, host_headers })
+    }
+}
+
+fn header_map(headers: &HashMap<String, String>, field: &str) -> Result<HeaderMap, McpError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = || McpError::validation(Some(field), format!("Invalid header {}", name));
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+        let mut value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+        // Kept out of logs and debug output
+        value.set_sensitive(true);
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+impl Fetcher {
+    // Settings that can't be applied are reported and replaced by defaults,
+    // so a broken proxy setting doesn't keep the app from starting
+    pub fn new(settings: &FetchSettings) -> Self {
+        let config = FetchConfig::build(settings).unwrap_or_else(|e| {
+            warn!("Ignoring fetch settings: {}", e);
+            FetchConfig::build(&FetchSettings::default()).unwrap_or_else(|e| {
+                error!("Failed to configure the HTTP client, using defaults: {}", e);
+                FetchConfig {
+                    client: reqwest::Client::new(),
+                    host_headers: HashMap::new(),
+                }
+            })
+        });
+        Self {
+            config: RwLock::new(config),
+        }
+    }
+
+    // Switches to new settings, keeping the current ones if they're invalid
+    pub fn configure(&self, settings: &FetchSettings) -> Result<(), McpError> {
+        let config = FetchConfig::build(settings)?;
+        *self.config.write().unwrap() = config;
+        Ok(())
+    }
+
+    // Only http(s) URLs; anything else is a validation error
+    pub fn parse_url(url: &str) -> Result<Url, McpError> {
+        let parsed = Url::parse(url.trim())
+            .map_err(|e| McpError::validation(Some("url"), format!("Invalid URL {}: {}", url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(McpError::validation(Some("url"), "Only http and https URLs can be fetched"));
+        }
+        Ok(parsed)
+    }
+
+    pub async fn fetch(&self, url: &Url) -> Result<FetchedPage, McpError> {
+        self.fetch_if_changed(url, None)
+            .await?
+            .ok_or_else(|| McpError::fetch(url.as_str(), "server answered 304 to an unconditional request"))
+    }
+
+    // With a record of an earlier download, asks the server to answer 304
+    // if the page hasn't changed since, which comes back as None
+    pub async fn fetch_if_changed(
+        &self,
+        url: &Url,
+        known: Option<&PageRecord>,
+    ) -> Result<Option<FetchedPage>, McpError> {
+        let mut request = self.request(url);
+        if let Some(known) = known {
+            if let Some(etag) = &known.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &known.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = self.send(url, request).await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED && known.is_some() {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(McpError::fetch(url.as_str(), format!("server answered {}", status)));
+        }
+
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let content_type = header(reqwest::header::CONTENT_TYPE).unwrap_or_default().to_ascii_lowercase();
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        let final_url = response.url().clone();
+        let body = read_body(response, url, MAX_PAGE_BYTES).await?;
+
+        Ok(Some(FetchedPage {
+            url: final_url,
+            content_type,
+            body,
+            etag,
+            last_modified,
+        }))
+    }
+
+    // The body as bytes, for files that aren't text (e.g. compressed
+    // inventories). No conditional requests.
+    pub async fn fetch_bytes(&self, url: &Url) -> Result<Vec<u8>, McpError> {
+        self.fetch_large(url, MAX_PAGE_BYTES).await
+    }
+
+    // Like `fetch_bytes`, for downloads allowed to be bigger than a page
+    // (e.g. a whole documentation set in one file)
+    pub async fn fetch_large(&self, url: &Url, limit: usize) -> Result<Vec<u8>, McpError> {
+        let response = self.get(url).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(McpError::fetch(url.as_str(), format!("server answered {}", status)));
+        }
+        read_bytes(response, url, limit).await
+    }
+
+    // Like `fetch_large`, with None for a 404, for things that may well not
+    // exist (e.g. a package asked for by name)
+    pub async fn fetch_if_exists(&self, url: &Url, limit: usize) -> Result<Option<Vec<u8>>, McpError> {
+        self.fetch_if_exists_with(url, &[], limit).await
+    }
+
+    // Like `fetch_if_exists`, with extra headers for this request, e.g. a
+    // token the caller passed in
+    pub async fn fetch_if_exists_with(
+        &self,
+        url: &Url,
+        headers: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<Option<Vec<u8>>, McpError> {
+        let mut request = self.request(url);
+        for (name, value) in headers {
+            let mut value = HeaderValue::from_str(value)
+                .map_err(|_| McpError::validation(None, format!("Invalid value for header {}", name)))?;
+            value.set_sensitive(true);
+            request = request.header(*name, value);
+        }
+        let response = self.send(url, request).await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(McpError::fetch(url.as_str(), format!("server answered {}", status)));
+        }
+        read_bytes(response, url, limit).await.map(Some)
+    }
+
+    // POSTs a JSON body to an API, e.g. an embeddings endpoint, and returns
+    // the JSON it answers, retried like any request. The token goes in an
+    // Authorization header; an error answer's body ends up in the message.
+    pub async fn post_json(
+        &self,
+        url: &Url,
+        bearer: Option<&str>,
+        body: &serde_json::Value,
+        limit: usize,
+    ) -> Result<serde_json::Value, McpError> {
+        let body = serde_json::to_vec(body)
+            .map_err(|e| McpError::internal(format!("Failed to encode the request: {}", e)))?;
+        let mut request = self
+            .request_as(reqwest::Method::POST, url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(token) = bearer {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| McpError::validation(Some("api_key"), "Invalid API key"))?;
+            value.set_sensitive(true);
+            request = request.header(reqwest::header::AUTHORIZATION, value);
+        }
+        let response = self.send(url, request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = read_body(response, url, MAX_ERROR_BYTES).await.unwrap_or_default();
+            return Err(McpError::fetch(
+                url.as_str(),
+                format!("server answered {}: {}", status, message.trim()),
+            ));
+        }
+        let answer = read_body(response, url, limit).await?;
+        serde_json::from_str(&answer).map_err(|e| McpError::fetch(url.as_str(), format!("invalid JSON: {}", e)))
+    }
+
+    // The raw response, whatever its status, for callers that treat
+    // statuses differently (e.g. robots.txt)
+    pub(super) async fn get(&self, url: &Url) -> Result<reqwest::Response, McpError> {
+        self.send(url, self.request(url)).await
+    }
+
+    // Sends `request`, trying again with exponential backoff while it fails
+    // transiently. The last answer or error is returned once attempts run out.
+    async fn send(&self, url: &Url, request: reqwest::RequestBuilder) -> Result<reqwest::Response, McpError> {
+        let mut request = request;
+        let mut attempt = 1;
+        loop {
+            let retry = if attempt < MAX_ATTEMPTS { request.try_clone() } else { None };
+            let result = request.send().await;
+            let Some(retry) = retry else {
+                return result.map_err(|e| McpError::fetch(url.as_str(), e));
+            };
+            let backoff = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+            let (wait, reason) = match &result {
+                Ok(response) if is_transient(response.status()) => {
+                    (retry_after(response).unwrap_or(backoff), response.status().to_string())
+                }
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => (backoff, e.to_string()),
+                _ => return result.map_err(|e| McpError::fetch(url.as_str(), e)),
+            };
+            let wait = wait.min(MAX_RETRY_WAIT);
+            warn!("Attempt {} at {} failed ({}), retrying in {:?}", attempt, url, reason, wait);
+            tokio::time::sleep(wait).await;
+            request = retry;
+            attempt += 1;
+        }
+    }
+
+    // A GET with the headers configured for the URL's host
+    fn request(&self, url: &Url) -> reqwest::RequestBuilder {
+        self.request_as(reqwest::Method::GET, url)
+    }
+
+    fn request_as(&self, method: reqwest::Method, url: &Url) -> reqwest::RequestBuilder {
+        let config = self.config.read().unwrap();
+        let request = config.client.request(method, url.clone());
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        match config.host_headers.get(&host) {
+            Some(headers) => request.headers(headers.clone()),
+            None => request,
+        }
+    }
+}
+
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+// Only the delay-seconds form; an HTTP date falls back to the usual backoff
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+// The body as text, refusing anything over `limit` bytes
+pub(super) async fn read_body(response: reqwest::Response, url: &Url, limit: usize) -> Result<String, McpError> {
+    let body = read_bytes(response, url, limit).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+async fn read_bytes(mut response: reqwest::Response, url: &Url, limit: usize) -> Result<Vec<u8>, McpError> {
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(McpError::fetch(url.as_str(), "page is too large"));
+    }
+
+    // Read in chunks so a missing or lying Content-Length can't blow the limit
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| McpError::fetch(url.as_str(), e))? {
+        if body.len() + chunk.len() > limit {
+            return Err(McpError::fetch(url.as_str(), "page is too large"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
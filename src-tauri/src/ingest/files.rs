@@ -0,0 +1,423 @@
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use url::Url;
+
+use super::{html, pdf, ChunkingRules};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "mdx", "markdown"];
+
+// Larger PDFs are refused rather than read into memory
+const MAX_PDF_BYTES: u64 = 200 * 1024 * 1024;
+
+// Directories that hold dependencies or build output, not a project's docs
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "target", "vendor"];
+
+// A folder with more files than this is probably not a docs folder
+const MAX_FILES: usize = 10_000;
+
+// Files are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPathOptions {
+    /// Directory to index the Markdown files of, or a single Markdown file
+    pub path: String,
+    /// Source to file the documents under; defaults to the directory's name
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Version for files whose frontmatter doesn't name one
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPathResponse {
+    pub source: String,
+    pub indexed: usize,
+    /// Files that couldn't be read or indexed, as "path: reason"
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AddFileOptions {
+    /// A .pdf, Markdown (.md, .mdx), HTML or plain text file
+    pub path: String,
+    /// Source to file the documents under; defaults to the file's name
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AddFileResponse {
+    pub source: String,
+    /// The documents added; one per page with text for PDFs
+    pub ids: Vec<String>,
+}
+
+// The frontmatter keys we use; everything else is ignored
+#[derive(Default, Debug)]
+struct Frontmatter {
+    title: Option<String>,
+    version: Option<String>,
+    tags: Vec<String>,
+}
+
+// Indexes every Markdown file under `options.path`, one document per file
+// with its `file://` URL as id. Blocking; run it off the async runtime.
+// `on_progress` gets (documents written, total).
+pub fn index_path(
+    search_service: &SearchService,
+    chunking: &ChunkingRules,
+    options: &IndexPathOptions,
+    on_progress: impl Fn(usize, usize),
+) -> Result<IndexPathResponse, McpError> {
+    let root = std::fs::canonicalize(options.path.trim())
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read {}: {}", options.path, e)))?;
+    let source = options.source.clone().unwrap_or_else(|| {
+        root.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local".to_string())
+    });
+
+    let files = markdown_files(&root)?;
+    let mut errors = Vec::new();
+    let mut documents = Vec::new();
+    for file in &files {
+        match read_document(file, &source, options.version.as_deref()) {
+            Ok(document) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", file.display(), e)),
+        }
+    }
+
+    let indexed = documents.len();
+    let documents: Vec<SearchableDocument> = documents.into_iter().flat_map(|page| chunking.split(page)).collect();
+    let mut written = 0;
+    for batch in documents.chunks(INDEX_BATCH_SIZE) {
+        search_service
+            .add_documents(batch.to_vec(), |done, _| on_progress(written + done, documents.len()))
+            .map_err(|e| McpError::index("Failed to add files", e))?;
+        written += batch.len();
+    }
+    info!("Indexed {} Markdown files from {:?} as source {}", indexed, root, source);
+    Ok(IndexPathResponse {
+        source,
+        indexed,
+        errors,
+    })
+}
+
+// Indexes a single file. PDFs become one document per page, with the page
+// number in the id (`file:///manual.pdf#page=3`) and title. Blocking.
+pub fn add_file(
+    search_service: &SearchService,
+    chunking: &ChunkingRules,
+    options: &AddFileOptions,
+) -> Result<AddFileResponse, McpError> {
+    let path = std::fs::canonicalize(options.path.trim())
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read {}: {}", options.path, e)))?;
+    let source = options.source.clone().unwrap_or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local".to_string())
+    });
+    let version = options.version.as_deref();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let documents = match extension.as_str() {
+        "pdf" => read_pdf(&path, &source, version)?,
+        "html" | "htm" => {
+            let contents = std::fs::read_to_string(&path).map_err(|e| McpError::internal(e.to_string()))?;
+            let page = html::extract(&contents, None);
+            let title = if page.title.is_empty() { file_stem(&path) } else { page.title };
+            vec![file_document(&path, None, title, page.markdown, &source, version)]
+        }
+        "txt" | "text" => {
+            let contents = std::fs::read_to_string(&path).map_err(|e| McpError::internal(e.to_string()))?;
+            let body = contents.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+            vec![file_document(&path, None, file_stem(&path), body.trim().to_string(), &source, version)]
+        }
+        _ if is_markdown(&path) => vec![read_document(&path, &source, version)?],
+        _ => {
+            return Err(McpError::validation(
+                Some("path"),
+                "Only PDF, Markdown, HTML and plain text files can be added",
+            ))
+        }
+    };
+    if documents.is_empty() {
+        return Err(McpError::validation(
+            Some("path"),
+            format!("No text found in {}; scanned PDFs need OCR first", path.display()),
+        ));
+    }
+    for document in &documents {
+        validate_document(document, "document")?;
+    }
+
+    let ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+    let documents: Vec<SearchableDocument> = documents.into_iter().flat_map(|page| chunking.split(page)).collect();
+    for batch in documents.chunks(INDEX_BATCH_SIZE) {
+        search_service
+            .add_documents(batch.to_vec(), |_, _| {})
+            .map_err(|e| McpError::index("Failed to add file", e))?;
+    }
+    info!("Added {} documents from {:?} as source {}", ids.len(), path, source);
+    Ok(AddFileResponse { source, ids })
+}
+
+// One document per page that has text
+fn read_pdf(path: &Path, source: &str, version: Option<&str>) -> Result<Vec<SearchableDocument>, McpError> {
+    let size = std::fs::metadata(path).map_err(|e| McpError::internal(e.to_string()))?.len();
+    if size > MAX_PDF_BYTES {
+        return Err(McpError::validation(
+            Some("path"),
+            format!("PDFs larger than {} MB are not supported", MAX_PDF_BYTES / 1024 / 1024),
+        ));
+    }
+    let data = std::fs::read(path).map_err(|e| McpError::internal(e.to_string()))?;
+    let text = pdf::extract(&data)
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read {}: {}", path.display(), e)))?;
+    let title = text.title.unwrap_or_else(|| file_stem(path));
+    Ok(text
+        .pages
+        .into_iter()
+        .enumerate()
+        .filter(|(_, page)| !page.is_empty())
+        .map(|(i, page)| {
+            let number = i + 1;
+            let page_title = format!("{} (page {})", title, number);
+            file_document(path, Some(number), page_title, page, source, version)
+        })
+        .collect())
+}
+
+// `page` goes in the id's fragment, as PDF viewers understand it
+fn file_document(
+    path: &Path,
+    page: Option<usize>,
+    title: String,
+    body: String,
+    source: &str,
+    version: Option<&str>,
+) -> SearchableDocument {
+    let mut id = file_id(path);
+    if let Some(page) = page {
+        id.push_str(&format!("#page={}", page));
+    }
+    SearchableDocument {
+        url: Some(id.clone()),
+        tags: Vec::new(),
+        indexed_at: None,
+        published_at: None,
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+        id,
+        title,
+        body,
+        source: source.to_string(),
+        version: version.map(str::to_string),
+    }
+}
+
+fn file_id(path: &Path) -> String {
+    Url::from_file_path(path)
+        .map(String::from)
+        .unwrap_or_else(|()| path.display().to_string())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Hidden directories and dependency folders are skipped
+fn markdown_files(root: &Path) -> Result<Vec<PathBuf>, McpError> {
+    if root.is_file() {
+        return if is_markdown(root) {
+            Ok(vec![root.to_path_buf()])
+        } else {
+            Err(McpError::validation(Some("path"), "Only Markdown files (.md, .mdx) can be indexed"))
+        };
+    }
+
+    let walker = walkdir::WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        let skipped = name.starts_with('.')
+            || entry.file_type().is_dir() && SKIPPED_DIRECTORIES.contains(&name.as_ref());
+        entry.depth() == 0 || !skipped
+    });
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Skipping unreadable entry under {:?}: {}", root, e);
+                continue;
+            }
+        };
+        if entry.file_type().is_file() && is_markdown(entry.path()) {
+            if files.len() == MAX_FILES {
+                return Err(McpError::validation(
+                    Some("path"),
+                    format!("{} holds more than {} Markdown files", root.display(), MAX_FILES),
+                ));
+            }
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| MARKDOWN_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+fn read_document(path: &Path, source: &str, version: Option<&str>) -> Result<SearchableDocument, McpError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| McpError::internal(e.to_string()))?;
+    let contents = contents.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let (frontmatter, markdown) = split_frontmatter(&contents);
+    let body = if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("mdx")) {
+        strip_mdx_statements(markdown)
+    } else {
+        markdown.trim().to_string()
+    };
+
+    let title = frontmatter
+        .title
+        .or_else(|| first_heading(&body))
+        .unwrap_or_else(|| file_stem(path));
+    let document = SearchableDocument {
+        id: file_id(path),
+        title,
+        body,
+        source: source.to_string(),
+        version: frontmatter.version.or_else(|| version.map(str::to_string)),
+        url: Some(file_id(path)),
+        tags: frontmatter.tags,
+        indexed_at: None,
+        published_at: None,
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    };
+    validate_document(&document, "document")?;
+    Ok(document)
+}
+
+// YAML frontmatter between `---` lines, if the file starts with one. Only
+// flat `key: value` pairs and lists (`[a, b]` or `- a` lines) are read,
+// which is all the keys we use need.
+fn split_frontmatter(contents: &str) -> (Frontmatter, &str) {
+    let mut frontmatter = Frontmatter::default();
+    let Some(rest) = contents.strip_prefix("---\n") else {
+        return (frontmatter, contents);
+    };
+    let mut end = None;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((header_end, markdown_start)) = end else {
+        return (frontmatter, contents);
+    };
+    let markdown = &rest[markdown_start..];
+
+    let mut list_key: Option<String> = None;
+    for line in rest[..header_end].lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if list_key.as_deref().is_some_and(|key| matches!(key, "tags" | "keywords")) {
+                frontmatter.tags.push(unquote(item));
+            }
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        list_key = value.is_empty().then(|| key.clone());
+        match key.as_str() {
+            "title" if !value.is_empty() => frontmatter.title = Some(unquote(value)),
+            "version" if !value.is_empty() => frontmatter.version = Some(unquote(value)),
+            "tags" | "keywords" => {
+                let list = value.strip_prefix('[').and_then(|list| list.strip_suffix(']'));
+                let items = list.unwrap_or(value);
+                frontmatter.tags.extend(items.split(',').map(unquote).filter(|tag| !tag.is_empty()));
+            }
+            _ => {}
+        }
+    }
+    (frontmatter, markdown)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote).and_then(|value| value.strip_suffix(*quote)));
+    unquoted.unwrap_or(value).to_string()
+}
+
+// MDX `import` and `export` lines are code for the site, not content
+fn strip_mdx_statements(markdown: &str) -> String {
+    let mut in_fence = false;
+    let lines: Vec<&str> = markdown
+        .lines()
+        .filter(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            in_fence || !(line.starts_with("import ") || line.starts_with("export "))
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+// Outside code blocks, where `# ` starts a shell comment
+fn first_heading(markdown: &str) -> Option<String> {
+    let mut in_fence = false;
+    markdown
+        .lines()
+        .filter(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            !in_fence
+        })
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|heading| heading.trim().to_string())
+        .filter(|heading| !heading.is_empty())
+}
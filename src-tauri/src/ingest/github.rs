@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use futures_util::stream::{self, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use super::{ChunkingRules, CrawlManager, CrawlOptions, CrawlStatus, Fetcher};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_RAW: &str = "https://raw.githubusercontent.com";
+const GITHUB: &str = "https://github.com";
+
+// Used when the caller passes no token
+const TOKEN_VARIABLE: &str = "GITHUB_TOKEN";
+
+// Trees of big monorepos run to tens of MB
+const MAX_API_BYTES: usize = 64 * 1024 * 1024;
+// Bigger files are generated, not written
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "markdown", "rst", "adoc", "txt"];
+
+const DOWNLOAD_CONCURRENCY: usize = 4;
+const WIKI_CRAWL_DEPTH: usize = 2;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHubRepoOptions {
+    /// "owner/repo", or the repository's URL
+    pub repo: String,
+    /// Token for private repositories and a higher rate limit; defaults to
+    /// $GITHUB_TOKEN. Never recorded in the audit log.
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+    /// Defaults to the repository's default branch
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Folder of documentation to index besides the README (default "docs")
+    #[serde(default = "default_docs_path")]
+    pub docs_path: String,
+    /// Also crawl the repository's wiki (default true)
+    #[serde(default = "default_wiki")]
+    pub wiki: bool,
+    /// Files indexed at most, and wiki pages crawled at most (default 1000)
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Source to file the documents under; defaults to "github:{owner}/{repo}"
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+fn default_docs_path() -> String {
+    "docs".to_string()
+}
+
+fn default_wiki() -> bool {
+    true
+}
+
+fn default_max_files() -> usize {
+    1000
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHubRepoResponse {
+    pub source: String,
+    /// "owner/repo"
+    pub repo: String,
+    pub branch: String,
+    /// The commit the files were read at, also their version
+    pub commit: String,
+    pub indexed: usize,
+    /// Files that couldn't be read or indexed, as "path: reason"
+    pub errors: Vec<String>,
+    /// The crawl of the wiki, which runs in the background
+    pub wiki_crawl: Option<CrawlStatus>,
+}
+
+// Indexes a GitHub repository's README and documentation folder, one
+// document per file, as of the branch's latest commit. Wikis have no API;
+// their pages are crawled. `on_progress` gets (documents written, total).
+pub async fn index_github_repo(
+    fetcher: &Fetcher,
+    crawls: &CrawlManager,
+    chunking: &ChunkingRules,
+    search_service: Arc<SearchService>,
+    options: GitHubRepoOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<GitHubRepoResponse, McpError> {
+    let repo = parse_repo(&options.repo)?;
+    let api = GitHubApi::new(fetcher, options.token.clone());
+
+    let metadata = api
+        .get(&format!("repos/{}", repo))
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("GitHub repository {}", repo)))?;
+    let branch = match &options.branch {
+        Some(branch) => branch.trim().to_string(),
+        None => metadata["default_branch"].as_str().unwrap_or("main").to_string(),
+    };
+    let commit = api
+        .get(&format!("repos/{}/commits/{}", repo, branch))
+        .await?
+        .and_then(|commit| commit["sha"].as_str().map(str::to_string))
+        .ok_or_else(|| McpError::not_found(format!("branch {} of {}", branch, repo)))?;
+    let tree = api
+        .get(&format!("repos/{}/git/trees/{}?recursive=1", repo, commit))
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("the files of {} at {}", repo, commit)))?;
+
+    let mut errors = Vec::new();
+    if tree["truncated"].as_bool() == Some(true) {
+        errors.push(format!("{}: the file list was cut short by GitHub; some files may be missing", repo));
+    }
+    let docs_path = options.docs_path.trim().trim_matches('/');
+    let mut paths: Vec<String> = tree["tree"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| entry["type"].as_str() == Some("blob"))
+        .filter(|entry| entry["size"].as_u64().is_some_and(|size| size <= MAX_FILE_BYTES))
+        .filter_map(|entry| entry["path"].as_str())
+        .filter(|path| is_readme(path) || (is_doc(path) && is_under(path, docs_path)))
+        .map(str::to_string)
+        .collect();
+    if paths.len() > options.max_files {
+        errors.push(format!("only the first {} of {} files were indexed", options.max_files, paths.len()));
+        paths.truncate(options.max_files);
+    }
+
+    let source = options.source.clone().unwrap_or_else(|| format!("github:{}", repo));
+    let metrics = repo_metrics(&metadata);
+    let downloads = paths.into_iter().map(|path| {
+        let api = &api;
+        let url = format!("{}/{}/{}/{}", GITHUB_RAW, repo, commit, path);
+        async move { (api.download(&url).await, path) }
+    });
+    let mut downloads = stream::iter(downloads).buffered(DOWNLOAD_CONCURRENCY);
+    let mut documents = Vec::new();
+    while let Some((text, path)) = downloads.next().await {
+        let text = match text {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                errors.push(format!("{}: not found", path));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+        let url = format!("{}/{}/blob/{}/{}", GITHUB, repo, commit, path);
+        let document = SearchableDocument {
+            id: url.clone(),
+            title: format!("{}/{}", repo, path),
+            body: text.trim().to_string(),
+            source: source.clone(),
+            version: Some(commit.clone()),
+            url: Some(url),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: metrics.clone(),
+            facet: None,
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: None,
+            content_hash: None,
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+    drop(downloads);
+
+    let indexed = documents.len();
+    if !documents.is_empty() {
+        let documents = documents.into_iter().flat_map(|page| chunking.split(page)).collect();
+        tokio::task::spawn_blocking(move || search_service.add_documents(documents, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+            .map_err(|e| McpError::index("Failed to add repository files", e))?;
+    }
+
+    let wiki_crawl = if options.wiki && metadata["has_wiki"].as_bool() == Some(true) {
+        Some(crawls.start(CrawlOptions {
+            seed_url: format!("{}/{}/wiki", GITHUB, repo),
+            source: source.clone(),
+            version: None,
+            same_host: true,
+            path_prefix: Some(format!("/{}/wiki", repo)),
+            max_depth: WIKI_CRAWL_DEPTH,
+            max_pages: options.max_files,
+            concurrency: DOWNLOAD_CONCURRENCY,
+            host_concurrency: DOWNLOAD_CONCURRENCY,
+            ignore_robots_txt: false,
+        })?)
+    } else {
+        None
+    };
+    info!("Indexed {} files of {} at {} as source {}", indexed, repo, commit, source);
+    Ok(GitHubRepoResponse {
+        source,
+        repo,
+        branch,
+        commit,
+        indexed,
+        errors,
+        wiki_crawl,
+    })
+}
+
+pub(super) struct GitHubApi<'a> {
+    fetcher: &'a Fetcher,
+    token: Option<String>,
+}
+
+impl<'a> GitHubApi<'a> {
+    pub(super) fn new(fetcher: &'a Fetcher, token: Option<String>) -> Self {
+        let token = token.or_else(|| std::env::var(TOKEN_VARIABLE).ok()).filter(|token| !token.is_empty());
+        GitHubApi { fetcher, token }
+    }
+
+    // A REST API resource, None if GitHub doesn't have it (or won't say,
+    // for private repositories without a token)
+    pub(super) async fn get(&self, path: &str) -> Result<Option<Value>, McpError> {
+        let url = Fetcher::parse_url(&format!("{}/{}", GITHUB_API, path))?;
+        let authorization = self.token.as_ref().map(|token| format!("Bearer {}", token));
+        let mut headers = vec![("Accept", "application/vnd.github+json"), ("X-GitHub-Api-Version", "2022-11-28")];
+        if let Some(authorization) = &authorization {
+            headers.push(("Authorization", authorization));
+        }
+        let Some(body) = self.fetcher.fetch_if_exists_with(&url, &headers, MAX_API_BYTES).await? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| McpError::fetch(url.as_str(), format!("not a GitHub API answer: {}", e)))
+    }
+
+    pub(super) async fn download(&self, url: &str) -> Result<Option<String>, McpError> {
+        let url = Fetcher::parse_url(url)?;
+        let authorization = self.token.as_ref().map(|token| format!("token {}", token));
+        let headers: Vec<(&str, &str)> = authorization.iter().map(|value| ("Authorization", value.as_str())).collect();
+        let body = self.fetcher.fetch_if_exists_with(&url, &headers, MAX_FILE_BYTES as usize).await?;
+        Ok(body.map(|body| String::from_utf8_lossy(&body).into_owned()))
+    }
+}
+
+// "owner/repo" out of itself, "https://github.com/owner/repo" or its .git
+// clone URL
+pub(super) fn parse_repo(repo: &str) -> Result<String, McpError> {
+    let repo = repo.trim().trim_end_matches('/');
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let path = match Url::parse(repo) {
+        Ok(url) if url.host_str() == Some("github.com") => url.path().trim_matches('/').to_string(),
+        _ => repo.to_string(),
+    };
+    let mut parts = path.split('/');
+    match (parts.next(), parts.next()) {
+        (Some(owner), Some(name)) if !owner.is_empty() && !name.is_empty() => Ok(format!("{}/{}", owner, name)),
+        _ => Err(McpError::validation(Some("repo"), format!("{} is not an owner/repo name", repo))),
+    }
+}
+
+// The repository's popularity, for ranking its documents against others
+pub(super) fn repo_metrics(metadata: &Value) -> BTreeMap<String, f64> {
+    [("stars", "stargazers_count"), ("forks", "forks_count")]
+        .into_iter()
+        .filter_map(|(name, key)| Some((name.to_string(), metadata[key].as_f64()?)))
+        .collect()
+}
+
+fn is_readme(path: &str) -> bool {
+    !path.contains('/') && path.to_ascii_lowercase().starts_with("readme")
+}
+
+fn is_doc(path: &str) -> bool {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    extension.is_some_and(|extension| DOC_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn is_under(path: &str, folder: &str) -> bool {
+    folder.is_empty() || path.strip_prefix(folder).is_some_and(|rest| rest.starts_with('/'))
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use url::Url;
+
+use super::ChunkingRules;
+use crate::search::{SearchService, SearchableDocument};
+use crate::util::sha256_hex;
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Bigger files are generated, not written
+const MAX_FILE_BYTES: usize = 2 * 1024 * 1024;
+
+// Files are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoOptions {
+    /// A clone URL (https, ssh or git), or the path of a repository on this
+    /// machine
+    pub repo: String,
+    /// Branch, tag or commit to read; defaults to the remote's default
+    /// branch, or what a local repository has checked out
+    #[serde(default, rename = "ref")]
+    pub git_ref: Option<String>,
+    /// Files to index, as globs from the repository's root with `*`, `**`,
+    /// `?` and `{a,b}` (default: the README and the docs/ and doc/ folders'
+    /// Markdown, reStructuredText, AsciiDoc and text files)
+    #[serde(default = "default_globs")]
+    pub globs: Vec<String>,
+    /// Source to file the documents under; defaults to "git:{name}"
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Files indexed at most (default 5000)
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Read every file again, not only those changed since the last run
+    /// (default false)
+    #[serde(default)]
+    pub full: bool,
+}
+
+fn default_globs() -> Vec<String> {
+    ["README*", "docs/**/*.{md,mdx,markdown,rst,adoc,txt}", "doc/**/*.{md,mdx,markdown,rst,adoc,txt}"]
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect()
+}
+
+fn default_max_files() -> usize {
+    5000
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoResponse {
+    pub source: String,
+    /// The branch, tag or commit read, which is also the documents' version
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub commit: String,
+    /// The commit the last run read, when only the changes since were indexed
+    pub previous_commit: Option<String>,
+    pub indexed: usize,
+    /// Documents of files that are gone since the last run
+    pub deleted: usize,
+    /// Files that couldn't be read or indexed, as "path: reason"
+    pub errors: Vec<String>,
+}
+
+// What the last run read for a ref, so the next one can index the changes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct IndexedRef {
+    commit: String,
+    source: String,
+    globs: Vec<String>,
+}
+
+// Indexes the files of a Git repository matching the globs, one document per
+// file with the ref as its version. Remote repositories are fetched at depth
+// 1 into a bare mirror under `mirrors`; later runs fetch again and only
+// index the files changed since, deleting the documents of removed files.
+// Blocking; run it off the async runtime. `on_progress` gets (documents
+// written, total).
+pub fn index_git_repo(
+    search_service: &SearchService,
+    chunking: &ChunkingRules,
+    mirrors: &Path,
+    options: &GitRepoOptions,
+    on_progress: impl Fn(usize, usize),
+) -> Result<GitRepoResponse, McpError> {
+    let repo = options.repo.trim();
+    let local = Path::new(repo).is_dir();
+    // Where the repository is, the same however it was written
+    let display = if local {
+        let path = std::fs::canonicalize(repo)
+            .map_err(|e| McpError::validation(Some("repo"), format!("{}: {}", repo, e)))?;
+        path.to_string_lossy().into_owned()
+    } else {
+        repo.trim_end_matches('/').to_string()
+    };
+    let name = display.trim_end_matches(".git").rsplit(['/', '\\', ':']).next().unwrap_or("repo").to_string();
+    let state_path = mirrors.join(format!("{}-{}.json", name, &sha256_hex(display.as_bytes())[..16]));
+
+    let (dir, git_ref, commit) = if local {
+        let dir = PathBuf::from(&display);
+        let git_ref = match &options.git_ref {
+            Some(git_ref) => git_ref.trim().to_string(),
+            None => match git(&dir, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+                // A detached HEAD goes by its commit
+                Ok(branch) if branch.trim() != "HEAD" => branch.trim().to_string(),
+                _ => git(&dir, &["rev-parse", "--short", "HEAD"]).map_err(|e| not_a_repo(repo, e))?.trim().to_string(),
+            },
+        };
+        let commit = resolve_commit(&dir, &git_ref)?;
+        (dir, git_ref, commit)
+    } else {
+        let dir = mirrors.join(format!("{}-{}.git", name, hash));
+        if !dir.join("HEAD").exists() {
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| McpError::internal(format!("Failed to create {:?}: {}", dir, e)))?;
+            git(&dir, &["init", "--bare", "--quiet"]).map_err(|e| McpError::internal(format!("git init: {}", e)))?;
+            git(&dir, &["remote", "add", "origin", repo])
+                .map_err(|e| McpError::internal(format!("git remote: {}", e)))?;
+        }
+        let git_ref = match &options.git_ref {
+            Some(git_ref) => git_ref.trim().to_string(),
+            None => default_branch(&dir).map_err(|e| McpError::fetch(repo, e))?,
+        };
+        git(&dir, &["fetch", "--depth", "1", "--no-tags", "--quiet", "origin", &git_ref])
+            .map_err(|e| McpError::fetch(repo, e))?;
+        let commit = resolve_commit(&dir, "FETCH_HEAD")?;
+        (dir, git_ref, commit)
+    };
+
+    let source = options.source.clone().unwrap_or_else(|| format!("git:{}", name));
+    let mut state: HashMap<String, IndexedRef> = std::fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    let previous = state
+        .get(&git_ref)
+        .filter(|previous| !options.full && previous.source == source && previous.globs == options.globs)
+        .filter(|previous| resolve_commit(&dir, &previous.commit).is_ok())
+        .cloned();
+
+    let matches = |path: &str| options.globs.iter().any(|glob| glob_matches(glob, path));
+    let (mut changed, removed): (Vec<String>, Vec<String>) = match &previous {
+        Some(previous) => {
+            let diff = git(&dir, &["diff", "--name-status", "-z", "--no-renames", &previous.commit, &commit])
+                .map_err(|e| McpError::internal(format!("git diff: {}", e)))?;
+            let mut changed = Vec::new();
+            let mut removed = Vec::new();
+            let mut fields = diff.split('\0').filter(|field| !field.is_empty());
+            while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+                if !matches(path) {
+                    continue;
+                }
+                match status {
+                    "D" => removed.push(path.to_string()),
+                    _ => changed.push(path.to_string()),
+                }
+            }
+            (changed, removed)
+        }
+        None => {
+            let files = git(&dir, &["ls-tree", "-r", "-z", "--name-only", &commit])
+                .map_err(|e| McpError::internal(format!("git ls-tree: {}", e)))?;
+            let files = files.split('\0').filter(|path| !path.is_empty() && matches(path)).map(str::to_string);
+            (files.collect(), Vec::new())
+        }
+    };
+    let mut errors = Vec::new();
+    if changed.len() > options.max_files {
+        errors.push(format!("only the first {} of {} files were indexed", options.max_files, changed.len()));
+        changed.truncate(options.max_files);
+    }
+
+    let id = |path: &str| format!("{}@{}:{}", display, git_ref, path);
+    let url = |path: &str| {
+        if local {
+            Url::from_file_path(dir.join(path)).ok().map(String::from)
+        } else {
+            browse_url(&display, &git_ref, path)
+        }
+    };
+    let mut documents = Vec::new();
+    for (path, text) in read_files(&dir, &commit, &changed)? {
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+        let document = SearchableDocument {
+            id: id(&path),
+            title: format!("{}/{}", name, path),
+            body: text.trim().to_string(),
+            source: source.clone(),
+            version: Some(git_ref.clone()),
+            url: url(&path),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: None,
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: None,
+            content_hash: None,
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+    let indexed = documents.len();
+    let documents: Vec<SearchableDocument> = documents.into_iter().flat_map(|page| chunking.split(page)).collect();
+    let mut written = 0;
+    for batch in documents.chunks(INDEX_BATCH_SIZE) {
+        search_service
+            .add_documents(batch.to_vec(), |done, _| on_progress(written + done, documents.len()))
+            .map_err(|e| McpError::index("Failed to add repository files", e))?;
+        written += batch.len();
+    }
+    let mut deleted = 0;
+    for path in &removed {
+        match search_service.delete_document(&id(path)) {
+            Ok(true) => deleted += 1,
+            Ok(false) => {}
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    if !local {
+        // Keeps the commit from being collected, for the next run's diff
+        let keep = format!("refs/indexed/{}", hash_ref(&git_ref));
+        if let Err(e) = git(&dir, &["update-ref", &keep, &commit]) {
+            error!("Failed to keep commit {} of {}: {}", commit, display, e);
+        }
+    }
+    state.insert(
+        git_ref.clone(),
+        IndexedRef {
+            commit: commit.clone(),
+            source: source.clone(),
+            globs: options.globs.clone(),
+        },
+    );
+    let saved = std::fs::create_dir_all(mirrors)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| Ok(std::fs::write(&state_path, serde_json::to_string_pretty(&state)?)?));
+    if let Err(e) = saved {
+        error!("Failed to save the state of {}: {}", display, e);
+    }
+    info!("Indexed {} files of {} at {} ({}) as source {}", indexed, display, git_ref, commit, source);
+    Ok(GitRepoResponse {
+        source,
+        git_ref,
+        commit,
+        previous_commit: previous.map(|previous| previous.commit),
+        indexed,
+        deleted,
+        errors,
+    })
+}
+
+// The file's page on the forges that serve files at "<repo>/blob/<ref>/<path>"
+fn browse_url(repo: &str, git_ref: &str, path: &str) -> Option<String> {
+    let url = Url::parse(repo).ok().filter(|url| url.scheme() == "https")?;
+    let forge = matches!(url.host_str()?, "github.com" | "gitlab.com" | "codeberg.org");
+    let repo = repo.trim_end_matches(".git");
+    forge.then(|| format!("{}/blob/{}/{}", repo, git_ref, path))
+}
+
+// Runs git in `dir` without prompting for credentials, and never with the
+// ext:: transport, which runs commands. The output, or what git said on
+// failure.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "protocol.ext.allow=never"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("couldn't run git ({}); is it installed?", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn not_a_repo(repo: &str, error: String) -> McpError {
+    McpError::validation(Some("repo"), format!("{} is not a Git repository: {}", repo, error))
+}
+
+fn resolve_commit(dir: &Path, git_ref: &str) -> Result<String, McpError> {
+    git(dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", git_ref)])
+        .map(|commit| commit.trim().to_string())
+        .map_err(|_| McpError::not_found(format!("ref {}", git_ref)))
+}
+
+// The branch the remote's HEAD points to
+fn default_branch(dir: &Path) -> Result<String, String> {
+    let heads = git(dir, &["ls-remote", "--symref", "origin", "HEAD"])?;
+    heads
+        .lines()
+        .find_map(|line| line.strip_prefix("ref: refs/heads/")?.split_once('\t').map(|(branch, _)| branch.to_string()))
+        .ok_or_else(|| "the remote has no default branch".to_string())
+}
+
+// Refs may hold characters a ref name can't; the mirror keeps them by hash
+fn hash_ref(git_ref: &str) -> String {
+    sha256_hex(git_ref.as_bytes())[..16].to_string()
+}
+
+// The files' text at the commit, read with one `git cat-file --batch`
+type FileText = (String, Result<String, String>);
+
+fn read_files(dir: &Path, commit: &str, paths: &[String]) -> Result<Vec<FileText>, McpError> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let failed = |e: std::io::Error| McpError::internal(format!("git cat-file: {}", e));
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(failed)?;
+    let mut stdin = child.stdin.take().ok_or_else(|| McpError::internal("git cat-file has no input"))?;
+    let requests: String = paths.iter().map(|path| format!("{}:{}\n", commit, path)).collect();
+    // Written from another thread so a full output pipe can't stall both sides
+    let writer = std::thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let stdout = child.stdout.take().ok_or_else(|| McpError::internal("git cat-file has no output"))?;
+    let mut stdout = BufReader::new(stdout);
+    let mut files = Vec::new();
+    for path in paths {
+        let mut header = String::new();
+        stdout.read_line(&mut header).map_err(failed)?;
+        // "<object> blob <size>", or "<name> missing"
+        let mut parts = header.split_whitespace();
+        let (kind, size) = (parts.nth(1), parts.next().and_then(|size| size.parse::<usize>().ok()));
+        let Some(size) = size else {
+            files.push((path.clone(), Err("not a file at this commit".to_string())));
+            continue;
+        };
+        let mut content = vec![0; size + 1];
+        stdout.read_exact(&mut content).map_err(failed)?;
+        content.pop();
+        let text = if kind != Some("blob") {
+            Err("not a file at this commit".to_string())
+        } else if size > MAX_FILE_BYTES {
+            Err(format!("larger than {} bytes", MAX_FILE_BYTES))
+        } else {
+            Ok(String::from_utf8_lossy(&content).into_owned())
+        };
+        files.push((path.clone(), text));
+    }
+    let _ = writer.join();
+    let _ = child.wait();
+    Ok(files)
+}
+
+// Glob matching against paths from the repository's root: `*` and `?`
+// within a folder, `**` across any number of them, `{a,b}` alternatives
+fn glob_matches(glob: &str, path: &str) -> bool {
+    expand_braces(glob.trim().trim_start_matches('/')).iter().any(|glob| {
+        let globs: Vec<&str> = glob.split('/').collect();
+        let parts: Vec<&str> = path.split('/').collect();
+        segments_match(&globs, &parts)
+    })
+}
+
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(close) = glob[open..].find('}').map(|close| open + close) else {
+        return vec![glob.to_string()];
+    };
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|choice| expand_braces(&format!("{}{}{}", &glob[..open], choice, &glob[close + 1..])))
+        .collect()
+}
+
+fn segments_match(globs: &[&str], parts: &[&str]) -> bool {
+    match globs.split_first() {
+        None => parts.is_empty(),
+        Some((&"**", rest)) => (0..=parts.len()).any(|skip| segments_match(rest, &parts[skip..])),
+        Some((glob, rest)) => {
+            parts.split_first().is_some_and(|(part, parts)| wildcard_match(glob, part) && segments_match(rest, parts))
+        }
+    }
+}
+
+fn wildcard_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // matched[j]: the glob so far matches the first j characters
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for c in glob {
+        let mut next = vec![false; text.len() + 1];
+        for j in 0..=text.len() {
+            next[j] = match c {
+                '*' => matched[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && text[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
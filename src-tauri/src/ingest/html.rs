@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::{Node, NodeRef};
+
+use super::markdown;
+use crate::settings::SourceExtraction;
+
+// Never part of the readable text
+const SKIPPED_ELEMENTS: &str = "script, style, noscript, template, svg, iframe, nav, header, footer, aside, form";
+
+// Landmark roles that mark page chrome rather than content
+const SKIPPED_ROLES: &str = "[role=navigation], [role=banner], [role=contentinfo], [role=complementary], [role=search]";
+
+// Where a page's content usually lives, tried in order before scoring the
+// body's blocks to find it
+const CONTENT_ROOTS: &[&str] = &["main", "article", "[role=main]"];
+
+// class/id fragments of page chrome. Elements matching them are dropped
+// unless they also look like content (`MAYBE_CONTENT_HINTS`).
+const UNLIKELY_HINTS: &[&str] = &[
+    "ad-break", "agegate", "banner", "breadcrumb", "combx", "comment", "community", "cookie", "disqus", "extra",
+    "footer", "gdpr", "header", "legends", "menu", "nav", "pager", "pagination", "popup", "related", "remark",
+    "replies", "rss", "share", "shoutbox", "sidebar", "skyscraper", "social", "sponsor", "supplemental", "toc",
+];
+const MAYBE_CONTENT_HINTS: &[&str] = &["and", "article", "body", "column", "content", "main", "shadow"];
+
+// Never dropped for their class or id: links (e.g. "header-anchor") and
+// the content itself
+const NEVER_UNLIKELY: &[&str] = &[
+    "a", "article", "body", "code", "h1", "h2", "h3", "h4", "h5", "h6", "html", "main", "pre", "table",
+];
+
+// class/id fragments that make a block more or less likely to be the content
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "body", "content", "doc", "entry", "main", "markdown", "page", "post", "prose", "story", "text",
+];
+const NEGATIVE_HINTS: &[&str] = &[
+    "banner", "combx", "comment", "contact", "foot", "hidden", "masthead", "media", "meta", "promo", "related",
+    "scroll", "share", "sidebar", "sponsor", "tags", "tool", "widget",
+];
+
+// Blocks whose text is scored; their ancestors collect the scores
+const SCORED_BLOCKS: &str = "p, pre, td, blockquote, dd, li";
+
+// Shorter blocks don't count as content
+const MIN_BLOCK_CHARS: usize = 25;
+
+pub struct ExtractedPage {
+    // Empty when the page has neither a <title> nor an <h1>
+    pub title: String,
+    pub markdown: String,
+    // Every `<a href>` on the page, navigation included, as written
+    pub links: Vec<String>,
+}
+
+// Pulls the title and the content, as Markdown, out of an HTML page. Page
+// chrome (navigation, sidebars, footers) is left out; headings, code blocks
+// and tables survive. `overrides` come from the source's extraction settings.
+pub fn extract(html: &str, overrides: Option<&SourceExtraction>) -> ExtractedPage {
+    let document = kuchikiki::parse_html().one(html);
+
+    let title = ["title", "h1"]
+        .iter()
+        .filter_map(|selector| document.select_first(selector).ok())
+        .map(|element| collapse_whitespace(&element.text_contents()))
+        .find(|title| !title.is_empty())
+        .unwrap_or_default();
+
+    let links = match document.select("a[href]") {
+        Ok(anchors) => anchors
+            .filter_map(|anchor| anchor.attributes.borrow().get("href").map(str::to_string))
+            .collect(),
+        Err(()) => Vec::new(),
+    };
+
+    remove_all(&document, SKIPPED_ELEMENTS);
+    remove_all(&document, SKIPPED_ROLES);
+    if let Some(overrides) = overrides {
+        for selector in &overrides.remove {
+            remove_all(&document, selector);
+        }
+    }
+
+    // A selector that matches nothing on this page falls back to detection
+    let root = overrides
+        .and_then(|overrides| overrides.content.as_deref())
+        .and_then(|selector| document.select_first(selector).ok())
+        .map(|element| element.as_node().clone())
+        .unwrap_or_else(|| main_content(&document));
+
+    ExtractedPage {
+        title,
+        markdown: markdown::render(&root),
+        links,
+    }
+}
+
+// Checks a selector from the extraction settings
+pub fn is_valid_selector(selector: &str) -> bool {
+    kuchikiki::Selectors::compile(selector).is_ok()
+}
+
+fn remove_all(root: &NodeRef, selector: &str) {
+    if let Ok(matches) = root.select(selector) {
+        // Collected first, detaching while iterating would cut the walk short
+        let matches: Vec<NodeRef> = matches.map(|element| element.as_node().clone()).collect();
+        for node in matches {
+            node.detach();
+        }
+    }
+}
+
+// The semantic content element if the page has one, else the body block
+// that scores best, in the spirit of Readability
+fn main_content(document: &NodeRef) -> NodeRef {
+    remove_unlikely(document);
+    if let Some(root) = CONTENT_ROOTS.iter().find_map(|selector| document.select_first(selector).ok()) {
+        return root.as_node().clone();
+    }
+    let body = document
+        .select_first("body")
+        .map(|body| body.as_node().clone())
+        .unwrap_or_else(|_| document.clone());
+    best_candidate(&body).unwrap_or(body)
+}
+
+fn remove_unlikely(document: &NodeRef) {
+    let unlikely: Vec<NodeRef> = document
+        .descendants()
+        .filter(|node| {
+            let Some(element) = node.as_element() else {
+                return false;
+            };
+            if NEVER_UNLIKELY.contains(&element.name.local.as_ref()) {
+                return false;
+            }
+            // Highlighters mark up code with classes like "hljs-comment"
+            let in_code = node.ancestors().any(|ancestor| {
+                ancestor
+                    .as_element()
+                    .is_some_and(|element| matches!(element.name.local.as_ref(), "pre" | "code"))
+            });
+            if in_code {
+                return false;
+            }
+            let hints = hints_of(node);
+            has_any(&hints, UNLIKELY_HINTS) && !has_any(&hints, MAYBE_CONTENT_HINTS)
+        })
+        .collect();
+    for node in unlikely {
+        node.detach();
+    }
+}
+
+// Each text block scores for its length and commas; its parent gets the
+// score and its grandparent half of it. The winner is the ancestor with the
+// best score after discounting link-heavy text.
+fn best_candidate(body: &NodeRef) -> Option<NodeRef> {
+    let mut candidates: Vec<(NodeRef, f64)> = Vec::new();
+    let mut index: HashMap<*const Node, usize> = HashMap::new();
+
+    let blocks: Vec<NodeRef> = body.select(SCORED_BLOCKS).ok()?.map(|block| block.as_node().clone()).collect();
+    for block in blocks {
+        let text = collapse_whitespace(&block.text_contents());
+        let length = text.chars().count();
+        if length < MIN_BLOCK_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let ancestors = block.ancestors().take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            if ancestor.as_element().is_none() {
+                break;
+            }
+            let key: *const Node = &*ancestor;
+            let slot = *index.entry(key).or_insert_with(|| {
+                candidates.push((ancestor.clone(), initial_score(&ancestor)));
+                candidates.len() - 1
+            });
+            candidates[slot].1 += if level == 0 { score } else { score / 2.0 };
+        }
+    }
+
+    candidates
+        .into_iter()
+        .map(|(node, score)| {
+            let adjusted = score * (1.0 - link_density(&node));
+            (node, adjusted)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(node, _)| node)
+}
+
+fn initial_score(node: &NodeRef) -> f64 {
+    let tag = match node.as_element().map(|element| element.name.local.as_ref().to_string()) {
+        Some(name) => match name.as_str() {
+            "div" | "section" => 5.0,
+            "pre" | "td" | "blockquote" => 3.0,
+            "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+            _ => 0.0,
+        },
+        None => 0.0,
+    };
+    let hints = hints_of(node);
+    let mut weight = 0.0;
+    if has_any(&hints, POSITIVE_HINTS) {
+        weight += 25.0;
+    }
+    if has_any(&hints, NEGATIVE_HINTS) {
+        weight -= 25.0;
+    }
+    tag + weight
+}
+
+// Share of the text that sits inside links
+fn link_density(node: &NodeRef) -> f64 {
+    let total = node.text_contents().chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = match node.select("a") {
+        Ok(links) => links.map(|link| link.text_contents().chars().count()).sum(),
+        Err(()) => 0,
+    };
+    linked as f64 / total as f64
+}
+
+// class and id, lowercased, for matching against the hint lists
+fn hints_of(node: &NodeRef) -> String {
+    let Some(element) = node.as_element() else {
+        return String::new();
+    };
+    let attributes = element.attributes.borrow();
+    let class = attributes.get("class").unwrap_or_default();
+    let id = attributes.get("id").unwrap_or_default();
+    format!("{} {}", class, id).to_ascii_lowercase()
+}
+
+fn has_any(hints: &str, fragments: &[&str]) -> bool {
+    fragments.iter().any(|fragment| hints.contains(fragment))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use url::Url;
+
+use super::{directory_url, Fetcher};
+use crate::McpError;
+
+// Inventories of the largest projects decompress to a few MB
+const MAX_INVENTORY_BYTES: u64 = 64 * 1024 * 1024;
+
+// What a Sphinx `objects.inv` lists
+pub struct Inventory {
+    // From the header; None when it's blank
+    pub version: Option<String>,
+    // Pages documenting the objects, in inventory order without duplicates
+    pub pages: Vec<Url>,
+}
+
+// Reads the inventory at `url` and collects the pages its objects are
+// documented on. `domains` keeps only objects of those Sphinx domains
+// (e.g. "py", "std"); empty keeps all of them. Stops after `limit` pages.
+pub async fn inventory_pages(
+    fetcher: &Fetcher,
+    url: &Url,
+    domains: &[String],
+    limit: usize,
+) -> Result<Inventory, McpError> {
+    let data = fetcher.fetch_bytes(url).await?;
+    parse(&data, url, domains, limit).map_err(|e| McpError::fetch(url.as_str(), e))
+}
+
+// The URL of the inventory for `url`, which may name the inventory itself,
+// the root of the docs (e.g. https://requests.readthedocs.io/en/latest) or
+// a page at the root such as index.html
+pub fn inventory_url(url: &Url) -> Url {
+    if url.path().ends_with(".inv") {
+        return url.clone();
+    }
+    let root = directory_url(url);
+    root.join("objects.inv").unwrap_or(root)
+}
+
+// Version 2 inventories: four header lines, then zlib-compressed lines of
+// `name domain:role priority uri display-name`
+fn parse(data: &[u8], base: &Url, domains: &[String], limit: usize) -> Result<Inventory, String> {
+    let mut header = Vec::new();
+    let mut rest = data;
+    for _ in 0..4 {
+        let end = rest.iter().position(|&b| b == b'\n').ok_or("not a Sphinx objects.inv inventory")?;
+        header.push(String::from_utf8_lossy(&rest[..end]).trim().to_string());
+        rest = &rest[end + 1..];
+    }
+    match header[0].as_str() {
+        "# Sphinx inventory version 2" => {}
+        "# Sphinx inventory version 1" => return Err("version 1 inventories are not supported".to_string()),
+        _ => return Err("not a Sphinx objects.inv inventory".to_string()),
+    }
+    if !header[3].contains("zlib") {
+        return Err("inventory is not zlib-compressed".to_string());
+    }
+    let version = header[2]
+        .strip_prefix("# Version:")
+        .map(str::trim)
+        .filter(|version| !version.is_empty())
+        .map(str::to_string);
+
+    let mut entries = Vec::new();
+    ZlibDecoder::new(rest)
+        .take(MAX_INVENTORY_BYTES)
+        .read_to_end(&mut entries)
+        .map_err(|e| format!("inventory is corrupt: {}", e))?;
+
+    let mut seen = HashSet::new();
+    let mut pages = Vec::new();
+    for line in String::from_utf8_lossy(&entries).lines() {
+        if pages.len() >= limit {
+            break;
+        }
+        let Some((name, domain, uri)) = parse_entry(line) else {
+            continue;
+        };
+        if !domains.is_empty() && !domains.iter().any(|wanted| wanted == domain) {
+            continue;
+        }
+        // A trailing `$` stands for the object's name, usually as the anchor
+        let uri = match uri.strip_suffix('$') {
+            Some(prefix) => format!("{}{}", prefix, name),
+            None => uri.to_string(),
+        };
+        let Ok(mut page) = base.join(&uri) else {
+            continue;
+        };
+        page.set_fragment(None);
+        if matches!(page.scheme(), "http" | "https") && seen.insert(page.to_string()) {
+            pages.push(page);
+        }
+    }
+    Ok(Inventory { version, pages })
+}
+
+// The name, domain and URI of one entry. Names (e.g. of std:label entries)
+// can contain spaces, so the entry is found by its `domain:role priority`
+// pair rather than by position.
+fn parse_entry(line: &str) -> Option<(&str, &str, &str)> {
+    let tokens: Vec<(usize, &str)> = line
+        .split_whitespace()
+        .map(|token| (token.as_ptr() as usize - line.as_ptr() as usize, token))
+        .collect();
+    let at = (1..tokens.len().saturating_sub(2)).find(|&i| {
+        tokens[i].1.contains(':') && tokens[i + 1].1.trim_start_matches('-').parse::<u32>().is_ok()
+    })?;
+    let name = line[..tokens[at].0].trim_end();
+    let domain = tokens[at].1.split(':').next()?;
+    Some((name, domain, tokens[at + 2].1))
+}
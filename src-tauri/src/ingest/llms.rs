@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use futures_util::stream::{self, StreamExt};
+use url::Url;
+
+use super::docsite::Section;
+use super::{html, Fetcher};
+use crate::McpError;
+
+// Full-text files of big sites run to tens of MB
+const MAX_LLMS_BYTES: usize = 64 * 1024 * 1024;
+
+// A site's llms.txt files (https://llmstxt.org), found and read
+pub(super) struct LlmsSite {
+    // "llms-full.txt" or "llms.txt"
+    pub(super) file: &'static str,
+    // The H1 the file opens with
+    pub(super) name: Option<String>,
+    pub(super) sections: Vec<Section>,
+}
+
+// Looks for llms-full.txt, then llms.txt, next to the docs and then at the
+// site's root. llms-full.txt is the docs themselves, split at its page
+// headings; llms.txt lists the pages, which are downloaded one by one.
+// None when the site has neither.
+pub(super) async fn llms_site(
+    fetcher: &Fetcher,
+    root: &Url,
+    max_pages: usize,
+    concurrency: usize,
+    errors: &mut Vec<String>,
+) -> Result<Option<LlmsSite>, McpError> {
+    let mut bases = vec![root.clone()];
+    if root.path() != "/" {
+        bases.extend(root.join("/").ok());
+    }
+    for base in &bases {
+        if let Some((url, text)) = llms_file(fetcher, base, "llms-full.txt").await {
+            let (name, sections) = full_sections(&url, &text);
+            if !sections.is_empty() {
+                return Ok(Some(LlmsSite {
+                    file: "llms-full.txt",
+                    name,
+                    sections,
+                }));
+            }
+        }
+        if let Some((url, text)) = llms_file(fetcher, base, "llms.txt").await {
+            let (name, links) = index_links(&url, &text);
+            if !links.is_empty() {
+                let sections = linked_pages(fetcher, links, max_pages, concurrency, errors).await;
+                return Ok(Some(LlmsSite {
+                    file: "llms.txt",
+                    name,
+                    sections,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// The file's URL and text, if the site serves it. Sites that answer every
+// path with their app's HTML don't count.
+async fn llms_file(fetcher: &Fetcher, base: &Url, name: &str) -> Option<(Url, String)> {
+    let url = base.join(name).ok()?;
+    let bytes = fetcher.fetch_if_exists(&url, MAX_LLMS_BYTES).await.ok()??;
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    let start = text.trim_start();
+    (!start.is_empty() && !start.starts_with('<')).then_some((url, text))
+}
+
+// Splits llms-full.txt at its page headings: the level with the most of
+// them, H1 or H2, since some files put the site's name alone in an H1. A
+// "Source: <url>" line under a heading gives the page's address.
+fn full_sections(url: &Url, text: &str) -> (Option<String>, Vec<Section>) {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut headings = Vec::new();
+    let mut in_code = false;
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("# ") {
+            headings.push((index, 1, heading.trim()));
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            headings.push((index, 2, heading.trim()));
+        }
+    }
+    let count = |level| headings.iter().filter(|(_, l, _)| *l == level).count();
+    let level = if count(1) >= 2 || count(2) == 0 { 1 } else { 2 };
+    let name = headings
+        .iter()
+        .find(|(_, l, _)| *l == 1)
+        .filter(|_| level == 2)
+        .map(|(_, _, heading)| heading.to_string());
+
+    let starts: Vec<&(usize, usize, &str)> = headings.iter().filter(|(_, l, _)| *l == level).collect();
+    let mut sections = Vec::new();
+    let mut ids = HashSet::new();
+    for (position, (start, _, heading)) in starts.iter().enumerate() {
+        let end = starts.get(position + 1).map_or(lines.len(), |(end, _, _)| *end);
+        let mut body: Vec<&str> = lines[start + 1..end].to_vec();
+        let source = body
+            .iter()
+            .take(3)
+            .position(|line| line.starts_with("Source: ") || line.starts_with("URL: "))
+            .and_then(|at| {
+                let address = body[at].split_once(": ")?.1.trim();
+                let address = url.join(address).ok()?;
+                body.remove(at);
+                Some(address)
+            });
+        let body = body.join("\n").trim().to_string();
+        if body.is_empty() {
+            continue;
+        }
+        let mut id = source.map_or_else(|| format!("{}#{}", url, anchor(heading)), |source| source.to_string());
+        // Pages can share a title, or an address
+        if !ids.insert(id.clone()) {
+            id = format!("{}#{}", url, position + 1);
+            ids.insert(id.clone());
+        }
+        sections.push(Section {
+            id,
+            breadcrumbs: vec![heading.to_string()],
+            body,
+            version: None,
+        });
+    }
+    (name, sections)
+}
+
+// llms.txt: an H1 with the site's name, then H2 sections listing pages as
+// "- [Title](url): notes". Each link with its section's heading.
+fn index_links(url: &Url, text: &str) -> (Option<String>, Vec<(Url, Vec<String>)>) {
+    let mut name = None;
+    let mut section = None;
+    let mut links = Vec::new();
+    let mut seen = HashSet::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix("# ") {
+            name = name.or_else(|| Some(heading.trim().to_string()));
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            section = Some(heading.trim().to_string());
+        } else if let Some(link) = line.strip_prefix("- [").or_else(|| line.strip_prefix("* [")) {
+            let Some((title, rest)) = link.split_once("](") else {
+                continue;
+            };
+            let Some((address, _)) = rest.split_once(')') else {
+                continue;
+            };
+            let Ok(address) = url.join(address.trim()) else {
+                continue;
+            };
+            if matches!(address.scheme(), "http" | "https") && seen.insert(address.to_string()) {
+                let breadcrumbs = section.iter().cloned().chain([title.trim().to_string()]).collect();
+                links.push((address, breadcrumbs));
+            }
+        }
+    }
+    (name, links)
+}
+
+// The pages llms.txt links to, usually Markdown; HTML pages are extracted
+// as the crawler extracts them
+async fn linked_pages(
+    fetcher: &Fetcher,
+    mut links: Vec<(Url, Vec<String>)>,
+    max_pages: usize,
+    concurrency: usize,
+    errors: &mut Vec<String>,
+) -> Vec<Section> {
+    if links.len() > max_pages {
+        errors.push(format!("only the first {} of {} linked pages were read", max_pages, links.len()));
+        links.truncate(max_pages);
+    }
+    let mut fetched = stream::iter(links)
+        .map(|(url, breadcrumbs)| async move {
+            let result = fetcher.fetch(&url).await;
+            (url, breadcrumbs, result)
+        })
+        .buffered(concurrency.max(1));
+    let mut sections = Vec::new();
+    while let Some((url, breadcrumbs, result)) = fetched.next().await {
+        let page = match result {
+            Ok(page) => page,
+            Err(e) => {
+                errors.push(format!("{}: {}", url, e));
+                continue;
+            }
+        };
+        let body = if page.content_type.contains("html") {
+            html::extract(&page.body, None).markdown
+        } else {
+            page.body
+        };
+        sections.push(Section {
+            id: url.to_string(),
+            breadcrumbs,
+            body: body.trim().to_string(),
+            version: None,
+        });
+    }
+    sections
+}
+
+// GitHub-style heading anchors: lowercase words joined by hyphens
+fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use url::Url;
+
+use super::roff;
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Pages are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 500;
+
+// Where man pages are installed when MANPATH doesn't say
+const DEFAULT_MAN_PATHS: &[&str] = &["/usr/local/share/man", "/usr/share/man", "/opt/homebrew/share/man"];
+
+// No man page comes close; anything bigger isn't one
+const MAX_PAGE_BYTES: u64 = 8 * 1024 * 1024;
+
+const DEFAULT_SOURCE: &str = "man";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ManPagesOptions {
+    /// Sections to index, e.g. ["1", "8"]; all of them when empty
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Only these pages, e.g. ["git-commit", "tar"]; all of them when empty
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// Man folders to read (the ones holding man1, man2, ...); defaults to
+    /// those in $MANPATH, or the usual system ones
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Source to file the pages under; defaults to "man"
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManPagesResponse {
+    pub source: String,
+    /// The man folders that were read
+    pub paths: Vec<String>,
+    pub indexed: usize,
+    /// Pages that couldn't be read or indexed, as "page: reason"
+    pub errors: Vec<String>,
+}
+
+// A page as installed, e.g. man1/ls.1.gz is "ls" in section "1"
+struct PageFile {
+    name: String,
+    section: String,
+    path: PathBuf,
+}
+
+// Indexes the installed man pages one document per page, as Markdown with
+// the page's sections as headings. A page that only includes another
+// (.so man1/other.1) is an alias and skipped, unless asked for by name.
+// Blocking; run it off the async runtime. `on_progress` gets (pages read,
+// total).
+pub fn index_man_pages(
+    search_service: &SearchService,
+    options: &ManPagesOptions,
+    on_progress: impl Fn(usize, usize),
+) -> Result<ManPagesResponse, McpError> {
+    let roots: Vec<PathBuf> = if options.paths.is_empty() {
+        default_roots()
+    } else {
+        options.paths.iter().map(|path| PathBuf::from(path.trim())).collect()
+    };
+    let roots: Vec<PathBuf> = roots.into_iter().filter(|root| root.is_dir()).collect();
+    if roots.is_empty() {
+        return Err(McpError::validation(Some("paths"), "No man page folders found"));
+    }
+    let source = options.source.clone().unwrap_or_else(|| DEFAULT_SOURCE.to_string());
+
+    let mut errors = Vec::new();
+    let mut documents = Vec::new();
+    let mut indexed = 0;
+    // The same page can be installed under several roots, or linked under
+    // several names; the first one wins
+    let mut seen_pages = HashSet::new();
+    let mut seen_files = HashSet::new();
+    let pages: Vec<(&PathBuf, PageFile)> = roots
+        .iter()
+        .flat_map(|root| page_files(root, options).into_iter().map(move |page| (root, page)))
+        .collect();
+    let total = pages.len();
+    for (done, (root, page)) in pages.into_iter().enumerate() {
+        on_progress(done, total);
+        if !seen_pages.insert((page.name.clone(), page.section.clone())) {
+            continue;
+        }
+        if let Ok(canonical) = std::fs::canonicalize(&page.path) {
+            if !seen_files.insert(canonical) {
+                continue;
+            }
+        }
+        let label = format!("{}({})", page.name, page.section);
+        let roff_source = match read_page(&page.path) {
+            Ok(roff_source) => roff_source,
+            Err(e) => {
+                errors.push(format!("{}: {}", label, e));
+                continue;
+            }
+        };
+        let roff_source = match alias_target(&roff_source) {
+            Some(_) if options.names.is_empty() => continue,
+            Some(target) => match read_included(root, &page.path, target) {
+                Ok(roff_source) => roff_source,
+                Err(e) => {
+                    errors.push(format!("{}: {}: {}", label, target, e));
+                    continue;
+                }
+            },
+            None => expand_includes(root, &page.path, &roff_source),
+        };
+
+        let converted = roff::convert(&roff_source);
+        if converted.markdown.trim().is_empty() {
+            errors.push(format!("{}: no text", label));
+            continue;
+        }
+        let title = match &converted.description {
+            Some(description) => format!("{} — {}", label, description),
+            None => label.clone(),
+        };
+        let document = SearchableDocument {
+            id: format!("man:{}", label),
+            title,
+            body: converted.markdown,
+            source: source.clone(),
+            version: options.version.clone(),
+            // The page's roff source, for lack of anything to browse
+            url: Url::from_file_path(&page.path).ok().map(String::from),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: None,
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: None,
+            content_hash: None,
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", label, e)),
+        }
+        if documents.len() >= INDEX_BATCH_SIZE {
+            indexed += documents.len();
+            search_service
+                .add_documents(std::mem::take(&mut documents), |_, _| {})
+                .map_err(|e| McpError::index("Failed to add man pages", e))?;
+        }
+    }
+    if !documents.is_empty() {
+        indexed += documents.len();
+        search_service
+            .add_documents(documents, |_, _| {})
+            .map_err(|e| McpError::index("Failed to add man pages", e))?;
+    }
+    on_progress(total, total);
+    info!("Indexed {} man pages as source {}", indexed, source);
+    Ok(ManPagesResponse {
+        source,
+        paths: roots.iter().map(|root| root.display().to_string()).collect(),
+        indexed,
+        errors,
+    })
+}
+
+// The folders in $MANPATH, where an empty entry stands for the defaults
+fn default_roots() -> Vec<PathBuf> {
+    let defaults = || DEFAULT_MAN_PATHS.iter().map(PathBuf::from);
+    match std::env::var("MANPATH") {
+        Ok(manpath) if !manpath.trim().is_empty() => {
+            let mut roots = Vec::new();
+            for entry in manpath.split(':') {
+                if entry.is_empty() {
+                    roots.extend(defaults());
+                } else {
+                    roots.push(PathBuf::from(entry));
+                }
+            }
+            roots
+        }
+        _ => defaults().collect(),
+    }
+}
+
+// The pages in the root's manN folders that the options ask for, sorted.
+// Translations live in folders per locale next to those and are left out.
+fn page_files(root: &Path, options: &ManPagesOptions) -> Vec<PageFile> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut folders: Vec<(String, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let folder = entry.file_name().to_string_lossy().into_owned();
+            let section = folder.strip_prefix("man")?.to_string();
+            (!section.is_empty()).then(|| (section, entry.path()))
+        })
+        .collect();
+    folders.sort();
+
+    let mut pages = Vec::new();
+    for (folder_section, folder) in folders {
+        let Ok(entries) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries.filter_map(Result::ok).map(|entry| entry.path()).collect();
+        files.sort();
+        for path in files {
+            let Some((name, section)) = page_name(&path) else {
+                continue;
+            };
+            let wanted_section = options.sections.is_empty()
+                || options.sections.iter().any(|wanted| {
+                    let wanted = wanted.trim();
+                    wanted.eq_ignore_ascii_case(&section) || wanted.eq_ignore_ascii_case(&folder_section)
+                });
+            let wanted_name = options.names.is_empty() || options.names.iter().any(|wanted| wanted.trim() == name);
+            if wanted_section && wanted_name {
+                pages.push(PageFile { name, section, path });
+            }
+        }
+    }
+    pages
+}
+
+// "ls.1.gz" is ls in section 1, "CA.pl.1ssl" is CA.pl in section 1ssl
+fn page_name(path: &Path) -> Option<(String, String)> {
+    let file_name = path.file_name()?.to_str()?;
+    let file_name = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    let (name, section) = file_name.rsplit_once('.')?;
+    // Sections are numbered, apart from n (Tcl) and l (local)
+    let is_section = section.chars().next().is_some_and(|c| c.is_ascii_digit() || c == 'n' || c == 'l');
+    (!name.is_empty() && is_section).then(|| (name.to_string(), section.to_string()))
+}
+
+fn read_page(path: &Path) -> std::io::Result<String> {
+    let file = std::fs::File::open(path)?.take(MAX_PAGE_BYTES);
+    let mut bytes = Vec::new();
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        GzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        let mut file = file;
+        file.read_to_end(&mut bytes)?;
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// The page a page is an alias of: its only request is `.so man1/other.1`
+fn alias_target(roff_source: &str) -> Option<&str> {
+    let mut lines = roff_source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(".\\\"") && !line.starts_with("'\\\""));
+    let target = lines.next()?.strip_prefix(".so")?.trim();
+    (lines.next().is_none() && !target.is_empty()).then_some(target)
+}
+
+// .so lines replaced by the file they name; one level deep, which is as
+// deep as man pages go
+fn expand_includes(root: &Path, page: &Path, roff_source: &str) -> String {
+    if !roff_source.lines().any(|line| line.starts_with(".so ")) {
+        return roff_source.to_string();
+    }
+    roff_source
+        .lines()
+        .map(|line| match line.strip_prefix(".so ") {
+            Some(target) => read_included(root, page, target.trim()).unwrap_or_default(),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Include paths are relative to the man folder (man1/other.1), or to the
+// page's own folder; the file may be compressed either way
+fn read_included(root: &Path, page: &Path, target: &str) -> std::io::Result<String> {
+    let folder = page.parent().unwrap_or(root);
+    let candidates = [root.join(target), folder.join(target)];
+    for candidate in &candidates {
+        let compressed = PathBuf::from(format!("{}.gz", candidate.display()));
+        for path in [candidate, &compressed] {
+            if path.is_file() && path.canonicalize()?.starts_with(root.canonicalize()?) {
+                return read_page(path);
+            }
+        }
+    }
+    Err(std::io::Error::new(std::io::ErrorKind::NotFound, "included page not found"))
+}
@@ -0,0 +1,297 @@
+use kuchikiki::NodeRef;
+
+// Elements that start a block of their own; everything else is inline
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "dd", "details", "div", "dl", "dt", "figcaption", "figure", "h1",
+    "h2", "h3", "h4", "h5", "h6", "hr", "li", "main", "ol", "p", "pre", "section", "summary", "table", "ul",
+];
+
+// Elements nested deeper than this are rendered as their plain text. Each
+// level is a level of recursion, and a crawled page can nest thousands.
+const MAX_DEPTH: usize = 64;
+
+// Renders an extracted content element as Markdown: headings keep their
+// level, code blocks become fenced blocks tagged with their language, and
+// tables become pipe tables. Links keep only their text.
+pub fn render(root: &NodeRef) -> String {
+    blocks_of(root, 0).join("\n\n")
+}
+
+// Renders a run of sibling nodes, e.g. one section of a page, as `render`
+// renders the children of an element
+pub fn render_nodes<'a>(nodes: impl IntoIterator<Item = &'a NodeRef>) -> String {
+    blocks_in(nodes.into_iter().cloned(), 0).join("\n\n")
+}
+
+// The Markdown blocks among `node`'s children; loose inline content
+// between blocks becomes a paragraph
+fn blocks_of(node: &NodeRef, depth: usize) -> Vec<String> {
+    if depth >= MAX_DEPTH {
+        let text = tidy(&node.text_contents());
+        return if text.is_empty() { Vec::new() } else { vec![text] };
+    }
+    blocks_in(node.children(), depth + 1)
+}
+
+fn blocks_in(nodes: impl Iterator<Item = NodeRef>, depth: usize) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut paragraph = String::new();
+    for child in nodes {
+        if is_block(&child) {
+            push_paragraph(&mut blocks, &mut paragraph);
+            blocks.extend(block(&child, depth));
+        } else {
+            paragraph.push_str(&inline(&child, depth));
+        }
+    }
+    push_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, paragraph: &mut String) {
+    let text = tidy(paragraph);
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    paragraph.clear();
+}
+
+// Cleans up the spaces left where inline pieces meet, line by line since
+// <br> splits inline content into lines
+fn tidy(inline: &str) -> String {
+    let lines: Vec<String> = inline
+        .lines()
+        .map(|line| collapse_whitespace(line).trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+fn is_block(node: &NodeRef) -> bool {
+    node.as_element()
+        .is_some_and(|element| BLOCK_ELEMENTS.contains(&element.name.local.as_ref()))
+}
+
+fn tag(node: &NodeRef) -> &str {
+    node.as_element().map_or("", |element| element.name.local.as_ref())
+}
+
+// One block element; containers such as <div> may yield several blocks
+fn block(node: &NodeRef, depth: usize) -> Vec<String> {
+    let name = tag(node);
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(name.as_bytes()[1] - b'0');
+            let text = collapse_whitespace(&inline(node, depth));
+            let text = text.trim();
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![format!("{} {}", "#".repeat(level), text)]
+            }
+        }
+        "p" | "dt" | "summary" | "figcaption" | "address" => {
+            let text = tidy(&inline(node, depth));
+            match (text.is_empty(), name) {
+                (true, _) => Vec::new(),
+                (false, "dt") => vec![format!("**{}**", text)],
+                (false, _) => vec![text],
+            }
+        }
+        "pre" => vec![code_block(node)],
+        "ul" | "ol" => {
+            let list = list(node, name == "ol", depth);
+            if list.is_empty() {
+                Vec::new()
+            } else {
+                vec![list]
+            }
+        }
+        "blockquote" => {
+            let inner = blocks_of(node, depth).join("\n\n");
+            if inner.is_empty() {
+                return Vec::new();
+            }
+            let quoted: Vec<String> = inner
+                .lines()
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                .collect();
+            vec![quoted.join("\n")]
+        }
+        "table" => table(node, depth).into_iter().collect(),
+        "hr" => vec!["---".to_string()],
+        _ => blocks_of(node, depth),
+    }
+}
+
+fn code_block(pre: &NodeRef) -> String {
+    let code = pre.text_contents();
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    // A fence longer than any run of backticks in the code
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, code_language(pre).unwrap_or_default(), code, fence)
+}
+
+// From "language-rust" or "lang-rust" on the <pre> or its <code>, or
+// "highlight-rust" on the wrapper Sphinx puts around it
+fn code_language(pre: &NodeRef) -> Option<String> {
+    let mut nodes = vec![pre.clone()];
+    nodes.extend(pre.children().filter(|child| tag(child) == "code"));
+    nodes.extend(pre.parent());
+    nodes.iter().find_map(|node| {
+        let element = node.as_element()?;
+        let attributes = element.attributes.borrow();
+        let class = attributes.get("class")?;
+        class.split_whitespace().find_map(|class| {
+            ["language-", "lang-", "highlight-"]
+                .iter()
+                .find_map(|prefix| class.strip_prefix(prefix))
+                .filter(|language| !language.is_empty())
+                .map(str::to_string)
+        })
+    })
+}
+
+fn list(node: &NodeRef, ordered: bool, depth: usize) -> String {
+    let mut items = Vec::new();
+    for item in node.children().filter(|child| tag(child) == "li") {
+        let marker = if ordered {
+            format!("{}. ", items.len() + 1)
+        } else {
+            "- ".to_string()
+        };
+        let content = blocks_of(&item, depth + 1).join("\n");
+        if content.is_empty() {
+            continue;
+        }
+        // Continuation lines line up with the item's text
+        let indent = " ".repeat(marker.len());
+        let mut rendered = String::new();
+        for (i, line) in content.lines().enumerate() {
+            if i == 0 {
+                rendered.push_str(&marker);
+            } else {
+                rendered.push('\n');
+                if !line.is_empty() {
+                    rendered.push_str(&indent);
+                }
+            }
+            rendered.push_str(line);
+        }
+        items.push(rendered);
+    }
+    items.join("\n")
+}
+
+// The first row becomes the header row, whether or not it uses <th>
+fn table(node: &NodeRef, depth: usize) -> Option<String> {
+    let rows: Vec<Vec<String>> = node
+        .select("tr")
+        .ok()?
+        .map(|row| {
+            row.as_node()
+                .children()
+                .filter(|cell| matches!(tag(cell), "td" | "th"))
+                .map(|cell| collapse_whitespace(&inline(&cell, depth + 1)).trim().replace('|', "\\|"))
+                .collect::<Vec<String>>()
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max()?;
+
+    let line = |cells: &[String]| {
+        let mut line = String::from("|");
+        for i in 0..columns {
+            line.push(' ');
+            line.push_str(cells.get(i).map_or("", String::as_str));
+            line.push_str(" |");
+        }
+        line
+    };
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    Some(lines.join("\n"))
+}
+
+// Inline content with whitespace collapsed
+fn inline(node: &NodeRef, depth: usize) -> String {
+    if let Some(text) = node.as_text() {
+        return collapse_whitespace(&text.borrow());
+    }
+    if depth >= MAX_DEPTH {
+        return collapse_whitespace(&node.text_contents());
+    }
+    let children = || node.children().map(|child| inline(&child, depth + 1)).collect::<String>();
+    match tag(node) {
+        "br" => "\n".to_string(),
+        "code" | "kbd" | "samp" => code_span(&node.text_contents()),
+        "strong" | "b" => emphasize(&children(), "**"),
+        "em" | "i" => emphasize(&children(), "*"),
+        "img" => String::new(),
+        // Blocks nested in inline content, e.g. <p> inside a table cell
+        _ if is_block(node) => format!(" {} ", children()),
+        _ => children(),
+    }
+}
+
+fn code_span(code: &str) -> String {
+    let code = collapse_whitespace(code);
+    let code = code.trim();
+    if code.is_empty() {
+        String::new()
+    } else if code.contains('`') {
+        format!("`` {} ``", code)
+    } else {
+        format!("`{}`", code)
+    }
+}
+
+// Keeps surrounding spaces outside the markers, where Markdown wants them
+fn emphasize(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let before = if text.starts_with(char::is_whitespace) { " " } else { "" };
+    let after = if text.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{}{}{}{}", before, marker, trimmed, marker, after)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                collapsed.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            collapsed.push(c);
+            last_was_space = false;
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use kuchikiki::traits::TendrilSink;
+
+    use super::*;
+
+    // Crawled pages are untrusted; nesting only makes them render as text
+    #[test]
+    fn deep_nesting_does_not_overflow_the_stack() {
+        for tag in ["div", "b"] {
+            let html = format!("{}text{}", format!("<{}>", tag).repeat(5_000), format!("</{}>", tag).repeat(5_000));
+            let document = kuchikiki::parse_html().one(html);
+            assert!(render(&document).contains("text"));
+        }
+    }
+}
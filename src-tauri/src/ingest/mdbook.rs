@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+use url::Url;
+
+use super::sections::{heading_anchor, section_anchor};
+use super::{directory_url, Fetcher};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Sections are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 200;
+
+// Joins the levels of a section's place in the book, as mdBook's own
+// search results do
+const BREADCRUMB_SEPARATOR: &str = " » ";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MdbookOptions {
+    /// A published book (e.g. https://doc.rust-lang.org/book/) or a local
+    /// book folder with a book.toml or SUMMARY.md
+    pub location: String,
+    /// Source to file the sections under; defaults to the book's title
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MdbookResponse {
+    pub source: String,
+    pub indexed: usize,
+    /// Chapters or sections that couldn't be read or indexed, as "where: reason"
+    pub errors: Vec<String>,
+}
+
+struct Book {
+    title: Option<String>,
+    sections: Vec<Section>,
+    errors: Vec<String>,
+}
+
+struct Section {
+    // The section's URL, with an anchor for sections below a chapter's top
+    id: String,
+    // Chapter titles from the top of the book down, then the heading
+    breadcrumbs: Vec<String>,
+    body: String,
+}
+
+// Indexes an mdBook one document per section, titled with the section's
+// breadcrumbs. Published books are read from the search index mdBook
+// generates; local ones from SUMMARY.md and the chapter files.
+// `on_progress` gets (sections written, total).
+pub async fn index_mdbook(
+    fetcher: &Fetcher,
+    search_service: Arc<SearchService>,
+    options: MdbookOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<MdbookResponse, McpError> {
+    let location = options.location.trim();
+    let book = if location.starts_with("http://") || location.starts_with("https://") {
+        remote_book(fetcher, &Fetcher::parse_url(location)?).await?
+    } else {
+        let path = PathBuf::from(location);
+        tokio::task::spawn_blocking(move || local_book(&path))
+            .await
+            .map_err(|e| McpError::internal(format!("Reading task failed: {}", e)))??
+    };
+    let source = options.source.or(book.title).unwrap_or_else(|| "mdbook".to_string());
+
+    let mut errors = book.errors;
+    let mut documents = Vec::new();
+    for section in book.sections {
+        let document = SearchableDocument {
+            url: Some(section.id.clone()),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: None,
+            parent_id: None,
+            heading_path: section.breadcrumbs.clone(),
+            anchor: section_anchor(&section.id),
+            content_hash: None,
+            id: section.id,
+            title: section.breadcrumbs.join(BREADCRUMB_SEPARATOR),
+            body: section.body,
+            source: source.clone(),
+            version: options.version.clone(),
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", document.id, e)),
+        }
+    }
+
+    let indexed = documents.len();
+    tokio::task::spawn_blocking(move || {
+        let mut written = 0;
+        for batch in documents.chunks(INDEX_BATCH_SIZE) {
+            search_service.add_documents(batch.to_vec(), |done, _| on_progress(written + done, indexed))?;
+            written += batch.len();
+        }
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+    .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+    .map_err(|e| McpError::index("Failed to add book sections", e))?;
+    info!("Indexed {} sections of {} as source {}", indexed, location, source);
+    Ok(MdbookResponse {
+        source,
+        indexed,
+        errors,
+    })
+}
+
+// A published book, through the search index its pages load. Recent
+// mdBook versions give the index a hashed name the book's pages point to.
+async fn remote_book(fetcher: &Fetcher, location: &Url) -> Result<Book, McpError> {
+    let root = directory_url(location);
+
+    let mut title = None;
+    let mut candidates = Vec::new();
+    match fetcher.fetch(&root).await {
+        Ok(page) => {
+            title = menu_title(&page.body);
+            candidates.extend(search_index_script(&page.body).and_then(|script| page.url.join(&script).ok()));
+        }
+        Err(e) => warn!("Could not read the front page of {}: {}", root, e),
+    }
+    candidates.extend(["searchindex.json", "searchindex.js"].iter().filter_map(|name| root.join(name).ok()));
+
+    let mut last_error = None;
+    for candidate in candidates {
+        let index = match fetcher.fetch(&candidate).await {
+            Ok(page) => parse_search_index(&page.body),
+            Err(e) => {
+                last_error = Some(e);
+                continue;
+            }
+        };
+        if let Some(index) = index {
+            let sections = search_index_sections(&index, &root);
+            return Ok(Book {
+                title,
+                sections,
+                errors: Vec::new(),
+            });
+        }
+    }
+    Err(last_error.unwrap_or_else(|| McpError::fetch(root.as_str(), "no mdBook search index found")))
+}
+
+// The book title mdBook shows in its menu bar
+fn menu_title(html: &str) -> Option<String> {
+    let start = html.find("class=\"menu-title\"")?;
+    let rest = &html[start..];
+    let text = &rest[rest.find('>')? + 1..];
+    let title = text[..text.find('<')?].trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+// e.g. `searchindex-2f1c4ab0.js`, from a <script> tag or the variable
+// the search script reads it from
+fn search_index_script(html: &str) -> Option<String> {
+    html.match_indices("searchindex").find_map(|(start, _)| {
+        let end = html[start..].find(['"', '\'', ' ', '>'])? + start;
+        let script = &html[start..end];
+        script.ends_with(".js").then(|| script.to_string())
+    })
+}
+
+// searchindex.json, or searchindex.js, which wraps the same JSON in
+// `Object.assign(window.search, ...)` and, in newer versions, in a
+// single-quoted `JSON.parse('...')` string
+fn parse_search_index(body: &str) -> Option<Value> {
+    if let Some(start) = body.find("JSON.parse('") {
+        let quoted = &body[start + "JSON.parse('".len()..];
+        let quoted = &quoted[..quoted.rfind("')")?];
+        let mut json = String::with_capacity(quoted.len());
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            json.extend(if c == '\\' { chars.next() } else { Some(c) });
+        }
+        return serde_json::from_str(&json).ok();
+    }
+    let json = &body[body.find('{')?..=body.rfind('}')?];
+    serde_json::from_str(json).ok()
+}
+
+fn search_index_sections(index: &Value, root: &Url) -> Vec<Section> {
+    let urls = index["doc_urls"].as_array();
+    let Some(docs) = index["index"]["documentStore"]["docs"].as_object() else {
+        return Vec::new();
+    };
+    let mut sections: Vec<(usize, Section)> = docs
+        .iter()
+        .filter_map(|(key, doc)| {
+            let position: usize = key.parse().ok()?;
+            let url = root.join(urls?.get(position)?.as_str()?).ok()?;
+            let body = doc["body"].as_str().unwrap_or_default().trim();
+            if body.is_empty() {
+                return None;
+            }
+            let mut breadcrumbs: Vec<String> = doc["breadcrumbs"]
+                .as_str()
+                .unwrap_or_default()
+                .split('»')
+                .map(|crumb| crumb.trim().to_string())
+                .filter(|crumb| !crumb.is_empty())
+                .collect();
+            // A chapter's opening section repeats the chapter's title
+            breadcrumbs.dedup();
+            if breadcrumbs.is_empty() {
+                breadcrumbs.push(doc["title"].as_str().unwrap_or(url.as_str()).to_string());
+            }
+            Some((
+                position,
+                Section {
+                    id: url.to_string(),
+                    breadcrumbs,
+                    body: body.to_string(),
+                },
+            ))
+        })
+        .collect();
+    sections.sort_by_key(|(position, _)| *position);
+    sections.into_iter().map(|(_, section)| section).collect()
+}
+
+// A book's source folder, its `src` folder, or its SUMMARY.md
+fn local_book(location: &Path) -> Result<Book, McpError> {
+    let location = std::fs::canonicalize(location)
+        .map_err(|e| McpError::validation(Some("location"), format!("Cannot read {}: {}", location.display(), e)))?;
+    let (book_dir, summary) = if location.is_file() {
+        let src = location.parent().unwrap_or(&location).to_path_buf();
+        (src.parent().map(Path::to_path_buf), location.clone())
+    } else if location.join("SUMMARY.md").is_file() {
+        (location.parent().map(Path::to_path_buf), location.join("SUMMARY.md"))
+    } else {
+        let src = book_config(&location)
+            .and_then(|config| config.get("book")?.get("src")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| "src".to_string());
+        (Some(location.clone()), location.join(src).join("SUMMARY.md"))
+    };
+    let title = book_dir
+        .as_deref()
+        .and_then(book_config)
+        .and_then(|config| config.get("book")?.get("title")?.as_str().map(str::to_string));
+    let contents = std::fs::read_to_string(&summary).map_err(|e| {
+        McpError::validation(Some("location"), format!("Cannot read {}: {}", summary.display(), e))
+    })?;
+    let src = summary.parent().unwrap_or(&location);
+
+    let mut sections = Vec::new();
+    let mut errors = Vec::new();
+    for chapter in summary_chapters(&contents) {
+        let path = src.join(&chapter.path);
+        match std::fs::read_to_string(&path) {
+            Ok(markdown) => sections.extend(chapter_sections(&path, &chapter.breadcrumbs, &markdown)),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    Ok(Book { title, sections, errors })
+}
+
+fn book_config(book_dir: &Path) -> Option<toml::Table> {
+    std::fs::read_to_string(book_dir.join("book.toml")).ok()?.parse().ok()
+}
+
+struct Chapter {
+    breadcrumbs: Vec<String>,
+    // Relative to the source folder
+    path: String,
+}
+
+// The chapters SUMMARY.md links, with the titles of the part and the
+// chapters they're nested in. Draft chapters (empty links) have no file
+// but still nest the chapters under them.
+fn summary_chapters(summary: &str) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut part: Option<String> = None;
+    // Open chapters by indentation, outermost first
+    let mut open: Vec<(usize, String)> = Vec::new();
+    let mut seen_heading = false;
+    for line in summary.lines() {
+        let trimmed = line.trim_start();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            // The first heading titles the summary itself; later ones start parts
+            let heading = heading.trim_start_matches('#').trim();
+            if seen_heading || !chapters.is_empty() {
+                part = Some(heading.to_string()).filter(|heading| !heading.is_empty());
+                open.clear();
+            }
+            seen_heading = true;
+            continue;
+        }
+        let indent: usize = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        let item = ["- ", "* "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+            .or_else(|| {
+                let (number, rest) = trimmed.split_once(". ")?;
+                number.chars().all(|c| c.is_ascii_digit()).then_some(rest)
+            })
+            .unwrap_or(trimmed);
+        let Some((title, path)) = summary_link(item) else {
+            continue;
+        };
+        while open.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+            open.pop();
+        }
+        open.push((indent, title.to_string()));
+        if path.is_empty() {
+            continue;
+        }
+        let breadcrumbs = part.iter().cloned().chain(open.iter().map(|(_, title)| title.clone())).collect();
+        chapters.push(Chapter {
+            breadcrumbs,
+            path: path.to_string(),
+        });
+    }
+    chapters
+}
+
+// `[Title](path.md)`
+fn summary_link(item: &str) -> Option<(&str, &str)> {
+    let rest = item.trim().strip_prefix('[')?;
+    let (title, rest) = rest.split_once("](")?;
+    let path = rest.strip_suffix(')')?;
+    let path = path.split(['#', '?']).next().unwrap_or_default().trim();
+    Some((title.trim(), path))
+}
+
+// A chapter split at its level 1 to 3 headings, anchored the way mdBook
+// anchors headings. A heading that opens the chapter is its title, which is
+// already the last breadcrumb, so the text under it stays the chapter's own.
+fn chapter_sections(path: &Path, breadcrumbs: &[String], markdown: &str) -> Vec<Section> {
+    let markdown = expand_includes(path, &markdown.replace("\r\n", "\n"));
+    let chapter_url = Url::from_file_path(path)
+        .map(String::from)
+        .unwrap_or_else(|()| path.display().to_string());
+
+    let mut sections = Vec::new();
+    let mut anchors: HashMap<String, usize> = HashMap::new();
+    // The heading and anchor of the section being read; None for the
+    // chapter's opening text
+    let mut current: Option<(String, String)> = None;
+    let mut body = String::new();
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let Some(heading) = (!in_fence).then(|| heading(line)).flatten() else {
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        };
+        let opens_chapter = current.is_none() && sections.is_empty() && body.trim().is_empty();
+        sections.extend(chapter_section(&chapter_url, breadcrumbs, current.take(), &body));
+        body.clear();
+
+        let mut anchor = heading_anchor(&heading);
+        let count = anchors.entry(anchor.clone()).or_insert(0);
+        if *count > 0 {
+            anchor = format!("{}-{}", anchor, count);
+        }
+        *count += 1;
+        if !opens_chapter {
+            current = Some((heading, anchor));
+        }
+    }
+    sections.extend(chapter_section(&chapter_url, breadcrumbs, current, &body));
+    sections
+}
+
+fn chapter_section(
+    chapter_url: &str,
+    breadcrumbs: &[String],
+    heading: Option<(String, String)>,
+    body: &str,
+) -> Option<Section> {
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+    let mut breadcrumbs = breadcrumbs.to_vec();
+    let id = match heading {
+        Some((heading, anchor)) => {
+            breadcrumbs.push(heading);
+            format!("{}#{}", chapter_url, anchor)
+        }
+        None => chapter_url.to_string(),
+    };
+    Some(Section {
+        id,
+        breadcrumbs,
+        body: body.to_string(),
+    })
+}
+
+// The text of an ATX heading of level 1 to 3
+fn heading(line: &str) -> Option<String> {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=3).contains(&hashes) {
+        return None;
+    }
+    let text = line[hashes..].strip_prefix(' ')?;
+    let text = text.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// Resolves `{{#include file}}` and `{{#rustdoc_include file}}` (with an
+// optional `:anchor` or line range) so listings kept in separate files are
+// searchable. Other directives, such as `{{#title}}`, are dropped.
+fn expand_includes(chapter: &Path, markdown: &str) -> String {
+    let dir = chapter.parent().unwrap_or(Path::new("."));
+    let mut expanded = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("{{#") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            rest = &rest[start..];
+            break;
+        };
+        let directive = rest[start + 3..end].trim();
+        rest = &rest[end + 2..];
+        let (name, argument) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+        if matches!(name, "include" | "rustdoc_include" | "playground" | "playpen") {
+            expanded.push_str(&include(dir, argument.trim()).unwrap_or_default());
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+// `path`, `path:anchor`, `path:5`, `path:5:10`, `path:5:` or `path::10`
+fn include(dir: &Path, argument: &str) -> Option<String> {
+    let mut parts = argument.splitn(2, ':');
+    let path = parts.next()?.trim();
+    let selector = parts.next().unwrap_or_default().trim();
+    let contents = std::fs::read_to_string(dir.join(path)).ok()?;
+    let lines: Vec<&str> = contents.lines().collect();
+    let selected: Vec<&str> = if selector.is_empty() {
+        lines
+    } else if let Some((from, to)) = selector.split_once(':') {
+        let from = from.trim().parse::<usize>().map_or(0, |from| from.saturating_sub(1));
+        let to = to.trim().parse::<usize>().unwrap_or(lines.len()).min(lines.len());
+        lines.get(from..to).unwrap_or_default().to_vec()
+    } else if let Ok(line) = selector.parse::<usize>() {
+        lines.get(line.saturating_sub(1)..line).unwrap_or_default().to_vec()
+    } else {
+        let start = format!("ANCHOR: {}", selector);
+        let end = format!("ANCHOR_END: {}", selector);
+        lines
+            .iter()
+            .skip_while(|line| !line.contains(&start))
+            .skip(1)
+            .take_while(|line| !line.contains(&end))
+            .copied()
+            .collect()
+    };
+    let selected: Vec<&str> = selected.into_iter().filter(|line| !line.contains("ANCHOR")).collect();
+    Some(selected.join("\n"))
+}
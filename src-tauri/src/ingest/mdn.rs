@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures_util::stream::{self, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use super::github::GitHubApi;
+use super::{ChunkingRules, Fetcher};
+use crate::search::{facet_path, SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// MDN's pages as Markdown, one index.md per page
+const MDN_CONTENT_REPO: &str = "mdn/content";
+const MDN_CONTENT_RAW: &str = "https://raw.githubusercontent.com/mdn/content";
+const MDN_WEB_FOLDER: &str = "files/en-us/web";
+// Ids link to the pages as MDN serves them
+const MDN_DOCS: &str = "https://developer.mozilla.org/en-US/docs";
+// The compat tables, as one file keyed by feature
+const BROWSER_COMPAT_DATA: &str = "https://unpkg.com/@mdn/browser-compat-data/data.json";
+
+const MAX_COMPAT_BYTES: usize = 64 * 1024 * 1024;
+
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+// Columns of the compat tables, for the browsers BCD knows the feature in
+const BROWSERS: &[(&str, &str)] = &[
+    ("chrome", "Chrome"),
+    ("edge", "Edge"),
+    ("firefox", "Firefox"),
+    ("safari", "Safari"),
+    ("chrome_android", "Chrome Android"),
+    ("firefox_android", "Firefox for Android"),
+    ("safari_ios", "Safari on iOS"),
+    ("nodejs", "Node.js"),
+    ("deno", "Deno"),
+];
+
+// Macros that link to another page; they become the text of the link
+const XREF_MACROS: &[&str] = &[
+    "jsxref",
+    "domxref",
+    "cssxref",
+    "htmlelement",
+    "httpheader",
+    "httpmethod",
+    "httpstatus",
+    "svgelement",
+    "svgattr",
+    "mathmlelement",
+    "htmlattrxref",
+    "glossary",
+];
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MdnOptions {
+    /// Technology areas, as the folders under MDN's Web docs: "html", "css",
+    /// "javascript", "api", "http", "svg", ... (default html, css and javascript)
+    #[serde(default = "default_areas")]
+    pub areas: Vec<String>,
+    /// Add each page's browser compatibility table (default true)
+    #[serde(default = "default_compat")]
+    pub compat: bool,
+    /// Pages indexed at most, over all areas (default 3000)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+    /// Source to file every page under; defaults to "mdn:{area}", e.g. "mdn:css"
+    #[serde(default)]
+    pub source: Option<String>,
+    /// GitHub token for a higher rate limit; defaults to $GITHUB_TOKEN.
+    /// Never recorded in the audit log.
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+}
+
+fn default_areas() -> Vec<String> {
+    ["html", "css", "javascript"].iter().map(|area| area.to_string()).collect()
+}
+
+fn default_compat() -> bool {
+    true
+}
+
+fn default_max_pages() -> usize {
+    3000
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MdnResponse {
+    /// The sources the pages were filed under
+    pub sources: Vec<String>,
+    /// The mdn/content commit the pages were read at, also their version
+    pub commit: String,
+    pub indexed: usize,
+    /// Pages that couldn't be read or indexed, as "page: reason"
+    pub errors: Vec<String>,
+}
+
+// Indexes MDN's reference pages from their Markdown sources in mdn/content,
+// one document per page, filed by technology area. Each page gets the
+// browser compatibility table of the features it documents, from
+// @mdn/browser-compat-data. `on_progress` gets (documents written, total).
+pub async fn index_mdn(
+    fetcher: &Fetcher,
+    chunking: &ChunkingRules,
+    search_service: Arc<SearchService>,
+    options: MdnOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<MdnResponse, McpError> {
+    let api = GitHubApi::new(fetcher, options.token.clone());
+    let commit = api
+        .get(&format!("repos/{}/commits/main", MDN_CONTENT_REPO))
+        .await?
+        .and_then(|commit| commit["sha"].as_str().map(str::to_string))
+        .ok_or_else(|| McpError::not_found(format!("the main branch of {}", MDN_CONTENT_REPO)))?;
+    let web = api
+        .get(&format!("repos/{}/contents/{}?ref={}", MDN_CONTENT_REPO, MDN_WEB_FOLDER, commit))
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("{} in {}", MDN_WEB_FOLDER, MDN_CONTENT_REPO)))?;
+    let folders: HashMap<&str, &str> = web
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| entry["type"].as_str() == Some("dir"))
+        .filter_map(|entry| Some((entry["name"].as_str()?, entry["sha"].as_str()?)))
+        .collect();
+
+    let mut errors = Vec::new();
+    let mut pages = Vec::new();
+    for area in &options.areas {
+        let area = area.trim().to_ascii_lowercase();
+        let Some(tree) = folders.get(area.as_str()) else {
+            errors.push(format!("{}: not an area of MDN's Web docs", area));
+            continue;
+        };
+        let Some(tree) = api.get(&format!("repos/{}/git/trees/{}?recursive=1", MDN_CONTENT_REPO, tree)).await? else {
+            errors.push(format!("{}: its pages couldn't be listed", area));
+            continue;
+        };
+        if tree["truncated"].as_bool() == Some(true) {
+            errors.push(format!("{}: the page list was cut short by GitHub; some pages may be missing", area));
+        }
+        let entries = tree["tree"].as_array().map(Vec::as_slice).unwrap_or_default();
+        pages.extend(
+            entries
+                .iter()
+                .filter(|entry| entry["type"].as_str() == Some("blob"))
+                .filter_map(|entry| entry["path"].as_str())
+                .filter(|path| *path == "index.md" || path.ends_with("/index.md"))
+                .map(|path| (area.clone(), format!("{}/{}/{}", MDN_WEB_FOLDER, area, path))),
+        );
+    }
+    if pages.len() > options.max_pages {
+        errors.push(format!("only the first {} of {} pages were indexed", options.max_pages, pages.len()));
+        pages.truncate(options.max_pages);
+    }
+
+    let compat = if options.compat {
+        match browser_compat_data(fetcher).await {
+            Ok(compat) => Some(compat),
+            Err(e) => {
+                errors.push(format!("browser compatibility data: {}", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let downloads = pages.into_iter().map(|(area, path)| {
+        let api = &api;
+        let url = format!("{}/{}/{}", MDN_CONTENT_RAW, commit, path);
+        async move { (api.download(&url).await, area, path) }
+    });
+    let mut downloads = stream::iter(downloads).buffered(DOWNLOAD_CONCURRENCY);
+    let mut documents = Vec::new();
+    let mut sources = Vec::new();
+    while let Some((text, area, path)) = downloads.next().await {
+        let text = match text {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                errors.push(format!("{}: not found", path));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+        let source = options.source.clone().unwrap_or_else(|| format!("mdn:{}", area));
+        let Some(document) = page_document(&text, &area, compat.as_ref(), &source, &commit) else {
+            errors.push(format!("{}: no title or slug", path));
+            continue;
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => {
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
+                documents.push(document);
+            }
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+    drop(downloads);
+
+    let indexed = documents.len();
+    if !documents.is_empty() {
+        let documents = documents.into_iter().flat_map(|page| chunking.split(page)).collect();
+        tokio::task::spawn_blocking(move || search_service.add_documents(documents, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+            .map_err(|e| McpError::index("Failed to add MDN pages", e))?;
+    }
+    info!("Indexed {} MDN pages at {}", indexed, commit);
+    Ok(MdnResponse {
+        sources,
+        commit,
+        indexed,
+        errors,
+    })
+}
+
+async fn browser_compat_data(fetcher: &Fetcher) -> Result<Value, McpError> {
+    let url = Fetcher::parse_url(BROWSER_COMPAT_DATA)?;
+    let data = fetcher.fetch_large(&url, MAX_COMPAT_BYTES).await?;
+    serde_json::from_slice(&data).map_err(|e| McpError::fetch(url.as_str(), format!("not compat data: {}", e)))
+}
+
+// A page's Markdown with its area, page type, status and compat table up
+// front; None for pages without the front matter every page has
+fn page_document(
+    text: &str,
+    area: &str,
+    compat: Option<&Value>,
+    source: &str,
+    commit: &str,
+) -> Option<SearchableDocument> {
+    let (front_matter, markdown) = split_front_matter(text);
+    let title = front_matter.get("title")?.first()?.clone();
+    let slug = front_matter.get("slug")?.first()?.clone();
+    let features = front_matter.get("browser-compat").cloned().unwrap_or_default();
+
+    let table = compat.map(|compat| compat_tables(compat, &features)).filter(|table| !table.is_empty());
+    let mut facts = vec![format!("Area: {}", area_name(area))];
+    if let Some(page_type) = front_matter.get("page-type").and_then(|values| values.first()) {
+        facts.push(format!("Page type: {}", page_type));
+    }
+    if let Some(status) = front_matter.get("status").filter(|status| !status.is_empty()) {
+        facts.push(format!("Status: {}", status.join(", ")));
+    }
+    let (mut body, placed) = expand_macros(markdown, table.as_deref());
+    if let Some(table) = table.filter(|_| !placed) {
+        body.push_str("\n\n## Browser compatibility\n\n");
+        body.push_str(&table);
+    }
+    let url = format!("{}/{}", MDN_DOCS, slug);
+    // e.g. /mdn/api/web-api-interface
+    let page_type = front_matter.get("page-type").and_then(|values| values.first());
+    let facet = facet_path(["mdn", area].into_iter().chain(page_type.map(String::as_str)));
+    Some(SearchableDocument {
+        id: url.clone(),
+        title: format!("{} - {}", title, area_name(area)),
+        body: format!("{}\n\n{}", facts.join(". "), body.trim()),
+        source: source.to_string(),
+        version: Some(commit.to_string()),
+        url: Some(url),
+        // "deprecated", "experimental" and "non-standard"
+        tags: front_matter.get("status").cloned().unwrap_or_default(),
+        indexed_at: None,
+        published_at: None,
+        metrics: Default::default(),
+        facet: Some(facet),
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    })
+}
+
+fn area_name(area: &str) -> String {
+    match area {
+        "api" => "Web APIs".to_string(),
+        "javascript" => "JavaScript".to_string(),
+        "webassembly" => "WebAssembly".to_string(),
+        "mathml" => "MathML".to_string(),
+        "css" | "html" | "http" | "svg" | "xml" | "xslt" | "uri" => area.to_ascii_uppercase(),
+        _ => {
+            let mut chars = area.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        }
+    }
+}
+
+// The YAML front matter's keys with their values, scalar or listed, and the
+// Markdown after it. Nested maps are skipped; pages only use flat keys.
+fn split_front_matter(text: &str) -> (HashMap<String, Vec<String>>, &str) {
+    let mut front_matter = HashMap::new();
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (front_matter, text);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (front_matter, text);
+    };
+    let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    let mut key: Option<String> = None;
+    for line in rest[..end].lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some(key) = &key {
+                let values: &mut Vec<String> = front_matter.entry(key.clone()).or_default();
+                values.push(unquote(item));
+            }
+        } else if !line.starts_with(' ') {
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                key = Some(name.trim().to_string());
+                let values = front_matter.entry(name.trim().to_string()).or_default();
+                if !value.is_empty() {
+                    values.push(unquote(value));
+                }
+            }
+        }
+    }
+    (front_matter, body)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+// Resolves the KumaScript macros MDN's Markdown is sprinkled with: links
+// keep their text, {{Compat}} becomes the compat table, and sidebars,
+// banners and the like are dropped. Code blocks are left alone. Also says
+// whether the table found a place.
+fn expand_macros(markdown: &str, table: Option<&str>) -> (String, bool) {
+    let mut out = String::new();
+    let mut placed = false;
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code || !line.contains("{{") {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find("}}") else {
+                out.push_str(&rest[start..]);
+                rest = "";
+                break;
+            };
+            let call = &rest[start + 2..start + end];
+            let (name, args) = match call.split_once('(') {
+                Some((name, args)) => (name.trim(), macro_args(args.trim_end().trim_end_matches(')'))),
+                None => (call.trim(), Vec::new()),
+            };
+            let name = name.to_ascii_lowercase();
+            if name == "compat" {
+                if let Some(table) = table {
+                    out.push_str(table);
+                    placed = true;
+                }
+            } else if XREF_MACROS.contains(&name.as_str()) {
+                let text = args.get(1).or(args.first()).cloned().unwrap_or_default();
+                match name.as_str() {
+                    "glossary" => out.push_str(&text),
+                    "htmlelement" | "svgelement" | "mathmlelement" if args.len() < 2 => {
+                        out.push_str(&format!("`<{}>`", text))
+                    }
+                    _ => out.push_str(&format!("`{}`", text)),
+                }
+            }
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        out.push('\n');
+    }
+    (out, placed)
+}
+
+// The arguments of a macro call, unquoted: "a", 'b', 3
+fn macro_args(args: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in args.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ',') => values.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        values.push(current.trim().to_string());
+    }
+    values
+}
+
+// One table per feature the page documents, browsers across, with the
+// version support started in
+fn compat_tables(compat: &Value, features: &[String]) -> String {
+    let mut tables = Vec::new();
+    for feature in features {
+        let node = feature.split('.').fold(compat, |node, key| &node[key]);
+        let data = &node["__compat"];
+        let Some(support) = data["support"].as_object() else {
+            continue;
+        };
+        let columns: Vec<(&str, String)> = BROWSERS
+            .iter()
+            .filter_map(|(key, name)| Some((*name, support_text(support.get(*key)?))))
+            .collect();
+        if columns.is_empty() {
+            continue;
+        }
+        let mut table = String::new();
+        if features.len() > 1 {
+            table.push_str(&format!("### `{}`\n\n", feature));
+        }
+        let status = &data["status"];
+        let flags: Vec<&str> = [("experimental", "Experimental"), ("deprecated", "Deprecated")]
+            .iter()
+            .filter(|(key, _)| status[*key].as_bool() == Some(true))
+            .map(|(_, label)| *label)
+            .chain((status["standard_track"].as_bool() == Some(false)).then_some("Non-standard"))
+            .collect();
+        if !flags.is_empty() {
+            table.push_str(&format!("{}.\n\n", flags.join(", ")));
+        }
+        let names: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+        let versions: Vec<&str> = columns.iter().map(|(_, version)| version.as_str()).collect();
+        table.push_str(&format!("| {} |\n", names.join(" | ")));
+        table.push_str(&format!("|{}\n", " --- |".repeat(names.len())));
+        table.push_str(&format!("| {} |", versions.join(" | ")));
+        tables.push(table);
+    }
+    tables.join("\n\n")
+}
+
+// The first (current) support statement: the version support was added in,
+// "No", or "?", with its caveats
+fn support_text(statements: &Value) -> String {
+    let statement = statements.as_array().and_then(|statements| statements.first()).unwrap_or(statements);
+    let mut text = match &statement["version_added"] {
+        Value::String(version) => version.clone(),
+        Value::Bool(true) => "Yes".to_string(),
+        Value::Bool(false) => "No".to_string(),
+        _ => "?".to_string(),
+    };
+    if let Some(removed) = statement["version_removed"].as_str() {
+        text.push_str(&format!(" (removed in {})", removed));
+    }
+    if let Some(prefix) = statement["prefix"].as_str() {
+        text.push_str(&format!(" (as {})", prefix));
+    }
+    if statement["partial_implementation"].as_bool() == Some(true) {
+        text.push_str(" (partial)");
+    }
+    if statement["flags"].is_array() {
+        text.push_str(" (behind a flag)");
+    }
+    text
+}
@@ -0,0 +1,226 @@
+// Getting documentation into the index from elsewhere: downloading pages
+// and turning them into `SearchableDocument`s. Single pages are indexed by
+// the callers, which validate and add them the same way as documents sent
+// by clients; crawls run in the background and index as they go.
+
+mod chunking;
+mod crawl;
+mod devdocs;
+mod docset;
+mod docsrs;
+mod docsite;
+mod failures;
+mod feeds;
+mod fetch;
+mod files;
+mod github;
+mod gitrepo;
+mod html;
+mod inventory;
+mod llms;
+mod manpages;
+mod markdown;
+mod mdbook;
+mod mdn;
+mod npm;
+mod pages;
+mod pdf;
+mod pypi;
+mod registry;
+mod releases;
+mod robots;
+mod roff;
+mod rustdoc;
+mod schedule;
+mod sections;
+mod sitemap;
+mod updates;
+
+pub use chunking::ChunkingRules;
+pub use crawl::{
+    CrawlEvent, CrawlManager, CrawlOptions, CrawlState, CrawlStatus, InventoryCrawlOptions, ListCrawlsParams,
+    RefreshOptions, SitemapCrawlOptions,
+};
+pub use devdocs::{index_devdocs, DevdocsOptions, DevdocsResponse};
+pub use docset::{index_docset, DocsetOptions, DocsetResponse};
+pub use docsrs::{index_crate, CrateOptions, CrateResponse};
+pub use docsite::{index_docs_site, DocsSiteOptions, DocsSiteResponse};
+pub use failures::PageFailure;
+pub use feeds::{FeedEvent, FeedWatcher, ListFeedsParams, WatchFeedOptions, WatchedFeed};
+pub use fetch::Fetcher;
+pub use files::{add_file, index_path, AddFileOptions, AddFileResponse, IndexPathOptions, IndexPathResponse};
+pub use github::{index_github_repo, GitHubRepoOptions, GitHubRepoResponse};
+pub use gitrepo::{index_git_repo, GitRepoOptions, GitRepoResponse};
+pub use html::is_valid_selector;
+pub use manpages::{index_man_pages, ManPagesOptions, ManPagesResponse};
+pub use mdbook::{index_mdbook, MdbookOptions, MdbookResponse};
+pub use mdn::{index_mdn, MdnOptions, MdnResponse};
+pub use npm::{index_npm_package, NpmPackageOptions, NpmPackageResponse};
+pub use pages::{PageRecord, PageStore};
+pub use pypi::{index_pypi_package, normalize_name as normalize_pypi_name, PypiPackageOptions, PypiPackageResponse};
+pub use registry::{latest_version, LatestVersionParams, LatestVersionResponse};
+pub use releases::{index_release_notes, ReleaseNotesOptions, ReleaseNotesResponse};
+pub use rustdoc::{index_rustdoc, RustdocOptions, RustdocResponse};
+pub use schedule::{ListSchedulesParams, ScheduleOptions, ScheduledTask, Scheduler, SourceSchedule};
+pub use updates::{PackageCheck, ReleaseChecksParams, ReleaseEvent, ReleaseWatcher};
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use url::Url;
+
+use crate::search::SearchableDocument;
+use crate::settings::SourceExtraction;
+use crate::util::{now_millis, sha256_hex};
+use crate::McpError;
+
+// Per-source extraction overrides from the settings, replaced in place when
+// the settings change so running crawls pick them up
+pub struct ExtractionRules {
+    sources: RwLock<HashMap<String, SourceExtraction>>,
+}
+
+impl ExtractionRules {
+    pub fn new(sources: HashMap<String, SourceExtraction>) -> Self {
+        Self {
+            sources: RwLock::new(sources),
+        }
+    }
+
+    pub fn replace(&self, sources: HashMap<String, SourceExtraction>) {
+        *self.sources.write().unwrap() = sources;
+    }
+
+    fn for_source(&self, source: &str) -> Option<SourceExtraction> {
+        self.sources.read().unwrap().get(source).cloned()
+    }
+}
+
+// A downloaded page as a document, plus what's needed to refresh it later
+pub struct PageDocument {
+    pub document: SearchableDocument,
+    pub record: PageRecord,
+    links: Vec<String>,
+    // The page's final URL, which links are resolved against
+    url: Url,
+}
+
+// Downloads `url` and extracts it into a document whose id is the page's
+// final URL (without fragment). `source` defaults to the host name.
+pub async fn fetch_document(
+    fetcher: &Fetcher,
+    rules: &ExtractionRules,
+    url: &str,
+    source: Option<String>,
+    version: Option<String>,
+) -> Result<PageDocument, McpError> {
+    let url = Fetcher::parse_url(url)?;
+    let page = fetch_page_document(fetcher, rules, &url, source, version, None).await?;
+    page.ok_or_else(|| McpError::fetch(url.as_str(), "page was not downloaded"))
+}
+
+// None when `known` describes the page and it hasn't changed since: the
+// server answered 304, or the extracted text is the same as before
+async fn fetch_page_document(
+    fetcher: &Fetcher,
+    rules: &ExtractionRules,
+    url: &Url,
+    source: Option<String>,
+    version: Option<String>,
+    known: Option<&PageRecord>,
+) -> Result<Option<PageDocument>, McpError> {
+    let Some(page) = fetcher.fetch_if_changed(url, known).await? else {
+        return Ok(None);
+    };
+
+    let mut page_url = page.url;
+    page_url.set_fragment(None);
+    let source = source.unwrap_or_else(|| page_url.host_str().unwrap_or("web").to_string());
+    let (title, body, links) = if page.content_type.is_empty() || page.content_type.contains("html") {
+        let extracted = html::extract(&page.body, rules.for_source(&source).as_ref());
+        (extracted.title, extracted.markdown, extracted.links)
+    } else if page.content_type.starts_with("text/") {
+        (String::new(), page.body, Vec::new())
+    } else {
+        return Err(McpError::fetch(
+            page_url.as_str(),
+            format!("unsupported content type {}", page.content_type),
+        ));
+    };
+
+    let title = if title.is_empty() { page_url.to_string() } else { title };
+    let digest = content_digest(&title, &body);
+    if known.is_some_and(|known| known.digest == digest) {
+        return Ok(None);
+    }
+
+    let document = SearchableDocument {
+        id: page_url.to_string(),
+        title,
+        body,
+        source,
+        version,
+        url: Some(page_url.to_string()),
+        tags: Vec::new(),
+        indexed_at: None,
+        published_at: None,
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    };
+    let record = PageRecord {
+        source: document.source.clone(),
+        version: document.version.clone(),
+        etag: page.etag,
+        last_modified: page.last_modified,
+        digest,
+        fetched_at: now_millis(),
+    };
+    Ok(Some(PageDocument {
+        document,
+        record,
+        links,
+        url: page_url,
+    }))
+}
+
+// `url` as the directory it names, for resolving the files a generated site
+// keeps at its root: https://host/docs becomes https://host/docs/, and
+// https://host/docs/index.html stays as it is
+fn directory_url(url: &Url) -> Url {
+    let mut root = url.clone();
+    root.set_query(None);
+    root.set_fragment(None);
+    let last_segment = root.path().rsplit('/').next().unwrap_or_default();
+    if !last_segment.is_empty() && !last_segment.contains('.') {
+        let path = format!("{}/", root.path());
+        root.set_path(&path);
+    }
+    root
+}
+
+fn content_digest(title: &str, body: &str) -> String {
+    let mut content = Vec::with_capacity(title.len() + body.len() + 1);
+    content.extend_from_slice(title.as_bytes());
+    content.push(0);
+    content.extend_from_slice(body.as_bytes());
+    sha256_hex(&content)
+}
+
+// RFC 3339 (Atom and most APIs) and RFC 2822 (RSS) dates, and plain
+// YYYY-MM-DD days, as milliseconds since the Unix epoch
+fn date_millis(date: &str) -> Option<u64> {
+    let date = date.trim();
+    let millis = chrono::DateTime::parse_from_rfc3339(date)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(date))
+        .map(|date| date.timestamp_millis())
+        .ok()
+        .or_else(|| {
+            let day = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some(day.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+        })?;
+    u64::try_from(millis).ok()
+}
@@ -0,0 +1,323 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use super::{date_millis, CrawlManager, CrawlOptions, CrawlStatus, Fetcher};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+const NPM_REGISTRY: &str = "https://registry.npmjs.org";
+// Ids link to the package's page and to its files as published
+const NPM_WEBSITE: &str = "https://www.npmjs.com/package";
+const UNPKG: &str = "https://unpkg.com";
+
+// npm metadata lists every release with its manifest; for the busiest
+// packages that's tens of MB
+const MAX_METADATA_BYTES: usize = 128 * 1024 * 1024;
+const MAX_TARBALL_BYTES: usize = 64 * 1024 * 1024;
+
+// Beyond this many declaration files a package is mostly generated code
+const MAX_TYPING_FILES: usize = 500;
+
+const TYPING_EXTENSIONS: &[&str] = &[".d.ts", ".d.mts", ".d.cts"];
+
+// Hosts whose pages are the README again, or the registry itself
+const NOT_DOCS_HOSTS: &[&str] = &["github.com", "gitlab.com", "bitbucket.org", "www.npmjs.com", "npmjs.com"];
+
+const HOMEPAGE_CRAWL_DEPTH: usize = 3;
+const HOMEPAGE_CRAWL_CONCURRENCY: usize = 4;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NpmPackageOptions {
+    /// The package's name, e.g. "express" or "@tanstack/react-query"
+    pub name: String,
+    /// Defaults to the release tagged `latest`
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Source to file the documents under; defaults to "npm:{name}"
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Also crawl the docs site the package names as its homepage (default true)
+    #[serde(default = "default_crawl_homepage")]
+    pub crawl_homepage: bool,
+    /// Pages the homepage crawl stops after (default 200)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+}
+
+pub(super) fn default_crawl_homepage() -> bool {
+    true
+}
+
+pub(super) fn default_max_pages() -> usize {
+    200
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NpmPackageResponse {
+    pub source: String,
+    pub version: String,
+    /// The README and declaration files indexed
+    pub indexed: usize,
+    /// Files that couldn't be read or indexed, as "file: reason"
+    pub errors: Vec<String>,
+    /// The crawl of the package's docs site, when it has one
+    pub crawl: Option<CrawlStatus>,
+}
+
+// Indexes an npm package: its README, its TypeScript declarations (from the
+// package itself, or from its DefinitelyTyped package when it ships none)
+// and, in the background, the docs site its homepage points to.
+// `on_progress` gets (documents written, total).
+pub async fn index_npm_package(
+    fetcher: &Fetcher,
+    crawls: &CrawlManager,
+    search_service: Arc<SearchService>,
+    options: NpmPackageOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<NpmPackageResponse, McpError> {
+    let name = options.name.trim().to_string();
+    let metadata = package_metadata(fetcher, &name)
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("package {}", name)))?;
+    let version = match &options.version {
+        Some(version) => version.trim().to_string(),
+        None => metadata["dist-tags"]["latest"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| McpError::not_found(format!("a latest release of {}", name)))?,
+    };
+    let manifest = &metadata["versions"][&version];
+    if !manifest.is_object() {
+        return Err(McpError::not_found(format!("{} {}", name, version)));
+    }
+    let source = options.source.clone().unwrap_or_else(|| format!("npm:{}", name));
+    let published_at = metadata["time"][&version].as_str().and_then(date_millis);
+
+    let mut errors = Vec::new();
+    let mut files = Vec::new();
+    match manifest["dist"]["tarball"].as_str() {
+        Some(tarball) => match download_files(fetcher, tarball).await {
+            Ok(package) => files = package,
+            Err(e) => errors.push(format!("{}: {}", tarball, e)),
+        },
+        None => errors.push(format!("{} {}: no tarball", name, version)),
+    }
+
+    let mut documents = Vec::new();
+    // The README as published with this release; the registry keeps the
+    // latest one's too, for releases packed without one
+    let readme = files
+        .iter()
+        .find(|(path, _)| is_readme(path))
+        .map(|(_, text)| text.clone())
+        .or_else(|| manifest["readme"].as_str().map(str::to_string))
+        .or_else(|| {
+            let latest = metadata["dist-tags"]["latest"].as_str() == Some(version.as_str());
+            metadata["readme"].as_str().filter(|_| latest).map(str::to_string)
+        })
+        .filter(|readme| !readme.trim().is_empty());
+    if let Some(readme) = readme {
+        let description = manifest["description"].as_str().map(|text| format!("{}\n\n", text)).unwrap_or_default();
+        let url = format!("{}/{}/v/{}", NPM_WEBSITE, name, version);
+        documents.push(SearchableDocument {
+            id: url.clone(),
+            title: format!("{} README", name),
+            body: format!("{}{}", description, readme.trim()),
+            source: source.clone(),
+            version: Some(version.clone()),
+            url: Some(url),
+            tags: Vec::new(),
+            indexed_at: None,
+            published_at,
+            metrics: Default::default(),
+            facet: None,
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: None,
+            content_hash: None,
+        });
+    }
+
+    let mut typings: Vec<SearchableDocument> = files
+        .iter()
+        .filter(|(path, _)| is_typing(path))
+        .map(|(path, text)| typing_document(&name, &version, path, text, &source, published_at))
+        .collect();
+    // Packages without their own declarations usually have them on
+    // DefinitelyTyped, as @types/{name} (@types/scope__name when scoped)
+    if typings.is_empty() && !name.starts_with("@types/") {
+        let types_name = format!("@types/{}", name.trim_start_matches('@').replace('/', "__"));
+        if let Some(types_metadata) = package_metadata(fetcher, &types_name).await? {
+            let types_version = types_metadata["dist-tags"]["latest"].as_str().unwrap_or_default().to_string();
+            let tarball = types_metadata["versions"][&types_version]["dist"]["tarball"].as_str().unwrap_or_default();
+            let types_published_at = types_metadata["time"][&types_version].as_str().and_then(date_millis);
+            match download_files(fetcher, tarball).await {
+                Ok(types_files) => typings.extend(
+                    types_files
+                        .iter()
+                        .filter(|(path, _)| is_typing(path))
+                        .map(|(path, text)| {
+                            typing_document(&types_name, &types_version, path, text, &source, types_published_at)
+                        })
+                        // Filed under the package's own version, which
+                        // DefinitelyTyped tracks by major and minor
+                        .map(|document| SearchableDocument {
+                            version: Some(version.clone()),
+                            ..document
+                        }),
+                ),
+                Err(e) => errors.push(format!("{}: {}", tarball, e)),
+            }
+        }
+    }
+    if typings.len() > MAX_TYPING_FILES {
+        errors.push(format!("only the first {} of {} declaration files were indexed", MAX_TYPING_FILES, typings.len()));
+        typings.truncate(MAX_TYPING_FILES);
+    }
+    documents.extend(typings);
+
+    let mut valid = Vec::new();
+    for document in documents {
+        match validate_document(&document, "document") {
+            Ok(()) => valid.push(document),
+            Err(e) => errors.push(format!("{}: {}", document.id, e)),
+        }
+    }
+    let indexed = valid.len();
+    if !valid.is_empty() {
+        tokio::task::spawn_blocking(move || search_service.add_documents(valid, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+            .map_err(|e| McpError::index("Failed to add package documents", e))?;
+    }
+
+    let homepage = manifest["homepage"].as_str().or_else(|| metadata["homepage"].as_str());
+    let crawl = match homepage.and_then(docs_homepage) {
+        Some(homepage) if options.crawl_homepage => Some(crawls.start(CrawlOptions {
+            seed_url: homepage.to_string(),
+            source: source.clone(),
+            version: Some(version.clone()),
+            same_host: true,
+            path_prefix: None,
+            max_depth: HOMEPAGE_CRAWL_DEPTH,
+            max_pages: options.max_pages,
+            concurrency: HOMEPAGE_CRAWL_CONCURRENCY,
+            host_concurrency: HOMEPAGE_CRAWL_CONCURRENCY,
+            ignore_robots_txt: false,
+        })?),
+        _ => None,
+    };
+    info!("Indexed {} files of npm package {} {} as source {}", indexed, name, version, source);
+    Ok(NpmPackageResponse {
+        source,
+        version,
+        indexed,
+        errors,
+        crawl,
+    })
+}
+
+// The registry's document for the package, None if there's no such package
+async fn package_metadata(fetcher: &Fetcher, name: &str) -> Result<Option<Value>, McpError> {
+    let mut url = Url::parse(NPM_REGISTRY).map_err(|e| McpError::internal(e.to_string()))?;
+    if let Ok(mut segments) = url.path_segments_mut() {
+        // Scoped packages keep their slash, escaped: @types%2Fnode
+        segments.push(name);
+    }
+    let Some(metadata) = fetcher.fetch_if_exists(&url, MAX_METADATA_BYTES).await? else {
+        return Ok(None);
+    };
+    serde_json::from_slice(&metadata)
+        .map(Some)
+        .map_err(|e| McpError::fetch(url.as_str(), format!("not package metadata: {}", e)))
+}
+
+// The text files in a package's tarball, by path inside the package
+async fn download_files(fetcher: &Fetcher, tarball: &str) -> Result<Vec<(String, String)>, McpError> {
+    let url = Fetcher::parse_url(tarball)?;
+    let bytes = fetcher.fetch_large(&url, MAX_TARBALL_BYTES).await?;
+    tokio::task::spawn_blocking(move || unpack(&bytes))
+        .await
+        .map_err(|e| McpError::internal(format!("Unpacking task failed: {}", e)))?
+        .map_err(|e| McpError::fetch(url.as_str(), format!("not a package tarball: {}", e)))
+}
+
+// READMEs and declaration files only; the rest is code
+fn unpack(tarball: &[u8]) -> std::io::Result<Vec<(String, String)>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        // Files sit under a top folder, usually "package/"
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let Some((_, path)) = path.split_once('/') else {
+            continue;
+        };
+        if path.split('/').any(|part| part == "node_modules") || !(is_readme(path) || is_typing(path)) {
+            continue;
+        }
+        let mut text = String::new();
+        if entry.read_to_string(&mut text).is_ok() {
+            files.push((path.to_string(), text));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_readme(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    matches!(path.as_str(), "readme.md" | "readme.markdown" | "readme" | "readme.txt")
+}
+
+fn is_typing(path: &str) -> bool {
+    TYPING_EXTENSIONS.iter().any(|extension| path.ends_with(extension))
+}
+
+fn typing_document(
+    name: &str,
+    version: &str,
+    path: &str,
+    text: &str,
+    source: &str,
+    published_at: Option<u64>,
+) -> SearchableDocument {
+    let url = format!("{}/{}@{}/{}", UNPKG, name, version, path);
+    SearchableDocument {
+        id: url.clone(),
+        title: format!("{}/{}", name, path),
+        body: format!("```ts\n{}\n```", text.trim()),
+        source: source.to_string(),
+        version: Some(version.to_string()),
+        url: Some(url),
+        tags: Vec::new(),
+        indexed_at: None,
+        published_at,
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    }
+}
+
+// The homepage, if it's a docs site of its own rather than the repository
+fn docs_homepage(homepage: &str) -> Option<&str> {
+    let url = Url::parse(homepage).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    (matches!(url.scheme(), "http" | "https") && !NOT_DOCS_HOSTS.contains(&host.as_str())).then_some(homepage)
+}
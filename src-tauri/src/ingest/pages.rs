@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// What we know about a downloaded page, so refreshing it can ask the
+// server whether it changed and skip re-indexing when it didn't
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRecord {
+    pub source: String,
+    pub version: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // SHA-256 of the extracted title and body, hex-encoded
+    pub digest: String,
+    // Milliseconds since the Unix epoch
+    pub fetched_at: u64,
+}
+
+// Records of every page indexed from the web, by document id (the page's
+// URL). Kept in a JSON file next to the settings.
+pub struct PageStore {
+    path: PathBuf,
+    pages: Mutex<HashMap<String, PageRecord>>,
+}
+
+impl PageStore {
+    // A missing or unreadable file starts an empty store; pages indexed
+    // before then are simply downloaded in full on their next refresh
+    pub fn load(path: PathBuf) -> Self {
+        let pages = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid page records {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            path,
+            pages: Mutex::new(pages),
+        }
+    }
+
+    pub fn remember(&self, records: impl IntoIterator<Item = (String, PageRecord)>) -> Result<()> {
+        let mut pages = self.pages.lock().unwrap();
+        pages.extend(records);
+        self.save(&pages)
+    }
+
+    pub fn forget(&self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let mut pages = self.pages.lock().unwrap();
+        for id in ids {
+            pages.remove(id);
+        }
+        self.save(&pages)
+    }
+
+    pub fn for_source(&self, source: &str) -> Vec<(String, PageRecord)> {
+        self.pages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, record)| record.source == source)
+            .map(|(id, record)| (id.clone(), record.clone()))
+            .collect()
+    }
+
+    fn save(&self, pages: &HashMap<String, PageRecord>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(pages)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,949 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+// Guards against reference cycles and absurdly deep page trees or forms
+const MAX_NESTING: usize = 32;
+const MAX_FORM_DEPTH: usize = 5;
+
+// Larger values in a TJ array are gaps between words rather than kerning
+const WORD_GAP: f64 = 200.0;
+
+// Decoded streams bigger than this are cut short
+const MAX_STREAM_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+enum Object {
+    Null,
+    Number(f64),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    // Object number; generations are ignored, the newest definition wins
+    Ref(u32),
+    // The dictionary and the still-encoded data
+    Stream(Dict, Vec<u8>),
+    // Content stream operators and other keywords, true and false included
+    Operator(String),
+}
+
+type Dict = HashMap<String, Object>;
+
+impl Object {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) | Object::Stream(dict, _) => Some(dict),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+pub struct PdfText {
+    // From the document information dictionary
+    pub title: Option<String>,
+    // The text of each page, in page order; empty for pages without text
+    pub pages: Vec<String>,
+}
+
+// Pulls the text out of a PDF, page by page. Handles the common encodings
+// (Flate-compressed streams, object streams, ToUnicode maps and WinAnsi
+// fonts); scanned pages and fonts without a Unicode mapping yield no text.
+pub fn extract(data: &[u8]) -> Result<PdfText, String> {
+    if !data.starts_with(b"%PDF") {
+        return Err("not a PDF file".to_string());
+    }
+    let document = Document::parse(data);
+    if document.trailers.iter().any(|trailer| trailer.contains_key("Encrypt")) {
+        return Err("encrypted PDFs are not supported".to_string());
+    }
+
+    let title = document
+        .trailers
+        .iter()
+        .filter_map(|trailer| trailer.get("Info"))
+        .filter_map(|info| document.resolve(info).as_dict())
+        .find_map(|info| match info.get("Title").map(|title| document.resolve(title)) {
+            Some(Object::String(title)) => Some(decode_text_string(title)),
+            _ => None,
+        })
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let mut fonts = HashMap::new();
+    let pages = document
+        .pages()
+        .iter()
+        .map(|(page, resources)| {
+            let mut text = String::new();
+            let content = document.page_content(page);
+            document.show_text(&content, resources.as_ref(), &mut fonts, &mut text, 0);
+            tidy(&text)
+        })
+        .collect();
+    Ok(PdfText { title, pages })
+}
+
+struct Document {
+    objects: HashMap<u32, Object>,
+    // The trailer dictionaries, from `trailer` keywords and xref streams
+    trailers: Vec<Dict>,
+}
+
+impl Document {
+    // Reads every `N G obj ... endobj` in the file rather than trusting the
+    // cross-reference table, so damaged files still give up their text
+    fn parse(data: &[u8]) -> Self {
+        let mut objects = HashMap::new();
+        let mut trailers = Vec::new();
+        let mut pos = 0;
+        while let Some(found) = find(data, b"obj", pos) {
+            pos = found + 3;
+            let Some(number) = object_number(data, found) else {
+                continue;
+            };
+            if data.get(pos).is_some_and(|&b| !is_whitespace(b) && !is_delimiter(b)) {
+                continue;
+            }
+            let mut lexer = Lexer::new(data, pos);
+            let Some(object) = lexer.next_object() else {
+                break;
+            };
+            let object = match object {
+                Object::Dict(dict) => match lexer.stream_data(&dict) {
+                    Some(stream) => Object::Stream(dict, stream),
+                    None => Object::Dict(dict),
+                },
+                object => object,
+            };
+            pos = lexer.pos;
+            if let Object::Stream(dict, _) = &object {
+                if dict.get("Type").and_then(Object::as_name) == Some("XRef") {
+                    trailers.push(dict.clone());
+                }
+            }
+            objects.insert(number, object);
+        }
+
+        let mut pos = 0;
+        while let Some(found) = find(data, b"trailer", pos) {
+            pos = found + 7;
+            if let Some(Object::Dict(trailer)) = Lexer::new(data, pos).next_object() {
+                trailers.push(trailer);
+            }
+        }
+        // Newest first, as later updates override earlier ones
+        trailers.reverse();
+
+        let mut document = Self { objects, trailers };
+        document.unpack_object_streams();
+        document
+    }
+
+    // Objects compressed into object streams, unless also defined directly
+    fn unpack_object_streams(&mut self) {
+        let mut unpacked = Vec::new();
+        for object in self.objects.values() {
+            let Object::Stream(dict, _) = object else {
+                continue;
+            };
+            if dict.get("Type").and_then(Object::as_name) != Some("ObjStm") {
+                continue;
+            }
+            let Some(data) = self.decode_stream(object) else {
+                continue;
+            };
+            let count = dict.get("N").and_then(Object::as_number).unwrap_or(0.0) as usize;
+            let first = dict.get("First").and_then(Object::as_number).unwrap_or(0.0) as usize;
+            let mut header = Lexer::new(&data, 0);
+            for _ in 0..count {
+                let (Some(Object::Number(number)), Some(Object::Number(offset))) =
+                    (header.next_object(), header.next_object())
+                else {
+                    break;
+                };
+                let Some(start) = first.checked_add(offset as usize) else {
+                    continue;
+                };
+                if let Some(object) = Lexer::new(&data, start).next_object() {
+                    unpacked.push((number as u32, object));
+                }
+            }
+        }
+        for (number, object) in unpacked {
+            self.objects.entry(number).or_insert(object);
+        }
+    }
+
+    fn resolve<'a>(&'a self, object: &'a Object) -> &'a Object {
+        let mut object = object;
+        for _ in 0..MAX_NESTING {
+            match object {
+                Object::Ref(number) => object = self.objects.get(number).unwrap_or(&Object::Null),
+                _ => return object,
+            }
+        }
+        &Object::Null
+    }
+
+    fn get<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Object> {
+        dict.get(key).map(|object| self.resolve(object))
+    }
+
+    // Every page with the resources it uses, some of which pages inherit
+    // from their parents in the page tree
+    fn pages(&self) -> Vec<(Dict, Option<Dict>)> {
+        let root = self
+            .trailers
+            .iter()
+            .filter_map(|trailer| self.get(trailer, "Root"))
+            .find_map(Object::as_dict)
+            .or_else(|| {
+                self.objects
+                    .values()
+                    .filter_map(Object::as_dict)
+                    .find(|dict| dict.get("Type").and_then(Object::as_name) == Some("Catalog"))
+            });
+        let mut pages = Vec::new();
+        if let Some(tree) = root.and_then(|root| self.get(root, "Pages")).and_then(Object::as_dict) {
+            let mut visited = HashSet::new();
+            self.collect_pages(tree, None, &mut pages, &mut visited, 0);
+        }
+        pages
+    }
+
+    fn collect_pages(
+        &self,
+        node: &Dict,
+        inherited: Option<&Dict>,
+        pages: &mut Vec<(Dict, Option<Dict>)>,
+        visited: &mut HashSet<u32>,
+        depth: usize,
+    ) {
+        if depth > MAX_NESTING {
+            return;
+        }
+        let resources = self.get(node, "Resources").and_then(Object::as_dict).or(inherited);
+        let Some(Object::Array(kids)) = self.get(node, "Kids") else {
+            pages.push((node.clone(), resources.cloned()));
+            return;
+        };
+        for kid in kids {
+            if let Object::Ref(number) = kid {
+                if !visited.insert(*number) {
+                    continue;
+                }
+            }
+            if let Some(kid) = self.resolve(kid).as_dict() {
+                self.collect_pages(kid, resources, pages, visited, depth + 1);
+            }
+        }
+    }
+
+    // A page's content streams, decoded and joined
+    fn page_content(&self, page: &Dict) -> Vec<u8> {
+        let streams: Vec<&Object> = match page.get("Contents") {
+            Some(contents) => match self.resolve(contents) {
+                Object::Array(parts) => parts.iter().map(|part| self.resolve(part)).collect(),
+                stream => vec![stream],
+            },
+            None => Vec::new(),
+        };
+        let mut content = Vec::new();
+        for stream in streams {
+            if let Some(data) = self.decode_stream(stream) {
+                content.extend_from_slice(&data);
+                content.push(b'\n');
+            }
+        }
+        content
+    }
+
+    // None for streams in a format we can't decode (e.g. images)
+    fn decode_stream(&self, stream: &Object) -> Option<Vec<u8>> {
+        let Object::Stream(dict, data) = stream else {
+            return None;
+        };
+        let filters = match dict.get("Filter").map(|filter| self.resolve(filter)) {
+            None => Vec::new(),
+            Some(Object::Name(name)) => vec![name.clone()],
+            Some(Object::Array(names)) => {
+                names.iter().filter_map(|name| self.resolve(name).as_name().map(str::to_string)).collect()
+            }
+            Some(_) => return None,
+        };
+        let mut data = data.clone();
+        for filter in filters {
+            data = match filter.as_str() {
+                "FlateDecode" | "Fl" => inflate(&data),
+                "ASCIIHexDecode" | "AHx" => hex_decode(&data),
+                _ => return None,
+            };
+        }
+        Some(data)
+    }
+
+    // Interprets a content stream's text operators, appending what they show
+    fn show_text(
+        &self,
+        content: &[u8],
+        resources: Option<&Dict>,
+        fonts: &mut HashMap<u32, Font>,
+        text: &mut String,
+        depth: usize,
+    ) {
+        let mut lexer = Lexer::new(content, 0);
+        let mut operands: Vec<Object> = Vec::new();
+        let mut font = Font::default();
+        let mut line_y: Option<f64> = None;
+        while let Some(object) = lexer.next_object() {
+            let Object::Operator(operator) = object else {
+                operands.push(object);
+                continue;
+            };
+            match operator.as_str() {
+                "Tf" => {
+                    if let Some(name) = operands.first().and_then(Object::as_name) {
+                        font = self.font(resources, name, fonts);
+                    }
+                }
+                "Tj" | "'" | "\"" => {
+                    if operator != "Tj" {
+                        new_line(text);
+                    }
+                    if let Some(Object::String(bytes)) = operands.last() {
+                        text.push_str(&font.decode(bytes));
+                    }
+                }
+                "TJ" => {
+                    if let Some(Object::Array(parts)) = operands.last() {
+                        for part in parts {
+                            match part {
+                                Object::String(bytes) => text.push_str(&font.decode(bytes)),
+                                Object::Number(gap) if -gap > WORD_GAP => space(text),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                "Td" | "TD" => match operands.get(1).and_then(Object::as_number) {
+                    Some(y) if y != 0.0 => new_line(text),
+                    _ => space(text),
+                },
+                "T*" => new_line(text),
+                "Tm" => {
+                    let y = operands.get(5).and_then(Object::as_number);
+                    if y != line_y {
+                        new_line(text);
+                    } else {
+                        space(text);
+                    }
+                    line_y = y;
+                }
+                "BI" => lexer.skip_inline_image(),
+                "Do" if depth < MAX_FORM_DEPTH => {
+                    let form = operands.first().and_then(Object::as_name).and_then(|name| {
+                        let xobjects = self.get(resources?, "XObject")?.as_dict()?;
+                        self.get(xobjects, name)
+                    });
+                    if let Some(form @ Object::Stream(dict, _)) = form {
+                        if dict.get("Subtype").and_then(Object::as_name) == Some("Form") {
+                            let form_resources = self.get(dict, "Resources").and_then(Object::as_dict).or(resources);
+                            if let Some(data) = self.decode_stream(form) {
+                                self.show_text(&data, form_resources, fonts, text, depth + 1);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    fn font(&self, resources: Option<&Dict>, name: &str, fonts: &mut HashMap<u32, Font>) -> Font {
+        let Some(entry) = resources
+            .and_then(|resources| self.get(resources, "Font"))
+            .and_then(Object::as_dict)
+            .and_then(|fonts| fonts.get(name))
+        else {
+            return Font::default();
+        };
+        if let Object::Ref(number) = entry {
+            if let Some(font) = fonts.get(number) {
+                return font.clone();
+            }
+        }
+        let font = self.resolve(entry).as_dict().map(|dict| self.load_font(dict)).unwrap_or_default();
+        if let Object::Ref(number) = entry {
+            fonts.insert(*number, font.clone());
+        }
+        font
+    }
+
+    fn load_font(&self, dict: &Dict) -> Font {
+        let composite = dict.get("Subtype").and_then(Object::as_name) == Some("Type0");
+        let mut font = Font {
+            code_bytes: if composite { 2 } else { 1 },
+            unicode: HashMap::new(),
+        };
+        if let Some(cmap) = dict.get("ToUnicode").and_then(|cmap| self.decode_stream(self.resolve(cmap))) {
+            font.read_cmap(&cmap);
+        }
+        font
+    }
+}
+
+// How a font's character codes map to text
+#[derive(Clone)]
+struct Font {
+    code_bytes: usize,
+    // From the font's ToUnicode CMap
+    unicode: HashMap<u32, String>,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            code_bytes: 1,
+            unicode: HashMap::new(),
+        }
+    }
+}
+
+impl Font {
+    // Single-byte codes without a mapping are read as Windows-1252, which
+    // is what most simple fonts use; unmapped two-byte codes are dropped
+    fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        for code in bytes.chunks(self.code_bytes) {
+            let value = code.iter().fold(0u32, |value, &byte| value << 8 | u32::from(byte));
+            match self.unicode.get(&value) {
+                Some(mapped) => text.push_str(mapped),
+                None if self.code_bytes == 1 => text.extend(windows_1252(code[0])),
+                None => {}
+            }
+        }
+        text
+    }
+
+    fn read_cmap(&mut self, cmap: &[u8]) {
+        let mut lexer = Lexer::new(cmap, 0);
+        let mut operands: Vec<Object> = Vec::new();
+        while let Some(object) = lexer.next_object() {
+            let Object::Operator(operator) = object else {
+                operands.push(object);
+                continue;
+            };
+            match operator.as_str() {
+                "endcodespacerange" => {
+                    if let Some(Object::String(low)) = operands.first() {
+                        self.code_bytes = low.len().clamp(1, 4);
+                    }
+                }
+                "endbfchar" => {
+                    for pair in operands.chunks(2) {
+                        if let [Object::String(code), Object::String(target)] = pair {
+                            self.unicode.insert(code_value(code), utf16_be(target));
+                        }
+                    }
+                }
+                "endbfrange" => {
+                    for range in operands.chunks(3) {
+                        let [Object::String(low), Object::String(high), target] = range else {
+                            continue;
+                        };
+                        let (low, high) = (code_value(low), code_value(high));
+                        if high < low || high - low > 0xffff {
+                            continue;
+                        }
+                        for (i, code) in (low..=high).enumerate() {
+                            let mapped = match target {
+                                Object::String(first) => {
+                                    let mut units = utf16_units(first);
+                                    if let Some(last) = units.last_mut() {
+                                        *last = last.wrapping_add(i as u16);
+                                    }
+                                    String::from_utf16_lossy(&units)
+                                }
+                                Object::Array(targets) => match targets.get(i) {
+                                    Some(Object::String(target)) => utf16_be(target),
+                                    _ => continue,
+                                },
+                                _ => continue,
+                            };
+                            self.unicode.insert(code, mapped);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).fold(0u32, |value, &byte| value << 8 | u32::from(byte))
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect()
+}
+
+fn utf16_be(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&utf16_units(bytes))
+}
+
+// Strings outside content streams: UTF-16 with a byte order mark, or
+// PDFDocEncoding, which is close enough to Latin-1 for titles
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => utf16_be(utf16),
+        None => bytes.iter().map(|&byte| char::from(byte)).collect(),
+    }
+}
+
+// The printable part of Windows-1252 that differs from Latin-1
+fn windows_1252(byte: u8) -> Option<char> {
+    let c = match byte {
+        0x80 => '€',
+        0x85 => '…',
+        0x91 => '\u{2018}',
+        0x92 => '\u{2019}',
+        0x93 => '\u{201c}',
+        0x94 => '\u{201d}',
+        0x95 => '•',
+        0x96 => '–',
+        0x97 => '—',
+        0x99 => '™',
+        0x00..=0x08 | 0x0b..=0x1f | 0x7f..=0x9f => return None,
+        byte => char::from(byte),
+    };
+    Some(c)
+}
+
+fn new_line(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+fn space(text: &mut String) {
+    if !text.is_empty() && !text.ends_with([' ', '\n']) {
+        text.push(' ');
+    }
+}
+
+// Trims lines and keeps at most one blank line in a row
+fn tidy(text: &str) -> String {
+    let mut tidy = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            blank = !tidy.is_empty();
+            continue;
+        }
+        if !tidy.is_empty() {
+            tidy.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        tidy.push_str(line);
+        blank = false;
+    }
+    tidy
+}
+
+// Damaged streams give up whatever decompressed before the damage
+fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut inflated = Vec::new();
+    let _ = ZlibDecoder::new(data).take(MAX_STREAM_BYTES).read_to_end(&mut inflated);
+    inflated
+}
+
+fn hex_decode(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|&&b| b != b'>')
+        .filter_map(|&b| char::from(b).to_digit(16).map(|digit| digit as u8))
+        .collect();
+    digits.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)).collect()
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?.windows(needle.len()).position(|window| window == needle).map(|at| from + at)
+}
+
+// The N in `N G obj`, reading backwards from `obj` at `at`
+fn object_number(data: &[u8], at: usize) -> Option<u32> {
+    let mut pos = at;
+    let skip_space = |pos: &mut usize| {
+        let start = *pos;
+        while *pos > 0 && is_whitespace(data[*pos - 1]) {
+            *pos -= 1;
+        }
+        *pos < start
+    };
+    let skip_digits = |pos: &mut usize| {
+        let end = *pos;
+        while *pos > 0 && data[*pos - 1].is_ascii_digit() {
+            *pos -= 1;
+        }
+        (*pos < end).then(|| std::str::from_utf8(&data[*pos..end]).ok()?.parse::<u32>().ok()).flatten()
+    };
+    if !skip_space(&mut pos) {
+        return None;
+    }
+    skip_digits(&mut pos)?;
+    if !skip_space(&mut pos) {
+        return None;
+    }
+    skip_digits(&mut pos)
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, 0 | b'\t' | b'\n' | 0x0c | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+// Tokenizer for PDF objects and content streams
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte == b'%' {
+                while self.peek().is_some_and(|byte| byte != b'\n' && byte != b'\r') {
+                    self.pos += 1;
+                }
+            } else if is_whitespace(byte) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_object(&mut self) -> Option<Object> {
+        self.object(0)
+    }
+
+    // Arrays and dictionaries nested deeper than `MAX_NESTING` come back as
+    // nulls, their contents read as if they were one level up, so a file of
+    // nothing but `[` can't run the stack out
+    fn object(&mut self, depth: usize) -> Option<Object> {
+        self.skip_whitespace();
+        let byte = self.peek()?;
+        Some(match byte {
+            b'[' if depth >= MAX_NESTING => {
+                self.pos += 1;
+                Object::Null
+            }
+            b'<' if depth >= MAX_NESTING && self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                Object::Null
+            }
+            b'/' => {
+                self.pos += 1;
+                Object::Name(self.name())
+            }
+            b'(' => {
+                self.pos += 1;
+                Object::String(self.literal_string())
+            }
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                Object::Dict(self.dict(depth + 1))
+            }
+            b'<' => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|byte| byte != b'>') {
+                    self.pos += 1;
+                }
+                let string = hex_decode(&self.data[start..self.pos]);
+                // An unterminated string runs to the end of the data
+                if self.peek() == Some(b'>') {
+                    self.pos += 1;
+                }
+                Object::String(string)
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b']') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        None => break,
+                        _ => items.extend(self.object(depth + 1)),
+                    }
+                }
+                Object::Array(items)
+            }
+            b'0'..=b'9' | b'+' | b'-' | b'.' => self.number(),
+            b')' | b'>' | b']' | b'{' | b'}' => {
+                self.pos += 1;
+                Object::Operator(char::from(byte).to_string())
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|byte| !is_whitespace(byte) && !is_delimiter(byte)) {
+                    self.pos += 1;
+                }
+                match &self.data[start..self.pos] {
+                    b"null" => Object::Null,
+                    keyword => Object::Operator(String::from_utf8_lossy(keyword).into_owned()),
+                }
+            }
+        })
+    }
+
+    // Names may escape bytes as #xx
+    fn name(&mut self) -> String {
+        let mut name = Vec::new();
+        while let Some(byte) = self.peek().filter(|&byte| !is_whitespace(byte) && !is_delimiter(byte)) {
+            self.pos += 1;
+            let escaped = self.data.get(self.pos..self.pos + 2).filter(|_| byte == b'#');
+            match escaped.and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()) {
+                Some(decoded) => {
+                    name.push(decoded);
+                    self.pos += 2;
+                }
+                None => name.push(byte),
+            }
+        }
+        String::from_utf8_lossy(&name).into_owned()
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut string = Vec::new();
+        let mut depth = 1;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(0x08),
+                        b'f' => string.push(0x0c),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            string.push(value as u8);
+                        }
+                        // A backslash at the end of a line continues the string
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => string.push(other),
+                    }
+                }
+                b'(' => {
+                    depth += 1;
+                    string.push(byte);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    string.push(byte);
+                }
+                _ => string.push(byte),
+            }
+        }
+        string
+    }
+
+    fn dict(&mut self, depth: usize) -> Dict {
+        let mut dict = Dict::new();
+        loop {
+            self.skip_whitespace();
+            if self.data.get(self.pos..).is_some_and(|rest| rest.starts_with(b">>")) {
+                self.pos += 2;
+                break;
+            }
+            match self.object(depth) {
+                Some(Object::Name(key)) => {
+                    if let Some(value) = self.object(depth) {
+                        dict.insert(key, value);
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+        dict
+    }
+
+    // A number, or `N G R` when the number starts a reference
+    fn number(&mut self) -> Object {
+        let start = self.pos;
+        while self.peek().is_some_and(|byte| matches!(byte, b'0'..=b'9' | b'+' | b'-' | b'.')) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or_default();
+        let value = text.parse::<f64>().unwrap_or(0.0);
+        if text.bytes().all(|byte| byte.is_ascii_digit()) {
+            let after_number = self.pos;
+            self.skip_whitespace();
+            let generation_start = self.pos;
+            while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            if self.pos > generation_start {
+                self.skip_whitespace();
+                let is_ref = self.peek() == Some(b'R')
+                    && self.data.get(self.pos + 1).map_or(true, |&byte| is_whitespace(byte) || is_delimiter(byte));
+                if is_ref {
+                    self.pos += 1;
+                    return Object::Ref(value as u32);
+                }
+            }
+            self.pos = after_number;
+        }
+        Object::Number(value)
+    }
+
+    // The raw data of the stream following `dict`, if one follows
+    fn stream_data(&mut self, dict: &Dict) -> Option<Vec<u8>> {
+        self.skip_whitespace();
+        if !self.data.get(self.pos..).is_some_and(|rest| rest.starts_with(b"stream")) {
+            return None;
+        }
+        self.pos += 6;
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        // A direct /Length is trusted when `endstream` follows it; an
+        // indirect one would need the object it points to, so search instead
+        let declared = dict
+            .get("Length")
+            .and_then(Object::as_number)
+            .and_then(|length| start.checked_add(length as usize));
+        let end = declared
+            .filter(|&end| {
+                let mut after = Lexer::new(self.data, end);
+                after.skip_whitespace();
+                self.data.get(after.pos..).is_some_and(|rest| rest.starts_with(b"endstream"))
+            })
+            .or_else(|| find(self.data, b"endstream", start))
+            .unwrap_or(self.data.len());
+        let data = self.data[start..end].to_vec();
+        self.pos = find(self.data, b"endstream", end).map_or(self.data.len(), |at| at + 9);
+        Some(data)
+    }
+
+    // Inline image data is binary; skips to the `EI` that ends it
+    fn skip_inline_image(&mut self) {
+        let Some(data_start) = find(self.data, b"ID", self.pos) else {
+            self.pos = self.data.len();
+            return;
+        };
+        let mut pos = data_start + 2;
+        while let Some(at) = find(self.data, b"EI", pos) {
+            let before = at.checked_sub(1).map(|before| self.data[before]);
+            let ends = self.data.get(at + 2).map_or(true, |&byte| is_whitespace(byte) || is_delimiter(byte));
+            if before.is_some_and(is_whitespace) && ends {
+                self.pos = at + 2;
+                return;
+            }
+            pos = at + 2;
+        }
+        self.pos = self.data.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Files added with `addDocumentFromFile` are untrusted; broken ones
+    // should come back as an error or no text, never a panic
+    #[test]
+    fn truncated_input_does_not_panic() {
+        let inputs: [&[u8]; 6] = [
+            b"%PDF-1.4\n1 0 obj\n<< /A <41",
+            b"%PDF-1.4\n1 0 obj\n<< /A <41>",
+            b"%PDF-1.4\n1 0 obj\n<< /A (abc",
+            b"%PDF-1.4\n1 0 obj\n<< /Length 5 >>\nstream\nab",
+            b"%PDF-1.4\n1 0 obj\n<< /Name#4",
+            b"%PDF-1.4\n1 0 obj\n<<",
+        ];
+        for input in inputs {
+            let _ = extract(input);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_does_not_overflow_the_stack() {
+        for opener in ["[", "<< /A "] {
+            let mut input = b"%PDF-1.4\n1 0 obj\n".to_vec();
+            input.extend(opener.repeat(100_000).bytes());
+            let _ = extract(&input);
+        }
+    }
+
+    #[test]
+    fn oversized_lengths_do_not_panic() {
+        let _ = extract(b"%PDF-1.4\n1 0 obj\n<< /Length 99999999999999999999 >>\nstream\nabc\nendstream\nendobj\n");
+        let _ = extract(
+            b"%PDF-1.4\n1 0 obj\n<< /Type /ObjStm /N 1 /First 99999999999999999999 /Length 5 >>\n\
+              stream\n2 99999999999999999999\nendstream\nendobj\n",
+        );
+    }
+}
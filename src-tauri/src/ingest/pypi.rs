@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use url::Url;
+
+use super::docsite::{self, DocsSiteOptions};
+use super::inventory::inventory_url;
+use super::{date_millis, CrawlManager, CrawlStatus, Fetcher, InventoryCrawlOptions};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+const PYPI_API: &str = "https://pypi.org/pypi";
+// Ids link to the release's page
+const PYPI_PROJECTS: &str = "https://pypi.org/project";
+
+// Project metadata is small; a long description is the bulk of it
+const MAX_METADATA_BYTES: usize = 16 * 1024 * 1024;
+
+// Hosts whose pages are the README again, not docs of their own
+const NOT_DOCS_HOSTS: &[&str] = &["github.com", "gitlab.com", "bitbucket.org", "pypi.org"];
+
+const DOCS_CONCURRENCY: usize = 4;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PypiPackageOptions {
+    /// The project's name on PyPI, e.g. "requests"
+    pub name: String,
+    /// Defaults to the newest release
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Source to file the documents under; defaults to "pypi:{name}", with
+    /// the name normalized the way PyPI does (lowercase, runs of -_. as -)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Also index the project's documentation site (default true)
+    #[serde(default = "default_index_docs")]
+    pub index_docs: bool,
+    /// Pages read from the documentation site at most (default 1000)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+}
+
+pub(super) fn default_index_docs() -> bool {
+    true
+}
+
+pub(super) fn default_max_pages() -> usize {
+    1000
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PypiPackageResponse {
+    pub source: String,
+    pub version: String,
+    /// The documentation site the project declares, if any
+    pub docs_url: Option<String>,
+    /// The project page plus the sections of a site read directly
+    pub indexed: usize,
+    /// What couldn't be indexed, as "where: reason"
+    pub errors: Vec<String>,
+    /// The crawl of the site's pages, for Sphinx and sites without an
+    /// llms.txt or a search index, which runs in the background
+    pub crawl: Option<CrawlStatus>,
+}
+
+// Indexes a PyPI project: one document with its metadata and description
+// (the README), and the documentation site it links to. Sphinx sites are
+// crawled page by page from their objects.inv; others are indexed as
+// `indexDocsSite` does, with `on_progress` getting (sections written, total).
+pub async fn index_pypi_package(
+    fetcher: &Fetcher,
+    crawls: &CrawlManager,
+    search_service: Arc<SearchService>,
+    options: PypiPackageOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<PypiPackageResponse, McpError> {
+    let name = normalize_name(&options.name);
+    let mut url = Url::parse(PYPI_API).map_err(|e| McpError::internal(e.to_string()))?;
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.push(&name);
+        if let Some(version) = &options.version {
+            segments.push(version.trim());
+        }
+        segments.push("json");
+    }
+    let metadata = fetcher.fetch_if_exists(&url, MAX_METADATA_BYTES).await?.ok_or_else(|| {
+        let what = options.version.as_deref().map(|version| format!(" {}", version.trim())).unwrap_or_default();
+        McpError::not_found(format!("PyPI project {}{}", name, what))
+    })?;
+    let metadata: Value = serde_json::from_slice(&metadata)
+        .map_err(|e| McpError::fetch(url.as_str(), format!("not project metadata: {}", e)))?;
+    let info = &metadata["info"];
+    let version = info["version"].as_str().unwrap_or_default().to_string();
+    let source = options.source.clone().unwrap_or_else(|| format!("pypi:{}", name));
+
+    let mut errors = Vec::new();
+    let url = format!("{}/{}/{}/", PYPI_PROJECTS, name, version);
+    let document = SearchableDocument {
+        id: url.clone(),
+        title: format!("{} {}", info["name"].as_str().unwrap_or(&name), version),
+        body: project_body(info),
+        source: source.clone(),
+        version: Some(version.clone()),
+        url: Some(url),
+        tags: Vec::new(),
+        indexed_at: None,
+        // The release's first upload
+        published_at: metadata["urls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|file| file["upload_time_iso_8601"].as_str().and_then(date_millis))
+            .min(),
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    };
+    let mut indexed = 0;
+    match validate_document(&document, "document") {
+        Ok(()) => {
+            let search_service = search_service.clone();
+            tokio::task::spawn_blocking(move || search_service.add_document(document))
+                .await
+                .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+                .map_err(|e| McpError::index("Failed to add project", e))?;
+            indexed += 1;
+        }
+        Err(e) => errors.push(format!("{}: {}", document.id, e)),
+    }
+
+    let docs_url = docs_url(info);
+    let mut crawl = None;
+    if let Some(docs) = docs_url.as_ref().filter(|_| options.index_docs) {
+        // Sphinx sites have an inventory at their root
+        let inventory = inventory_url(docs);
+        if fetcher.fetch_if_exists(&inventory, MAX_METADATA_BYTES).await.is_ok_and(|found| found.is_some()) {
+            crawl = Some(crawls.start_inventory(InventoryCrawlOptions {
+                url: inventory.to_string(),
+                source: source.clone(),
+                version: Some(version.clone()),
+                domains: Vec::new(),
+                same_host: true,
+                path_prefix: None,
+                max_pages: options.max_pages,
+                concurrency: DOCS_CONCURRENCY,
+                host_concurrency: DOCS_CONCURRENCY,
+                ignore_robots_txt: false,
+            })?);
+        } else {
+            let site = DocsSiteOptions {
+                url: docs.to_string(),
+                source: Some(source.clone()),
+                version: Some(version.clone()),
+                max_pages: options.max_pages,
+                concurrency: DOCS_CONCURRENCY,
+                llms_txt: true,
+            };
+            match docsite::index_docs_site(fetcher, crawls, search_service, site, on_progress).await {
+                Ok(site) => {
+                    indexed += site.indexed;
+                    errors.extend(site.errors);
+                    crawl = site.crawl;
+                }
+                Err(e) => errors.push(format!("{}: {}", docs, e)),
+            }
+        }
+    }
+    info!("Indexed {} documents of PyPI project {} {} as source {}", indexed, name, version, source);
+    Ok(PypiPackageResponse {
+        source,
+        version,
+        docs_url: docs_url.map(String::from),
+        indexed,
+        errors,
+        crawl,
+    })
+}
+
+// PEP 503: lowercase, with runs of -, _ and . made a single -
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::new();
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.extend(c.to_lowercase());
+        }
+    }
+    normalized
+}
+
+// The summary, the facts an agent asks about (supported Pythons,
+// dependencies, links) and the description
+fn project_body(info: &Value) -> String {
+    let mut body = String::new();
+    if let Some(summary) = info["summary"].as_str().filter(|summary| !summary.trim().is_empty()) {
+        body.push_str(summary.trim());
+        body.push_str("\n\n");
+    }
+    let mut facts = Vec::new();
+    for (key, label) in [("requires_python", "Requires Python"), ("license", "License"), ("author", "Author")] {
+        if let Some(value) = info[key].as_str().filter(|value| !value.trim().is_empty() && value.len() < 200) {
+            facts.push(format!("- {}: {}", label, value.trim()));
+        }
+    }
+    let dependencies: Vec<&str> = info["requires_dist"]
+        .as_array()
+        .map(|dependencies| dependencies.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if !dependencies.is_empty() {
+        facts.push(format!("- Dependencies: {}", dependencies.join(", ")));
+    }
+    if let Some(links) = info["project_urls"].as_object() {
+        facts.extend(links.iter().filter_map(|(label, url)| Some(format!("- {}: {}", label, url.as_str()?))));
+    }
+    if !facts.is_empty() {
+        body.push_str("## Project\n\n");
+        body.push_str(&facts.join("\n"));
+        body.push_str("\n\n");
+    }
+    if let Some(description) = info["description"].as_str().filter(|description| !description.trim().is_empty()) {
+        body.push_str(description.trim());
+    }
+    body.trim().to_string()
+}
+
+// The project's documentation site: a "Documentation" link, the docs URL
+// PyPI hosted, or a home page that is a docs site (e.g. on Read the Docs)
+fn docs_url(info: &Value) -> Option<Url> {
+    let links = info["project_urls"].as_object();
+    let labelled = links.and_then(|links| {
+        links
+            .iter()
+            .filter(|(label, _)| label.to_ascii_lowercase().contains("doc"))
+            .find_map(|(_, url)| url.as_str())
+    });
+    let home = links
+        .and_then(|links| links.iter().find(|(label, _)| label.eq_ignore_ascii_case("homepage")))
+        .and_then(|(_, url)| url.as_str())
+        .or_else(|| info["home_page"].as_str())
+        .filter(|home| home.contains("readthedocs.") || home.contains("://docs."));
+    [labelled, info["docs_url"].as_str(), home]
+        .into_iter()
+        .flatten()
+        .filter_map(|url| Url::parse(url.trim()).ok())
+        .find(|url| {
+            matches!(url.scheme(), "http" | "https")
+                && url.host_str().is_some_and(|host| !NOT_DOCS_HOSTS.contains(&host.to_ascii_lowercase().as_str()))
+        })
+}
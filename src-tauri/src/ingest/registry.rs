@@ -0,0 +1,185 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+use super::Fetcher;
+use crate::McpError;
+
+const CRATES_IO_API: &str = "https://crates.io/api/v1/crates";
+const NPM_REGISTRY: &str = "https://registry.npmjs.org";
+const PYPI_API: &str = "https://pypi.org/pypi";
+
+// npm metadata lists every release with its manifest; for the busiest
+// packages that's tens of MB
+const MAX_METADATA_BYTES: usize = 128 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    #[serde(alias = "crates.io", alias = "cargo")]
+    Crates,
+    Npm,
+    #[serde(alias = "pip")]
+    Pypi,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestVersionParams {
+    /// "crates", "npm" or "pypi"
+    pub ecosystem: Ecosystem,
+    /// The package's name in that registry, e.g. "tokio", "@types/node", "requests"
+    pub package: String,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub version: String,
+    /// When the release was published, as the registry reports it (RFC 3339)
+    pub published: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestVersionResponse {
+    pub ecosystem: Ecosystem,
+    pub package: String,
+    /// The newest stable release; None when there are only pre-releases
+    pub latest: Option<Release>,
+    /// A pre-release newer than `latest`, if there is one
+    pub prerelease: Option<Release>,
+}
+
+// Looks up a package's newest releases in its registry's metadata API
+pub async fn latest_version(
+    fetcher: &Fetcher,
+    params: &LatestVersionParams,
+) -> Result<LatestVersionResponse, McpError> {
+    let package = params.package.trim();
+    let base = match params.ecosystem {
+        Ecosystem::Crates => CRATES_IO_API,
+        Ecosystem::Npm => NPM_REGISTRY,
+        Ecosystem::Pypi => PYPI_API,
+    };
+    let mut url = Url::parse(base).map_err(|e| McpError::internal(e.to_string()))?;
+    if let Ok(mut segments) = url.path_segments_mut() {
+        // Scoped npm packages keep their slash, escaped: @types%2Fnode
+        segments.push(package);
+        if params.ecosystem == Ecosystem::Pypi {
+            segments.push("json");
+        }
+    }
+    let metadata = fetcher
+        .fetch_if_exists(&url, MAX_METADATA_BYTES)
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("package {}", package)))?;
+    let metadata: Value = serde_json::from_slice(&metadata)
+        .map_err(|e| McpError::fetch(url.as_str(), format!("not package metadata: {}", e)))?;
+
+    let (latest, prerelease) = match params.ecosystem {
+        Ecosystem::Crates => crates_releases(&metadata),
+        Ecosystem::Npm => npm_releases(&metadata),
+        Ecosystem::Pypi => pypi_releases(&metadata),
+    };
+    if latest.is_none() && prerelease.is_none() {
+        return Err(McpError::not_found(format!("releases of {}", package)));
+    }
+    Ok(LatestVersionResponse {
+        ecosystem: params.ecosystem,
+        package: package.to_string(),
+        latest,
+        prerelease,
+    })
+}
+
+// crates.io names the newest stable and newest overall release; dates come
+// from the version list
+fn crates_releases(metadata: &Value) -> (Option<Release>, Option<Release>) {
+    let krate = &metadata["crate"];
+    let versions = metadata["versions"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let release = |version: &str| Release {
+        version: version.to_string(),
+        published: versions
+            .iter()
+            .find(|entry| entry["num"].as_str() == Some(version))
+            .and_then(|entry| entry["created_at"].as_str())
+            .map(str::to_string),
+    };
+    let stable = krate["max_stable_version"].as_str().filter(|version| !version.is_empty());
+    let newest = krate["max_version"].as_str().filter(|version| !version.is_empty());
+    let prerelease = newest.filter(|newest| Some(*newest) != stable && newest.contains('-'));
+    (stable.map(release), prerelease.map(release))
+}
+
+// npm's `latest` tag is the stable release; the newest pre-release is the
+// most recently published version with a pre-release part
+fn npm_releases(metadata: &Value) -> (Option<Release>, Option<Release>) {
+    let empty = Map::new();
+    let times = metadata["time"].as_object().unwrap_or(&empty);
+    let published = |version: &str| times.get(version).and_then(Value::as_str).map(str::to_string);
+    let latest = metadata["dist-tags"]["latest"].as_str().map(|version| Release {
+        version: version.to_string(),
+        published: published(version),
+    });
+    let versions = metadata["versions"].as_object().unwrap_or(&empty);
+    let prerelease = versions
+        .keys()
+        .filter(|version| version.contains('-'))
+        .filter_map(|version| Some((published(version)?, version)))
+        .max()
+        .map(|(published, version)| Release {
+            version: version.clone(),
+            published: Some(published),
+        });
+    (latest.clone(), newer(prerelease, latest.as_ref()))
+}
+
+// PyPI's `info.version` is the newest stable release (or pre-release, when
+// there are only those). A release's date is its first file's upload.
+fn pypi_releases(metadata: &Value) -> (Option<Release>, Option<Release>) {
+    let empty = Map::new();
+    let releases = metadata["releases"].as_object().unwrap_or(&empty);
+    let published = |version: &str| {
+        let files = releases.get(version)?.as_array()?;
+        // Releases whose files were all yanked don't count
+        if files.iter().all(|file| file["yanked"].as_bool() == Some(true)) {
+            return None;
+        }
+        files.iter().filter_map(|file| file["upload_time_iso_8601"].as_str()).min().map(str::to_string)
+    };
+    let latest = metadata["info"]["version"]
+        .as_str()
+        .filter(|version| !is_pep440_prerelease(version))
+        .map(|version| Release {
+            version: version.to_string(),
+            published: published(version),
+        });
+    let prerelease = releases
+        .keys()
+        .filter(|version| is_pep440_prerelease(version))
+        .filter_map(|version| Some((published(version)?, version)))
+        .max()
+        .map(|(published, version)| Release {
+            version: version.clone(),
+            published: Some(published),
+        });
+    (latest.clone(), newer(prerelease, latest.as_ref()))
+}
+
+// The pre-release, if it came out after the stable release. RFC 3339 dates
+// in UTC compare as text.
+fn newer(prerelease: Option<Release>, latest: Option<&Release>) -> Option<Release> {
+    let latest_published = latest.and_then(|latest| latest.published.as_deref());
+    prerelease.filter(|prerelease| match (prerelease.published.as_deref(), latest_published) {
+        (Some(prerelease_date), Some(latest_date)) => prerelease_date > latest_date,
+        _ => latest.is_none(),
+    })
+}
+
+// 1.0a1, 2.0b3, 3.0rc1 and 4.0.dev2 are pre-releases; 1.0.post1 is not
+fn is_pep440_prerelease(version: &str) -> bool {
+    let public = version.split('+').next().unwrap_or_default().to_ascii_lowercase();
+    public.replace("post", "").chars().any(|c| c.is_ascii_alphabetic())
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use super::github::{parse_repo, repo_metrics, GitHubApi};
+use super::{date_millis, Fetcher};
+use crate::search::{SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+const GITHUB: &str = "https://github.com";
+
+// The most the releases API hands out per page
+const RELEASES_PER_PAGE: usize = 100;
+
+// Looked for at the repository's root, in this order
+const CHANGELOG_NAMES: &[&str] = &["changelog.md", "changes.md", "history.md", "news.md", "releases.md", "changelog"];
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotesOptions {
+    /// "owner/repo", or the repository's URL
+    pub repo: String,
+    /// Token for private repositories and a higher rate limit; defaults to
+    /// $GITHUB_TOKEN. Never recorded in the audit log.
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+    /// Also index the sections of the repository's CHANGELOG.md for versions
+    /// without release notes on GitHub (default true)
+    #[serde(default = "default_changelog")]
+    pub changelog: bool,
+    /// Also index pre-releases (default true)
+    #[serde(default = "default_prereleases")]
+    pub prereleases: bool,
+    /// The newest releases indexed at most (default 200)
+    #[serde(default = "default_max_releases")]
+    pub max_releases: usize,
+    /// Source to file the notes under; defaults to "releases:{owner}/{repo}"
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+fn default_changelog() -> bool {
+    true
+}
+
+fn default_prereleases() -> bool {
+    true
+}
+
+fn default_max_releases() -> usize {
+    200
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotesResponse {
+    pub source: String,
+    /// "owner/repo"
+    pub repo: String,
+    /// The versions whose notes were indexed, newest first
+    pub versions: Vec<String>,
+    /// The changelog file read, if one was found
+    pub changelog: Option<String>,
+    /// Notes that couldn't be read or indexed, as "version: reason"
+    pub errors: Vec<String>,
+}
+
+// Indexes a project's release notes, one document per release with the
+// release as its version: the GitHub Releases first, then the sections of
+// its changelog for the versions those don't cover. `on_progress` gets
+// (releases written, total).
+pub async fn index_release_notes(
+    fetcher: &Fetcher,
+    search_service: Arc<SearchService>,
+    options: ReleaseNotesOptions,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<ReleaseNotesResponse, McpError> {
+    let repo = parse_repo(&options.repo)?;
+    let api = GitHubApi::new(fetcher, options.token.clone());
+    let metadata = api
+        .get(&format!("repos/{}", repo))
+        .await?
+        .ok_or_else(|| McpError::not_found(format!("GitHub repository {}", repo)))?;
+    let source = options.source.clone().unwrap_or_else(|| format!("releases:{}", repo));
+
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+    let mut page = 1;
+    while documents.len() < options.max_releases {
+        let path = format!("repos/{}/releases?per_page={}&page={}", repo, RELEASES_PER_PAGE, page);
+        let releases = api.get(&path).await?.unwrap_or_default();
+        let releases = releases.as_array().map(Vec::as_slice).unwrap_or_default();
+        for release in releases {
+            if release["draft"].as_bool() == Some(true)
+                || (release["prerelease"].as_bool() == Some(true) && !options.prereleases)
+            {
+                continue;
+            }
+            if let Some(document) = release_document(&repo, release, &source) {
+                documents.push(document);
+            }
+        }
+        if releases.len() < RELEASES_PER_PAGE {
+            break;
+        }
+        page += 1;
+    }
+    documents.truncate(options.max_releases);
+
+    let mut changelog = None;
+    if options.changelog && documents.len() < options.max_releases {
+        let branch = metadata["default_branch"].as_str().unwrap_or("main");
+        match changelog_file(&api, &repo, branch).await {
+            Ok(Some((name, text))) => {
+                let covered: HashSet<String> =
+                    documents.iter().filter_map(|document| document.version.clone()).collect();
+                let url = format!("{}/{}/blob/{}/{}", GITHUB, repo, branch, name);
+                let sections = changelog_sections(&text)
+                    .into_iter()
+                    .filter(|(version, _)| !covered.contains(version))
+                    .filter(|(version, _)| options.prereleases || !version.contains('-'))
+                    .take(options.max_releases - documents.len())
+                    .map(|(version, body)| SearchableDocument {
+                        id: format!("{}#{}", url, version),
+                        url: Some(url.clone()),
+                        tags: Vec::new(),
+                        indexed_at: None,
+                        published_at: None,
+                        metrics: Default::default(),
+                        facet: None,
+                        parent_id: None,
+                        heading_path: Vec::new(),
+                        anchor: None,
+                        content_hash: None,
+                        title: format!("{} {} changelog", repo, version),
+                        body,
+                        source: source.clone(),
+                        version: Some(version),
+                    })
+                    .collect::<Vec<_>>();
+                documents.extend(sections);
+                changelog = Some(url);
+            }
+            Ok(None) => {}
+            Err(e) => errors.push(format!("changelog: {}", e)),
+        }
+    }
+
+    let metrics = repo_metrics(&metadata);
+    let mut valid = Vec::new();
+    for mut document in documents {
+        document.metrics = metrics.clone();
+        match validate_document(&document, "document") {
+            Ok(()) => valid.push(document),
+            Err(e) => errors.push(format!("{}: {}", document.version.unwrap_or_default(), e)),
+        }
+    }
+    let versions = valid.iter().filter_map(|document| document.version.clone()).collect();
+    if !valid.is_empty() {
+        tokio::task::spawn_blocking(move || search_service.add_documents(valid, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+            .map_err(|e| McpError::index("Failed to add release notes", e))?;
+    }
+    info!("Indexed the release notes of {} as source {}", repo, source);
+    Ok(ReleaseNotesResponse {
+        source,
+        repo,
+        versions,
+        changelog,
+        errors,
+    })
+}
+
+// A GitHub release's notes, under its name, publish date and tag. None for
+// releases without notes, which the changelog may cover instead.
+fn release_document(repo: &str, release: &Value, source: &str) -> Option<SearchableDocument> {
+    let notes = release["body"].as_str().map(str::trim).filter(|notes| !notes.is_empty())?;
+    let tag = release["tag_name"].as_str()?;
+    let version = tag_version(tag);
+    let mut body = String::new();
+    if let Some(name) = release["name"].as_str().map(str::trim).filter(|name| !name.is_empty() && *name != tag) {
+        body.push_str(&format!("# {}\n\n", name));
+    }
+    let published = release["published_at"].as_str().and_then(|date| date.get(..10));
+    let mut facts = vec![format!("Tag: {}", tag)];
+    facts.extend(published.map(|date| format!("Released: {}", date)));
+    if release["prerelease"].as_bool() == Some(true) {
+        facts.push("Pre-release".to_string());
+    }
+    body.push_str(&facts.join(". "));
+    body.push_str("\n\n");
+    body.push_str(notes);
+    let id = release["html_url"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}/{}/releases/tag/{}", GITHUB, repo, tag));
+    Some(SearchableDocument {
+        id: id.clone(),
+        title: format!("{} {} release notes", repo, version),
+        body,
+        source: source.to_string(),
+        version: Some(version),
+        url: Some(id.clone()),
+        tags: Vec::new(),
+        indexed_at: None,
+        published_at: release["published_at"].as_str().and_then(date_millis),
+        metrics: Default::default(),
+        facet: None,
+        parent_id: None,
+        heading_path: Vec::new(),
+        anchor: None,
+        content_hash: None,
+    })
+}
+
+// The changelog at the repository's root, as its file name and text
+async fn changelog_file(api: &GitHubApi<'_>, repo: &str, branch: &str) -> Result<Option<(String, String)>, McpError> {
+    let Some(root) = api.get(&format!("repos/{}/contents/?ref={}", repo, branch)).await? else {
+        return Ok(None);
+    };
+    let files = root.as_array().map(Vec::as_slice).unwrap_or_default();
+    let found = CHANGELOG_NAMES.iter().find_map(|wanted| {
+        files
+            .iter()
+            .filter(|file| file["type"].as_str() == Some("file"))
+            .find(|file| file["name"].as_str().is_some_and(|name| name.eq_ignore_ascii_case(wanted)))
+    });
+    let Some((name, url)) = found.and_then(|file| Some((file["name"].as_str()?, file["download_url"].as_str()?))) else {
+        return Ok(None);
+    };
+    Ok(api.download(url).await?.map(|text| (name.to_string(), text)))
+}
+
+// Splits a changelog into its versions' sections, newest first as written.
+// The headings that name versions are those at the level where most of
+// them are, e.g. "## [1.2.0] - 2024-05-01" or "# v1.2.0"; "Unreleased" and
+// other headings at that level end a section without starting one.
+fn changelog_sections(text: &str) -> Vec<(String, String)> {
+    let mut headings = Vec::new();
+    let mut in_code = false;
+    for (index, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+            headings.push((index, level, heading_version(&line[level..])));
+        }
+    }
+    let level = (1..=6)
+        .max_by_key(|level| {
+            let versions = headings.iter().filter(|(_, l, version)| l == level && version.is_some()).count();
+            // Ties go to the higher level
+            (versions, std::cmp::Reverse(*level))
+        })
+        .unwrap_or(2);
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut sections = Vec::new();
+    let mut seen = HashSet::new();
+    let starts: Vec<&(usize, usize, Option<String>)> = headings.iter().filter(|(_, l, _)| *l <= level).collect();
+    for (position, (start, heading_level, version)) in starts.iter().enumerate() {
+        let Some(version) = version.as_ref().filter(|_| *heading_level == level) else {
+            continue;
+        };
+        let end = starts.get(position + 1).map_or(lines.len(), |(end, _, _)| *end);
+        let body = lines[*start..end].join("\n").trim().to_string();
+        // A version listed twice keeps its first, newest, section
+        if body.lines().count() > 1 && seen.insert(version.clone()) {
+            sections.push((version.clone(), body));
+        }
+    }
+    sections
+}
+
+// The version a changelog heading names: "[1.2.0] - 2024-05-01", "v1.2.0
+// (2024-05-01)", "Version 1.2" and "tokio-1.38.0" all name one; dates don't
+fn heading_version(heading: &str) -> Option<String> {
+    heading
+        .split(|c: char| c.is_whitespace() || matches!(c, '[' | ']' | '(' | ')' | '*' | '`'))
+        .map(tag_version)
+        .find(|word| {
+            word.starts_with(|c: char| c.is_ascii_digit())
+                && word.contains('.')
+                && word.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+        })
+}
+
+// A release's version from its tag: "v1.2.0", "1.2.0" and "tokio-1.2.0" are
+// all 1.2.0
+fn tag_version(tag: &str) -> String {
+    let tag = tag.trim().trim_end_matches([':', ',', ';']);
+    let start = tag
+        .char_indices()
+        .find(|(index, c)| {
+            c.is_ascii_digit() && tag[..*index].chars().last().map_or(true, |before| "vV-@/".contains(before))
+        })
+        .map_or(0, |(index, _)| index);
+    tag[start..].to_string()
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+use tracing::warn;
+use url::Url;
+
+use super::fetch::{read_body, USER_AGENT_TOKEN};
+use super::Fetcher;
+
+// RFC 9309 asks crawlers to read at least this much of a robots.txt
+const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+// Longer Crawl-delay values are capped, so one odd site can't stall a crawl
+// for hours
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+// The rules of one host that apply to us
+#[derive(Default)]
+struct HostRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    // Set when robots.txt couldn't be read for a reason other than it not
+    // existing, which RFC 9309 treats as "disallow everything"
+    unreachable: bool,
+}
+
+impl HostRules {
+    // The longest matching pattern decides; Allow wins a tie
+    fn allows(&self, url: &Url) -> bool {
+        if self.unreachable {
+            return false;
+        }
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .map_or(true, |rule| rule.allow)
+    }
+}
+
+// robots.txt handling for one crawl: reads each host's file once, answers
+// whether a URL may be fetched, and spaces out requests to hosts that ask
+// for a Crawl-delay.
+pub struct RobotsPolicy {
+    fetcher: Arc<Fetcher>,
+    hosts: Mutex<HashMap<String, Arc<OnceCell<HostRules>>>>,
+    // When the next request to each delayed host may start
+    next_request: Mutex<HashMap<String, Instant>>,
+}
+
+impl RobotsPolicy {
+    pub fn new(fetcher: Arc<Fetcher>) -> Self {
+        Self {
+            fetcher,
+            hosts: Mutex::new(HashMap::new()),
+            next_request: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // False if robots.txt disallows `url`. Otherwise waits out the host's
+    // Crawl-delay, if any, before returning true.
+    pub async fn admit(&self, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        let cell = self.hosts.lock().unwrap().entry(origin.clone()).or_default().clone();
+        let rules = cell.get_or_init(|| self.load(url)).await;
+        if !rules.allows(url) {
+            return false;
+        }
+
+        if let Some(delay) = rules.crawl_delay {
+            let start = {
+                let mut next_request = self.next_request.lock().unwrap();
+                let now = Instant::now();
+                let start = next_request.get(&origin).map_or(now, |next| (*next).max(now));
+                next_request.insert(origin, start + delay);
+                start
+            };
+            tokio::time::sleep_until(start).await;
+        }
+        true
+    }
+
+    async fn load(&self, url: &Url) -> HostRules {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return HostRules::default();
+        };
+        let response = match self.fetcher.get(&robots_url).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Could not read {}, not crawling the host: {}", robots_url, e);
+                return HostRules {
+                    unreachable: true,
+                    ..HostRules::default()
+                };
+            }
+        };
+
+        let status = response.status();
+        if status.is_client_error() {
+            // No robots.txt (or not for us to see) means no restrictions
+            return HostRules::default();
+        }
+        if !status.is_success() {
+            warn!("{} answered {}, not crawling the host", robots_url, status);
+            return HostRules {
+                unreachable: true,
+                ..HostRules::default()
+            };
+        }
+        match read_body(response, &robots_url, MAX_ROBOTS_BYTES).await {
+            Ok(text) => parse(&text),
+            Err(e) => {
+                warn!("Could not read {}, not crawling the host: {}", robots_url, e);
+                HostRules {
+                    unreachable: true,
+                    ..HostRules::default()
+                }
+            }
+        }
+    }
+}
+
+// Keeps the groups addressed to us by name, or else the `*` groups
+fn parse(text: &str) -> HostRules {
+    struct Group {
+        agents: Vec<String>,
+        rules: Vec<Rule>,
+        crawl_delay: Option<Duration>,
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut in_agent_lines = false;
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        if key == "user-agent" {
+            if !in_agent_lines {
+                groups.push(Group {
+                    agents: Vec::new(),
+                    rules: Vec::new(),
+                    crawl_delay: None,
+                });
+            }
+            in_agent_lines = true;
+            if let Some(group) = groups.last_mut() {
+                group.agents.push(value.to_ascii_lowercase());
+            }
+            continue;
+        }
+        in_agent_lines = false;
+
+        // Lines before the first User-agent belong to no group
+        let Some(group) = groups.last_mut() else {
+            continue;
+        };
+        match key.as_str() {
+            "allow" | "disallow" if !value.is_empty() => group.rules.push(Rule {
+                allow: key == "allow",
+                pattern: value.to_string(),
+            }),
+            "crawl-delay" => {
+                group.crawl_delay = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                    .map(|seconds| Duration::from_secs_f64(seconds).min(MAX_CRAWL_DELAY));
+            }
+            _ => {}
+        }
+    }
+
+    let token = USER_AGENT_TOKEN.to_ascii_lowercase();
+    let ours = |group: &Group| group.agents.contains(&token);
+    let wanted: Vec<Group> = if groups.iter().any(ours) {
+        groups.into_iter().filter(ours).collect()
+    } else {
+        groups.into_iter().filter(|group| group.agents.iter().any(|agent| agent == "*")).collect()
+    };
+
+    let mut rules = HostRules::default();
+    for group in wanted {
+        rules.rules.extend(group.rules);
+        rules.crawl_delay = rules.crawl_delay.max(group.crawl_delay);
+    }
+    rules
+}
+
+// `*` matches any run of characters and a trailing `$` anchors the end;
+// everything else is a prefix match
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = parts.next().and_then(|first| path.strip_prefix(first)) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(robots: &str, path: &str) -> bool {
+        parse(robots).allows(&Url::parse("https://example.com").unwrap().join(path).unwrap())
+    }
+
+    #[test]
+    fn longest_match_decides() {
+        let robots = "User-agent: *\nDisallow: /docs\nAllow: /docs/public\nDisallow: /docs/public/drafts";
+        assert!(allows(robots, "/"));
+        assert!(!allows(robots, "/docs"));
+        assert!(!allows(robots, "/docs/private"));
+        assert!(allows(robots, "/docs/public/page"));
+        assert!(!allows(robots, "/docs/public/drafts/page"));
+    }
+
+    #[test]
+    fn allow_wins_a_tie() {
+        assert!(allows("User-agent: *\nDisallow: /page\nAllow: /page", "/page"));
+        assert!(allows("User-agent: *\nAllow: /page\nDisallow: /page", "/page"));
+    }
+
+    #[test]
+    fn wildcards_and_anchors() {
+        let robots = "User-agent: *\nDisallow: /*.pdf$\nDisallow: /*/private/\nDisallow: /search?\nAllow: /$";
+        assert!(!allows(robots, "/files/manual.pdf"));
+        assert!(allows(robots, "/files/manual.pdf?download=1"));
+        assert!(allows(robots, "/files/manual.pdf.html"));
+        assert!(!allows(robots, "/team/private/notes"));
+        assert!(allows(robots, "/private/notes"));
+        assert!(!allows(robots, "/search?q=tokio"));
+        assert!(allows(robots, "/search"));
+        assert!(allows(robots, "/"));
+
+        assert!(!allows("User-agent: *\nDisallow: /", "/index.html"));
+        assert!(allows("User-agent: *\nDisallow: /$", "/index.html"));
+        assert!(!allows("User-agent: *\nDisallow: /*", "/index.html"));
+    }
+
+    #[test]
+    fn our_group_replaces_the_wildcard_one() {
+        let robots = format!(
+            "User-agent: *\nDisallow: /\n\nUser-agent: other\nUser-agent: {}\nDisallow: /private\nCrawl-delay: 2",
+            USER_AGENT_TOKEN
+        );
+        assert!(allows(&robots, "/docs"));
+        assert!(!allows(&robots, "/private"));
+        assert_eq!(parse(&robots).crawl_delay, Some(Duration::from_secs(2)));
+        assert!(!allows("User-agent: other\nAllow: /\n\nUser-agent: *\nDisallow: /", "/docs"));
+    }
+
+    #[test]
+    fn robots_txt_itself_is_always_allowed() {
+        assert!(allows("User-agent: *\nDisallow: /", "/robots.txt"));
+        let unreachable = HostRules {
+            unreachable: true,
+            ..HostRules::default()
+        };
+        assert!(!unreachable.allows(&Url::parse("https://example.com/robots.txt").unwrap()));
+    }
+
+    #[test]
+    fn crawl_delay_is_capped() {
+        assert_eq!(parse("User-agent: *\nCrawl-delay: 86400").crawl_delay, Some(MAX_CRAWL_DELAY));
+        assert_eq!(parse("User-agent: *\nCrawl-delay: 0.5").crawl_delay, Some(Duration::from_millis(500)));
+        assert_eq!(parse("User-agent: *\nCrawl-delay: soon").crawl_delay, None);
+    }
+}
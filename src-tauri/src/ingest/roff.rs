@@ -0,0 +1,648 @@
+// Turns man pages written in roff, with either the man or the mdoc macros,
+// into Markdown: section headings become ## and ###, literal blocks become
+// code blocks, and font changes are dropped. Requests that only affect
+// layout are ignored. Tables (tbl) come out one row per line.
+
+pub struct ManPage {
+    // The NAME section's one-line description, e.g. "list directory contents"
+    pub description: Option<String>,
+    pub markdown: String,
+}
+
+// mdoc macros that can be called on a line of another macro, and change how
+// the words after them read
+const MDOC_CALLABLE: &[&str] = &[
+    "Ad", "An", "Ap", "Ar", "At", "Bsx", "Bx", "Cd", "Cm", "Dq", "Dv", "Dx", "Em", "Er", "Ev", "Fa", "Fl", "Fn",
+    "Fr", "Ft", "Fx", "Ic", "Li", "Lk", "Ms", "Mt", "Nm", "No", "Ns", "Nx", "Op", "Ox", "Pa", "Pf", "Pq", "Ql",
+    "Qq", "Sq", "St", "Sx", "Sy", "Tn", "Ux", "Va", "Vt", "Xr",
+];
+
+// Requests that only affect layout, define things for later, or give the
+// page's name and section (already known from its file name)
+const IGNORED_REQUESTS: &[&str] = &[
+    "ad", "als", "bd", "bp", "cc", "ce", "cs", "DT", "ds", "ec", "eo", "ev", "fam", "fc", "ft", "hw", "hy", "in",
+    "lf", "ll", "mso", "na", "ne", "nh", "nr", "ns", "PD", "ps", "RE", "rm", "rs", "RS", "so", "ss", "ta", "ti",
+    "tr", "UC", "vs", "Os", "Bk", "Ek", "Bf", "Ef", "El", "Ed", "Rs", "Re", "Ud", "Tg", "YS", "UE", "ME", "pc",
+    "TH", "Dt", "Dd", "Bl", "Sm",
+];
+
+pub fn convert(source: &str) -> ManPage {
+    let mut page = Converter::default();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        // A line ending in a backslash continues on the next one
+        let mut line = line.to_string();
+        while line.ends_with('\\') && !line.ends_with("\\\\") {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next),
+                None => break,
+            }
+        }
+        page.line(&line, &mut lines);
+    }
+    page.finish()
+}
+
+#[derive(Default)]
+struct Converter {
+    // The first name given to .Nm, which later bare .Nm calls repeat
+    mdoc_name: Option<String>,
+    blocks: Vec<String>,
+    paragraph: String,
+    // Inside .nf/.fi, .EX/.EE or a literal .Bd
+    literal: Option<Vec<String>>,
+    // The next text line is the tag of a .TP paragraph
+    tag_next: bool,
+    // Inside a .It ... Xo tag
+    open_tag: bool,
+    // Inside the NAME section, for the description
+    in_name_section: bool,
+    name_text: String,
+}
+
+impl Converter {
+    fn line<'a>(&mut self, line: &str, rest: &mut impl Iterator<Item = &'a str>) {
+        let control = line.starts_with('.') || line.starts_with('\'');
+        if !control {
+            if let Some(literal) = &mut self.literal {
+                literal.push(text(line));
+            } else if line.trim().is_empty() {
+                self.end_paragraph();
+            } else {
+                self.text(&text(line));
+            }
+            return;
+        }
+
+        let request = line[1..].trim_start();
+        if request.starts_with("\\\"") || request.is_empty() {
+            return;
+        }
+        let (name, arguments) = match request.find(|c: char| c.is_whitespace()) {
+            Some(at) => (&request[..at], request[at..].trim()),
+            None => (request, ""),
+        };
+        let args = split_arguments(arguments);
+        let closes_tag = self.open_tag && (name == "Xc" || args.last().is_some_and(|arg| arg == "Xc"));
+        match name {
+            "SH" | "Sh" | "SS" | "Ss" => {
+                self.end_literal();
+                self.end_paragraph();
+                let heading = if args.is_empty() {
+                    rest.next().map(text).unwrap_or_default()
+                } else if name.starts_with('S') && name.ends_with('h') {
+                    self.mdoc_line(&args)
+                } else {
+                    text(&args.join(" "))
+                };
+                self.in_name_section = matches!(name, "SH" | "Sh") && heading.trim().eq_ignore_ascii_case("name");
+                let level = if name.eq_ignore_ascii_case("sh") { "##" } else { "###" };
+                self.blocks.push(format!("{} {}", level, heading.trim()));
+            }
+            "PP" | "P" | "LP" | "Pp" | "Lp" | "sp" | "HP" => self.end_paragraph(),
+            "br" => {
+                if !self.paragraph.is_empty() {
+                    self.paragraph.push('\n');
+                }
+            }
+            "TP" | "TQ" => {
+                self.end_paragraph();
+                self.tag_next = true;
+            }
+            "IP" | "It" => {
+                self.end_paragraph();
+                let tag = match name {
+                    "It" => self.mdoc_line(&args),
+                    _ => args.first().map(|arg| text(arg)).unwrap_or_default(),
+                };
+                let tag = tag.trim();
+                // Bullets and numbers of lists are tags too. An Xo tag goes
+                // on over the next lines, up to an Xc.
+                if args.last().is_some_and(|arg| arg == "Xo") {
+                    self.paragraph.push_str(tag);
+                    self.open_tag = true;
+                } else if !tag.is_empty() {
+                    self.paragraph.push_str(tag);
+                    self.paragraph.push('\n');
+                }
+            }
+            "nf" | "EX" => self.start_literal(),
+            "fi" | "EE" => self.end_literal(),
+            "Bd" => {
+                self.end_paragraph();
+                if args.iter().any(|arg| matches!(arg.as_str(), "-literal" | "-unfilled" | "-code")) {
+                    self.start_literal();
+                }
+            }
+            "B" | "I" | "SM" | "SB" | "UR" | "MT" => {
+                // Without arguments, they apply to the next line
+                let words = match args.is_empty() {
+                    true => rest.next().map(text).unwrap_or_default(),
+                    false => text(&args.join(" ")),
+                };
+                self.text(&words);
+            }
+            "BR" | "BI" | "IB" | "IR" | "RB" | "RI" => self.text(&text(&args.concat())),
+            "OP" => self.text(&format!("[{}]", text(&args.join(" ")))),
+            "SY" => {
+                self.end_paragraph();
+                self.text(&text(&args.join(" ")));
+            }
+            "TS" => self.table(rest),
+            "de" | "de1" | "am" | "ig" => {
+                // Both end at "..", or .ig at the line it names
+                let end = match name {
+                    "ig" => args.first().map(|end| format!(".{}", end)).unwrap_or_else(|| "..".to_string()),
+                    _ => "..".to_string(),
+                };
+                for line in rest.by_ref() {
+                    if line.trim() == end {
+                        break;
+                    }
+                }
+            }
+            "if" | "ie" | "el" | "while" => {
+                // Conditions are about the output device; skip the body,
+                // all of it when it's a \{ ... \} block
+                if line.contains("\\{") {
+                    let mut depth = line.matches("\\{").count() - line.matches("\\}").count().min(1);
+                    while depth > 0 {
+                        let Some(line) = rest.next() else {
+                            break;
+                        };
+                        depth += line.matches("\\{").count();
+                        depth = depth.saturating_sub(line.matches("\\}").count());
+                    }
+                }
+            }
+            "Nm" => {
+                if self.mdoc_name.is_none() {
+                    self.mdoc_name = args.first().map(|arg| text(arg));
+                }
+                let words = self.mdoc_line(&[vec!["Nm".to_string()], args].concat());
+                self.text(&words);
+            }
+            "Dl" | "D1" => {
+                self.end_paragraph();
+                let words = self.mdoc_line(&args);
+                self.blocks.push(format!("```\n{}\n```", words));
+            }
+            "Nd" => {
+                let words = self.mdoc_line(&args);
+                self.text(&format!("- {}", words));
+            }
+            _ if IGNORED_REQUESTS.contains(&name) => {}
+            _ if is_callable(name) => {
+                let words = self.mdoc_line(&[vec![name.to_string()], args].concat());
+                self.text(&words);
+            }
+            // Macros this converter doesn't know: keep their words
+            _ => {
+                if !args.is_empty() {
+                    self.text(&text(&args.join(" ")));
+                }
+            }
+        }
+        if closes_tag {
+            self.paragraph.push('\n');
+            self.open_tag = false;
+        }
+    }
+
+    fn text(&mut self, words: &str) {
+        if words.trim().is_empty() {
+            return;
+        }
+        if let Some(literal) = &mut self.literal {
+            literal.push(words.to_string());
+            return;
+        }
+        if !self.paragraph.is_empty() && !self.paragraph.ends_with('\n') {
+            self.paragraph.push(' ');
+        }
+        self.paragraph.push_str(words.trim());
+        if self.tag_next {
+            self.paragraph.push('\n');
+            self.tag_next = false;
+        }
+        if self.in_name_section {
+            self.name_text.push(' ');
+            self.name_text.push_str(words.trim());
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        let paragraph = self.paragraph.trim();
+        if !paragraph.is_empty() {
+            self.blocks.push(paragraph.to_string());
+        }
+        self.paragraph.clear();
+    }
+
+    fn start_literal(&mut self) {
+        self.end_paragraph();
+        if self.literal.is_none() {
+            self.literal = Some(Vec::new());
+        }
+    }
+
+    fn end_literal(&mut self) {
+        let Some(lines) = self.literal.take() else {
+            return;
+        };
+        let code = lines.join("\n");
+        let code = code.trim_matches('\n');
+        if !code.trim().is_empty() {
+            self.blocks.push(format!("```\n{}\n```", code));
+        }
+    }
+
+    // tbl data, one row per line with cells separated by " | ". The
+    // options and format lines before the data (the last ends in '.') are
+    // skipped.
+    fn table<'a>(&mut self, rest: &mut impl Iterator<Item = &'a str>) {
+        self.end_paragraph();
+        let mut in_format = true;
+        let mut rows = Vec::new();
+        let mut cell_block: Option<String> = None;
+        for line in rest.by_ref() {
+            if line.starts_with(".TE") {
+                break;
+            }
+            if in_format {
+                in_format = !line.trim_end().ends_with('.');
+                continue;
+            }
+            if let Some(block) = &mut cell_block {
+                // A T{ ... T} cell spans lines
+                if let Some(after) = line.strip_prefix("T}") {
+                    let cell = std::mem::take(block);
+                    cell_block = None;
+                    if rows.is_empty() {
+                        rows.push(String::new());
+                    }
+                    if let Some(row) = rows.last_mut() {
+                        row.push_str(cell.trim());
+                        row.push_str(&text(after).replace('\t', " | "));
+                    }
+                } else if !line.starts_with('.') {
+                    block.push(' ');
+                    block.push_str(&text(line));
+                }
+                continue;
+            }
+            if line.starts_with('.') || matches!(line.trim(), "_" | "=" | "") {
+                continue;
+            }
+            let line = text(line).replace('\t', " | ");
+            match line.strip_suffix("T{") {
+                Some(start) => {
+                    rows.push(start.to_string());
+                    cell_block = Some(String::new());
+                }
+                None => rows.push(line),
+            }
+        }
+        let rows: Vec<String> =
+            rows.into_iter().map(|row| row.trim().to_string()).filter(|row| !row.is_empty()).collect();
+        if !rows.is_empty() {
+            self.blocks.push(rows.join("\n"));
+        }
+    }
+
+    // The words of an mdoc line, with the callable macros among them
+    // applied: `Fl v Ar file` reads "-v file", `Xr ls 1` reads "ls(1)"
+    fn mdoc_line(&self, args: &[String]) -> String {
+        let mut out = String::new();
+        let mut closers: Vec<&str> = Vec::new();
+        let mut no_space = false;
+        let mut i = 0;
+        while i < args.len() {
+            let word = args[i].as_str();
+            i += 1;
+            let piece = match word {
+                "Fl" => {
+                    // Flags are the following words, each with a dash
+                    let flag = args.get(i).filter(|next| !is_callable(next)).map(|next| text(next));
+                    if flag.is_some() {
+                        i += 1;
+                    }
+                    format!("-{}", flag.unwrap_or_default())
+                }
+                "Xr" => {
+                    let name = args.get(i).map(|name| text(name)).unwrap_or_default();
+                    let section = args.get(i + 1).map(|section| text(section)).unwrap_or_default();
+                    i += 2;
+                    format!("{}({})", name, section)
+                }
+                "Nm" => {
+                    if args.get(i).is_some_and(|next| !is_callable(next) && !is_punctuation(next)) {
+                        continue;
+                    }
+                    self.mdoc_name.clone().unwrap_or_default()
+                }
+                "Op" => {
+                    closers.push("]");
+                    no_space = true;
+                    "[".to_string()
+                }
+                // Unlike Op, Oo stays open until an Oc, maybe on a later line
+                "Oo" => {
+                    no_space = true;
+                    "[".to_string()
+                }
+                "Oc" => {
+                    out.push(']');
+                    continue;
+                }
+                "Pq" => {
+                    closers.push(")");
+                    no_space = true;
+                    "(".to_string()
+                }
+                "Dq" | "Qq" => {
+                    closers.push("\"");
+                    no_space = true;
+                    "\"".to_string()
+                }
+                "Sq" | "Ql" => {
+                    closers.push("'");
+                    no_space = true;
+                    "'".to_string()
+                }
+                "Ns" | "Ap" | "Pf" => {
+                    if word == "Ap" {
+                        out.push('\'');
+                    }
+                    no_space = true;
+                    continue;
+                }
+                "Ex" => "The utility exits 0 on success, and >0 if an error occurs.".to_string(),
+                "Rv" => "The function returns the value 0 if successful; otherwise -1 is returned.".to_string(),
+                "St" | "Bx" | "Ux" | "At" | "Bsx" | "Dx" | "Fx" | "Nx" | "Ox" => {
+                    let system = match word {
+                        "Bx" => "BSD",
+                        "Ux" => "UNIX",
+                        "At" => "AT&T UNIX",
+                        "Bsx" => "BSD/OS",
+                        "Dx" => "DragonFly",
+                        "Fx" => "FreeBSD",
+                        "Nx" => "NetBSD",
+                        "Ox" => "OpenBSD",
+                        _ => "",
+                    };
+                    if word == "St" {
+                        i += 1;
+                        continue;
+                    }
+                    system.to_string()
+                }
+                _ if is_callable(word) => continue,
+                _ => text(word),
+            };
+            if piece.is_empty() {
+                continue;
+            }
+            if !out.is_empty() && !no_space && !is_punctuation(&piece) && !out.ends_with(['[', '(', '"']) {
+                out.push(' ');
+            }
+            no_space = matches!(piece.as_str(), "[" | "(" | "\"" | "'");
+            out.push_str(&piece);
+        }
+        // Closing punctuation stays outside the brackets and quotes
+        let mut trailing = out.len() - out.trim_end_matches(['.', ',', ';', ':', '?', '!']).len();
+        if out[..out.len() - trailing].ends_with(['[', '(', '"', '\'']) {
+            trailing = 0;
+        }
+        let punctuation = out.split_off(out.len() - trailing);
+        while let Some(closer) = closers.pop() {
+            out.push_str(closer);
+        }
+        out + &punctuation
+    }
+
+    fn finish(mut self) -> ManPage {
+        self.end_literal();
+        self.end_paragraph();
+        let description = self.name_text.split_once(" - ").map(|(_, description)| description.trim().to_string());
+        ManPage {
+            description: description.filter(|description| !description.is_empty()),
+            markdown: self.blocks.join("\n\n"),
+        }
+    }
+}
+
+fn is_callable(word: &str) -> bool {
+    MDOC_CALLABLE.contains(&word) || matches!(word, "Oc" | "Oo" | "Ex" | "Rv" | "Xo" | "Xc")
+}
+
+fn is_punctuation(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| matches!(c, '.' | ',' | ';' | ':' | '?' | '!' | ')' | ']'))
+}
+
+// A request's arguments: split at spaces, except inside double quotes
+// (where "" is a literal quote)
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = arguments.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut arg = String::new();
+        if first == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                if c == '"' {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        arg.push('"');
+                        continue;
+                    }
+                    break;
+                }
+                arg.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+                // An escape keeps the character after it, spaces included
+                if c == '\\' {
+                    if let Some(next) = chars.next() {
+                        arg.push(next);
+                    }
+                }
+            }
+        }
+        // A comment ends the arguments
+        if arg.starts_with("\\\"") {
+            break;
+        }
+        args.push(arg);
+    }
+    args
+}
+
+// Text with roff escapes resolved: special characters become their Unicode
+// counterparts and font, size and motion escapes are dropped
+fn text(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(escape) = chars.next() else {
+            break;
+        };
+        match escape {
+            '"' => break,
+            '-' => out.push('-'),
+            'e' | '\\' => out.push('\\'),
+            '.' => out.push('.'),
+            ' ' | '~' | '0' => out.push(' '),
+            '\'' => out.push('\''),
+            '`' => out.push('`'),
+            '&' | '|' | '^' | '%' | ':' | 'c' | ')' | '/' | ',' | 'd' | 'u' | 'p' | 'a' | 't' | 'r' | '{' | '}' => {}
+            '(' => {
+                let name: String = chars.by_ref().take(2).collect();
+                out.push_str(special_character(&name));
+            }
+            '[' => {
+                let name: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                out.push_str(&bracketed_character(&name));
+            }
+            '*' => {
+                let name = register_name(&mut chars);
+                out.push_str(match name.as_str() {
+                    "lq" | "rq" | "Lq" | "Rq" => "\"",
+                    "R" | "rg" => "®",
+                    "Tm" | "tm" => "™",
+                    _ => "",
+                });
+            }
+            'f' | 'n' | 'F' | 'g' | 'k' | 'm' | 'M' | 'V' | 'Y' => {
+                register_name(&mut chars);
+            }
+            's' => {
+                // \s+2, \s-1, \s0, \s(12, \s[12]
+                if matches!(chars.peek(), Some('+') | Some('-')) {
+                    chars.next();
+                }
+                match chars.peek() {
+                    Some('(') => {
+                        chars.next();
+                        chars.by_ref().take(2).for_each(drop);
+                    }
+                    Some('[') => {
+                        chars.by_ref().take_while(|&c| c != ']').for_each(drop);
+                    }
+                    _ => {
+                        while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                            chars.next();
+                        }
+                    }
+                }
+            }
+            // Escapes with a quoted argument: motions, widths, overstrikes
+            'h' | 'v' | 'w' | 'o' | 'l' | 'L' | 'X' | 'N' | 'D' | 'b' | 'x' | 'A' | 'B' | 'C' | 'R' | 'S' | 'Z' => {
+                if let Some(delimiter) = chars.next() {
+                    let argument: String = chars.by_ref().take_while(|&c| c != delimiter).collect();
+                    if escape == 'C' {
+                        out.push_str(&bracketed_character(&argument));
+                    }
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+// A register or font name after \f, \n or \*: one character, (xx or [name]
+fn register_name(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    match chars.next() {
+        Some('(') => chars.by_ref().take(2).collect(),
+        Some('[') => chars.by_ref().take_while(|&c| c != ']').collect(),
+        Some('+') | Some('-') => register_name(chars),
+        Some(c) => c.to_string(),
+        None => String::new(),
+    }
+}
+
+// \[name]: a named character, or a Unicode one as u00E9
+fn bracketed_character(name: &str) -> String {
+    if let Some(code) = name.strip_prefix('u') {
+        let decoded: Option<String> = code
+            .split('_')
+            .map(|code| u32::from_str_radix(code, 16).ok().and_then(char::from_u32))
+            .collect();
+        if let Some(decoded) = decoded {
+            return decoded;
+        }
+    }
+    special_character(name).to_string()
+}
+
+fn special_character(name: &str) -> &'static str {
+    match name {
+        "em" => "—",
+        "en" => "–",
+        "hy" | "mi" | "\\-" => "-",
+        "aq" | "oq" | "cq" | "fm" => "'",
+        "dq" | "lq" | "rq" => "\"",
+        "Fo" => "«",
+        "Fc" => "»",
+        "bu" => "•",
+        "co" => "©",
+        "rg" => "®",
+        "tm" => "™",
+        "de" => "°",
+        "ga" => "`",
+        "ti" | "ap" => "~",
+        "ha" | "a^" => "^",
+        "rs" => "\\",
+        "sl" => "/",
+        "ba" | "br" | "or" => "|",
+        "pl" => "+",
+        "eq" => "=",
+        "mu" => "×",
+        "di" => "÷",
+        "+-" => "±",
+        "<=" => "≤",
+        ">=" => "≥",
+        "!=" => "≠",
+        "->" | "ra" => "→",
+        "<-" | "la" => "←",
+        "lB" => "[",
+        "rB" => "]",
+        "lC" => "{",
+        "rC" => "}",
+        "fo" => "‹",
+        "fc" => "›",
+        "ss" => "ß",
+        "sc" => "§",
+        "ps" => "¶",
+        "ct" => "¢",
+        "Po" => "£",
+        "Eu" | "eu" => "€",
+        "Ye" => "¥",
+        "dg" => "†",
+        "at" => "@",
+        "sh" => "#",
+        "Do" => "$",
+        "ul" | "ru" => "_",
+        "lh" => "☜",
+        "rh" => "☞",
+        _ => "",
+    }
+}
@@ -0,0 +1,790 @@
+use std::collections::{HashSet, VecDeque};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::info;
+
+use crate::search::{facet_path, SearchService, SearchableDocument};
+use crate::validation::validate_document;
+use crate::McpError;
+
+// Items are written to the index in batches of this many
+const INDEX_BATCH_SIZE: usize = 500;
+
+// Ids and links point at the item's page on docs.rs
+const DOCS_RS: &str = "https://docs.rs";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RustdocOptions {
+    /// JSON written by `cargo rustdoc -- -Z unstable-options --output-format json`,
+    /// e.g. target/doc/tokio.json
+    pub path: String,
+    /// Source to file the items under; defaults to the crate's name
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Defaults to the crate version recorded in the JSON
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RustdocResponse {
+    pub source: String,
+    pub version: Option<String>,
+    pub indexed: usize,
+    /// Items that couldn't be indexed, as "path: reason"
+    pub errors: Vec<String>,
+}
+
+// Indexes a crate's rustdoc JSON, one document per public item. Blocking;
+// run it off the async runtime. `on_progress` gets (items written, total).
+pub fn index_rustdoc(
+    search_service: &SearchService,
+    options: &RustdocOptions,
+    on_progress: impl Fn(usize, usize),
+) -> Result<RustdocResponse, McpError> {
+    let path = options.path.trim();
+    let contents = std::fs::read(path)
+        .map_err(|e| McpError::validation(Some("path"), format!("Cannot read {}: {}", path, e)))?;
+    let krate: Value = serde_json::from_slice(&contents)
+        .map_err(|e| McpError::validation(Some("path"), format!("{} is not rustdoc JSON: {}", path, e)))?;
+    index_crate(search_service, &krate, options.source.clone(), options.version.clone(), on_progress)
+}
+
+pub(super) fn index_crate(
+    search_service: &SearchService,
+    krate: &Value,
+    source: Option<String>,
+    version: Option<String>,
+    on_progress: impl Fn(usize, usize),
+) -> Result<RustdocResponse, McpError> {
+    let items = crate_items(krate).map_err(|e| McpError::validation(Some("path"), e))?;
+    let name = items.first().map(|item| item.path[0].clone()).unwrap_or_default();
+    let source = source.unwrap_or_else(|| name.clone());
+    let version = version.or_else(|| krate["crate_version"].as_str().map(str::to_string));
+
+    let mut errors = Vec::new();
+    let mut documents = Vec::new();
+    for item in items {
+        let url = item.url(version.as_deref());
+        // /rust/tokio/1.38.0/sync/Mutex for tokio::sync::Mutex::lock
+        let parents = &item.path[1..item.path.len().max(2) - 1];
+        let facet = facet_path(
+            ["rust", name.as_str()]
+                .into_iter()
+                .chain(version.as_deref())
+                .chain(parents.iter().map(String::as_str)),
+        );
+        let document = SearchableDocument {
+            id: url.clone(),
+            title: item.path.join("::"),
+            body: item.body,
+            source: source.clone(),
+            version: version.clone(),
+            url: Some(url),
+            tags: item.tags,
+            indexed_at: None,
+            published_at: None,
+            metrics: Default::default(),
+            facet: Some(facet),
+            parent_id: None,
+            heading_path: Vec::new(),
+            anchor: None,
+            content_hash: None,
+        };
+        match validate_document(&document, "document") {
+            Ok(()) => documents.push(document),
+            Err(e) => errors.push(format!("{}: {}", document.title, e)),
+        }
+    }
+
+    let mut indexed = 0;
+    for batch in documents.chunks(INDEX_BATCH_SIZE) {
+        search_service
+            .add_documents(batch.to_vec(), |done, _| on_progress(indexed + done, documents.len()))
+            .map_err(|e| McpError::index("Failed to add items", e))?;
+        indexed += batch.len();
+    }
+    info!("Indexed {} items of crate {} as source {}", indexed, name, source);
+    Ok(RustdocResponse {
+        source,
+        version,
+        indexed,
+        errors,
+    })
+}
+
+// A documented item and where docs.rs shows it
+struct CrateItem {
+    // Crate name first, e.g. ["tokio", "sync", "Mutex", "lock"]
+    path: Vec<String>,
+    // The docs.rs page, relative to the crate's root, e.g.
+    // "sync/struct.Mutex.html"
+    page: String,
+    // Anchor on the page for methods and associated items
+    anchor: Option<String>,
+    body: String,
+    // "async", "unsafe" and "deprecated", as they apply
+    tags: Vec<String>,
+}
+
+impl CrateItem {
+    fn url(&self, version: Option<&str>) -> String {
+        let mut url = format!("{}/{}/{}/{}/{}", DOCS_RS, self.path[0], version.unwrap_or("latest"), self.path[0], self.page);
+        if let Some(anchor) = &self.anchor {
+            url.push('#');
+            url.push_str(anchor);
+        }
+        url
+    }
+}
+
+// The crate's public items, found by walking its modules from the root the
+// way rustdoc's HTML does, so re-exported items get their public path
+fn crate_items(krate: &Value) -> Result<Vec<CrateItem>, String> {
+    let index = krate["index"].as_object().ok_or("missing the item index")?;
+    let root = krate.get("root").map(id_key).ok_or("missing the root module")?;
+    let name = index
+        .get(&root)
+        .and_then(|root| root["name"].as_str())
+        .ok_or("missing the root module")?
+        .to_string();
+    let walker = Walker { index };
+
+    let mut items = Vec::new();
+    let mut visited = HashSet::from([root.clone()]);
+    // Modules pulled in by a glob import add their items to the importing
+    // module's page rather than getting one of their own
+    let mut modules = VecDeque::from([(root, vec![name], true)]);
+    while let Some((id, path, has_page)) = modules.pop_front() {
+        let Some(module) = index.get(&id) else {
+            continue;
+        };
+        let body = if has_page { walker.body(module, "module", &path) } else { None };
+        if let Some(body) = body {
+            items.push(CrateItem {
+                page: page_dir(&path) + "index.html",
+                path: path.clone(),
+                anchor: None,
+                body,
+                tags: item_tags(module),
+            });
+        }
+        let Some(("module", inner)) = kind_and_inner(module) else {
+            continue;
+        };
+        for child in inner["items"].as_array().into_iter().flatten() {
+            let Some(item) = walker.local(child) else {
+                continue;
+            };
+            // `pub use` puts an item, or with a glob a module's items,
+            // into this module
+            let (target, name) = match kind_and_inner(item) {
+                Some(("use" | "import", reexport)) => {
+                    let Some(target) = reexport.get("id").filter(|id| !id.is_null()) else {
+                        continue;
+                    };
+                    if reexport["is_glob"].as_bool() == Some(true) || reexport["glob"].as_bool() == Some(true) {
+                        if visited.insert(id_key(target)) {
+                            modules.push_back((id_key(target), path.clone(), false));
+                        }
+                        continue;
+                    }
+                    (target, reexport["name"].as_str())
+                }
+                _ => (child, item["name"].as_str()),
+            };
+            let (Some(item), Some(name)) = (walker.local(target), name) else {
+                continue;
+            };
+            if !is_public(item) || !visited.insert(id_key(target)) {
+                continue;
+            }
+            let mut item_path = path.clone();
+            item_path.push(name.to_string());
+            match kind_and_inner(item) {
+                Some(("module", _)) => modules.push_back((id_key(target), item_path, true)),
+                Some((kind, inner)) => walker.push_item(kind, inner, item, item_path, &mut items),
+                None => {}
+            }
+        }
+    }
+    Ok(items)
+}
+
+struct Walker<'a> {
+    index: &'a Map<String, Value>,
+}
+
+impl<'a> Walker<'a> {
+    // Items of other crates (re-exports of dependencies) are left to those
+    // crates' own docs
+    fn local(&self, id: &Value) -> Option<&'a Value> {
+        self.index.get(&id_key(id)).filter(|item| item["crate_id"].as_u64() == Some(0))
+    }
+
+    // An item and its methods and associated items
+    fn push_item(&self, kind: &str, inner: &Value, item: &Value, path: Vec<String>, items: &mut Vec<CrateItem>) {
+        let Some(prefix) = page_prefix(kind, inner) else {
+            return;
+        };
+        let page = format!("{}{}.{}.html", page_dir(&path[..path.len() - 1]), prefix, path[path.len() - 1]);
+
+        let mut members = Vec::new();
+        match kind {
+            "struct" | "enum" | "union" => {
+                // Inherent impls only; trait impls are documented on the trait
+                for implementation in inner["impls"].as_array().into_iter().flatten() {
+                    let Some(("impl", implementation)) = self.local(implementation).and_then(kind_and_inner) else {
+                        continue;
+                    };
+                    let is_synthetic =
+                        implementation["is_synthetic"].as_bool() == Some(true) || implementation["synthetic"].as_bool() == Some(true);
+                    if !implementation["trait"].is_null() || !implementation["blanket_impl"].is_null() || is_synthetic {
+                        continue;
+                    }
+                    members.extend(implementation["items"].as_array().into_iter().flatten().filter_map(|id| self.local(id)));
+                }
+            }
+            "trait" => members.extend(inner["items"].as_array().into_iter().flatten().filter_map(|id| self.local(id))),
+            _ => {}
+        }
+
+        let mut member_names = Vec::new();
+        for member in members {
+            let (Some((member_kind, member_inner)), Some(name)) = (kind_and_inner(member), member["name"].as_str()) else {
+                continue;
+            };
+            if kind != "trait" && !is_public(member) {
+                continue;
+            }
+            let anchor = match member_kind {
+                "function" if kind == "trait" && member_inner["has_body"].as_bool() == Some(false) => "tymethod",
+                "function" => "method",
+                "assoc_const" => "associatedconstant",
+                "assoc_type" => "associatedtype",
+                _ => continue,
+            };
+            let mut member_path = path.clone();
+            member_path.push(name.to_string());
+            let Some(body) = self.body(member, member_kind, &member_path) else {
+                continue;
+            };
+            member_names.push(format!("`{}`", name));
+            items.push(CrateItem {
+                path: member_path,
+                page: page.clone(),
+                anchor: Some(format!("{}.{}", anchor, name)),
+                body,
+                tags: item_tags(member),
+            });
+        }
+
+        if let Some(mut body) = self.body(item, kind, &path) {
+            if !member_names.is_empty() {
+                let heading = if kind == "trait" { "Items" } else { "Methods" };
+                body.push_str(&format!("\n\n{}: {}", heading, member_names.join(", ")));
+            }
+            items.push(CrateItem {
+                path,
+                page,
+                anchor: None,
+                body,
+                tags: item_tags(item),
+            });
+        }
+    }
+
+    // The item's signature as a Rust code block, then its docs
+    fn body(&self, item: &Value, kind: &str, path: &[String]) -> Option<String> {
+        let (_, inner) = kind_and_inner(item)?;
+        let signature = self.signature(kind, inner, item, path)?;
+        let mut body = format!("```rust\n{}\n```", signature);
+        if let Some(deprecation) = item.get("deprecation").filter(|deprecation| !deprecation.is_null()) {
+            let mut note = "Deprecated".to_string();
+            if let Some(since) = deprecation["since"].as_str() {
+                note.push_str(&format!(" since {}", since));
+            }
+            if let Some(reason) = deprecation["note"].as_str() {
+                note.push_str(&format!(": {}", reason));
+            }
+            body.push_str(&format!("\n\n{}", note));
+        }
+        if let Some(docs) = item["docs"].as_str().map(strip_hidden_lines).filter(|docs| !docs.is_empty()) {
+            body.push_str("\n\n");
+            body.push_str(&docs);
+        }
+        Some(body)
+    }
+
+    fn signature(&self, kind: &str, inner: &Value, item: &Value, path: &[String]) -> Option<String> {
+        let name = path.last()?;
+        let path_is_root = path.len() == 1;
+        let visibility = if item["visibility"].as_str() == Some("public") { "pub " } else { "" };
+        let (params, bounds) = generics(&inner["generics"]);
+        Some(match kind {
+            "module" if path_is_root => format!("extern crate {};", name),
+            "module" => format!("{}mod {}", visibility, name),
+            "function" => function(visibility, name, inner),
+            "struct" | "union" => {
+                let keyword = if kind == "struct" { "struct" } else { "union" };
+                let fields = match inner.get("kind").unwrap_or(&Value::Null) {
+                    Value::String(unit) if unit == "unit" => ";".to_string(),
+                    Value::Object(struct_kind) if struct_kind.contains_key("tuple") => {
+                        format!("({});", self.tuple_fields(&struct_kind["tuple"]))
+                    }
+                    // Unions list their fields directly
+                    struct_kind => {
+                        let plain = struct_kind.get("plain").unwrap_or(inner);
+                        format!(" {{{}", self.named_fields(&plain["fields"], plain["has_stripped_fields"].as_bool(), "    "))
+                    }
+                };
+                format!("{}{} {}{}{}{}", visibility, keyword, name, params, bounds, fields)
+            }
+            "enum" => {
+                let mut variants = String::new();
+                for variant in inner["variants"].as_array().into_iter().flatten().filter_map(|id| self.local(id)) {
+                    let (Some(("variant", variant_inner)), Some(variant_name)) =
+                        (kind_and_inner(variant), variant["name"].as_str())
+                    else {
+                        continue;
+                    };
+                    let fields = match &variant_inner["kind"] {
+                        Value::Object(variant_kind) if variant_kind.contains_key("tuple") => {
+                            format!("({})", self.tuple_fields(&variant_kind["tuple"]))
+                        }
+                        Value::Object(variant_kind) if variant_kind.contains_key("struct") => {
+                            let fields = &variant_kind["struct"];
+                            format!(
+                                " {{{}    }}",
+                                self.named_fields(&fields["fields"], fields["has_stripped_fields"].as_bool(), "        ")
+                                    .trim_end_matches('}')
+                            )
+                        }
+                        _ => String::new(),
+                    };
+                    variants.push_str(&format!("\n    {}{},", variant_name, fields));
+                }
+                if inner["has_stripped_variants"].as_bool() == Some(true) {
+                    variants.push_str("\n    // some variants omitted");
+                }
+                format!("{}enum {}{}{} {{{}\n}}", visibility, name, params, bounds, variants)
+            }
+            "trait" => {
+                let unsafety = if inner["is_unsafe"].as_bool() == Some(true) { "unsafe " } else { "" };
+                let supertraits = render_bounds(&inner["bounds"]);
+                let supertraits = if supertraits.is_empty() { supertraits } else { format!(": {}", supertraits) };
+                format!("{}{}trait {}{}{}{}", visibility, unsafety, name, params, supertraits, bounds)
+            }
+            "type_alias" | "typedef" => {
+                format!("{}type {}{}{} = {};", visibility, name, params, bounds, render_type(&inner["type"]))
+            }
+            "constant" => {
+                // The expression moved under `const` in later format versions
+                let expression = inner["const"]["expr"].as_str().or_else(|| inner["expr"].as_str()).unwrap_or("_");
+                format!("{}const {}: {} = {};", visibility, name, render_type(&inner["type"]), expression)
+            }
+            "static" => {
+                let mutable = inner["is_mutable"].as_bool().or_else(|| inner["mutable"].as_bool()) == Some(true);
+                let keyword = if mutable { "static mut" } else { "static" };
+                format!("{}{} {}: {};", visibility, keyword, name, render_type(&inner["type"]))
+            }
+            "macro" => inner.as_str().unwrap_or_default().to_string(),
+            "proc_macro" => match inner["kind"].as_str() {
+                Some("derive") => format!("#[derive({})]", name),
+                Some("attr") => format!("#[{}]", name),
+                _ => format!("{}!()", name),
+            },
+            "assoc_const" => {
+                let value = inner["value"].as_str().or_else(|| inner["default"].as_str());
+                let value = value.map(|value| format!(" = {}", value)).unwrap_or_default();
+                format!("{}const {}: {}{};", visibility, name, render_type(&inner["type"]), value)
+            }
+            "assoc_type" => {
+                let bounds = render_bounds(&inner["bounds"]);
+                let bounds = if bounds.is_empty() { bounds } else { format!(": {}", bounds) };
+                let default = inner.get("type").or_else(|| inner.get("default")).filter(|default| !default.is_null());
+                let default = default.map(|default| format!(" = {}", render_type(default))).unwrap_or_default();
+                format!("{}type {}{}{}{};", visibility, name, params, bounds, default)
+            }
+            _ => return None,
+        })
+    }
+
+    // `pub a: T,` lines for the public fields
+    fn named_fields(&self, fields: &Value, stripped: Option<bool>, indent: &str) -> String {
+        let mut lines = String::new();
+        for field in fields.as_array().into_iter().flatten().filter_map(|id| self.local(id)) {
+            let (Some(("struct_field", field_type)), Some(name)) = (kind_and_inner(field), field["name"].as_str()) else {
+                continue;
+            };
+            let visibility = if field["visibility"].as_str() == Some("public") { "pub " } else { "" };
+            lines.push_str(&format!("\n{}{}{}: {},", indent, visibility, name, render_type(field_type)));
+        }
+        if stripped == Some(true) {
+            lines.push_str(&format!("\n{}// private fields", indent));
+        }
+        lines.push('\n');
+        lines.push('}');
+        lines
+    }
+
+    // Stripped (private) fields show as `_`
+    fn tuple_fields(&self, fields: &Value) -> String {
+        let fields: Vec<String> = fields
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|id| match self.local(id).and_then(kind_and_inner) {
+                Some(("struct_field", field_type)) => {
+                    let field = self.local(id).unwrap_or(&Value::Null);
+                    let visibility = if field["visibility"].as_str() == Some("public") { "pub " } else { "" };
+                    format!("{}{}", visibility, render_type(field_type))
+                }
+                _ => "_".to_string(),
+            })
+            .collect();
+        fields.join(", ")
+    }
+}
+
+// Doctest lines starting with `# ` are setup that rustdoc hides
+fn strip_hidden_lines(docs: &str) -> String {
+    // Untagged code blocks are Rust too
+    let mut fence: Option<bool> = None;
+    let lines: Vec<&str> = docs
+        .lines()
+        .filter(|line| {
+            if let Some(info) = line.trim_start().strip_prefix("```") {
+                fence = match fence {
+                    Some(_) => None,
+                    None => Some(info.trim().is_empty() || info.split(',').any(|tag| tag.trim() == "rust")),
+                };
+                return true;
+            }
+            let in_rust_fence = fence == Some(true);
+            let trimmed = line.trim_start();
+            !(in_rust_fence && (trimmed == "#" || trimmed.starts_with("# ")))
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+// Ids are numbers in recent format versions and strings in older ones
+fn id_key(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+// The item's kind and its kind-specific fields. Recent format versions nest
+// the fields under the kind (`"inner": {"struct": {...}}`), older ones name
+// the kind beside them (`"kind": "struct", "inner": {...}`).
+fn kind_and_inner(item: &Value) -> Option<(&str, &Value)> {
+    if let Some(kind) = item["kind"].as_str() {
+        return Some((kind, &item["inner"]));
+    }
+    let (kind, inner) = item["inner"].as_object()?.iter().next()?;
+    Some((kind.as_str(), inner))
+}
+
+fn item_tags(item: &Value) -> Vec<String> {
+    let mut tags = Vec::new();
+    if let Some(("function", inner)) = kind_and_inner(item) {
+        if flag(&inner["header"], &["is_async", "async_", "async"]) {
+            tags.push("async".to_string());
+        }
+        if flag(&inner["header"], &["is_unsafe", "unsafe_", "unsafe"]) {
+            tags.push("unsafe".to_string());
+        }
+    }
+    if item.get("deprecation").is_some_and(|deprecation| !deprecation.is_null()) {
+        tags.push("deprecated".to_string());
+    }
+    tags
+}
+
+fn is_public(item: &Value) -> bool {
+    matches!(item["visibility"].as_str(), Some("public" | "default"))
+}
+
+// "sync/" for an item in tokio::sync
+fn page_dir(module_path: &[String]) -> String {
+    module_path.iter().skip(1).map(|segment| format!("{}/", segment)).collect()
+}
+
+// The docs.rs page name prefix for a kind, e.g. `fn` in fn.spawn.html
+fn page_prefix<'a>(kind: &str, inner: &'a Value) -> Option<&'a str> {
+    Some(match kind {
+        "struct" => "struct",
+        "enum" => "enum",
+        "union" => "union",
+        "trait" => "trait",
+        "trait_alias" => "traitalias",
+        "function" => "fn",
+        "type_alias" | "typedef" => "type",
+        "constant" => "constant",
+        "static" => "static",
+        "macro" => "macro",
+        "proc_macro" => match inner["kind"].as_str() {
+            Some("derive") => "derive",
+            Some("attr") => "attr",
+            _ => "macro",
+        },
+        _ => return None,
+    })
+}
+
+fn flag(header: &Value, names: &[&str]) -> bool {
+    names.iter().any(|name| header[*name].as_bool() == Some(true))
+}
+
+fn function(visibility: &str, name: &str, inner: &Value) -> String {
+    let header = &inner["header"];
+    let mut qualifiers = String::new();
+    if flag(header, &["is_const", "const_", "const"]) {
+        qualifiers.push_str("const ");
+    }
+    if flag(header, &["is_async", "async_", "async"]) {
+        qualifiers.push_str("async ");
+    }
+    if flag(header, &["is_unsafe", "unsafe_", "unsafe"]) {
+        qualifiers.push_str("unsafe ");
+    }
+    let (params, bounds) = generics(&inner["generics"]);
+    let signature = inner.get("sig").unwrap_or(&inner["decl"]);
+    format!("{}{}fn {}{}{}{}", visibility, qualifiers, name, params, fn_signature(signature), bounds)
+}
+
+// "(&self, key: &str) -> Option<V>"
+fn fn_signature(signature: &Value) -> String {
+    let inputs: Vec<String> = signature["inputs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|input| {
+            let name = input[0].as_str()?;
+            let input_type = &input[1];
+            if name == "self" {
+                return Some(self_parameter(input_type));
+            }
+            Some(format!("{}: {}", name, render_type(input_type)))
+        })
+        .collect();
+    let output = match &signature["output"] {
+        Value::Null => String::new(),
+        output => format!(" -> {}", render_type(output)),
+    };
+    format!("({}){}", inputs.join(", "), output)
+}
+
+// `self`, `&self` and `&mut self` are written short, as in the source
+fn self_parameter(self_type: &Value) -> String {
+    let is_self = |value: &Value| value["generic"].as_str() == Some("Self");
+    if is_self(self_type) {
+        return "self".to_string();
+    }
+    let reference = &self_type["borrowed_ref"];
+    if is_self(&reference["type"]) {
+        let lifetime = reference["lifetime"].as_str().map(|lifetime| format!("{} ", lifetime)).unwrap_or_default();
+        let mutable = flag(reference, &["is_mutable", "mutable"]);
+        return format!("&{}{}self", lifetime, if mutable { "mut " } else { "" });
+    }
+    format!("self: {}", render_type(self_type))
+}
+
+// The `<...>` parameters and the ` where ...` clause; parameters rustdoc made
+// up for `impl Trait` arguments are left out
+fn generics(generics: &Value) -> (String, String) {
+    let params: Vec<String> = generics["params"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|param| {
+            let name = param["name"].as_str()?;
+            let kind = param["kind"].as_object()?;
+            if let Some(lifetime) = kind.get("lifetime") {
+                let outlives: Vec<&str> =
+                    lifetime["outlives"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+                return Some(if outlives.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}: {}", name, outlives.join(" + "))
+                });
+            }
+            if let Some(type_param) = kind.get("type") {
+                if flag(type_param, &["is_synthetic", "synthetic"]) {
+                    return None;
+                }
+                let bounds = render_bounds(&type_param["bounds"]);
+                return Some(if bounds.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}: {}", name, bounds)
+                });
+            }
+            let const_param = kind.get("const")?;
+            Some(format!("const {}: {}", name, render_type(&const_param["type"])))
+        })
+        .collect();
+    let params = if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) };
+
+    let predicates: Vec<String> = generics["where_predicates"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|predicate| {
+            if let Some(bound) = predicate.get("bound_predicate") {
+                return Some(format!("{}: {}", render_type(&bound["type"]), render_bounds(&bound["bounds"])));
+            }
+            if let Some(lifetime) = predicate.get("lifetime_predicate") {
+                let outlives: Vec<&str> =
+                    lifetime["outlives"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+                return Some(format!("{}: {}", lifetime["lifetime"].as_str()?, outlives.join(" + ")));
+            }
+            let equality = predicate.get("eq_predicate")?;
+            let rhs = equality["rhs"].get("type").unwrap_or(&equality["rhs"]);
+            Some(format!("{} = {}", render_type(&equality["lhs"]), render_type(rhs)))
+        })
+        .collect();
+    let bounds = if predicates.is_empty() {
+        String::new()
+    } else {
+        format!("\nwhere\n    {},", predicates.join(",\n    "))
+    };
+    (params, bounds)
+}
+
+fn render_bounds(bounds: &Value) -> String {
+    let bounds: Vec<String> = bounds
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|bound| {
+            if let Some(outlives) = bound["outlives"].as_str() {
+                return Some(outlives.to_string());
+            }
+            let trait_bound = bound.get("trait_bound")?;
+            let maybe = if trait_bound["modifier"].as_str() == Some("maybe") { "?" } else { "" };
+            Some(format!("{}{}", maybe, render_path(&trait_bound["trait"])))
+        })
+        .collect();
+    bounds.join(" + ")
+}
+
+// Paths are shortened to their last segment, as rustdoc shows them
+fn render_path(path: &Value) -> String {
+    let full = path["path"].as_str().or_else(|| path["name"].as_str()).unwrap_or("_");
+    let name = full.rsplit("::").next().unwrap_or(full);
+    format!("{}{}", name, render_args(&path["args"]))
+}
+
+fn render_args(args: &Value) -> String {
+    if let Some(angle_bracketed) = args.get("angle_bracketed") {
+        let mut rendered: Vec<String> = angle_bracketed["args"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|arg| {
+                if let Some(lifetime) = arg["lifetime"].as_str() {
+                    lifetime.to_string()
+                } else if let Some(arg_type) = arg.get("type") {
+                    render_type(arg_type)
+                } else if let Some(constant) = arg.get("const") {
+                    constant["expr"].as_str().unwrap_or("_").to_string()
+                } else {
+                    "_".to_string()
+                }
+            })
+            .collect();
+        let constraints = angle_bracketed.get("constraints").or_else(|| angle_bracketed.get("bindings"));
+        for constraint in constraints.and_then(Value::as_array).into_iter().flatten() {
+            let Some(name) = constraint["name"].as_str() else {
+                continue;
+            };
+            let binding = &constraint["binding"];
+            if let Some(equality) = binding.get("equality") {
+                let equal_to = equality.get("type").unwrap_or(equality);
+                rendered.push(format!("{} = {}", name, render_type(equal_to)));
+            } else if let Some(bounds) = binding.get("constraint") {
+                rendered.push(format!("{}: {}", name, render_bounds(bounds)));
+            }
+        }
+        if rendered.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", rendered.join(", "))
+        }
+    } else if let Some(parenthesized) = args.get("parenthesized") {
+        let inputs: Vec<String> = parenthesized["inputs"].as_array().into_iter().flatten().map(render_type).collect();
+        let output = match &parenthesized["output"] {
+            Value::Null => String::new(),
+            output => format!(" -> {}", render_type(output)),
+        };
+        format!("({}){}", inputs.join(", "), output)
+    } else {
+        String::new()
+    }
+}
+
+// Rust syntax for a rustdoc JSON type; anything unrecognised shows as `_`
+fn render_type(value: &Value) -> String {
+    if value.as_str() == Some("infer") {
+        return "_".to_string();
+    }
+    let Some((kind, inner)) = value.as_object().and_then(|object| object.iter().next()) else {
+        return "_".to_string();
+    };
+    match kind.as_str() {
+        "resolved_path" => render_path(inner),
+        "generic" | "primitive" => inner.as_str().unwrap_or("_").to_string(),
+        "tuple" => {
+            let types: Vec<String> = inner.as_array().into_iter().flatten().map(render_type).collect();
+            if types.len() == 1 {
+                format!("({},)", types[0])
+            } else {
+                format!("({})", types.join(", "))
+            }
+        }
+        "slice" => format!("[{}]", render_type(inner)),
+        "array" => format!("[{}; {}]", render_type(&inner["type"]), inner["len"].as_str().unwrap_or("_")),
+        "pat" => render_type(&inner["type"]),
+        "impl_trait" => format!("impl {}", render_bounds(inner)),
+        "dyn_trait" => {
+            let mut traits: Vec<String> = inner["traits"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|bound| render_path(&bound["trait"]))
+                .collect();
+            traits.extend(inner["lifetime"].as_str().map(str::to_string));
+            format!("dyn {}", traits.join(" + "))
+        }
+        "borrowed_ref" => {
+            let lifetime = inner["lifetime"].as_str().map(|lifetime| format!("{} ", lifetime)).unwrap_or_default();
+            let mutable = if flag(inner, &["is_mutable", "mutable"]) { "mut " } else { "" };
+            format!("&{}{}{}", lifetime, mutable, render_type(&inner["type"]))
+        }
+        "raw_pointer" => {
+            let mutability = if flag(inner, &["is_mutable", "mutable"]) { "mut" } else { "const" };
+            format!("*{} {}", mutability, render_type(&inner["type"]))
+        }
+        "function_pointer" => {
+            let signature = inner.get("sig").unwrap_or(&inner["decl"]);
+            format!("fn{}", fn_signature(signature))
+        }
+        "qualified_path" => {
+            let name = inner["name"].as_str().unwrap_or("_");
+            let self_type = render_type(&inner["self_type"]);
+            match inner.get("trait").filter(|path| !path.is_null()) {
+                Some(path) => format!("<{} as {}>::{}", self_type, render_path(path), name),
+                None => format!("{}::{}", self_type, name),
+            }
+        }
+        _ => "_".to_string(),
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use super::crawl::{CrawlManager, CrawlOptions, CrawlState, InventoryCrawlOptions, RefreshOptions, SitemapCrawlOptions};
+use crate::util::now_millis;
+use crate::McpError;
+
+const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+
+// How often the scheduler looks at the clock when nothing is due sooner.
+// Timers may stall while the machine sleeps, the wall clock doesn't.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// What a scheduled run does
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "kind", content = "options", rename_all = "camelCase")]
+pub enum ScheduledTask {
+    /// Re-check the source's downloaded pages, as `refreshSource` does
+    Refresh(RefreshOptions),
+    /// Crawl again from the seed, picking up new pages, as `startCrawl` does
+    Crawl(CrawlOptions),
+    /// Read the sitemap again, as `crawlSitemap` does
+    Sitemap(SitemapCrawlOptions),
+    /// Read the Sphinx inventory again, as `crawlInventory` does
+    Inventory(InventoryCrawlOptions),
+}
+
+impl ScheduledTask {
+    pub fn source(&self) -> &str {
+        match self {
+            ScheduledTask::Refresh(options) => &options.source,
+            ScheduledTask::Crawl(options) => &options.source,
+            ScheduledTask::Sitemap(options) => &options.source,
+            ScheduledTask::Inventory(options) => &options.source,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleOptions {
+    /// Hours between runs, e.g. 24 for nightly docs or 168 for stable releases
+    pub interval_hours: u64,
+    pub task: ScheduledTask,
+    /// Run once right away instead of waiting a full interval (default false)
+    #[serde(default)]
+    pub run_now: bool,
+}
+
+// listSchedules takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListSchedulesParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSchedule {
+    pub source: String,
+    pub interval_hours: u64,
+    pub task: ScheduledTask,
+    // Milliseconds since the Unix epoch
+    pub last_run: Option<u64>,
+    pub next_run: u64,
+    // The crawl the last run started, for `getCrawlStatus`
+    pub last_crawl_id: Option<String>,
+    // Why the last run didn't start, if it didn't
+    pub last_error: Option<String>,
+}
+
+// Re-runs a crawl for each scheduled source on its interval. Schedules are
+// kept in a JSON file next to the settings, so runs that came due while
+// the app was closed start soon after it opens.
+pub struct Scheduler {
+    crawls: Arc<CrawlManager>,
+    path: PathBuf,
+    schedules: Mutex<HashMap<String, SourceSchedule>>,
+    changed: Notify,
+}
+
+impl Scheduler {
+    // A missing or unreadable file starts with no schedules
+    pub fn load(path: PathBuf, crawls: Arc<CrawlManager>) -> Self {
+        let schedules = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid refresh schedules {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            crawls,
+            path,
+            schedules: Mutex::new(schedules),
+            changed: Notify::new(),
+        }
+    }
+
+    // Adds or replaces the source's schedule. A replaced schedule keeps its
+    // history and counts the new interval from its last run. Options are
+    // expected to be validated already.
+    pub fn schedule(&self, options: ScheduleOptions) -> Result<SourceSchedule, McpError> {
+        let source = options.task.source().to_string();
+        let now = now_millis();
+        let interval = options.interval_hours * HOUR_MILLIS;
+        let schedule = {
+            let mut schedules = self.schedules.lock().unwrap();
+            let previous = schedules.remove(&source);
+            let last_run = previous.as_ref().and_then(|previous| previous.last_run);
+            let schedule = SourceSchedule {
+                source: source.clone(),
+                interval_hours: options.interval_hours,
+                task: options.task,
+                last_run,
+                next_run: if options.run_now {
+                    now
+                } else {
+                    last_run.unwrap_or(now) + interval
+                },
+                last_crawl_id: previous.as_ref().and_then(|previous| previous.last_crawl_id.clone()),
+                last_error: previous.and_then(|previous| previous.last_error),
+            };
+            schedules.insert(source, schedule.clone());
+            self.save(&schedules)
+                .map_err(|e| McpError::internal(format!("Failed to save refresh schedules: {}", e)))?;
+            schedule
+        };
+        self.changed.notify_one();
+        Ok(schedule)
+    }
+
+    // Running crawls started by the schedule carry on
+    pub fn unschedule(&self, source: &str) -> Result<SourceSchedule, McpError> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let schedule = schedules
+            .remove(source)
+            .ok_or_else(|| McpError::not_found(format!("Refresh schedule of source {}", source)))?;
+        self.save(&schedules)
+            .map_err(|e| McpError::internal(format!("Failed to save refresh schedules: {}", e)))?;
+        Ok(schedule)
+    }
+
+    // Soonest next run first
+    pub fn list(&self) -> Vec<SourceSchedule> {
+        let mut schedules: Vec<SourceSchedule> = self.schedules.lock().unwrap().values().cloned().collect();
+        schedules.sort_by(|a, b| a.next_run.cmp(&b.next_run).then_with(|| a.source.cmp(&b.source)));
+        schedules
+    }
+
+    // Starts due runs until the app exits
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            self.start_due().await;
+            let wait = self
+                .schedules
+                .lock()
+                .unwrap()
+                .values()
+                .map(|schedule| schedule.next_run)
+                .min()
+                .map_or(CHECK_INTERVAL, |next| {
+                    Duration::from_millis(next.saturating_sub(now_millis())).min(CHECK_INTERVAL)
+                });
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+
+    // A source whose previous run is still going skips its turn. Runs are
+    // started without the lock held, since a refresh reads the index first.
+    async fn start_due(&self) {
+        let now = now_millis();
+        let due: Vec<(String, ScheduledTask)> = self
+            .schedules
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|schedule| schedule.next_run <= now)
+            .map(|schedule| (schedule.source.clone(), schedule.task.clone()))
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+        let mut results = Vec::new();
+        for (source, task) in due {
+            let busy = self
+                .crawls
+                .list()
+                .iter()
+                .any(|crawl| crawl.source == source && crawl.state == CrawlState::Running);
+            let result = if busy {
+                Err("the previous run is still going".to_string())
+            } else {
+                let started = match task {
+                    ScheduledTask::Refresh(options) => self.crawls.start_refresh(options).await,
+                    ScheduledTask::Crawl(options) => self.crawls.start(options),
+                    ScheduledTask::Sitemap(options) => self.crawls.start_sitemap(options),
+                    ScheduledTask::Inventory(options) => self.crawls.start_inventory(options),
+                };
+                started.map_err(|e| e.to_string())
+            };
+            results.push((source, result));
+        }
+
+        let mut schedules = self.schedules.lock().unwrap();
+        for (source, result) in results {
+            // Unscheduled while it was starting
+            let Some(schedule) = schedules.get_mut(&source) else {
+                continue;
+            };
+            match result {
+                Ok(crawl) => {
+                    info!("Scheduled crawl {} started for source {}", crawl.id, schedule.source);
+                    schedule.last_crawl_id = Some(crawl.id);
+                    schedule.last_error = None;
+                }
+                Err(e) => {
+                    warn!("Scheduled crawl of source {} did not start: {}", schedule.source, e);
+                    schedule.last_error = Some(e.to_string());
+                }
+            }
+            schedule.last_run = Some(now);
+            schedule.next_run = now + schedule.interval_hours * HOUR_MILLIS;
+        }
+        if let Err(e) = self.save(&schedules) {
+            error!("Failed to save refresh schedules: {}", e);
+        }
+    }
+
+    fn save(&self, schedules: &HashMap<String, SourceSchedule>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(schedules)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use crate::search::SearchableDocument;
+use crate::validation::validate_document;
+
+// Pages shorter than this stay whole: a few paragraphs read fine as one
+const MIN_SPLIT_BYTES: usize = 4 * 1024;
+
+// Deeper headings stay inside their section
+const MAX_SPLIT_LEVEL: usize = 3;
+
+const BREADCRUMB_SEPARATOR: &str = " » ";
+
+// Splits a page's Markdown body at its level 1 to 3 headings, so a search
+// hit points at the section to read rather than the whole page. The page
+// keeps its id and the text above its first section; each section becomes
+// a document of its own, "<page id>#<anchor>", with the page as its
+// parent_id, the headings down to it and the anchor that jumps to it. The
+// page comes first, so adding the batch replaces the sections of an older
+// copy. Short pages, pages without headings, and pages with a section
+// that wouldn't pass validation come back as they are.
+pub fn split_sections(page: SearchableDocument) -> Vec<SearchableDocument> {
+    if page.body.len() < MIN_SPLIT_BYTES || page.parent_id.is_some() {
+        return vec![page];
+    }
+
+    let mut lead = String::new();
+    let mut sections: Vec<(Vec<String>, String, String)> = Vec::new();
+    // Headings above the line being read, with their levels
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut anchors: HashMap<String, usize> = HashMap::new();
+    let mut in_fence = false;
+    for line in page.body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let found = (!in_fence).then(|| heading(line)).flatten();
+        let Some((level, text)) = found.filter(|(level, _)| *level <= MAX_SPLIT_LEVEL) else {
+            let body = sections.last_mut().map_or(&mut lead, |(_, _, body)| body);
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        };
+        // A heading that opens the page is its title
+        if level == 1 && sections.is_empty() && lead.trim().is_empty() {
+            anchors.insert(heading_anchor(&text), 1);
+            lead.push_str(line);
+            lead.push('\n');
+            continue;
+        }
+
+        let mut anchor = heading_anchor(&text);
+        if anchor.is_empty() {
+            anchor = format!("section-{}", sections.len() + 1);
+        }
+        let count = anchors.entry(anchor.clone()).or_insert(0);
+        if *count > 0 {
+            anchor = format!("{}-{}", anchor, count);
+        }
+        *count += 1;
+        path.retain(|(above, _)| *above < level);
+        path.push((level, text));
+        let headings = path.iter().map(|(_, text)| text.clone()).collect();
+        sections.push((headings, anchor, String::new()));
+    }
+    if sections.is_empty() {
+        return vec![page];
+    }
+
+    let mut documents = Vec::with_capacity(sections.len() + 1);
+    for (heading_path, anchor, body) in sections {
+        let body = body.trim();
+        if body.is_empty() {
+            continue;
+        }
+        documents.push(SearchableDocument {
+            id: format!("{}#{}", page.id, anchor),
+            title: breadcrumb_title(&page.title, &heading_path),
+            body: body.to_string(),
+            url: page.url.as_ref().map(|url| format!("{}#{}", url.split('#').next().unwrap_or_default(), anchor)),
+            indexed_at: None,
+            parent_id: Some(page.id.clone()),
+            heading_path,
+            anchor: Some(anchor),
+            ..page.clone()
+        });
+    }
+    if documents.iter().any(|section| validate_document(section, "document").is_err()) {
+        return vec![page];
+    }
+    documents.insert(
+        0,
+        SearchableDocument {
+            body: lead.trim().to_string(),
+            ..page
+        },
+    );
+    documents
+}
+
+// The page's title, then the headings down to a part of it, leaving out a
+// heading that repeats the title
+pub(super) fn breadcrumb_title(page_title: &str, heading_path: &[String]) -> String {
+    let crumbs = heading_path.iter().skip_while(|heading| heading.eq_ignore_ascii_case(page_title.trim()));
+    std::iter::once(page_title)
+        .chain(crumbs.map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(BREADCRUMB_SEPARATOR)
+}
+
+// The anchor of a section its site's generator already split out, e.g.
+// "usage" for "https://host/page#usage"
+pub(super) fn section_anchor(id: &str) -> Option<String> {
+    let (_, anchor) = id.split_once('#')?;
+    (!anchor.is_empty()).then(|| anchor.to_string())
+}
+
+// The level and text of an ATX heading, e.g. (2, "Usage") for "## Usage ##"
+pub(super) fn heading(line: &str) -> Option<(usize, String)> {
+    let unindented = line.trim_start_matches(' ');
+    // Indented four spaces, it's code
+    if line.len() - unindented.len() > 3 {
+        return None;
+    }
+    let line = unindented;
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = line[level..].strip_prefix(' ')?;
+    let text = text.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then(|| (level, text.to_string()))
+}
+
+// GitHub's (and mdBook's) heading ids: lowercase, spaces to dashes, other
+// punctuation dropped. Inline code and links count by their text.
+pub(super) fn heading_anchor(heading: &str) -> String {
+    let mut text = String::with_capacity(heading.len());
+    let mut rest = heading;
+    while let Some(start) = rest.find("](") {
+        text.push_str(&rest[..start]);
+        rest = rest[start..].find(')').map_or("", |end| &rest[start + end + 1..]);
+    }
+    text.push_str(rest);
+    text.chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                Some(c.to_lowercase().next().unwrap_or(c))
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
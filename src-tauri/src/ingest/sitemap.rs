@@ -0,0 +1,130 @@
+use std::collections::{HashSet, VecDeque};
+
+use tracing::warn;
+use url::Url;
+
+use super::Fetcher;
+use crate::McpError;
+
+// Sitemap indexes can nest; this many sitemaps are read at most per crawl
+const MAX_SITEMAPS: usize = 100;
+
+enum Sitemap {
+    // <sitemapindex>: locations of further sitemaps
+    Index(Vec<String>),
+    // <urlset>: locations of pages
+    Pages(Vec<String>),
+}
+
+// Page URLs listed by the sitemap at `url`, in order, following sitemap
+// indexes. Stops after `limit` URLs. Only a broken root sitemap is an
+// error; nested ones that fail are skipped.
+pub async fn sitemap_urls(fetcher: &Fetcher, url: &Url, limit: usize) -> Result<Vec<Url>, McpError> {
+    let mut pending = VecDeque::from([url.clone()]);
+    let mut seen_sitemaps = HashSet::from([url.to_string()]);
+    let mut seen_pages = HashSet::new();
+    let mut pages = Vec::new();
+    let mut read = 0usize;
+
+    while let Some(sitemap_url) = pending.pop_front() {
+        if pages.len() >= limit || read >= MAX_SITEMAPS {
+            break;
+        }
+        read += 1;
+        let sitemap = match read_sitemap(fetcher, &sitemap_url).await {
+            Ok(sitemap) => sitemap,
+            Err(e) if read == 1 => return Err(e),
+            Err(e) => {
+                warn!("Skipping sitemap {}: {}", sitemap_url, e);
+                continue;
+            }
+        };
+
+        match sitemap {
+            Sitemap::Index(locations) => {
+                for location in resolve(&sitemap_url, locations) {
+                    if seen_sitemaps.insert(location.to_string()) {
+                        pending.push_back(location);
+                    }
+                }
+            }
+            Sitemap::Pages(locations) => {
+                for location in resolve(&sitemap_url, locations) {
+                    if pages.len() >= limit {
+                        break;
+                    }
+                    if seen_pages.insert(location.to_string()) {
+                        pages.push(location);
+                    }
+                }
+            }
+        }
+    }
+    Ok(pages)
+}
+
+async fn read_sitemap(fetcher: &Fetcher, url: &Url) -> Result<Sitemap, McpError> {
+    if url.path().ends_with(".gz") {
+        return Err(McpError::fetch(url.as_str(), "compressed sitemaps are not supported"));
+    }
+    let page = fetcher.fetch(url).await?;
+    parse(&page.body).ok_or_else(|| McpError::fetch(url.as_str(), "not a sitemap or sitemap index"))
+}
+
+// http(s) locations only, without fragments
+fn resolve(base: &Url, locations: Vec<String>) -> impl Iterator<Item = Url> + '_ {
+    locations.into_iter().filter_map(move |location| {
+        let mut url = base.join(&location).ok()?;
+        url.set_fragment(None);
+        matches!(url.scheme(), "http" | "https").then_some(url)
+    })
+}
+
+// Sitemaps are a tiny XML vocabulary, so this only looks for the root
+// element and the <loc> elements rather than parsing XML in general.
+// Namespaced extensions such as <image:loc> are ignored.
+fn parse(xml: &str) -> Option<Sitemap> {
+    let root_is = |name: &str| xml.find(&format!("<{}", name));
+    let is_index = match (root_is("sitemapindex"), root_is("urlset")) {
+        (Some(index), Some(urlset)) => index < urlset,
+        (Some(_), None) => true,
+        (None, Some(_)) => false,
+        (None, None) => return None,
+    };
+
+    let mut locations = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<loc>") {
+        rest = &rest[start + "<loc>".len()..];
+        let Some(end) = rest.find("</loc>") else {
+            break;
+        };
+        let location = unescape(strip_cdata(rest[..end].trim()));
+        if !location.is_empty() {
+            locations.push(location);
+        }
+        rest = &rest[end..];
+    }
+
+    Some(if is_index {
+        Sitemap::Index(locations)
+    } else {
+        Sitemap::Pages(locations)
+    })
+}
+
+fn strip_cdata(text: &str) -> &str {
+    text.strip_prefix("<![CDATA[")
+        .and_then(|inner| inner.strip_suffix("]]>"))
+        .map(str::trim)
+        .unwrap_or(text)
+}
+
+// The five predefined XML entities; sitemaps must escape URLs with these
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
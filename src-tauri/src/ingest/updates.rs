@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Notify};
+use tracing::{error, info, warn};
+
+use super::registry::{latest_version, Ecosystem, LatestVersionParams};
+use super::{
+    docsrs, index_crate, index_npm_package, index_pypi_package, npm, pypi, CrateOptions, CrawlManager, Fetcher,
+    NpmPackageOptions, PypiPackageOptions,
+};
+use crate::search::SearchService;
+use crate::settings::ReleaseWatchSettings;
+use crate::util::now_millis;
+use crate::versions::version_key;
+use crate::McpError;
+
+const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+
+// As with schedules: how often the watcher looks at the clock
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Packages are recognised in the index by the sources indexCrate,
+// indexNpmPackage and indexPypiPackage file them under by default
+const PACKAGE_SOURCES: &[(&str, Ecosystem)] =
+    &[("crate:", Ecosystem::Crates), ("npm:", Ecosystem::Npm), ("pypi:", Ecosystem::Pypi)];
+
+// checkReleases and listReleaseChecks take no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ReleaseChecksParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageCheck {
+    pub source: String,
+    pub ecosystem: Ecosystem,
+    pub package: String,
+    /// The highest version indexed at the last check
+    pub indexed_version: String,
+    /// The registry's newest stable release, or pre-release when there are
+    /// only those
+    pub registry_version: Option<String>,
+    /// The registry's release, while it's newer than any indexed
+    pub new_version: Option<String>,
+    // Milliseconds since the Unix epoch
+    pub last_check: Option<u64>,
+    // Why the last check, or indexing the new release, failed
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ReleaseEvent {
+    // A release newer than any indexed, reported once per version
+    #[serde(rename_all = "camelCase")]
+    NewRelease {
+        source: String,
+        ecosystem: Ecosystem,
+        package: String,
+        indexed_version: String,
+        version: String,
+        // Whether its docs are being indexed
+        auto_index: bool,
+    },
+}
+
+// Asks crates.io, npm and PyPI whether the packages in the index have had a
+// newer release, on the interval the settings give, and reports each new
+// one; with `auto_index` on, it indexes the release's docs under the same
+// source too. What was found is kept in a JSON file next to the settings,
+// so a release is only reported once across restarts.
+pub struct ReleaseWatcher {
+    fetcher: Arc<Fetcher>,
+    crawls: Arc<CrawlManager>,
+    search_service: Arc<SearchService>,
+    settings: RwLock<ReleaseWatchSettings>,
+    path: PathBuf,
+    checks: Mutex<HashMap<String, PackageCheck>>,
+    // When the last round of checks started
+    last_round: Mutex<Option<u64>>,
+    changed: Notify,
+    events: broadcast::Sender<ReleaseEvent>,
+}
+
+impl ReleaseWatcher {
+    // A missing or unreadable file starts with nothing checked
+    pub fn load(
+        path: PathBuf,
+        fetcher: Arc<Fetcher>,
+        crawls: Arc<CrawlManager>,
+        search_service: Arc<SearchService>,
+        settings: ReleaseWatchSettings,
+    ) -> Self {
+        let checks: HashMap<String, PackageCheck> = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid release checks {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let last_round = checks.values().filter_map(|check| check.last_check).max();
+        let (events, _) = broadcast::channel(64);
+        Self {
+            fetcher,
+            crawls,
+            search_service,
+            settings: RwLock::new(settings),
+            path,
+            checks: Mutex::new(checks),
+            last_round: Mutex::new(last_round),
+            changed: Notify::new(),
+            events,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ReleaseEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn replace(&self, settings: ReleaseWatchSettings) {
+        *self.settings.write().unwrap() = settings;
+        self.changed.notify_one();
+    }
+
+    // Packages with a new release first, then by source
+    pub fn list(&self) -> Vec<PackageCheck> {
+        let mut checks: Vec<PackageCheck> = self.checks.lock().unwrap().values().cloned().collect();
+        checks.sort_by(|a, b| {
+            b.new_version.is_some().cmp(&a.new_version.is_some()).then_with(|| a.source.cmp(&b.source))
+        });
+        checks
+    }
+
+    // Checks every package in the index now, one at a time. Packages no
+    // longer in the index are forgotten.
+    pub async fn check_now(&self) -> Result<Vec<PackageCheck>, McpError> {
+        let now = now_millis();
+        *self.last_round.lock().unwrap() = Some(now);
+        let auto_index = self.settings.read().unwrap().auto_index;
+        let packages = self.indexed_packages()?;
+
+        let mut checks = HashMap::with_capacity(packages.len());
+        for (source, ecosystem, package, indexed_version) in packages {
+            let previous = self.checks.lock().unwrap().get(&source).cloned();
+            let mut check = PackageCheck {
+                source,
+                ecosystem,
+                package,
+                indexed_version,
+                registry_version: previous.as_ref().and_then(|previous| previous.registry_version.clone()),
+                new_version: None,
+                last_check: Some(now),
+                last_error: None,
+            };
+            let params = LatestVersionParams {
+                ecosystem,
+                package: check.package.clone(),
+            };
+            match latest_version(&self.fetcher, &params).await {
+                Ok(response) => {
+                    check.registry_version = response.latest.or(response.prerelease).map(|release| release.version);
+                }
+                Err(e) => check.last_error = Some(e.to_string()),
+            }
+            check.new_version = check
+                .registry_version
+                .clone()
+                .filter(|version| is_newer(version, &check.indexed_version));
+
+            let reported = previous.and_then(|previous| previous.new_version);
+            if let Some(version) = check.new_version.clone().filter(|version| reported.as_ref() != Some(version)) {
+                info!("New release {} of {} (indexed: {})", version, check.source, check.indexed_version);
+                let _ = self.events.send(ReleaseEvent::NewRelease {
+                    source: check.source.clone(),
+                    ecosystem,
+                    package: check.package.clone(),
+                    indexed_version: check.indexed_version.clone(),
+                    version: version.clone(),
+                    auto_index,
+                });
+                if auto_index {
+                    if let Err(e) = self.index_release(&check, &version).await {
+                        error!("Failed to index release {} of {}: {}", version, check.source, e);
+                        check.last_error = Some(e.to_string());
+                    }
+                }
+            }
+            checks.insert(check.source.clone(), check);
+        }
+
+        let mut records = self.checks.lock().unwrap();
+        *records = checks;
+        self.save(&records)
+            .map_err(|e| McpError::internal(format!("Failed to save release checks: {}", e)))?;
+        drop(records);
+        Ok(self.list())
+    }
+
+    // Checks on the settings' interval until the app exits
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let settings = self.settings.read().unwrap().clone();
+            let last_round = *self.last_round.lock().unwrap();
+            let interval = settings.interval_hours.max(1) * HOUR_MILLIS;
+            let due = last_round.map_or(true, |last| last + interval <= now_millis());
+            if settings.enabled && due {
+                if let Err(e) = self.check_now().await {
+                    warn!("Release check failed: {}", e);
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+
+    // (source, ecosystem, package, highest indexed version) of each package
+    // source with a semver version
+    fn indexed_packages(&self) -> Result<Vec<(String, Ecosystem, String, String)>, McpError> {
+        let aliases = self
+            .search_service
+            .version_aliases(None)
+            .map_err(|e| McpError::index("Failed to read the indexed versions", e))?;
+        Ok(aliases
+            .into_iter()
+            .filter(|alias| alias.alias == "latest" && !alias.pinned)
+            .filter_map(|alias| {
+                let (prefix, ecosystem) =
+                    PACKAGE_SOURCES.iter().find(|(prefix, _)| alias.source.starts_with(prefix))?;
+                let package = alias.source[prefix.len()..].to_string();
+                Some((alias.source, *ecosystem, package, alias.version))
+            })
+            .collect())
+    }
+
+    // Indexes the release the way the package was indexed before, minus
+    // options that weren't kept
+    async fn index_release(&self, check: &PackageCheck, version: &str) -> Result<(), McpError> {
+        let (fetcher, crawls, search_service) = (&self.fetcher, &self.crawls, self.search_service.clone());
+        let (name, version, source) = (check.package.clone(), Some(version.to_string()), Some(check.source.clone()));
+        match check.ecosystem {
+            Ecosystem::Crates => {
+                let options = CrateOptions {
+                    name,
+                    version,
+                    source,
+                    max_pages: docsrs::default_max_pages(),
+                };
+                index_crate(fetcher, crawls, search_service, options, |_, _| {}).await.map(|_| ())
+            }
+            Ecosystem::Npm => {
+                let options = NpmPackageOptions {
+                    name,
+                    version,
+                    source,
+                    crawl_homepage: npm::default_crawl_homepage(),
+                    max_pages: npm::default_max_pages(),
+                };
+                index_npm_package(fetcher, crawls, search_service, options, |_, _| {}).await.map(|_| ())
+            }
+            Ecosystem::Pypi => {
+                let options = PypiPackageOptions {
+                    name,
+                    version,
+                    source,
+                    index_docs: pypi::default_index_docs(),
+                    max_pages: pypi::default_max_pages(),
+                };
+                index_pypi_package(fetcher, crawls, search_service, options, |_, _| {}).await.map(|_| ())
+            }
+        }
+    }
+
+    fn save(&self, checks: &HashMap<String, PackageCheck>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(checks)?)?;
+        Ok(())
+    }
+}
+
+// Versions that aren't semver can't be told apart by age
+fn is_newer(version: &str, indexed: &str) -> bool {
+    match (version_key(version), version_key(indexed)) {
+        (Some(version), Some(indexed)) => version > indexed,
+        _ => false,
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{info, warn};
+
+use crate::util::now_millis;
+use crate::McpError;
+
+// Jobs running at once; the rest wait their turn in the order they came
+const MAX_RUNNING_JOBS: usize = 2;
+
+// How often a running job's progress goes out, at most
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+// Finished jobs kept around for `listJobs`; older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    /// The call that queued it, e.g. "indexCrate"
+    pub method: String,
+    /// What it indexes: a path, package, repository or URL, or the MDN areas
+    /// or man page sections asked for (none for all of them)
+    pub target: String,
+    pub state: JobState,
+    /// Milliseconds since the Unix epoch
+    pub queued_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Documents written so far and in all, as `[done, total]`, once the
+    /// job has started writing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<(usize, usize)>,
+    /// What the call returns, once the job is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Why it failed, as the call would have returned the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct JobParams {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListJobsParams {
+    /// Only jobs in this state
+    pub state: Option<JobState>,
+}
+
+// Handed to a job's work to report how far it has got. Reports closer
+// together than `PROGRESS_INTERVAL` are dropped, except the last one.
+#[derive(Clone)]
+pub struct JobProgress {
+    queue: Arc<JobQueue>,
+    id: String,
+    reported_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl JobProgress {
+    pub fn report(&self, done: usize, total: usize) {
+        {
+            let mut reported_at = self.reported_at.lock().unwrap();
+            if done < total && reported_at.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *reported_at = Some(Instant::now());
+        }
+        self.queue.update(&self.id, |job| job.progress = Some((done, total)));
+    }
+}
+
+// Ingestion that runs in the background: the call that starts it gets the
+// job back once it's queued, and the result is kept on the job. Every change
+// of state goes out to subscribers, for the UI.
+pub struct JobQueue {
+    jobs: Mutex<HashMap<String, Job>>,
+    slots: Arc<Semaphore>,
+    events: broadcast::Sender<Job>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            slots: Arc::new(Semaphore::new(MAX_RUNNING_JOBS)),
+            events,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Job> {
+        self.events.subscribe()
+    }
+
+    // Queues the work and returns its job straight away. The work should
+    // have checked its options already, so that a bad call fails at once
+    // rather than as a job. It gets a handle to report its progress with.
+    pub fn submit<T, F>(self: &Arc<Self>, method: &str, target: &str, work: impl FnOnce(JobProgress) -> F) -> Job
+    where
+        T: Serialize + Send + 'static,
+        F: Future<Output = Result<T, McpError>> + Send + 'static,
+    {
+        let job = Job {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            method: method.to_string(),
+            target: target.to_string(),
+            state: JobState::Queued,
+            queued_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+            progress: None,
+            result: None,
+            error: None,
+        };
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            prune_finished(&mut jobs);
+            jobs.insert(job.id.clone(), job.clone());
+        }
+        info!("Job {} ({} {}) queued", job.id, method, target);
+        let _ = self.events.send(job.clone());
+
+        let queue = self.clone();
+        let id = job.id.clone();
+        let work = work(JobProgress {
+            queue: self.clone(),
+            id: id.clone(),
+            reported_at: Arc::new(Mutex::new(None)),
+        });
+        tokio::spawn(async move {
+            let Ok(_slot) = queue.slots.clone().acquire_owned().await else {
+                return;
+            };
+            queue.update(&id, |job| {
+                job.state = JobState::Running;
+                job.started_at = Some(now_millis());
+            });
+            // Run as a task of its own, so a panic fails the job instead of
+            // leaving it running
+            let outcome = match tokio::spawn(work).await {
+                Ok(result) => result.and_then(|result| {
+                    serde_json::to_value(result)
+                        .map_err(|e| McpError::internal(format!("Failed to serialize job result: {}", e)))
+                }),
+                Err(e) => Err(McpError::internal(format!("Job failed: {}", e))),
+            };
+            queue.update(&id, |job| {
+                job.finished_at = Some(now_millis());
+                match outcome {
+                    Ok(result) => {
+                        info!("Job {} ({} {}) done", job.id, job.method, job.target);
+                        job.state = JobState::Done;
+                        job.result = Some(result);
+                    }
+                    Err(e) => {
+                        warn!("Job {} ({} {}) failed: {}", job.id, job.method, job.target, e);
+                        job.state = JobState::Failed;
+                        job.error = serde_json::to_value(&e).ok();
+                    }
+                }
+            });
+        });
+        job
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Job)) {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.get_mut(id) else {
+                return;
+            };
+            change(job);
+            job.clone()
+        };
+        let _ = self.events.send(job);
+    }
+
+    pub fn get(&self, id: &str) -> Result<Job, McpError> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(id)
+            .cloned()
+            .ok_or_else(|| McpError::not_found(format!("Job {}", id)))
+    }
+
+    // Newest first
+    pub fn list(&self, params: &ListJobsParams) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|job| params.state.map_or(true, |state| job.state == state))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.queued_at));
+        jobs
+    }
+}
+
+fn prune_finished(jobs: &mut HashMap<String, Job>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .values()
+        .filter_map(|job| job.finished_at.map(|at| (at, job.id.clone())))
+        .collect();
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    let excess = finished.len() + 1 - MAX_FINISHED_JOBS;
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
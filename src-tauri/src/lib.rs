@@ -1,10 +1,79 @@
+#[cfg(windows)]
+mod pipe;
+mod aliases;
+mod archive;
+mod ask;
+mod audit;
+mod batching;
+mod benchmark;
+mod bodies;
+mod cache;
+mod context;
+mod crypto;
+mod diagnostics;
+mod diff;
+mod embeddings;
+mod error;
+mod explain;
+mod health;
+mod hnsw;
+mod ingest;
+mod jobs;
+mod logging;
+mod rerank;
+mod resources;
+mod roots;
+pub mod rpc;
 mod search;
+mod server;
+mod settings;
+mod slow_queries;
+mod staleness;
+mod summary;
+mod tls;
+mod util;
+mod validation;
+mod versions;
+mod workspaces;
 
-use std::sync::Arc;
-use search::SearchService;
-use tempfile::tempdir;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use aliases::{ListVersionAliasesParams, RemoveVersionAliasParams, SetVersionAliasParams, VersionAlias};
+use archive::{ArchiveVersionsParams, ArchivedVersion, ListArchivedParams, RestoreVersionsParams};
+use ask::{AskDocsParams, AskDocsResponse};
+use audit::{AuditLog, AuditLogResponse, AuditQuery};
+use context::{ContextResponse, GetContextParams};
+use diff::{DiffVersionsParams, VersionDiff};
+use embeddings::{SemanticSearchParams, SemanticSearchResponse};
+use jobs::{Job, JobParams, JobQueue, ListJobsParams};
+use logging::{LogLevel, Logging};
+pub use error::McpError;
+use search::{
+    DuplicateGroup, FacetCount, IndexEvent, MetricBoost, MetricRange, SearchOptions, SearchReady, SearchService,
+    SearchSort, SearchTimings, DEFAULT_HITS_PER_VERSION, MAX_VERSION_GROUPS,
+};
+use server::{RpcServer, ServerStatus};
+use settings::{
+    AssistantSettings, BatchSettings, ChunkingSettings, EmbeddingSettings, FetchSettings, IndexSettings, LogSettings,
+    RateLimitSettings, ReleaseWatchSettings, RerankSettings, ServerSettings, SettingsStore, SlowQuerySettings,
+    SourceExtraction, StalenessSettings, WriterSettings, DEFAULT_SESSION_IDLE_MINUTES,
+};
+use benchmark::{BenchmarkReport, RunBenchmarkParams};
+use diagnostics::DiagnosticsBundle;
+use explain::{ExplainQueryParams, QueryExplanation};
+use health::Health;
+use resources::{ResourceUsage, REPORT_INTERVAL_SECS};
+use slow_queries::{GetSlowQueriesParams, SlowQueriesResponse, SlowQueryLog};
+use staleness::{PurgeStaleParams, StaleReport};
+use summary::{DocumentSummary, SummarizeDocumentParams};
+use tracing::{debug, error, info, warn};
+use workspaces::{Workspace, WorkspaceParams, WorkspacePin};
+use tantivy::TantivyError;
 use tauri::State;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use tauri::Emitter;
 use tauri::Manager;
 use tauri::Listener;
@@ -15,28 +84,140 @@ use search::SearchableDocument;
 // Shared application state
 pub struct AppState {
     pub search_service: Arc<SearchService>,
+    pub settings: Arc<SettingsStore>,
+    pub rpc_server: Arc<RpcServer>,
+    pub audit: Arc<AuditLog>,
+    // Set once the index has been warmed up after opening
+    pub search_ready: Arc<OnceLock<SearchReady>>,
+    pub logging: Arc<Logging>,
+    // Ingestion running in the background, shared with the RPC server
+    pub jobs: Arc<JobQueue>,
     // Add more shared resources as needed
 }
 
 impl AppState {
-    fn new() -> Result<Self, anyhow::Error> {
-        // For development, use a temporary directory for the index
-        // In production, you'd use a persistent path
-        let temp_dir = tempdir()?;
-        let index_dir = temp_dir.keep();
-        
-        println!("Initializing Tantivy index at: {:?}", index_dir);
+    fn new(config_dir: PathBuf, data_dir: PathBuf, logging: Arc<Logging>) -> Result<Self, anyhow::Error> {
+        // Kept across restarts, like the page records, schedules and feeds
+        // in the config dir that assume what they fetched is still indexed
+        let index_dir = data_dir.join("index");
+
+        info!("Opening Tantivy index at: {:?}", index_dir);
         
-        let search_service = Arc::new(SearchService::new(index_dir)?);
+        let settings = Arc::new(SettingsStore::load(config_dir.join("settings.json")));
+        if let Err(e) = logging.configure(&settings.get().log) {
+            warn!("Ignoring the saved log level: {}", e);
+        }
+        if let Err(e) = validation::validate_server_settings(&settings.get().server) {
+            warn!("Resetting the saved rate limit and session timeout: {}", e);
+            settings.update(|current| {
+                current.server.rate_limit = RateLimitSettings::default();
+                current.server.session_idle_minutes = DEFAULT_SESSION_IDLE_MINUTES;
+            })?;
+        }
+        let cipher = if settings.get().index.encrypt_at_rest {
+            info!("Document titles and bodies are stored encrypted");
+            let key_storage = settings.get().index.key_storage;
+            Some(crypto::DocumentCipher::load_or_create(key_storage, &config_dir.join("index.key"))?)
+        } else {
+            None
+        };
+        let external_body_bytes = settings.get().index.external_body_bytes;
+        if let Some(bytes) = external_body_bytes {
+            info!("Document bodies over {} bytes are kept outside the index", bytes);
+        }
+        let slow_queries = Arc::new(SlowQueryLog::open(
+            config_dir.join("slow_queries.log"),
+            settings.get().slow_queries,
+        )?);
+        let open_index = || {
+            SearchService::new(
+                index_dir.clone(),
+                cipher.clone(),
+                external_body_bytes,
+                settings.get().index.docstore,
+                settings.get().writer,
+                Some(slow_queries.clone()),
+            )
+        };
+        let search_service = match open_index() {
+            Ok(service) => service,
+            // Turning encryption or external bodies on or off changes what
+            // the index stores, and one written the other way won't open
+            Err(e) if matches!(e.downcast_ref::<TantivyError>(), Some(TantivyError::SchemaError(_))) => {
+                let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                let previous = data_dir.join(format!("index.{}", stamp));
+                warn!(
+                    "The index at {:?} was written with other settings ({}); moving it to {:?}",
+                    index_dir, e, previous
+                );
+                std::fs::rename(&index_dir, &previous)?;
+                open_index()?
+            }
+            Err(e) => return Err(e),
+        };
+        let search_service = Arc::new(search_service);
+        let api_keys = Arc::new(rpc::ApiKeyStore::load_or_create(&config_dir)?);
+        let audit = Arc::new(AuditLog::open(config_dir.join("audit.log"))?);
+        let fetcher = Arc::new(ingest::Fetcher::new(&settings.get().fetch));
+        let embeddings = Arc::new(embeddings::Embeddings::new(
+            config_dir.join("models"),
+            search_service.clone(),
+            fetcher.clone(),
+            settings.get().embeddings,
+        ));
+        let reranker = Arc::new(rerank::Reranker::new(
+            config_dir.join("models"),
+            fetcher.clone(),
+            settings.get().rerank,
+        ));
+        let assistant = Arc::new(ask::Assistant::new(
+            search_service.clone(),
+            embeddings.clone(),
+            reranker.clone(),
+            fetcher.clone(),
+            settings.get().assistant,
+        ));
+        let pages = Arc::new(ingest::PageStore::load(config_dir.join("fetched_pages.json")));
+        let extraction = Arc::new(ingest::ExtractionRules::new(settings.get().extraction));
+        let chunking = Arc::new(ingest::ChunkingRules::new(settings.get().chunking));
+        let staleness = Arc::new(staleness::StaleSweeper::new(search_service.clone(), settings.get().staleness));
+        let jobs = Arc::new(JobQueue::new());
+        let rpc_state = rpc::RpcState::new(
+            search_service.clone(),
+            api_keys,
+            audit.clone(),
+            fetcher,
+            pages,
+            extraction,
+            chunking,
+            staleness,
+            embeddings,
+            reranker,
+            assistant,
+            logging.clone(),
+            jobs.clone(),
+            settings.get().release_watch,
+            settings.get().batching,
+            &config_dir,
+        );
+        // Shared with the RPC server, for /readyz and getHealth
+        let search_ready = rpc_state.search_ready.clone();
+        let rpc_server = Arc::new(RpcServer::new(rpc_state, config_dir.join("tls")));
         
         Ok(Self {
             search_service,
+            settings,
+            rpc_server,
+            audit,
+            search_ready,
+            logging,
+            jobs,
         })
     }
 }
 
 // Define the types needed for Tauri commands
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct PingParams {
     #[serde(default = "default_ping_message")]
     pub message: String,
@@ -46,25 +227,283 @@ fn default_ping_message() -> String {
     "No message provided".to_string()
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct PingResponse {
     pub reply: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct AddDocumentParams {
     pub document: SearchableDocument,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct AddDocumentsParams {
+    pub documents: Vec<SearchableDocument>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct SearchParams {
+    /// Tantivy query string
     pub query: String,
+    /// Maximum number of results (default 10)
     pub limit: Option<usize>,
+    /// Only return documents with at least one of these tags
+    #[serde(default)]
+    pub tags_any: Vec<String>,
+    /// Only return documents with all of these tags
+    #[serde(default)]
+    pub tags_all: Vec<String>,
+    /// Result order (default relevance)
+    pub sort: Option<SearchSort>,
+    /// Rank recent documents higher: one this many days old gets half the
+    /// boost of a new one
+    pub freshness_half_life_days: Option<u32>,
+    /// Only return documents whose metrics fall within these ranges
+    #[serde(default)]
+    pub ranges: Vec<MetricRange>,
+    /// Metrics to blend into the relevance score, e.g. `stars`
+    #[serde(default)]
+    pub rank_by: Vec<MetricBoost>,
+    /// Only return documents at or below this facet, e.g. `/rust/tokio`
+    pub facet: Option<String>,
+    /// Only return documents whose version matches these comparators, e.g.
+    /// `>=1.4, <2.0`, compared as semver
+    pub version_range: Option<String>,
+    /// Only return the highest matching version of each source
+    #[serde(default)]
+    pub highest_version: bool,
+    /// Only search each source's newest indexed version (its newest
+    /// release, when it has one)
+    #[serde(default)]
+    pub latest_only: bool,
+    /// Only return documents of this version, e.g. `1.38.0`, or of the
+    /// version an alias like `stable` or `latest` stands for in each source
+    pub version: Option<String>,
+    /// Search in this workspace's context: the versions it pins rank higher,
+    /// and other versions of those sources are left out
+    pub workspace: Option<String>,
+    /// Also search the archive of superseded versions; its hits come after
+    /// the others (default false)
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Also return documents whose bodies are nearly the same as a higher
+    /// ranked one's (default false)
+    #[serde(default)]
+    pub near_duplicates: bool,
+    /// Rank by meaning: hits are the documents whose embeddings are nearest
+    /// the query's, in that order, with the filters still applied. Needs
+    /// embeddings turned on (default false)
+    #[serde(default)]
+    pub semantic: bool,
+    /// Rerank the best hits with a cross-encoder before returning them:
+    /// slower, but better hits on top (default false)
+    #[serde(default)]
+    pub rerank: bool,
+    /// Only return these fields of each hit, e.g. `["id", "title", "url",
+    /// "snippet"]`; `snippet` is a passage of the body around the query's
+    /// terms. Every field but the snippet when empty.
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Also return where the search's time went (default false)
+    #[serde(default)]
+    pub include_timings: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct SearchResponse {
-    pub documents: Vec<SearchableDocument>,
+    // Whole documents, or only the fields asked for
+    #[schemars(with = "Vec<SearchableDocument>")]
+    pub documents: Vec<serde_json::Value>,
+    /// Only with include_timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<ResponseTimings>,
+}
+
+/// Where a search's time went, in milliseconds, as measured in the server:
+/// sending the response isn't counted
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct ResponseTimings {
+    /// Parsing the query and building its filters, and embedding it for a
+    /// semantic search
+    pub parse_ms: f64,
+    /// Finding and ranking the hits, reranking included
+    pub search_ms: f64,
+    /// Loading the hits' stored documents
+    pub fetch_ms: f64,
+    /// Picking the fields asked for, making snippets and turning the hits
+    /// into JSON
+    pub serialize_ms: f64,
+    pub total_ms: f64,
+    /// The hits came from the search cache, so the index wasn't searched
+    pub cached: bool,
+}
+
+impl ResponseTimings {
+    // Adds the index's share, None when the cache answered
+    fn add_search(&mut self, timings: Option<SearchTimings>) {
+        match timings {
+            Some(timings) => {
+                self.parse_ms += timings.parse_ms;
+                self.search_ms += timings.search_ms;
+                self.fetch_ms += timings.fetch_ms;
+            }
+            None => self.cached = true,
+        }
+    }
+}
+
+fn elapsed_ms(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+// Checks a search's filters and turns them into options, the same for the
+// app's search and the RPC one. What needs the server, like a workspace's
+// pins or the query's embedding, is left to the caller.
+fn search_options(params: &SearchParams) -> Result<SearchOptions, McpError> {
+    validation::validate_search(&params.query, params.limit.unwrap_or(10))?;
+    validation::validate_tag_filters(&params.tags_any, &params.tags_all)?;
+    validation::validate_freshness(params.freshness_half_life_days)?;
+    validation::validate_metric_filters(&params.ranges, &params.rank_by)?;
+    validation::validate_facet(params.facet.as_deref())?;
+    validation::validate_version_range(params.version_range.as_deref())?;
+    validation::validate_version_filter(params.version.as_deref())?;
+    Ok(SearchOptions {
+        tags_any: params.tags_any.clone(),
+        tags_all: params.tags_all.clone(),
+        sort: params.sort.unwrap_or_default(),
+        freshness_half_life_days: params.freshness_half_life_days,
+        ranges: params.ranges.clone(),
+        rank_by: params.rank_by.clone(),
+        facet: params.facet.clone(),
+        keep_near_duplicates: params.near_duplicates,
+        version_range: params.version_range.clone(),
+        highest_version: params.highest_version,
+        latest_only: params.latest_only,
+        version: params.version.clone(),
+        include_archived: params.include_archived,
+        ..Default::default()
+    })
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct MultiSearchParams {
+    /// Searches to run at once, each as searchDocuments takes it, e.g.
+    /// reformulations of one question. Their queries must differ, as the
+    /// results are keyed by them.
+    pub searches: Vec<SearchParams>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct MultiSearchResponse {
+    /// Each search's hits, by its query
+    pub results: BTreeMap<String, MultiSearchResult>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct MultiSearchResult {
+    // As in searchDocuments; empty when the search failed
+    #[schemars(with = "Vec<SearchableDocument>")]
+    pub documents: Vec<serde_json::Value>,
+    /// Why this search failed; the others still ran
+    pub error: Option<String>,
+    /// Only with include_timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<ResponseTimings>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SearchVersionsParams {
+    /// Tantivy query string
+    pub query: String,
+    /// The source whose versions to compare, e.g. `tokio`
+    pub source: String,
+    /// Versions to search, in the order to list them, e.g. `["0.14", "0.15"]`;
+    /// aliases like `stable` work too. Default the newest versions (up to
+    /// 10) with a match, oldest first.
+    #[serde(default)]
+    pub versions: Vec<String>,
+    /// Maximum number of results per version (default 3)
+    pub limit_per_version: Option<usize>,
+    /// Only return these fields of each hit, as in searchDocuments
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct VersionHits {
+    pub version: String,
+    #[schemars(with = "Vec<SearchableDocument>")]
+    pub documents: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct SearchVersionsResponse {
+    pub source: String,
+    pub versions: Vec<VersionHits>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct GetDocumentParams {
+    pub id: String,
+    /// Only return these fields, e.g. `["id", "title", "url"]`; every field
+    /// when empty
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct GetDocumentResponse {
+    #[schemars(with = "SearchableDocument")]
+    pub document: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ListFacetsParams {
+    /// Facet to list the children of (default `/`, the top level)
+    pub facet: Option<String>,
+    /// Only count documents matching this Tantivy query
+    pub query: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ListFacetsResponse {
+    pub facets: Vec<FacetCount>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct FindDuplicatesParams {
+    /// Maximum number of groups to report, largest first (default 100)
+    pub limit: Option<usize>,
+    /// Group bodies that are nearly the same, not just identical ones
+    #[serde(default)]
+    pub near: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct FindDuplicatesResponse {
+    pub groups: Vec<DuplicateGroup>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct FetchAndIndexParams {
+    /// http(s) URL of the page to index
+    pub url: String,
+    /// Source to file the page under; defaults to the URL's host
+    pub source: Option<String>,
+    /// Version of the documented library, if known
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct FetchAndIndexResponse {
+    /// The page's final URL, which is also its document id
+    pub id: String,
+    pub title: String,
+    pub source: String,
+    /// Documents split out of the page, by default at its headings with ids
+    /// like `<id>#<anchor>`; 0 when the page is indexed whole
+    pub sections: usize,
 }
 
 // Tauri commands
@@ -74,8 +513,8 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn ping(params: PingParams) -> Result<PingResponse, String> {
-    println!("Received ping with message: {}", params.message);
+async fn ping(params: PingParams) -> Result<PingResponse, McpError> {
+    debug!("Received ping with message: {}", params.message);
     Ok(PingResponse {
         reply: format!("pong - received: {}", params.message),
     })
@@ -85,99 +524,1990 @@ async fn ping(params: PingParams) -> Result<PingResponse, String> {
 async fn add_document(
     state: State<'_, AppState>,
     params: AddDocumentParams
-) -> Result<String, String> {
-    println!("Command: add_document called with id: {}", params.document.id);
-    // Writer memory budget: 50MB per add operation, adjust as needed
-    const WRITER_MEMORY_BUDGET: usize = 50_000_000; 
-    
-    match state.search_service.add_document(params.document.clone(), WRITER_MEMORY_BUDGET) {
+) -> Result<String, McpError> {
+    debug!("Command: add_document called with id: {}", params.document.id);
+    let audit = state.audit.begin("app", "app", "add_document", &params);
+    if let Err(error) = validation::validate_document(&params.document, "document") {
+        return audit.record(Err(error));
+    }
+
+    let document = params.document.clone();
+    let result = state
+        .search_service
+        .blocking(move |service| service.add_document(document))
+        .await;
+    audit.record(match result {
         Ok(_) => Ok(format!("Document {} added successfully.", params.document.id)),
         Err(e) => {
-            eprintln!("Failed to add document: {:?}", e);
-            Err(format!("Failed to add document: {}", e))
+            error!("Failed to add document: {:?}", e);
+            Err(McpError::index("Failed to add document", e))
+        }
+    })
+}
+
+#[tauri::command]
+async fn add_documents(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    params: AddDocumentsParams
+) -> Result<String, McpError> {
+    let count = params.documents.len();
+    debug!("Command: add_documents called with {} documents", count);
+    let audit = state.audit.begin("app", "app", "add_documents", &params);
+    if let Err(error) = validation::validate_documents(&params.documents) {
+        return audit.record(Err(error));
+    }
+
+    let result = state
+        .search_service
+        .blocking(move |service| {
+            service.add_documents(params.documents, |done, total| {
+                let _ = window.emit("add-documents-progress", (done, total));
+            })
+        })
+        .await;
+    audit.record(match result {
+        Ok(_) => Ok(format!("{} documents added successfully.", count)),
+        Err(e) => {
+            error!("Failed to add documents: {:?}", e);
+            Err(McpError::index("Failed to add documents", e))
+        }
+    })
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct DeleteDocumentParams {
+    pub id: String,
+}
+
+#[tauri::command]
+async fn delete_document(
+    state: State<'_, AppState>,
+    params: DeleteDocumentParams
+) -> Result<bool, McpError> {
+    debug!("Command: delete_document called with id: {}", params.id);
+
+    let audit = state.audit.begin("app", "app", "delete_document", &params);
+    let id = params.id.clone();
+    let result = state
+        .search_service
+        .blocking(move |service| service.delete_document(&id))
+        .await;
+    audit.record(match result {
+        Ok(deleted) => Ok(deleted),
+        Err(e) => {
+            error!("Failed to delete document: {:?}", e);
+            Err(McpError::index("Failed to delete document", e))
+        }
+    })
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct OpenDocumentParams {
+    pub id: String,
+}
+
+// Local files that are safe to hand to their default app; anything else,
+// an executable above all, is never opened
+const OPENABLE_FILE_EXTENSIONS: &[&str] =
+    &["html", "htm", "md", "mdx", "markdown", "txt", "rst", "adoc", "pdf", "json", "xml"];
+
+// Opens a document's page in the default browser, or a local file in its
+// default app
+#[tauri::command]
+async fn open_document(
+    state: State<'_, AppState>,
+    params: OpenDocumentParams
+) -> Result<String, McpError> {
+    debug!("Command: open_document called with id: {}", params.id);
+    let id = params.id.clone();
+    let document = state
+        .search_service
+        .blocking(move |service| service.get_document(&id))
+        .await
+        .map_err(|e| McpError::index("Failed to look the document up", e))?
+        .ok_or_else(|| McpError::not_found(format!("document {}", params.id)))?;
+    let url = document
+        .url
+        .as_deref()
+        .and_then(|url| url::Url::parse(url).ok())
+        .ok_or_else(|| McpError::unsupported(format!("Document {} has no URL to open", params.id)))?;
+    match url.scheme() {
+        "http" | "https" => {}
+        "file" => {
+            let path = url.to_file_path().map_err(|()| McpError::unsupported(format!("{} is not a local path", url)))?;
+            let extension = path.extension().map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+            if !extension.is_some_and(|extension| OPENABLE_FILE_EXTENSIONS.contains(&extension.as_str())) {
+                return Err(McpError::unsupported(format!("{} is not a documentation file", path.display())));
+            }
+            if !path.is_file() {
+                return Err(McpError::not_found(format!("file {}", path.display())));
+            }
         }
+        scheme => return Err(McpError::unsupported(format!("Can't open {} URLs", scheme))),
     }
+    open::that_detached(url.as_str()).map_err(|e| McpError::internal(format!("Failed to open {}: {}", url, e)))?;
+    Ok(url.to_string())
 }
 
 #[tauri::command]
 async fn search_documents(
     state: State<'_, AppState>,
     params: SearchParams
-) -> Result<SearchResponse, String> {
-    println!("Command: search_documents called with query: {}", params.query);
+) -> Result<SearchResponse, McpError> {
+    debug!("Command: search_documents called with query: {}", params.query);
+    let started = Instant::now();
+    let mut timings = ResponseTimings::default();
     let limit = params.limit.unwrap_or(10); // Default limit
-    
-    match state.search_service.search_documents(&params.query, limit) {
-        Ok(documents) => Ok(SearchResponse { documents }),
-        Err(e) => {
-            eprintln!("Failed to search documents: {:?}", e);
-            Err(format!("Failed to search documents: {}", e))
+    let audit = state.audit.begin("app", "app", "search_documents", &params);
+    let checked = search_options(&params)
+        .and_then(|options| validation::validate_fields(&params.fields, true).map(|()| options));
+    let embedding = Instant::now();
+    let query_vector = match (&checked, params.semantic) {
+        (Ok(_), true) => state.rpc_server.state().embeddings.query_vector(&params.query).await.map(Some),
+        _ => Ok(None),
+    };
+    timings.parse_ms += elapsed_ms(embedding);
+    let reranker = &state.rpc_server.state().reranker;
+    let options = checked.and_then(|mut options| {
+        options.query_vector = query_vector?;
+        options.pinned_versions = state.rpc_server.state().workspaces.pinned_versions(params.workspace.as_deref())?;
+        Ok(options)
+    });
+    let wanted = if params.rerank { reranker.candidates(limit) } else { limit };
+    let documents = match options {
+        Ok(options) => match state.search_service.search_timed(&params.query, wanted, &options).await {
+            Ok((documents, search_timings)) => {
+                timings.add_search(search_timings);
+                Ok(documents)
+            }
+            Err(e) => {
+                error!("Failed to search documents: {:?}", e);
+                Err(McpError::search(&params.query, e))
+            }
+        },
+        Err(e) => Err(e),
+    };
+    let reranking = Instant::now();
+    let documents = match documents {
+        Ok(documents) if params.rerank => reranker.rerank(&params.query, documents, limit).await,
+        documents => documents,
+    };
+    timings.search_ms += elapsed_ms(reranking);
+    let result = match documents {
+        Ok(documents) => {
+            let selecting = Instant::now();
+            state
+                .search_service
+                .select(&params.query, documents, &params.fields)
+                .await
+                .map(|documents| {
+                    timings.serialize_ms = elapsed_ms(selecting);
+                    timings.total_ms = elapsed_ms(started);
+                    SearchResponse {
+                        documents,
+                        timings: params.include_timings.then_some(timings),
+                    }
+                })
+                .map_err(|e| McpError::search(&params.query, e))
         }
+        Err(e) => Err(e),
+    };
+    audit.record(result)
+}
+
+// Counts the documents under each child of a facet, for browsing the
+// taxonomy a level at a time
+#[tauri::command]
+async fn list_facets(
+    state: State<'_, AppState>,
+    params: ListFacetsParams
+) -> Result<ListFacetsResponse, McpError> {
+    let facet = params.facet.as_deref().unwrap_or("/");
+    debug!("Command: list_facets called with facet: {}", facet);
+    let audit = state.audit.begin("app", "app", "list_facets", &params);
+    if let Err(error) = validation::validate_facet_counts(params.query.as_deref(), facet) {
+        return audit.record(Err(error));
     }
+    let (query, parent) = (params.query.clone(), facet.to_string());
+    let result = state
+        .search_service
+        .blocking(move |service| service.facet_counts(query.as_deref(), &parent))
+        .await
+        .map(|facets| ListFacetsResponse { facets })
+        .map_err(|e| McpError::search(params.query.as_deref().unwrap_or_default(), e));
+    audit.record(result)
 }
 
+// Reports bodies indexed under more than one id
 #[tauri::command]
-async fn emit_event_example(window: tauri::Window) -> Result<(), String> {
-    window.emit("custom-event", Some("Event payload"))
-        .map_err(|e| e.to_string())
+async fn find_duplicates(
+    state: State<'_, AppState>,
+    params: FindDuplicatesParams
+) -> Result<FindDuplicatesResponse, McpError> {
+    let limit = params.limit.unwrap_or(100);
+    debug!("Command: find_duplicates called with limit: {}", limit);
+    let audit = state.audit.begin("app", "app", "find_duplicates", &params);
+    if let Err(error) = validation::validate_find_duplicates(limit) {
+        return audit.record(Err(error));
+    }
+    let near = params.near;
+    let groups = state
+        .search_service
+        .blocking(move |service| {
+            if near {
+                service.find_near_duplicates(limit)
+            } else {
+                service.find_duplicates(limit)
+            }
+        })
+        .await;
+    let result = groups
+        .map(|groups| FindDuplicatesResponse { groups })
+        .map_err(|e| McpError::index("Failed to look for duplicates", e));
+    audit.record(result)
 }
 
+// What each version of a source says about the query, side by side
 #[tauri::command]
-async fn run_background_task(window: tauri::Window) -> Result<(), String> {
-    tauri::async_runtime::spawn(async move {
-        for i in 0..10 {
-            // Do some work
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            
-            // Update the frontend
-            let _ = window.emit("progress", i);
-        }
-        
-        // Notify completion
-        let _ = window.emit("task-complete", true);
+async fn search_versions(
+    state: State<'_, AppState>,
+    params: SearchVersionsParams
+) -> Result<SearchVersionsResponse, McpError> {
+    debug!("Command: search_versions called with query: {} in {}", params.query, params.source);
+    let audit = state.audit.begin("app", "app", "search_versions", &params);
+    audit.record(search_by_version(&state.search_service, params).await)
+}
+
+// Shared with the searchVersions method and tool
+pub(crate) async fn search_by_version(
+    search_service: &Arc<SearchService>,
+    params: SearchVersionsParams,
+) -> Result<SearchVersionsResponse, McpError> {
+    validation::validate_search_versions(&params)?;
+    search_service.blocking(move |service| versions_side_by_side(service, &params)).await
+}
+
+fn versions_side_by_side(
+    search_service: &SearchService,
+    params: &SearchVersionsParams,
+) -> Result<SearchVersionsResponse, McpError> {
+    let per_version = params.limit_per_version.unwrap_or(DEFAULT_HITS_PER_VERSION);
+    let groups = search_service
+        .search_by_version(&params.query, &params.source, &params.versions, per_version, MAX_VERSION_GROUPS)
+        .map_err(|e| McpError::search(&params.query, e))?;
+    let mut versions = Vec::with_capacity(groups.len());
+    for (version, documents) in groups {
+        let documents = search_service
+            .select_fields(&params.query, documents, &params.fields)
+            .map_err(|e| McpError::search(&params.query, e))?;
+        versions.push(VersionHits { version, documents });
+    }
+    Ok(SearchVersionsResponse {
+        source: params.source.clone(),
+        versions,
+    })
+}
+
+// Documents close in meaning to the query, whatever words they use
+#[tauri::command]
+async fn semantic_search(
+    state: State<'_, AppState>,
+    params: SemanticSearchParams
+) -> Result<SemanticSearchResponse, McpError> {
+    debug!("Command: semantic_search called with query: {}", params.query);
+    let audit = state.audit.begin("app", "app", "semantic_search", &params);
+    audit.record(state.rpc_server.state().embeddings.clone().search(params).await)
+}
+
+// Compares the indexed pages of two versions of a source
+#[tauri::command]
+async fn diff_versions(
+    state: State<'_, AppState>,
+    params: DiffVersionsParams
+) -> Result<VersionDiff, McpError> {
+    debug!("Command: diff_versions called for {} {} and {}", params.source, params.v1, params.v2);
+    let audit = state.audit.begin("app", "app", "diff_versions", &params);
+    let result = match validation::validate_diff_versions(&params) {
+        Ok(()) => state.search_service.blocking(move |service| diff::diff_versions(service, &params)).await,
+        Err(e) => Err(e),
+    };
+    audit.record(result)
+}
+
+// The best hits for a query as one block of text for an LLM, with citations
+#[tauri::command]
+async fn get_context(
+    state: State<'_, AppState>,
+    params: GetContextParams
+) -> Result<ContextResponse, McpError> {
+    debug!("Command: get_context called with query: {}", params.query);
+    let audit = state.audit.begin("app", "app", "get_context", &params);
+    let rpc_state = state.rpc_server.state();
+    let options = match rpc_state.workspaces.pinned_versions(params.workspace.as_deref()) {
+        Ok(pinned_versions) => SearchOptions {
+            pinned_versions,
+            ..Default::default()
+        },
+        Err(e) => return audit.record(Err(e)),
+    };
+    let result = context::get_context(
+        &state.search_service,
+        &rpc_state.embeddings,
+        &rpc_state.reranker,
+        options,
+        &params,
+    )
+    .await;
+    audit.record(result)
+}
+
+// Answers a question from the indexed docs with the LLM set in the settings
+#[tauri::command]
+async fn ask_docs(
+    state: State<'_, AppState>,
+    params: AskDocsParams
+) -> Result<AskDocsResponse, McpError> {
+    debug!("Command: ask_docs called with question: {}", params.question);
+    let audit = state.audit.begin("app", "app", "ask_docs", &params);
+    let rpc_state = state.rpc_server.state();
+    let options = match rpc_state.workspaces.pinned_versions(params.workspace.as_deref()) {
+        Ok(pinned_versions) => SearchOptions {
+            pinned_versions,
+            ..Default::default()
+        },
+        Err(e) => return audit.record(Err(e)),
+    };
+    audit.record(rpc_state.assistant.ask(options, &params).await)
+}
+
+// A short preview of a stored document
+#[tauri::command]
+async fn summarize_document(
+    state: State<'_, AppState>,
+    params: SummarizeDocumentParams
+) -> Result<DocumentSummary, McpError> {
+    debug!("Command: summarize_document called with id: {}", params.id);
+    let audit = state.audit.begin("app", "app", "summarize_document", &params);
+    let rpc_state = state.rpc_server.state();
+    audit.record(summary::summarize_document(&rpc_state.search_service, &rpc_state.assistant, &params).await)
+}
+
+// Pins a version alias such as `nightly`, or holds `stable` at a version
+#[tauri::command]
+async fn set_version_alias(
+    state: State<'_, AppState>,
+    params: SetVersionAliasParams
+) -> Result<VersionAlias, McpError> {
+    debug!("Command: set_version_alias called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "set_version_alias", &params);
+    if let Err(e) = validation::validate_version_alias(&params) {
+        return audit.record(Err(e));
+    }
+    let result = state.search_service
+        .set_alias(&params.source, &params.alias, &params.version)
+        .map(|()| VersionAlias {
+            source: params.source.clone(),
+            alias: params.alias.clone(),
+            version: params.version.clone(),
+            pinned: true,
+        })
+        .map_err(|e| McpError::internal(format!("Failed to save version aliases: {}", e)));
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn remove_version_alias(
+    state: State<'_, AppState>,
+    params: RemoveVersionAliasParams
+) -> Result<VersionAlias, McpError> {
+    debug!("Command: remove_version_alias called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "remove_version_alias", &params);
+    let result = state.search_service
+        .remove_alias(&params.source, &params.alias)
+        .map_err(|e| McpError::internal(format!("Failed to save version aliases: {}", e)))
+        .and_then(|version| {
+            let alias = format!("Version alias {} of source {}", params.alias, params.source);
+            version.ok_or_else(|| McpError::not_found(alias))
+        })
+        .map(|version| VersionAlias {
+            source: params.source.clone(),
+            alias: params.alias.clone(),
+            version,
+            pinned: true,
+        });
+    audit.record(result)
+}
+
+// Pinned and automatic aliases, with the versions they stand for now
+#[tauri::command]
+async fn list_version_aliases(
+    state: State<'_, AppState>,
+    params: ListVersionAliasesParams
+) -> Result<Vec<VersionAlias>, McpError> {
+    state.search_service
+        .version_aliases(params.source.as_deref())
+        .map_err(|e| McpError::index("Failed to list version aliases", e))
+}
+
+// Moves old versions of a source into the archive, out of default searches
+#[tauri::command]
+async fn archive_versions(
+    state: State<'_, AppState>,
+    params: ArchiveVersionsParams
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    debug!("Command: archive_versions called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "archive_versions", &params);
+    let result = match validation::validate_archive_versions(&params) {
+        Ok(()) => state.search_service.blocking(move |service| archive::archive_versions(service, &params)).await,
+        Err(e) => Err(e),
+    };
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn restore_versions(
+    state: State<'_, AppState>,
+    params: RestoreVersionsParams
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    debug!("Command: restore_versions called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "restore_versions", &params);
+    let result = match validation::validate_restore_versions(&params) {
+        Ok(()) => state.search_service.blocking(move |service| archive::restore_versions(service, &params)).await,
+        Err(e) => Err(e),
+    };
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn list_archived_versions(
+    state: State<'_, AppState>,
+    params: ListArchivedParams
+) -> Result<Vec<ArchivedVersion>, McpError> {
+    archive::list_archived(&state.search_service, &params)
+}
+
+// Adds a workspace, or replaces the one of the same name
+#[tauri::command]
+async fn set_workspace(state: State<'_, AppState>, workspace: Workspace) -> Result<Workspace, McpError> {
+    debug!("Command: set_workspace called with {:?}", workspace);
+    let audit = state.audit.begin("app", "app", "set_workspace", &workspace);
+    let result = validation::validate_workspace(&workspace).and_then(|()| {
+        state.rpc_server.state()
+            .workspaces
+            .set(workspace.clone())
+            .map(|()| workspace)
+            .map_err(|e| McpError::internal(format!("Failed to save workspaces: {}", e)))
     });
-    
-    Ok(())
+    audit.record(result)
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Initialize app state
-    let app_state = match AppState::new() {
-        Ok(state) => state,
-        Err(err) => {
-            eprintln!("Failed to initialize app state: {}", err);
-            return;
-        }
+#[tauri::command]
+async fn remove_workspace(state: State<'_, AppState>, params: WorkspaceParams) -> Result<Workspace, McpError> {
+    debug!("Command: remove_workspace called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "remove_workspace", &params);
+    let result = state.rpc_server.state()
+        .workspaces
+        .remove(&params.name)
+        .map_err(|e| McpError::internal(format!("Failed to save workspaces: {}", e)))
+        .and_then(|removed| removed.ok_or_else(|| McpError::not_found(format!("Workspace {}", params.name))));
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn list_workspaces(state: State<'_, AppState>) -> Result<Vec<Workspace>, McpError> {
+    Ok(state.rpc_server.state().workspaces.list())
+}
+
+// The versions a workspace pins now, with those read from its lockfiles
+#[tauri::command]
+async fn get_workspace_pins(
+    state: State<'_, AppState>,
+    params: WorkspaceParams
+) -> Result<Vec<WorkspacePin>, McpError> {
+    state.rpc_server.state().workspaces.pins(&params.name)
+}
+
+// Finds, and unless it's a dry run removes, documents past their source's TTL
+#[tauri::command]
+async fn purge_stale(
+    state: State<'_, AppState>,
+    params: PurgeStaleParams
+) -> Result<StaleReport, McpError> {
+    debug!("Command: purge_stale called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "purge_stale", &params);
+    let result = validation::validate_purge_stale(&params)
+        .and_then(|()| state.rpc_server.state().staleness.purge(&params));
+    audit.record(result)
+}
+
+// Downloads a page and indexes its title and text under its URL
+#[tauri::command]
+async fn fetch_and_index(
+    state: State<'_, AppState>,
+    params: FetchAndIndexParams
+) -> Result<FetchAndIndexResponse, McpError> {
+    debug!("Command: fetch_and_index called with url: {}", params.url);
+    let audit = state.audit.begin("app", "app", "fetch_and_index", &params);
+    let rpc_state = state.rpc_server.state();
+    let fetched =
+        ingest::fetch_document(&rpc_state.fetcher, &rpc_state.extraction, &params.url, params.source, params.version)
+            .await;
+    let page = match fetched {
+        Ok(page) => page,
+        Err(e) => return audit.record(Err(e)),
     };
-    
-    tauri::Builder::default()
-        .setup(move |app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                  tauri_plugin_log::Builder::default()
-                    .level(log::LevelFilter::Info)
-                    .build(),
-                )?;
+    let (chunking, pages) = (rpc_state.chunking.clone(), rpc_state.pages.clone());
+    let result = state
+        .search_service
+        .blocking(move |service| {
+            let document = page.document;
+            validation::validate_document(&document, "document")?;
+            let documents = chunking.split(document);
+            let response = FetchAndIndexResponse {
+                id: documents[0].id.clone(),
+                title: documents[0].title.clone(),
+                source: documents[0].source.clone(),
+                sections: documents.len() - 1,
+            };
+            service
+                .add_documents(documents, |_, _| {})
+                .map_err(|e| McpError::index("Failed to add document", e))?;
+            if let Err(e) = pages.remember([(response.id.clone(), page.record)]) {
+                error!("Failed to save page records: {}", e);
             }
-            
-            // Setup event listeners
-            let window = app.get_webview_window("main").unwrap();
-            window.listen("frontend-event", |event| {
-                println!("Got event from frontend: {:?}", event.payload());
-            });
+            Ok(response)
+        })
+        .await;
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn emit_event_example(window: tauri::Window) -> Result<(), McpError> {
+    window.emit("custom-event", Some("Event payload"))
+        .map_err(|e| McpError::internal(e.to_string()))
+}
+
+// None until the index has been warmed up, for a frontend that started
+// listening for `search-ready` too late
+#[tauri::command]
+async fn get_search_ready(state: State<'_, AppState>) -> Result<Option<SearchReady>, McpError> {
+    Ok(state.search_ready.get().cloned())
+}
+
+// Takes a while: thousands of documents are indexed into a scratch index
+#[tauri::command]
+async fn run_benchmark(
+    state: State<'_, AppState>,
+    params: RunBenchmarkParams
+) -> Result<BenchmarkReport, McpError> {
+    debug!("Command: run_benchmark called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "run_benchmark", &params);
+    if let Err(e) = validation::validate_run_benchmark(&params) {
+        return audit.record(Err(e));
+    }
+    let result = state
+        .search_service
+        .blocking(move |service| benchmark::run_benchmark(service, &params))
+        .await
+        .map_err(|e| McpError::index("Benchmark failed", e));
+    audit.record(result)
+}
+
+// How a query parses and scores one document, for tuning relevance
+#[tauri::command]
+async fn explain_query(
+    state: State<'_, AppState>,
+    params: ExplainQueryParams,
+) -> Result<QueryExplanation, McpError> {
+    debug!("Command: explain_query called with {:?}", params);
+    explain::explain_query(&state.search_service, &params).await
+}
+
+#[tauri::command]
+async fn get_health(state: State<'_, AppState>) -> Result<Health, McpError> {
+    Ok(health::health(state.rpc_server.state()))
+}
+
+#[tauri::command]
+async fn get_resource_usage(state: State<'_, AppState>) -> Result<ResourceUsage, McpError> {
+    resources::resource_usage(state.rpc_server.state()).await
+}
+
+#[tauri::command]
+async fn get_server_status(state: State<'_, AppState>) -> Result<ServerStatus, McpError> {
+    Ok(state.rpc_server.status().await)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RestartServerParams {
+    // Saved to settings when given, otherwise the configured port is reused
+    pub port: Option<u16>,
+}
+
+#[tauri::command]
+async fn restart_server(
+    state: State<'_, AppState>,
+    params: RestartServerParams
+) -> Result<ServerStatus, McpError> {
+    debug!("Command: restart_server called with port: {:?}", params.port);
+    let audit = state.audit.begin("app", "app", "restart_server", &params);
+    let settings = match params.port {
+        Some(port) => match state.settings.update(|settings| settings.server.port = port) {
+            Ok(saved) => saved,
+            Err(e) => return audit.record(Err(McpError::internal(format!("Failed to save settings: {}", e)))),
+        },
+        None => state.settings.get(),
+    };
+    audit.record(state.rpc_server.restart(&settings.server).await)
+}
+
+#[tauri::command]
+async fn get_server_settings(state: State<'_, AppState>) -> Result<ServerSettings, McpError> {
+    Ok(state.settings.get().server)
+}
+
+// Saves new server settings and restarts the server with them
+#[tauri::command]
+async fn update_server_settings(
+    state: State<'_, AppState>,
+    settings: ServerSettings
+) -> Result<ServerStatus, McpError> {
+    debug!("Command: update_server_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_server_settings", &settings);
+    if let Err(e) = validation::validate_server_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = match state.settings.update(|current| current.server = settings) {
+        Ok(saved) => saved,
+        Err(e) => return audit.record(Err(McpError::internal(format!("Failed to save settings: {}", e)))),
+    };
+    audit.record(state.rpc_server.restart(&saved.server).await)
+}
+
+#[tauri::command]
+async fn get_index_settings(state: State<'_, AppState>) -> Result<IndexSettings, McpError> {
+    Ok(state.settings.get().index)
+}
+
+// Saved only; the index is opened with these settings on the next launch
+#[tauri::command]
+async fn update_index_settings(
+    state: State<'_, AppState>,
+    settings: IndexSettings
+) -> Result<IndexSettings, McpError> {
+    debug!("Command: update_index_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_index_settings", &settings);
+    if let Err(e) = validation::validate_index_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    audit.record(state.settings
+        .update(|current| current.index = settings)
+        .map(|saved| saved.index)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e))))
+}
+
+#[tauri::command]
+async fn get_batch_settings(state: State<'_, AppState>) -> Result<BatchSettings, McpError> {
+    Ok(state.settings.get().batching)
+}
+
+#[tauri::command]
+async fn update_batch_settings(
+    state: State<'_, AppState>,
+    settings: BatchSettings
+) -> Result<BatchSettings, McpError> {
+    debug!("Command: update_batch_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_batch_settings", &settings);
+    if let Err(e) = validation::validate_batch_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.batching = settings)
+        .map(|saved| saved.batching)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().add_queue.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_writer_settings(state: State<'_, AppState>) -> Result<WriterSettings, McpError> {
+    Ok(state.settings.get().writer)
+}
+
+#[tauri::command]
+async fn update_writer_settings(
+    state: State<'_, AppState>,
+    settings: WriterSettings
+) -> Result<WriterSettings, McpError> {
+    debug!("Command: update_writer_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_writer_settings", &settings);
+    if let Err(e) = validation::validate_writer_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.writer = settings)
+        .map(|saved| saved.writer)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.search_service.set_writer_settings(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_slow_query_settings(state: State<'_, AppState>) -> Result<SlowQuerySettings, McpError> {
+    Ok(state.settings.get().slow_queries)
+}
+
+#[tauri::command]
+async fn update_slow_query_settings(
+    state: State<'_, AppState>,
+    settings: SlowQuerySettings
+) -> Result<SlowQuerySettings, McpError> {
+    debug!("Command: update_slow_query_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_slow_query_settings", &settings);
+    if let Err(e) = validation::validate_slow_query_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.slow_queries = settings)
+        .map(|saved| saved.slow_queries)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let (Ok(saved), Some(slow_queries)) = (&saved, state.search_service.slow_queries()) {
+        slow_queries.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+// Searches that took longer than the slow-query threshold, slowest first
+#[tauri::command]
+async fn get_slow_queries(
+    state: State<'_, AppState>,
+    params: GetSlowQueriesParams,
+) -> Result<SlowQueriesResponse, McpError> {
+    slow_queries::get_slow_queries(&state.search_service, &params)
+}
+
+#[tauri::command]
+async fn get_extraction_settings(
+    state: State<'_, AppState>
+) -> Result<HashMap<String, SourceExtraction>, McpError> {
+    Ok(state.settings.get().extraction)
+}
+
+// Per-source selector overrides for fetched pages, keyed by source. Applies
+// to pages fetched from now on, including by running crawls.
+#[tauri::command]
+async fn update_extraction_settings(
+    state: State<'_, AppState>,
+    settings: HashMap<String, SourceExtraction>
+) -> Result<HashMap<String, SourceExtraction>, McpError> {
+    debug!("Command: update_extraction_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_extraction_settings", &settings);
+    for (source, extraction) in &settings {
+        let invalid = extraction.content.iter()
+            .chain(&extraction.remove)
+            .find(|selector| !ingest::is_valid_selector(selector));
+        if let Some(selector) = invalid {
+            return audit.record(Err(McpError::validation(
+                Some(source),
+                format!("Invalid CSS selector for {}: {}", source, selector),
+            )));
+        }
+    }
+    let saved = state.settings
+        .update(|current| current.extraction = settings)
+        .map(|saved| saved.extraction)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().extraction.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_chunking_settings(state: State<'_, AppState>) -> Result<ChunkingSettings, McpError> {
+    Ok(state.settings.get().chunking)
+}
+
+// How pages are split into documents, for every source or by source.
+// Applies to pages indexed from now on, including by running crawls.
+#[tauri::command]
+async fn update_chunking_settings(
+    state: State<'_, AppState>,
+    settings: ChunkingSettings
+) -> Result<ChunkingSettings, McpError> {
+    debug!("Command: update_chunking_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_chunking_settings", &settings);
+    if let Err(e) = validation::validate_chunking_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.chunking = settings)
+        .map(|saved| saved.chunking)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().chunking.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_fetch_settings(state: State<'_, AppState>) -> Result<FetchSettings, McpError> {
+    Ok(state.settings.get().fetch)
+}
+
+// Proxy, extra headers and TLS trust for downloading pages. Applies to the
+// next request, including those of running crawls.
+#[tauri::command]
+async fn update_fetch_settings(
+    state: State<'_, AppState>,
+    settings: FetchSettings
+) -> Result<FetchSettings, McpError> {
+    // Not printed, the proxy URL and headers may hold credentials
+    debug!("Command: update_fetch_settings called");
+    let audit = state.audit.begin("app", "app", "update_fetch_settings", &settings);
+    if let Err(e) = state.rpc_server.state().fetcher.configure(&settings) {
+        return audit.record(Err(e));
+    }
+    audit.record(state.settings
+        .update(|current| current.fetch = settings)
+        .map(|saved| saved.fetch)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e))))
+}
+
+#[tauri::command]
+async fn get_staleness_settings(state: State<'_, AppState>) -> Result<StalenessSettings, McpError> {
+    Ok(state.settings.get().staleness)
+}
+
+// Per-source TTLs, and whether the hourly sweep removes stale documents or
+// only reports them. Applies from the next sweep.
+#[tauri::command]
+async fn update_staleness_settings(
+    state: State<'_, AppState>,
+    settings: StalenessSettings
+) -> Result<StalenessSettings, McpError> {
+    debug!("Command: update_staleness_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_staleness_settings", &settings);
+    if let Err(e) = validation::validate_staleness_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.staleness = settings)
+        .map(|saved| saved.staleness)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().staleness.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_release_watch_settings(state: State<'_, AppState>) -> Result<ReleaseWatchSettings, McpError> {
+    Ok(state.settings.get().release_watch)
+}
+
+// Whether and how often indexed packages are checked for new releases, and
+// whether those are indexed straight away. Applies from the next check.
+#[tauri::command]
+async fn update_release_watch_settings(
+    state: State<'_, AppState>,
+    settings: ReleaseWatchSettings
+) -> Result<ReleaseWatchSettings, McpError> {
+    debug!("Command: update_release_watch_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_release_watch_settings", &settings);
+    if let Err(e) = validation::validate_release_watch_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.release_watch = settings)
+        .map(|saved| saved.release_watch)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().releases.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_embedding_settings(state: State<'_, AppState>) -> Result<EmbeddingSettings, McpError> {
+    Ok(state.settings.get().embeddings)
+}
+
+// Turning embeddings on, or switching models, embeds the whole index in the
+// background; the local model is downloaded first
+#[tauri::command]
+async fn update_embedding_settings(
+    state: State<'_, AppState>,
+    settings: EmbeddingSettings
+) -> Result<EmbeddingSettings, McpError> {
+    // Not printed, the API key is a credential
+    debug!("Command: update_embedding_settings called");
+    let audit = state.audit.begin("app", "app", "update_embedding_settings", &settings);
+    if let Err(e) = validation::validate_embedding_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.embeddings = settings)
+        .map(|saved| saved.embeddings)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().embeddings.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_rerank_settings(state: State<'_, AppState>) -> Result<RerankSettings, McpError> {
+    Ok(state.settings.get().rerank)
+}
+
+#[tauri::command]
+async fn update_rerank_settings(
+    state: State<'_, AppState>,
+    settings: RerankSettings
+) -> Result<RerankSettings, McpError> {
+    // Not printed, the API key is a credential
+    debug!("Command: update_rerank_settings called");
+    let audit = state.audit.begin("app", "app", "update_rerank_settings", &settings);
+    if let Err(e) = validation::validate_rerank_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.rerank = settings)
+        .map(|saved| saved.rerank)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().reranker.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+#[tauri::command]
+async fn get_assistant_settings(state: State<'_, AppState>) -> Result<AssistantSettings, McpError> {
+    Ok(state.settings.get().assistant)
+}
+
+#[tauri::command]
+async fn update_assistant_settings(
+    state: State<'_, AppState>,
+    settings: AssistantSettings
+) -> Result<AssistantSettings, McpError> {
+    // Not printed, the API key is a credential
+    debug!("Command: update_assistant_settings called");
+    let audit = state.audit.begin("app", "app", "update_assistant_settings", &settings);
+    if let Err(e) = validation::validate_assistant_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.assistant = settings)
+        .map(|saved| saved.assistant)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    if let Ok(saved) = &saved {
+        state.rpc_server.state().assistant.replace(saved.clone());
+    }
+    audit.record(saved)
+}
+
+// The report of the last stale document sweep, None before the first
+#[tauri::command]
+async fn get_stale_report(state: State<'_, AppState>) -> Result<Option<StaleReport>, McpError> {
+    Ok(state.rpc_server.state().staleness.last_report())
+}
+
+// The key HTTP and WebSocket clients must send as `Authorization: Bearer <key>`
+#[tauri::command]
+async fn get_api_key(state: State<'_, AppState>) -> Result<String, McpError> {
+    Ok(state.rpc_server.state().api_keys.get())
+}
+
+// Generates a new key; clients using the old one have to be reconfigured
+#[tauri::command]
+async fn rotate_api_key(state: State<'_, AppState>) -> Result<String, McpError> {
+    debug!("Command: rotate_api_key called");
+    let audit = state.audit.begin("app", "app", "rotate_api_key", &());
+    audit.record(state.rpc_server.state().api_keys
+        .rotate()
+        .map_err(|e| McpError::internal(format!("Failed to save API key: {}", e))))
+}
+
+// Additional tokens, e.g. a `read` one for an agent that should only search
+#[tauri::command]
+async fn list_api_tokens(state: State<'_, AppState>) -> Result<Vec<rpc::ApiToken>, McpError> {
+    Ok(state.rpc_server.state().api_keys.list_tokens())
+}
+
+// The returned `key` is not stored and can't be shown again
+#[tauri::command]
+async fn create_api_token(
+    state: State<'_, AppState>,
+    name: String,
+    scope: rpc::Scope,
+) -> Result<rpc::IssuedToken, McpError> {
+    debug!("Command: create_api_token called with name: {}, scope: {:?}", name, scope);
+    let audit = state.audit.begin("app", "app", "create_api_token", &(&name, scope));
+    if name.trim().is_empty() {
+        return audit.record(Err(McpError::validation(Some("name"), "Token name must not be empty")));
+    }
+    audit.record(state.rpc_server.state().api_keys
+        .create_token(name.trim(), scope)
+        .map_err(|e| McpError::internal(format!("Failed to save API token: {}", e))))
+}
+
+#[tauri::command]
+async fn revoke_api_token(state: State<'_, AppState>, id: String) -> Result<(), McpError> {
+    debug!("Command: revoke_api_token called with id: {}", id);
+    let audit = state.audit.begin("app", "app", "revoke_api_token", &id);
+    audit.record(match state.rpc_server.state().api_keys.revoke_token(&id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(McpError::not_found(format!("API token {}", id))),
+        Err(e) => Err(McpError::internal(format!("Failed to save API tokens: {}", e))),
+    })
+}
+
+// MCP sessions and recently active API keys
+#[tauri::command]
+async fn list_clients(state: State<'_, AppState>) -> Result<Vec<rpc::ClientInfo>, McpError> {
+    Ok(rpc::list_clients(state.rpc_server.state()))
+}
+
+#[tauri::command]
+async fn disconnect_client(state: State<'_, AppState>, id: String) -> Result<(), McpError> {
+    debug!("Command: disconnect_client called with id: {}", id);
+    let audit = state.audit.begin("app", "app", "disconnect_client", &id);
+    audit.record(rpc::disconnect_client(state.rpc_server.state(), &id))
+}
+
+// Tells the UI about agents connecting and going away as
+// `client-connected` (a ClientInfo) and `client-disconnected` (the id)
+async fn forward_client_events(app: tauri::AppHandle, mut events: tokio::sync::broadcast::Receiver<rpc::ClientEvent>) {
+    loop {
+        let result = match events.recv().await {
+            Ok(rpc::ClientEvent::Connected { client }) => app.emit("client-connected", client),
+            Ok(rpc::ClientEvent::Disconnected { id }) => app.emit("client-disconnected", id),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit client event: {:?}", e);
+        }
+    }
+}
+
+// Indexes a local PDF, Markdown, HTML or text file; PDFs become one
+// document per page
+#[tauri::command]
+async fn add_document_from_file(
+    state: State<'_, AppState>,
+    options: ingest::AddFileOptions
+) -> Result<ingest::AddFileResponse, McpError> {
+    debug!("Command: add_document_from_file called with path: {}", options.path);
+    let audit = state.audit.begin("app", "app", "add_document_from_file", &options);
+    if let Err(e) = validation::validate_add_file(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let chunking = state.rpc_server.state().chunking.clone();
+    let result = tokio::task::spawn_blocking(move || ingest::add_file(&search_service, &chunking, &options))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+        .and_then(|result| result);
+    audit.record(result)
+}
+
+// Indexes the Markdown files of a local folder, e.g. a project's docs/
+#[tauri::command]
+async fn index_path(
+    state: State<'_, AppState>,
+    options: ingest::IndexPathOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_path called with path: {}", options.path);
+    let audit = state.audit.begin("app", "app", "index_path", &options);
+    if let Err(e) = validation::validate_index_path(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let chunking = state.rpc_server.state().chunking.clone();
+    let target = options.path.clone();
+    let job = state.jobs.submit("index_path", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        tokio::task::spawn_blocking(move || ingest::index_path(&search_service, &chunking, &options, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+            .and_then(|result| result)
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a crate's rustdoc JSON output item by item
+#[tauri::command]
+async fn index_rustdoc(
+    state: State<'_, AppState>,
+    options: ingest::RustdocOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_rustdoc called with path: {}", options.path);
+    let audit = state.audit.begin("app", "app", "index_rustdoc", &options);
+    if let Err(e) = validation::validate_rustdoc(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let target = options.path.clone();
+    let job = state.jobs.submit("index_rustdoc", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        tokio::task::spawn_blocking(move || ingest::index_rustdoc(&search_service, &options, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+            .and_then(|result| result)
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a published or local mdBook section by section
+#[tauri::command]
+async fn index_mdbook(
+    state: State<'_, AppState>,
+    options: ingest::MdbookOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_mdbook called with location: {}", options.location);
+    let audit = state.audit.begin("app", "app", "index_mdbook", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_mdbook(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.location.clone();
+    let job = state.jobs.submit("index_mdbook", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        ingest::index_mdbook(&rpc_state.fetcher, rpc_state.search_service.clone(), options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Looks up a package's newest releases on crates.io, npm or PyPI
+#[tauri::command]
+async fn get_latest_version(
+    state: State<'_, AppState>,
+    params: ingest::LatestVersionParams
+) -> Result<ingest::LatestVersionResponse, McpError> {
+    debug!("Command: get_latest_version called with package: {}", params.package);
+    let audit = state.audit.begin("app", "app", "get_latest_version", &params);
+    let rpc_state = state.rpc_server.state();
+    if let Err(e) = validation::validate_latest_version(&params) {
+        return audit.record(Err(e));
+    }
+    let result = ingest::latest_version(&rpc_state.fetcher, &params).await;
+    audit.record(result)
+}
+
+// Asks the registries now whether indexed packages have newer releases
+#[tauri::command]
+async fn check_releases(state: State<'_, AppState>) -> Result<Vec<ingest::PackageCheck>, McpError> {
+    debug!("Command: check_releases called");
+    let audit = state.audit.begin("app", "app", "check_releases", &());
+    let result = state.rpc_server.state().releases.check_now().await;
+    audit.record(result)
+}
+
+// What the last checks found, packages with a new release first
+#[tauri::command]
+async fn list_release_checks(state: State<'_, AppState>) -> Result<Vec<ingest::PackageCheck>, McpError> {
+    Ok(state.rpc_server.state().releases.list())
+}
+
+// Indexes a crate's documentation from docs.rs
+#[tauri::command]
+async fn index_crate(
+    state: State<'_, AppState>,
+    options: ingest::CrateOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_crate called with name: {}", options.name);
+    let audit = state.audit.begin("app", "app", "index_crate", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_crate(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.name.clone();
+    let job = state.jobs.submit("index_crate", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        let search_service = rpc_state.search_service.clone();
+        ingest::index_crate(&rpc_state.fetcher, &rpc_state.crawls, search_service, options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes an npm package's README and typings, and crawls its docs site
+#[tauri::command]
+async fn index_npm_package(
+    state: State<'_, AppState>,
+    options: ingest::NpmPackageOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_npm_package called with name: {}", options.name);
+    let audit = state.audit.begin("app", "app", "index_npm_package", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_npm_package(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.name.clone();
+    let job = state.jobs.submit("index_npm_package", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        let search_service = rpc_state.search_service.clone();
+        ingest::index_npm_package(&rpc_state.fetcher, &rpc_state.crawls, search_service, options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a PyPI project's description and its documentation site
+#[tauri::command]
+async fn index_pypi_package(
+    state: State<'_, AppState>,
+    options: ingest::PypiPackageOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_pypi_package called with name: {}", options.name);
+    let audit = state.audit.begin("app", "app", "index_pypi_package", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_pypi_package(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.name.clone();
+    let job = state.jobs.submit("index_pypi_package", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        let search_service = rpc_state.search_service.clone();
+        ingest::index_pypi_package(&rpc_state.fetcher, &rpc_state.crawls, search_service, options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a GitHub repository's README, docs folder and wiki
+#[tauri::command]
+async fn index_github_repo(
+    state: State<'_, AppState>,
+    options: ingest::GitHubRepoOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_github_repo called with repo: {}", options.repo);
+    let audit = state.audit.begin("app", "app", "index_github_repo", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_github_repo(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.repo.clone();
+    let job = state.jobs.submit("index_github_repo", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        ingest::index_github_repo(
+            &rpc_state.fetcher,
+            &rpc_state.crawls,
+            &rpc_state.chunking,
+            rpc_state.search_service.clone(),
+            options,
+            on_progress,
+        )
+        .await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes the docs in a Git repository, cloned or on this machine, as of a
+// branch, tag or commit
+#[tauri::command]
+async fn index_git_repo(
+    state: State<'_, AppState>,
+    options: ingest::GitRepoOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_git_repo called with repo: {}, ref: {:?}", options.repo, options.git_ref);
+    let audit = state.audit.begin("app", "app", "index_git_repo", &options);
+    if let Err(e) = validation::validate_git_repo(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let chunking = state.rpc_server.state().chunking.clone();
+    let mirrors = state.rpc_server.state().git_mirrors.clone();
+    let target = options.repo.clone();
+    let job = state.jobs.submit("index_git_repo", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        tokio::task::spawn_blocking(move || {
+            ingest::index_git_repo(&search_service, &chunking, &mirrors, &options, on_progress)
+        })
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+        .and_then(|result| result)
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a GitHub repository's release notes and changelog, one document
+// per release
+#[tauri::command]
+async fn index_release_notes(
+    state: State<'_, AppState>,
+    options: ingest::ReleaseNotesOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_release_notes called with repo: {}", options.repo);
+    let audit = state.audit.begin("app", "app", "index_release_notes", &options);
+    if let Err(e) = validation::validate_release_notes(&options) {
+        return audit.record(Err(e));
+    }
+    let rpc_state = state.rpc_server.state().clone();
+    let target = options.repo.clone();
+    let job = state.jobs.submit("index_release_notes", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        ingest::index_release_notes(&rpc_state.fetcher, rpc_state.search_service.clone(), options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes MDN's web docs for some technology areas, with compat tables
+#[tauri::command]
+async fn index_mdn(
+    state: State<'_, AppState>,
+    options: ingest::MdnOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_mdn called with areas: {:?}", options.areas);
+    let audit = state.audit.begin("app", "app", "index_mdn", &options);
+    if let Err(e) = validation::validate_mdn(&options) {
+        return audit.record(Err(e));
+    }
+    let rpc_state = state.rpc_server.state().clone();
+    let target = options.areas.join(", ");
+    let job = state.jobs.submit("index_mdn", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        let search_service = rpc_state.search_service.clone();
+        ingest::index_mdn(&rpc_state.fetcher, &rpc_state.chunking, search_service, options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes the entries of a Dash or Zeal docset
+#[tauri::command]
+async fn index_docset(
+    state: State<'_, AppState>,
+    options: ingest::DocsetOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_docset called with path: {}", options.path);
+    let audit = state.audit.begin("app", "app", "index_docset", &options);
+    if let Err(e) = validation::validate_docset(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let target = options.path.clone();
+    let job = state.jobs.submit("index_docset", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        tokio::task::spawn_blocking(move || ingest::index_docset(&search_service, &options, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+            .and_then(|result| result)
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes the man pages installed on this machine
+#[tauri::command]
+async fn index_man_pages(
+    state: State<'_, AppState>,
+    options: ingest::ManPagesOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_man_pages called with sections: {:?}", options.sections);
+    let audit = state.audit.begin("app", "app", "index_man_pages", &options);
+    if let Err(e) = validation::validate_man_pages(&options) {
+        return audit.record(Err(e));
+    }
+    let search_service = state.search_service.clone();
+    let target = options.sections.join(", ");
+    let job = state.jobs.submit("index_man_pages", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        tokio::task::spawn_blocking(move || ingest::index_man_pages(&search_service, &options, on_progress))
+            .await
+            .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))
+            .and_then(|result| result)
+    });
+    audit.record(Ok(job))
+}
+
+// Downloads a DevDocs documentation pack and indexes its entries
+#[tauri::command]
+async fn index_devdocs(
+    state: State<'_, AppState>,
+    options: ingest::DevdocsOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_devdocs called with doc: {}", options.doc);
+    let audit = state.audit.begin("app", "app", "index_devdocs", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_devdocs(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.doc.clone();
+    let job = state.jobs.submit("index_devdocs", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        ingest::index_devdocs(&rpc_state.fetcher, rpc_state.search_service.clone(), options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Indexes a Docusaurus or MkDocs site section by section
+#[tauri::command]
+async fn index_docs_site(
+    state: State<'_, AppState>,
+    options: ingest::DocsSiteOptions
+) -> Result<Job, McpError> {
+    debug!("Command: index_docs_site called with url: {}", options.url);
+    let audit = state.audit.begin("app", "app", "index_docs_site", &options);
+    let rpc_state = state.rpc_server.state().clone();
+    if let Err(e) = validation::validate_docs_site(&options) {
+        return audit.record(Err(e));
+    }
+    let target = options.url.clone();
+    let job = state.jobs.submit("index_docs_site", &target, |progress| async move {
+        let on_progress = move |done, total| progress.report(done, total);
+        let search_service = rpc_state.search_service.clone();
+        ingest::index_docs_site(&rpc_state.fetcher, &rpc_state.crawls, search_service, options, on_progress).await
+    });
+    audit.record(Ok(job))
+}
+
+// Ingestion jobs, newest first; each change of state also arrives as an
+// `index://job-progress` event
+#[tauri::command]
+async fn list_jobs(state: State<'_, AppState>, params: ListJobsParams) -> Result<Vec<Job>, McpError> {
+    Ok(state.jobs.list(&params))
+}
+
+#[tauri::command]
+async fn get_job(state: State<'_, AppState>, params: JobParams) -> Result<Job, McpError> {
+    state.jobs.get(&params.id)
+}
+
+// Crawls a documentation site in the background; progress arrives as
+// `index://crawl-progress` events
+#[tauri::command]
+async fn start_crawl(
+    state: State<'_, AppState>,
+    options: ingest::CrawlOptions
+) -> Result<ingest::CrawlStatus, McpError> {
+    debug!("Command: start_crawl called with seed: {}", options.seed_url);
+    let audit = state.audit.begin("app", "app", "start_crawl", &options);
+    let rpc_state = state.rpc_server.state();
+    audit.record(validation::validate_crawl(&options).and_then(|()| {
+        rpc_state.crawls.start(options)
+    }))
+}
+
+// Indexes the pages a sitemap.xml lists instead of following links
+#[tauri::command]
+async fn crawl_sitemap(
+    state: State<'_, AppState>,
+    options: ingest::SitemapCrawlOptions
+) -> Result<ingest::CrawlStatus, McpError> {
+    debug!("Command: crawl_sitemap called with url: {}", options.url);
+    let audit = state.audit.begin("app", "app", "crawl_sitemap", &options);
+    let rpc_state = state.rpc_server.state();
+    audit.record(validation::validate_sitemap_crawl(&options).and_then(|()| {
+        rpc_state.crawls.start_sitemap(options)
+    }))
+}
+
+// Indexes the pages a Sphinx objects.inv documents objects on
+#[tauri::command]
+async fn crawl_inventory(
+    state: State<'_, AppState>,
+    options: ingest::InventoryCrawlOptions
+) -> Result<ingest::CrawlStatus, McpError> {
+    debug!("Command: crawl_inventory called with url: {}", options.url);
+    let audit = state.audit.begin("app", "app", "crawl_inventory", &options);
+    let rpc_state = state.rpc_server.state();
+    audit.record(validation::validate_inventory_crawl(&options).and_then(|()| {
+        rpc_state.crawls.start_inventory(options)
+    }))
+}
+
+// Downloads a source's pages again where the server says they changed
+#[tauri::command]
+async fn refresh_source(
+    state: State<'_, AppState>,
+    options: ingest::RefreshOptions
+) -> Result<ingest::CrawlStatus, McpError> {
+    debug!("Command: refresh_source called with source: {}", options.source);
+    let audit = state.audit.begin("app", "app", "refresh_source", &options);
+    let result = match validation::validate_refresh(&options) {
+        Ok(()) => state.rpc_server.state().crawls.start_refresh(options).await,
+        Err(e) => Err(e),
+    };
+    audit.record(result)
+}
+
+#[tauri::command]
+async fn cancel_crawl(state: State<'_, AppState>, id: String) -> Result<ingest::CrawlStatus, McpError> {
+    debug!("Command: cancel_crawl called with id: {}", id);
+    let audit = state.audit.begin("app", "app", "cancel_crawl", &id);
+    audit.record(state.rpc_server.state().crawls.cancel(&id))
+}
+
+#[tauri::command]
+async fn list_crawls(state: State<'_, AppState>) -> Result<Vec<ingest::CrawlStatus>, McpError> {
+    Ok(state.rpc_server.state().crawls.list())
+}
+
+// Pages of the source that crawls couldn't download or index, and why
+#[tauri::command]
+async fn get_source_failures(
+    state: State<'_, AppState>,
+    source: String
+) -> Result<Vec<ingest::PageFailure>, McpError> {
+    Ok(state.rpc_server.state().crawls.failures(&source))
+}
+
+// Re-runs a refresh, crawl, sitemap or inventory crawl for a source every few hours
+#[tauri::command]
+async fn schedule_source(
+    state: State<'_, AppState>,
+    options: ingest::ScheduleOptions
+) -> Result<ingest::SourceSchedule, McpError> {
+    debug!("Command: schedule_source called for source: {}", options.task.source());
+    let audit = state.audit.begin("app", "app", "schedule_source", &options);
+    let schedules = &state.rpc_server.state().schedules;
+    audit.record(validation::validate_schedule(&options).and_then(|()| schedules.schedule(options)))
+}
+
+#[tauri::command]
+async fn unschedule_source(state: State<'_, AppState>, source: String) -> Result<ingest::SourceSchedule, McpError> {
+    debug!("Command: unschedule_source called with source: {}", source);
+    let audit = state.audit.begin("app", "app", "unschedule_source", &source);
+    audit.record(state.rpc_server.state().schedules.unschedule(&source))
+}
+
+#[tauri::command]
+async fn list_schedules(state: State<'_, AppState>) -> Result<Vec<ingest::SourceSchedule>, McpError> {
+    Ok(state.rpc_server.state().schedules.list())
+}
+
+// Checks an RSS or Atom feed every few minutes and indexes its new entries
+#[tauri::command]
+async fn watch_feed(
+    state: State<'_, AppState>,
+    options: ingest::WatchFeedOptions
+) -> Result<ingest::WatchedFeed, McpError> {
+    debug!("Command: watch_feed called with url: {}", options.url);
+    let audit = state.audit.begin("app", "app", "watch_feed", &options);
+    let feeds = &state.rpc_server.state().feeds;
+    audit.record(validation::validate_watch_feed(&options).and_then(|()| feeds.watch(options)))
+}
+
+#[tauri::command]
+async fn unwatch_feed(state: State<'_, AppState>, url: String) -> Result<ingest::WatchedFeed, McpError> {
+    debug!("Command: unwatch_feed called with url: {}", url);
+    let audit = state.audit.begin("app", "app", "unwatch_feed", &url);
+    audit.record(state.rpc_server.state().feeds.unwatch(&url))
+}
+
+#[tauri::command]
+async fn list_feeds(state: State<'_, AppState>) -> Result<Vec<ingest::WatchedFeed>, McpError> {
+    Ok(state.rpc_server.state().feeds.list())
+}
+
+// What the index is doing: `index://document-added` for each document a
+// write adds, then `index://commit`, or `index://error` for a write that
+// failed. Deletes only show in their commit.
+async fn forward_index_events(app: tauri::AppHandle, mut events: tokio::sync::broadcast::Receiver<IndexEvent>) {
+    loop {
+        let result = match events.recv().await {
+            Ok(event @ IndexEvent::DocumentAdded { .. }) => app.emit("index://document-added", event),
+            Ok(event @ IndexEvent::Commit(_)) => app.emit("index://commit", event),
+            Ok(event @ IndexEvent::Error(_)) => app.emit("index://error", event),
+            Ok(IndexEvent::DocumentDeleted { .. }) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit index event: {:?}", e);
+        }
+    }
+}
+
+// Every change to a crawl's status, including the final one, as
+// `index://crawl-progress`
+async fn forward_crawl_events(app: tauri::AppHandle, mut events: tokio::sync::broadcast::Receiver<ingest::CrawlEvent>) {
+    loop {
+        let result = match events.recv().await {
+            Ok(event) => app.emit("index://crawl-progress", event),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit crawl event: {:?}", e);
+        }
+    }
+}
+
+// Every change to a job's state, from queued to done or failed, as
+// `index://job-progress`
+async fn forward_job_events(app: tauri::AppHandle, mut events: tokio::sync::broadcast::Receiver<Job>) {
+    loop {
+        let result = match events.recv().await {
+            Ok(job) => app.emit("index://job-progress", job),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit job event: {:?}", e);
+        }
+    }
+}
+
+// Entries a feed check found and indexed, as `feed-new-content`
+async fn forward_feed_events(app: tauri::AppHandle, mut events: tokio::sync::broadcast::Receiver<ingest::FeedEvent>) {
+    loop {
+        let result = match events.recv().await {
+            Ok(event) => app.emit("feed-new-content", event),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit feed event: {:?}", e);
+        }
+    }
+}
+
+// Sweeps that found stale documents, as `stale-documents`
+async fn forward_stale_reports(app: tauri::AppHandle, mut reports: tokio::sync::broadcast::Receiver<StaleReport>) {
+    loop {
+        let result = match reports.recv().await {
+            Ok(report) => app.emit("stale-documents", report),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit stale report: {:?}", e);
+        }
+    }
+}
+
+// Releases newer than the indexed version of a package, as `new-release`
+async fn forward_release_events(
+    app: tauri::AppHandle,
+    mut events: tokio::sync::broadcast::Receiver<ingest::ReleaseEvent>,
+) {
+    loop {
+        let result = match events.recv().await {
+            Ok(event) => app.emit("new-release", event),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if let Err(e) = result {
+            error!("Failed to emit release event: {:?}", e);
+        }
+    }
+}
+
+// What the app is holding on to, every REPORT_INTERVAL_SECS, as
+// `resource-usage`
+async fn report_resource_usage(app: tauri::AppHandle, state: rpc::RpcState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(REPORT_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        let result = match resources::resource_usage(&state).await {
+            Ok(usage) => app.emit("resource-usage", usage),
+            Err(e) => {
+                error!("Failed to measure resource usage: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = result {
+            error!("Failed to emit resource usage: {:?}", e);
+        }
+    }
+}
+
+// Warms the index up when the settings say to, then tells the frontend that
+// searches are ready, as `search-ready`
+async fn warm_up_index(
+    app: tauri::AppHandle,
+    search_service: Arc<SearchService>,
+    ready: Arc<OnceLock<SearchReady>>,
+    enabled: bool,
+) {
+    let skipped = SearchReady {
+        warmed: false,
+        segments: 0,
+        terms: 0,
+        elapsed_ms: 0,
+    };
+    let report = if enabled {
+        match search_service.blocking(|service| service.warm_up()).await {
+            Ok(report) => {
+                info!("Warmed up {} index segments in {} ms", report.segments, report.elapsed_ms);
+                report
+            }
+            Err(e) => {
+                error!("Failed to warm up the index: {}", e);
+                skipped
+            }
+        }
+    } else {
+        skipped
+    };
+    let _ = ready.set(report.clone());
+    if let Err(e) = app.emit("search-ready", report) {
+        error!("Failed to emit search-ready: {:?}", e);
+    }
+}
+
+// What RPC clients, REST scripts and the app have done, newest first
+#[tauri::command]
+async fn get_audit_log(state: State<'_, AppState>, params: AuditQuery) -> Result<AuditLogResponse, McpError> {
+    state.audit
+        .query(&params)
+        .map(|entries| AuditLogResponse { entries })
+        .map_err(|e| McpError::internal(format!("Failed to read audit log: {}", e)))
+}
+
+#[tauri::command]
+async fn get_log_level(state: State<'_, AppState>) -> Result<LogLevel, McpError> {
+    Ok(state.logging.level())
+}
+
+// Lasts until the app quits, for chasing down a problem without a restart.
+// The level in the log settings is the one the app starts with.
+#[tauri::command]
+async fn set_log_level(state: State<'_, AppState>, params: LogLevel) -> Result<LogLevel, McpError> {
+    debug!("Command: set_log_level called with {:?}", params);
+    let audit = state.audit.begin("app", "app", "set_log_level", &params);
+    audit.record(state.logging.set_level(&params.level))
+}
+
+// Recent logs, redacted settings and index stats in one file to attach to a
+// bug report
+#[tauri::command]
+async fn export_diagnostics(state: State<'_, AppState>) -> Result<DiagnosticsBundle, McpError> {
+    debug!("Command: export_diagnostics called");
+    let settings = state.settings.get();
+    let bundle = diagnostics::export_diagnostics(state.rpc_server.state(), &settings, &state.logging).await?;
+    info!("Wrote diagnostics to {:?}", bundle.path);
+    Ok(bundle)
+}
+
+#[tauri::command]
+async fn get_log_settings(state: State<'_, AppState>) -> Result<LogSettings, McpError> {
+    Ok(state.settings.get().log)
+}
+
+// The level and rotation limits apply at once, and the level again at
+// startup
+#[tauri::command]
+async fn update_log_settings(
+    state: State<'_, AppState>,
+    settings: LogSettings
+) -> Result<LogSettings, McpError> {
+    debug!("Command: update_log_settings called with {:?}", settings);
+    let audit = state.audit.begin("app", "app", "update_log_settings", &settings);
+    if let Err(e) = validation::validate_log_settings(&settings) {
+        return audit.record(Err(e));
+    }
+    let saved = state.settings
+        .update(|current| current.log = settings)
+        .map(|saved| saved.log)
+        .map_err(|e| McpError::internal(format!("Failed to save settings: {}", e)));
+    let applied = saved.and_then(|saved| {
+        state.logging.set_rotation(&saved);
+        state.logging.set_level(&saved.level)?;
+        Ok(saved)
+    });
+    audit.record(applied)
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .setup(move |app| {
+            // Everything after this logs through tracing, to stderr and a
+            // file in the app's log dir
+            let logging = Arc::new(Logging::init(&app.path().app_log_dir()?)?);
+            logging::capture_panics();
+            info!("Logging to {:?} at level {}", logging.path(), logging.level().level);
+
+            if cfg!(debug_assertions) {
+                app.handle().plugin(
+                  tauri_plugin_log::Builder::default()
+                    .level(log::LevelFilter::Info)
+                    .build(),
+                )?;
+            }
+            
+            // Setup event listeners
+            let window = app.get_webview_window("main").unwrap();
+            window.listen("frontend-event", |event| {
+                debug!("Got event from frontend: {:?}", event.payload());
+            });
+
+            // Initialize app state; settings live in the app config dir, the
+            // index in the app data dir
+            let app_state = AppState::new(app.path().app_config_dir()?, app.path().app_data_dir()?, logging)?;
+
+            // Serve the JSON-RPC / MCP endpoint alongside the desktop UI. A
+            // failed bind (e.g. port in use) shows up in get_server_status.
+            tauri::async_runtime::spawn(rpc::forward_index_events(app_state.rpc_server.state().clone()));
+            tauri::async_runtime::spawn(rpc::expire_idle_sessions(app_state.rpc_server.state().clone()));
+            tauri::async_runtime::spawn(forward_index_events(
+                app.handle().clone(),
+                app_state.search_service.subscribe(),
+            ));
+            tauri::async_runtime::spawn(forward_client_events(
+                app.handle().clone(),
+                app_state.rpc_server.state().subscribe_clients(),
+            ));
+            tauri::async_runtime::spawn(forward_crawl_events(
+                app.handle().clone(),
+                app_state.rpc_server.state().crawls.subscribe(),
+            ));
+            tauri::async_runtime::spawn(forward_job_events(app.handle().clone(), app_state.jobs.subscribe()));
+            tauri::async_runtime::spawn(forward_feed_events(
+                app.handle().clone(),
+                app_state.rpc_server.state().feeds.subscribe(),
+            ));
+            tauri::async_runtime::spawn(app_state.rpc_server.state().schedules.clone().run());
+            tauri::async_runtime::spawn(app_state.rpc_server.state().feeds.clone().run());
+            tauri::async_runtime::spawn(forward_stale_reports(
+                app.handle().clone(),
+                app_state.rpc_server.state().staleness.subscribe(),
+            ));
+            tauri::async_runtime::spawn(app_state.rpc_server.state().staleness.clone().run());
+            tauri::async_runtime::spawn(forward_release_events(
+                app.handle().clone(),
+                app_state.rpc_server.state().releases.subscribe(),
+            ));
+            tauri::async_runtime::spawn(app_state.rpc_server.state().releases.clone().run());
+            tauri::async_runtime::spawn(app_state.rpc_server.state().embeddings.clone().run());
+            tauri::async_runtime::spawn(app_state.rpc_server.state().add_queue.clone().run());
+            tauri::async_runtime::spawn(report_resource_usage(
+                app.handle().clone(),
+                app_state.rpc_server.state().clone(),
+            ));
+            tauri::async_runtime::spawn(warm_up_index(
+                app.handle().clone(),
+                app_state.search_service.clone(),
+                app_state.search_ready.clone(),
+                app_state.settings.get().index.warm_up,
+            ));
+            let rpc_server = app_state.rpc_server.clone();
+            let server_settings = app_state.settings.get().server;
+            tauri::async_runtime::spawn(async move {
+                let _ = rpc_server.start(&server_settings).await;
+            });
+
+            app.manage(app_state); // Share state with commands
             
             Ok(())
         })
-        .manage(app_state) // Share state with commands
         .plugin(tauri_plugin_http::init())
         .invoke_handler(tauri::generate_handler![
             greet, 
             ping, 
             add_document, 
+            add_documents,
+            delete_document,
+            open_document,
             search_documents,
-            emit_event_example,
-            run_background_task
+            list_facets,
+            find_duplicates,
+            diff_versions,
+            get_context,
+            ask_docs,
+            summarize_document,
+            search_versions,
+            semantic_search,
+            purge_stale,
+            set_version_alias,
+            remove_version_alias,
+            list_version_aliases,
+            archive_versions,
+            restore_versions,
+            list_archived_versions,
+            set_workspace,
+            remove_workspace,
+            list_workspaces,
+            get_workspace_pins,
+            get_stale_report,
+            fetch_and_index,
+            add_document_from_file,
+            index_path,
+            index_rustdoc,
+            index_mdbook,
+            index_docs_site,
+            list_jobs,
+            get_job,
+            get_latest_version,
+            check_releases,
+            list_release_checks,
+            index_crate,
+            index_npm_package,
+            index_pypi_package,
+            index_github_repo,
+            index_git_repo,
+            index_release_notes,
+            index_mdn,
+            index_docset,
+            index_devdocs,
+            index_man_pages,
+            start_crawl,
+            crawl_sitemap,
+            crawl_inventory,
+            refresh_source,
+            cancel_crawl,
+            list_crawls,
+            get_source_failures,
+            schedule_source,
+            unschedule_source,
+            list_schedules,
+            watch_feed,
+            unwatch_feed,
+            list_feeds,
+            get_server_status,
+            get_search_ready,
+            explain_query,
+            get_health,
+            get_resource_usage,
+            run_benchmark,
+            restart_server,
+            get_server_settings,
+            update_server_settings,
+            get_index_settings,
+            update_index_settings,
+            get_batch_settings,
+            update_batch_settings,
+            get_writer_settings,
+            update_writer_settings,
+            get_slow_query_settings,
+            update_slow_query_settings,
+            get_slow_queries,
+            get_extraction_settings,
+            update_extraction_settings,
+            get_chunking_settings,
+            update_chunking_settings,
+            get_fetch_settings,
+            update_fetch_settings,
+            get_staleness_settings,
+            update_staleness_settings,
+            get_release_watch_settings,
+            update_release_watch_settings,
+            get_embedding_settings,
+            update_embedding_settings,
+            get_rerank_settings,
+            update_rerank_settings,
+            get_assistant_settings,
+            update_assistant_settings,
+            get_api_key,
+            rotate_api_key,
+            list_api_tokens,
+            create_api_token,
+            revoke_api_token,
+            get_audit_log,
+            get_log_level,
+            set_log_level,
+            get_log_settings,
+            update_log_settings,
+            export_diagnostics,
+            list_clients,
+            disconnect_client,
+            emit_event_example
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,341 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::{DefaultFields, Writer};
+use tracing_subscriber::fmt::{FormatFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::settings::LogSettings;
+use crate::McpError;
+
+pub const LOG_FILE: &str = "latest-docs.log";
+
+// Until the settings are read, unless RUST_LOG says otherwise
+const DEFAULT_LEVEL: &str = "info";
+
+// getLogLevel takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct GetLogLevelParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct LogLevel {
+    /// A level such as "debug", or per-module directives such as
+    /// "info,app_lib::search=debug", as RUST_LOG takes them
+    pub level: String,
+}
+
+// The app's tracing subscriber: events go to stderr and to a log file in the
+// app's log directory, filtered by a level that can be changed while the app
+// runs
+pub struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    level: Mutex<String>,
+    // Whether RUST_LOG set the level, which then wins over the settings at
+    // startup
+    from_env: bool,
+    file: Option<Arc<RotatingFile>>,
+    path: PathBuf,
+}
+
+impl Logging {
+    // Installs the subscriber for the whole process, so only once
+    pub fn init(log_dir: &Path) -> Result<Self> {
+        let env_level = std::env::var("RUST_LOG")
+            .ok()
+            .filter(|level| EnvFilter::try_new(level).is_ok());
+        let from_env = env_level.is_some();
+        let level = env_level.unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+        let (filter, handle) = reload::Layer::new(EnvFilter::try_new(&level)?);
+        let path = log_dir.join(LOG_FILE);
+        let file = RotatingFile::open(path.clone(), Rotation::from(&LogSettings::default())).map(Arc::new);
+        let file_error = file.as_ref().err().map(|e| e.to_string());
+        let file = file.ok();
+        let subscriber = Registry::default()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer().with_writer(io::stderr))
+            .with(file.clone().map(|file| {
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .fmt_fields(FileFields::default())
+                    .with_writer(FileWriter(file))
+            }));
+        tracing::subscriber::set_global_default(subscriber)?;
+        // Logging to stderr alone beats not starting
+        if let Some(e) = file_error {
+            tracing::warn!("Not logging to {:?}: {}", path, e);
+        }
+        Ok(Self {
+            filter: handle,
+            level: Mutex::new(level),
+            from_env,
+            file,
+            path,
+        })
+    }
+
+    // The saved settings, once they're loaded. RUST_LOG still picks the
+    // level when it's set.
+    pub fn configure(&self, settings: &LogSettings) -> Result<(), McpError> {
+        self.set_rotation(settings);
+        if !self.from_env && *self.level.lock().unwrap() != settings.level {
+            self.set_level(&settings.level)?;
+        }
+        Ok(())
+    }
+
+    // Applies to the next line written, and drops rotated files the new
+    // limits no longer keep
+    pub fn set_rotation(&self, settings: &LogSettings) {
+        if let Some(file) = &self.file {
+            let rotation = Rotation::from(settings);
+            *file.rotation.write().unwrap() = rotation;
+            file.prune(&rotation);
+        }
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel {
+            level: self.level.lock().unwrap().clone(),
+        }
+    }
+
+    // Takes effect for the next event; spans already open keep the level
+    // they were created under
+    pub fn set_level(&self, level: &str) -> Result<LogLevel, McpError> {
+        let filter = parse_level(level)?;
+        self.filter
+            .reload(filter)
+            .map_err(|e| McpError::internal(format!("Failed to change the log level: {}", e)))?;
+        *self.level.lock().unwrap() = level.to_string();
+        tracing::info!("Log level set to {}", level);
+        Ok(self.level())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The log and the rotated ones still around, newest first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.clone()];
+        for n in 1.. {
+            let path = rotated_path(&self.path, n);
+            if !path.exists() {
+                break;
+            }
+            files.push(path);
+        }
+        files
+    }
+}
+
+// Panics go to the log with where they happened and a backtrace, whichever
+// thread they happen on: a command, an RPC handler, or a background crawl or
+// indexing task, whose panics would otherwise only reach stderr. They
+// unwind as before.
+pub fn capture_panics() {
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(no message)");
+        let location = info.location().map_or_else(|| "an unknown location".to_string(), |l| l.to_string());
+        let thread = std::thread::current();
+        tracing::error!(
+            "Panic in thread {} at {}: {}\n{}",
+            thread.name().unwrap_or("<unnamed>"),
+            location,
+            message,
+            std::backtrace::Backtrace::force_capture()
+        );
+    }));
+}
+
+pub fn parse_level(level: &str) -> Result<EnvFilter, McpError> {
+    EnvFilter::try_new(level)
+        .map_err(|e| McpError::validation(Some("level"), format!("Invalid log level {:?}: {}", level, e)))
+}
+
+// The same fields as on stderr, formatted apart: layers with the same field
+// formatter share span fields once formatted, colours and all
+#[derive(Default)]
+struct FileFields(DefaultFields);
+
+impl<'w> FormatFields<'w> for FileFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'w>, fields: R) -> std::fmt::Result {
+        self.0.format_fields(writer, fields)
+    }
+}
+
+// When the log moves aside, and how many of the old ones stay
+#[derive(Clone, Copy)]
+struct Rotation {
+    max_bytes: u64,
+    every: Option<Duration>,
+    keep: usize,
+    max_age: Option<Duration>,
+}
+
+impl From<&LogSettings> for Rotation {
+    fn from(settings: &LogSettings) -> Self {
+        let hours = |hours: u32| Duration::from_secs(u64::from(hours) * 60 * 60);
+        Self {
+            max_bytes: settings.max_file_bytes,
+            every: settings.rotate_every_hours.map(hours),
+            keep: settings.max_files,
+            max_age: settings.max_age_days.map(|days| hours(days * 24)),
+        }
+    }
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+    // When the file was started, for rotating by time
+    opened: SystemTime,
+}
+
+// A log file that rotates by size, like the audit log, or by age, keeping a
+// few old ones
+struct RotatingFile {
+    path: PathBuf,
+    file: Mutex<LogFile>,
+    rotation: RwLock<Rotation>,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, rotation: Rotation) -> Result<Self> {
+        let file = open_append(&path)?;
+        let metadata = file.metadata()?;
+        // An existing log was started when it was created, where the
+        // platform says
+        let opened = metadata.created().unwrap_or_else(|_| SystemTime::now());
+        Ok(Self {
+            path,
+            file: Mutex::new(LogFile {
+                file,
+                size: metadata.len(),
+                opened,
+            }),
+            rotation: RwLock::new(rotation),
+        })
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut log = self.file.lock().unwrap();
+        let rotation = *self.rotation.read().unwrap();
+        let too_big = log.size + buf.len() as u64 > rotation.max_bytes;
+        let too_old = rotation
+            .every
+            .is_some_and(|every| log.opened.elapsed().is_ok_and(|age| age >= every));
+        if log.size > 0 && (too_big || too_old) {
+            self.rotate(&rotation)?;
+            log.file = open_append(&self.path)?;
+            log.size = 0;
+            log.opened = SystemTime::now();
+        }
+        let written = log.file.write(buf)?;
+        log.size += written as u64;
+        Ok(written)
+    }
+
+    // latest-docs.log.4 becomes .5, the oldest falling off, down to
+    // latest-docs.log becoming .1
+    fn rotate(&self, rotation: &Rotation) -> io::Result<()> {
+        if rotation.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..rotation.keep).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.prune(rotation);
+        Ok(())
+    }
+
+    // Deletes rotated logs past the count or older than the age limit.
+    // Nothing can be logged from here, so a file that won't go is left for
+    // the next rotation.
+    fn prune(&self, rotation: &Rotation) {
+        let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name().and_then(|name| name.to_str())) else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let prefix = format!("{}.", name);
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(n) = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(&prefix))
+                .and_then(|n| n.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let expired = rotation.max_age.is_some_and(|max_age| {
+                let modified = entry.metadata().and_then(|metadata| metadata.modified());
+                modified.ok().and_then(|modified| modified.elapsed().ok()).is_some_and(|age| age > max_age)
+            });
+            if n > rotation.keep || expired {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        rotated_path(&self.path, n)
+    }
+}
+
+// The file layer's handle on the log, shared with Logging so the rotation
+// can change
+struct FileWriter(Arc<RotatingFile>);
+
+impl<'a> MakeWriter<'a> for FileWriter {
+    type Writer = RotatingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingWriter(&self.0)
+    }
+}
+
+struct RotatingWriter<'a>(&'a RotatingFile);
+
+impl Write for RotatingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.file.lock().unwrap().file.flush()
+    }
+}
+
+// latest-docs.log.<n>
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(format!(".{}", n));
+    path.into()
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
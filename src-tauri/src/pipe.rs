@@ -0,0 +1,60 @@
+use std::io;
+use std::time::Duration;
+
+use axum::serve::Listener;
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+use tracing::{error, warn};
+
+// Serves connections on a Windows named pipe (e.g. `\\.\pipe\latest-docs-mcp`).
+// A pipe instance carries a single client, so a fresh instance is created
+// before each accepted one is handed to the server; that way the pipe name
+// never disappears while a client is connecting.
+pub struct NamedPipeListener {
+    name: String,
+    next: NamedPipeServer,
+}
+
+impl NamedPipeListener {
+    pub fn bind(name: &str) -> io::Result<Self> {
+        // Fails if another process already owns the pipe name
+        let next = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            next,
+        })
+    }
+}
+
+impl Listener for NamedPipeListener {
+    type Io = NamedPipeServer;
+    type Addr = String;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            if let Err(e) = self.next.connect().await {
+                warn!("Named pipe accept failed: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+
+            match ServerOptions::new().create(&self.name) {
+                Ok(next) => {
+                    let connected = std::mem::replace(&mut self.next, next);
+                    return (connected, self.name.clone());
+                }
+                Err(e) => {
+                    // Drop the client rather than lose the pipe name
+                    error!("Failed to create named pipe instance {}: {}", self.name, e);
+                    let _ = self.next.disconnect();
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.name.clone())
+    }
+}
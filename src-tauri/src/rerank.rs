@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{anyhow, bail, Result};
+use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::ingest::Fetcher;
+use crate::search::SearchableDocument;
+use crate::settings::{RemoteRerankSettings, RerankSettings};
+use crate::McpError;
+
+// Jina's turbo reranker: about 150 MB, downloaded on first use, and quick
+// enough to read a page of hits on a CPU
+const MODEL: RerankerModel = RerankerModel::JINARerankerV1TurboEn;
+
+// The query and a hit share the model's 512 tokens
+const MAX_TEXT_CHARS: usize = 1500;
+
+// Hits scored per call to the local model
+const BATCH_SIZE: usize = 16;
+
+// Scores for the most candidates allowed, with room to spare
+const MAX_ANSWER_BYTES: usize = 1024 * 1024;
+
+// What a Cohere- or Jina-compatible /rerank endpoint answers
+#[derive(Deserialize)]
+struct RerankAnswer {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+// A second stage for searches that ask for it: the first stage's best hits
+// are scored again by a cross-encoder, which reads the query and each hit
+// together. That's slower than ranking on terms or vectors, but puts the
+// hits that answer the query on top far more often.
+pub struct Reranker {
+    fetcher: Arc<Fetcher>,
+    settings: RwLock<RerankSettings>,
+    model_dir: PathBuf,
+    // Loaded when first needed, since that may mean downloading it
+    model: Mutex<Option<TextRerank>>,
+}
+
+impl Reranker {
+    pub fn new(model_dir: PathBuf, fetcher: Arc<Fetcher>, settings: RerankSettings) -> Self {
+        Self {
+            fetcher,
+            settings: RwLock::new(settings),
+            model_dir,
+            model: Mutex::new(None),
+        }
+    }
+
+    pub fn replace(&self, settings: RerankSettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    // How many hits the first stage should find for `limit` to be reranked
+    pub fn candidates(&self, limit: usize) -> usize {
+        self.settings.read().unwrap().candidates.max(limit)
+    }
+
+    // The `limit` hits that best answer the query, best first
+    pub async fn rerank(
+        self: &Arc<Self>,
+        query: &str,
+        mut documents: Vec<SearchableDocument>,
+        limit: usize,
+    ) -> Result<Vec<SearchableDocument>, McpError> {
+        if documents.len() > 1 {
+            let texts = documents.iter().map(rerank_text).collect();
+            let scores = self
+                .score(query, texts)
+                .await
+                .map_err(|e| McpError::internal(format!("Failed to rerank the hits: {}", e)))?;
+            let mut ranked: Vec<(f32, SearchableDocument)> = scores.into_iter().zip(documents).collect();
+            ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+            documents = ranked.into_iter().map(|(_, document)| document).collect();
+        }
+        documents.truncate(limit);
+        Ok(documents)
+    }
+
+    // The texts' scores, in their order; higher is more relevant
+    async fn score(self: &Arc<Self>, query: &str, texts: Vec<String>) -> Result<Vec<f32>> {
+        let remote = self.settings.read().unwrap().remote.clone();
+        match remote {
+            Some(remote) => self.score_remotely(&remote, query, texts).await,
+            None => {
+                let (this, query) = (self.clone(), query.to_string());
+                tokio::task::spawn_blocking(move || this.score_locally(&query, texts))
+                    .await
+                    .map_err(|e| anyhow!("Reranking task failed: {}", e))?
+            }
+        }
+    }
+
+    fn score_locally(&self, query: &str, texts: Vec<String>) -> Result<Vec<f32>> {
+        let mut model = self.model.lock().unwrap();
+        if model.is_none() {
+            info!("Loading reranking model {:?} into {:?}", MODEL, self.model_dir);
+            let options = RerankInitOptions::new(MODEL)
+                .with_cache_dir(self.model_dir.clone())
+                .with_show_download_progress(false);
+            *model = Some(TextRerank::try_new(options)?);
+        }
+        let model = model.as_mut().ok_or_else(|| anyhow!("Reranking model not loaded"))?;
+        let mut scores = vec![f32::MIN; texts.len()];
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let results = model.rerank(query, texts, false, Some(BATCH_SIZE))?;
+        for result in results {
+            if let Some(score) = scores.get_mut(result.index) {
+                *score = result.score;
+            }
+        }
+        Ok(scores)
+    }
+
+    // One request for every candidate, retried by the fetcher while it fails
+    // transiently
+    async fn score_remotely(&self, remote: &RemoteRerankSettings, query: &str, texts: Vec<String>) -> Result<Vec<f32>> {
+        let url = Fetcher::parse_url(&format!("{}/rerank", remote.url.trim_end_matches('/')))?;
+        let count = texts.len();
+        let body = json!({ "model": remote.model, "query": query, "documents": texts, "top_n": count });
+        let answer = self
+            .fetcher
+            .post_json(&url, remote.api_key.as_deref(), &body, MAX_ANSWER_BYTES)
+            .await?;
+        let answer: RerankAnswer = serde_json::from_value(answer)?;
+        let mut scores = vec![f32::MIN; count];
+        for result in answer.results {
+            match scores.get_mut(result.index) {
+                Some(score) => *score = result.relevance_score,
+                None => bail!("{} answered with a score for hit {} of {}", url, result.index, count),
+            }
+        }
+        Ok(scores)
+    }
+}
+
+// A section's title already leads with its page's headings
+fn rerank_text(document: &SearchableDocument) -> String {
+    let text = format!("{}\n{}", document.title, document.body);
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
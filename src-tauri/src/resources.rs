@@ -0,0 +1,64 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ingest::CrawlState;
+use crate::rpc::RpcState;
+use crate::search::IndexUsage;
+use crate::McpError;
+
+// How often `resource-usage` goes out to the UI
+pub const REPORT_INTERVAL_SECS: u64 = 30;
+
+// getResourceUsage takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ResourceUsageParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ResourceUsage {
+    /// Resident memory of the whole app, where the OS reports it (Linux)
+    pub resident_bytes: Option<u64>,
+    #[serde(flatten)]
+    pub index: IndexUsage,
+    /// Documents added with addDocument and not committed yet
+    pub queued_documents: usize,
+    pub running_crawls: usize,
+    /// Open MCP sessions
+    pub sessions: usize,
+}
+
+// A snapshot of what the app is holding on to, so the UI can warn before it
+// grows out of hand
+pub async fn resource_usage(state: &RpcState) -> Result<ResourceUsage, McpError> {
+    let index = state
+        .search_service
+        .blocking(|service| service.usage())
+        .await
+        .map_err(|e| McpError::index("Failed to measure the index", e))?;
+    let running_crawls = state
+        .crawls
+        .list()
+        .iter()
+        .filter(|crawl| crawl.state == CrawlState::Running)
+        .count();
+    Ok(ResourceUsage {
+        resident_bytes: resident_bytes(),
+        index,
+        queued_documents: state.add_queue.queued(),
+        running_crawls,
+        sessions: state.sessions.all().len(),
+    })
+}
+
+// VmRSS from /proc, which other platforms don't have
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::ingest::normalize_pypi_name;
+use crate::search::PreferredVersion;
+use crate::versions::version_key;
+
+// A project directory declared by an MCP client via `roots/list`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Root {
+    pub uri: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+// Reads the dependency manifests found in each root and turns the declared
+// versions into ranking preferences. Only `file://` roots are looked at.
+pub fn preferred_versions(roots: &[Root]) -> Vec<PreferredVersion> {
+    let mut preferences = Vec::new();
+    for root in roots {
+        let Some(dir) = root_path(&root.uri) else {
+            continue;
+        };
+
+        for (name, version) in cargo_dependencies(&dir.join("Cargo.toml")) {
+            push_preference(&mut preferences, "crate", &name, version);
+        }
+        for (name, version) in npm_dependencies(&dir.join("package.json")) {
+            push_preference(&mut preferences, "npm", &name, version);
+        }
+    }
+    preferences
+}
+
+// The exact versions a project's lockfiles resolve its declared
+// dependencies to: Cargo.lock and package-lock.json, and the `==` pins of
+// requirements.txt. Transitive dependencies are left out.
+pub fn locked_versions(dir: &Path) -> Vec<PreferredVersion> {
+    let mut pins = Vec::new();
+    for (name, version) in cargo_locked(dir) {
+        push_preference(&mut pins, "crate", &name, Some(version));
+    }
+    for (name, version) in npm_locked(dir) {
+        push_preference(&mut pins, "npm", &name, Some(version));
+    }
+    for (name, version) in python_pinned(&dir.join("requirements.txt")) {
+        push_preference(&mut pins, "pypi", &name, Some(version));
+    }
+    pins
+}
+
+fn root_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+// Docs for a package may have been indexed under the ecosystem-qualified
+// source (`crate:tokio`) or just the package name, so prefer both.
+fn push_preference(preferences: &mut Vec<PreferredVersion>, ecosystem: &str, name: &str, version: Option<String>) {
+    for source in [format!("{}:{}", ecosystem, name), name.to_string()] {
+        preferences.push(PreferredVersion {
+            source,
+            version: version.clone(),
+        });
+    }
+}
+
+// Strips requirement operators so `^1.38`, `~1.38.2` and `=1.38` all become
+// a plain version prefix. Ranges and wildcards give no usable version.
+fn normalize_requirement(requirement: &str) -> Option<String> {
+    let version = requirement
+        .trim()
+        .trim_start_matches(['^', '~', '=', 'v', ' '])
+        .split([',', ' '])
+        .next()?
+        .trim_end_matches(".*");
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    Some(version.to_string())
+}
+
+fn cargo_dependencies(manifest_path: &Path) -> Vec<(String, Option<String>)> {
+    let Ok(contents) = std::fs::read_to_string(manifest_path) else {
+        return Vec::new();
+    };
+    let manifest: toml::Table = match contents.parse() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("Failed to parse {:?}: {}", manifest_path, e);
+            return Vec::new();
+        }
+    };
+
+    let mut tables = Vec::new();
+    for key in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        tables.extend(manifest.get(key).and_then(|v| v.as_table()));
+    }
+    if let Some(workspace_deps) = manifest
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table())
+    {
+        tables.push(workspace_deps);
+    }
+
+    let mut deps = BTreeMap::new();
+    for table in tables {
+        for (name, spec) in table {
+            // `tokio = "1.38"` or `tokio = { version = "1.38", ... }`
+            let requirement = match spec {
+                toml::Value::String(version) => Some(version.as_str()),
+                toml::Value::Table(spec) => spec.get("version").and_then(|v| v.as_str()),
+                _ => None,
+            };
+            // Renamed dependencies are indexed under their real crate name
+            let crate_name = spec
+                .get("package")
+                .and_then(|p| p.as_str())
+                .unwrap_or(name);
+            deps.insert(crate_name.to_string(), requirement.and_then(normalize_requirement));
+        }
+    }
+    deps.into_iter().collect()
+}
+
+fn npm_dependencies(manifest_path: &Path) -> Vec<(String, Option<String>)> {
+    let Ok(contents) = std::fs::read_to_string(manifest_path) else {
+        return Vec::new();
+    };
+    let manifest: serde_json::Value = match serde_json::from_str(&contents) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("Failed to parse {:?}: {}", manifest_path, e);
+            return Vec::new();
+        }
+    };
+
+    let mut deps = BTreeMap::new();
+    for key in ["dependencies", "devDependencies"] {
+        if let Some(table) = manifest.get(key).and_then(|v| v.as_object()) {
+            for (name, requirement) in table {
+                let version = requirement.as_str().and_then(normalize_requirement);
+                deps.insert(name.clone(), version);
+            }
+        }
+    }
+    deps.into_iter().collect()
+}
+
+fn cargo_locked(dir: &Path) -> Vec<(String, String)> {
+    let declared = cargo_dependencies(&dir.join("Cargo.toml"));
+    let lockfile_path = dir.join("Cargo.lock");
+    let Ok(contents) = std::fs::read_to_string(&lockfile_path) else {
+        return Vec::new();
+    };
+    let lockfile: toml::Table = match contents.parse() {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            error!("Failed to parse {:?}: {}", lockfile_path, e);
+            return Vec::new();
+        }
+    };
+
+    // A crate locked at several versions counts at its highest
+    let mut locked: BTreeMap<String, String> = BTreeMap::new();
+    for package in lockfile.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+        let name = package.get("name").and_then(|n| n.as_str());
+        let version = package.get("version").and_then(|v| v.as_str());
+        let (Some(name), Some(version)) = (name, version) else {
+            continue;
+        };
+        let highest = locked.entry(name.to_string()).or_insert_with(|| version.to_string());
+        if version_key(version) > version_key(highest) {
+            *highest = version.to_string();
+        }
+    }
+    declared
+        .into_iter()
+        .filter_map(|(name, _)| {
+            let version = locked.remove(&name)?;
+            Some((name, version))
+        })
+        .collect()
+}
+
+fn npm_locked(dir: &Path) -> Vec<(String, String)> {
+    let declared = npm_dependencies(&dir.join("package.json"));
+    let lockfile_path = dir.join("package-lock.json");
+    let Ok(contents) = std::fs::read_to_string(&lockfile_path) else {
+        return Vec::new();
+    };
+    let lockfile: serde_json::Value = match serde_json::from_str(&contents) {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            error!("Failed to parse {:?}: {}", lockfile_path, e);
+            return Vec::new();
+        }
+    };
+
+    declared
+        .into_iter()
+        .filter_map(|(name, _)| {
+            // Lockfile v2 and v3 list packages by path, v1 by name
+            let package = lockfile
+                .get("packages")
+                .and_then(|packages| packages.get(format!("node_modules/{}", name).as_str()))
+                .or_else(|| lockfile.get("dependencies").and_then(|deps| deps.get(name.as_str())))?;
+            let version = package.get("version")?.as_str()?.to_string();
+            Some((name, version))
+        })
+        .collect()
+}
+
+// `requests==2.32.3`, with extras, markers and comments ignored. Ranges pin
+// nothing.
+fn python_pinned(requirements_path: &Path) -> Vec<(String, String)> {
+    let Ok(contents) = std::fs::read_to_string(requirements_path) else {
+        return Vec::new();
+    };
+
+    let mut deps = BTreeMap::new();
+    for line in contents.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default().trim();
+        let Some((name, version)) = line.split_once("==") else {
+            continue;
+        };
+        let name = name.split('[').next().unwrap_or_default().trim();
+        let version = version.trim_start_matches('=').trim();
+        if name.is_empty() || version.is_empty() || version.contains(['*', ',', ' ']) {
+            continue;
+        }
+        deps.insert(normalize_pypi_name(name), version.to_string());
+    }
+    deps.into_iter().collect()
+}
@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{error_response, header_str, RpcError, RpcState};
+use crate::util;
+use crate::McpError;
+
+pub(super) const API_KEY_HEADER: &str = "x-api-key";
+
+// Browsers can't set headers on a WebSocket handshake, so `/ws` also takes
+// the key as `?api_key=...`
+const API_KEY_QUERY_PARAM: &str = "api_key";
+
+// What a key may do. `Read` covers search and reading documents, `Write`
+// additionally adds and deletes them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+impl Scope {
+    pub fn allows(self, required: Scope) -> bool {
+        self == Scope::Write || required == Scope::Read
+    }
+}
+
+// Who made a request, attached to it once the key checks out. `key_id` is a
+// fingerprint of the key, safe to log and to key limits on; `name` is
+// "default" for the main key or the name a token was issued under.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub key_id: String,
+    pub name: String,
+    pub scope: Scope,
+}
+
+impl Caller {
+    pub fn require(&self, scope: Scope) -> Result<(), McpError> {
+        if self.scope.allows(scope) {
+            Ok(())
+        } else {
+            Err(McpError::forbidden(format!(
+                "API key \"{}\" has {:?} scope; this needs {:?}",
+                self.name, self.scope, scope
+            )))
+        }
+    }
+}
+
+// An issued token as listed in the UI. The token itself is only shown once,
+// when it is created; only its hash is stored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scope: Scope,
+    // Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub key: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    // SHA-256 of the token, in hex
+    hash: String,
+}
+
+// The key every HTTP and WebSocket request has to present. The main key is
+// generated on first run and kept in its own file (readable only by the
+// user) rather than in settings.json, so it never ends up in a settings
+// export; it always has `Write` scope. Further tokens, e.g. a search-only one
+// for an agent, are kept hashed in `api_tokens.json` next to it.
+pub struct ApiKeyStore {
+    path: PathBuf,
+    key: RwLock<String>,
+    tokens_path: PathBuf,
+    tokens: RwLock<Vec<StoredToken>>,
+}
+
+impl ApiKeyStore {
+    pub fn load_or_create(config_dir: &Path) -> Result<Self> {
+        let path = config_dir.join("api_key");
+        let key = match std::fs::read_to_string(&path) {
+            Ok(contents) if !contents.trim().is_empty() => contents.trim().to_string(),
+            _ => {
+                let key = generate_key();
+                util::write_private(&path, key.as_bytes())?;
+                info!("Generated a new API key in {:?}", path);
+                key
+            }
+        };
+
+        let tokens_path = config_dir.join("api_tokens.json");
+        let tokens = match std::fs::read_to_string(&tokens_path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse {:?}", tokens_path))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            path,
+            key: RwLock::new(key),
+            tokens_path,
+            tokens: RwLock::new(tokens),
+        })
+    }
+
+    pub fn get(&self) -> String {
+        self.key.read().unwrap().clone()
+    }
+
+    // Replaces the key; requests with the old one are rejected from now on
+    pub fn rotate(&self) -> Result<String> {
+        let key = generate_key();
+        util::write_private(&self.path, key.as_bytes())?;
+        *self.key.write().unwrap() = key.clone();
+        Ok(key)
+    }
+
+    pub fn list_tokens(&self) -> Vec<ApiToken> {
+        self.tokens.read().unwrap().iter().map(|stored| stored.token.clone()).collect()
+    }
+
+    pub fn create_token(&self, name: &str, scope: Scope) -> Result<IssuedToken> {
+        let key = generate_key();
+        let token = ApiToken {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            name: name.to_string(),
+            scope,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+
+        let mut tokens = self.tokens.write().unwrap();
+        tokens.push(StoredToken {
+            token: token.clone(),
+            hash: key_hash(&key),
+        });
+        if let Err(e) = util::write_private(&self.tokens_path, &serde_json::to_vec_pretty(&*tokens)?) {
+            tokens.pop();
+            return Err(e);
+        }
+        Ok(IssuedToken { token, key })
+    }
+
+    // Returns false when no token has this id
+    pub fn revoke_token(&self, id: &str) -> Result<bool> {
+        let mut tokens = self.tokens.write().unwrap();
+        let Some(index) = tokens.iter().position(|stored| stored.token.id == id) else {
+            return Ok(false);
+        };
+        let removed = tokens.remove(index);
+        if let Err(e) = util::write_private(&self.tokens_path, &serde_json::to_vec_pretty(&*tokens)?) {
+            tokens.insert(index, removed);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    // The caller a presented key belongs to, if it is the main key or an
+    // issued token
+    pub fn authenticate(&self, candidate: &str) -> Option<Caller> {
+        if constant_time_eq(self.key.read().unwrap().as_bytes(), candidate.as_bytes()) {
+            return Some(Caller {
+                key_id: key_fingerprint(candidate),
+                name: "default".to_string(),
+                scope: Scope::Write,
+            });
+        }
+
+        let hash = key_hash(candidate);
+        let tokens = self.tokens.read().unwrap();
+        tokens
+            .iter()
+            .find(|stored| constant_time_eq(stored.hash.as_bytes(), hash.as_bytes()))
+            .map(|stored| Caller {
+                key_id: key_fingerprint(candidate),
+                name: stored.token.name.clone(),
+                scope: stored.token.scope,
+            })
+    }
+}
+
+fn key_hash(key: &str) -> String {
+    util::sha256_hex(key.as_bytes())
+}
+
+// First 8 bytes of the key's SHA-256, in hex
+fn key_fingerprint(key: &str) -> String {
+    key_hash(key)[..16].to_string()
+}
+
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    util::hex(&bytes)
+}
+
+// Doesn't bail out on the first differing byte, so response timing says
+// nothing about how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// `Authorization: Bearer <key>` or `X-API-Key: <key>`, plus the query
+// parameter on WebSocket upgrades
+fn presented_key<'a>(headers: &'a HeaderMap, query: Option<&'a str>) -> Option<&'a str> {
+    if let Some(token) = header_str(headers, header::AUTHORIZATION.as_str()).and_then(|v| v.strip_prefix("Bearer ")) {
+        return Some(token.trim());
+    }
+    if let Some(key) = header_str(headers, API_KEY_HEADER) {
+        return Some(key.trim());
+    }
+
+    let is_websocket = header_str(headers, header::UPGRADE.as_str()).is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if !is_websocket {
+        return None;
+    }
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == API_KEY_QUERY_PARAM)
+        .map(|(_, value)| value)
+}
+
+pub(super) async fn require_api_key(State(state): State<RpcState>, mut request: Request, next: Next) -> Response {
+    let caller = presented_key(request.headers(), request.uri().query()).and_then(|key| state.api_keys.authenticate(key));
+    if let Some(caller) = caller {
+        state.callers.seen(&caller);
+        request.extensions_mut().insert(caller);
+        return next.run(request).await;
+    }
+
+    let error = McpError::unauthorized("Missing or invalid API key");
+    let mut response = error_response(StatusCode::UNAUTHORIZED, RpcError::from(error));
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::info;
+
+use super::auth::Caller;
+use super::RpcState;
+use crate::util::now_millis;
+use crate::McpError;
+
+// Plain HTTP callers drop off the client list after this long without a request
+const RECENT_CALLER_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+// Expired callers are only swept once there are this many
+const CALLER_SWEEP_THRESHOLD: usize = 256;
+
+// Client ids of plain HTTP callers are this prefix plus the key fingerprint
+const HTTP_CLIENT_PREFIX: &str = "http:";
+
+// One entry in the client list: an MCP session (Streamable HTTP or
+// WebSocket), or an API key that made plain requests recently.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientInfo {
+    pub id: String,
+    // "streamableHttp", "websocket" or "http"
+    pub transport: String,
+    // Name of the API key in use
+    pub caller: String,
+    // From the client's `initialize`, when it sent one
+    pub client_name: Option<String>,
+    pub client_version: Option<String>,
+    // Milliseconds since the Unix epoch
+    pub connected_at: u64,
+    pub last_seen: u64,
+    pub requests: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ClientEvent {
+    Connected { client: ClientInfo },
+    Disconnected { id: String },
+}
+
+struct RecentCaller {
+    name: String,
+    first_seen: u64,
+    last_seen: Instant,
+    last_seen_ms: u64,
+    requests: u64,
+}
+
+// API keys seen on any authenticated request, by fingerprint. A key counts
+// as connected again once it has been quiet for `RECENT_CALLER_WINDOW`.
+pub struct RecentCallers {
+    callers: Mutex<HashMap<String, RecentCaller>>,
+    events: broadcast::Sender<ClientEvent>,
+}
+
+impl RecentCallers {
+    pub fn new(events: broadcast::Sender<ClientEvent>) -> Self {
+        Self {
+            callers: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    pub fn seen(&self, caller: &Caller) {
+        let now = Instant::now();
+        let now_ms = now_millis();
+        let mut callers = self.callers.lock().unwrap();
+        if callers.len() >= CALLER_SWEEP_THRESHOLD {
+            callers.retain(|_, recent| now.duration_since(recent.last_seen) < RECENT_CALLER_WINDOW);
+        }
+
+        let recent = callers.entry(caller.key_id.clone()).or_insert_with(|| RecentCaller {
+            name: caller.name.clone(),
+            first_seen: now_ms,
+            last_seen: now,
+            last_seen_ms: now_ms,
+            requests: 0,
+        });
+        if now.duration_since(recent.last_seen) >= RECENT_CALLER_WINDOW {
+            // Back after a quiet spell, so a new connection
+            recent.first_seen = now_ms;
+            recent.requests = 0;
+        }
+        recent.last_seen = now;
+        recent.last_seen_ms = now_ms;
+        recent.requests += 1;
+        if recent.requests == 1 {
+            let client = http_client_info(&caller.key_id, recent);
+            let _ = self.events.send(ClientEvent::Connected { client });
+        }
+    }
+
+    fn list(&self) -> Vec<ClientInfo> {
+        let now = Instant::now();
+        self.callers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, recent)| now.duration_since(recent.last_seen) < RECENT_CALLER_WINDOW)
+            .map(|(key_id, recent)| http_client_info(key_id, recent))
+            .collect()
+    }
+}
+
+fn http_client_info(key_id: &str, recent: &RecentCaller) -> ClientInfo {
+    ClientInfo {
+        id: format!("{}{}", HTTP_CLIENT_PREFIX, key_id),
+        transport: "http".to_string(),
+        caller: recent.name.clone(),
+        client_name: None,
+        client_version: None,
+        connected_at: recent.first_seen,
+        last_seen: recent.last_seen_ms,
+        requests: recent.requests,
+    }
+}
+
+// Open sessions first, then recent HTTP callers, each most recently active first
+pub fn list_clients(state: &RpcState) -> Vec<ClientInfo> {
+    let mut sessions: Vec<ClientInfo> = state.sessions.all().iter().map(|session| session.info()).collect();
+    sessions.sort_by_key(|client| std::cmp::Reverse(client.last_seen));
+    let mut callers = state.callers.list();
+    callers.sort_by_key(|client| std::cmp::Reverse(client.last_seen));
+    sessions.extend(callers);
+    sessions
+}
+
+// Ends a session and its open stream or socket. The client may reconnect
+// unless its token is revoked as well.
+pub fn disconnect_client(state: &RpcState, id: &str) -> Result<(), McpError> {
+    if id.starts_with(HTTP_CLIENT_PREFIX) {
+        return Err(McpError::unsupported(
+            "Plain HTTP callers hold no connection; revoke their API token instead",
+        ));
+    }
+    match state.sessions.remove(id) {
+        Some(_) => {
+            info!("Client disconnected by the user: {}", id);
+            Ok(())
+        }
+        None => Err(McpError::not_found(format!("Client {}", id))),
+    }
+}
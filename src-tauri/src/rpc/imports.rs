@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::McpError;
+
+// The folders RPC and REST callers may import local files from. The app's
+// own imports aren't limited; it runs as the user anyway. Reconfigured
+// whenever the server (re)starts.
+#[derive(Default)]
+pub struct ImportRoots {
+    roots: RwLock<Vec<PathBuf>>,
+}
+
+impl ImportRoots {
+    pub fn configure(&self, roots: &[PathBuf]) {
+        *self.roots.write().unwrap() = roots.iter().filter_map(|root| std::fs::canonicalize(root).ok()).collect();
+    }
+
+    // The path, resolved, when it is inside one of the roots. A path that
+    // doesn't exist gets the same error as one outside them, so callers
+    // can't probe the disk with it.
+    pub fn resolve(&self, field: &str, path: &str) -> Result<String, McpError> {
+        let refused = || {
+            McpError::validation(
+                Some(field),
+                format!("Cannot import {}: it doesn't exist or isn't inside server.import_roots", path),
+            )
+        };
+        let resolved = std::fs::canonicalize(path.trim()).map_err(|_| refused())?;
+        if !self.roots.read().unwrap().iter().any(|root| resolved.starts_with(root)) {
+            return Err(refused());
+        }
+        resolved.to_str().map(str::to_string).ok_or_else(refused)
+    }
+}
+
+// Fetched over the network rather than read from disk: an http(s), ssh or
+// git URL, or scp-like `user@host:path`
+pub fn is_remote_repo(repo: &str) -> bool {
+    let repo = repo.trim();
+    if ["http://", "https://", "ssh://", "git://"].iter().any(|scheme| repo.starts_with(scheme)) {
+        return true;
+    }
+    repo.split_once(':').is_some_and(|(host, _)| host.contains('@') && !host.contains(['/', '\\']))
+}
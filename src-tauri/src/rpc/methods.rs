@@ -0,0 +1,1409 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use super::auth::{Caller, Scope};
+use super::imports::is_remote_repo;
+use super::registry::{parse_params, to_value, MethodRegistry, RpcContext};
+use super::schema::inline_schema;
+use super::session::{ProgressReporter, Session};
+use super::{RpcError, RpcState, INVALID_PARAMS, INVALID_REQUEST, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::aliases::{ListVersionAliasesParams, RemoveVersionAliasParams, SetVersionAliasParams, VersionAlias};
+use crate::archive::{self, ArchiveVersionsParams, ListArchivedParams, RestoreVersionsParams};
+use crate::audit::{AuditLogResponse, AuditQuery};
+use crate::ask::{AskDocsParams, AskDocsResponse};
+use crate::batching::FlushParams;
+use crate::benchmark::{self, RunBenchmarkParams};
+use crate::context::{self, ContextResponse, GetContextParams};
+use crate::diff::{self, DiffVersionsParams, VersionDiff};
+use crate::embeddings::SemanticSearchParams;
+use crate::explain::{self, ExplainQueryParams};
+use crate::health::{self, GetHealthParams};
+use crate::ingest::{
+    self, AddFileOptions, AddFileResponse, CrateOptions, CrateResponse, CrawlOptions, CrawlStatus, DevdocsOptions,
+    DevdocsResponse, DocsSiteOptions, DocsSiteResponse, DocsetOptions, DocsetResponse, GitHubRepoOptions,
+    GitHubRepoResponse, GitRepoOptions, GitRepoResponse, IndexPathOptions, IndexPathResponse, InventoryCrawlOptions,
+    LatestVersionParams, LatestVersionResponse, ListCrawlsParams, ListFeedsParams, ListSchedulesParams, ManPagesOptions,
+    ManPagesResponse, MdbookOptions, MdbookResponse, MdnOptions, MdnResponse, NpmPackageOptions, NpmPackageResponse,
+    PypiPackageOptions, PypiPackageResponse, RefreshOptions, ReleaseChecksParams, ReleaseNotesOptions,
+    ReleaseNotesResponse, RustdocOptions, RustdocResponse, ScheduleOptions, SitemapCrawlOptions, SourceSchedule,
+    WatchFeedOptions, WatchedFeed,
+};
+use crate::jobs::{Job, JobParams, JobProgress, ListJobsParams};
+use crate::logging::{GetLogLevelParams, LogLevel};
+use crate::resources::{self, ResourceUsageParams};
+use crate::search::{project_document, SearchableDocument};
+use crate::slow_queries::{self, GetSlowQueriesParams};
+use crate::staleness::{PurgeStaleParams, StaleReportParams};
+use crate::summary::{self, DocumentSummary, SummarizeDocumentParams};
+use crate::validation::{
+    validate_add_file, validate_archive_versions, validate_crate, validate_crawl, validate_devdocs,
+    validate_diff_versions, validate_docs_site, validate_docset, validate_document, validate_documents,
+    validate_facet_counts, validate_fields, validate_find_duplicates, validate_git_repo, validate_github_repo,
+    validate_index_path, validate_inventory_crawl, validate_latest_version, validate_man_pages, validate_mdbook,
+    validate_mdn, validate_multi_search, validate_npm_package, validate_purge_stale, validate_pypi_package,
+    validate_refresh, validate_release_notes, validate_restore_versions, validate_run_benchmark, validate_rustdoc,
+    validate_schedule, validate_sitemap_crawl, validate_version_alias, validate_watch_feed, validate_workspace,
+};
+use crate::workspaces::{ListWorkspacesParams, Workspace, WorkspaceParams};
+use crate::{
+    elapsed_ms, search_by_version, search_options, AddDocumentParams, AddDocumentsParams, DeleteDocumentParams,
+    FetchAndIndexParams, FetchAndIndexResponse, FindDuplicatesParams, FindDuplicatesResponse, GetDocumentParams,
+    GetDocumentResponse, ListFacetsParams, ListFacetsResponse, McpError, MultiSearchParams, MultiSearchResponse,
+    MultiSearchResult, PingParams, PingResponse, ResponseTimings, SearchParams, SearchResponse, SearchVersionsParams,
+};
+
+// Page size for `resources/list`
+const RESOURCE_PAGE_SIZE: usize = 100;
+
+// Documents are exposed as MCP resources under this scheme, e.g. `doc://tokio-spawn`
+const DOCUMENT_URI_PREFIX: &str = "doc://";
+
+// Every JSON-RPC method the server answers, with the scope a key needs to
+// call it. New methods only need a handler and a line here.
+pub fn default_methods() -> MethodRegistry {
+    let mut methods = MethodRegistry::default();
+    methods
+        .register("initialize", Scope::Read, initialize)
+        .register("ping", Scope::Read, ping)
+        .register("addDocument", Scope::Write, |ctx: RpcContext, params: AddDocumentParams| async move {
+            add_document(&ctx.state, params)
+        })
+        .register("flush", Scope::Write, |ctx: RpcContext, _params: FlushParams| async move {
+            ctx.state.add_queue.flush().await
+        })
+        .register("addDocuments", Scope::Write, |ctx: RpcContext, params: AddDocumentsParams| async move {
+            add_documents(&ctx.state, params, &ctx.progress).await
+        })
+        .register("searchDocuments", Scope::Read, |ctx: RpcContext, params: SearchParams| async move {
+            search_documents(&ctx.state, ctx.session.as_ref(), params).await
+        })
+        .register("multiSearch", Scope::Read, |ctx: RpcContext, params: MultiSearchParams| async move {
+            multi_search(&ctx.state, ctx.session.as_ref(), params).await
+        })
+        .register("explainQuery", Scope::Read, |ctx: RpcContext, params: ExplainQueryParams| async move {
+            explain::explain_query(&ctx.state.search_service, &params).await
+        })
+        // Write, though it leaves the index alone: it keeps cores busy for a while
+        .register("runBenchmark", Scope::Write, |ctx: RpcContext, params: RunBenchmarkParams| async move {
+            validate_run_benchmark(&params)?;
+            ctx.state
+                .search_service
+                .blocking(move |service| benchmark::run_benchmark(service, &params))
+                .await
+                .map_err(|e| McpError::index("Benchmark failed", e))
+        })
+        .register("getHealth", Scope::Read, |ctx: RpcContext, _params: GetHealthParams| async move {
+            Ok::<_, McpError>(health::health(&ctx.state))
+        })
+        .register("getResourceUsage", Scope::Read, |ctx: RpcContext, _params: ResourceUsageParams| async move {
+            resources::resource_usage(&ctx.state).await
+        })
+        .register("listFacets", Scope::Read, |ctx: RpcContext, params: ListFacetsParams| async move {
+            list_facets(&ctx.state, params).await
+        })
+        .register("findDuplicates", Scope::Read, |ctx: RpcContext, params: FindDuplicatesParams| async move {
+            find_duplicates(&ctx.state, params).await
+        })
+        .register("searchVersions", Scope::Read, |ctx: RpcContext, params: SearchVersionsParams| async move {
+            search_by_version(&ctx.state.search_service, params).await
+        })
+        .register("semanticSearch", Scope::Read, |ctx: RpcContext, params: SemanticSearchParams| async move {
+            ctx.state.embeddings.clone().search(params).await
+        })
+        .register("diffVersions", Scope::Read, |ctx: RpcContext, params: DiffVersionsParams| async move {
+            diff_versions(&ctx.state, params).await
+        })
+        .register("getContext", Scope::Read, |ctx: RpcContext, params: GetContextParams| async move {
+            get_context(&ctx.state, ctx.session.as_ref(), params).await
+        })
+        .register("askDocs", Scope::Read, |ctx: RpcContext, params: AskDocsParams| async move {
+            ask_docs(&ctx.state, ctx.session.as_ref(), params).await
+        })
+        .register("summarizeDocument", Scope::Read, |ctx: RpcContext, params: SummarizeDocumentParams| async move {
+            summarize_document(&ctx.state, params).await
+        })
+        .register("fetchAndIndex", Scope::Write, |ctx: RpcContext, params: FetchAndIndexParams| async move {
+            fetch_and_index(&ctx.state, params).await
+        })
+        .register("addDocumentFromFile", Scope::Write, |ctx: RpcContext, options: AddFileOptions| async move {
+            add_document_from_file(&ctx.state, options).await
+        })
+        .register("indexPath", Scope::Write, |ctx: RpcContext, mut options: IndexPathOptions| async move {
+            validate_index_path(&options)?;
+            options.path = ctx.state.import_roots.resolve("path", &options.path)?;
+            let target = options.path.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexPath", &target, |state, progress| async move {
+                index_path(&state, options, progress).await
+            }))
+        })
+        .register("indexRustdoc", Scope::Write, |ctx: RpcContext, mut options: RustdocOptions| async move {
+            validate_rustdoc(&options)?;
+            options.path = ctx.state.import_roots.resolve("path", &options.path)?;
+            let target = options.path.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexRustdoc", &target, |state, progress| async move {
+                index_rustdoc(&state, options, progress).await
+            }))
+        })
+        .register("indexMdbook", Scope::Write, |ctx: RpcContext, mut options: MdbookOptions| async move {
+            validate_mdbook(&options)?;
+            let location = options.location.trim();
+            if !(location.starts_with("http://") || location.starts_with("https://")) {
+                options.location = ctx.state.import_roots.resolve("location", &options.location)?;
+            }
+            let target = options.location.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexMdbook", &target, |state, progress| async move {
+                index_mdbook(&state, options, progress).await
+            }))
+        })
+        .register("indexCrate", Scope::Write, |ctx: RpcContext, options: CrateOptions| async move {
+            queue_crate(&ctx.state, "indexCrate", options)
+        })
+        .register("indexNpmPackage", Scope::Write, |ctx: RpcContext, options: NpmPackageOptions| async move {
+            queue_npm_package(&ctx.state, "indexNpmPackage", options)
+        })
+        .register("indexPyPiPackage", Scope::Write, |ctx: RpcContext, options: PypiPackageOptions| async move {
+            queue_pypi_package(&ctx.state, "indexPyPiPackage", options)
+        })
+        .register("indexGitHubRepo", Scope::Write, |ctx: RpcContext, options: GitHubRepoOptions| async move {
+            queue_github_repo(&ctx.state, "indexGitHubRepo", options)
+        })
+        .register("indexGitRepo", Scope::Write, |ctx: RpcContext, mut options: GitRepoOptions| async move {
+            validate_git_repo(&options)?;
+            if !is_remote_repo(&options.repo) {
+                options.repo = ctx.state.import_roots.resolve("repo", &options.repo)?;
+            }
+            let target = options.repo.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexGitRepo", &target, |state, progress| async move {
+                index_git_repo(&state, options, progress).await
+            }))
+        })
+        .register("indexReleaseNotes", Scope::Write, |ctx: RpcContext, options: ReleaseNotesOptions| async move {
+            queue_release_notes(&ctx.state, "indexReleaseNotes", options)
+        })
+        .register("indexMdn", Scope::Write, |ctx: RpcContext, options: MdnOptions| async move {
+            queue_mdn(&ctx.state, "indexMdn", options)
+        })
+        .register("indexDocset", Scope::Write, |ctx: RpcContext, mut options: DocsetOptions| async move {
+            validate_docset(&options)?;
+            options.path = ctx.state.import_roots.resolve("path", &options.path)?;
+            let target = options.path.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexDocset", &target, |state, progress| async move {
+                index_docset(&state, options, progress).await
+            }))
+        })
+        .register("indexDevdocs", Scope::Write, |ctx: RpcContext, options: DevdocsOptions| async move {
+            validate_devdocs(&options)?;
+            let target = options.doc.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexDevdocs", &target, |state, progress| async move {
+                index_devdocs(&state, options, progress).await
+            }))
+        })
+        .register("indexManPages", Scope::Write, |ctx: RpcContext, options: ManPagesOptions| async move {
+            validate_man_pages(&options)?;
+            let target = options.sections.join(", ");
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexManPages", &target, |state, progress| async move {
+                index_man_pages(&state, options, progress).await
+            }))
+        })
+        .register("indexDocsSite", Scope::Write, |ctx: RpcContext, options: DocsSiteOptions| async move {
+            validate_docs_site(&options)?;
+            let target = options.url.clone();
+            Ok::<_, McpError>(queue_job(&ctx.state, "indexDocsSite", &target, |state, progress| async move {
+                index_docs_site(&state, options, progress).await
+            }))
+        })
+        .register("startCrawl", Scope::Write, |ctx: RpcContext, options: CrawlOptions| async move {
+            start_crawl(&ctx.state, options)
+        })
+        .register("crawlSitemap", Scope::Write, |ctx: RpcContext, options: SitemapCrawlOptions| async move {
+            crawl_sitemap(&ctx.state, options)
+        })
+        .register("crawlInventory", Scope::Write, |ctx: RpcContext, options: InventoryCrawlOptions| async move {
+            crawl_inventory(&ctx.state, options)
+        })
+        .register("refreshSource", Scope::Write, |ctx: RpcContext, options: RefreshOptions| async move {
+            refresh_source(&ctx.state, options).await
+        })
+        .register("cancelCrawl", Scope::Write, |ctx: RpcContext, params: CrawlParams| async move {
+            ctx.state.crawls.cancel(&params.id)
+        })
+        .register("listJobs", Scope::Read, |ctx: RpcContext, params: ListJobsParams| async move {
+            Ok::<_, McpError>(json!({ "jobs": ctx.state.jobs.list(&params) }))
+        })
+        .register("getJob", Scope::Read, |ctx: RpcContext, params: JobParams| async move {
+            ctx.state.jobs.get(&params.id)
+        })
+        .register("getLatestVersion", Scope::Read, |ctx: RpcContext, params: LatestVersionParams| async move {
+            get_latest_version(&ctx.state, params).await
+        })
+        .register("checkReleases", Scope::Write, |ctx: RpcContext, _params: ReleaseChecksParams| async move {
+            let packages = ctx.state.releases.check_now().await?;
+            Ok::<_, McpError>(json!({ "packages": packages }))
+        })
+        .register("listReleaseChecks", Scope::Read, |ctx: RpcContext, _params: ReleaseChecksParams| async move {
+            Ok::<_, McpError>(json!({ "packages": ctx.state.releases.list() }))
+        })
+        .register("getCrawlStatus", Scope::Read, |ctx: RpcContext, params: CrawlParams| async move {
+            ctx.state.crawls.status(&params.id)
+        })
+        .register("listCrawls", Scope::Read, |ctx: RpcContext, _params: ListCrawlsParams| async move {
+            Ok::<_, McpError>(json!({ "crawls": ctx.state.crawls.list() }))
+        })
+        .register("getSourceFailures", Scope::Read, |ctx: RpcContext, params: SourceParams| async move {
+            let failures = ctx.state.crawls.failures(&params.source);
+            Ok::<_, McpError>(json!({ "source": params.source, "failures": failures }))
+        })
+        .register("scheduleSource", Scope::Write, |ctx: RpcContext, options: ScheduleOptions| async move {
+            schedule_source(&ctx.state, options)
+        })
+        .register("unscheduleSource", Scope::Write, |ctx: RpcContext, params: SourceParams| async move {
+            ctx.state.schedules.unschedule(&params.source)
+        })
+        .register("listSchedules", Scope::Read, |ctx: RpcContext, _params: ListSchedulesParams| async move {
+            Ok::<_, McpError>(json!({ "schedules": ctx.state.schedules.list() }))
+        })
+        .register("watchFeed", Scope::Write, |ctx: RpcContext, options: WatchFeedOptions| async move {
+            watch_feed(&ctx.state, options)
+        })
+        .register("unwatchFeed", Scope::Write, |ctx: RpcContext, params: FeedParams| async move {
+            ctx.state.feeds.unwatch(&params.url)
+        })
+        .register("listFeeds", Scope::Read, |ctx: RpcContext, _params: ListFeedsParams| async move {
+            Ok::<_, McpError>(json!({ "feeds": ctx.state.feeds.list() }))
+        })
+        .register("getDocument", Scope::Read, |ctx: RpcContext, params: GetDocumentParams| async move {
+            get_document(&ctx.state, params).await
+        })
+        .register("deleteDocument", Scope::Write, |ctx: RpcContext, params: DeleteDocumentParams| async move {
+            delete_document(&ctx.state, params.id).await.map(|deleted| json!({ "deleted": deleted }))
+        })
+        .register("purgeStale", Scope::Write, |ctx: RpcContext, params: PurgeStaleParams| async move {
+            validate_purge_stale(&params)?;
+            ctx.state.staleness.purge(&params)
+        })
+        .register("setVersionAlias", Scope::Write, |ctx: RpcContext, params: SetVersionAliasParams| async move {
+            set_version_alias(&ctx.state, params)
+        })
+        .register("removeVersionAlias", Scope::Write, |ctx: RpcContext, params: RemoveVersionAliasParams| async move {
+            remove_version_alias(&ctx.state, params)
+        })
+        .register("listVersionAliases", Scope::Read, |ctx: RpcContext, params: ListVersionAliasesParams| async move {
+            ctx.state
+                .search_service
+                .version_aliases(params.source.as_deref())
+                .map(|aliases| json!({ "aliases": aliases }))
+                .map_err(|e| McpError::index("Failed to list version aliases", e))
+        })
+        .register("archiveVersions", Scope::Write, |ctx: RpcContext, params: ArchiveVersionsParams| async move {
+            validate_archive_versions(&params)?;
+            let versions = ctx
+                .state
+                .search_service
+                .blocking(move |service| archive::archive_versions(service, &params))
+                .await?;
+            Ok::<_, McpError>(json!({ "versions": versions }))
+        })
+        .register("restoreVersions", Scope::Write, |ctx: RpcContext, params: RestoreVersionsParams| async move {
+            validate_restore_versions(&params)?;
+            let versions = ctx
+                .state
+                .search_service
+                .blocking(move |service| archive::restore_versions(service, &params))
+                .await?;
+            Ok::<_, McpError>(json!({ "versions": versions }))
+        })
+        .register("listArchivedVersions", Scope::Read, |ctx: RpcContext, params: ListArchivedParams| async move {
+            let versions = archive::list_archived(&ctx.state.search_service, &params)?;
+            Ok::<_, McpError>(json!({ "versions": versions }))
+        })
+        .register("setWorkspace", Scope::Write, |ctx: RpcContext, params: Workspace| async move {
+            set_workspace(&ctx.state, params)
+        })
+        .register("removeWorkspace", Scope::Write, |ctx: RpcContext, params: WorkspaceParams| async move {
+            ctx.state
+                .workspaces
+                .remove(&params.name)
+                .map_err(|e| McpError::internal(format!("Failed to save workspaces: {}", e)))?
+                .ok_or_else(|| McpError::not_found(format!("Workspace {}", params.name)))
+        })
+        .register("listWorkspaces", Scope::Read, |ctx: RpcContext, _params: ListWorkspacesParams| async move {
+            Ok::<_, McpError>(json!({ "workspaces": ctx.state.workspaces.list() }))
+        })
+        .register("getWorkspacePins", Scope::Read, |ctx: RpcContext, params: WorkspaceParams| async move {
+            let pins = ctx.state.workspaces.pins(&params.name)?;
+            Ok::<_, McpError>(json!({ "name": params.name, "pins": pins }))
+        })
+        .register("getStaleReport", Scope::Read, |ctx: RpcContext, _params: StaleReportParams| async move {
+            Ok::<_, McpError>(json!({ "report": ctx.state.staleness.last_report() }))
+        })
+        .register("tools/list", Scope::Read, |ctx: RpcContext, _params: Value| async move {
+            Ok::<_, RpcError>(json!({ "tools": tool_definitions(ctx.caller.scope) }))
+        })
+        // Tools check their own scope, see `TOOLS`
+        .register("tools/call", Scope::Read, |ctx: RpcContext, params: ToolCallParams| async move {
+            call_tool(&ctx.state, ctx.session.as_ref(), &ctx.caller, params, &ctx.progress, &ctx.request_id).await
+        })
+        .register("resources/list", Scope::Read, |ctx: RpcContext, params: ResourceListParams| async move {
+            list_resources(&ctx.state, params)
+        })
+        .register("resources/read", Scope::Read, |ctx: RpcContext, params: ResourceParams| async move {
+            read_resource(&ctx.state, &params.uri).await
+        })
+        .register("resources/subscribe", Scope::Read, |ctx: RpcContext, params: ResourceParams| async move {
+            set_subscription(ctx.session.as_ref(), params.uri, true)
+        })
+        .register("resources/unsubscribe", Scope::Read, |ctx: RpcContext, params: ResourceParams| async move {
+            set_subscription(ctx.session.as_ref(), params.uri, false)
+        })
+        // Shows what every key has been doing, so only full-access keys may read it
+        .register("getAuditLog", Scope::Write, |ctx: RpcContext, params: AuditQuery| async move {
+            ctx.state
+                .audit
+                .query(&params)
+                .map(|entries| AuditLogResponse { entries })
+                .map_err(|e| McpError::internal(format!("Failed to read audit log: {}", e)))
+        })
+        // Other callers' queries, so like the audit log, full-access keys only
+        .register("getSlowQueries", Scope::Write, |ctx: RpcContext, params: GetSlowQueriesParams| async move {
+            slow_queries::get_slow_queries(&ctx.state.search_service, &params)
+        })
+        .register("getLogLevel", Scope::Read, |ctx: RpcContext, _params: GetLogLevelParams| async move {
+            Ok::<_, McpError>(ctx.state.logging.level())
+        })
+        // Until the app quits, like the set_log_level command
+        .register("setLogLevel", Scope::Write, |ctx: RpcContext, params: LogLevel| async move {
+            ctx.state.logging.set_level(&params.level)
+        });
+    methods
+}
+
+async fn initialize(ctx: RpcContext, _params: Value) -> Result<Value, RpcError> {
+    let protocol_version = ctx
+        .session
+        .map(|s| s.protocol_version())
+        .unwrap_or_else(|| SUPPORTED_PROTOCOL_VERSIONS[0].to_string());
+    Ok(json!({
+        "protocolVersion": protocol_version,
+        "capabilities": {
+            "tools": {},
+            "resources": { "subscribe": true, "listChanged": true }
+        },
+        "serverInfo": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    }))
+}
+
+async fn ping(_ctx: RpcContext, params: PingParams) -> Result<PingResponse, RpcError> {
+    Ok(PingResponse {
+        reply: format!("pong - received: {}", params.message),
+    })
+}
+
+fn set_subscription(session: Option<&Arc<Session>>, uri: String, subscribed: bool) -> Result<Value, RpcError> {
+    let session =
+        session.ok_or_else(|| RpcError::new(INVALID_REQUEST, "Resource subscriptions require an MCP session"))?;
+    let mut subscriptions = session.subscriptions.lock().unwrap();
+    if subscribed {
+        subscriptions.insert(uri);
+    } else {
+        subscriptions.remove(&uri);
+    }
+    Ok(json!({}))
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+struct ResourceListParams {
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CrawlParams {
+    id: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct SourceParams {
+    source: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct FeedParams {
+    url: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct ResourceParams {
+    uri: String,
+}
+
+pub(super) fn document_uri(id: &str) -> String {
+    format!("{}{}", DOCUMENT_URI_PREFIX, id)
+}
+
+fn list_resources(state: &RpcState, params: ResourceListParams) -> Result<Value, McpError> {
+    // The cursor is simply the offset of the next page
+    let offset = match params.cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .map_err(|_| McpError::validation(Some("cursor"), "Invalid cursor"))?,
+        None => 0,
+    };
+
+    let documents = state
+        .search_service
+        .list_documents(offset, RESOURCE_PAGE_SIZE)
+        .map_err(|e| McpError::index("Failed to list documents", e))?;
+
+    let next_cursor = (documents.len() == RESOURCE_PAGE_SIZE).then(|| (offset + RESOURCE_PAGE_SIZE).to_string());
+    let resources: Vec<Value> = documents
+        .iter()
+        .map(|doc| {
+            let description = match &doc.version {
+                Some(version) => format!("{} {}", doc.source, version),
+                None => doc.source.clone(),
+            };
+            json!({
+                "uri": document_uri(&doc.id),
+                "name": doc.title,
+                "description": description,
+                "mimeType": "text/plain"
+            })
+        })
+        .collect();
+
+    let mut result = json!({ "resources": resources });
+    if let Some(next_cursor) = next_cursor {
+        result["nextCursor"] = Value::String(next_cursor);
+    }
+    Ok(result)
+}
+
+async fn read_resource(state: &RpcState, uri: &str) -> Result<Value, McpError> {
+    let id = uri
+        .strip_prefix(DOCUMENT_URI_PREFIX)
+        .ok_or_else(|| McpError::validation(Some("uri"), format!("Unknown resource: {}", uri)))?
+        .to_string();
+
+    let document = state
+        .search_service
+        .blocking(move |service| service.get_document(&id))
+        .await
+        .map_err(|e| McpError::index("Failed to read document", e))?
+        .ok_or_else(|| McpError::not_found(format!("Resource {}", uri)))?;
+
+    Ok(json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": "text/plain",
+            "text": document.body
+        }]
+    }))
+}
+
+// Queued rather than committed right away; see batching.rs
+fn add_document(state: &RpcState, params: AddDocumentParams) -> Result<String, McpError> {
+    validate_document(&params.document, "document")?;
+    let id = params.document.id.clone();
+    state.add_queue.add(params.document);
+    Ok(format!("Document {} queued; it's searchable after the next commit, or a flush.", id))
+}
+
+async fn add_document_from_file(
+    state: &RpcState,
+    mut options: AddFileOptions,
+) -> Result<AddFileResponse, McpError> {
+    validate_add_file(&options)?;
+    options.path = state.import_roots.resolve("path", &options.path)?;
+    let (search_service, chunking) = (state.search_service.clone(), state.chunking.clone());
+    tokio::task::spawn_blocking(move || ingest::add_file(&search_service, &chunking, &options))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_path(
+    state: &RpcState,
+    options: IndexPathOptions,
+    progress: JobProgress,
+) -> Result<IndexPathResponse, McpError> {
+    validate_index_path(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let (search_service, chunking) = (state.search_service.clone(), state.chunking.clone());
+    tokio::task::spawn_blocking(move || ingest::index_path(&search_service, &chunking, &options, on_progress))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_rustdoc(
+    state: &RpcState,
+    options: RustdocOptions,
+    progress: JobProgress,
+) -> Result<RustdocResponse, McpError> {
+    validate_rustdoc(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    tokio::task::spawn_blocking(move || ingest::index_rustdoc(&search_service, &options, on_progress))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_mdbook(
+    state: &RpcState,
+    options: MdbookOptions,
+    progress: JobProgress,
+) -> Result<MdbookResponse, McpError> {
+    validate_mdbook(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_mdbook(&state.fetcher, search_service, options, on_progress).await
+}
+
+async fn get_latest_version(state: &RpcState, params: LatestVersionParams) -> Result<LatestVersionResponse, McpError> {
+    validate_latest_version(&params)?;
+    ingest::latest_version(&state.fetcher, &params).await
+}
+
+async fn index_crate(
+    state: &RpcState,
+    options: CrateOptions,
+    progress: JobProgress,
+) -> Result<CrateResponse, McpError> {
+    validate_crate(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_crate(&state.fetcher, &state.crawls, search_service, options, on_progress).await
+}
+
+async fn index_npm_package(
+    state: &RpcState,
+    options: NpmPackageOptions,
+    progress: JobProgress,
+) -> Result<NpmPackageResponse, McpError> {
+    validate_npm_package(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_npm_package(&state.fetcher, &state.crawls, search_service, options, on_progress).await
+}
+
+async fn index_pypi_package(
+    state: &RpcState,
+    options: PypiPackageOptions,
+    progress: JobProgress,
+) -> Result<PypiPackageResponse, McpError> {
+    validate_pypi_package(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_pypi_package(&state.fetcher, &state.crawls, search_service, options, on_progress).await
+}
+
+async fn index_github_repo(
+    state: &RpcState,
+    options: GitHubRepoOptions,
+    progress: JobProgress,
+) -> Result<GitHubRepoResponse, McpError> {
+    validate_github_repo(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_github_repo(
+        &state.fetcher,
+        &state.crawls,
+        &state.chunking,
+        search_service,
+        options,
+        on_progress,
+    )
+    .await
+}
+
+async fn index_git_repo(
+    state: &RpcState,
+    options: GitRepoOptions,
+    progress: JobProgress,
+) -> Result<GitRepoResponse, McpError> {
+    validate_git_repo(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let (search_service, chunking) = (state.search_service.clone(), state.chunking.clone());
+    let mirrors = state.git_mirrors.clone();
+    tokio::task::spawn_blocking(move || {
+        ingest::index_git_repo(&search_service, &chunking, &mirrors, &options, on_progress)
+    })
+    .await
+    .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_release_notes(
+    state: &RpcState,
+    options: ReleaseNotesOptions,
+    progress: JobProgress,
+) -> Result<ReleaseNotesResponse, McpError> {
+    validate_release_notes(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_release_notes(&state.fetcher, search_service, options, on_progress).await
+}
+
+async fn index_mdn(state: &RpcState, options: MdnOptions, progress: JobProgress) -> Result<MdnResponse, McpError> {
+    validate_mdn(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_mdn(&state.fetcher, &state.chunking, search_service, options, on_progress).await
+}
+
+async fn index_docset(
+    state: &RpcState,
+    options: DocsetOptions,
+    progress: JobProgress,
+) -> Result<DocsetResponse, McpError> {
+    validate_docset(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    tokio::task::spawn_blocking(move || ingest::index_docset(&search_service, &options, on_progress))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_devdocs(
+    state: &RpcState,
+    options: DevdocsOptions,
+    progress: JobProgress,
+) -> Result<DevdocsResponse, McpError> {
+    validate_devdocs(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_devdocs(&state.fetcher, search_service, options, on_progress).await
+}
+
+async fn index_man_pages(
+    state: &RpcState,
+    options: ManPagesOptions,
+    progress: JobProgress,
+) -> Result<ManPagesResponse, McpError> {
+    validate_man_pages(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    tokio::task::spawn_blocking(move || ingest::index_man_pages(&search_service, &options, on_progress))
+        .await
+        .map_err(|e| McpError::internal(format!("Indexing task failed: {}", e)))?
+}
+
+async fn index_docs_site(
+    state: &RpcState,
+    options: DocsSiteOptions,
+    progress: JobProgress,
+) -> Result<DocsSiteResponse, McpError> {
+    validate_docs_site(&options)?;
+    let on_progress = move |done, total| progress.report(done, total);
+    let search_service = state.search_service.clone();
+    ingest::index_docs_site(&state.fetcher, &state.crawls, search_service, options, on_progress).await
+}
+
+// Ingestion runs as a job, which the call returns once it's queued; its
+// options are checked before that, so a bad call still fails at once
+fn queue_job<T, F>(state: &RpcState, method: &str, target: &str, work: impl FnOnce(RpcState, JobProgress) -> F) -> Job
+where
+    T: Serialize + Send + 'static,
+    F: Future<Output = Result<T, McpError>> + Send + 'static,
+{
+    state
+        .jobs
+        .submit(method, target, |progress| work(state.clone(), progress))
+}
+
+// The sources that are MCP tools as well, queued the same way from either
+fn queue_crate(state: &RpcState, method: &str, options: CrateOptions) -> Result<Job, McpError> {
+    validate_crate(&options)?;
+    let target = options.name.clone();
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_crate(&state, options, progress).await
+    }))
+}
+
+fn queue_npm_package(state: &RpcState, method: &str, options: NpmPackageOptions) -> Result<Job, McpError> {
+    validate_npm_package(&options)?;
+    let target = options.name.clone();
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_npm_package(&state, options, progress).await
+    }))
+}
+
+fn queue_pypi_package(state: &RpcState, method: &str, options: PypiPackageOptions) -> Result<Job, McpError> {
+    validate_pypi_package(&options)?;
+    let target = options.name.clone();
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_pypi_package(&state, options, progress).await
+    }))
+}
+
+fn queue_github_repo(state: &RpcState, method: &str, options: GitHubRepoOptions) -> Result<Job, McpError> {
+    validate_github_repo(&options)?;
+    let target = options.repo.clone();
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_github_repo(&state, options, progress).await
+    }))
+}
+
+fn queue_release_notes(state: &RpcState, method: &str, options: ReleaseNotesOptions) -> Result<Job, McpError> {
+    validate_release_notes(&options)?;
+    let target = options.repo.clone();
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_release_notes(&state, options, progress).await
+    }))
+}
+
+fn queue_mdn(state: &RpcState, method: &str, options: MdnOptions) -> Result<Job, McpError> {
+    validate_mdn(&options)?;
+    let target = options.areas.join(", ");
+    Ok(queue_job(state, method, &target, |state, progress| async move {
+        index_mdn(&state, options, progress).await
+    }))
+}
+
+fn start_crawl(state: &RpcState, options: CrawlOptions) -> Result<CrawlStatus, McpError> {
+    validate_crawl(&options)?;
+    state.crawls.start(options)
+}
+
+fn crawl_sitemap(state: &RpcState, options: SitemapCrawlOptions) -> Result<CrawlStatus, McpError> {
+    validate_sitemap_crawl(&options)?;
+    state.crawls.start_sitemap(options)
+}
+
+fn crawl_inventory(state: &RpcState, options: InventoryCrawlOptions) -> Result<CrawlStatus, McpError> {
+    validate_inventory_crawl(&options)?;
+    state.crawls.start_inventory(options)
+}
+
+async fn refresh_source(state: &RpcState, options: RefreshOptions) -> Result<CrawlStatus, McpError> {
+    validate_refresh(&options)?;
+    state.crawls.start_refresh(options).await
+}
+
+fn schedule_source(state: &RpcState, options: ScheduleOptions) -> Result<SourceSchedule, McpError> {
+    validate_schedule(&options)?;
+    state.schedules.schedule(options)
+}
+
+fn watch_feed(state: &RpcState, options: WatchFeedOptions) -> Result<WatchedFeed, McpError> {
+    validate_watch_feed(&options)?;
+    state.feeds.watch(options)
+}
+
+async fn fetch_and_index(state: &RpcState, params: FetchAndIndexParams) -> Result<FetchAndIndexResponse, McpError> {
+    let page = ingest::fetch_document(&state.fetcher, &state.extraction, &params.url, params.source, params.version).await?;
+    let document = page.document;
+    validate_document(&document, "document")?;
+    let documents = state.chunking.split(document);
+    let response = FetchAndIndexResponse {
+        id: documents[0].id.clone(),
+        title: documents[0].title.clone(),
+        source: documents[0].source.clone(),
+        sections: documents.len() - 1,
+    };
+    state
+        .search_service
+        .blocking(move |service| service.add_documents(documents, |_, _| {}))
+        .await
+        .map_err(|e| McpError::index("Failed to add document", e))?;
+    // Only needed for refreshing the page later, so not worth failing over
+    if let Err(e) = state.pages.remember([(response.id.clone(), page.record)]) {
+        error!("Failed to save page records: {}", e);
+    }
+    Ok(response)
+}
+
+async fn add_documents(
+    state: &RpcState,
+    params: AddDocumentsParams,
+    progress: &ProgressReporter,
+) -> Result<String, McpError> {
+    validate_documents(&params.documents)?;
+    let count = params.documents.len();
+    // Roughly one notification per percent keeps big batches from flooding the stream
+    let step = (count / 100).max(1);
+    let progress = progress.clone();
+    state
+        .search_service
+        .blocking(move |service| {
+            service.add_documents(params.documents, |done, total| {
+                if done % step == 0 || done == total {
+                    progress.report(done as u64, Some(total as u64), None);
+                }
+            })
+        })
+        .await
+        .map(|_| format!("{} documents added successfully.", count))
+        .map_err(|e| McpError::index("Failed to add documents", e))
+}
+
+async fn delete_document(state: &RpcState, id: String) -> Result<bool, McpError> {
+    state
+        .search_service
+        .blocking(move |service| service.delete_document(&id))
+        .await
+        .map_err(|e| McpError::index("Failed to delete document", e))
+}
+
+async fn search_documents(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    mut params: SearchParams,
+) -> Result<SearchResponse, McpError> {
+    let started = Instant::now();
+    validate_fields(&params.fields, true)?;
+    let query = params.query.clone();
+    let fields = std::mem::take(&mut params.fields);
+    let include_timings = params.include_timings;
+    let (documents, mut timings) = find_documents(state, session, params).await?;
+    let selecting = Instant::now();
+    let documents = state
+        .search_service
+        .select(&query, documents, &fields)
+        .await
+        .map_err(|e| McpError::search(&query, e))?;
+    timings.serialize_ms = elapsed_ms(selecting);
+    timings.total_ms = elapsed_ms(started);
+    Ok(SearchResponse {
+        documents,
+        timings: include_timings.then_some(timings),
+    })
+}
+
+// Runs the searches concurrently: each one's work goes to the blocking pool,
+// so they use as many cores as the pool has rather than taking turns
+async fn multi_search(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    params: MultiSearchParams,
+) -> Result<MultiSearchResponse, McpError> {
+    validate_multi_search(&params)?;
+    let searches = params.searches.into_iter().map(|search| async move {
+        let query = search.query.clone();
+        let result = match search_documents(state, session, search).await {
+            Ok(response) => MultiSearchResult {
+                documents: response.documents,
+                error: None,
+                timings: response.timings,
+            },
+            Err(e) => MultiSearchResult {
+                documents: Vec::new(),
+                error: Some(e.to_string()),
+                timings: None,
+            },
+        };
+        (query, result)
+    });
+    let results = futures_util::future::join_all(searches).await.into_iter().collect();
+    Ok(MultiSearchResponse { results })
+}
+
+// The hits themselves, whole, for callers that read them here, and where
+// the time went so far
+async fn find_documents(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    params: SearchParams,
+) -> Result<(Vec<SearchableDocument>, ResponseTimings), McpError> {
+    let mut timings = ResponseTimings::default();
+    let limit = params.limit.unwrap_or(10);
+    let mut options = search_options(&params)?;
+    if let Some(session) = session {
+        options.preferred_versions = session.search_options().preferred_versions;
+    }
+    options.pinned_versions = state.workspaces.pinned_versions(params.workspace.as_deref())?;
+    if params.semantic {
+        let embedding = Instant::now();
+        options.query_vector = Some(state.embeddings.query_vector(&params.query).await?);
+        timings.parse_ms += elapsed_ms(embedding);
+    }
+    let wanted = if params.rerank { state.reranker.candidates(limit) } else { limit };
+    let (documents, search_timings) = state
+        .search_service
+        .search_timed(&params.query, wanted, &options)
+        .await
+        .map_err(|e| McpError::search(&params.query, e))?;
+    timings.add_search(search_timings);
+    let documents = if params.rerank {
+        let reranking = Instant::now();
+        let documents = state.reranker.rerank(&params.query, documents, limit).await?;
+        timings.search_ms += elapsed_ms(reranking);
+        documents
+    } else {
+        documents
+    };
+    Ok((documents, timings))
+}
+
+async fn get_document(state: &RpcState, params: GetDocumentParams) -> Result<GetDocumentResponse, McpError> {
+    validate_fields(&params.fields, false)?;
+    let id = params.id.clone();
+    let document = state
+        .search_service
+        .blocking(move |service| service.get_document(&id))
+        .await
+        .map_err(|e| McpError::index("Failed to read document", e))?
+        .ok_or_else(|| McpError::not_found(format!("Document {}", params.id)))?;
+    project_document(document, None, &params.fields)
+        .map(|document| GetDocumentResponse { document })
+        .map_err(|e| McpError::internal(format!("Failed to serialize document: {}", e)))
+}
+
+async fn list_facets(state: &RpcState, params: ListFacetsParams) -> Result<ListFacetsResponse, McpError> {
+    let facet = params.facet.unwrap_or_else(|| "/".to_string());
+    validate_facet_counts(params.query.as_deref(), &facet)?;
+    let query = params.query.clone();
+    state
+        .search_service
+        .blocking(move |service| service.facet_counts(query.as_deref(), &facet))
+        .await
+        .map(|facets| ListFacetsResponse { facets })
+        .map_err(|e| McpError::search(params.query.as_deref().unwrap_or_default(), e))
+}
+
+async fn find_duplicates(state: &RpcState, params: FindDuplicatesParams) -> Result<FindDuplicatesResponse, McpError> {
+    let limit = params.limit.unwrap_or(100);
+    validate_find_duplicates(limit)?;
+    let near = params.near;
+    let groups = state
+        .search_service
+        .blocking(move |service| {
+            if near {
+                service.find_near_duplicates(limit)
+            } else {
+                service.find_duplicates(limit)
+            }
+        })
+        .await;
+    groups
+        .map(|groups| FindDuplicatesResponse { groups })
+        .map_err(|e| McpError::index("Failed to look for duplicates", e))
+}
+
+fn set_version_alias(state: &RpcState, params: SetVersionAliasParams) -> Result<VersionAlias, McpError> {
+    validate_version_alias(&params)?;
+    state
+        .search_service
+        .set_alias(&params.source, &params.alias, &params.version)
+        .map_err(|e| McpError::internal(format!("Failed to save version aliases: {}", e)))?;
+    Ok(VersionAlias {
+        source: params.source,
+        alias: params.alias,
+        version: params.version,
+        pinned: true,
+    })
+}
+
+// Removing a pinned `stable` or `latest` brings back the automatic one
+fn remove_version_alias(state: &RpcState, params: RemoveVersionAliasParams) -> Result<VersionAlias, McpError> {
+    let version = state
+        .search_service
+        .remove_alias(&params.source, &params.alias)
+        .map_err(|e| McpError::internal(format!("Failed to save version aliases: {}", e)))?
+        .ok_or_else(|| McpError::not_found(format!("Version alias {} of source {}", params.alias, params.source)))?;
+    Ok(VersionAlias {
+        source: params.source,
+        alias: params.alias,
+        version,
+        pinned: true,
+    })
+}
+
+fn set_workspace(state: &RpcState, workspace: Workspace) -> Result<Workspace, McpError> {
+    validate_workspace(&workspace)?;
+    state
+        .workspaces
+        .set(workspace.clone())
+        .map_err(|e| McpError::internal(format!("Failed to save workspaces: {}", e)))?;
+    Ok(workspace)
+}
+
+async fn diff_versions(state: &RpcState, params: DiffVersionsParams) -> Result<VersionDiff, McpError> {
+    validate_diff_versions(&params)?;
+    state.search_service.blocking(move |service| diff::diff_versions(service, &params)).await
+}
+
+async fn get_context(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    params: GetContextParams,
+) -> Result<ContextResponse, McpError> {
+    let mut options = session.map(|s| s.search_options()).unwrap_or_default();
+    options.pinned_versions = state.workspaces.pinned_versions(params.workspace.as_deref())?;
+    context::get_context(&state.search_service, &state.embeddings, &state.reranker, options, &params).await
+}
+
+async fn ask_docs(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    params: AskDocsParams,
+) -> Result<AskDocsResponse, McpError> {
+    let mut options = session.map(|s| s.search_options()).unwrap_or_default();
+    options.pinned_versions = state.workspaces.pinned_versions(params.workspace.as_deref())?;
+    state.assistant.ask(options, &params).await
+}
+
+async fn summarize_document(state: &RpcState, params: SummarizeDocumentParams) -> Result<DocumentSummary, McpError> {
+    summary::summarize_document(&state.search_service, &state.assistant, &params).await
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct ToolCallParams {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+// The scope each tool needs; tools a key can't call aren't listed to it
+const TOOLS: &[(&str, Scope)] = &[
+    ("search_documents", Scope::Read),
+    ("multi_search", Scope::Read),
+    ("list_facets", Scope::Read),
+    ("add_document", Scope::Write),
+    ("add_documents", Scope::Write),
+    ("flush", Scope::Write),
+    ("fetch_url", Scope::Write),
+    ("index_crate", Scope::Write),
+    ("index_npm_package", Scope::Write),
+    ("index_pypi_package", Scope::Write),
+    ("index_github_repo", Scope::Write),
+    ("index_release_notes", Scope::Write),
+    ("index_mdn", Scope::Write),
+    ("get_job", Scope::Read),
+    ("get_latest_version", Scope::Read),
+    ("search_versions", Scope::Read),
+    ("semantic_search", Scope::Read),
+    ("diff_versions", Scope::Read),
+    ("get_context", Scope::Read),
+    ("ask_docs", Scope::Read),
+    ("summarize_document", Scope::Read),
+    ("summarize_results", Scope::Read),
+];
+
+fn tool_scope(name: &str) -> Option<Scope> {
+    TOOLS.iter().find(|(tool, _)| *tool == name).map(|(_, scope)| *scope)
+}
+
+// Tool input schemas come from the same types the arguments are parsed
+// into, so they can't drift from what `call_tool` accepts.
+fn tool_definitions(scope: Scope) -> Value {
+    let tools = [
+        tool::<SearchParams>("search_documents", "Full-text search over the indexed documentation."),
+        tool::<MultiSearchParams>(
+            "multi_search",
+            "Run several searches at once, e.g. reformulations of one question, and get each one's hits keyed by its query. Each search takes the same arguments as search_documents.",
+        ),
+        tool::<ListFacetsParams>(
+            "list_facets",
+            "List the taxonomy under a facet (e.g. /rust/tokio) with how many documents fall under each child, to narrow a search_documents call with its facet filter.",
+        ),
+        tool::<AddDocumentParams>(
+            "add_document",
+            "Add a document to the search index. Documents added this way are committed in batches, within a second by default; call flush to make them searchable at once.",
+        ),
+        tool::<FlushParams>(
+            "flush",
+            "Commit the documents add_document has queued, so searches see them.",
+        ),
+        tool::<AddDocumentsParams>(
+            "add_documents",
+            "Add a batch of documents to the search index in one commit. Reports progress when called with a progress token.",
+        ),
+        tool::<FetchAndIndexParams>(
+            "fetch_url",
+            "Download a web page (e.g. the latest version of a documentation page) and add its text to the search index, with the URL as its id.",
+        ),
+        tool::<CrateOptions>(
+            "index_crate",
+            "Index a Rust crate's documentation from docs.rs by name, for its newest stable release unless a version is given. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<NpmPackageOptions>(
+            "index_npm_package",
+            "Index an npm package's README and TypeScript declarations, for its latest release unless a version is given, and crawl the docs site it names as its homepage. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<PypiPackageOptions>(
+            "index_pypi_package",
+            "Index a PyPI project's metadata and README, for its newest release unless a version is given, and its documentation site. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<GitHubRepoOptions>(
+            "index_github_repo",
+            "Index a GitHub repository's README and docs folder as of its default branch's latest commit, and crawl its wiki. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<ReleaseNotesOptions>(
+            "index_release_notes",
+            "Index a GitHub repository's release notes and CHANGELOG.md, one document per release with the release as its version, to answer what changed between two versions. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<MdnOptions>(
+            "index_mdn",
+            "Index MDN's web docs for the given technology areas (html, css, javascript, api, http, ...), one document per page, each with its browser compatibility table. Runs in the background: returns the queued job at once, which get_job reports on.",
+        ),
+        tool::<JobParams>(
+            "get_job",
+            "Check on an indexing job by id: whether it is queued, running, done or failed, and once it has finished, what it indexed or why it failed.",
+        ),
+        tool::<LatestVersionParams>(
+            "get_latest_version",
+            "Look up a package's newest stable release and pre-release, with their publish dates, on crates.io, npm or PyPI. Use it to check whether the indexed documentation is for the current release.",
+        ),
+        tool::<SearchVersionsParams>(
+            "search_versions",
+            "Search several versions of one source at once and get the best hits grouped by version, to see how what the docs say changed between versions.",
+        ),
+        tool::<SemanticSearchParams>(
+            "semantic_search",
+            "Search the indexed documentation by meaning rather than by keyword, e.g. for a question phrased differently from the docs. Needs embeddings turned on in the app's settings.",
+        ),
+        tool::<DiffVersionsParams>(
+            "diff_versions",
+            "Compare the indexed documentation of two versions of a source: pages added and removed, and a line diff of each changed page or section. Use it to answer migration questions.",
+        ),
+        tool::<GetContextParams>(
+            "get_context",
+            "Search the index and get the best hits packed into one block of text within a token budget, each under a numbered citation line with its source and URL, ready to paste into a prompt.",
+        ),
+        tool::<AskDocsParams>(
+            "ask_docs",
+            "Ask a question about the indexed documentation and get an answer citing the excerpts it rests on, written by the LLM set in the app's settings. Needs that LLM turned on.",
+        ),
+        tool::<SummarizeDocumentParams>(
+            "summarize_document",
+            "Summarize a stored document by id, to judge a long page before reading it whole. Written by the app's LLM when one is set up, otherwise made of the page's own key sentences.",
+        ),
+        tool::<SummarizeParams>(
+            "summarize_results",
+            "Search the index and have the client's model synthesize a cited answer from the top results. Requires the client to support sampling.",
+        ),
+    ];
+    tools
+        .into_iter()
+        .filter(|(name, _)| tool_scope(name).is_some_and(|required| scope.allows(required)))
+        .map(|(_, definition)| definition)
+        .collect()
+}
+
+fn tool<T: JsonSchema>(name: &'static str, description: &str) -> (&'static str, Value) {
+    let definition = json!({
+        "name": name,
+        "description": description,
+        "inputSchema": inline_schema::<T>()
+    });
+    (name, definition)
+}
+
+// Tool failures are reported inside the result (`isError`) so the model can
+// see them, protocol-level problems (unknown tool, bad arguments) stay
+// JSON-RPC errors.
+async fn call_tool(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    caller: &Caller,
+    params: ToolCallParams,
+    progress: &ProgressReporter,
+    request_id: &str,
+) -> Result<Value, RpcError> {
+    if let Some(scope) = tool_scope(&params.name) {
+        caller.require(scope)?;
+    }
+    let outcome = match params.name.as_str() {
+        "summarize_results" => summarize_results(state, session, parse_params(params.arguments)?).await,
+        "search_documents" => search_documents(state, session, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "multi_search" => multi_search(state, session, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "list_facets" => list_facets(state, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "add_document" => add_document(state, parse_params(params.arguments)?)
+            .map(Value::String)
+            .map_err(RpcError::from),
+        "add_documents" => add_documents(state, parse_params(params.arguments)?, progress)
+            .await
+            .map(Value::String)
+            .map_err(RpcError::from),
+        "flush" => state.add_queue.flush().await.map_err(RpcError::from).and_then(to_value),
+        "fetch_url" => fetch_and_index(state, parse_params(params.arguments)?)
+            .await
+            .map(|indexed| Value::String(format!("Indexed \"{}\" as {}", indexed.title, indexed.id)))
+            .map_err(RpcError::from),
+        "search_versions" => search_by_version(&state.search_service, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "semantic_search" => state
+            .embeddings
+            .clone()
+            .search(parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "diff_versions" => diff_versions(state, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "get_context" => get_context(state, session, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "ask_docs" => ask_docs(state, session, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "summarize_document" => summarize_document(state, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "get_latest_version" => get_latest_version(state, parse_params(params.arguments)?)
+            .await
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_crate" => queue_crate(state, "index_crate", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_npm_package" => queue_npm_package(state, "index_npm_package", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_pypi_package" => queue_pypi_package(state, "index_pypi_package", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_github_repo" => queue_github_repo(state, "index_github_repo", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_release_notes" => queue_release_notes(state, "index_release_notes", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "index_mdn" => queue_mdn(state, "index_mdn", parse_params(params.arguments)?)
+            .map_err(RpcError::from)
+            .and_then(to_value),
+        "get_job" => {
+            let params: JobParams = parse_params(params.arguments)?;
+            state.jobs.get(&params.id).map_err(RpcError::from).and_then(to_value)
+        }
+        other => return Err(RpcError::new(INVALID_PARAMS, format!("Unknown tool: {}", other))),
+    };
+
+    Ok(match outcome {
+        Ok(Value::String(text)) => json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": false
+        }),
+        Ok(structured) => json!({
+            "content": [{ "type": "text", "text": structured.to_string() }],
+            "structuredContent": structured,
+            "isError": false
+        }),
+        Err(error) => json!({
+            "content": [{ "type": "text", "text": error.message }],
+            "isError": true,
+            "_meta": { "requestId": request_id }
+        }),
+    })
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SummarizeParams {
+    /// Tantivy query string
+    query: String,
+    /// Number of results to summarize (default 5)
+    limit: Option<usize>,
+    /// Token budget for the answer (default 1024)
+    max_tokens: Option<u32>,
+}
+
+// How much of each hit's body goes into the sampling prompt
+const SUMMARY_EXCERPT_CHARS: usize = 1500;
+
+fn excerpt(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+// Runs the search here but leaves the LLM call to the client through
+// `sampling/createMessage`, so no model credentials live in this server.
+async fn summarize_results(
+    state: &RpcState,
+    session: Option<&Arc<Session>>,
+    params: SummarizeParams,
+) -> Result<Value, RpcError> {
+    let session = session
+        .filter(|s| s.supports("sampling"))
+        .ok_or_else(|| McpError::unsupported("The client does not support sampling"))?;
+
+    let search = SearchParams {
+        query: params.query.clone(),
+        limit: Some(params.limit.unwrap_or(5)),
+        ..Default::default()
+    };
+    let (documents, _) = find_documents(state, Some(session), search).await?;
+    if documents.is_empty() {
+        return Ok(Value::String(format!("No indexed documents match \"{}\".", params.query)));
+    }
+
+    let mut prompt = format!(
+        "Answer the question using only the numbered documentation excerpts below.\n\nQuestion: {}\n\n",
+        params.query
+    );
+    for (i, doc) in documents.iter().enumerate() {
+        let version = doc.version.as_deref().map(|v| format!(" {}", v)).unwrap_or_default();
+        prompt.push_str(&format!(
+            "[{}] {} ({}{})\n{}\n\n",
+            i + 1,
+            doc.title,
+            doc.source,
+            version,
+            excerpt(&doc.body, SUMMARY_EXCERPT_CHARS)
+        ));
+    }
+
+    let reply = session
+        .request(
+            "sampling/createMessage",
+            Some(json!({
+                "messages": [{
+                    "role": "user",
+                    "content": { "type": "text", "text": prompt }
+                }],
+                "systemPrompt": "You are a documentation assistant. Cite the excerpts you rely on as [n]. If the excerpts do not answer the question, say so.",
+                "includeContext": "none",
+                "maxTokens": params.max_tokens.unwrap_or(1024)
+            })),
+        )
+        .await?;
+
+    let answer = reply
+        .pointer("/content/text")
+        .and_then(Value::as_str)
+        .ok_or_else(|| McpError::internal("Sampling reply did not contain text"))?;
+    let sources: Vec<Value> = documents
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| {
+            json!({
+                "index": i + 1,
+                "id": doc.id,
+                "title": doc.title,
+                "source": doc.source,
+                "version": doc.version
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "answer": answer,
+        "model": reply.get("model"),
+        "sources": sources
+    }))
+}
@@ -0,0 +1,701 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use axum::extract::rejection::BytesRejection;
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, info, info_span, Instrument};
+
+use crate::ask::Assistant;
+use crate::audit::AuditLog;
+use crate::batching::AddQueue;
+use crate::embeddings::Embeddings;
+use crate::health;
+use crate::ingest::{
+    ChunkingRules, CrawlManager, ExtractionRules, FeedWatcher, Fetcher, PageStore, ReleaseWatcher, Scheduler,
+};
+use crate::jobs::JobQueue;
+use crate::logging::Logging;
+use crate::rerank::Reranker;
+use crate::search::{IndexEvent, SearchReady, SearchService};
+use crate::settings::{BatchSettings, ReleaseWatchSettings, ServerSettings};
+use crate::staleness::StaleSweeper;
+use crate::workspaces::Workspaces;
+use crate::McpError;
+use origin::AllowedOrigins;
+
+mod auth;
+mod clients;
+mod imports;
+mod methods;
+mod origin;
+mod ratelimit;
+mod registry;
+mod rest;
+mod schema;
+mod session;
+mod ws;
+
+pub use auth::{ApiKeyStore, ApiToken, Caller, IssuedToken, Scope};
+pub use clients::{disconnect_client, list_clients, ClientEvent, ClientInfo, RecentCallers};
+pub use imports::ImportRoots;
+pub use ratelimit::RateLimiter;
+pub use registry::{MethodRegistry, RpcContext};
+pub use session::{ProgressReporter, Session, SessionManager};
+
+// Header names used by the MCP Streamable HTTP transport
+pub const SESSION_HEADER: &str = "mcp-session-id";
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+// The ids the server gave the messages of a POST, in order
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// How often sessions are checked for having gone idle
+const IDLE_SESSION_SWEEP: Duration = Duration::from_secs(60);
+
+// Newest first; the first entry is what we answer with when the client asks
+// for a version we don't know.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26"];
+
+// JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    // Names the request that failed, so a client can point at its log lines
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        match &mut self.data {
+            Some(Value::Object(data)) => {
+                data.insert("requestId".to_string(), json!(request_id));
+            }
+            None => self.data = Some(json!({ "requestId": request_id })),
+            Some(_) => {}
+        }
+        self
+    }
+}
+
+// Application errors keep their stable code and structured data on the wire
+impl From<McpError> for RpcError {
+    fn from(error: McpError) -> Self {
+        Self {
+            code: error.code(),
+            message: error.to_string(),
+            data: Some(error.data()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RpcResponse {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcState {
+    pub search_service: Arc<SearchService>,
+    pub add_queue: Arc<AddQueue>,
+    pub api_keys: Arc<ApiKeyStore>,
+    pub audit: Arc<AuditLog>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub import_roots: Arc<ImportRoots>,
+    pub sessions: Arc<SessionManager>,
+    pub callers: Arc<RecentCallers>,
+    pub fetcher: Arc<Fetcher>,
+    pub extraction: Arc<ExtractionRules>,
+    pub chunking: Arc<ChunkingRules>,
+    pub pages: Arc<PageStore>,
+    pub crawls: Arc<CrawlManager>,
+    pub schedules: Arc<Scheduler>,
+    pub feeds: Arc<FeedWatcher>,
+    pub staleness: Arc<StaleSweeper>,
+    pub releases: Arc<ReleaseWatcher>,
+    pub workspaces: Arc<Workspaces>,
+    pub embeddings: Arc<Embeddings>,
+    pub reranker: Arc<Reranker>,
+    pub assistant: Arc<Assistant>,
+    pub logging: Arc<Logging>,
+    pub jobs: Arc<JobQueue>,
+    // Bare mirrors of the remote Git repositories indexed, and their state
+    pub git_mirrors: PathBuf,
+    // Set once the index has been warmed up after opening
+    pub search_ready: Arc<OnceLock<SearchReady>>,
+    pub started: Instant,
+    client_events: broadcast::Sender<ClientEvent>,
+    pub methods: Arc<MethodRegistry>,
+}
+
+impl RpcState {
+    pub fn new(
+        search_service: Arc<SearchService>,
+        api_keys: Arc<ApiKeyStore>,
+        audit: Arc<AuditLog>,
+        fetcher: Arc<Fetcher>,
+        pages: Arc<PageStore>,
+        extraction: Arc<ExtractionRules>,
+        chunking: Arc<ChunkingRules>,
+        staleness: Arc<StaleSweeper>,
+        embeddings: Arc<Embeddings>,
+        reranker: Arc<Reranker>,
+        assistant: Arc<Assistant>,
+        logging: Arc<Logging>,
+        jobs: Arc<JobQueue>,
+        release_watch: ReleaseWatchSettings,
+        batching: BatchSettings,
+        config_dir: &Path,
+    ) -> Self {
+        let (client_events, _) = broadcast::channel(64);
+        let crawls = Arc::new(CrawlManager::new(
+            fetcher.clone(),
+            extraction.clone(),
+            chunking.clone(),
+            search_service.clone(),
+            pages.clone(),
+        ));
+        let schedules = Arc::new(Scheduler::load(config_dir.join("refresh_schedules.json"), crawls.clone()));
+        let feeds = Arc::new(FeedWatcher::load(
+            config_dir.join("watched_feeds.json"),
+            fetcher.clone(),
+            extraction.clone(),
+            chunking.clone(),
+            search_service.clone(),
+            pages.clone(),
+        ));
+        let releases = Arc::new(ReleaseWatcher::load(
+            config_dir.join("release_checks.json"),
+            fetcher.clone(),
+            crawls.clone(),
+            search_service.clone(),
+            release_watch,
+        ));
+        Self {
+            add_queue: Arc::new(AddQueue::new(search_service.clone(), batching)),
+            search_service,
+            api_keys,
+            audit,
+            rate_limiter: Arc::new(RateLimiter::default()),
+            import_roots: Arc::new(ImportRoots::default()),
+            sessions: Arc::new(SessionManager::new(client_events.clone())),
+            callers: Arc::new(RecentCallers::new(client_events.clone())),
+            fetcher,
+            extraction,
+            chunking,
+            pages,
+            crawls,
+            schedules,
+            feeds,
+            staleness,
+            releases,
+            workspaces: Arc::new(Workspaces::load(config_dir.join("workspaces.json"))),
+            embeddings,
+            reranker,
+            assistant,
+            logging,
+            jobs,
+            git_mirrors: config_dir.join("git"),
+            search_ready: Arc::new(OnceLock::new()),
+            started: Instant::now(),
+            client_events,
+            methods: Arc::new(methods::default_methods()),
+        }
+    }
+
+    // Clients connecting and disconnecting, for the UI
+    pub fn subscribe_clients(&self) -> broadcast::Receiver<ClientEvent> {
+        self.client_events.subscribe()
+    }
+}
+
+// Turns index changes into MCP notifications for every open session. Bursts
+// (e.g. a batch of adds) collapse into a single `list_changed`.
+pub async fn forward_index_events(state: RpcState) {
+    let mut events = state.search_service.subscribe();
+    loop {
+        let first = match events.recv().await {
+            // Clients hear about documents; a commit follows the events for
+            // its own, and a failed write adds or removes none
+            Ok(IndexEvent::Commit(_) | IndexEvent::Error(_)) => continue,
+            Ok(event) => Some(event),
+            Err(broadcast::error::RecvError::Lagged(_)) => None,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let mut changed_uris = HashSet::new();
+        let mut pending = first;
+        loop {
+            if let Some(IndexEvent::DocumentAdded { id, .. } | IndexEvent::DocumentDeleted { id }) = pending.take() {
+                changed_uris.insert(methods::document_uri(&id));
+            }
+            match events.try_recv() {
+                Ok(event) => pending = Some(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+
+        for session in state.sessions.all() {
+            session.notify("notifications/resources/list_changed", None);
+            let subscriptions = session.subscriptions.lock().unwrap().clone();
+            for uri in changed_uris.intersection(&subscriptions) {
+                session.notify("notifications/resources/updated", Some(json!({ "uri": uri })));
+            }
+        }
+    }
+}
+
+// Drops the sessions no request has come in on for longer than the server
+// settings allow, so abandoned ones don't hold their history forever
+pub async fn expire_idle_sessions(state: RpcState) {
+    let mut interval = tokio::time::interval(IDLE_SESSION_SWEEP);
+    loop {
+        interval.tick().await;
+        for id in state.sessions.remove_idle() {
+            info!("Session {} expired after going idle", id);
+        }
+    }
+}
+
+// Builds the router for one server run; `settings` supplies the allowed
+// browser origins and the request size limit.
+pub fn create_rpc_router(state: RpcState, settings: &ServerSettings) -> Router {
+    let origins = AllowedOrigins::new(&settings.allowed_origins);
+    // POST carries client-to-server messages, GET opens the SSE stream for
+    // server-to-client messages and DELETE ends the session.
+    Router::new()
+        .route("/rpc", post(handle_post).get(handle_get).delete(handle_delete))
+        .route("/rpc/schema", get(schema::handle_schema))
+        .route("/ws", get(ws::handle_upgrade))
+        .route("/documents", post(rest::add_documents))
+        .route("/documents/{id}", get(rest::get_document).delete(rest::delete_document))
+        .route("/search", get(rest::search))
+        // Applies to request bodies; `/ws` reads the same limit for messages
+        .layer(DefaultBodyLimit::max(settings.max_request_bytes))
+        .layer(Extension(RequestLimit(settings.max_request_bytes)))
+        .layer(middleware::from_fn_with_state(state.clone(), ratelimit::limit_requests))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::require_api_key))
+        .layer(middleware::from_fn_with_state(origins.clone(), origin::check_origin))
+        // Added after the checks above so supervisors can probe without a key
+        .route("/healthz", get(handle_healthz))
+        .route("/readyz", get(handle_readyz))
+        // Outermost, so CORS preflights are answered without a key
+        .layer(origins.cors_layer())
+        .with_state(state)
+}
+
+// Largest request body (or WebSocket message) accepted, in bytes
+#[derive(Clone, Copy)]
+struct RequestLimit(usize);
+
+// Oversized or unreadable bodies get the usual error shape rather than
+// axum's plain-text rejection
+fn body_rejection(status: StatusCode, message: String) -> Response {
+    error_response(status, RpcError::from(McpError::validation(Some("body"), message)))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn error_response(status: StatusCode, error: RpcError) -> Response {
+    (status, Json(RpcResponse::failure(Value::Null, error))).into_response()
+}
+
+// Looks up the session named by the request headers. Requests without a
+// session id are served statelessly so plain JSON-RPC clients (curl, scripts)
+// keep working; an unknown id means the session expired and the client has
+// to initialize again. Another key's session looks unknown too.
+fn resolve_session(
+    state: &RpcState,
+    caller: &Caller,
+    headers: &HeaderMap,
+) -> Result<Option<Arc<Session>>, (StatusCode, RpcError)> {
+    if let Some(version) = header_str(headers, PROTOCOL_VERSION_HEADER) {
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                RpcError::new(INVALID_REQUEST, format!("Unsupported protocol version: {}", version)),
+            ));
+        }
+    }
+
+    match header_str(headers, SESSION_HEADER) {
+        None => Ok(None),
+        Some(id) => match state.sessions.get(id).filter(|session| session.belongs_to(caller)) {
+            Some(session) => Ok(Some(session)),
+            None => Err((StatusCode::NOT_FOUND, RpcError::new(INVALID_REQUEST, "Session not found"))),
+        },
+    }
+}
+
+async fn handle_post(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Response {
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return body_rejection(rejection.status(), rejection.body_text()),
+    };
+    let mut session = match resolve_session(&state, &caller, &headers) {
+        Ok(session) => session,
+        Err((status, error)) => return error_response(status, error),
+    };
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+            )
+        }
+    };
+
+    let reply = match process_payload(&state, &caller, &mut session, payload).await {
+        Ok(reply) => reply,
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, error),
+    };
+
+    let created_session = reply.created_session.clone();
+    let request_ids = reply.request_ids.join(", ");
+    let mut response = match reply.body() {
+        Some(body) => Json(body).into_response(),
+        // Notifications and responses alone get no body back
+        None => StatusCode::ACCEPTED.into_response(),
+    };
+
+    if let Some(id) = created_session {
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            response.headers_mut().insert(SESSION_HEADER, value);
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_ids) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+// What came out of one POST body or WebSocket frame
+struct Reply {
+    responses: Vec<RpcResponse>,
+    is_batch: bool,
+    created_session: Option<String>,
+    // One per message, whether or not it was answered
+    request_ids: Vec<String>,
+}
+
+impl Reply {
+    // The JSON to send back, or None when the payload held no requests
+    fn body(mut self) -> Option<Value> {
+        if self.responses.is_empty() {
+            return None;
+        }
+        let body = if self.is_batch {
+            serde_json::to_value(&self.responses)
+        } else {
+            serde_json::to_value(self.responses.remove(0))
+        };
+        // Serializing our own response types can't fail
+        body.ok()
+    }
+}
+
+// Runs a single message or a batch through the dispatcher. `session` is
+// updated in place when an `initialize` creates a new one.
+async fn process_payload(
+    state: &RpcState,
+    caller: &Caller,
+    session: &mut Option<Arc<Session>>,
+    payload: Value,
+) -> Result<Reply, RpcError> {
+    let (messages, is_batch) = match payload {
+        Value::Array(messages) if !messages.is_empty() => (messages, true),
+        Value::Array(_) => return Err(RpcError::new(INVALID_REQUEST, "Empty batch")),
+        message => (vec![message], false),
+    };
+
+    let mut created_session = None;
+    let mut responses = Vec::new();
+    let mut request_ids = Vec::new();
+    for message in messages {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let response = handle_message(state, caller, session, &mut created_session, &request_id, message).await;
+        responses.extend(response);
+        request_ids.push(request_id);
+    }
+
+    Ok(Reply {
+        responses,
+        is_batch,
+        created_session,
+        request_ids,
+    })
+}
+
+// Handles one JSON-RPC message, returning a response only for requests.
+async fn handle_message(
+    state: &RpcState,
+    caller: &Caller,
+    session: &mut Option<Arc<Session>>,
+    created_session: &mut Option<String>,
+    request_id: &str,
+    message: Value,
+) -> Option<RpcResponse> {
+    let Value::Object(mut message) = message else {
+        return Some(RpcResponse::failure(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Invalid request").with_request_id(request_id),
+        ));
+    };
+
+    let id = message.remove("id");
+    let method = match message.remove("method") {
+        Some(Value::String(method)) => method,
+        // Reply to one of our own requests
+        None if id.is_some() => {
+            if let (Some(session), Some(id)) = (session.as_ref(), id.as_ref()) {
+                let reply = match message.remove("error") {
+                    Some(error) => Err(serde_json::from_value(error)
+                        .unwrap_or_else(|_| RpcError::new(INTERNAL_ERROR, "Malformed error from client"))),
+                    None => Ok(message.remove("result").unwrap_or(Value::Null)),
+                };
+                session.resolve_request(id, reply);
+            }
+            return None;
+        }
+        _ => {
+            return Some(RpcResponse::failure(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Invalid request").with_request_id(request_id),
+            ))
+        }
+    };
+    let params = message.remove("params").unwrap_or(Value::Null);
+    // Tool calls are logged under the tool's name, that's what an agent actually did
+    let audited_method = match (method.as_str(), params.get("name").and_then(Value::as_str)) {
+        ("tools/call", Some(tool)) => format!("tools/call {}", tool),
+        _ => method.clone(),
+    };
+    // Whatever is logged while handling the message, down to the index
+    // operations it runs, is tagged with its request id
+    let span = info_span!(
+        "rpc",
+        request_id = %request_id,
+        method = %audited_method,
+        caller = %caller.name,
+        session = session.as_ref().map(|session| session.id.as_str()),
+    );
+
+    // Notifications (no id) never get a response
+    let Some(id) = id else {
+        span.in_scope(|| debug!("RPC notification: {}", method));
+        if let Some(session) = session.as_ref() {
+            let roots_changed = method == "notifications/initialized" || method == "notifications/roots/list_changed";
+            if roots_changed && session.supports("roots") {
+                tokio::spawn(session::refresh_roots(session.clone()).instrument(span));
+            }
+        }
+        return None;
+    };
+
+    if method == "initialize" {
+        let requested = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let version = if SUPPORTED_PROTOCOL_VERSIONS.contains(&requested) {
+            requested
+        } else {
+            SUPPORTED_PROTOCOL_VERSIONS[0]
+        };
+        let client_capabilities = params.get("capabilities").cloned().unwrap_or_else(|| json!({}));
+        let client_info = params.get("clientInfo").cloned().unwrap_or(Value::Null);
+        match session.as_ref() {
+            // WebSocket connections already have their session
+            Some(existing) => existing.initialize(version.to_string(), client_capabilities, client_info),
+            None => {
+                let new_session = state.sessions.create(
+                    "streamableHttp",
+                    caller,
+                    version.to_string(),
+                    client_capabilities,
+                    client_info,
+                );
+                *created_session = Some(new_session.id.clone());
+                *session = Some(new_session);
+            }
+        }
+        if let Some(session) = session.as_ref() {
+            span.record("session", session.id.as_str());
+            span.in_scope(|| info!("MCP session initialized: {}", session.id));
+        }
+    }
+
+    span.in_scope(|| debug!("RPC request: {}", method));
+    if let Some(session) = session.as_ref() {
+        session.touch();
+    }
+    let ctx = RpcContext {
+        state: state.clone(),
+        session: session.clone(),
+        progress: ProgressReporter::from_params(session.as_ref(), &params),
+        caller: caller.clone(),
+        request_id: request_id.to_string(),
+    };
+    let audit = state
+        .audit
+        .begin("rpc", &caller.name, &audited_method, &params)
+        .with_request_id(request_id);
+    let result = state.methods.call(ctx, &method, params).instrument(span.clone()).await;
+    if let Err(error) = &result {
+        span.in_scope(|| debug!("RPC request failed: {}", error.message));
+    }
+    audit.finish(result.as_ref().err().map(|error| error.code));
+    Some(match result {
+        Ok(result) => RpcResponse::success(id, result),
+        Err(error) => RpcResponse::failure(id, error.with_request_id(request_id)),
+    })
+}
+
+// The process is up and serving requests
+async fn handle_healthz() -> Response {
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+// 200 once the server can take work, 503 with what's missing until then
+async fn handle_readyz(State(state): State<RpcState>) -> Response {
+    let health = health::health(&state);
+    let status = if health.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(health)).into_response()
+}
+
+async fn handle_get(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Response {
+    let accepts_sse = header_str(&headers, header::ACCEPT.as_str())
+        .map(|accept| accept.contains("text/event-stream"))
+        .unwrap_or(false);
+    if !accepts_sse {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+
+    let session = match resolve_session(&state, &caller, &headers) {
+        Ok(Some(session)) => session,
+        Ok(None) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                RpcError::new(INVALID_REQUEST, "Missing Mcp-Session-Id header"),
+            )
+        }
+        Err((status, error)) => return error_response(status, error),
+    };
+
+    let last_event_id = header_str(&headers, LAST_EVENT_ID_HEADER).and_then(|id| id.parse::<u64>().ok());
+    let (backlog, receiver) = session.subscribe(last_event_id);
+
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                // Slow consumer; skipped events can still be replayed on reconnect
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    // Ends when the session is removed, e.g. disconnected from the UI
+    let closed = async move { session.closed().await };
+    let events = stream::iter(backlog).chain(live).take_until(closed).map(|(event_id, message)| {
+        Ok::<_, Infallible>(
+            Event::default()
+                .id(event_id.to_string())
+                .event("message")
+                .data(message.to_string()),
+        )
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn handle_delete(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(id) = header_str(&headers, SESSION_HEADER) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if !state.sessions.get(id).is_some_and(|session| session.belongs_to(&caller)) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match state.sessions.remove(id) {
+        Some(_) => {
+            info!("MCP session terminated: {}", id);
+            StatusCode::OK.into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
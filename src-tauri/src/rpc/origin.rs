@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::warn;
+
+use super::auth::API_KEY_HEADER;
+use super::{
+    error_response, header_str, RpcError, LAST_EVENT_ID_HEADER, PROTOCOL_VERSION_HEADER, REQUEST_ID_HEADER,
+    SESSION_HEADER,
+};
+use crate::McpError;
+
+// Origins from `server.allowed_origins`; `*` allows every origin
+#[derive(Clone)]
+pub(super) struct AllowedOrigins(Arc<Vec<String>>);
+
+impl AllowedOrigins {
+    pub(super) fn new(origins: &[String]) -> Self {
+        Self(Arc::new(origins.to_vec()))
+    }
+
+    fn allows_any(&self) -> bool {
+        self.0.iter().any(|origin| origin == "*")
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allows_any() || self.0.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    pub(super) fn cors_layer(&self) -> CorsLayer {
+        let allow_origin = if self.allows_any() {
+            AllowOrigin::any()
+        } else {
+            let origins: Vec<HeaderValue> = self
+                .0
+                .iter()
+                .filter_map(|origin| match HeaderValue::from_str(origin) {
+                    Ok(value) => Some(value),
+                    Err(_) => {
+                        warn!("Ignoring invalid allowed origin: {:?}", origin);
+                        None
+                    }
+                })
+                .collect();
+            AllowOrigin::list(origins)
+        };
+
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                HeaderName::from_static(API_KEY_HEADER),
+                HeaderName::from_static(SESSION_HEADER),
+                HeaderName::from_static(PROTOCOL_VERSION_HEADER),
+                HeaderName::from_static(LAST_EVENT_ID_HEADER),
+            ])
+            .expose_headers([HeaderName::from_static(SESSION_HEADER), HeaderName::from_static(REQUEST_ID_HEADER)])
+    }
+}
+
+// CORS only stops a browser from reading the response; the request itself
+// (a simple POST, a WebSocket handshake) still reaches us. Refuse any
+// browser request from an origin that isn't allowed, which also blocks DNS
+// rebinding. Requests without an `Origin` header (curl, editors) pass.
+pub(super) async fn check_origin(State(allowed): State<AllowedOrigins>, request: Request, next: Next) -> Response {
+    match header_str(request.headers(), header::ORIGIN.as_str()) {
+        Some(origin) if !allowed.allows(origin) => {
+            let error = McpError::validation(Some("origin"), format!("Origin {} is not allowed", origin));
+            error_response(StatusCode::FORBIDDEN, RpcError::from(error))
+        }
+        _ => next.run(request).await,
+    }
+}
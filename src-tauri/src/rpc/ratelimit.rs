@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use super::auth::Caller;
+use super::{error_response, RpcError, RpcState};
+use crate::settings::RateLimitSettings;
+use crate::McpError;
+
+// Idle buckets are only swept once there are this many
+const BUCKET_SWEEP_THRESHOLD: usize = 1024;
+
+// The longest a throttled client is told to wait, whatever the rate
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(3600);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// Token bucket per client (API key): `burst` requests at once, refilled at
+// `requests_per_second`. Reconfigured whenever the server (re)starts.
+pub struct RateLimiter {
+    settings: RwLock<RateLimitSettings>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            settings: RwLock::new(RateLimitSettings::default()),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn configure(&self, settings: &RateLimitSettings) {
+        *self.settings.write().unwrap() = settings.clone();
+        self.buckets.lock().unwrap().clear();
+    }
+
+    // Takes one token from `client`'s bucket, or says how long until one is
+    // available
+    pub fn check(&self, client: &str) -> Result<(), McpError> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, now: Instant) -> Result<(), McpError> {
+        let settings = self.settings.read().unwrap().clone();
+        if !settings.enabled {
+            return Ok(());
+        }
+        let burst = f64::from(settings.burst.max(1));
+        let rate = settings.requests_per_second.max(f64::MIN_POSITIVE);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= BUCKET_SWEEP_THRESHOLD {
+            // A bucket that has refilled completely carries no state
+            buckets.retain(|_, bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst);
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = Duration::try_from_secs_f64((1.0 - bucket.tokens) / rate).unwrap_or(MAX_RETRY_AFTER);
+            Err(McpError::rate_limited(wait.min(MAX_RETRY_AFTER)))
+        }
+    }
+}
+
+// Runs after authentication, so every request has a caller to charge
+pub(super) async fn limit_requests(State(state): State<RpcState>, request: Request, next: Next) -> Response {
+    let client = request
+        .extensions()
+        .get::<Caller>()
+        .map(|caller| caller.key_id.clone())
+        .unwrap_or_default();
+
+    match state.rate_limiter.check(&client) {
+        Ok(()) => next.run(request).await,
+        Err(error) => {
+            let retry_after = retry_after_secs(&error);
+            let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, RpcError::from(error));
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
+
+// Retry-After counts whole seconds; rounded up, so a client that waits that
+// long finds a token
+fn retry_after_secs(error: &McpError) -> u64 {
+    match error {
+        McpError::RateLimited { retry_after_ms } => retry_after_ms.div_ceil(1000).max(1),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(requests_per_second: f64, burst: u32) -> RateLimiter {
+        let limiter = RateLimiter::default();
+        limiter.configure(&RateLimitSettings {
+            enabled: true,
+            requests_per_second,
+            burst,
+        });
+        limiter
+    }
+
+    fn retry_after_ms(result: Result<(), McpError>) -> u64 {
+        match result {
+            Err(McpError::RateLimited { retry_after_ms }) => retry_after_ms,
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn burst_then_refill() {
+        let limiter = configured(2.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", start).is_ok());
+        }
+        assert_eq!(retry_after_ms(limiter.check_at("a", start)), 500);
+        // Clients have buckets of their own
+        assert!(limiter.check_at("b", start).is_ok());
+
+        // Half a second brings back one token, and only one
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at("a", later).is_ok());
+        assert_eq!(retry_after_ms(limiter.check_at("a", later)), 500);
+
+        // A long wait refills no more than the burst
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", much_later).is_ok());
+        }
+        assert!(limiter.check_at("a", much_later).is_err());
+    }
+
+    #[test]
+    fn retry_after_is_how_long_until_a_token() {
+        let limiter = configured(0.25, 1);
+        let start = Instant::now();
+        assert!(limiter.check_at("a", start).is_ok());
+        let error = limiter.check_at("a", start + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(retry_after_secs(&error), 3);
+        assert_eq!(retry_after_ms(Err(error)), 3000);
+
+        // Very slow rates still get a bounded wait
+        let limiter = configured(1e-9, 1);
+        assert!(limiter.check_at("a", start).is_ok());
+        assert_eq!(retry_after_ms(limiter.check_at("a", start)), MAX_RETRY_AFTER.as_millis() as u64);
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        assert_eq!(retry_after_secs(&McpError::rate_limited(Duration::from_millis(1))), 1);
+        assert_eq!(retry_after_secs(&McpError::rate_limited(Duration::from_millis(1000))), 1);
+        assert_eq!(retry_after_secs(&McpError::rate_limited(Duration::from_millis(1001))), 2);
+        assert_eq!(retry_after_secs(&McpError::rate_limited(Duration::ZERO)), 1);
+        assert_eq!(retry_after_secs(&McpError::internal("boom")), 1);
+    }
+
+    #[test]
+    fn disabled_never_limits() {
+        let limiter = RateLimiter::default();
+        limiter.configure(&RateLimitSettings {
+            enabled: false,
+            requests_per_second: 1.0,
+            burst: 1,
+        });
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.check_at("a", now).is_ok());
+        }
+    }
+}
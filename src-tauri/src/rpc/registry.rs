@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::auth::{Caller, Scope};
+use super::session::{ProgressReporter, Session};
+use super::{RpcError, RpcState, INTERNAL_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND};
+
+// Everything a method handler gets besides its params. Owned so handlers can
+// be plain `async fn`s without borrowing from the transport.
+pub struct RpcContext {
+    pub state: RpcState,
+    pub session: Option<Arc<Session>>,
+    pub progress: ProgressReporter,
+    pub caller: Caller,
+    // The id this message's log lines and errors carry
+    pub request_id: String,
+}
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+type Handler = Box<dyn Fn(RpcContext, Value) -> HandlerFuture + Send + Sync>;
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+struct Method {
+    handler: Handler,
+    scope: Scope,
+    params_schema: SchemaFn,
+    result_schema: SchemaFn,
+}
+
+// Maps JSON-RPC method names to typed handlers. Params are deserialized into
+// the handler's argument type and results serialized back with serde, so a
+// method is just
+//
+//     registry.register("ping", Scope::Read, ping);
+//
+// where `ping` is `async fn(RpcContext, PingParams) -> Result<PingResponse, RpcError>`
+// (or any error that converts into `RpcError`, such as `McpError`).
+// The same types also describe the method in the published schema. Callers
+// whose key lacks `scope` get a `forbidden` error without the handler running.
+#[derive(Default)]
+pub struct MethodRegistry {
+    methods: HashMap<String, Method>,
+}
+
+impl MethodRegistry {
+    pub fn register<P, R, E, F, Fut>(&mut self, name: &str, scope: Scope, handler: F) -> &mut Self
+    where
+        P: DeserializeOwned + JsonSchema + Send + 'static,
+        R: Serialize + JsonSchema + 'static,
+        E: Into<RpcError> + 'static,
+        F: Fn(RpcContext, P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let handler: Handler = Box::new(move |ctx, params| match parse_params::<P>(params) {
+            Ok(params) => {
+                let call = handler(ctx, params);
+                Box::pin(async move { to_value(call.await.map_err(Into::into)?) })
+            }
+            Err(error) => Box::pin(std::future::ready(Err(error))),
+        });
+        let method = Method {
+            handler,
+            scope,
+            // Params are described inline so their fields can be listed one by one
+            params_schema: <P as JsonSchema>::json_schema,
+            result_schema: SchemaGenerator::subschema_for::<R>,
+        };
+        self.methods.insert(name.to_string(), method);
+        self
+    }
+
+    // Method names with their params and result schemas, sorted by name
+    pub fn describe(&self, generator: &mut SchemaGenerator) -> Vec<(String, Schema, Schema)> {
+        let mut names: Vec<&String> = self.methods.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let method = &self.methods[name];
+                let params = (method.params_schema)(generator);
+                let result = (method.result_schema)(generator);
+                (name.clone(), params, result)
+            })
+            .collect()
+    }
+
+    pub async fn call(&self, ctx: RpcContext, method: &str, params: Value) -> Result<Value, RpcError> {
+        let entry = self
+            .methods
+            .get(method)
+            .ok_or_else(|| RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method)))?;
+        ctx.caller.require(entry.scope)?;
+        (entry.handler)(ctx, params).await
+    }
+}
+
+pub fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // Treat missing params as an empty object so defaults can apply
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+pub fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
@@ -0,0 +1,262 @@
+use std::time::Instant;
+
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::auth::{Caller, Scope};
+use super::RpcState;
+use crate::search::{project_document, SearchOptions, SearchSort, SearchableDocument};
+use crate::validation::{
+    validate_documents, validate_facet, validate_fields, validate_freshness, validate_search, validate_tag_filters,
+    validate_version_filter, validate_version_range,
+};
+use crate::{elapsed_ms, McpError, ResponseTimings, SearchResponse};
+
+// Plain REST routes over the same SearchService as the JSON-RPC methods, for
+// scripts that would rather not build JSON-RPC envelopes. Errors come back as
+// `{ "error": { "code", "message", "data" } }` with a matching HTTP status.
+
+fn rest_error(error: McpError) -> Response {
+    let status = match error {
+        McpError::NotFound { .. } => StatusCode::NOT_FOUND,
+        McpError::Validation { .. } | McpError::QueryParse { .. } => StatusCode::BAD_REQUEST,
+        McpError::Unsupported { .. } => StatusCode::NOT_IMPLEMENTED,
+        McpError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+        McpError::Forbidden { .. } => StatusCode::FORBIDDEN,
+        McpError::Fetch { .. } => StatusCode::BAD_GATEWAY,
+        McpError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        McpError::Index { .. } | McpError::Server { .. } | McpError::Internal { .. } => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+// Bodies or query strings axum couldn't extract (too large, malformed)
+fn rejection_error(status: StatusCode, message: String) -> Response {
+    let error = McpError::validation(None, message);
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+#[derive(Deserialize, Debug)]
+pub(super) struct DocumentQuery {
+    // Comma-separated, e.g. `fields=id,title,url`
+    fields: Option<String>,
+}
+
+pub(super) async fn get_document(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    query: Result<Query<DocumentQuery>, QueryRejection>,
+) -> Response {
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(rejection) => return rejection_error(rejection.status(), rejection.body_text()),
+    };
+    let audit = state.audit.begin("rest", &caller.name, "GET /documents/{id}", &id);
+    let fields = comma_list(query.fields.as_deref());
+    if let Err(error) = validate_fields(&fields, false) {
+        return audit.record(Err(error)).unwrap_or_else(rest_error);
+    }
+    let lookup = id.clone();
+    let result = match state.search_service.blocking(move |service| service.get_document(&lookup)).await {
+        Ok(Some(document)) => project_document(document, None, &fields)
+            .map(|document| Json(document).into_response())
+            .map_err(|e| McpError::internal(format!("Failed to serialize document: {}", e))),
+        Ok(None) => Err(McpError::not_found(format!("Document {}", id))),
+        Err(e) => Err(McpError::index("Failed to read document", e)),
+    };
+    audit.record(result).unwrap_or_else(rest_error)
+}
+
+// `POST /documents` takes a single document or an array of them
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub(super) enum DocumentsBody {
+    One(SearchableDocument),
+    Many(Vec<SearchableDocument>),
+}
+
+pub(super) async fn add_documents(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    body: Result<Json<DocumentsBody>, JsonRejection>,
+) -> Response {
+    let body = match body {
+        Ok(Json(body)) => body,
+        Err(rejection) => return rejection_error(rejection.status(), rejection.body_text()),
+    };
+    let audit = state.audit.begin("rest", &caller.name, "POST /documents", &body);
+    let documents = match body {
+        DocumentsBody::One(document) => vec![document],
+        DocumentsBody::Many(documents) => documents,
+    };
+    if let Err(error) = caller.require(Scope::Write).and_then(|()| validate_documents(&documents)) {
+        return audit.record(Err(error)).unwrap_or_else(rest_error);
+    }
+    let ids: Vec<String> = documents.iter().map(|d| d.id.clone()).collect();
+    let result = state
+        .search_service
+        .blocking(move |service| service.add_documents(documents, |_, _| {}))
+        .await
+        .map(|()| (StatusCode::CREATED, Json(json!({ "ids": ids }))).into_response())
+        .map_err(|e| McpError::index("Failed to add documents", e));
+    audit.record(result).unwrap_or_else(rest_error)
+}
+
+pub(super) async fn delete_document(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Response {
+    let audit = state.audit.begin("rest", &caller.name, "DELETE /documents/{id}", &id);
+    if let Err(error) = caller.require(Scope::Write) {
+        return audit.record(Err(error)).unwrap_or_else(rest_error);
+    }
+    let target = id.clone();
+    let result = match state
+        .search_service
+        .blocking(move |service| service.delete_document(&target))
+        .await
+    {
+        Ok(true) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(false) => Err(McpError::not_found(format!("Document {}", id))),
+        Err(e) => Err(McpError::index("Failed to delete document", e)),
+    };
+    audit.record(result).unwrap_or_else(rest_error)
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(super) struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+    // Comma-separated, e.g. `tags_any=async,tutorial`
+    tags_any: Option<String>,
+    tags_all: Option<String>,
+    sort: Option<SearchSort>,
+    freshness_half_life_days: Option<u32>,
+    // e.g. `facet=/rust/tokio`
+    facet: Option<String>,
+    #[serde(default)]
+    near_duplicates: bool,
+    // e.g. `version_range=>=1.4,<2.0`, URL-encoded
+    version_range: Option<String>,
+    #[serde(default)]
+    highest_version: bool,
+    #[serde(default)]
+    latest_only: bool,
+    // A version or an alias, e.g. `version=stable`
+    version: Option<String>,
+    // A workspace whose pinned versions to hold the search to
+    workspace: Option<String>,
+    #[serde(default)]
+    include_archived: bool,
+    #[serde(default)]
+    semantic: bool,
+    #[serde(default)]
+    rerank: bool,
+    // Comma-separated too, e.g. `fields=id,title,url,snippet`
+    fields: Option<String>,
+    #[serde(default)]
+    include_timings: bool,
+}
+
+fn comma_list(items: Option<&str>) -> Vec<String> {
+    items
+        .into_iter()
+        .flat_map(|items| items.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub(super) async fn search(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    query: Result<Query<SearchQuery>, QueryRejection>,
+) -> Response {
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(rejection) => return rejection_error(rejection.status(), rejection.body_text()),
+    };
+    let audit = state.audit.begin("rest", &caller.name, "GET /search", &query);
+    let started = Instant::now();
+    let mut timings = ResponseTimings::default();
+    let limit = query.limit.unwrap_or(10);
+    let options = SearchOptions {
+        tags_any: comma_list(query.tags_any.as_deref()),
+        tags_all: comma_list(query.tags_all.as_deref()),
+        sort: query.sort.unwrap_or_default(),
+        freshness_half_life_days: query.freshness_half_life_days,
+        facet: query.facet.clone(),
+        keep_near_duplicates: query.near_duplicates,
+        version_range: query.version_range.clone(),
+        highest_version: query.highest_version,
+        latest_only: query.latest_only,
+        version: query.version.clone(),
+        include_archived: query.include_archived,
+        ..Default::default()
+    };
+    let fields = comma_list(query.fields.as_deref());
+    let checked = validate_search(&query.q, limit)
+        .and_then(|()| validate_tag_filters(&options.tags_any, &options.tags_all))
+        .and_then(|()| validate_freshness(options.freshness_half_life_days))
+        .and_then(|()| validate_facet(options.facet.as_deref()))
+        .and_then(|()| validate_version_range(options.version_range.as_deref()))
+        .and_then(|()| validate_version_filter(options.version.as_deref()))
+        .and_then(|()| validate_fields(&fields, true));
+    let embedding = Instant::now();
+    let query_vector = match (&checked, query.semantic) {
+        (Ok(()), true) => state.embeddings.query_vector(&query.q).await.map(Some),
+        _ => Ok(None),
+    };
+    timings.parse_ms += elapsed_ms(embedding);
+    let options = checked.and_then(|()| query_vector).and_then(|query_vector| {
+        let mut options = options;
+        options.query_vector = query_vector;
+        options.pinned_versions = state.workspaces.pinned_versions(query.workspace.as_deref())?;
+        Ok(options)
+    });
+    let wanted = if query.rerank { state.reranker.candidates(limit) } else { limit };
+    let documents = match options {
+        Ok(options) => state
+            .search_service
+            .search_timed(&query.q, wanted, &options)
+            .await
+            .map(|(documents, search_timings)| {
+                timings.add_search(search_timings);
+                documents
+            })
+            .map_err(|e| McpError::search(&query.q, e)),
+        Err(e) => Err(e),
+    };
+    let reranking = Instant::now();
+    let documents = match documents {
+        Ok(documents) if query.rerank => state.reranker.rerank(&query.q, documents, limit).await,
+        documents => documents,
+    };
+    timings.search_ms += elapsed_ms(reranking);
+    let selecting = Instant::now();
+    let result = match documents {
+        Ok(documents) => state
+            .search_service
+            .select(&query.q, documents, &fields)
+            .await
+            .map(|documents| {
+                timings.serialize_ms = elapsed_ms(selecting);
+                timings.total_ms = elapsed_ms(started);
+                let timings = query.include_timings.then_some(timings);
+                Json(SearchResponse { documents, timings }).into_response()
+            })
+            .map_err(|e| McpError::search(&query.q, e)),
+        Err(e) => Err(e),
+    };
+    audit.record(result).unwrap_or_else(rest_error)
+}
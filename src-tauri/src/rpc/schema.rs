@@ -0,0 +1,91 @@
+use axum::extract::State;
+use axum::Json;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use super::RpcState;
+use crate::search::SearchableDocument;
+
+// OpenRPC spec version the document at `/rpc/schema` follows
+const OPEN_RPC_VERSION: &str = "1.2.6";
+
+// A JSON Schema for `T` with every nested type inlined, as MCP tool
+// `inputSchema`s have no place to put shared definitions.
+pub(super) fn inline_schema<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    let root = generator.into_root_schema_for::<T>();
+    serde_json::to_value(root.schema).unwrap_or(Value::Bool(true))
+}
+
+// Builds the OpenRPC document from the registered methods. Params are listed
+// by name, one content descriptor per field; shared types such as
+// `SearchableDocument` land in `components.schemas`.
+pub(super) fn open_rpc_document(state: &RpcState) -> Value {
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| settings.definitions_path = "#/components/schemas/".to_string())
+        .into_generator();
+    generator.subschema_for::<SearchableDocument>();
+
+    let methods: Vec<Value> = state
+        .methods
+        .describe(&mut generator)
+        .into_iter()
+        .map(|(name, params, result)| {
+            let params = serde_json::to_value(params).unwrap_or(Value::Bool(true));
+            let result = serde_json::to_value(result).unwrap_or(Value::Bool(true));
+            json!({
+                "name": name,
+                "paramStructure": "by-name",
+                "params": param_descriptors(&params),
+                "result": { "name": "result", "schema": result }
+            })
+        })
+        .collect();
+
+    json!({
+        "openrpc": OPEN_RPC_VERSION,
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "methods": methods,
+        "components": { "schemas": take_definitions(&mut generator) }
+    })
+}
+
+fn param_descriptors(params: &Value) -> Vec<Value> {
+    let Some(properties) = params.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let required: Vec<&str> = params
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    properties
+        .iter()
+        .map(|(name, schema)| {
+            json!({
+                "name": name,
+                "required": required.contains(&name.as_str()),
+                "schema": schema
+            })
+        })
+        .collect()
+}
+
+fn take_definitions(generator: &mut SchemaGenerator) -> Map<String, Value> {
+    generator
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap_or(Value::Bool(true))))
+        .collect()
+}
+
+pub(super) async fn handle_schema(State(state): State<RpcState>) -> Json<Value> {
+    Json(open_rpc_document(&state))
+}
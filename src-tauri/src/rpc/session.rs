@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, oneshot, watch};
+use tracing::{error, info, warn};
+
+use super::auth::Caller;
+use super::clients::{ClientEvent, ClientInfo};
+use super::{RpcError, INTERNAL_ERROR};
+use crate::roots::{self, Root};
+use crate::search::{PreferredVersion, SearchOptions};
+use crate::settings::DEFAULT_SESSION_IDLE_MINUTES;
+use crate::util::now_millis;
+
+// How many server-to-client messages a session keeps for replay when a
+// client reconnects with `Last-Event-ID`.
+const SESSION_HISTORY_LIMIT: usize = 256;
+
+// A server-to-client message tagged with its SSE event id
+pub(super) type SessionEvent = (u64, Value);
+
+// How long a server-to-client request (e.g. `roots/list`) waits for the
+// client's reply before giving up.
+const CLIENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+type PendingReply = oneshot::Sender<Result<Value, RpcError>>;
+
+// A single MCP session. Server-to-client messages are numbered so that a
+// client whose SSE stream dropped can resume from the last event it saw.
+pub struct Session {
+    pub id: String,
+    // "streamableHttp" or "websocket"
+    pub transport: &'static str,
+    // Name of the API key the session was opened with
+    pub caller: String,
+    // Fingerprint of that key; only it may use the session afterwards
+    key_id: String,
+    // Milliseconds since the Unix epoch
+    pub connected_at: u64,
+    last_seen: AtomicU64,
+    requests: AtomicU64,
+    // `clientInfo` from `initialize`
+    client_info: Mutex<Value>,
+    // Set when the session is removed, so its open streams end
+    closed: watch::Sender<bool>,
+    // What the client sent in `initialize`. A WebSocket connection gets its
+    // session before the client initializes, so these can change later.
+    protocol_version: Mutex<String>,
+    client_capabilities: Mutex<Value>,
+    next_event_id: AtomicU64,
+    history: Mutex<VecDeque<SessionEvent>>,
+    sender: broadcast::Sender<SessionEvent>,
+    // Set once a client has opened its first SSE stream
+    streamed: AtomicBool,
+    // Resource URIs the client asked to hear `resources/updated` about
+    pub(super) subscriptions: Mutex<HashSet<String>>,
+    // Server-to-client requests waiting for the client's reply, by request id
+    next_request_id: AtomicU64,
+    pending_requests: Mutex<HashMap<String, PendingReply>>,
+    // Derived from the client's roots, applied to searches made in this session
+    preferred_versions: Mutex<Vec<PreferredVersion>>,
+}
+
+impl Session {
+    fn new(
+        transport: &'static str,
+        caller: &Caller,
+        protocol_version: String,
+        client_capabilities: Value,
+        client_info: Value,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(SESSION_HISTORY_LIMIT);
+        let now = now_millis();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            transport,
+            caller: caller.name.clone(),
+            key_id: caller.key_id.clone(),
+            connected_at: now,
+            last_seen: AtomicU64::new(now),
+            requests: AtomicU64::new(0),
+            client_info: Mutex::new(client_info),
+            closed: watch::Sender::new(false),
+            protocol_version: Mutex::new(protocol_version),
+            client_capabilities: Mutex::new(client_capabilities),
+            next_event_id: AtomicU64::new(0),
+            history: Mutex::new(VecDeque::new()),
+            sender,
+            streamed: AtomicBool::new(false),
+            subscriptions: Mutex::new(HashSet::new()),
+            next_request_id: AtomicU64::new(0),
+            pending_requests: Mutex::new(HashMap::new()),
+            preferred_versions: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn initialize(&self, protocol_version: String, client_capabilities: Value, client_info: Value) {
+        *self.protocol_version.lock().unwrap() = protocol_version;
+        *self.client_capabilities.lock().unwrap() = client_capabilities;
+        *self.client_info.lock().unwrap() = client_info;
+    }
+
+    // Counts a request from the client
+    pub fn touch(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.last_seen.store(now_millis(), Ordering::Relaxed);
+    }
+
+    // Whether the caller opened the session. Ids travel in headers and
+    // logs, so knowing one isn't enough to use it.
+    pub fn belongs_to(&self, caller: &Caller) -> bool {
+        self.key_id == caller.key_id
+    }
+
+    pub fn info(&self) -> ClientInfo {
+        let client_info = self.client_info.lock().unwrap();
+        let field = |name: &str| client_info.get(name).and_then(Value::as_str).map(String::from);
+        ClientInfo {
+            id: self.id.clone(),
+            transport: self.transport.to_string(),
+            caller: self.caller.clone(),
+            client_name: field("name"),
+            client_version: field("version"),
+            connected_at: self.connected_at,
+            last_seen: self.last_seen.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+        }
+    }
+
+    fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    // Resolves once the session has been removed
+    pub async fn closed(&self) {
+        let mut closed = self.closed.subscribe();
+        let _ = closed.wait_for(|closed| *closed).await;
+    }
+
+    pub fn protocol_version(&self) -> String {
+        self.protocol_version.lock().unwrap().clone()
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.client_capabilities.lock().unwrap().get(capability).is_some()
+    }
+
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            preferred_versions: self.preferred_versions.lock().unwrap().clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Send a request to the client over the SSE stream and wait for the
+    /// reply it POSTs back.
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value, RpcError> {
+        let request_id = format!("srv-{}", self.next_request_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        self.pending_requests
+            .lock()
+            .unwrap()
+            .insert(request_id.clone(), reply_sender);
+
+        let mut message = json!({ "jsonrpc": "2.0", "id": request_id, "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        self.send(message);
+
+        match tokio::time::timeout(CLIENT_REQUEST_TIMEOUT, reply_receiver).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => Err(RpcError::new(INTERNAL_ERROR, "Session closed before the client replied")),
+            Err(_) => {
+                self.pending_requests.lock().unwrap().remove(&request_id);
+                Err(RpcError::new(INTERNAL_ERROR, format!("Client did not answer {} in time", method)))
+            }
+        }
+    }
+
+    // Hands a client's reply to whoever is waiting in `request`
+    pub(super) fn resolve_request(&self, id: &Value, reply: Result<Value, RpcError>) {
+        let key = match id {
+            Value::String(id) => id.clone(),
+            other => other.to_string(),
+        };
+        match self.pending_requests.lock().unwrap().remove(&key) {
+            Some(waiter) => {
+                let _ = waiter.send(reply);
+            }
+            None => warn!("Reply to unknown request {} on session {}", key, self.id),
+        }
+    }
+
+    /// Queue a JSON-RPC notification on this session's SSE stream.
+    pub fn notify(&self, method: &str, params: Option<Value>) {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        self.send(message);
+    }
+
+    /// Queue a server-to-client message on this session's SSE stream.
+    pub fn send(&self, message: Value) {
+        // Hold the history lock while broadcasting so a subscriber can't see
+        // an event both in the replay backlog and on the live channel.
+        let mut history = self.history.lock().unwrap();
+        let event_id = self.next_event_id.fetch_add(1, Ordering::SeqCst) + 1;
+        history.push_back((event_id, message.clone()));
+        if history.len() > SESSION_HISTORY_LIMIT {
+            history.pop_front();
+        }
+        // No open stream is fine, the message stays in history for replay
+        let _ = self.sender.send((event_id, message));
+    }
+
+    // Returns the messages to replay (those after `last_event_id`) together
+    // with a receiver for everything sent afterwards.
+    // The very first stream also gets whatever was queued before the client
+    // connected (e.g. a `roots/list` sent right after initialization).
+    pub(super) fn subscribe(&self, last_event_id: Option<u64>) -> (Vec<SessionEvent>, broadcast::Receiver<SessionEvent>) {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let first_stream = !self.streamed.swap(true, Ordering::SeqCst);
+        let backlog = match last_event_id {
+            Some(last) => history.iter().filter(|(id, _)| *id > last).cloned().collect(),
+            None if first_stream => history.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+        (backlog, receiver)
+    }
+}
+
+pub struct SessionManager {
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+    events: broadcast::Sender<ClientEvent>,
+    // Milliseconds without a request before a session is dropped
+    idle_timeout: AtomicU64,
+}
+
+impl SessionManager {
+    pub fn new(events: broadcast::Sender<ClientEvent>) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            events,
+            idle_timeout: AtomicU64::new(DEFAULT_SESSION_IDLE_MINUTES * 60 * 1000),
+        }
+    }
+
+    pub fn set_idle_timeout(&self, timeout: Duration) {
+        self.idle_timeout.store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn create(
+        &self,
+        transport: &'static str,
+        caller: &Caller,
+        protocol_version: String,
+        client_capabilities: Value,
+        client_info: Value,
+    ) -> Arc<Session> {
+        let session = Arc::new(Session::new(
+            transport,
+            caller,
+            protocol_version,
+            client_capabilities,
+            client_info,
+        ));
+        self.sessions.lock().unwrap().insert(session.id.clone(), session.clone());
+        let _ = self.events.send(ClientEvent::Connected { client: session.info() });
+        session
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Session>> {
+        self.sessions.lock().unwrap().get(id).cloned()
+    }
+
+    // Also ends the session's open SSE stream or WebSocket
+    pub fn remove(&self, id: &str) -> Option<Arc<Session>> {
+        let session = self.sessions.lock().unwrap().remove(id)?;
+        session.close();
+        let _ = self.events.send(ClientEvent::Disconnected { id: id.to_string() });
+        Some(session)
+    }
+
+    pub fn all(&self) -> Vec<Arc<Session>> {
+        self.sessions.lock().unwrap().values().cloned().collect()
+    }
+
+    // Removes the sessions whose last request is older than the idle
+    // timeout, as `remove` would, and returns their ids
+    pub fn remove_idle(&self) -> Vec<String> {
+        let cutoff = now_millis().saturating_sub(self.idle_timeout.load(Ordering::Relaxed));
+        let idle: Vec<String> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| session.last_seen.load(Ordering::Relaxed) < cutoff)
+            .map(|session| session.id.clone())
+            .collect();
+        idle.into_iter().filter(|id| self.remove(id).is_some()).collect()
+    }
+}
+
+// Asks the client for its roots and re-derives the session's search
+// preferences from the project manifests found there.
+pub(super) async fn refresh_roots(session: Arc<Session>) {
+    let roots = match session.request("roots/list", None).await {
+        Ok(result) => result
+            .get("roots")
+            .cloned()
+            .and_then(|roots| serde_json::from_value::<Vec<Root>>(roots).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to list roots for session {}: {}", session.id, e.message);
+            return;
+        }
+    };
+
+    let preferences = roots::preferred_versions(&roots);
+    info!(
+        "Session {} declared {} roots, {} version preferences",
+        session.id,
+        roots.len(),
+        preferences.len()
+    );
+    *session.preferred_versions.lock().unwrap() = preferences;
+}
+
+// Sends `notifications/progress` for a request that carried
+// `_meta.progressToken`. Without a token or a session to deliver on, reporting
+// is a no-op.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    session: Option<Arc<Session>>,
+    token: Option<Value>,
+}
+
+impl ProgressReporter {
+    pub fn from_params(session: Option<&Arc<Session>>, params: &Value) -> Self {
+        Self {
+            session: session.cloned(),
+            token: params.pointer("/_meta/progressToken").cloned(),
+        }
+    }
+
+    pub fn report(&self, progress: u64, total: Option<u64>, message: Option<&str>) {
+        let (Some(session), Some(token)) = (&self.session, &self.token) else {
+            return;
+        };
+        let mut params = json!({ "progressToken": token, "progress": progress });
+        if let Some(total) = total {
+            params["total"] = total.into();
+        }
+        if let Some(message) = message {
+            params["message"] = message.into();
+        }
+        session.notify("notifications/progress", Some(params));
+    }
+}
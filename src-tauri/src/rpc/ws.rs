@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::Extension;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use super::auth::Caller;
+use super::{process_payload, RequestLimit, RpcError, RpcResponse, RpcState, PARSE_ERROR, SUPPORTED_PROTOCOL_VERSIONS};
+
+// JSON-RPC over WebSocket. Each connection is its own session, so index
+// notifications, progress and server-to-client requests are pushed on the
+// same socket the client sends requests over.
+pub(super) async fn handle_upgrade(
+    State(state): State<RpcState>,
+    Extension(caller): Extension<Caller>,
+    Extension(RequestLimit(max_message_bytes)): Extension<RequestLimit>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade
+        .max_message_size(max_message_bytes)
+        .on_upgrade(move |socket| serve_connection(state, caller, socket))
+}
+
+async fn serve_connection(state: RpcState, caller: Caller, socket: WebSocket) {
+    let session = state.sessions.create(
+        "websocket",
+        &caller,
+        SUPPORTED_PROTOCOL_VERSIONS[0].to_string(),
+        json!({}),
+        Value::Null,
+    );
+    info!("WebSocket client connected, session {}", session.id);
+
+    let (mut sink, mut incoming) = socket.split();
+    let (_, mut pushed) = session.subscribe(None);
+    let (replies, mut outgoing_replies) = mpsc::unbounded_channel::<String>();
+
+    // One writer owns the sink; replies and pushed messages are funneled to it
+    let writer = tokio::spawn(async move {
+        loop {
+            let text = tokio::select! {
+                reply = outgoing_replies.recv() => match reply {
+                    Some(text) => text,
+                    None => break,
+                },
+                pushed_message = pushed.recv() => match pushed_message {
+                    Ok((_, message)) => message.to_string(),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket client fell behind, dropped {} messages", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            if sink.send(Message::Text(text.into())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        let frame = tokio::select! {
+            frame = incoming.next() => match frame {
+                Some(Ok(frame)) => frame,
+                _ => break,
+            },
+            // Disconnected from the UI
+            _ = session.closed() => break,
+        };
+        let text = match frame {
+            Message::Text(text) => text.to_string(),
+            Message::Close(_) => break,
+            // Pings are answered by axum, binary frames aren't part of the protocol
+            _ => continue,
+        };
+
+        // Each frame runs on its own task: a handler waiting on the client
+        // (e.g. sampling) must not block the replies it is waiting for.
+        let state = state.clone();
+        let session = Arc::clone(&session);
+        let replies = replies.clone();
+        let caller = caller.clone();
+        // Frames count against the same limit as HTTP requests
+        let allowed = state.rate_limiter.check(&caller.key_id);
+        tokio::spawn(async move {
+            let reply = match (serde_json::from_str::<Value>(&text), allowed) {
+                (Ok(payload), Ok(())) => match process_payload(&state, &caller, &mut Some(session), payload).await {
+                    Ok(reply) => reply.body(),
+                    Err(error) => serde_json::to_value(RpcResponse::failure(Value::Null, error)).ok(),
+                },
+                (Ok(payload), Err(error)) => {
+                    let id = payload.get("id").cloned().unwrap_or(Value::Null);
+                    serde_json::to_value(RpcResponse::failure(id, RpcError::from(error))).ok()
+                }
+                (Err(e), _) => serde_json::to_value(RpcResponse::failure(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+                ))
+                .ok(),
+            };
+            if let Some(reply) = reply {
+                let _ = replies.send(reply.to_string());
+            }
+        });
+    }
+
+    state.sessions.remove(&session.id);
+    writer.abort();
+    info!("WebSocket client disconnected, session {}", session.id);
+}
@@ -1,20 +1,333 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, Deref, DerefMut};
 use std::path::Path;
-use tantivy::collector::{Count, TopDocs};
-use tantivy::query::QueryParser;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+use tantivy::collector::{Count, DocSetCollector, FacetCollector, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, ConstScoreQuery, ExistsQuery, Occur, Query, QueryParser, RangeQuery, RegexQuery,
+    TermQuery, TermSetQuery,
+};
 use tantivy::schema::*;
-use tantivy::{doc, Index, IndexWriter, ReloadPolicy};
+use tantivy::columnar::{Column, ColumnValues};
+use tantivy::{doc, DocAddress, DocId, Index, IndexWriter, ReloadPolicy, Score, Searcher, SegmentReader, Term};
+use tantivy::indexer::{LogMergePolicy, MergePolicy, NoMergePolicy};
 use tantivy::directory::MmapDirectory;
+use tantivy::store::{Compressor, ZstdCompressor};
+use tantivy::snippet::SnippetGenerator;
 use tantivy::TantivyDocument;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{debug, warn};
+
+use crate::aliases::{VersionAlias, VersionAliases, AUTOMATIC_ALIASES};
+use crate::bodies::BodyStore;
+use crate::cache::SearchCache;
+use crate::crypto::DocumentCipher;
+use crate::explain::{self, QueryExplanation};
+use crate::hnsw::VectorIndex;
+use crate::settings::{DocstoreCompression, DocstoreSettings, WriterSettings};
+use crate::slow_queries::SlowQueryLog;
+use crate::util::{now_millis, sha256_hex};
+use crate::versions::{is_release, version_key, VersionRange};
 
 // Define a struct for our document for easier handling
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 pub struct SearchableDocument {
     pub id: String,
     pub title: String,
     pub body: String,
     pub source: String, // e.g., "rust-docs", "api-spec-v1"
     pub version: Option<String>, // Optional versioning
+    pub url: Option<String>, // Where to read it: a web page, or a file:// URL for local files
+    #[serde(default)]
+    pub tags: Vec<String>, // e.g. "async", "deprecated", "tutorial"; stored lowercased
+    // Both in milliseconds since the Unix epoch. indexed_at is set when the
+    // document is added, whatever the caller sent.
+    pub indexed_at: Option<u64>,
+    pub published_at: Option<u64>, // When the source says it was published or last updated
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>, // Numbers to filter and rank by, e.g. "stars", "downloads", "page_rank"
+    // Place in the taxonomy, e.g. "/rust/tokio/1.38.0/sync". Without one the
+    // document goes under its source and version.
+    pub facet: Option<String>,
+    // Set on a section of a page: the page's id, the headings leading to the
+    // section, and the fragment that jumps to it within the page's URL
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub heading_path: Vec<String>,
+    pub anchor: Option<String>,
+    // SHA-256 of the body, hex. Set when the document is added, whatever the
+    // caller sent.
+    pub content_hash: Option<String>,
+}
+
+// What a client can ask for in `fields`: the document's own fields, plus
+// "snippet", a passage of the body around the query's terms
+pub const DOCUMENT_FIELDS: &[&str] = &[
+    "id", "title", "body", "source", "version", "url", "tags", "indexed_at", "published_at", "metrics", "facet",
+    "parent_id", "heading_path", "anchor", "content_hash", "snippet",
+];
+
+// Documents indexed under different ids with the same body, or nearly
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct DuplicateGroup {
+    pub content_hash: Option<String>, // None for near-duplicates
+    pub ids: Vec<String>,
+}
+
+// A child facet and how many documents fall under it
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct FacetCount {
+    pub facet: String,
+    pub count: u64,
+}
+
+// Extra score for hits from a source the caller prefers, and for hits that
+// also match the preferred version of that source.
+const PREFERRED_SOURCE_BOOST: f32 = 1.0;
+const PREFERRED_VERSION_BOOST: f32 = 2.0;
+
+// With a freshness half-life, a document published just now scores up to
+// this much more (relative to its own score) than a very old one
+const FRESHNESS_BOOST: f32 = 1.0;
+const MILLIS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+// Bodies whose SimHashes are at most this many bits apart are near-duplicates:
+// the same page with a different footer, say, or served under another URL
+const NEAR_DUPLICATE_BITS: u32 = 3;
+// Words per shingle hashed into a SimHash
+const SIMHASH_SHINGLE_WORDS: usize = 3;
+// Hits fetched per result wanted when near-duplicates are dropped, so enough
+// are left to fill the page
+const NEAR_DUPLICATE_OVERFETCH: usize = 2;
+
+// How much of a body kept in the body store the index stores itself
+const BODY_EXCERPT_BYTES: usize = 1024;
+
+// Longest snippet returned for a hit
+const SNIPPET_CHARS: usize = 300;
+
+// Where superseded versions are moved to, inside the index directory
+const ARCHIVE_DIR: &str = "archive";
+
+// The embeddings' nearest-neighbour graph, inside the index directory
+const VECTORS_FILE: &str = "vectors.hnsw";
+// Neighbours fetched per result wanted in a vector search, so enough are
+// left to fill the page once the filters have had their say
+const KNN_OVERFETCH: usize = 5;
+// The least of the graph a vector search looks through
+const KNN_MIN_EF: usize = 100;
+
+// Grouped by version, a few hits each are enough to compare, and more than
+// a handful of versions side by side is hard to read
+pub const DEFAULT_HITS_PER_VERSION: usize = 3;
+pub const MAX_VERSION_GROUPS: usize = 10;
+
+// A source (and optionally a version prefix like "1.38") that should rank
+// higher, e.g. because the caller's project depends on it.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PreferredVersion {
+    pub source: String,
+    pub version: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    /// Best match first
+    #[default]
+    Relevance,
+    /// Newest published_at first; documents without one come last
+    Published,
+    /// Most recently indexed first
+    Indexed,
+    /// Highest version first, as semver; documents whose version isn't
+    /// semver-like come last
+    Version,
+}
+
+/// Keeps documents whose metric is between `min` and `max`, both included.
+/// Either end may be left open; documents without the metric are dropped.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+pub struct MetricRange {
+    pub metric: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Adds `weight` times ln(1 + value) of a metric to each hit's relevance
+/// score, so e.g. popular packages rank higher among similar matches.
+/// Documents without the metric, or with a negative value, get nothing.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+pub struct MetricBoost {
+    pub metric: String,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub preferred_versions: Vec<PreferredVersion>,
+    // Versions a workspace pins. They rank higher, like preferred versions,
+    // and the other versions of their sources are left out.
+    pub pinned_versions: Vec<PreferredVersion>,
+    // Only documents with at least one of these tags
+    pub tags_any: Vec<String>,
+    // Only documents with every one of these tags
+    pub tags_all: Vec<String>,
+    pub sort: SearchSort,
+    // Boosts recent documents when sorting by relevance: one this many days
+    // old gets half the boost of a new one. Uses published_at, or
+    // indexed_at for documents without it.
+    pub freshness_half_life_days: Option<u32>,
+    pub ranges: Vec<MetricRange>,
+    pub rank_by: Vec<MetricBoost>,
+    // Only documents at or below this facet
+    pub facet: Option<String>,
+    // Only documents of this source
+    pub source: Option<String>,
+    // Only documents whose version matches, e.g. ">=1.4, <2.0"
+    pub version_range: Option<String>,
+    // Only the highest version of each source among the matches
+    pub highest_version: bool,
+    // Only each source's newest indexed version, its newest release if it
+    // has one
+    pub latest_only: bool,
+    // Only documents of this version, or of the version it's an alias of in
+    // each source, e.g. "stable"
+    pub version: Option<String>,
+    // Also return hits that are near-duplicates of a better one
+    pub keep_near_duplicates: bool,
+    // Also search the archive of superseded versions
+    pub include_archived: bool,
+    // The query's embedding: hits are then its nearest neighbours in the
+    // vector index, most similar first, and the query string only picks
+    // snippets
+    pub query_vector: Option<Vec<f32>>,
+}
+
+// Sent as `search-ready` once the index has been warmed up after opening,
+// or straight away when warm-up is off
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SearchReady {
+    pub warmed: bool,
+    pub segments: usize,
+    // Terms read from the title and body dictionaries
+    pub terms: usize,
+    pub elapsed_ms: u64,
+}
+
+// Where a search's time went, in milliseconds
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+pub struct SearchTimings {
+    /// Parsing the query and building its filters, version filters and a
+    /// vector search's nearest neighbours included
+    pub parse_ms: f64,
+    /// Finding and ranking the hits
+    pub search_ms: f64,
+    /// Loading the hits' stored documents
+    pub fetch_ms: f64,
+    /// From start to finish, the archive's search included
+    pub total_ms: f64,
+}
+
+// What the index holds in memory and maps from disk, for getResourceUsage
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct IndexUsage {
+    /// Bytes of segment files the searcher maps, the archive's included
+    pub index_bytes: u64,
+    pub segments: usize,
+    /// Writers open for writes under way, each allowed `writer_budget_bytes`
+    /// of heap
+    pub open_writers: usize,
+    pub writer_budget_bytes: u64,
+    /// Searches whose results are cached
+    pub cached_searches: usize,
+    /// Embeddings held in memory
+    pub vectors: usize,
+    /// Searches and writes running on the blocking pool, out of `workers`
+    pub busy_workers: usize,
+    pub workers: usize,
+}
+
+// An index writer, counted as open until it's dropped
+struct OpenWriter {
+    writer: IndexWriter,
+    _open: OpenCount,
+}
+
+struct OpenCount(Arc<AtomicUsize>);
+
+impl OpenCount {
+    fn new(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count.clone())
+    }
+}
+
+impl Drop for OpenCount {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Deref for OpenWriter {
+    type Target = IndexWriter;
+
+    fn deref(&self) -> &IndexWriter {
+        &self.writer
+    }
+}
+
+impl DerefMut for OpenWriter {
+    fn deref_mut(&mut self) -> &mut IndexWriter {
+        &mut self.writer
+    }
+}
+
+// Emitted after every committed change to the index so transports can tell
+// their clients to refresh, and the app can show what the index is doing.
+// A commit follows the events for the documents it changed.
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum IndexEvent {
+    DocumentAdded { id: String, source: String },
+    DocumentDeleted { id: String },
+    Commit(IndexCommit),
+    Error(IndexFailure),
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexOperation {
+    Add,
+    // Documents moved over from another index, such as the archive
+    Import,
+    Delete,
+}
+
+// One commit to the index
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct IndexCommit {
+    pub operation: IndexOperation,
+    pub added: usize,
+    pub deleted: usize,
+    // Sent again exactly as indexed, so left alone
+    pub unchanged: usize,
+    // Milliseconds since the Unix epoch
+    pub committed_at: u64,
+}
+
+// A write that failed. Nothing changed unless its commit went out first.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct IndexFailure {
+    pub operation: IndexOperation,
+    // How many documents the write was for
+    pub documents: usize,
+    pub message: String,
+    // Milliseconds since the Unix epoch
+    pub failed_at: u64,
 }
 
 pub struct SearchService {
@@ -26,16 +339,126 @@ pub struct SearchService {
     pub body_field: Field,
     pub source_field: Field,
     pub version_field: Field,
+    pub version_key_field: Field,
+    pub url_field: Field,
+    pub tags_field: Field,
+    pub indexed_at_field: Field,
+    pub published_at_field: Field,
+    pub metrics_field: Field,
+    pub facet_field: Field,
+    pub parent_id_field: Field,
+    pub heading_path_field: Field,
+    pub anchor_field: Field,
+    pub content_hash_field: Field,
+    pub simhash_field: Field,
+    // Hash of everything the caller sent, to tell whether a document being
+    // added again has changed at all
+    fingerprint_field: Field,
+    // Only used with a cipher: holds the encrypted title and body, which are
+    // then indexed but not stored
+    sealed_field: Field,
+    // Only used with a body store: the body, or the excerpt of one kept in
+    // the store, which external_body then marks
+    stored_body_field: Field,
+    external_body_field: Field,
+    cipher: Option<DocumentCipher>,
+    body_store: Option<BodyStore>,
+    // As opened with, for `open_like`
+    external_body_bytes: Option<usize>,
+    docstore: DocstoreSettings,
+    aliases: VersionAliases,
+    // Versions moved out of the way: an index of its own, with the same
+    // settings, that's only searched when asked. The archive has none.
+    archive: Option<Box<SearchService>>,
+    // Embeddings of documents, added by embeddings.rs and dropped here as
+    // documents change or go
+    vectors: RwLock<VectorIndex>,
+    events: broadcast::Sender<IndexEvent>,
+    // Bounds the searches and writes run through `blocking` at once
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+    open_writers: Arc<AtomicUsize>,
+    // Memory and threads for the writer each write opens
+    writer_settings: RwLock<WriterSettings>,
+    // Why the last write couldn't open a writer, cleared once one opens
+    writer_error: Mutex<Option<String>>,
+    // Recent results, dropped on every commit
+    cache: SearchCache,
+    // Where searches slower than its threshold go. Only the live index has
+    // one: the archive's searches are timed as part of the live index's.
+    slow_queries: Option<Arc<SlowQueryLog>>,
+}
+
+// What gets encrypted into the sealed field
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SealedFields {
+    title: String,
+    body: String,
+    #[serde(default)]
+    heading_path: Vec<String>,
 }
 
 impl SearchService {
-    pub fn new(index_path: impl AsRef<Path>) -> Result<Self> {
+    // With a `cipher`, titles and bodies are only stored encrypted. With
+    // `external_body_bytes`, bodies longer than that are kept whole in a body
+    // store in the index directory and the index stores only their start.
+    // `docstore` sets how segments written from now on compress stored
+    // fields. `writer` sizes the writer each write opens. Both apply to the
+    // archive too. Slow searches go to `slow_queries`.
+    pub fn new(
+        index_path: impl AsRef<Path>,
+        cipher: Option<DocumentCipher>,
+        external_body_bytes: Option<usize>,
+        docstore: DocstoreSettings,
+        writer: WriterSettings,
+        slow_queries: Option<Arc<SlowQueryLog>>,
+    ) -> Result<Self> {
+        let archive = Self::open(
+            index_path.as_ref().join(ARCHIVE_DIR),
+            cipher.clone(),
+            external_body_bytes,
+            &docstore,
+            writer.clone(),
+            None,
+        )?;
+        let mut service =
+            Self::open(index_path, cipher, external_body_bytes, &docstore, writer, Some(Box::new(archive)))?;
+        service.slow_queries = slow_queries;
+        Ok(service)
+    }
+
+    fn open(
+        index_path: impl AsRef<Path>,
+        cipher: Option<DocumentCipher>,
+        external_body_bytes: Option<usize>,
+        docstore: &DocstoreSettings,
+        writer: WriterSettings,
+        archive: Option<Box<SearchService>>,
+    ) -> Result<Self> {
+        let content_options = if cipher.is_some() { TEXT } else { TEXT | STORED };
+        let body_options = if external_body_bytes.is_some() { TEXT } else { content_options.clone() };
         let mut schema_builder = Schema::builder();
         let id_field = schema_builder.add_text_field("id", STRING | STORED); // Unique ID for the document
-        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
-        let body_field = schema_builder.add_text_field("body", TEXT | STORED); // Main content for full-text search
+        let title_field = schema_builder.add_text_field("title", content_options);
+        let body_field = schema_builder.add_text_field("body", body_options); // Main content for full-text search
         let source_field = schema_builder.add_text_field("source", STRING | STORED | FAST); // Faceting/filtering
         let version_field = schema_builder.add_text_field("version", STRING | STORED | FAST); // Optional, for filtering
+        let version_key_field = schema_builder.add_u64_field("version_key", INDEXED | FAST); // Semver order, for ranges
+        let url_field = schema_builder.add_text_field("url", STORED); // Returned with results, not searched
+        let tags_field = schema_builder.add_text_field("tags", STRING | STORED | FAST); // Multi-valued, for filtering
+        let indexed_at_field = schema_builder.add_u64_field("indexed_at", INDEXED | STORED | FAST); // For sorting and freshness
+        let published_at_field = schema_builder.add_u64_field("published_at", INDEXED | STORED | FAST);
+        let metrics_field = schema_builder.add_json_field("metrics", STORED | FAST); // Numeric, for range filters and ranking
+        let facet_field = schema_builder.add_facet_field("facet", STORED); // Hierarchy to drill down and count by
+        let parent_id_field = schema_builder.add_text_field("parent_id", STRING | STORED); // To replace a page's sections with it
+        let heading_path_field = schema_builder.add_text_field("heading_path", STORED); // Multi-valued; the title has it for search
+        let anchor_field = schema_builder.add_text_field("anchor", STORED);
+        let content_hash_field = schema_builder.add_text_field("content_hash", STRING | STORED | FAST); // For finding duplicates
+        let fingerprint_field = schema_builder.add_text_field("fingerprint", STORED);
+        let simhash_field = schema_builder.add_u64_field("simhash", FAST); // For spotting near-duplicates
+        let sealed_field = schema_builder.add_bytes_field("sealed", STORED);
+        let stored_body_field = schema_builder.add_text_field("stored_body", STORED);
+        let external_body_field = schema_builder.add_bool_field("external_body", STORED);
 
         let schema = schema_builder.build();
         
@@ -43,7 +466,27 @@ impl SearchService {
         std::fs::create_dir_all(index_dir)?; // Ensure directory exists
 
         let directory = MmapDirectory::open(index_dir)?;
-        let index = Index::open_or_create(directory, schema.clone())?;
+        let mut index = Index::open_or_create(directory, schema.clone())?;
+        // An existing index keeps these in its meta.json, and they're saved
+        // there again at the next commit. Each segment's doc store records
+        // its own compressor, so segments written before still read.
+        let index_settings = index.settings_mut();
+        index_settings.docstore_compression = match docstore.compression {
+            DocstoreCompression::None => Compressor::None,
+            DocstoreCompression::Lz4 => Compressor::Lz4,
+            DocstoreCompression::Zstd => Compressor::Zstd(ZstdCompressor {
+                compression_level: docstore.zstd_level,
+            }),
+        };
+        index_settings.docstore_blocksize = docstore.block_size;
+        let body_store = external_body_bytes
+            .map(|min_bytes| BodyStore::open(index_dir.join("bodies"), min_bytes))
+            .transpose()?;
+        let aliases = VersionAliases::load(index_dir.join("version_aliases.json"));
+        let vectors = VectorIndex::load(index_dir.join(VECTORS_FILE));
+
+        let (events, _) = broadcast::channel(1024);
+        let workers = std::thread::available_parallelism().map_or(4, |cores| cores.get());
 
         Ok(SearchService {
             index,
@@ -53,92 +496,1848 @@ impl SearchService {
             body_field,
             source_field,
             version_field,
+            version_key_field,
+            url_field,
+            tags_field,
+            indexed_at_field,
+            published_at_field,
+            metrics_field,
+            facet_field,
+            parent_id_field,
+            heading_path_field,
+            anchor_field,
+            content_hash_field,
+            simhash_field,
+            fingerprint_field,
+            sealed_field,
+            stored_body_field,
+            external_body_field,
+            cipher,
+            body_store,
+            external_body_bytes,
+            docstore: docstore.clone(),
+            aliases,
+            archive,
+            vectors: RwLock::new(vectors),
+            events,
+            workers: Arc::new(Semaphore::new(workers)),
+            worker_count: workers,
+            open_writers: Arc::new(AtomicUsize::new(0)),
+            writer_settings: RwLock::new(writer),
+            writer_error: Mutex::new(None),
+            cache: SearchCache::default(),
+            slow_queries: None,
+        })
+    }
+
+    // A new, empty index at `index_path` with this one's settings, to try
+    // them out without touching its documents. It has no archive.
+    pub fn open_like(&self, index_path: impl AsRef<Path>) -> Result<Self> {
+        let writer = self.writer_settings.read().unwrap().clone();
+        Self::open(index_path, self.cipher.clone(), self.external_body_bytes, &self.docstore, writer, None)
+    }
+
+    pub fn archive(&self) -> Option<&SearchService> {
+        self.archive.as_deref()
+    }
+
+    pub fn slow_queries(&self) -> Option<&SlowQueryLog> {
+        self.slow_queries.as_deref()
+    }
+
+    // Applies to the next write, here and in the archive; a write under way
+    // keeps the writer it opened
+    pub fn set_writer_settings(&self, settings: WriterSettings) {
+        if let Some(archive) = &self.archive {
+            archive.set_writer_settings(settings.clone());
+        }
+        *self.writer_settings.write().unwrap() = settings;
+    }
+
+    // Opens a writer, noting whether that worked for getHealth
+    fn writer(&self) -> Result<OpenWriter> {
+        let opened = self.open_writer();
+        *self.writer_error.lock().unwrap() = opened.as_ref().err().map(|e| e.to_string());
+        opened
+    }
+
+    // Why the last write couldn't open a writer, if it couldn't
+    pub fn writer_error(&self) -> Option<String> {
+        self.writer_error.lock().unwrap().clone()
+    }
+
+    // A writer with the configured memory budget, split between a fixed
+    // number of threads when one is set, and merge policy
+    fn open_writer(&self) -> Result<OpenWriter> {
+        let settings = self.writer_settings.read().unwrap().clone();
+        let budget = settings.memory_budget_mb * 1_000_000;
+        let index_writer: IndexWriter = match settings.threads {
+            Some(threads) => self.index.writer_with_num_threads(threads, budget)?,
+            None => self.index.writer(budget)?,
+        };
+        let merge_policy: Box<dyn MergePolicy> = if settings.merge.enabled {
+            let mut policy = LogMergePolicy::default();
+            policy.set_min_num_segments(settings.merge.min_segments);
+            policy.set_max_docs_before_merge(settings.merge.max_docs);
+            Box::new(policy)
+        } else {
+            Box::new(NoMergePolicy)
+        };
+        index_writer.set_merge_policy(merge_policy);
+        Ok(OpenWriter {
+            writer: index_writer,
+            _open: OpenCount::new(&self.open_writers),
+        })
+    }
+
+    // Done with a writer that has committed: merges the commit started finish
+    // first when the settings say to, and are otherwise abandoned
+    fn close_writer(&self, index_writer: OpenWriter) -> Result<()> {
+        if self.writer_settings.read().unwrap().merge.merge_on_commit {
+            index_writer.writer.wait_merging_threads()?;
+        }
+        Ok(())
+    }
+
+    // Documents and segments the searcher sees, which also shows the index
+    // opens
+    pub fn index_stats(&self) -> Result<(u64, usize)> {
+        let searcher = self.searcher()?;
+        Ok((searcher.num_docs(), searcher.segment_readers().len()))
+    }
+
+    pub fn usage(&self) -> Result<IndexUsage> {
+        let searcher = self.searcher()?;
+        let mut index_bytes = searcher.space_usage()?.total().get_bytes();
+        let mut segments = searcher.segment_readers().len();
+        let mut open_writers = self.open_writers.load(Ordering::Relaxed);
+        if let Some(archive) = &self.archive {
+            let searcher = archive.searcher()?;
+            index_bytes += searcher.space_usage()?.total().get_bytes();
+            segments += searcher.segment_readers().len();
+            open_writers += archive.open_writers.load(Ordering::Relaxed);
+        }
+        Ok(IndexUsage {
+            index_bytes,
+            segments,
+            open_writers,
+            writer_budget_bytes: self.writer_settings.read().unwrap().memory_budget_mb as u64 * 1_000_000,
+            cached_searches: self.cache.count(),
+            vectors: self.vectors.read().unwrap().len(),
+            busy_workers: self.worker_count - self.workers.available_permits(),
+            workers: self.worker_count,
         })
     }
 
-    pub fn add_document(&self, doc_to_add: SearchableDocument, writer_mem_budget: usize) -> Result<()> {
-        // Create an IndexWriter. Consider managing this more globally or per-batch for performance.
-        // For simplicity here, we create one per add.
-        // The memory budget is per thread.
-        let mut index_writer: IndexWriter = self.index.writer(writer_mem_budget)?; 
+    pub fn subscribe(&self) -> broadcast::Receiver<IndexEvent> {
+        self.events.subscribe()
+    }
+
+    // Runs `work` on Tokio's blocking pool, for callers on the async
+    // runtime: Tantivy's searches and commits would otherwise hold up one of
+    // its few threads, and every request queued behind it. At most one task
+    // per core runs at once, and the rest wait their turn without taking a
+    // thread. A panic in `work` carries on in the caller, and what it logs
+    // stays in the caller's span.
+    pub async fn blocking<T, F>(self: &Arc<Self>, work: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&SearchService) -> T + Send + 'static,
+    {
+        let permit = self.workers.clone().acquire_owned().await.expect("The worker semaphore is never closed");
+        let service = self.clone();
+        let span = tracing::Span::current();
+        let task = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            span.in_scope(|| work(&service))
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    // search_documents_with, off the async runtime
+    pub async fn search(
+        self: &Arc<Self>,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchableDocument>> {
+        let (query_str, options) = (query_str.to_string(), options.clone());
+        self.blocking(move |service| service.search_documents_with(&query_str, limit, &options)).await
+    }
+
+    // search_documents_timed, off the async runtime
+    pub async fn search_timed(
+        self: &Arc<Self>,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<(Vec<SearchableDocument>, Option<SearchTimings>)> {
+        let (query_str, options) = (query_str.to_string(), options.clone());
+        self.blocking(move |service| service.search_documents_timed(&query_str, limit, &options)).await
+    }
 
-        // Create the document with base fields
-        let doc = doc!(
+    // select_fields, off the async runtime, since snippets take a search of
+    // their own
+    pub async fn select(
+        self: &Arc<Self>,
+        query_str: &str,
+        documents: Vec<SearchableDocument>,
+        fields: &[String],
+    ) -> Result<Vec<serde_json::Value>> {
+        let (query_str, fields) = (query_str.to_string(), fields.to_vec());
+        self.blocking(move |service| service.select_fields(&query_str, documents, &fields)).await
+    }
+
+    // Vectors from another embedding model than the index holds are dropped
+    // wholesale, as they can't be compared with the new model's
+    pub fn use_vector_model(&self, model: &str) {
+        self.vectors.write().unwrap().set_model(model);
+    }
+
+    // Stores embeddings `model` made. They're left out, returning false, if
+    // the index has switched to another model since.
+    pub fn set_vectors(&self, model: &str, embeddings: Vec<(String, Vec<f32>)>) -> bool {
+        let mut vectors = self.vectors.write().unwrap();
+        if vectors.model() != Some(model) {
+            return false;
+        }
+        for (id, vector) in embeddings {
+            vectors.insert(&id, vector);
+        }
+        true
+    }
+
+    pub fn has_vector(&self, id: &str) -> bool {
+        self.vectors.read().unwrap().contains(id)
+    }
+
+    pub fn similarity(&self, id: &str, query: &[f32]) -> Option<f32> {
+        self.vectors.read().unwrap().similarity(id, query)
+    }
+
+    pub fn vector_ids(&self) -> Vec<String> {
+        self.vectors.read().unwrap().ids().cloned().collect()
+    }
+
+    pub fn remove_vectors(&self, ids: &[String]) {
+        let mut vectors = self.vectors.write().unwrap();
+        for id in ids {
+            vectors.remove(id);
+        }
+    }
+
+    // Writes the vector index out if it changed; changes since the last save
+    // are lost on a crash, and embedded again at the next start
+    pub fn save_vectors(&self) -> Result<()> {
+        self.vectors.write().unwrap().save()
+    }
+
+    fn searcher(&self) -> Result<Searcher> {
+        let reader = self.index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        Ok(reader.searcher())
+    }
+
+    // Reads the title and body term dictionaries and the fast fields that
+    // filters and sorting use, then runs a query and loads a stored
+    // document, so the first real search finds the index in the page cache
+    // instead of paying for cold mmap reads. The archive is left cold, as it's
+    // only searched when asked.
+    pub fn warm_up(&self) -> Result<SearchReady> {
+        let started = std::time::Instant::now();
+        let searcher = self.searcher()?;
+        let mut terms = 0;
+        for segment_reader in searcher.segment_readers() {
+            for field in [self.title_field, self.body_field] {
+                let inverted_index = segment_reader.inverted_index(field)?;
+                let mut stream = inverted_index.terms().stream()?;
+                while stream.advance() {
+                    terms += 1;
+                }
+            }
+            let fast_fields = segment_reader.fast_fields();
+            for field in ["version_key", "indexed_at", "published_at", "simhash"] {
+                if let Ok(column) = fast_fields.u64(field) {
+                    column.values.iter().for_each(drop);
+                }
+            }
+            for field in ["source", "version", "tags", "content_hash"] {
+                if let Some(column) = fast_fields.str(field)? {
+                    column.ords().values.iter().for_each(drop);
+                }
+            }
+        }
+        let top_docs = searcher.search(&AllQuery, &TopDocs::with_limit(1))?;
+        if let Some((_, address)) = top_docs.first() {
+            searcher.doc::<TantivyDocument>(*address)?;
+        }
+        Ok(SearchReady {
+            warmed: true,
+            segments: searcher.segment_readers().len(),
+            terms,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    pub fn add_document(&self, doc_to_add: SearchableDocument) -> Result<()> {
+        self.add_documents(vec![doc_to_add], |_, _| {})
+    }
+
+    // Adds a batch with a single writer and a single commit. A document
+    // replaces any already indexed under its id, so re-fetching a page
+    // doesn't duplicate it. A page also replaces the sections split out of
+    // it, so its sections must come after it in the batch. Documents indexed
+    // exactly as sent are skipped and keep their indexed_at. `on_progress`
+    // is called with (documents written, total) as the batch goes through.
+    #[tracing::instrument(skip_all, fields(documents = docs_to_add.len()))]
+    pub fn add_documents(
+        &self,
+        docs_to_add: Vec<SearchableDocument>,
+        on_progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        let documents = docs_to_add.len();
+        let result = self.write_documents(docs_to_add, on_progress);
+        self.report(IndexOperation::Add, documents, result)
+    }
+
+    fn write_documents(
+        &self,
+        mut docs_to_add: Vec<SearchableDocument>,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        let mut index_writer = self.writer()?;
+
+        self.resolve_document_versions(&mut docs_to_add)?;
+        let total = docs_to_add.len();
+        let indexed_at = now_millis();
+        let fingerprints = docs_to_add.iter().map(fingerprint).collect::<Result<Vec<_>>>()?;
+        let unchanged = self.unchanged_documents(&docs_to_add, &fingerprints)?;
+        let dropped_sections = self.dropped_sections(&docs_to_add, &unchanged)?;
+        for (i, doc_to_add) in docs_to_add.iter().enumerate() {
+            if !unchanged.contains(&i) {
+                index_writer.delete_term(Term::from_field_text(self.id_field, &doc_to_add.id));
+                if doc_to_add.parent_id.is_none() {
+                    index_writer.delete_term(Term::from_field_text(self.parent_id_field, &doc_to_add.id));
+                }
+                let mut doc = self.to_tantivy_document(doc_to_add, indexed_at)?;
+                doc.add_text(self.fingerprint_field, &fingerprints[i]);
+                if let Some(store) = &self.body_store {
+                    self.store_body(store, doc_to_add)?;
+                }
+                index_writer.add_document(doc)?;
+            }
+            on_progress(i + 1, total);
+        }
+        
+        index_writer.commit()?; // Committing makes changes visible
+        self.cache.clear();
+        if let Some(store) = &self.body_store {
+            for id in &dropped_sections {
+                store.remove(id)?;
+            }
+        }
+        // Changed documents are embedded again once the event goes out
+        {
+            let mut vectors = self.vectors.write().unwrap();
+            let changed = docs_to_add.iter().enumerate().filter(|(i, _)| !unchanged.contains(i));
+            for id in changed.map(|(_, doc)| &doc.id).chain(&dropped_sections) {
+                vectors.remove(id);
+            }
+        }
+        if !unchanged.is_empty() {
+            debug!("Skipped {} unchanged documents", unchanged.len());
+        }
+        let added = total - unchanged.len();
+        for (i, doc_to_add) in docs_to_add.into_iter().enumerate() {
+            if unchanged.contains(&i) {
+                continue;
+            }
+            debug!("Document added and committed: {}", doc_to_add.id);
+            // Nobody listening is not an error
+            let _ = self.events.send(IndexEvent::DocumentAdded {
+                id: doc_to_add.id,
+                source: doc_to_add.source,
+            });
+        }
+        self.committed(IndexOperation::Add, added, dropped_sections.len(), unchanged.len());
+        self.close_writer(index_writer)
+    }
+
+    // Writes documents moved over from another index, such as the archive,
+    // as they were: they keep their indexed_at, and only replace what's
+    // under their own ids, so pages and sections can come in any order
+    #[tracing::instrument(skip_all, fields(documents = docs.len()))]
+    pub fn import_documents(&self, docs: &[SearchableDocument]) -> Result<()> {
+        let result = self.write_imported(docs);
+        self.report(IndexOperation::Import, docs.len(), result)
+    }
+
+    fn write_imported(&self, docs: &[SearchableDocument]) -> Result<()> {
+        let mut index_writer = self.writer()?;
+        let now = now_millis();
+        for doc_to_add in docs {
+            index_writer.delete_term(Term::from_field_text(self.id_field, &doc_to_add.id));
+            let mut doc = self.to_tantivy_document(doc_to_add, doc_to_add.indexed_at.unwrap_or(now))?;
+            doc.add_text(self.fingerprint_field, &fingerprint(doc_to_add)?);
+            if let Some(store) = &self.body_store {
+                self.store_body(store, doc_to_add)?;
+            }
+            index_writer.add_document(doc)?;
+        }
+        index_writer.commit()?;
+        self.cache.clear();
+        {
+            let mut vectors = self.vectors.write().unwrap();
+            for doc in docs {
+                vectors.remove(&doc.id);
+            }
+        }
+        for doc in docs {
+            let _ = self.events.send(IndexEvent::DocumentAdded {
+                id: doc.id.clone(),
+                source: doc.source.clone(),
+            });
+        }
+        self.committed(IndexOperation::Import, docs.len(), 0, 0);
+        self.close_writer(index_writer)
+    }
+
+    fn committed(&self, operation: IndexOperation, added: usize, deleted: usize, unchanged: usize) {
+        let _ = self.events.send(IndexEvent::Commit(IndexCommit {
+            operation,
+            added,
+            deleted,
+            unchanged,
+            committed_at: now_millis(),
+        }));
+    }
+
+    // Passes the write's result on, telling listeners first if it failed
+    fn report<T>(&self, operation: IndexOperation, documents: usize, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            let _ = self.events.send(IndexEvent::Error(IndexFailure {
+                operation,
+                documents,
+                message: e.to_string(),
+                failed_at: now_millis(),
+            }));
+        }
+        result
+    }
+
+    // Documents sent under an alias, e.g. version "stable", are filed under
+    // the version it stands for when they're added
+    fn resolve_document_versions(&self, docs: &mut [SearchableDocument]) -> Result<()> {
+        let mut resolved: HashMap<(String, String), String> = HashMap::new();
+        for doc in docs.iter_mut() {
+            let version = doc.version.as_ref();
+            let Some(version) = version.filter(|version| self.aliases.is_alias(&doc.source, version)) else {
+                continue;
+            };
+            let key = (doc.source.clone(), version.clone());
+            let concrete = match resolved.get(&key) {
+                Some(concrete) => concrete.clone(),
+                None => {
+                    let concrete = self.resolve_version(&doc.source, version)?;
+                    resolved.insert(key, concrete.clone());
+                    concrete
+                }
+            };
+            doc.version = Some(concrete);
+        }
+        Ok(())
+    }
+
+    // The documents in a batch that are indexed exactly as they are. A page
+    // only counts if its sections in the batch do too and it has no others
+    // indexed, since writing it would drop those; when a page is written,
+    // its sections have to be written again after it.
+    fn unchanged_documents(&self, docs: &[SearchableDocument], fingerprints: &[String]) -> Result<HashSet<usize>> {
+        let searcher = self.searcher()?;
+        let mut unchanged = HashSet::new();
+        for (i, doc) in docs.iter().enumerate() {
+            let query = TermQuery::new(Term::from_field_text(self.id_field, &doc.id), IndexRecordOption::Basic);
+            let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.into_iter().next() else {
+                continue;
+            };
+            let stored = searcher.doc::<TantivyDocument>(address)?;
+            if stored.get_first(self.fingerprint_field).and_then(|v| v.as_str()) == Some(fingerprints[i].as_str()) {
+                unchanged.insert(i);
+            }
+        }
+
+        let mut sections: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, doc) in docs.iter().enumerate() {
+            if let Some(parent_id) = &doc.parent_id {
+                sections.entry(parent_id.as_str()).or_default().push(i);
+            }
+        }
+        for (i, doc) in docs.iter().enumerate().filter(|(_, doc)| doc.parent_id.is_none()) {
+            let batch_sections = sections.get(doc.id.as_str()).map(Vec::as_slice).unwrap_or_default();
+            let keep = unchanged.contains(&i)
+                && batch_sections.iter().all(|j| unchanged.contains(j))
+                && self.section_ids(&searcher, &doc.id)?.iter().all(|id| {
+                    batch_sections.iter().any(|&j| docs[j].id == *id)
+                });
+            if !keep {
+                unchanged.remove(&i);
+                for j in batch_sections {
+                    unchanged.remove(j);
+                }
+            }
+        }
+        Ok(unchanged)
+    }
+
+    // Sections the batch drops by replacing their page without bringing
+    // them back, whose bodies have to leave the body store with them
+    fn dropped_sections(&self, docs: &[SearchableDocument], unchanged: &HashSet<usize>) -> Result<Vec<String>> {
+        if self.body_store.is_none() && self.vectors.read().unwrap().is_empty() {
+            return Ok(Vec::new());
+        }
+        let searcher = self.searcher()?;
+        let batch_ids: HashSet<&str> = docs.iter().map(|doc| doc.id.as_str()).collect();
+        let mut dropped = Vec::new();
+        for (i, doc) in docs.iter().enumerate() {
+            if doc.parent_id.is_none() && !unchanged.contains(&i) {
+                let sections = self.section_ids(&searcher, &doc.id)?;
+                dropped.extend(sections.into_iter().filter(|id| !batch_ids.contains(id.as_str())));
+            }
+        }
+        Ok(dropped)
+    }
+
+    // Keeps a large body whole in the body store, sealed like the index's
+    // copy; a body small enough for the index drops one kept for an older
+    // version of the document
+    fn store_body(&self, store: &BodyStore, document: &SearchableDocument) -> Result<()> {
+        if !store.keeps(&document.body) {
+            return store.remove(&document.id);
+        }
+        match &self.cipher {
+            Some(cipher) => store.put(&document.id, &cipher.seal(document.body.as_bytes())?),
+            None => store.put(&document.id, document.body.as_bytes()),
+        }
+    }
+
+    // The whole of a body kept in the body store. A missing file leaves the
+    // excerpt, so one lost body doesn't fail every search that hits it.
+    fn load_body(&self, store: &BodyStore, id: &str, excerpt: String) -> Result<String> {
+        let Some(contents) = store.get(id)? else {
+            warn!("The body of {} is missing from the body store, returning its excerpt", id);
+            return Ok(excerpt);
+        };
+        let contents = match &self.cipher {
+            Some(cipher) => cipher.open(&contents)?,
+            None => contents,
+        };
+        Ok(String::from_utf8(contents)?)
+    }
+
+    // Ids of the sections indexed for a page
+    fn section_ids(&self, searcher: &Searcher, parent_id: &str) -> Result<Vec<String>> {
+        let query = TermQuery::new(Term::from_field_text(self.parent_id_field, parent_id), IndexRecordOption::Basic);
+        let mut ids = Vec::new();
+        for address in searcher.search(&query, &DocSetCollector)? {
+            let stored = searcher.doc::<TantivyDocument>(address)?;
+            ids.extend(stored.get_first(self.id_field).and_then(|v| v.as_str()).map(String::from));
+        }
+        Ok(ids)
+    }
+
+    fn to_tantivy_document(&self, doc_to_add: &SearchableDocument, indexed_at: u64) -> Result<TantivyDocument> {
+        // Clone the Strings to pass them by value, see rust_syntax_I_Know.txt
+        let mut doc = doc!(
             self.id_field => doc_to_add.id.clone(),
             self.title_field => doc_to_add.title.clone(),
             self.body_field => doc_to_add.body.clone(),
             self.source_field => doc_to_add.source.clone()
         );
-        
-        // Add version if present - fix: first modify doc, then add it
         if let Some(version) = &doc_to_add.version {
-            // Fix: Dereference the version string to avoid double reference
-            index_writer.add_document(doc!(
-                self.id_field => doc_to_add.id.clone(),
-                self.title_field => doc_to_add.title.clone(),
-                self.body_field => doc_to_add.body.clone(),
-                self.source_field => doc_to_add.source.clone(),
-                self.version_field => version.clone() // Clone the String to pass it by value
-            ))?;
+            doc.add_text(self.version_field, version);
+            if let Some(key) = version_key(version) {
+                doc.add_u64(self.version_key_field, key);
+            }
+        }
+        if let Some(url) = &doc_to_add.url {
+            doc.add_text(self.url_field, url);
+        }
+        for tag in &doc_to_add.tags {
+            doc.add_text(self.tags_field, normalize_tag(tag));
+        }
+        doc.add_u64(self.indexed_at_field, indexed_at);
+        if let Some(published_at) = doc_to_add.published_at {
+            doc.add_u64(self.published_at_field, published_at);
+        }
+        if !doc_to_add.metrics.is_empty() {
+            // Always floats, so each metric gets a single f64 column
+            let metrics = doc_to_add
+                .metrics
+                .iter()
+                .map(|(name, value)| (name.clone(), OwnedValue::F64(*value)))
+                .collect();
+            doc.add_object(self.metrics_field, metrics);
+        }
+        let facet = match &doc_to_add.facet {
+            Some(facet) => parse_facet(facet)?,
+            None => default_facet(doc_to_add),
+        };
+        doc.add_facet(self.facet_field, facet);
+        if let Some(parent_id) = &doc_to_add.parent_id {
+            doc.add_text(self.parent_id_field, parent_id);
+        }
+        if let Some(anchor) = &doc_to_add.anchor {
+            doc.add_text(self.anchor_field, anchor);
+        }
+        doc.add_text(self.content_hash_field, sha256_hex(doc_to_add.body.as_bytes()));
+        doc.add_u64(self.simhash_field, simhash(&doc_to_add.body));
+        let stored_body = match &self.body_store {
+            Some(store) if store.keeps(&doc_to_add.body) => {
+                doc.add_bool(self.external_body_field, true);
+                body_excerpt(&doc_to_add.body)
+            }
+            _ => doc_to_add.body.clone(),
+        };
+        if let Some(cipher) = &self.cipher {
+            let fields = SealedFields {
+                title: doc_to_add.title.clone(),
+                body: stored_body,
+                heading_path: doc_to_add.heading_path.clone(),
+            };
+            doc.add_bytes(self.sealed_field, &cipher.seal(&serde_json::to_vec(&fields)?)?);
         } else {
-            index_writer.add_document(doc)?;
+            if self.body_store.is_some() {
+                doc.add_text(self.stored_body_field, stored_body);
+            }
+            for heading in &doc_to_add.heading_path {
+                doc.add_text(self.heading_path_field, heading);
+            }
         }
-        
-        index_writer.commit()?; // Committing makes changes visible
-        println!("Document added and committed: {}", doc_to_add.id);
+        Ok(doc)
+    }
+
+    // Removes every document stored under `id`, and the sections split out
+    // of it. Returns false if there was none.
+    pub fn delete_document(&self, id: &str) -> Result<bool> {
+        Ok(self.delete_documents(&[id.to_string()])? > 0)
+    }
+
+    // Removes the documents and their sections in one commit. Returns how
+    // many of the ids were indexed.
+    #[tracing::instrument(skip_all, fields(documents = ids.len()))]
+    pub fn delete_documents(&self, ids: &[String]) -> Result<usize> {
+        let result = self.remove_documents(ids);
+        self.report(IndexOperation::Delete, ids.len(), result)
+    }
+
+    fn remove_documents(&self, ids: &[String]) -> Result<usize> {
+        let searcher = self.searcher()?;
+        let mut found = Vec::new();
+        for id in ids {
+            let query = TermQuery::new(Term::from_field_text(self.id_field, id), IndexRecordOption::Basic);
+            if searcher.search(&query, &TopDocs::with_limit(1))?.is_empty() {
+                continue;
+            }
+            let sections = if self.body_store.is_some() || !self.vectors.read().unwrap().is_empty() {
+                self.section_ids(&searcher, id)?
+            } else {
+                Vec::new()
+            };
+            found.push((id, sections));
+        }
+        if found.is_empty() {
+            return Ok(0);
+        }
+
+        let mut index_writer = self.writer()?;
+        for (id, _) in &found {
+            index_writer.delete_term(Term::from_field_text(self.id_field, id));
+            index_writer.delete_term(Term::from_field_text(self.parent_id_field, id));
+        }
+        index_writer.commit()?;
+        self.cache.clear();
+        for (id, sections) in &found {
+            if let Some(store) = &self.body_store {
+                for id in std::iter::once(id.as_str()).chain(sections.iter().map(String::as_str)) {
+                    store.remove(id)?;
+                }
+            }
+            let mut vectors = self.vectors.write().unwrap();
+            for id in std::iter::once(*id).chain(sections) {
+                vectors.remove(id);
+            }
+            drop(vectors);
+            debug!("Document deleted and committed: {}", id);
+            let _ = self.events.send(IndexEvent::DocumentDeleted { id: id.to_string() });
+        }
+        self.committed(IndexOperation::Delete, 0, found.len(), 0);
+        self.close_writer(index_writer)?;
+        Ok(found.len())
+    }
+
+    // Looks in the archive too, so archived hits can still be opened
+    pub fn get_document(&self, id: &str) -> Result<Option<SearchableDocument>> {
+        let searcher = self.searcher()?;
+        let query = TermQuery::new(
+            Term::from_field_text(self.id_field, id),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+        match top_docs.first() {
+            Some((_score, doc_address)) => {
+                let retrieved_doc = searcher.doc::<TantivyDocument>(*doc_address)?;
+                Ok(Some(self.to_searchable_document(&retrieved_doc)?))
+            }
+            None => match &self.archive {
+                Some(archive) => archive.get_document(id),
+                None => Ok(None),
+            },
+        }
+    }
+
+    // How the query parses and scores the document with the id, for
+    // explainQuery. Looks in the archive too, as get_document does.
+    pub fn explain(&self, query_str: &str, id: &str) -> Result<Option<QueryExplanation>> {
+        let searcher = self.searcher()?;
+        let query_parser = QueryParser::for_index(&self.index, vec![self.title_field, self.body_field]);
+        let query = query_parser.parse_query(query_str)?;
+        let id_query = TermQuery::new(Term::from_field_text(self.id_field, id), IndexRecordOption::Basic);
+        match searcher.search(&id_query, &TopDocs::with_limit(1))?.first() {
+            Some((_score, doc_address)) => Ok(Some(explain::explain(&searcher, query.as_ref(), *doc_address)?)),
+            None => match &self.archive {
+                Some(archive) => archive.explain(query_str, id),
+                None => Ok(None),
+            },
+        }
+    }
+
+    // How many documents, sections included, each version of one source or
+    // all has, by source and then version. Unversioned documents aren't
+    // counted.
+    pub fn version_counts(&self, source: Option<&str>) -> Result<BTreeMap<(String, String), usize>> {
+        let searcher = self.searcher()?;
+        let scope: Box<dyn Query> = match source {
+            Some(source) => Box::new(TermQuery::new(
+                Term::from_field_text(self.source_field, source),
+                IndexRecordOption::Basic,
+            )),
+            None => Box::new(AllQuery),
+        };
+        let mut counts = BTreeMap::new();
+        let (mut source_name, mut version) = (String::new(), String::new());
+        for address in searcher.search(scope.as_ref(), &DocSetCollector)? {
+            let fast_fields = searcher.segment_reader(address.segment_ord).fast_fields();
+            let (Some(sources), Some(versions)) = (fast_fields.str("source")?, fast_fields.str("version")?) else {
+                continue;
+            };
+            let ords = (sources.term_ords(address.doc_id).next(), versions.term_ords(address.doc_id).next());
+            let (Some(source_ord), Some(version_ord)) = ords else {
+                continue;
+            };
+            source_name.clear();
+            version.clear();
+            if !sources.ord_to_str(source_ord, &mut source_name)? || !versions.ord_to_str(version_ord, &mut version)? {
+                continue;
+            }
+            *counts.entry((source_name.clone(), version.clone())).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    // Every document of one version of a source, sections included
+    pub fn version_documents(&self, source: &str, version: &str) -> Result<Vec<SearchableDocument>> {
+        let searcher = self.searcher()?;
+        let query = BooleanQuery::intersection(vec![
+            Box::new(TermQuery::new(Term::from_field_text(self.source_field, source), IndexRecordOption::Basic)),
+            Box::new(TermQuery::new(Term::from_field_text(self.version_field, version), IndexRecordOption::Basic)),
+        ]);
+        let mut documents = Vec::new();
+        for address in searcher.search(&query, &DocSetCollector)? {
+            let stored = searcher.doc::<TantivyDocument>(address)?;
+            documents.push(self.to_searchable_document(&stored)?);
+        }
+        Ok(documents)
+    }
+
+    // The pages of a source last indexed before `before` (milliseconds since
+    // the Unix epoch), oldest first. Sections are left out: they go with
+    // their page.
+    pub fn stale_documents(&self, source: &str, before: u64) -> Result<Vec<SearchableDocument>> {
+        let searcher = self.searcher()?;
+        let indexed_before: Box<dyn Query> = Box::new(RangeQuery::new(
+            Bound::Unbounded,
+            Bound::Excluded(Term::from_field_u64(self.indexed_at_field, before)),
+        ));
+        let query = BooleanQuery::intersection(vec![
+            Box::new(TermQuery::new(Term::from_field_text(self.source_field, source), IndexRecordOption::Basic)),
+            indexed_before,
+        ]);
+        let mut documents = Vec::new();
+        for address in searcher.search(&query, &DocSetCollector)? {
+            let stored = searcher.doc::<TantivyDocument>(address)?;
+            let document = self.to_searchable_document(&stored)?;
+            if document.parent_id.is_none() {
+                documents.push(document);
+            }
+        }
+        documents.sort_by(|a, b| a.indexed_at.cmp(&b.indexed_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(documents)
+    }
+
+    // Pins an alias, which changes what searches for it find
+    pub fn set_alias(&self, source: &str, alias: &str, version: &str) -> Result<()> {
+        self.aliases.set(source, alias, version)?;
+        self.cache.clear();
         Ok(())
     }
 
+    pub fn remove_alias(&self, source: &str, alias: &str) -> Result<Option<String>> {
+        let removed = self.aliases.remove(source, alias)?;
+        self.cache.clear();
+        Ok(removed)
+    }
+
+    // The version `name` stands for in `source`: what it's pinned to, else
+    // what the automatic alias of that name works out to, else `name` itself
+    pub fn resolve_version(&self, source: &str, name: &str) -> Result<String> {
+        if let Some(version) = self.aliases.get(source, name) {
+            return Ok(version);
+        }
+        if AUTOMATIC_ALIASES.contains(&name) {
+            let automatic = self.automatic_aliases(Some(source))?.remove(source);
+            if let Some(version) = automatic.and_then(|mut aliases| aliases.remove(name)) {
+                return Ok(version);
+            }
+        }
+        Ok(name.to_string())
+    }
+
+    // Every alias of one source or all, pinned ones in place of automatic
+    // ones of the same name, by source and then alias
+    pub fn version_aliases(&self, source: Option<&str>) -> Result<Vec<VersionAlias>> {
+        let mut aliases: BTreeMap<(String, String), VersionAlias> = BTreeMap::new();
+        let automatic = self.automatic_aliases(source)?.into_iter().map(|entry| (entry, false));
+        let pinned = self.aliases.all().into_iter().map(|entry| (entry, true));
+        for ((alias_source, names), pinned) in automatic.chain(pinned) {
+            if source.is_some_and(|source| source != alias_source) {
+                continue;
+            }
+            for (alias, version) in names {
+                let key = (alias_source.clone(), alias.clone());
+                aliases.insert(key, VersionAlias { source: alias_source.clone(), alias, version, pinned });
+            }
+        }
+        Ok(aliases.into_values().collect())
+    }
+
+    // What the automatic aliases stand for in one source or all, e.g.
+    // {"tokio": {"latest": "1.39.0-rc.1", "stable": "1.38.0"}}. Sources
+    // without semver versions have none; `stable` needs a release.
+    fn automatic_aliases(&self, source: Option<&str>) -> Result<HashMap<String, BTreeMap<String, String>>> {
+        let searcher = self.searcher()?;
+        let scope: Box<dyn Query> = match source {
+            Some(source) => Box::new(TermQuery::new(
+                Term::from_field_text(self.source_field, source),
+                IndexRecordOption::Basic,
+            )),
+            None => Box::new(AllQuery),
+        };
+        // By source: the highest version, then the highest release
+        let mut highest: HashMap<String, [Option<(u64, String)>; 2]> = HashMap::new();
+        let (mut source_name, mut version) = (String::new(), String::new());
+        for address in searcher.search(scope.as_ref(), &DocSetCollector)? {
+            let fast_fields = searcher.segment_reader(address.segment_ord).fast_fields();
+            let (Some(sources), Some(versions), Ok(keys)) =
+                (fast_fields.str("source")?, fast_fields.str("version")?, fast_fields.u64("version_key"))
+            else {
+                continue;
+            };
+            let Some(key) = keys.first(address.doc_id) else {
+                continue;
+            };
+            let ords = (sources.term_ords(address.doc_id).next(), versions.term_ords(address.doc_id).next());
+            let (Some(source_ord), Some(version_ord)) = ords else {
+                continue;
+            };
+            source_name.clear();
+            version.clear();
+            if !sources.ord_to_str(source_ord, &mut source_name)? || !versions.ord_to_str(version_ord, &mut version)? {
+                continue;
+            }
+            let [latest, stable] = highest.entry(source_name.clone()).or_default();
+            if latest.as_ref().map_or(true, |(max, _)| key > *max) {
+                *latest = Some((key, version.clone()));
+            }
+            if is_release(key) && stable.as_ref().map_or(true, |(max, _)| key > *max) {
+                *stable = Some((key, version.clone()));
+            }
+        }
+        Ok(highest
+            .into_iter()
+            .map(|(source, found)| {
+                let aliases = AUTOMATIC_ALIASES
+                    .iter()
+                    .zip(found)
+                    .filter_map(|(alias, found)| Some((alias.to_string(), found?.1)))
+                    .collect();
+                (source, aliases)
+            })
+            .collect())
+    }
+
+    // What `name` stands for in each source that has it as an alias
+    fn versions_named(&self, name: &str) -> Result<HashMap<String, String>> {
+        let mut versions = HashMap::new();
+        if AUTOMATIC_ALIASES.contains(&name) {
+            for (source, mut aliases) in self.automatic_aliases(None)? {
+                if let Some(version) = aliases.remove(name) {
+                    versions.insert(source, version);
+                }
+            }
+        }
+        versions.extend(self.aliases.pinned_to(name));
+        Ok(versions)
+    }
+
+    // Pages through every document in the index, used for resource listings.
+    pub fn list_documents(&self, offset: usize, limit: usize) -> Result<Vec<SearchableDocument>> {
+        let searcher = self.searcher()?;
+        let top_docs = searcher.search(&AllQuery, &TopDocs::with_limit(limit).and_offset(offset))?;
+
+        let mut results = Vec::new();
+        for (_score, doc_address) in top_docs {
+            let retrieved_doc = searcher.doc::<TantivyDocument>(doc_address)?;
+            results.push(self.to_searchable_document(&retrieved_doc)?);
+        }
+        Ok(results)
+    }
+
+    // Bodies indexed under more than one id, largest groups first. Empty
+    // bodies, such as pages whose text all went into their sections, don't
+    // count.
+    pub fn find_duplicates(&self, limit: usize) -> Result<Vec<DuplicateGroup>> {
+        let searcher = self.searcher()?;
+        let empty_hash = sha256_hex(b"");
+        let mut by_hash: HashMap<String, Vec<DocAddress>> = HashMap::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let Some(hashes) = segment_reader.fast_fields().str("content_hash")? else {
+                continue;
+            };
+            let mut hash = String::new();
+            for doc_id in segment_reader.doc_ids_alive() {
+                for ord in hashes.term_ords(doc_id) {
+                    hash.clear();
+                    if hashes.ord_to_str(ord, &mut hash)? && hash != empty_hash {
+                        by_hash
+                            .entry(hash.clone())
+                            .or_default()
+                            .push(DocAddress::new(segment_ord as u32, doc_id));
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<(String, Vec<DocAddress>)> =
+            by_hash.into_iter().filter(|(_, addresses)| addresses.len() > 1).collect();
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+        groups
+            .into_iter()
+            .take(limit)
+            .map(|(content_hash, addresses)| {
+                let mut ids = Vec::with_capacity(addresses.len());
+                for address in addresses {
+                    let stored = searcher.doc::<TantivyDocument>(address)?;
+                    ids.extend(stored.get_first(self.id_field).and_then(|v| v.as_str()).map(String::from));
+                }
+                ids.sort();
+                Ok(DuplicateGroup {
+                    content_hash: Some(content_hash),
+                    ids,
+                })
+            })
+            .collect()
+    }
+
+    // Groups of documents whose bodies are near-duplicates, exact ones
+    // included, largest first. Hashes within NEAR_DUPLICATE_BITS of each
+    // other agree on at least one of four 16-bit bands, so only hashes that
+    // share a band are compared.
+    pub fn find_near_duplicates(&self, limit: usize) -> Result<Vec<DuplicateGroup>> {
+        let searcher = self.searcher()?;
+        let mut by_hash: HashMap<u64, Vec<DocAddress>> = HashMap::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let Ok(hashes) = segment_reader.fast_fields().u64("simhash") else {
+                continue;
+            };
+            for doc_id in segment_reader.doc_ids_alive() {
+                if let Some(hash) = hashes.first(doc_id).filter(|hash| *hash != 0) {
+                    by_hash.entry(hash).or_default().push(DocAddress::new(segment_ord as u32, doc_id));
+                }
+            }
+        }
+
+        let hashes: Vec<u64> = by_hash.keys().copied().collect();
+        let mut groups = DisjointSets::new(hashes.len());
+        for band in 0..4 {
+            let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+            for (i, hash) in hashes.iter().enumerate() {
+                buckets.entry((hash >> (band * 16)) & 0xffff).or_default().push(i);
+            }
+            for bucket in buckets.values() {
+                for (n, &i) in bucket.iter().enumerate() {
+                    for &j in &bucket[n + 1..] {
+                        if (hashes[i] ^ hashes[j]).count_ones() <= NEAR_DUPLICATE_BITS {
+                            groups.join(i, j);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut members: HashMap<usize, Vec<DocAddress>> = HashMap::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            members.entry(groups.root(i)).or_default().extend(&by_hash[hash]);
+        }
+        let mut members: Vec<Vec<DocAddress>> =
+            members.into_values().filter(|addresses| addresses.len() > 1).collect();
+        members.sort_by(|a, b| b.len().cmp(&a.len()));
+        members
+            .into_iter()
+            .take(limit)
+            .map(|addresses| {
+                let mut ids = Vec::with_capacity(addresses.len());
+                for address in addresses {
+                    let stored = searcher.doc::<TantivyDocument>(address)?;
+                    ids.extend(stored.get_first(self.id_field).and_then(|v| v.as_str()).map(String::from));
+                }
+                ids.sort();
+                Ok(DuplicateGroup { content_hash: None, ids })
+            })
+            .collect()
+    }
+
     pub fn search_documents(&self, query_str: &str, limit: usize) -> Result<Vec<SearchableDocument>> {
-        let reader = self.index
-            .reader_builder()
-            .reload_policy(ReloadPolicy::Manual) // Or OnCommit
-            .try_into()?;
+        self.search_documents_with(query_str, limit, &SearchOptions::default())
+    }
+
+    // Answered from the cache when the same search was run since the last
+    // commit
+    pub fn search_documents_with(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchableDocument>> {
+        self.search_documents_timed(query_str, limit, options).map(|(results, _)| results)
+    }
+
+    // search_documents_with, with where its time went; None when the cache
+    // answered
+    #[tracing::instrument(level = "debug", skip_all, fields(query = query_str, limit = limit))]
+    pub fn search_documents_timed(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<(Vec<SearchableDocument>, Option<SearchTimings>)> {
+        let Some(key) = SearchCache::key(query_str, limit, options) else {
+            let (results, timings) = self.logged_search(query_str, limit, options)?;
+            return Ok((results, Some(timings)));
+        };
+        let (cached, generation) = self.cache.get(&key);
+        if let Some(results) = cached {
+            return Ok((results.as_ref().clone(), None));
+        }
+        let (results, timings) = self.logged_search(query_str, limit, options)?;
+        self.cache.insert(key, generation, Arc::new(results.clone()));
+        Ok((results, Some(timings)))
+    }
 
-        let searcher = reader.searcher();
+    // Searches the index, and logs the search if it was slow
+    fn logged_search(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<(Vec<SearchableDocument>, SearchTimings)> {
+        let (results, timings) = self.run_search_timed(query_str, limit, options)?;
+        if let Some(slow_queries) = &self.slow_queries {
+            slow_queries.record(query_str, limit, options, results.len(), &timings);
+        }
+        Ok((results, timings))
+    }
+
+    // Always searches the index, for timing searches
+    pub fn run_search(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchableDocument>> {
+        self.run_search_timed(query_str, limit, options).map(|(results, _)| results)
+    }
+
+    // run_search, with where its time went
+    pub fn run_search_timed(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<(Vec<SearchableDocument>, SearchTimings)> {
+        let started = Instant::now();
+        let searcher = self.searcher()?;
         let query_parser = QueryParser::for_index(&self.index, vec![self.title_field, self.body_field]);
-        let query = query_parser.parse_query(query_str)?;
+        let preferences: Vec<PreferredVersion> =
+            options.preferred_versions.iter().chain(&options.pinned_versions).cloned().collect();
+        // A vector search only looks at the query's nearest neighbours, which
+        // then go through the same filters as any other hits
+        let neighbours = options.query_vector.as_ref().map(|vector| {
+            let wanted = limit * KNN_OVERFETCH;
+            self.vectors.read().unwrap().search(vector, wanted, wanted.max(KNN_MIN_EF))
+        });
+        let query: Box<dyn Query> = match &neighbours {
+            Some(neighbours) => {
+                let ids = neighbours.iter().map(|(id, _)| Term::from_field_text(self.id_field, id));
+                Box::new(TermSetQuery::new(ids))
+            }
+            None => self.apply_preferences(query_parser.parse_query(query_str)?, &preferences)?,
+        };
+        let query = self.apply_filters(query, options)?;
+        // Newest across the index, whatever the query matches, and preferring
+        // releases, as a reader following the latest docs would
+        let query = if options.latest_only {
+            let newest = self.highest_versions(&searcher, &AllQuery, true)?;
+            self.keep_versions(query, newest)
+        } else {
+            query
+        };
+        let query = if options.highest_version {
+            let highest = self.highest_versions(&searcher, query.as_ref(), false)?;
+            self.keep_versions(query, highest)
+        } else {
+            query
+        };
+        let planned = Instant::now();
 
-        let top_docs = searcher.search(&query, &(TopDocs::with_limit(limit), Count))?;
+        let top_docs = if let Some(neighbours) = &neighbours {
+            self.nearest_first(&searcher, query.as_ref(), neighbours, limit, options.keep_near_duplicates)?
+        } else if options.keep_near_duplicates {
+            self.top_docs(&searcher, query.as_ref(), limit, options)?
+        } else {
+            let top_docs = self.top_docs(&searcher, query.as_ref(), limit * NEAR_DUPLICATE_OVERFETCH, options)?;
+            let mut top_docs = self.drop_near_duplicates(&searcher, top_docs);
+            top_docs.truncate(limit);
+            top_docs
+        };
+        let collected = Instant::now();
         
         let mut results = Vec::new();
-        for (_score, doc_address) in top_docs.0 {
+        for doc_address in top_docs {
             // Use the correct type parameter with searcher.doc()
             let retrieved_doc = searcher.doc::<TantivyDocument>(doc_address)?;
-            
-            // Fix: use appropriate methods to extract text values
-            let id = retrieved_doc.get_first(self.id_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-                
-            let title = retrieved_doc.get_first(self.title_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-                
-            let body = retrieved_doc.get_first(self.body_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-                
-            let source = retrieved_doc.get_first(self.source_field)
+            results.push(self.to_searchable_document(&retrieved_doc)?);
+        }
+        let fetched = Instant::now();
+
+        // Archived versions are superseded, so their hits only fill what's
+        // left of the page. None of them is any source's newest.
+        if let Some(archive) = self.archive.as_ref().filter(|_| options.include_archived && !options.latest_only) {
+            if results.len() < limit {
+                let options = SearchOptions {
+                    include_archived: false,
+                    ..options.clone()
+                };
+                results.extend(archive.search_documents_with(query_str, limit - results.len(), &options)?);
+            }
+        }
+        let millis = |from: Instant, to: Instant| to.duration_since(from).as_secs_f64() * 1000.0;
+        let timings = SearchTimings {
+            parse_ms: millis(started, planned),
+            search_ms: millis(planned, collected),
+            fetch_ms: millis(collected, fetched),
+            total_ms: millis(started, Instant::now()),
+        };
+        Ok((results, timings))
+    }
+
+    // The best `per_version` hits for the query in each version of a source,
+    // to set what the versions say side by side. Versions may be aliases and
+    // keep their order. Without any, the newest `max_versions` versions with
+    // a hit are searched, oldest first.
+    pub fn search_by_version(
+        &self,
+        query_str: &str,
+        source: &str,
+        versions: &[String],
+        per_version: usize,
+        max_versions: usize,
+    ) -> Result<Vec<(String, Vec<SearchableDocument>)>> {
+        let searcher = self.searcher()?;
+        let query_parser = QueryParser::for_index(&self.index, vec![self.title_field, self.body_field]);
+        let query = query_parser.parse_query(query_str)?;
+        let term_query = |field: Field, text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic))
+        };
+
+        let versions = if versions.is_empty() {
+            let in_source = BooleanQuery::intersection(vec![query.box_clone(), term_query(self.source_field, source)]);
+            let mut found = HashSet::new();
+            let mut version = String::new();
+            for address in searcher.search(&in_source, &DocSetCollector)? {
+                let Some(column) = searcher.segment_reader(address.segment_ord).fast_fields().str("version")? else {
+                    continue;
+                };
+                for ord in column.term_ords(address.doc_id) {
+                    version.clear();
+                    if column.ord_to_str(ord, &mut version)? {
+                        found.insert(version.clone());
+                    }
+                }
+            }
+            // Newest first to cut the list, versions that aren't semver last
+            let mut found: Vec<String> = found.into_iter().collect();
+            found.sort_by(|a, b| version_key(b).cmp(&version_key(a)).then_with(|| a.cmp(b)));
+            found.truncate(max_versions);
+            found.reverse();
+            found
+        } else {
+            versions
+                .iter()
+                .map(|version| self.resolve_version(source, version))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut groups = Vec::with_capacity(versions.len());
+        for version in versions {
+            let scope = BooleanQuery::intersection(vec![
+                term_query(self.source_field, source),
+                term_query(self.version_field, &version),
+            ]);
+            let scoped = BooleanQuery::new(vec![
+                (Occur::Must, query.box_clone()),
+                (Occur::Must, Box::new(ConstScoreQuery::new(Box::new(scope), 0.0))),
+            ]);
+            let mut documents = Vec::new();
+            for (_score, address) in searcher.search(&scoped, &TopDocs::with_limit(per_version))? {
+                let retrieved_doc = searcher.doc::<TantivyDocument>(address)?;
+                documents.push(self.to_searchable_document(&retrieved_doc)?);
+            }
+            groups.push((version, documents));
+        }
+        Ok(groups)
+    }
+
+    // The best `limit` hits in the order `options` asks for. Sorting by a
+    // date keeps relevance as the tie-breaker.
+    fn top_docs(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<DocAddress>> {
+        let collector = TopDocs::with_limit(limit);
+        let addresses = match options.sort {
+            SearchSort::Relevance if options.freshness_half_life_days.is_none() && options.rank_by.is_empty() => {
+                searcher.search(query, &collector)?.into_iter().map(|(_, address)| address).collect()
+            }
+            SearchSort::Relevance => {
+                let half_life = options.freshness_half_life_days.map(|days| f64::from(days.max(1)) * MILLIS_PER_DAY);
+                let now = now_millis();
+                let rank_by: Vec<(String, f32)> = options
+                    .rank_by
+                    .iter()
+                    .map(|boost| (format!("metrics.{}", boost.metric), boost.weight))
+                    .collect();
+                let collector = collector.tweak_score(move |segment_reader: &SegmentReader| {
+                    let published = date_column(segment_reader, "published_at");
+                    let indexed = date_column(segment_reader, "indexed_at");
+                    let metrics: Vec<(Option<Column<f64>>, f32)> = rank_by
+                        .iter()
+                        .map(|(path, weight)| (segment_reader.fast_fields().f64(path).ok(), *weight))
+                        .collect();
+                    move |doc: DocId, score: Score| {
+                        let mut score = score;
+                        if let Some(half_life) = half_life {
+                            let date = first_date(&published, doc).or_else(|| first_date(&indexed, doc));
+                            let freshness =
+                                date.map_or(0.0, |date| 0.5f64.powf(now.saturating_sub(date) as f64 / half_life));
+                            score *= 1.0 + FRESHNESS_BOOST * freshness as f32;
+                        }
+                        for (column, weight) in &metrics {
+                            let value = column.as_ref().and_then(|column| column.first(doc)).unwrap_or(0.0);
+                            score += weight * value.max(0.0).ln_1p() as f32;
+                        }
+                        score
+                    }
+                });
+                searcher.search(query, &collector)?.into_iter().map(|(_, address)| address).collect()
+            }
+            SearchSort::Published | SearchSort::Indexed | SearchSort::Version => {
+                let field = match options.sort {
+                    SearchSort::Published => "published_at",
+                    SearchSort::Version => "version_key",
+                    _ => "indexed_at",
+                };
+                let collector = collector.tweak_score(move |segment_reader: &SegmentReader| {
+                    let dates = date_column(segment_reader, field);
+                    move |doc: DocId, score: Score| (first_date(&dates, doc).unwrap_or(0), score)
+                });
+                searcher.search(query, &collector)?.into_iter().map(|(_, address)| address).collect()
+            }
+        };
+        Ok(addresses)
+    }
+
+    // Each document as JSON with only `fields` in it, or whole when `fields`
+    // is empty. A snippet is only worked out when asked for; a hit that
+    // matched on its title alone gets the start of its body.
+    pub fn select_fields(
+        &self,
+        query_str: &str,
+        documents: Vec<SearchableDocument>,
+        fields: &[String],
+    ) -> Result<Vec<serde_json::Value>> {
+        let snippets = if fields.iter().any(|field| field == "snippet") {
+            let query_parser = QueryParser::for_index(&self.index, vec![self.title_field, self.body_field]);
+            let query = query_parser.parse_query(query_str)?;
+            let mut generator = SnippetGenerator::create(&self.searcher()?, query.as_ref(), self.body_field)?;
+            generator.set_max_num_chars(SNIPPET_CHARS);
+            Some(generator)
+        } else {
+            None
+        };
+        documents
+            .into_iter()
+            .map(|document| {
+                let snippet = snippets.as_ref().map(|generator| {
+                    let snippet = generator.snippet(&document.body);
+                    if snippet.is_empty() {
+                        let body = &document.body;
+                        let end = body.char_indices().nth(SNIPPET_CHARS).map_or(body.len(), |(i, _)| i);
+                        body[..end].trim().to_string()
+                    } else {
+                        snippet.fragment().trim().to_string()
+                    }
+                });
+                project_document(document, snippet, fields)
+            })
+            .collect()
+    }
+
+    // Keeps each hit unless a better one has nearly the same body. Hits
+    // without a SimHash, such as empty pages, are always kept.
+    // The matches of a vector search's filtered query, in the order of the
+    // neighbours they came from rather than by score
+    fn nearest_first(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        neighbours: &[(String, f32)],
+        limit: usize,
+        keep_near_duplicates: bool,
+    ) -> Result<Vec<DocAddress>> {
+        let rank: HashMap<&str, usize> =
+            neighbours.iter().enumerate().map(|(i, (id, _))| (id.as_str(), i)).collect();
+        let mut found = Vec::new();
+        for address in searcher.search(query, &DocSetCollector)? {
+            let stored = searcher.doc::<TantivyDocument>(address)?;
+            let id = stored.get_first(self.id_field).and_then(|v| v.as_str());
+            if let Some(&i) = id.and_then(|id| rank.get(id)) {
+                found.push((i, address));
+            }
+        }
+        found.sort_by_key(|(i, _)| *i);
+        let addresses = found.into_iter().map(|(_, address)| address).collect();
+        let mut addresses = if keep_near_duplicates {
+            addresses
+        } else {
+            self.drop_near_duplicates(searcher, addresses)
+        };
+        addresses.truncate(limit);
+        Ok(addresses)
+    }
+
+    fn drop_near_duplicates(&self, searcher: &Searcher, addresses: Vec<DocAddress>) -> Vec<DocAddress> {
+        let mut columns: HashMap<u32, Option<Column<u64>>> = HashMap::new();
+        let mut kept_hashes: Vec<u64> = Vec::new();
+        let mut kept = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let column = columns.entry(address.segment_ord).or_insert_with(|| {
+                searcher.segment_reader(address.segment_ord).fast_fields().u64("simhash").ok()
+            });
+            let hash = column.as_ref().and_then(|column| column.first(address.doc_id)).unwrap_or(0);
+            if hash != 0 {
+                if kept_hashes.iter().any(|kept| (kept ^ hash).count_ones() <= NEAR_DUPLICATE_BITS) {
+                    continue;
+                }
+                kept_hashes.push(hash);
+            }
+            kept.push(address);
+        }
+        kept
+    }
+
+    // Keeps the user's query as the only required clause and adds constant
+    // score bonuses for preferred sources/versions, so preferences reorder
+    // results without ever filtering anything out.
+    fn apply_preferences(&self, query: Box<dyn Query>, preferences: &[PreferredVersion]) -> Result<Box<dyn Query>> {
+        if preferences.is_empty() {
+            return Ok(query);
+        }
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        for preference in preferences {
+            let source_query: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_field_text(self.source_field, &preference.source),
+                IndexRecordOption::Basic,
+            ));
+            clauses.push((
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(source_query.box_clone(), PREFERRED_SOURCE_BOOST)),
+            ));
+
+            if let Some(version) = &preference.version {
+                let both = BooleanQuery::intersection(vec![source_query, self.version_prefix_query(version)?]);
+                clauses.push((
+                    Occur::Should,
+                    Box::new(ConstScoreQuery::new(Box::new(both), PREFERRED_VERSION_BOOST)),
+                ));
+            }
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    // "1.38" should match "1.38" as well as "1.38.0", "1.38.1", ...
+    fn version_prefix_query(&self, version: &str) -> Result<Box<dyn Query>> {
+        let pattern = format!("{}(\\..*)?", escape_regex(version));
+        Ok(Box::new(RegexQuery::from_pattern(&pattern, self.version_field)?))
+    }
+
+    // The children of `parent` with how many documents fall under each, for
+    // browsing the taxonomy. Only documents matching `query_str` are counted
+    // when there is one.
+    pub fn facet_counts(&self, query_str: Option<&str>, parent: &str) -> Result<Vec<FacetCount>> {
+        let searcher = self.searcher()?;
+        let parent = parse_facet(parent)?;
+        let query: Box<dyn Query> = match query_str {
+            Some(query_str) => {
+                QueryParser::for_index(&self.index, vec![self.title_field, self.body_field]).parse_query(query_str)?
+            }
+            None => Box::new(AllQuery),
+        };
+        let mut collector = FacetCollector::for_field("facet");
+        collector.add_facet(parent.clone());
+        let counts = searcher.search(query.as_ref(), &collector)?;
+        Ok(counts
+            .get(parent)
+            .map(|(facet, count)| FacetCount {
+                facet: facet.to_string(),
+                count,
+            })
+            .collect())
+    }
+
+    // The highest version of each source among the documents `scope`
+    // matches. With `releases_first`, a source's pre-releases only count
+    // when none of its releases are indexed.
+    fn highest_versions(
+        &self,
+        searcher: &Searcher,
+        scope: &dyn Query,
+        releases_first: bool,
+    ) -> Result<HashMap<String, u64>> {
+        let mut highest: HashMap<String, (bool, u64)> = HashMap::new();
+        let mut source = String::new();
+        for address in searcher.search(scope, &DocSetCollector)? {
+            let segment_reader = searcher.segment_reader(address.segment_ord);
+            let (Some(sources), Ok(keys)) =
+                (segment_reader.fast_fields().str("source")?, segment_reader.fast_fields().u64("version_key"))
+            else {
+                continue;
+            };
+            let Some(key) = keys.first(address.doc_id) else {
+                continue;
+            };
+            let rank = (!releases_first || is_release(key), key);
+            for ord in sources.term_ords(address.doc_id) {
+                source.clear();
+                if sources.ord_to_str(ord, &mut source)? {
+                    let max = highest.entry(source.clone()).or_insert(rank);
+                    *max = (*max).max(rank);
+                }
+            }
+        }
+        Ok(highest.into_iter().map(|(source, (_, key))| (source, key)).collect())
+    }
+
+    // Narrows `query` to each source's document at `versions`. Documents
+    // whose version has no semver key are all kept, since there's no telling
+    // which is highest.
+    fn keep_versions(&self, query: Box<dyn Query>, versions: HashMap<String, u64>) -> Box<dyn Query> {
+        let unversioned: Box<dyn Query> = Box::new(BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::MustNot, Box::new(ExistsQuery::new("version_key".to_string(), false))),
+        ]));
+        let mut allowed = vec![unversioned];
+        for (source, key) in versions {
+            allowed.push(Box::new(BooleanQuery::intersection(vec![
+                Box::new(TermQuery::new(Term::from_field_text(self.source_field, &source), IndexRecordOption::Basic)),
+                Box::new(TermQuery::new(Term::from_field_u64(self.version_key_field, key), IndexRecordOption::Basic)),
+            ])));
+        }
+        let allowed = ConstScoreQuery::new(Box::new(BooleanQuery::union(allowed)), 0.0);
+        Box::new(BooleanQuery::new(vec![(Occur::Must, query), (Occur::Must, Box::new(allowed))]))
+    }
+
+    // Requires every tag in `tags_all`, one of `tags_any`, every metric range,
+    // the facet, the source, the version range and the pinned versions. The
+    // filters score nothing, so they only drop results and never reorder them.
+    fn apply_filters(&self, query: Box<dyn Query>, options: &SearchOptions) -> Result<Box<dyn Query>> {
+        if options.tags_any.is_empty()
+            && options.tags_all.is_empty()
+            && options.ranges.is_empty()
+            && options.facet.is_none()
+            && options.source.is_none()
+            && options.version_range.is_none()
+            && options.version.is_none()
+            && options.pinned_versions.is_empty()
+        {
+            return Ok(query);
+        }
+
+        let tag_query = |tag: &String| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(self.tags_field, &normalize_tag(tag)),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        for tag in &options.tags_all {
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(tag_query(tag), 0.0))));
+        }
+        if !options.tags_any.is_empty() {
+            let any = BooleanQuery::union(options.tags_any.iter().map(tag_query).collect());
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(Box::new(any), 0.0))));
+        }
+        for range in &options.ranges {
+            let term = |value: f64| {
+                let mut term = Term::from_field_json_path(self.metrics_field, &range.metric, false);
+                term.append_type_and_fast_value(value);
+                term
+            };
+            let lower = range.min.map_or(Bound::Unbounded, |min| Bound::Included(term(min)));
+            let upper = range.max.map_or(Bound::Unbounded, |max| Bound::Included(term(max)));
+            // A range query needs a bound to know its field; an open range
+            // still requires the metric to be there
+            let (lower, upper) = match (lower, upper) {
+                (Bound::Unbounded, Bound::Unbounded) => (Bound::Included(term(f64::MIN)), Bound::Unbounded),
+                bounds => bounds,
+            };
+            let range_query: Box<dyn Query> = Box::new(RangeQuery::new(lower, upper));
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(range_query, 0.0))));
+        }
+        if let Some(facet) = &options.facet {
+            // A facet is indexed with all its ancestors, so this matches
+            // everything below it too
+            let facet_query: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_facet(self.facet_field, &parse_facet(facet)?),
+                IndexRecordOption::Basic,
+            ));
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(facet_query, 0.0))));
+        }
+        if let Some(source) = &options.source {
+            let source_query: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_field_text(self.source_field, source),
+                IndexRecordOption::Basic,
+            ));
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(source_query, 0.0))));
+        }
+        if let Some(version_range) = &options.version_range {
+            let range = VersionRange::parse(version_range)?;
+            let term = |key: u64| Term::from_field_u64(self.version_key_field, key);
+            let upper = range.end.map_or(Bound::Unbounded, |end| Bound::Excluded(term(end)));
+            let range_query: Box<dyn Query> = Box::new(RangeQuery::new(Bound::Included(term(range.min)), upper));
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(range_query, 0.0))));
+        }
+        if let Some(version) = &options.version {
+            let term_query = |field: Field, text: &str| -> Box<dyn Query> {
+                Box::new(TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic))
+            };
+            // Documents indexed under the name itself match too
+            let mut allowed = vec![term_query(self.version_field, version)];
+            for (source, concrete) in self.versions_named(version)? {
+                allowed.push(Box::new(BooleanQuery::intersection(vec![
+                    term_query(self.source_field, &source),
+                    term_query(self.version_field, &concrete),
+                ])));
+            }
+            let version_query: Box<dyn Query> = Box::new(BooleanQuery::union(allowed));
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(version_query, 0.0))));
+        }
+        if !options.pinned_versions.is_empty() {
+            let searcher = self.searcher()?;
+            for pin in &options.pinned_versions {
+                let Some(version) = &pin.version else {
+                    continue;
+                };
+                let source_query: Box<dyn Query> = Box::new(TermQuery::new(
+                    Term::from_field_text(self.source_field, &pin.source),
+                    IndexRecordOption::Basic,
+                ));
+                // A pin whose version isn't indexed only ranks, rather than
+                // hiding the source
+                let pinned =
+                    BooleanQuery::intersection(vec![source_query.box_clone(), self.version_prefix_query(version)?]);
+                if searcher.search(&pinned, &Count)? == 0 {
+                    continue;
+                }
+                // The source's unversioned documents stay
+                let other_versions: Box<dyn Query> = Box::new(BooleanQuery::new(vec![
+                    (Occur::Must, source_query),
+                    (Occur::Must, Box::new(ExistsQuery::new("version".to_string(), false))),
+                    (Occur::MustNot, self.version_prefix_query(version)?),
+                ]));
+                clauses.push((Occur::MustNot, other_versions));
+            }
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    fn to_searchable_document(&self, retrieved_doc: &TantivyDocument) -> Result<SearchableDocument> {
+        // Fix: use appropriate methods to extract text values
+        let text = |field: Field| {
+            retrieved_doc.get_first(field)
                 .and_then(|v| v.as_str())
                 .unwrap_or_default()
-                .to_string();
-                
-            let version = retrieved_doc.get_first(self.version_field)
-                .and_then(|v| v.as_str())
-                .map(String::from);
-                
-            results.push(SearchableDocument {
-                id,
-                title,
-                body,
-                source,
-                version,
-            });
+                .to_string()
+        };
+
+        let version = retrieved_doc.get_first(self.version_field)
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let url = retrieved_doc.get_first(self.url_field)
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        let date = |field: Field| retrieved_doc.get_first(field).and_then(|v| v.as_u64());
+        let metrics = retrieved_doc
+            .get_first(self.metrics_field)
+            .and_then(|v| v.as_object())
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, v)| {
+                let value = v.as_f64().or_else(|| v.as_i64().map(|n| n as f64)).or_else(|| v.as_u64().map(|n| n as f64));
+                Some((name.to_string(), value?))
+            })
+            .collect();
+        let tags = retrieved_doc.get_all(self.tags_field)
+            .filter_map(|v| v.as_str())
+            .map(String::from)
+            .collect();
+
+        let (title, body, heading_path) = match &self.cipher {
+            Some(cipher) => {
+                let sealed = retrieved_doc.get_first(self.sealed_field)
+                    .and_then(|v| v.as_bytes())
+                    .ok_or_else(|| anyhow!("Document {} has no encrypted fields", text(self.id_field)))?;
+                let fields: SealedFields = serde_json::from_slice(&cipher.open(sealed)?)?;
+                (fields.title, fields.body, fields.heading_path)
+            }
+            None => {
+                let heading_path = retrieved_doc.get_all(self.heading_path_field)
+                    .filter_map(|v| v.as_str())
+                    .map(String::from)
+                    .collect();
+                let body_field = if self.body_store.is_some() { self.stored_body_field } else { self.body_field };
+                (text(self.title_field), text(body_field), heading_path)
+            }
+        };
+        let external = retrieved_doc.get_first(self.external_body_field).and_then(|v| v.as_bool()) == Some(true);
+        let body = match &self.body_store {
+            Some(store) if external => self.load_body(store, &text(self.id_field), body)?,
+            _ => body,
+        };
+        let optional_text = |field: Field| retrieved_doc.get_first(field).and_then(|v| v.as_str()).map(String::from);
+
+        Ok(SearchableDocument {
+            id: text(self.id_field),
+            title,
+            body,
+            source: text(self.source_field),
+            version,
+            url,
+            tags,
+            indexed_at: date(self.indexed_at_field),
+            published_at: date(self.published_at_field),
+            metrics,
+            facet: retrieved_doc
+                .get_first(self.facet_field)
+                .and_then(|v| v.as_facet())
+                .and_then(|encoded| Facet::from_encoded(encoded.as_bytes().to_vec()).ok())
+                .map(|facet| facet.to_string()),
+            parent_id: optional_text(self.parent_id_field),
+            heading_path,
+            anchor: optional_text(self.anchor_field),
+            content_hash: optional_text(self.content_hash_field),
+        })
+    }
+}
+
+// "/npm/react/18.2.0" out of ["npm", "react", "18.2.0"]. Slashes within a
+// segment are escaped, and empty segments are left out.
+pub fn facet_path<S: AsRef<str>>(segments: impl IntoIterator<Item = S>) -> String {
+    facet_from_segments(segments).to_string()
+}
+
+fn facet_from_segments<S: AsRef<str>>(segments: impl IntoIterator<Item = S>) -> Facet {
+    let segments: Vec<String> = segments
+        .into_iter()
+        .map(|segment| segment.as_ref().trim().to_string())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    Facet::from_path(segments)
+}
+
+pub fn parse_facet(facet: &str) -> Result<Facet> {
+    Facet::from_text(facet).map_err(|e| anyhow!("{}", e))
+}
+
+// A document's source, split at ':' and '/' ("github:rust-lang/book" is
+// /github/rust-lang/book), then its version
+fn default_facet(document: &SearchableDocument) -> Facet {
+    let mut segments: Vec<&str> = document.source.split([':', '/']).collect();
+    segments.extend(document.version.as_deref());
+    facet_from_segments(segments)
+}
+
+// Everything the caller sent, so re-adding a document that changed in any
+// way, its tags or metrics say, writes it again
+fn fingerprint(document: &SearchableDocument) -> Result<String> {
+    let sent = SearchableDocument {
+        indexed_at: None,
+        content_hash: None,
+        ..document.clone()
+    };
+    Ok(sha256_hex(&serde_json::to_vec(&sent)?))
+}
+
+// 64-bit SimHash of a body's word shingles: each bit is the majority vote of
+// that bit across the shingles' hashes, so bodies that differ in a few words
+// get hashes a few bits apart. 0 for a body without words.
+fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return 0;
+    }
+    let mut votes = [0i64; 64];
+    for shingle in words.windows(SIMHASH_SHINGLE_WORDS.min(words.len())) {
+        let hash = shingle_hash(shingle);
+        for (bit, vote) in votes.iter_mut().enumerate() {
+            *vote += if (hash >> bit) & 1 == 1 { 1 } else { -1 };
         }
-        Ok(results)
     }
-} 
\ No newline at end of file
+    votes
+        .iter()
+        .enumerate()
+        .filter(|(_, vote)| **vote > 0)
+        .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+// FNV-1a, then SplitMix64's finalizer to spread it over all 64 bits. Stable
+// across releases, unlike std's hasher, since the hashes are stored.
+fn shingle_hash(words: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in words.iter().flat_map(|word| word.bytes().chain([b' '])) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+// Union-find over 0..n, for grouping near-duplicates
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> Self {
+        Self { parents: (0..n).collect() }
+    }
+
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parents[a] = b;
+    }
+}
+
+// A document as JSON cut down to `fields`, with its snippet if there is
+// one; whole when `fields` is empty
+pub fn project_document(
+    document: SearchableDocument,
+    snippet: Option<String>,
+    fields: &[String],
+) -> Result<serde_json::Value> {
+    let serde_json::Value::Object(mut object) = serde_json::to_value(document)? else {
+        return Err(anyhow!("Document did not serialize to an object"));
+    };
+    if fields.is_empty() {
+        return Ok(serde_json::Value::Object(object));
+    }
+    if let Some(snippet) = snippet {
+        object.insert("snippet".to_string(), serde_json::Value::String(snippet));
+    }
+    object.retain(|key, _| fields.iter().any(|field| field == key));
+    Ok(serde_json::Value::Object(object))
+}
+
+// The start of a body, cut at a character boundary
+fn body_excerpt(body: &str) -> String {
+    let end = (0..=BODY_EXCERPT_BYTES.min(body.len())).rev().find(|&i| body.is_char_boundary(i)).unwrap_or(0);
+    body[..end].to_string()
+}
+
+// None for segments where no document has the field
+fn date_column(segment_reader: &SegmentReader, field: &str) -> Option<Column<u64>> {
+    segment_reader.fast_fields().u64(field).ok()
+}
+
+fn first_date(column: &Option<Column<u64>>, doc: DocId) -> Option<u64> {
+    column.as_ref().and_then(|column| column.first(doc))
+}
+
+// Tags match case-insensitively: "Async" and "async " are the same tag
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
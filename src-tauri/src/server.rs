@@ -0,0 +1,285 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::serve::Listener;
+use axum::Router;
+use serde::Serialize;
+use tokio::net::TcpListener;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::rpc::{self, RpcState};
+use crate::McpError;
+use crate::settings::ServerSettings;
+use crate::tls::{self, TlsListener};
+
+// How long a stop waits for in-flight requests before giving up on them.
+// Open SSE/WebSocket streams never finish on their own.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ServerStatus {
+    pub running: bool,
+    pub address: Option<String>,
+    pub port: u16,
+    pub tls: bool,
+    // The certificate clients need to trust when it is self-signed
+    pub certificate: Option<String>,
+    pub unix_socket: Option<String>,
+    pub named_pipe: Option<String>,
+    pub last_error: Option<String>,
+}
+
+// All listeners of one server run share a shutdown signal
+struct RunningServer {
+    address: Option<SocketAddr>,
+    certificate: Option<PathBuf>,
+    unix_socket: Option<PathBuf>,
+    named_pipe: Option<String>,
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct ServerRuntime {
+    running: Option<RunningServer>,
+    port: u16,
+    last_error: Option<String>,
+}
+
+// Owns the embedded RPC server so it can be started, stopped and restarted
+// on another port without restarting the app.
+pub struct RpcServer {
+    rpc_state: RpcState,
+    // Where a self-signed certificate is generated when TLS has none
+    tls_dir: PathBuf,
+    runtime: Mutex<ServerRuntime>,
+}
+
+impl RpcServer {
+    pub fn new(rpc_state: RpcState, tls_dir: PathBuf) -> Self {
+        Self {
+            rpc_state,
+            tls_dir,
+            runtime: Mutex::new(ServerRuntime::default()),
+        }
+    }
+
+    // Binds `bind_address:port` (unless TCP is turned off) and, if configured,
+    // a Unix domain socket, and serves the RPC router on each. A bind failure
+    // is kept in the status instead of taking the app down.
+    pub async fn start(&self, settings: &ServerSettings) -> Result<ServerStatus, McpError> {
+        let mut runtime = self.runtime.lock().await;
+        if runtime.running.is_some() {
+            return Err(McpError::server("RPC server is already running"));
+        }
+        runtime.port = settings.port;
+        self.rpc_state.rate_limiter.configure(&settings.rate_limit);
+        self.rpc_state.import_roots.configure(&settings.import_roots);
+        self.rpc_state.sessions.set_idle_timeout(Duration::from_secs(settings.session_idle_minutes * 60));
+
+        match self.spawn_listeners(settings).await {
+            Ok(running) => {
+                // Report the port actually bound when the OS picked one
+                if let Some(address) = running.address {
+                    runtime.port = address.port();
+                }
+                runtime.last_error = None;
+                runtime.running = Some(running);
+                Ok(Self::status_of(&runtime))
+            }
+            Err(message) => {
+                error!("{}", message);
+                runtime.last_error = Some(message.clone());
+                Err(McpError::server(message))
+            }
+        }
+    }
+
+    async fn spawn_listeners(&self, settings: &ServerSettings) -> Result<RunningServer, String> {
+        if !settings.tcp_enabled && settings.unix_socket.is_none() && settings.named_pipe.is_none() {
+            return Err("TCP is disabled and no Unix socket or named pipe is configured; nothing to serve on".to_string());
+        }
+
+        let router = rpc::create_rpc_router(self.rpc_state.clone(), settings);
+        let (shutdown, shutdown_signal) = watch::channel(false);
+        let mut running = RunningServer {
+            address: None,
+            certificate: None,
+            unix_socket: None,
+            named_pipe: None,
+            shutdown,
+            tasks: Vec::new(),
+        };
+
+        if settings.tcp_enabled {
+            if !settings.bind_address.is_loopback() && !settings.allow_remote {
+                return Err(format!(
+                    "Refusing to bind to non-loopback address {}; set allow_remote to expose the API to the network",
+                    settings.bind_address
+                ));
+            }
+
+            let port = settings.port;
+            let listener = TcpListener::bind((settings.bind_address, port)).await.map_err(|e| {
+                if e.kind() == io::ErrorKind::AddrInUse {
+                    format!("Port {} is already in use. Pick another port and restart the server.", port)
+                } else {
+                    format!("Failed to bind RPC server on {}:{}: {}", settings.bind_address, port, e)
+                }
+            })?;
+            let address = listener.local_addr().map_err(|e| e.to_string())?;
+
+            if settings.tls.enabled {
+                let (config, certificate) = tls::server_config(&settings.tls, settings.bind_address, &self.tls_dir)
+                    .map_err(|e| format!("Failed to set up TLS: {:#}", e))?;
+                let listener = TlsListener::new(listener, Arc::clone(&config)).map_err(|e| e.to_string())?;
+                info!("RPC server listening on https://{}/rpc", address);
+                running.certificate = Some(certificate);
+                running.tasks.push(spawn_server(listener, router.clone(), shutdown_signal.clone()));
+            } else {
+                info!("RPC server listening on http://{}/rpc", address);
+                running.tasks.push(spawn_server(listener, router.clone(), shutdown_signal.clone()));
+            }
+            running.address = Some(address);
+        }
+
+        if let Some(path) = &settings.unix_socket {
+            #[cfg(unix)]
+            {
+                let listener = bind_unix_socket(path)
+                    .map_err(|e| format!("Failed to bind Unix socket {:?}: {}", path, e));
+                let listener = match listener {
+                    Ok(listener) => listener,
+                    Err(message) => {
+                        // Don't leave the TCP side running half-configured
+                        Self::shut_down(running).await;
+                        return Err(message);
+                    }
+                };
+                info!("RPC server listening on unix:{}", path.display());
+                running.unix_socket = Some(path.clone());
+                running.tasks.push(spawn_server(listener, router.clone(), shutdown_signal.clone()));
+            }
+            #[cfg(not(unix))]
+            warn!("Ignoring unix_socket {:?}: Unix sockets are not supported on this platform", path);
+        }
+
+        if let Some(name) = &settings.named_pipe {
+            #[cfg(windows)]
+            {
+                let listener = crate::pipe::NamedPipeListener::bind(name)
+                    .map_err(|e| format!("Failed to create named pipe {}: {}", name, e));
+                let listener = match listener {
+                    Ok(listener) => listener,
+                    Err(message) => {
+                        Self::shut_down(running).await;
+                        return Err(message);
+                    }
+                };
+                info!("RPC server listening on pipe:{}", name);
+                running.named_pipe = Some(name.clone());
+                running.tasks.push(spawn_server(listener, router.clone(), shutdown_signal.clone()));
+            }
+            #[cfg(not(windows))]
+            warn!("Ignoring named_pipe {}: named pipes are only supported on Windows", name);
+        }
+
+        Ok(running)
+    }
+
+    pub fn state(&self) -> &RpcState {
+        &self.rpc_state
+    }
+
+    pub async fn stop(&self) {
+        let running = self.runtime.lock().await.running.take();
+        if let Some(running) = running {
+            Self::shut_down(running).await;
+        }
+    }
+
+    async fn shut_down(running: RunningServer) {
+        let _ = running.shutdown.send(true);
+        // Listeners are released as soon as the signal lands; this only gives
+        // open requests a moment to complete.
+        for mut task in running.tasks {
+            if tokio::time::timeout(SHUTDOWN_GRACE, &mut task).await.is_err() {
+                task.abort();
+            }
+        }
+        if let Some(path) = &running.unix_socket {
+            let _ = std::fs::remove_file(path);
+        }
+        info!("RPC server stopped");
+    }
+
+    pub async fn restart(&self, settings: &ServerSettings) -> Result<ServerStatus, McpError> {
+        self.stop().await;
+        self.start(settings).await
+    }
+
+    pub async fn status(&self) -> ServerStatus {
+        Self::status_of(&*self.runtime.lock().await)
+    }
+
+    fn status_of(runtime: &ServerRuntime) -> ServerStatus {
+        ServerStatus {
+            running: runtime.running.is_some(),
+            address: runtime.running.as_ref().and_then(|r| r.address).map(|a| a.to_string()),
+            port: runtime.port,
+            tls: runtime.running.as_ref().is_some_and(|r| r.certificate.is_some()),
+            certificate: runtime
+                .running
+                .as_ref()
+                .and_then(|r| r.certificate.as_ref())
+                .map(|p| p.display().to_string()),
+            unix_socket: runtime
+                .running
+                .as_ref()
+                .and_then(|r| r.unix_socket.as_ref())
+                .map(|p| p.display().to_string()),
+            named_pipe: runtime.running.as_ref().and_then(|r| r.named_pipe.clone()),
+            last_error: runtime.last_error.clone(),
+        }
+    }
+}
+
+fn spawn_server<L>(listener: L, router: Router, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()>
+where
+    L: Listener,
+    L::Addr: std::fmt::Debug,
+{
+    tokio::spawn(async move {
+        let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+            let _ = shutdown.changed().await;
+        });
+        if let Err(e) = server.await {
+            error!("RPC server stopped: {}", e);
+        }
+    })
+}
+
+#[cfg(unix)]
+fn bind_unix_socket(path: &std::path::Path) -> io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // A socket file left behind by a crash would make bind fail
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)?;
+    // Only the current user may connect
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+// Default port of the embedded JSON-RPC / MCP server
+pub const DEFAULT_RPC_PORT: u16 = 3000;
+
+// Room for a decent batch of documents in one addDocuments call
+const DEFAULT_MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+
+// Long enough that a client thinking between calls keeps its session
+pub const DEFAULT_SESSION_IDLE_MINUTES: u64 = 30;
+
+// The Tauri webview's own origins (macOS/Linux, then Windows)
+const DEFAULT_ALLOWED_ORIGINS: &[&str] = &["tauri://localhost", "http://tauri.localhost"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ServerSettings {
+    // Loopback by default; anything else also needs `allow_remote`
+    pub bind_address: IpAddr,
+    // 0 lets the OS pick a free port; the status reports the one in use
+    pub port: u16,
+    // Opt-in for binding to a non-loopback address, which exposes the API
+    // to the network
+    pub allow_remote: bool,
+    // Turn off to serve only over the Unix socket or named pipe
+    pub tcp_enabled: bool,
+    // Also serve the RPC API on this Unix domain socket (macOS/Linux)
+    pub unix_socket: Option<PathBuf>,
+    // Also serve the RPC API on this named pipe, e.g. `\\.\pipe\latest-docs-mcp` (Windows)
+    pub named_pipe: Option<String>,
+    // Browser origins allowed to call the API; "*" allows any website
+    pub allowed_origins: Vec<String>,
+    // HTTPS for the TCP listener; the Unix socket and named pipe stay plain
+    pub tls: TlsSettings,
+    pub rate_limit: RateLimitSettings,
+    // Largest request body or WebSocket message accepted, in bytes
+    pub max_request_bytes: usize,
+    // MCP sessions with no request for this many minutes are dropped, with
+    // their event history
+    pub session_idle_minutes: u64,
+    // Folders RPC and REST callers may index local files from; none by
+    // default. The app's own imports can read anywhere.
+    pub import_roots: Vec<PathBuf>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: DEFAULT_RPC_PORT,
+            allow_remote: false,
+            tcp_enabled: true,
+            unix_socket: None,
+            named_pipe: None,
+            allowed_origins: DEFAULT_ALLOWED_ORIGINS.iter().map(|o| o.to_string()).collect(),
+            tls: TlsSettings::default(),
+            rate_limit: RateLimitSettings::default(),
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+            session_idle_minutes: DEFAULT_SESSION_IDLE_MINUTES,
+            import_roots: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TlsSettings {
+    pub enabled: bool,
+    // PEM files; leave both unset to use a generated self-signed certificate
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    // Extra DNS names or IP addresses the generated certificate covers,
+    // besides localhost and the bind address
+    pub hostnames: Vec<String>,
+}
+
+// User-editable settings, persisted as JSON in the app config dir. Every
+// field has a default so older settings files keep loading.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub index: IndexSettings,
+    pub batching: BatchSettings,
+    pub writer: WriterSettings,
+    pub slow_queries: SlowQuerySettings,
+    pub log: LogSettings,
+    // Content extraction overrides for pages fetched from the web, by source
+    pub extraction: HashMap<String, SourceExtraction>,
+    pub fetch: FetchSettings,
+    pub staleness: StalenessSettings,
+    pub release_watch: ReleaseWatchSettings,
+    pub embeddings: EmbeddingSettings,
+    pub rerank: RerankSettings,
+    pub chunking: ChunkingSettings,
+    pub assistant: AssistantSettings,
+}
+
+// Read once at startup, so changes apply the next time the app starts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct IndexSettings {
+    // Store document titles and bodies encrypted; see crypto.rs
+    pub encrypt_at_rest: bool,
+    // Where the encryption key is kept
+    pub key_storage: KeyStorage,
+    // Keep bodies longer than this many bytes in files beside the index,
+    // which then stores only their start; see bodies.rs
+    pub external_body_bytes: Option<usize>,
+    // Read the index into the page cache in the background after opening
+    // it, so the first search isn't slowed by cold reads
+    pub warm_up: bool,
+    pub docstore: DocstoreSettings,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            encrypt_at_rest: false,
+            key_storage: KeyStorage::default(),
+            external_body_bytes: None,
+            warm_up: true,
+            docstore: DocstoreSettings::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStorage {
+    // Keychain on macOS, Credential Manager on Windows, the Secret Service
+    // (GNOME Keyring, KWallet) on Linux
+    #[default]
+    Keychain,
+    // index.key in the config directory, for systems without a keychain
+    File,
+}
+
+// How tantivy compresses stored fields, bodies above all. Segments keep the
+// compression they were written with, so a change reaches old documents as
+// their segments are merged.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DocstoreSettings {
+    pub compression: DocstoreCompression,
+    // For zstd, 1 to 22; None is zstd's default of 3
+    pub zstd_level: Option<i32>,
+    // Bytes of documents compressed together. Bigger blocks compress better
+    // but make reading one document decompress more.
+    pub block_size: usize,
+}
+
+impl Default for DocstoreSettings {
+    fn default() -> Self {
+        Self {
+            compression: DocstoreCompression::Lz4,
+            zstd_level: None,
+            block_size: 16_384,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocstoreCompression {
+    None,
+    // Fast, tantivy's default
+    #[default]
+    Lz4,
+    // Smaller, and slower to write
+    Zstd,
+}
+
+// How single addDocument calls are batched into commits; see batching.rs.
+// Changes apply to the batch being filled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BatchSettings {
+    // A batch is committed as soon as it holds this many documents
+    pub max_documents: usize,
+    // or this long after its first document was queued, whichever is first
+    pub max_delay_ms: u64,
+}
+
+impl Default for BatchSettings {
+    fn default() -> Self {
+        Self {
+            max_documents: 100,
+            max_delay_ms: 1000,
+        }
+    }
+}
+
+// The index writer each write opens; see search.rs. Changes apply to the
+// next write.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WriterSettings {
+    // Memory the writer may buffer before flushing a segment, shared by its
+    // threads
+    pub memory_budget_mb: usize,
+    // Indexing threads; None lets tantivy pick from the CPU count and budget
+    pub threads: Option<usize>,
+    pub merge: MergeSettings,
+}
+
+impl Default for WriterSettings {
+    fn default() -> Self {
+        Self {
+            memory_budget_mb: 50,
+            threads: None,
+            merge: MergeSettings::default(),
+        }
+    }
+}
+
+// When segments are merged: fewer segments make searches faster, merging
+// them makes writes slower. Tantivy's log merge policy, whose defaults these
+// are, merges segments of about the same size.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MergeSettings {
+    // Off leaves every commit's segment as it is
+    pub enabled: bool,
+    // Segments of about the same size are merged once there are this many
+    pub min_segments: usize,
+    // Segments with more documents than this are left alone
+    pub max_docs: usize,
+    // Wait for the merges a commit starts before the write returns. Each
+    // write drops its writer when done, which abandons merges still running.
+    pub merge_on_commit: bool,
+}
+
+impl Default for MergeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_segments: 8,
+            max_docs: 10_000_000,
+            merge_on_commit: false,
+        }
+    }
+}
+
+// Which searches go into the slow-query log; see slow_queries.rs. Changes
+// apply to the next search.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SlowQuerySettings {
+    pub enabled: bool,
+    // Searches that take longer than this are logged. Cached results never
+    // are.
+    pub threshold_ms: u64,
+}
+
+impl Default for SlowQuerySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_ms: 500,
+        }
+    }
+}
+
+// The app's own log; see logging.rs. Changes apply at once.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LogSettings {
+    // A level such as "debug", or per-module directives as RUST_LOG takes
+    // them. RUST_LOG, when set, wins at startup.
+    pub level: String,
+    // The log moves to `latest-docs.log.1` once it grows past this
+    pub max_file_bytes: u64,
+    // ...or once it's been written to for this long; None rotates by size
+    // alone
+    pub rotate_every_hours: Option<u32>,
+    // Rotated logs kept, the oldest deleted first
+    pub max_files: usize,
+    // Rotated logs older than this are deleted, however few there are
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            max_file_bytes: 10 * 1024 * 1024,
+            rotate_every_hours: None,
+            max_files: 5,
+            max_age_days: None,
+        }
+    }
+}
+
+// For sites where automatic main-content detection picks the wrong part of
+// the page. Selectors are CSS, as in `document.querySelector`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SourceExtraction {
+    // The element holding the content; the first match is used
+    pub content: Option<String>,
+    // Elements to drop before extracting, e.g. ".edit-this-page"
+    pub remove: Vec<String>,
+}
+
+// How long a source's documents stay current before the stale sweep picks
+// them up; see staleness.rs. Changes apply to the next sweep.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StalenessSettings {
+    // Hours since a document was last indexed before it counts as stale, by
+    // source. Sources left out never go stale.
+    pub ttl_hours: HashMap<String, u64>,
+    // Whether the sweep removes stale documents; otherwise it only reports
+    // them
+    pub remove: bool,
+}
+
+// Checks crates.io, npm and PyPI for releases newer than the ones indexed;
+// see ingest/updates.rs. Changes apply from the next check.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReleaseWatchSettings {
+    pub enabled: bool,
+    // Hours between rounds of checks
+    pub interval_hours: u64,
+    // Index a new release's docs under the same source as soon as it's found
+    pub auto_index: bool,
+}
+
+impl Default for ReleaseWatchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            auto_index: false,
+        }
+    }
+}
+
+// Embedding documents for semantic search, with a model run locally unless
+// a remote one is set; see embeddings.rs. Off by default, since the local
+// model is downloaded on first use.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EmbeddingSettings {
+    // Turning it on embeds everything already indexed, in the background
+    pub enabled: bool,
+    // An OpenAI-compatible embeddings API to use instead of the local
+    // model. Changing the model embeds the whole index again.
+    pub remote: Option<RemoteEmbeddingSettings>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RemoteEmbeddingSettings {
+    // The API's base URL, e.g. "https://api.openai.com/v1"; texts are posted
+    // to its /embeddings
+    pub url: String,
+    // Sent as a bearer token; unset for servers without one, e.g. Ollama
+    pub api_key: Option<String>,
+    // e.g. "text-embedding-3-small"
+    pub model: String,
+    // Documents sent per request
+    pub batch_size: usize,
+}
+
+impl Default for RemoteEmbeddingSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            api_key: None,
+            model: String::new(),
+            batch_size: 64,
+        }
+    }
+}
+
+// Reordering the best hits of searches that ask for it with a cross-encoder,
+// run locally unless a remote one is set; see rerank.rs. The local model is
+// downloaded on first use.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RerankSettings {
+    // How many of the best hits are reordered; more is slower but finds
+    // good hits from further down
+    pub candidates: usize,
+    // A Cohere- or Jina-compatible rerank API to use instead of the local
+    // model
+    pub remote: Option<RemoteRerankSettings>,
+}
+
+impl Default for RerankSettings {
+    fn default() -> Self {
+        Self {
+            candidates: 30,
+            remote: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RemoteRerankSettings {
+    // The API's base URL, e.g. "https://api.cohere.com/v2"; candidates are
+    // posted to its /rerank
+    pub url: String,
+    // Sent as a bearer token; unset for servers without one
+    pub api_key: Option<String>,
+    // e.g. "rerank-v3.5"
+    pub model: String,
+}
+
+// The LLM askDocs answers questions with, and summarizeDocument writes
+// summaries with; see ask.rs. Off by default, since it sends excerpts of the
+// docs to the endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AssistantSettings {
+    pub enabled: bool,
+    // An OpenAI-compatible API's base URL, e.g. "https://api.openai.com/v1"
+    // or "http://localhost:11434/v1" for Ollama; questions are posted to its
+    // /chat/completions
+    pub url: String,
+    // Sent as a bearer token; unset for servers without one
+    pub api_key: Option<String>,
+    // e.g. "gpt-4o-mini" or "llama3.1"
+    pub model: String,
+    // How much of the docs goes into the prompt, in tokens
+    pub context_tokens: usize,
+    // How long the answer may be, in tokens
+    pub max_tokens: usize,
+}
+
+impl Default for AssistantSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            api_key: None,
+            model: String::new(),
+            context_tokens: 4000,
+            max_tokens: 1024,
+        }
+    }
+}
+
+// How pages are split into documents before indexing; see chunking.rs.
+// Changes apply to pages indexed from then on.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ChunkingSettings {
+    // For sources without a strategy of their own
+    pub strategy: ChunkStrategy,
+    // By source, e.g. {"tokio": {"by": "tokens", "tokens": 300, "overlap": 50}}
+    pub sources: HashMap<String, ChunkStrategy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    // A document per section under a level 1 to 3 heading, for pages over
+    // 4 KB
+    #[default]
+    Headings,
+    // Runs of lines of about `tokens` words, each starting `overlap` words
+    // before the last one ended
+    Tokens { tokens: usize, overlap: usize },
+    // Runs of paragraphs of up to `max_chars`, never cutting through a
+    // fenced code block
+    CodeBlocks { max_chars: usize },
+    // Pages stay whole
+    Whole,
+}
+
+// How pages are downloaded, for single fetches, crawls and robots.txt
+// alike. Changes apply to the next request.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FetchSettings {
+    // e.g. "http://proxy.example.com:8080", credentials may go in the URL.
+    // Unset uses HTTP_PROXY / HTTPS_PROXY from the environment.
+    pub proxy: Option<String>,
+    // Hosts reached without the proxy, comma-separated as in NO_PROXY
+    pub no_proxy: Option<String>,
+    // Headers sent with every request
+    pub headers: HashMap<String, String>,
+    // Headers sent only to one host, by host name, e.g. an Authorization
+    // header for a private documentation portal
+    pub host_headers: HashMap<String, HashMap<String, String>>,
+    // PEM files with extra root certificates to trust, e.g. the CA of a
+    // proxy that inspects TLS traffic
+    pub ca_certificates: Vec<PathBuf>,
+    // Skips certificate checks altogether; for testing only
+    pub accept_invalid_certs: bool,
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: RwLock<Settings>,
+}
+
+impl SettingsStore {
+    // Loads settings from `path`, falling back to defaults when the file is
+    // missing or unreadable (a broken file is reported, not fatal).
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid settings file {:?}: {}", path, e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+        info!("Loaded settings from {:?}", path);
+
+        Self {
+            path,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    // Applies `change` and writes the result to disk
+    pub fn update(&self, change: impl FnOnce(&mut Settings)) -> Result<Settings> {
+        let mut settings = self.settings.write().unwrap();
+        change(&mut settings);
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&*settings)?)?;
+        Ok(settings.clone())
+    }
+}
+
+// Per-client request limits (HTTP requests and WebSocket messages alike)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    // Sustained rate each client is refilled at
+    pub requests_per_second: f64,
+    // Requests a client may fire in one go before being throttled
+    pub burst: u32,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            requests_per_second: 20.0,
+            burst: 100,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, warn};
+
+use crate::search::{SearchOptions, SearchService, SearchSort, SearchTimings};
+use crate::settings::SlowQuerySettings;
+use crate::util::now_millis;
+use crate::McpError;
+
+// The log is rotated to `slow_queries.log.1` once it grows past this
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+// One search that took longer than the threshold
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct SlowQuery {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub query: String,
+    pub limit: usize,
+    /// The filters and ranking options that differ from a plain search
+    pub filters: BTreeMap<String, Value>,
+    pub hits: usize,
+    pub timings: SearchTimings,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct GetSlowQueriesParams {
+    /// Maximum number of entries to return, slowest first (default 100, at most 1000)
+    pub limit: Option<usize>,
+    /// Only searches at or after this time, in milliseconds since the Unix epoch
+    pub since: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SlowQueriesResponse {
+    /// Searches are logged once they take longer than this
+    pub threshold_ms: u64,
+    pub entries: Vec<SlowQuery>,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+// Searches slower than the configured threshold, as JSON lines in the config
+// directory, for finding the queries that take an index down a slow path.
+// Like the audit log, writing is best effort.
+pub struct SlowQueryLog {
+    path: PathBuf,
+    file: Mutex<LogFile>,
+    settings: RwLock<SlowQuerySettings>,
+}
+
+impl SlowQueryLog {
+    pub fn open(path: PathBuf, settings: SlowQuerySettings) -> Result<Self> {
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file: Mutex::new(LogFile { file, size }),
+            settings: RwLock::new(settings),
+        })
+    }
+
+    pub fn replace(&self, settings: SlowQuerySettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    // Logs the search if it took longer than the threshold
+    pub fn record(&self, query: &str, limit: usize, options: &SearchOptions, hits: usize, timings: &SearchTimings) {
+        let settings = self.settings.read().unwrap().clone();
+        if !settings.enabled || timings.total_ms <= settings.threshold_ms as f64 {
+            return;
+        }
+        warn!("Slow search ({:.0} ms, {} hits): {:?}", timings.total_ms, hits, query);
+        let entry = SlowQuery {
+            timestamp: now_millis(),
+            query: query.to_string(),
+            limit,
+            filters: filters(options),
+            hits,
+            timings: timings.clone(),
+        };
+        if let Err(e) = self.append(&entry) {
+            error!("Failed to write slow-query log: {:?}", e);
+        }
+    }
+
+    fn append(&self, entry: &SlowQuery) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut log = self.file.lock().unwrap();
+        if log.size + line.len() as u64 > MAX_LOG_BYTES {
+            std::fs::rename(&self.path, self.rotated_path())?;
+            log.file = open_append(&self.path)?;
+            log.size = 0;
+        }
+        log.file.write_all(&line)?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Matching entries, slowest first
+    pub fn query(&self, params: &GetSlowQueriesParams) -> Result<SlowQueriesResponse> {
+        let limit = params.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT);
+        let mut entries = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for line in BufReader::new(file).lines() {
+                let Ok(entry) = serde_json::from_str::<SlowQuery>(&line?) else {
+                    continue;
+                };
+                if params.since.map_or(true, |since| entry.timestamp >= since) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| b.timings.total_ms.total_cmp(&a.timings.total_ms));
+        entries.truncate(limit);
+        Ok(SlowQueriesResponse {
+            threshold_ms: self.settings.read().unwrap().threshold_ms,
+            entries,
+        })
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".1");
+        path.into()
+    }
+}
+
+// getSlowQueries, for the live index's log
+pub fn get_slow_queries(
+    search_service: &SearchService,
+    params: &GetSlowQueriesParams,
+) -> Result<SlowQueriesResponse, McpError> {
+    search_service
+        .slow_queries()
+        .ok_or_else(|| McpError::unsupported("This index keeps no slow-query log"))?
+        .query(params)
+        .map_err(|e| McpError::internal(format!("Failed to read slow-query log: {}", e)))
+}
+
+// What the search was asked for beyond the query, leaving out what's left at
+// its default. The query's embedding would only be noise, so a vector search
+// just says it was one.
+fn filters(options: &SearchOptions) -> BTreeMap<String, Value> {
+    let mut filters = BTreeMap::new();
+    let mut set = |name: &str, value: Value| {
+        filters.insert(name.to_string(), value);
+    };
+    if let Some(source) = &options.source {
+        set("source", json!(source));
+    }
+    if let Some(version) = &options.version {
+        set("version", json!(version));
+    }
+    if let Some(version_range) = &options.version_range {
+        set("version_range", json!(version_range));
+    }
+    if let Some(facet) = &options.facet {
+        set("facet", json!(facet));
+    }
+    if !options.tags_any.is_empty() {
+        set("tags_any", json!(options.tags_any));
+    }
+    if !options.tags_all.is_empty() {
+        set("tags_all", json!(options.tags_all));
+    }
+    if !options.ranges.is_empty() {
+        set("ranges", json!(options.ranges));
+    }
+    if !options.rank_by.is_empty() {
+        set("rank_by", json!(options.rank_by));
+    }
+    if !options.preferred_versions.is_empty() {
+        set("preferred_versions", json!(options.preferred_versions));
+    }
+    if !options.pinned_versions.is_empty() {
+        set("pinned_versions", json!(options.pinned_versions));
+    }
+    if options.sort != SearchSort::Relevance {
+        set("sort", json!(options.sort));
+    }
+    if let Some(days) = options.freshness_half_life_days {
+        set("freshness_half_life_days", json!(days));
+    }
+    for (name, on) in [
+        ("latest_only", options.latest_only),
+        ("highest_version", options.highest_version),
+        ("keep_near_duplicates", options.keep_near_duplicates),
+        ("include_archived", options.include_archived),
+        ("vector", options.query_vector.is_some()),
+    ] {
+        if on {
+            set(name, json!(true));
+        }
+    }
+    filters
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::search::SearchService;
+use crate::settings::StalenessSettings;
+use crate::util::now_millis;
+use crate::McpError;
+
+const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+
+// Time between sweeps; TTLs are counted in hours, so checking more often
+// would rarely find anything new
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeStaleParams {
+    /// Only this source (default every source with a TTL)
+    pub source: Option<String>,
+    /// Hours a document stays current, instead of the source's TTL from the
+    /// settings; needs `source`
+    pub ttl_hours: Option<u64>,
+    /// List the stale documents without removing them (default false)
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleDocument {
+    pub id: String,
+    pub title: String,
+    pub source: String,
+    pub version: Option<String>,
+    // Milliseconds since the Unix epoch
+    pub indexed_at: Option<u64>,
+}
+
+// getStaleReport takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct StaleReportParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleReport {
+    // Milliseconds since the Unix epoch
+    pub swept_at: u64,
+    /// The TTL each source was checked against, in hours
+    pub ttl_hours: HashMap<String, u64>,
+    /// Pages found stale, oldest first; their sections go with them
+    pub documents: Vec<StaleDocument>,
+    /// Whether the documents were removed, or only reported
+    pub removed: bool,
+}
+
+// Finds documents that haven't been indexed again within their source's
+// TTL, so the index doesn't quietly keep serving docs nobody refreshes any
+// more. A sweep runs every hour and either removes them or only reports
+// them, as the settings say; `purgeStale` runs one on demand. The TTLs are
+// replaced in place when the settings change.
+pub struct StaleSweeper {
+    search_service: Arc<SearchService>,
+    settings: RwLock<StalenessSettings>,
+    last_report: Mutex<Option<StaleReport>>,
+    events: broadcast::Sender<StaleReport>,
+}
+
+impl StaleSweeper {
+    pub fn new(search_service: Arc<SearchService>, settings: StalenessSettings) -> Self {
+        let (events, _) = broadcast::channel(16);
+        Self {
+            search_service,
+            settings: RwLock::new(settings),
+            last_report: Mutex::new(None),
+            events,
+        }
+    }
+
+    pub fn replace(&self, settings: StalenessSettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    // Reports of sweeps that found something, for the UI
+    pub fn subscribe(&self) -> broadcast::Receiver<StaleReport> {
+        self.events.subscribe()
+    }
+
+    // The report of the last sweep, scheduled or not
+    pub fn last_report(&self) -> Option<StaleReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    // Params are expected to be validated already
+    pub fn purge(&self, params: &PurgeStaleParams) -> Result<StaleReport, McpError> {
+        let configured = self.settings.read().unwrap().ttl_hours.clone();
+        let ttl_hours: HashMap<String, u64> = match (&params.source, params.ttl_hours) {
+            (Some(source), Some(hours)) => HashMap::from([(source.clone(), hours)]),
+            (Some(source), None) => {
+                let hours = configured
+                    .get(source)
+                    .ok_or_else(|| McpError::not_found(format!("Staleness TTL of source {}", source)))?;
+                HashMap::from([(source.clone(), *hours)])
+            }
+            (None, _) => configured,
+        };
+        self.sweep(ttl_hours, !params.dry_run)
+    }
+
+    // Sweeps every hour until the app exits
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let settings = self.settings.read().unwrap().clone();
+            if !settings.ttl_hours.is_empty() {
+                if let Err(e) = self.sweep(settings.ttl_hours, settings.remove) {
+                    warn!("Stale document sweep failed: {}", e);
+                }
+            }
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    }
+
+    fn sweep(&self, ttl_hours: HashMap<String, u64>, remove: bool) -> Result<StaleReport, McpError> {
+        let now = now_millis();
+        let mut documents = Vec::new();
+        for (source, hours) in &ttl_hours {
+            let before = now.saturating_sub(hours.saturating_mul(HOUR_MILLIS));
+            let stale = self
+                .search_service
+                .stale_documents(source, before)
+                .map_err(|e| McpError::index("Failed to find stale documents", e))?;
+            documents.extend(stale.into_iter().map(|document| StaleDocument {
+                id: document.id,
+                title: document.title,
+                source: document.source,
+                version: document.version,
+                indexed_at: document.indexed_at,
+            }));
+        }
+        documents.sort_by(|a, b| a.indexed_at.cmp(&b.indexed_at).then_with(|| a.id.cmp(&b.id)));
+
+        if remove && !documents.is_empty() {
+            let ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+            self.search_service
+                .delete_documents(&ids)
+                .map_err(|e| McpError::index("Failed to remove stale documents", e))?;
+        }
+        if !documents.is_empty() {
+            info!(
+                "{} {} stale documents",
+                if remove { "Removed" } else { "Found" },
+                documents.len()
+            );
+        }
+
+        let report = StaleReport {
+            swept_at: now,
+            ttl_hours,
+            documents,
+            removed: remove,
+        };
+        *self.last_report.lock().unwrap() = Some(report.clone());
+        if !report.documents.is_empty() {
+            let _ = self.events.send(report.clone());
+        }
+        Ok(report)
+    }
+}
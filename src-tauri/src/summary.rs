@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ask::Assistant;
+use crate::search::SearchService;
+use crate::validation;
+use crate::McpError;
+
+pub const DEFAULT_SUMMARY_CHARS: usize = 1000;
+
+// A rough count, as in getContext
+const BYTES_PER_TOKEN: usize = 4;
+
+// Words too common to say what a sentence is about
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "and", "any", "are", "because", "been", "before", "being", "but", "can", "could", "does",
+    "each", "for", "from", "had", "has", "have", "how", "its", "into", "may", "more", "most", "must", "not", "one",
+    "only", "other", "our", "should", "some", "such", "than", "that", "the", "their", "them", "then", "there", "these",
+    "they", "this", "those", "through", "use", "used", "using", "was", "were", "what", "when", "where", "which",
+    "while", "will", "with", "would", "you", "your",
+];
+
+const SYSTEM_PROMPT: &str = "You summarize documentation pages. Say what the page covers and what a reader \
+can do with it, in plain prose, using only what the page says.";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SummarizeDocumentParams {
+    /// The document's id, as search hits give it
+    pub id: String,
+    /// How long the summary may be, in characters (default 1000)
+    pub max_length: Option<usize>,
+    /// Pick the summary's sentences out of the document even when an LLM is
+    /// set up, which is quicker and free
+    #[serde(default)]
+    pub extractive: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMethod {
+    /// The document's own sentences that say most about it
+    Extractive,
+    /// Written by the LLM set for askDocs
+    Llm,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct DocumentSummary {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub summary: String,
+    pub method: SummaryMethod,
+    /// The model that wrote it, for LLM summaries
+    pub model: Option<String>,
+}
+
+// A preview of a stored document, so an agent can judge a long page before
+// reading it whole. The LLM set for askDocs writes it when there is one, and
+// otherwise, or when asked to, the document's best sentences make it up.
+pub async fn summarize_document(
+    search_service: &Arc<SearchService>,
+    assistant: &Assistant,
+    params: &SummarizeDocumentParams,
+) -> Result<DocumentSummary, McpError> {
+    validation::validate_summarize_document(params)?;
+    let max_length = params.max_length.unwrap_or(DEFAULT_SUMMARY_CHARS);
+    let id = params.id.clone();
+    let document = search_service
+        .blocking(move |service| service.get_document(&id))
+        .await
+        .map_err(|e| McpError::index("Failed to read document", e))?
+        .ok_or_else(|| McpError::not_found(format!("Document {}", params.id)))?;
+
+    let (summary, method, model) = match assistant.settings().filter(|_| !params.extractive) {
+        Some(settings) => {
+            let limit = settings.context_tokens * BYTES_PER_TOKEN;
+            let text = match document.body.char_indices().nth(limit) {
+                Some((end, _)) => &document.body[..end],
+                None => &document.body,
+            };
+            let prompt = format!(
+                "Summarize this page in at most {} characters.\n\n# {}\n\n{}",
+                max_length, document.title, text
+            );
+            let tokens = max_length.div_ceil(BYTES_PER_TOKEN).min(settings.max_tokens);
+            let summary = assistant.complete(&settings, SYSTEM_PROMPT, &prompt, tokens).await?;
+            (summary, SummaryMethod::Llm, Some(settings.model))
+        }
+        None => (
+            extract(&document.title, &document.body, max_length),
+            SummaryMethod::Extractive,
+            None,
+        ),
+    };
+    Ok(DocumentSummary {
+        id: document.id,
+        title: document.title,
+        url: document.url,
+        summary,
+        method,
+        model,
+    })
+}
+
+// The sentences whose words the document uses most, taken best first while
+// they fit in `max_length` and set out in the document's order. Code, tables
+// and headings are left out, though the title's words count for more. The
+// opening sentence gets a boost, since pages tend to say up front what
+// they're about. A first sentence too long to fit is cut short.
+fn extract(title: &str, body: &str, max_length: usize) -> String {
+    let sentences = sentences(body);
+    let stop_words: HashSet<&str> = STOP_WORDS.iter().copied().collect();
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| word.chars().count() > 2)
+            .map(str::to_lowercase)
+            .filter(|word| !stop_words.contains(word.as_str()))
+            .collect()
+    };
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for sentence in &sentences {
+        for word in words(sentence) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    let title_words: HashSet<String> = words(title).into_iter().collect();
+    let most = counts.values().copied().max().unwrap_or(1) as f32;
+    let mut ranked: Vec<(f32, usize)> = sentences
+        .iter()
+        .enumerate()
+        .map(|(i, sentence)| {
+            let words = words(sentence);
+            let weight: f32 = words
+                .iter()
+                .map(|word| {
+                    let weight = counts[word] as f32 / most;
+                    if title_words.contains(word) {
+                        weight * 2.0
+                    } else {
+                        weight
+                    }
+                })
+                .sum();
+            // Long sentences don't win on length alone
+            let score = weight / (words.len() as f32).sqrt().max(1.0);
+            (if i == 0 { score * 1.5 } else { score }, i)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut picked = Vec::new();
+    let mut length = 0;
+    for (_, i) in ranked {
+        let needed = sentences[i].len() + usize::from(!picked.is_empty());
+        if length + needed <= max_length {
+            length += needed;
+            picked.push(i);
+        }
+    }
+    if picked.is_empty() {
+        return match sentences.first() {
+            Some(sentence) => {
+                let limit = max_length.saturating_sub(3);
+                let cut = (0..=limit.min(sentence.len()))
+                    .rev()
+                    .find(|&i| sentence.is_char_boundary(i))
+                    .unwrap_or(0);
+                format!("{}...", sentence[..cut].trim_end())
+            }
+            None => String::new(),
+        };
+    }
+    picked.sort_unstable();
+    picked
+        .iter()
+        .map(|&i| sentences[i].as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The prose of a markdown body, a sentence at a time. Paragraphs and list
+// items are joined up from their lines first, with the list markers and
+// emphasis taken off.
+fn sentences(body: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut paragraph = String::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            paragraphs.push(std::mem::take(&mut paragraph));
+            continue;
+        }
+        if in_fence || trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('|') {
+            paragraphs.push(std::mem::take(&mut paragraph));
+            continue;
+        }
+        let text = trimmed.trim_start_matches(['>', ' ']);
+        let item = text.strip_prefix("- ").or_else(|| text.strip_prefix("* ")).or_else(|| {
+            let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+            text[digits..].strip_prefix(". ").filter(|_| digits > 0)
+        });
+        if let Some(item) = item {
+            paragraphs.push(std::mem::take(&mut paragraph));
+            paragraph.push_str(item);
+            continue;
+        }
+        if !paragraph.is_empty() {
+            paragraph.push(' ');
+        }
+        paragraph.push_str(text);
+    }
+    paragraphs.push(paragraph);
+
+    let mut sentences = Vec::new();
+    for paragraph in paragraphs {
+        let paragraph = paragraph.replace("**", "").replace('`', "");
+        let mut start = 0;
+        let mut chars = paragraph.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let ends = matches!(c, '.' | '!' | '?')
+                && chars.peek().map_or(true, |&(_, next)| next == ' ')
+                && paragraph[i + 1..]
+                    .trim_start()
+                    .chars()
+                    .next()
+                    .map_or(true, char::is_uppercase);
+            if ends {
+                sentences.push(paragraph[start..=i].trim().to_string());
+                start = i + 1;
+            }
+        }
+        sentences.push(paragraph[start..].trim().to_string());
+    }
+    sentences.retain(|sentence| sentence.split_whitespace().count() > 2);
+    sentences
+}
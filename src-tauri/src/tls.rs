@@ -0,0 +1,201 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use axum::serve::Listener;
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use time::OffsetDateTime;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::{error, info, warn};
+
+use crate::settings::TlsSettings;
+use crate::util;
+
+// A client that stalls mid-handshake must not hold up anyone else
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Generated certificates are valid this long and replaced a few days before
+// they run out
+const GENERATED_CERT_LIFETIME: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+const GENERATED_CERT_RENEW_AFTER: Duration = Duration::from_secs(358 * 24 * 60 * 60);
+
+const GENERATED_CERT_FILE: &str = "cert.pem";
+const GENERATED_KEY_FILE: &str = "key.pem";
+// The names the generated certificate was issued for, so that it's issued
+// again when they change
+const GENERATED_NAMES_FILE: &str = "names.json";
+
+// Builds the rustls config from the user's PEM files, or from a self-signed
+// certificate kept in `generated_dir` when none are configured. Returns the
+// certificate path alongside so clients can be told what to trust.
+pub fn server_config(
+    settings: &TlsSettings,
+    bind_address: IpAddr,
+    generated_dir: &Path,
+) -> Result<(Arc<ServerConfig>, PathBuf)> {
+    let (cert_path, key_path) = match (&settings.cert_path, &settings.key_path) {
+        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+        (None, None) => {
+            let cert = generated_dir.join(GENERATED_CERT_FILE);
+            let key = generated_dir.join(GENERATED_KEY_FILE);
+            let names_file = generated_dir.join(GENERATED_NAMES_FILE);
+            let names = subject_alt_names(&settings.hostnames, bind_address);
+            if needs_generation(&cert, &key, &names_file, &names) {
+                generate_self_signed(&cert, &key, &names)?;
+                std::fs::write(&names_file, serde_json::to_vec(&names)?)?;
+                info!("Generated a self-signed TLS certificate for {} in {:?}", names.join(", "), cert);
+            }
+            (cert, key)
+        }
+        _ => return Err(anyhow!("TLS needs both cert_path and key_path, or neither to use a generated certificate")),
+    };
+
+    let cert_pem = std::fs::read(&cert_path).with_context(|| format!("Failed to read {:?}", cert_path))?;
+    let certs = CertificateDer::pem_slice_iter(&cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Invalid certificate {:?}: {:?}", cert_path, e))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificate found in {:?}", cert_path));
+    }
+    let key_pem = std::fs::read(&key_path).with_context(|| format!("Failed to read {:?}", key_path))?;
+    let key = PrivateKeyDer::from_pem_slice(&key_pem).map_err(|e| anyhow!("Invalid private key {:?}: {:?}", key_path, e))?;
+
+    // Pick the provider explicitly; more than one may be compiled in
+    let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok((Arc::new(config), cert_path))
+}
+
+// localhost, the bind address unless it's a wildcard, then `hostnames`
+fn subject_alt_names(hostnames: &[String], bind_address: IpAddr) -> Vec<String> {
+    let mut names = vec![
+        "localhost".to_string(),
+        Ipv4Addr::LOCALHOST.to_string(),
+        Ipv6Addr::LOCALHOST.to_string(),
+    ];
+    let bind_address = (!bind_address.is_unspecified()).then(|| bind_address.to_string());
+    for name in bind_address.into_iter().chain(hostnames.iter().map(|name| name.trim().to_string())) {
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn needs_generation(cert: &Path, key: &Path, names_file: &Path, names: &[String]) -> bool {
+    if !key.exists() {
+        return true;
+    }
+    let issued_for = std::fs::read(names_file)
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<Vec<String>>(&contents).ok());
+    if issued_for.as_deref() != Some(names) {
+        return true;
+    }
+    let age = std::fs::metadata(cert)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok());
+    age.map_or(true, |age| age > GENERATED_CERT_RENEW_AFTER)
+}
+
+// Accepts TCP connections and completes the TLS handshake off the accept
+// loop, handing finished connections to axum
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    acceptor_task: JoinHandle<()>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (sender, connections) = mpsc::channel(64);
+
+        let acceptor_task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        error!("Failed to accept connection: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls)) => {
+                            let _ = sender.send((tls, peer)).await;
+                        }
+                        Ok(Err(e)) => warn!("TLS handshake with {} failed: {}", peer, e),
+                        Err(_) => warn!("TLS handshake with {} timed out", peer),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            connections,
+            acceptor_task,
+        })
+    }
+}
+
+// Axum drops the listener on shutdown; this frees the port
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.acceptor_task.abort();
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+// A self-signed ECDSA P-256 certificate for `names` (DNS names or IP
+// addresses), written as PEM
+fn generate_self_signed(cert_path: &Path, key_path: &Path, names: &[String]) -> Result<()> {
+    let key_pair = KeyPair::generate().context("Failed to generate a TLS key")?;
+    let mut params = CertificateParams::new(names.to_vec()).context("Invalid name in tls.hostnames")?;
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, env!("CARGO_PKG_NAME"));
+    let now = SystemTime::now();
+    params.not_before = OffsetDateTime::from(now);
+    params.not_after = OffsetDateTime::from(now + GENERATED_CERT_LIFETIME);
+    let certificate = params.self_signed(&key_pair).context("Failed to sign the TLS certificate")?;
+
+    if let Some(dir) = cert_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    util::write_private(key_path, key_pair.serialize_pem().as_bytes())?;
+    std::fs::write(cert_path, certificate.pem())?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ring::digest::{digest, SHA256};
+
+// Opens a file that only the user can read and write once it is created,
+// e.g. for keys, tokens and the audit log. `options` says how to open it;
+// its folder is created first.
+pub fn open_private(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+// Replaces the file's contents, as `open_private` opens it
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    open_private(path, OpenOptions::new().write(true).create(true).truncate(true))?.write_all(contents)
+}
+
+// Milliseconds since the Unix epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Lowercase hex, two digits per byte
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex(digest(&SHA256, bytes).as_ref())
+}
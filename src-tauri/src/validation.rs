@@ -0,0 +1,1095 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use url::Url;
+
+use crate::ingest::{
+    AddFileOptions, CrateOptions, CrawlOptions, DevdocsOptions, DocsSiteOptions, DocsetOptions, GitHubRepoOptions,
+    GitRepoOptions, IndexPathOptions, InventoryCrawlOptions, LatestVersionParams, ManPagesOptions, MdbookOptions,
+    MdnOptions, NpmPackageOptions, PypiPackageOptions, RefreshOptions, ReleaseNotesOptions, RustdocOptions,
+    ScheduleOptions, ScheduledTask, SitemapCrawlOptions, WatchFeedOptions,
+};
+use crate::aliases::SetVersionAliasParams;
+use crate::archive::{ArchiveVersionsParams, RestoreVersionsParams};
+use crate::ask::AskDocsParams;
+use crate::benchmark::{RunBenchmarkParams, DEFAULT_BENCHMARK_DOCUMENTS, DEFAULT_BENCHMARK_QUERIES};
+use crate::context::{GetContextParams, DEFAULT_CONTEXT_TOKENS};
+use crate::diff::{DiffVersionsParams, DEFAULT_DIFF_LIMIT};
+use crate::embeddings::SemanticSearchParams;
+use crate::explain::ExplainQueryParams;
+use crate::logging;
+use crate::search::{
+    MetricBoost, MetricRange, SearchableDocument, DEFAULT_HITS_PER_VERSION, DOCUMENT_FIELDS, MAX_VERSION_GROUPS,
+};
+use crate::settings::{
+    AssistantSettings, BatchSettings, ChunkStrategy, ChunkingSettings, EmbeddingSettings, ReleaseWatchSettings,
+    DocstoreCompression, IndexSettings, LogSettings, RerankSettings, ServerSettings, SlowQuerySettings,
+    StalenessSettings, WriterSettings,
+};
+use crate::staleness::PurgeStaleParams;
+use crate::summary::{SummarizeDocumentParams, DEFAULT_SUMMARY_CHARS};
+use crate::versions::{version_key, VersionRange};
+use crate::workspaces::Workspace;
+use crate::{McpError, MultiSearchParams, SearchVersionsParams};
+
+// Per-document limits. Far above anything real documentation needs, they
+// only stop a broken client from feeding the index garbage.
+const MAX_ID_CHARS: usize = 512;
+const MAX_TITLE_CHARS: usize = 1024;
+const MAX_SOURCE_CHARS: usize = 512;
+const MAX_VERSION_CHARS: usize = 64;
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+const MAX_HEADING_PATH: usize = 16;
+const MAX_TAGS: usize = 64;
+const MAX_TAG_CHARS: usize = 64;
+const MAX_METRICS: usize = 32;
+const MAX_METRIC_NAME_CHARS: usize = 64;
+const MAX_METRIC_WEIGHT: f32 = 100.0;
+
+const MAX_QUERY_CHARS: usize = 4096;
+const MAX_SEARCH_LIMIT: usize = 1000;
+const MAX_MULTI_SEARCHES: usize = 20;
+const MAX_BENCHMARK_DOCUMENTS: usize = 100_000;
+const MAX_BENCHMARK_QUERIES: usize = 10_000;
+const MAX_FRESHNESS_DAYS: u32 = 100 * 366;
+
+// A single crawl can't grow past these
+const MAX_CRAWL_PAGES: usize = 10_000;
+const MAX_CRAWL_DEPTH: usize = 20;
+const MAX_CRAWL_CONCURRENCY: usize = 16;
+
+// Scheduled runs happen at least hourly and at most yearly
+const MAX_SCHEDULE_HOURS: u64 = 24 * 366;
+
+// Ten years; documents older than that are stale by any measure
+const MAX_TTL_HOURS: u64 = 10 * 24 * 366;
+
+// Requests to an embeddings API carry at most this many documents
+const MAX_EMBEDDING_BATCH: usize = 256;
+
+// Chunks of more than this many words, or characters, are as good as whole
+// pages; fewer characters than the minimum split paragraphs into crumbs
+const MAX_CHUNK_TOKENS: usize = 8192;
+const MIN_CHUNK_CHARS: usize = 200;
+const MAX_CHUNK_CHARS: usize = 64 * 1024;
+
+// A queued document is committed within a minute, in batches no larger than
+// a crawl's
+const MAX_BATCH_DOCUMENTS: usize = 10_000;
+const MAX_BATCH_DELAY_MS: u64 = 60_000;
+// A search that takes over ten minutes is broken, not slow
+const MAX_SLOW_QUERY_MS: u64 = 600_000;
+// Log files from 64 KiB to 1 GiB, rotated at least yearly, and no more than
+// a hundred or ten years of them kept
+const MIN_LOG_FILE_BYTES: u64 = 64 * 1024;
+const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+const MAX_LOG_ROTATE_HOURS: u32 = 24 * 366;
+const MAX_LOG_FILES: usize = 100;
+const MAX_LOG_AGE_DAYS: u32 = 10 * 366;
+// Tantivy's limits: each indexing thread needs at least 15 MB, and a writer
+// runs at most 8 threads
+const MIN_WRITER_MB_PER_THREAD: usize = 15;
+const MAX_WRITER_THREADS: usize = 8;
+const MAX_WRITER_MEMORY_MB: usize = 4096;
+const MAX_MERGE_SEGMENTS: usize = 100;
+const MIN_DOCSTORE_BLOCK_SIZE: usize = 4096;
+const MAX_DOCSTORE_BLOCK_SIZE: usize = 1024 * 1024;
+const MAX_ZSTD_LEVEL: i32 = 22;
+
+// Hits a reranker reorders at most; a cross-encoder reads each one whole
+const MAX_RERANK_CANDIDATES: usize = 200;
+
+// A context for an LLM of more tokens than this fits no model's window
+const MAX_CONTEXT_TOKENS: usize = 1_000_000;
+
+// Answers longer than this aren't answers any more
+const MAX_ANSWER_TOKENS: usize = 32_768;
+
+// A summary longer than this is no quicker to read than the page
+const MAX_SUMMARY_CHARS: usize = 20_000;
+
+// Feeds are checked at least every 5 minutes and at least weekly
+const MIN_FEED_MINUTES: u64 = 5;
+const MAX_FEED_MINUTES: u64 = 7 * 24 * 60;
+
+// Checks one document; `path` names it in errors, e.g. "document" or
+// "documents[3]"
+pub fn validate_document(document: &SearchableDocument, path: &str) -> Result<(), McpError> {
+    check_text(&document.id, path, "id", MAX_ID_CHARS, true)?;
+    check_text(&document.title, path, "title", MAX_TITLE_CHARS, true)?;
+    check_text(&document.source, path, "source", MAX_SOURCE_CHARS, false)?;
+    if let Some(version) = &document.version {
+        check_text(version, path, "version", MAX_VERSION_CHARS, false)?;
+    }
+    if let Some(url) = &document.url {
+        check_text(url, path, "url", MAX_ID_CHARS, true)?;
+        if !Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https" | "file")) {
+            return Err(McpError::validation(
+                Some(&format!("{}.url", path)),
+                "Document url must be an http(s) or file:// URL",
+            ));
+        }
+    }
+    check_tags(&document.tags, path, "tags")?;
+    if let Some(parent_id) = &document.parent_id {
+        check_text(parent_id, path, "parent_id", MAX_ID_CHARS, true)?;
+    }
+    if document.heading_path.len() > MAX_HEADING_PATH {
+        return Err(McpError::validation(
+            Some(&format!("{}.heading_path", path)),
+            format!("At most {} headings are allowed", MAX_HEADING_PATH),
+        ));
+    }
+    for heading in &document.heading_path {
+        check_text(heading, path, "heading_path", MAX_TITLE_CHARS, true)?;
+    }
+    if let Some(anchor) = &document.anchor {
+        check_text(anchor, path, "anchor", MAX_ID_CHARS, true)?;
+    }
+    if let Some(facet) = &document.facet {
+        check_facet(facet, path, "facet")?;
+    }
+    if document.metrics.len() > MAX_METRICS {
+        return Err(McpError::validation(
+            Some(&format!("{}.metrics", path)),
+            format!("At most {} metrics are allowed", MAX_METRICS),
+        ));
+    }
+    for (name, value) in &document.metrics {
+        check_metric_name(name, &format!("{}.metrics", path))?;
+        if !value.is_finite() {
+            return Err(McpError::validation(
+                Some(&format!("{}.metrics.{}", path, name)),
+                "Metrics must be finite numbers",
+            ));
+        }
+    }
+    if document.body.len() > MAX_BODY_BYTES {
+        return Err(McpError::validation(
+            Some(&format!("{}.body", path)),
+            format!("Document body is {} bytes; the limit is {}", document.body.len(), MAX_BODY_BYTES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_documents(documents: &[SearchableDocument]) -> Result<(), McpError> {
+    for (i, document) in documents.iter().enumerate() {
+        validate_document(document, &format!("documents[{}]", i))?;
+    }
+    Ok(())
+}
+
+pub fn validate_search(query: &str, limit: usize) -> Result<(), McpError> {
+    if query.trim().is_empty() {
+        return Err(McpError::validation(Some("query"), "Query must not be empty"));
+    }
+    if query.chars().count() > MAX_QUERY_CHARS {
+        return Err(McpError::validation(
+            Some("query"),
+            format!("Query is longer than {} characters", MAX_QUERY_CHARS),
+        ));
+    }
+    if limit == 0 || limit > MAX_SEARCH_LIMIT {
+        return Err(McpError::validation(
+            Some("limit"),
+            format!("Limit must be between 1 and {}", MAX_SEARCH_LIMIT),
+        ));
+    }
+    Ok(())
+}
+
+// Each search is checked as it runs, so one bad query only fails itself
+pub fn validate_multi_search(params: &MultiSearchParams) -> Result<(), McpError> {
+    if params.searches.is_empty() || params.searches.len() > MAX_MULTI_SEARCHES {
+        return Err(McpError::validation(
+            Some("searches"),
+            format!("searches must hold between 1 and {} searches", MAX_MULTI_SEARCHES),
+        ));
+    }
+    let mut queries = HashSet::new();
+    for search in &params.searches {
+        if !queries.insert(search.query.as_str()) {
+            return Err(McpError::validation(
+                Some("searches"),
+                format!("Query \"{}\" appears more than once; results are keyed by query", search.query),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_find_duplicates(limit: usize) -> Result<(), McpError> {
+    if limit == 0 || limit > MAX_SEARCH_LIMIT {
+        return Err(McpError::validation(
+            Some("limit"),
+            format!("Limit must be between 1 and {}", MAX_SEARCH_LIMIT),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_diff_versions(params: &DiffVersionsParams) -> Result<(), McpError> {
+    check_text(&params.source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    check_text(&params.v1, "params", "v1", MAX_VERSION_CHARS, true)?;
+    check_text(&params.v2, "params", "v2", MAX_VERSION_CHARS, true)?;
+    if params.v1 == params.v2 {
+        return Err(McpError::validation(Some("v2"), "v1 and v2 must be different versions"));
+    }
+    validate_find_duplicates(params.limit.unwrap_or(DEFAULT_DIFF_LIMIT))
+}
+
+pub fn validate_purge_stale(params: &PurgeStaleParams) -> Result<(), McpError> {
+    if let Some(source) = &params.source {
+        check_text(source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    if let Some(hours) = params.ttl_hours {
+        if params.source.is_none() {
+            return Err(McpError::validation(Some("ttlHours"), "ttlHours needs a source"));
+        }
+        check_ttl_hours("ttlHours", hours)?;
+    }
+    Ok(())
+}
+
+pub fn validate_staleness_settings(settings: &StalenessSettings) -> Result<(), McpError> {
+    for (source, hours) in &settings.ttl_hours {
+        check_text(source, "ttl_hours", "source", MAX_SOURCE_CHARS, true)?;
+        check_ttl_hours(source, *hours)?;
+    }
+    Ok(())
+}
+
+pub fn validate_release_watch_settings(settings: &ReleaseWatchSettings) -> Result<(), McpError> {
+    if settings.interval_hours == 0 || settings.interval_hours > MAX_SCHEDULE_HOURS {
+        return Err(McpError::validation(
+            Some("interval_hours"),
+            format!("interval_hours must be between 1 and {}", MAX_SCHEDULE_HOURS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_embedding_settings(settings: &EmbeddingSettings) -> Result<(), McpError> {
+    let Some(remote) = &settings.remote else {
+        return Ok(());
+    };
+    check_text(&remote.url, "remote", "url", MAX_ID_CHARS, true)?;
+    if !Url::parse(&remote.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(McpError::validation(Some("remote.url"), "The embeddings API must be an http(s) URL"));
+    }
+    check_text(&remote.model, "remote", "model", MAX_SOURCE_CHARS, true)?;
+    if remote.batch_size == 0 || remote.batch_size > MAX_EMBEDDING_BATCH {
+        return Err(McpError::validation(
+            Some("remote.batch_size"),
+            format!("batch_size must be between 1 and {}", MAX_EMBEDDING_BATCH),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_batch_settings(settings: &BatchSettings) -> Result<(), McpError> {
+    if settings.max_documents == 0 || settings.max_documents > MAX_BATCH_DOCUMENTS {
+        return Err(McpError::validation(
+            Some("max_documents"),
+            format!("max_documents must be between 1 and {}", MAX_BATCH_DOCUMENTS),
+        ));
+    }
+    if settings.max_delay_ms > MAX_BATCH_DELAY_MS {
+        return Err(McpError::validation(
+            Some("max_delay_ms"),
+            format!("max_delay_ms must be at most {}", MAX_BATCH_DELAY_MS),
+        ));
+    }
+    Ok(())
+}
+
+// A rate of zero would make a throttled client wait forever, and a session
+// timeout of zero would drop sessions between two calls
+pub fn validate_server_settings(settings: &ServerSettings) -> Result<(), McpError> {
+    let rate_limit = &settings.rate_limit;
+    if !rate_limit.requests_per_second.is_finite() || rate_limit.requests_per_second <= 0.0 {
+        return Err(McpError::validation(
+            Some("rate_limit.requests_per_second"),
+            "requests_per_second must be a number above 0",
+        ));
+    }
+    if rate_limit.burst == 0 {
+        return Err(McpError::validation(Some("rate_limit.burst"), "burst must be at least 1"));
+    }
+    if settings.session_idle_minutes == 0 {
+        return Err(McpError::validation(
+            Some("session_idle_minutes"),
+            "session_idle_minutes must be at least 1",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_slow_query_settings(settings: &SlowQuerySettings) -> Result<(), McpError> {
+    if settings.threshold_ms == 0 || settings.threshold_ms > MAX_SLOW_QUERY_MS {
+        return Err(McpError::validation(
+            Some("threshold_ms"),
+            format!("threshold_ms must be between 1 and {}", MAX_SLOW_QUERY_MS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_log_settings(settings: &LogSettings) -> Result<(), McpError> {
+    logging::parse_level(&settings.level)?;
+    if settings.max_file_bytes < MIN_LOG_FILE_BYTES || settings.max_file_bytes > MAX_LOG_FILE_BYTES {
+        return Err(McpError::validation(
+            Some("max_file_bytes"),
+            format!("max_file_bytes must be between {} and {}", MIN_LOG_FILE_BYTES, MAX_LOG_FILE_BYTES),
+        ));
+    }
+    if settings.rotate_every_hours.is_some_and(|hours| hours == 0 || hours > MAX_LOG_ROTATE_HOURS) {
+        return Err(McpError::validation(
+            Some("rotate_every_hours"),
+            format!("rotate_every_hours must be between 1 and {}", MAX_LOG_ROTATE_HOURS),
+        ));
+    }
+    if settings.max_files > MAX_LOG_FILES {
+        return Err(McpError::validation(
+            Some("max_files"),
+            format!("max_files must be at most {}", MAX_LOG_FILES),
+        ));
+    }
+    if settings.max_age_days.is_some_and(|days| days == 0 || days > MAX_LOG_AGE_DAYS) {
+        return Err(McpError::validation(
+            Some("max_age_days"),
+            format!("max_age_days must be between 1 and {}", MAX_LOG_AGE_DAYS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_index_settings(settings: &IndexSettings) -> Result<(), McpError> {
+    let docstore = &settings.docstore;
+    if docstore.block_size < MIN_DOCSTORE_BLOCK_SIZE || docstore.block_size > MAX_DOCSTORE_BLOCK_SIZE {
+        return Err(McpError::validation(
+            Some("docstore.block_size"),
+            format!(
+                "docstore.block_size must be between {} and {}",
+                MIN_DOCSTORE_BLOCK_SIZE, MAX_DOCSTORE_BLOCK_SIZE
+            ),
+        ));
+    }
+    if let Some(level) = docstore.zstd_level {
+        if docstore.compression != DocstoreCompression::Zstd {
+            return Err(McpError::validation(
+                Some("docstore.zstd_level"),
+                "docstore.zstd_level only applies to zstd compression",
+            ));
+        }
+        if !(1..=MAX_ZSTD_LEVEL).contains(&level) {
+            return Err(McpError::validation(
+                Some("docstore.zstd_level"),
+                format!("docstore.zstd_level must be between 1 and {}", MAX_ZSTD_LEVEL),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_writer_settings(settings: &WriterSettings) -> Result<(), McpError> {
+    if let Some(threads) = settings.threads {
+        if threads == 0 || threads > MAX_WRITER_THREADS {
+            return Err(McpError::validation(
+                Some("threads"),
+                format!("threads must be between 1 and {}", MAX_WRITER_THREADS),
+            ));
+        }
+    }
+    let min_mb = MIN_WRITER_MB_PER_THREAD * settings.threads.unwrap_or(1);
+    if settings.memory_budget_mb < min_mb || settings.memory_budget_mb > MAX_WRITER_MEMORY_MB {
+        return Err(McpError::validation(
+            Some("memory_budget_mb"),
+            format!(
+                "memory_budget_mb must be between {} and {}, at least {} per thread",
+                min_mb, MAX_WRITER_MEMORY_MB, MIN_WRITER_MB_PER_THREAD
+            ),
+        ));
+    }
+    if settings.merge.min_segments < 2 || settings.merge.min_segments > MAX_MERGE_SEGMENTS {
+        return Err(McpError::validation(
+            Some("merge.min_segments"),
+            format!("merge.min_segments must be between 2 and {}", MAX_MERGE_SEGMENTS),
+        ));
+    }
+    if settings.merge.max_docs == 0 {
+        return Err(McpError::validation(Some("merge.max_docs"), "merge.max_docs must be at least 1"));
+    }
+    Ok(())
+}
+
+pub fn validate_rerank_settings(settings: &RerankSettings) -> Result<(), McpError> {
+    if settings.candidates == 0 || settings.candidates > MAX_RERANK_CANDIDATES {
+        return Err(McpError::validation(
+            Some("candidates"),
+            format!("candidates must be between 1 and {}", MAX_RERANK_CANDIDATES),
+        ));
+    }
+    let Some(remote) = &settings.remote else {
+        return Ok(());
+    };
+    check_text(&remote.url, "remote", "url", MAX_ID_CHARS, true)?;
+    if !Url::parse(&remote.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(McpError::validation(Some("remote.url"), "The rerank API must be an http(s) URL"));
+    }
+    check_text(&remote.model, "remote", "model", MAX_SOURCE_CHARS, true)
+}
+
+pub fn validate_chunking_settings(settings: &ChunkingSettings) -> Result<(), McpError> {
+    check_chunk_strategy(&settings.strategy, "strategy")?;
+    for (source, strategy) in &settings.sources {
+        check_text(source, "sources", "source", MAX_SOURCE_CHARS, true)?;
+        check_chunk_strategy(strategy, &format!("sources.{}", source))?;
+    }
+    Ok(())
+}
+
+fn check_chunk_strategy(strategy: &ChunkStrategy, path: &str) -> Result<(), McpError> {
+    match *strategy {
+        ChunkStrategy::Tokens { tokens, overlap } => {
+            if tokens == 0 || tokens > MAX_CHUNK_TOKENS {
+                return Err(McpError::validation(
+                    Some(&format!("{}.tokens", path)),
+                    format!("tokens must be between 1 and {}", MAX_CHUNK_TOKENS),
+                ));
+            }
+            if overlap >= tokens {
+                return Err(McpError::validation(
+                    Some(&format!("{}.overlap", path)),
+                    "overlap must be less than tokens",
+                ));
+            }
+        }
+        ChunkStrategy::CodeBlocks { max_chars } => {
+            if !(MIN_CHUNK_CHARS..=MAX_CHUNK_CHARS).contains(&max_chars) {
+                return Err(McpError::validation(
+                    Some(&format!("{}.max_chars", path)),
+                    format!("max_chars must be between {} and {}", MIN_CHUNK_CHARS, MAX_CHUNK_CHARS),
+                ));
+            }
+        }
+        ChunkStrategy::Headings | ChunkStrategy::Whole => {}
+    }
+    Ok(())
+}
+
+pub fn validate_assistant_settings(settings: &AssistantSettings) -> Result<(), McpError> {
+    if settings.context_tokens == 0 || settings.context_tokens > MAX_CONTEXT_TOKENS {
+        return Err(McpError::validation(
+            Some("context_tokens"),
+            format!("context_tokens must be between 1 and {}", MAX_CONTEXT_TOKENS),
+        ));
+    }
+    if settings.max_tokens == 0 || settings.max_tokens > MAX_ANSWER_TOKENS {
+        return Err(McpError::validation(
+            Some("max_tokens"),
+            format!("max_tokens must be between 1 and {}", MAX_ANSWER_TOKENS),
+        ));
+    }
+    // An endpoint is only needed once it's turned on
+    if !settings.enabled && settings.url.is_empty() {
+        return Ok(());
+    }
+    check_text(&settings.url, "settings", "url", MAX_ID_CHARS, true)?;
+    if !Url::parse(&settings.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(McpError::validation(Some("url"), "The LLM endpoint must be an http(s) URL"));
+    }
+    check_text(&settings.model, "settings", "model", MAX_SOURCE_CHARS, true)
+}
+
+fn check_ttl_hours(field: &str, hours: u64) -> Result<(), McpError> {
+    if hours == 0 || hours > MAX_TTL_HOURS {
+        return Err(McpError::validation(
+            Some(field),
+            format!("TTL must be between 1 and {} hours", MAX_TTL_HOURS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_tag_filters(tags_any: &[String], tags_all: &[String]) -> Result<(), McpError> {
+    check_tags(tags_any, "params", "tags_any")?;
+    check_tags(tags_all, "params", "tags_all")
+}
+
+pub fn validate_facet(facet: Option<&str>) -> Result<(), McpError> {
+    match facet {
+        Some(facet) => check_facet(facet, "params", "facet"),
+        None => Ok(()),
+    }
+}
+
+pub fn validate_version_range(version_range: Option<&str>) -> Result<(), McpError> {
+    let Some(version_range) = version_range else {
+        return Ok(());
+    };
+    check_text(version_range, "params", "version_range", MAX_VERSION_CHARS, true)?;
+    VersionRange::parse(version_range)
+        .map(|_| ())
+        .map_err(|e| McpError::validation(Some("version_range"), e.to_string()))
+}
+
+pub fn validate_search_versions(params: &SearchVersionsParams) -> Result<(), McpError> {
+    validate_search(&params.query, params.limit_per_version.unwrap_or(DEFAULT_HITS_PER_VERSION))?;
+    check_text(&params.source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    if params.versions.len() > MAX_VERSION_GROUPS {
+        return Err(McpError::validation(
+            Some("versions"),
+            format!("At most {} versions can be compared", MAX_VERSION_GROUPS),
+        ));
+    }
+    for version in &params.versions {
+        check_text(version, "params", "versions", MAX_VERSION_CHARS, true)?;
+    }
+    validate_fields(&params.fields, true)
+}
+
+// Semantic hits come without snippets, since no words of the query need to
+// appear in them
+pub fn validate_semantic_search(params: &SemanticSearchParams) -> Result<(), McpError> {
+    validate_search(&params.query, params.limit.unwrap_or(10))?;
+    if let Some(source) = &params.source {
+        check_text(source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    validate_version_filter(params.version.as_deref())?;
+    validate_fields(&params.fields, false)
+}
+
+pub fn validate_get_context(params: &GetContextParams) -> Result<(), McpError> {
+    validate_search(&params.query, 1)?;
+    let max_tokens = params.max_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    if max_tokens == 0 || max_tokens > MAX_CONTEXT_TOKENS {
+        return Err(McpError::validation(
+            Some("max_tokens"),
+            format!("max_tokens must be between 1 and {}", MAX_CONTEXT_TOKENS),
+        ));
+    }
+    if let Some(source) = &params.source {
+        check_text(source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    validate_version_filter(params.version.as_deref())
+}
+
+pub fn validate_summarize_document(params: &SummarizeDocumentParams) -> Result<(), McpError> {
+    check_text(&params.id, "params", "id", MAX_ID_CHARS, true)?;
+    let max_length = params.max_length.unwrap_or(DEFAULT_SUMMARY_CHARS);
+    if max_length == 0 || max_length > MAX_SUMMARY_CHARS {
+        return Err(McpError::validation(
+            Some("max_length"),
+            format!("max_length must be between 1 and {}", MAX_SUMMARY_CHARS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_explain_query(params: &ExplainQueryParams) -> Result<(), McpError> {
+    check_text(&params.query, "params", "query", MAX_QUERY_CHARS, true)?;
+    check_text(&params.id, "params", "id", MAX_ID_CHARS, true)
+}
+
+pub fn validate_run_benchmark(params: &RunBenchmarkParams) -> Result<(), McpError> {
+    let documents = params.documents.unwrap_or(DEFAULT_BENCHMARK_DOCUMENTS);
+    if documents == 0 || documents > MAX_BENCHMARK_DOCUMENTS {
+        return Err(McpError::validation(
+            Some("documents"),
+            format!("documents must be between 1 and {}", MAX_BENCHMARK_DOCUMENTS),
+        ));
+    }
+    let queries = params.queries.unwrap_or(DEFAULT_BENCHMARK_QUERIES);
+    if queries == 0 || queries > MAX_BENCHMARK_QUERIES {
+        return Err(McpError::validation(
+            Some("queries"),
+            format!("queries must be between 1 and {}", MAX_BENCHMARK_QUERIES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_ask_docs(params: &AskDocsParams) -> Result<(), McpError> {
+    check_text(&params.question, "params", "question", MAX_QUERY_CHARS, true)?;
+    if let Some(source) = &params.source {
+        check_text(source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    validate_version_filter(params.version.as_deref())
+}
+
+pub fn validate_version_filter(version: Option<&str>) -> Result<(), McpError> {
+    match version {
+        Some(version) => check_text(version, "params", "version", MAX_VERSION_CHARS, true),
+        None => Ok(()),
+    }
+}
+
+// An alias that reads as a version would hide the version of that name
+pub fn validate_version_alias(params: &SetVersionAliasParams) -> Result<(), McpError> {
+    check_text(&params.source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    check_text(&params.alias, "params", "alias", MAX_VERSION_CHARS, true)?;
+    check_text(&params.version, "params", "version", MAX_VERSION_CHARS, true)?;
+    if version_key(&params.alias).is_some() {
+        return Err(McpError::validation(
+            Some("alias"),
+            format!("{} is a version, not an alias", params.alias),
+        ));
+    }
+    if params.alias == params.version {
+        return Err(McpError::validation(Some("version"), "An alias can't stand for itself"));
+    }
+    Ok(())
+}
+
+// Archiving every version would leave the source out of default searches
+// altogether
+pub fn validate_archive_versions(params: &ArchiveVersionsParams) -> Result<(), McpError> {
+    check_text(&params.source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    for version in &params.versions {
+        check_text(version, "params", "versions", MAX_VERSION_CHARS, true)?;
+    }
+    if params.keep == Some(0) {
+        return Err(McpError::validation(Some("keep"), "keep must be at least 1"));
+    }
+    Ok(())
+}
+
+pub fn validate_restore_versions(params: &RestoreVersionsParams) -> Result<(), McpError> {
+    check_text(&params.source, "params", "source", MAX_SOURCE_CHARS, true)?;
+    if params.versions.is_empty() {
+        return Err(McpError::validation(Some("versions"), "versions must not be empty"));
+    }
+    for version in &params.versions {
+        check_text(version, "params", "versions", MAX_VERSION_CHARS, true)?;
+    }
+    Ok(())
+}
+
+// The root is read on the machine the app runs on, so it has to be a path
+// there rather than one relative to whoever asked
+pub fn validate_workspace(workspace: &Workspace) -> Result<(), McpError> {
+    check_text(&workspace.name, "workspace", "name", MAX_SOURCE_CHARS, true)?;
+    if let Some(root) = &workspace.root {
+        check_text(root, "workspace", "root", MAX_ID_CHARS, true)?;
+        if !Path::new(root).is_absolute() {
+            return Err(McpError::validation(Some("root"), format!("{} is not an absolute path", root)));
+        }
+    }
+    for (source, version) in &workspace.pins {
+        check_text(source, "pins", "source", MAX_SOURCE_CHARS, true)?;
+        check_text(version, "pins", "version", MAX_VERSION_CHARS, true)?;
+    }
+    Ok(())
+}
+
+// Fields to return, e.g. ["id", "title", "url", "snippet"]. A snippet
+// needs a query, so getDocument can't have one.
+pub fn validate_fields(fields: &[String], snippet: bool) -> Result<(), McpError> {
+    for field in fields {
+        if !DOCUMENT_FIELDS.contains(&field.as_str()) || (field == "snippet" && !snippet) {
+            return Err(McpError::validation(
+                Some("fields"),
+                format!("Unknown field {}; fields can be {}", field, allowed_fields(snippet)),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn allowed_fields(snippet: bool) -> String {
+    DOCUMENT_FIELDS
+        .iter()
+        .filter(|field| snippet || **field != "snippet")
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// A facet to list the children of, and optionally a query to count only
+// its matches
+pub fn validate_facet_counts(query: Option<&str>, facet: &str) -> Result<(), McpError> {
+    if let Some(query) = query {
+        validate_search(query, 1)?;
+    }
+    check_facet(facet, "params", "facet")
+}
+
+pub fn validate_freshness(half_life_days: Option<u32>) -> Result<(), McpError> {
+    if half_life_days.is_some_and(|days| days == 0 || days > MAX_FRESHNESS_DAYS) {
+        return Err(McpError::validation(
+            Some("freshness_half_life_days"),
+            format!("freshness_half_life_days must be between 1 and {}", MAX_FRESHNESS_DAYS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_metric_filters(ranges: &[MetricRange], rank_by: &[MetricBoost]) -> Result<(), McpError> {
+    if ranges.len() > MAX_METRICS || rank_by.len() > MAX_METRICS {
+        return Err(McpError::validation(
+            Some(if ranges.len() > MAX_METRICS { "ranges" } else { "rank_by" }),
+            format!("At most {} metrics can be used at once", MAX_METRICS),
+        ));
+    }
+    for (i, range) in ranges.iter().enumerate() {
+        let path = format!("ranges[{}]", i);
+        check_metric_name(&range.metric, &path)?;
+        let (min, max) = (range.min.unwrap_or(f64::MIN), range.max.unwrap_or(f64::MAX));
+        if range.min.is_none() && range.max.is_none() || !min.is_finite() || !max.is_finite() || min > max {
+            return Err(McpError::validation(
+                Some(&path),
+                "A range needs a min, a max or both, with min no greater than max",
+            ));
+        }
+    }
+    for (i, boost) in rank_by.iter().enumerate() {
+        let path = format!("rank_by[{}]", i);
+        check_metric_name(&boost.metric, &path)?;
+        if !boost.weight.is_finite() || boost.weight.abs() > MAX_METRIC_WEIGHT {
+            return Err(McpError::validation(
+                Some(&format!("{}.weight", path)),
+                format!("weight must be between -{} and {}", MAX_METRIC_WEIGHT, MAX_METRIC_WEIGHT),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_crawl(options: &CrawlOptions) -> Result<(), McpError> {
+    check_crawl_limits(&options.source, options.version.as_deref(), options.max_pages)?;
+    check_concurrency(options.concurrency, options.host_concurrency)?;
+    if options.max_depth > MAX_CRAWL_DEPTH {
+        return Err(McpError::validation(
+            Some("maxDepth"),
+            format!("maxDepth must be at most {}", MAX_CRAWL_DEPTH),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_sitemap_crawl(options: &SitemapCrawlOptions) -> Result<(), McpError> {
+    check_crawl_limits(&options.source, options.version.as_deref(), options.max_pages)?;
+    check_concurrency(options.concurrency, options.host_concurrency)
+}
+
+pub fn validate_inventory_crawl(options: &InventoryCrawlOptions) -> Result<(), McpError> {
+    check_crawl_limits(&options.source, options.version.as_deref(), options.max_pages)?;
+    for domain in &options.domains {
+        check_text(domain, "crawl", "domains", MAX_SOURCE_CHARS, true)?;
+    }
+    check_concurrency(options.concurrency, options.host_concurrency)
+}
+
+pub fn validate_refresh(options: &RefreshOptions) -> Result<(), McpError> {
+    check_crawl_limits(&options.source, None, 1)?;
+    check_concurrency(options.concurrency, options.host_concurrency)
+}
+
+pub fn validate_schedule(options: &ScheduleOptions) -> Result<(), McpError> {
+    if options.interval_hours == 0 || options.interval_hours > MAX_SCHEDULE_HOURS {
+        return Err(McpError::validation(
+            Some("intervalHours"),
+            format!("intervalHours must be between 1 and {}", MAX_SCHEDULE_HOURS),
+        ));
+    }
+    match &options.task {
+        ScheduledTask::Refresh(task) => validate_refresh(task),
+        ScheduledTask::Crawl(task) => validate_crawl(task),
+        ScheduledTask::Sitemap(task) => validate_sitemap_crawl(task),
+        ScheduledTask::Inventory(task) => validate_inventory_crawl(task),
+    }
+}
+
+pub fn validate_watch_feed(options: &WatchFeedOptions) -> Result<(), McpError> {
+    check_local_import("url", &options.url, options.source.as_deref(), options.version.as_deref())?;
+    if options.interval_minutes < MIN_FEED_MINUTES || options.interval_minutes > MAX_FEED_MINUTES {
+        return Err(McpError::validation(
+            Some("intervalMinutes"),
+            format!("intervalMinutes must be between {} and {}", MIN_FEED_MINUTES, MAX_FEED_MINUTES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_index_path(options: &IndexPathOptions) -> Result<(), McpError> {
+    check_local_import("path", &options.path, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_add_file(options: &AddFileOptions) -> Result<(), McpError> {
+    check_local_import("path", &options.path, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_rustdoc(options: &RustdocOptions) -> Result<(), McpError> {
+    check_local_import("path", &options.path, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_mdbook(options: &MdbookOptions) -> Result<(), McpError> {
+    check_local_import("location", &options.location, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_docset(options: &DocsetOptions) -> Result<(), McpError> {
+    check_local_import("path", &options.path, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_devdocs(options: &DevdocsOptions) -> Result<(), McpError> {
+    check_local_import("doc", &options.doc, options.source.as_deref(), options.version.as_deref())
+}
+
+pub fn validate_crate(options: &CrateOptions) -> Result<(), McpError> {
+    check_local_import("name", &options.name, options.source.as_deref(), options.version.as_deref())?;
+    // crates.io names: ASCII letters, digits, - and _
+    if !options.name.trim().chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(McpError::validation(Some("name"), format!("{} is not a crate name", options.name)));
+    }
+    if let Some(version) = &options.version {
+        if version.contains(['/', '?', '#']) {
+            return Err(McpError::validation(Some("version"), format!("{} is not a version", version)));
+        }
+    }
+    if options.max_pages == 0 || options.max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_npm_package(options: &NpmPackageOptions) -> Result<(), McpError> {
+    check_local_import("name", &options.name, options.source.as_deref(), options.version.as_deref())?;
+    // npm names: no spaces, and a slash only after a @scope
+    let name = options.name.trim();
+    let slashes = name.matches('/').count();
+    if name.contains(char::is_whitespace) || slashes > 1 || (slashes == 1 && !name.starts_with('@')) {
+        return Err(McpError::validation(Some("name"), format!("{} is not an npm package name", name)));
+    }
+    if options.max_pages == 0 || options.max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_pypi_package(options: &PypiPackageOptions) -> Result<(), McpError> {
+    check_local_import("name", &options.name, options.source.as_deref(), options.version.as_deref())?;
+    // PyPI names: ASCII letters, digits, and - _ . between them
+    if !options.name.trim().chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Err(McpError::validation(Some("name"), format!("{} is not a PyPI project name", options.name)));
+    }
+    if let Some(version) = &options.version {
+        if version.contains(['/', '?', '#']) {
+            return Err(McpError::validation(Some("version"), format!("{} is not a version", version)));
+        }
+    }
+    if options.max_pages == 0 || options.max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_github_repo(options: &GitHubRepoOptions) -> Result<(), McpError> {
+    check_local_import("repo", &options.repo, options.source.as_deref(), None)?;
+    check_text(&options.docs_path, "options", "docsPath", MAX_ID_CHARS, false)?;
+    if let Some(branch) = &options.branch {
+        check_text(branch, "options", "branch", MAX_ID_CHARS, true)?;
+    }
+    if options.max_files == 0 || options.max_files > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxFiles"),
+            format!("maxFiles must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_git_repo(options: &GitRepoOptions) -> Result<(), McpError> {
+    check_local_import("repo", &options.repo, options.source.as_deref(), None)?;
+    // Git would read either as an option
+    if options.repo.trim().starts_with('-') {
+        return Err(McpError::validation(Some("repo"), "repo must be a URL or a path"));
+    }
+    if let Some(git_ref) = &options.git_ref {
+        check_text(git_ref, "options", "ref", MAX_VERSION_CHARS, true)?;
+        if git_ref.trim().starts_with('-') {
+            return Err(McpError::validation(Some("ref"), "ref must name a branch, tag or commit"));
+        }
+    }
+    if options.globs.is_empty() {
+        return Err(McpError::validation(Some("globs"), "globs must list at least one pattern"));
+    }
+    for glob in &options.globs {
+        check_text(glob, "options", "globs", MAX_ID_CHARS, true)?;
+    }
+    if options.max_files == 0 || options.max_files > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxFiles"),
+            format!("maxFiles must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_release_notes(options: &ReleaseNotesOptions) -> Result<(), McpError> {
+    check_local_import("repo", &options.repo, options.source.as_deref(), None)?;
+    if options.max_releases == 0 || options.max_releases > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxReleases"),
+            format!("maxReleases must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_mdn(options: &MdnOptions) -> Result<(), McpError> {
+    if options.areas.is_empty() {
+        return Err(McpError::validation(Some("areas"), "areas must name at least one area"));
+    }
+    for area in &options.areas {
+        check_text(area, "options", "areas", MAX_SOURCE_CHARS, true)?;
+    }
+    if let Some(source) = &options.source {
+        check_text(source, "options", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    if options.max_pages == 0 || options.max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_latest_version(params: &LatestVersionParams) -> Result<(), McpError> {
+    check_text(&params.package, "params", "package", MAX_SOURCE_CHARS, true)
+}
+
+pub fn validate_man_pages(options: &ManPagesOptions) -> Result<(), McpError> {
+    for (field, values) in [("sections", &options.sections), ("names", &options.names), ("paths", &options.paths)] {
+        for value in values {
+            check_text(value, "options", field, MAX_ID_CHARS, true)?;
+        }
+    }
+    if let Some(source) = &options.source {
+        check_text(source, "options", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    if let Some(version) = &options.version {
+        check_text(version, "options", "version", MAX_VERSION_CHARS, false)?;
+    }
+    Ok(())
+}
+
+pub fn validate_docs_site(options: &DocsSiteOptions) -> Result<(), McpError> {
+    check_local_import("url", &options.url, options.source.as_deref(), options.version.as_deref())?;
+    if options.max_pages == 0 || options.max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    if options.concurrency == 0 || options.concurrency > MAX_CRAWL_CONCURRENCY {
+        return Err(McpError::validation(
+            Some("concurrency"),
+            format!("concurrency must be between 1 and {}", MAX_CRAWL_CONCURRENCY),
+        ));
+    }
+    Ok(())
+}
+
+// What to import (a local file or folder, a site's URL or a pack's name),
+// and what to file it under
+fn check_local_import(field: &str, path: &str, source: Option<&str>, version: Option<&str>) -> Result<(), McpError> {
+    check_text(path, "options", field, MAX_ID_CHARS, true)?;
+    if let Some(source) = source {
+        check_text(source, "options", "source", MAX_SOURCE_CHARS, true)?;
+    }
+    if let Some(version) = version {
+        check_text(version, "options", "version", MAX_VERSION_CHARS, false)?;
+    }
+    Ok(())
+}
+
+fn check_crawl_limits(source: &str, version: Option<&str>, max_pages: usize) -> Result<(), McpError> {
+    check_text(source, "crawl", "source", MAX_SOURCE_CHARS, true)?;
+    if let Some(version) = version {
+        check_text(version, "crawl", "version", MAX_VERSION_CHARS, false)?;
+    }
+    if max_pages == 0 || max_pages > MAX_CRAWL_PAGES {
+        return Err(McpError::validation(
+            Some("maxPages"),
+            format!("maxPages must be between 1 and {}", MAX_CRAWL_PAGES),
+        ));
+    }
+    Ok(())
+}
+
+fn check_concurrency(concurrency: usize, host_concurrency: usize) -> Result<(), McpError> {
+    for (field, value) in [("concurrency", concurrency), ("hostConcurrency", host_concurrency)] {
+        if value == 0 || value > MAX_CRAWL_CONCURRENCY {
+            return Err(McpError::validation(
+                Some(field),
+                format!("{} must be between 1 and {}", field, MAX_CRAWL_CONCURRENCY),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_tags(tags: &[String], path: &str, field: &str) -> Result<(), McpError> {
+    if tags.len() > MAX_TAGS {
+        return Err(McpError::validation(
+            Some(&format!("{}.{}", path, field)),
+            format!("At most {} tags are allowed", MAX_TAGS),
+        ));
+    }
+    for tag in tags {
+        check_text(tag, path, field, MAX_TAG_CHARS, true)?;
+    }
+    Ok(())
+}
+
+// A facet path like /rust/tokio, with \/ for a slash inside a segment
+fn check_facet(facet: &str, path: &str, field: &str) -> Result<(), McpError> {
+    check_text(facet, path, field, MAX_ID_CHARS, true)?;
+    if !facet.starts_with('/') {
+        return Err(McpError::validation(
+            Some(&format!("{}.{}", path, field)),
+            format!("{} is not a facet; use a path like /rust/tokio", facet),
+        ));
+    }
+    Ok(())
+}
+
+// Metric names end up in JSON paths, so dots and other punctuation are out
+fn check_metric_name(name: &str, path: &str) -> Result<(), McpError> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_METRIC_NAME_CHARS
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(McpError::validation(
+            Some(path),
+            format!("{} is not a metric name; use up to {} letters, digits and _", name, MAX_METRIC_NAME_CHARS),
+        ));
+    }
+    Ok(())
+}
+
+fn check_text(value: &str, path: &str, field: &str, max_chars: usize, required: bool) -> Result<(), McpError> {
+    let field = format!("{}.{}", path, field);
+    if required && value.trim().is_empty() {
+        return Err(McpError::validation(Some(&field), format!("{} must not be empty", field)));
+    }
+    if value.chars().count() > max_chars {
+        return Err(McpError::validation(
+            Some(&field),
+            format!("{} is longer than {} characters", field, max_chars),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use anyhow::{anyhow, bail, Result};
+
+// Semver-style versions as sortable numbers. Documentation versions are
+// only mostly semver ("v1.38", "2.0.0-rc.1", "3.12", "1.0b2"), so parsing is
+// lenient: a leading "v", missing minor and patch numbers, build metadata
+// and PEP 440 style pre-releases are all accepted. Anything else ("latest",
+// "nightly", "2024-05-01") has no key and is left out of version filters.
+
+// A pre-release sorts below its release, and alpha < beta < rc within it
+const RELEASE: u64 = 0xffff;
+const MAX_PRE_NUMBER: u64 = 0xfff;
+
+// The numbers of a version, with the ones it leaves out as None
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Version {
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: u64,
+}
+
+impl Version {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        let text = text.split('+').next().unwrap_or_default();
+        let core_len = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+        let (core, pre) = text.split_at(core_len);
+
+        let mut numbers = Vec::new();
+        for part in core.strip_suffix('.').unwrap_or(core).split('.') {
+            let number: u64 = part.parse().ok()?;
+            // Leaves room to count one past it
+            if number >= 0xffff {
+                return None;
+            }
+            numbers.push(number);
+        }
+        if numbers.len() > 3 {
+            return None;
+        }
+        Some(Self {
+            major: numbers[0],
+            minor: numbers.get(1).copied(),
+            patch: numbers.get(2).copied(),
+            pre: pre_release(pre.trim_start_matches(['-', '.', '_']))?,
+        })
+    }
+
+    fn key(&self) -> u64 {
+        (self.major << 48) | (self.minor.unwrap_or(0) << 32) | (self.patch.unwrap_or(0) << 16) | self.pre
+    }
+
+    // The lowest key of the next version at the precision written: 1.5.0's
+    // earliest pre-release for "1.4", 2.0.0's for "1"
+    fn next_key(&self) -> u64 {
+        match (self.minor, self.patch) {
+            (Some(minor), Some(patch)) => Self::lowest(self.major, minor, patch + 1),
+            (Some(minor), None) => Self::lowest(self.major, minor + 1, 0),
+            _ => Self::lowest(self.major + 1, 0, 0),
+        }
+    }
+
+    // Cargo's caret: the next version that changes the leftmost non-zero
+    // number, e.g. 2.0.0 for ^1.4, 0.5.0 for ^0.4 and 0.0.4 for ^0.0.3
+    fn caret_key(&self) -> u64 {
+        match (self.minor, self.patch) {
+            _ if self.major > 0 => Self::lowest(self.major + 1, 0, 0),
+            (Some(minor), _) if minor > 0 => Self::lowest(0, minor + 1, 0),
+            (Some(minor), Some(patch)) => Self::lowest(0, minor, patch + 1),
+            _ => self.next_key(),
+        }
+    }
+
+    // The next minor version when there is a minor number, e.g. 1.5.0 for
+    // ~1.4.2, or the next major one
+    fn tilde_key(&self) -> u64 {
+        match self.minor {
+            Some(minor) => Self::lowest(self.major, minor + 1, 0),
+            None => Self::lowest(self.major + 1, 0, 0),
+        }
+    }
+
+    fn is_partial(&self) -> bool {
+        self.patch.is_none() && self.pre == RELEASE
+    }
+
+    fn lowest(major: u64, minor: u64, patch: u64) -> u64 {
+        (major << 48) | (minor << 32) | (patch << 16)
+    }
+}
+
+// Pre-releases rank by their kind, then their number: "alpha.2" < "beta"
+// < "rc.1" < "rc.2". An empty one is the release itself.
+fn pre_release(pre: &str) -> Option<u64> {
+    if pre.is_empty() {
+        return Some(RELEASE);
+    }
+    if !pre.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let kind_len = pre.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(pre.len());
+    // Anything else, "dev" say, comes first
+    let kind = match pre[..kind_len].to_ascii_lowercase().as_str() {
+        "a" | "alpha" => 1,
+        "b" | "beta" => 2,
+        "c" | "rc" | "pre" | "preview" => 3,
+        _ => 0,
+    };
+    let number = pre[kind_len..]
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|part| !part.is_empty())
+        .and_then(|part| part.parse::<u64>().ok())
+        .unwrap_or(0)
+        .min(MAX_PRE_NUMBER);
+    Some((kind << 12) | number)
+}
+
+// The sortable key of a version, e.g. for "1.38.0": 1.38.0 sorts above
+// 1.38.0-rc.1 and below 1.39.0. None when the version isn't semver-like.
+pub fn version_key(version: &str) -> Option<u64> {
+    Version::parse(version).map(|version| version.key())
+}
+
+// Whether a key is a release's rather than a pre-release's
+pub fn is_release(key: u64) -> bool {
+    key & RELEASE == RELEASE
+}
+
+// Versions matching comma-separated comparators, e.g. ">= 1.4, < 2.0",
+// as the keys from `min` up to but not including `end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VersionRange {
+    pub min: u64,
+    pub end: Option<u64>,
+}
+
+impl VersionRange {
+    // Comparators are `=`, `>`, `>=`, `<`, `<=`, `^` and `~`, as in Cargo;
+    // a bare version is a caret one. Leaving numbers out widens `=`, `>` and
+    // `<=` to the whole of what's written: "=1.4" matches every 1.4.x.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut range = Self { min: 0, end: None };
+        for comparator in text.split(',') {
+            let comparator = comparator.trim();
+            let op_len = comparator.find(|c: char| !"=<>^~".contains(c)).unwrap_or(comparator.len());
+            let (op, version) = comparator.split_at(op_len);
+            let version = Version::parse(version)
+                .ok_or_else(|| anyhow!("{} is not a version comparator like >=1.4", comparator))?;
+            let (min, end) = match op {
+                "" | "^" => (version.key(), Some(version.caret_key())),
+                "~" => (version.key(), Some(version.tilde_key())),
+                "=" if version.is_partial() => (version.key(), Some(version.next_key())),
+                "=" => (version.key(), Some(version.key() + 1)),
+                ">" if version.is_partial() => (version.next_key(), None),
+                ">" => (version.key() + 1, None),
+                ">=" => (version.key(), None),
+                // Pre-releases of the version are below it too
+                "<" if version.pre == RELEASE => (0, Some(version.key() - RELEASE)),
+                "<" => (0, Some(version.key())),
+                "<=" if version.is_partial() => (0, Some(version.next_key())),
+                "<=" => (0, Some(version.key() + 1)),
+                _ => bail!("Unknown version comparator {} in {}", op, comparator),
+            };
+            range.min = range.min.max(min);
+            range.end = match (range.end, end) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        if range.end.is_some_and(|end| end <= range.min) {
+            bail!("No version matches {}", text);
+        }
+        Ok(range)
+    }
+}
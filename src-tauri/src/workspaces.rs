@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::roots;
+use crate::search::PreferredVersion;
+use crate::McpError;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Workspace {
+    pub name: String,
+    /// The project directory whose lockfiles pin versions, e.g.
+    /// `/home/me/my-app`
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Versions pinned by hand, by source, e.g. `{"tokio": "1.38.0"}`; they
+    /// win over the lockfiles
+    #[serde(default)]
+    pub pins: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct WorkspaceParams {
+    pub name: String,
+}
+
+// listWorkspaces takes no arguments
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct ListWorkspacesParams {}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct WorkspacePin {
+    pub source: String,
+    pub version: String,
+    /// Read from a lockfile rather than pinned by hand
+    pub locked: bool,
+}
+
+// Named projects whose library versions searches can be held to, kept in a
+// JSON file next to the settings. Lockfiles are read again each time the
+// pins are needed, so they follow the project's upgrades.
+pub struct Workspaces {
+    path: PathBuf,
+    workspaces: RwLock<BTreeMap<String, Workspace>>,
+}
+
+impl Workspaces {
+    // A missing or unreadable file starts with no workspaces
+    pub fn load(path: PathBuf) -> Self {
+        let workspaces = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid workspaces {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path,
+            workspaces: RwLock::new(workspaces),
+        }
+    }
+
+    pub fn list(&self) -> Vec<Workspace> {
+        self.workspaces.read().unwrap().values().cloned().collect()
+    }
+
+    // Replaces a workspace of the same name
+    pub fn set(&self, workspace: Workspace) -> Result<()> {
+        let mut workspaces = self.workspaces.write().unwrap();
+        workspaces.insert(workspace.name.clone(), workspace);
+        self.save(&workspaces)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<Option<Workspace>> {
+        let mut workspaces = self.workspaces.write().unwrap();
+        let removed = workspaces.remove(name);
+        if removed.is_some() {
+            self.save(&workspaces)?;
+        }
+        Ok(removed)
+    }
+
+    // What the workspace pins, by source: its lockfiles' versions, then the
+    // ones pinned by hand
+    pub fn pins(&self, name: &str) -> Result<Vec<WorkspacePin>, McpError> {
+        let workspace = self
+            .workspaces
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| McpError::not_found(format!("Workspace {}", name)))?;
+
+        let mut pins = BTreeMap::new();
+        if let Some(root) = &workspace.root {
+            for locked in roots::locked_versions(Path::new(root)) {
+                let Some(version) = locked.version else {
+                    continue;
+                };
+                let pin = WorkspacePin {
+                    source: locked.source.clone(),
+                    version,
+                    locked: true,
+                };
+                pins.insert(locked.source, pin);
+            }
+        }
+        for (source, version) in workspace.pins {
+            pins.insert(
+                source.clone(),
+                WorkspacePin {
+                    source,
+                    version,
+                    locked: false,
+                },
+            );
+        }
+        Ok(pins.into_values().collect())
+    }
+
+    // The workspace's pins as search options take them; none without a
+    // workspace
+    pub fn pinned_versions(&self, name: Option<&str>) -> Result<Vec<PreferredVersion>, McpError> {
+        let Some(name) = name else {
+            return Ok(Vec::new());
+        };
+        Ok(self
+            .pins(name)?
+            .into_iter()
+            .map(|pin| PreferredVersion {
+                source: pin.source,
+                version: Some(pin.version),
+            })
+            .collect())
+    }
+
+    fn save(&self, workspaces: &BTreeMap<String, Workspace>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(workspaces)?)?;
+        Ok(())
+    }
+}